@@ -6,6 +6,8 @@ use futures_util::StreamExt;
 use std::fs;
 use std::io::{self, Cursor};
 use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
 
 pub fn default_tsv_file_path(network: &StacksNetwork) -> String {
     format!("{:?}-stacks-events.tsv", network).to_lowercase()
@@ -15,6 +17,19 @@ pub fn default_tsv_sha_file_path(network: &StacksNetwork) -> String {
     format!("{:?}-stacks-events.sha256", network).to_lowercase()
 }
 
+/// Identifies a version of the local Stacks archive file, by its size and last modification.
+pub fn get_stacks_tsv_fingerprint(tsv_file_path: &PathBuf) -> Result<String, String> {
+    let metadata = fs::metadata(tsv_file_path)
+        .map_err(|e| format!("unable to read {}: {}", tsv_file_path.display(), e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    Ok(format!("{}-{}", metadata.len(), modified))
+}
+
 pub async fn download_tsv_file(config: &Config) -> Result<(), String> {
     let mut destination_path = config.expected_cache_path();
     std::fs::create_dir_all(&destination_path).unwrap_or_else(|e| {
@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+
+use hiro_system_kit::slog::{self, Drain, Logger, OwnedKVList, Record};
+
+use crate::config::{LogFormat, LogsConfig};
+
+/// Drops the records below the level of the module they were emitted from.
+struct ModuleLevelFilter<D> {
+    drain: D,
+    logs: LogsConfig,
+}
+
+impl<D: Drain> Drain for ModuleLevelFilter<D> {
+    type Ok = ();
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let level = self.logs.level_of(record.module());
+        if record.level().is_at_least(level) {
+            self.drain.log(record, values)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the logger of the process, writing text logs to the terminal or json logs to stdout,
+/// one record per line.
+pub fn build_logger(logs: &LogsConfig) -> Logger {
+    match logs.format.unwrap_or(LogFormat::Text) {
+        LogFormat::Text => {
+            let decorator = slog_term::TermDecorator::new().build();
+            let drain = slog_term::FullFormat::new(decorator).build();
+            build_filtered_logger(drain, logs)
+        }
+        LogFormat::Json => {
+            let drain = slog_json::Json::new(std::io::stdout())
+                .add_default_keys()
+                .build();
+            build_filtered_logger(drain, logs)
+        }
+    }
+}
+
+fn build_filtered_logger<D>(drain: D, logs: &LogsConfig) -> Logger
+where
+    D: Drain<Ok = (), Err = std::io::Error> + Send + 'static,
+{
+    let drain = ModuleLevelFilter {
+        drain,
+        logs: logs.clone(),
+    };
+    Logger::root(Mutex::new(drain).fuse(), slog::o!())
+}
@@ -1,43 +1,111 @@
+mod logs;
+mod node;
+pub mod templates;
+
 use crate::config::generator::generate_config;
-use crate::config::Config;
-use crate::scan::bitcoin::scan_bitcoin_chainstate_via_rpc_using_predicate;
+use crate::config::report::{check_config, ConfigCheck};
+use crate::config::{
+    parse_log_format, parse_log_level, parse_module_log_level, Config, LogsConfig,
+};
+use crate::scan::bitcoin::{
+    open_bitcoin_block_cache, scan_bitcoin_chainstate_via_rpc_using_predicate,
+    scan_bitcoin_chainstate_via_rpc_using_predicate_in_chunks,
+};
 use crate::scan::stacks::{
     consolidate_local_stacks_chainstate_using_csv, scan_stacks_chainstate_via_csv_using_predicate,
     scan_stacks_chainstate_via_rocksdb_using_predicate,
+    scan_stacks_chainstate_via_rocksdb_using_predicate_in_chunks,
 };
+use crate::service::dry_run::test_predicate_on_fixtures;
+use crate::service::error_reporting::init_error_reporting;
 use crate::service::http_api::document_predicate_api_server;
+use crate::service::replay::{load_predicates_to_replay, replay_blocks};
+use crate::service::startup::check_service_start;
+use crate::service::stats::{format_stats_table, load_predicate_stats};
 use crate::service::Service;
+use crate::storage::migrations::migrate_dbs;
 use crate::storage::{
     delete_confirmed_entry_from_stacks_blocks, delete_unconfirmed_entry_from_stacks_blocks,
-    get_last_block_height_inserted, get_last_unconfirmed_block_height_inserted,
-    get_stacks_block_at_block_height, insert_unconfirmed_entry_in_stacks_blocks,
-    is_stacks_block_present, open_readonly_stacks_db_conn, open_readonly_stacks_db_conn_with_retry,
-    open_readwrite_stacks_db_conn, set_last_confirmed_insert_key,
+    export_stacks_db_snapshot, find_stacks_db_divergence, get_last_block_height_inserted,
+    get_last_unconfirmed_block_height_inserted, get_stacks_block_at_block_height,
+    import_stacks_db_snapshot, insert_unconfirmed_entry_in_stacks_blocks, is_stacks_block_present,
+    open_readonly_stacks_db_conn, open_readonly_stacks_db_conn_with_retry,
+    open_readwrite_stacks_db_conn, rollback_stacks_blocks, set_last_confirmed_insert_key,
+    verify_stacks_db, StacksStorage,
 };
 use chainhook_sdk::chainhooks::bitcoin::BitcoinChainhookSpecification;
 use chainhook_sdk::chainhooks::bitcoin::BitcoinChainhookSpecificationNetworkMap;
 use chainhook_sdk::chainhooks::bitcoin::BitcoinPredicateType;
 use chainhook_sdk::chainhooks::bitcoin::InscriptionFeedData;
 use chainhook_sdk::chainhooks::bitcoin::OrdinalOperations;
+use chainhook_sdk::chainhooks::diagnostics::check_predicate;
+use chainhook_sdk::chainhooks::schemas::{json_schema, JSON_SCHEMA_NAMES};
 use chainhook_sdk::chainhooks::stacks::StacksChainhookSpecification;
 use chainhook_sdk::chainhooks::stacks::StacksChainhookSpecificationNetworkMap;
 use chainhook_sdk::chainhooks::stacks::StacksPredicate;
 use chainhook_sdk::chainhooks::stacks::StacksPrintEventBasedPredicate;
-use chainhook_sdk::chainhooks::types::{ChainhookSpecificationNetworkMap, FileHook, HookAction};
-use chainhook_sdk::types::{BitcoinNetwork, BlockIdentifier, StacksNetwork};
+use chainhook_sdk::chainhooks::types::{
+    ChainhookInstance, ChainhookSpecificationNetworkMap, DeliveryOptions, FileHook, HookAction,
+};
+use chainhook_sdk::indexer::bitcoin::build_http_client;
+use chainhook_sdk::indexer::stacks::retrieve_canonical_stacks_block_identifiers;
+use chainhook_sdk::observer::ObserverCommand;
+use chainhook_sdk::types::{BitcoinBlockSignaling, BitcoinNetwork, BlockIdentifier, StacksNetwork};
+use chainhook_sdk::utils::file_sink::STDOUT_FILE_SINK_PATH;
 use chainhook_sdk::utils::{BlockHeights, Context};
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_generate::generate;
+use clap_generate::generators::{Bash, Fish, Zsh};
+use futures_util::StreamExt;
 use hiro_system_kit;
+use logs::build_logger;
+use node::PredicatesApiClient;
+use serde_json::Value as JsonValue;
 use std::collections::BTreeMap;
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use templates::{PredicateTemplate, PREDICATE_TEMPLATES};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Opts {
     #[clap(subcommand)]
     command: Command,
+    /// Minimum level of the logs: critical, error, warn, info, debug or trace
+    #[clap(long = "log-level", global = true)]
+    log_level: Option<String>,
+    /// Format of the logs
+    #[clap(long = "log-format", global = true, possible_values = &["text", "json"])]
+    log_format: Option<String>,
+    /// Level of the logs of a module, overriding --log-level (--log-module chainhook_sdk::observer=warn)
+    #[clap(long = "log-module", global = true, multiple_occurrences = true)]
+    log_modules: Vec<String>,
+}
+
+impl Opts {
+    /// Returns the logs settings set from the command line, taking precedence over the ones of
+    /// the config file.
+    fn logs_config(&self) -> Result<LogsConfig, String> {
+        let mut module_levels = BTreeMap::new();
+        for module_level in self.log_modules.iter() {
+            let (module, level) = parse_module_log_level(module_level)?;
+            module_levels.insert(module, level);
+        }
+        Ok(LogsConfig {
+            level: self.log_level.as_deref().map(parse_log_level).transpose()?,
+            format: self
+                .log_format
+                .as_deref()
+                .map(parse_log_format)
+                .transpose()?,
+            module_levels,
+        })
+    }
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -54,9 +122,18 @@ enum Command {
     /// Stacks related subcommands
     #[clap(subcommand)]
     Stacks(StacksCommand),
+    /// Db maintenance related commands, covering the Stacks and predicates dbs
+    #[clap(subcommand)]
+    Db(DbCommand),
     /// Generate documentation
     #[clap(subcommand)]
     Docs(DocsCommand),
+    /// Replay blocks from the local storage through the predicates and their actions
+    #[clap(name = "replay", bin_name = "replay")]
+    Replay(ReplayBlocks),
+    /// Generate shell completions, printed to stdout
+    #[clap(name = "completions", bin_name = "completions")]
+    Completions(Completions),
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -71,6 +148,27 @@ enum PredicatesCommand {
     /// Check given predicate
     #[clap(name = "check", bin_name = "check")]
     Check(CheckPredicate),
+    /// Evaluate given predicate on local block fixtures
+    #[clap(name = "test", bin_name = "test")]
+    Test(TestPredicate),
+    /// List the predicates registered on a running service
+    #[clap(name = "list", bin_name = "list")]
+    List(ListPredicates),
+    /// Get a predicate registered on a running service
+    #[clap(name = "get", bin_name = "get")]
+    Get(ManagePredicate),
+    /// Deregister a predicate from a running service
+    #[clap(name = "delete", bin_name = "delete")]
+    Delete(ManagePredicate),
+    /// Resume the evaluation of a paused predicate on a running service
+    #[clap(name = "enable", bin_name = "enable", aliases = &["resume"])]
+    Enable(ManagePredicate),
+    /// Pause the evaluation of a predicate on a running service
+    #[clap(name = "pause", bin_name = "pause")]
+    Pause(ManagePredicate),
+    /// Report the triggers, evaluations and deliveries of the registered predicates
+    #[clap(name = "stats", bin_name = "stats")]
+    Stats(PredicateStatsCommand),
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -79,6 +177,9 @@ enum ConfigCommand {
     /// Generate new config
     #[clap(name = "new", bin_name = "new", aliases = &["generate"])]
     New(NewConfig),
+    /// Check the config file, the nodes and databases it relies on, its ports and its paths
+    #[clap(name = "validate", bin_name = "validate")]
+    Validate(ValidateConfig),
 }
 
 #[derive(Parser, PartialEq, Clone, Debug)]
@@ -106,6 +207,26 @@ struct NewConfig {
     pub mainnet: bool,
 }
 
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ValidateConfig {
+    /// Load config file path
+    #[clap(long = "config-path", default_value = "Chainhook.toml")]
+    pub config_path: String,
+    /// Print the report as json
+    #[clap(long = "json")]
+    pub json: bool,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct PredicateStatsCommand {
+    /// Load config file path, the stats are read from its predicates db
+    #[clap(long = "config-path", default_value = "Chainhook.toml")]
+    pub config_path: String,
+    /// Print the stats as json
+    #[clap(long = "json")]
+    pub json: bool,
+}
+
 #[derive(Parser, PartialEq, Clone, Debug)]
 struct NewPredicate {
     /// Predicate's name
@@ -116,12 +237,67 @@ struct NewPredicate {
     /// Generate a Stacks predicate
     #[clap(long = "stacks", conflicts_with = "bitcoin")]
     pub stacks: bool,
+    /// Generate a commented predicate from a template
+    #[clap(
+        long = "template",
+        possible_values = PREDICATE_TEMPLATES,
+        conflicts_with = "bitcoin",
+        conflicts_with = "stacks"
+    )]
+    pub template: Option<PredicateTemplate>,
 }
 
 #[derive(Parser, PartialEq, Clone, Debug)]
 struct ScanPredicate {
-    /// Chainhook spec file to scan (json format)
-    pub predicate_path: String,
+    /// Chainhook spec files to scan (json format)
+    #[clap(required = true)]
+    pub predicate_paths: Vec<String>,
+    /// Number of concurrent scans. Predicates are scanned concurrently, and the jobs left split
+    /// their block ranges into chunks scanned concurrently
+    #[clap(long = "jobs", default_value = "1")]
+    pub jobs: usize,
+    /// Stream the occurrences to stdout as newline-delimited JSON (`ndjson` or `-`), instead of
+    /// delivering them with the actions of the predicates
+    #[clap(long = "output", possible_values = &["ndjson", "-"])]
+    pub output: Option<String>,
+    /// Scan from this block height, overriding the range of the predicates
+    #[clap(long = "start-block")]
+    pub start_block: Option<u64>,
+    /// Scan up to this block height, overriding the range of the predicates
+    #[clap(long = "end-block")]
+    pub end_block: Option<u64>,
+    /// Target Testnet network
+    #[clap(long = "testnet", conflicts_with = "mainnet")]
+    pub testnet: bool,
+    /// Target Mainnet network
+    #[clap(long = "mainnet", conflicts_with = "testnet")]
+    pub mainnet: bool,
+    /// Load config file path
+    #[clap(
+        long = "config-path",
+        conflicts_with = "mainnet",
+        conflicts_with = "testnet"
+    )]
+    pub config_path: Option<String>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ReplayBlocks {
+    /// Height of the first block to replay
+    #[clap(long = "from")]
+    pub from: u64,
+    /// Height of the last block to replay
+    #[clap(long = "to")]
+    pub to: u64,
+    /// Chain of the blocks to replay, required when predicates of both chains are replayed
+    #[clap(long = "chain", possible_values = &["bitcoin", "stacks"])]
+    pub chain: Option<String>,
+    /// Only replay the registered predicates with these uuids
+    #[clap(long = "predicate-uuid")]
+    pub predicate_uuids: Vec<String>,
+    /// Chainhook spec files (json format) to replay along with the registered predicates
+    #[clap(long = "predicate-path")]
+    pub predicate_paths: Vec<String>,
     /// Target Testnet network
     #[clap(long = "testnet", conflicts_with = "mainnet")]
     pub testnet: bool,
@@ -137,6 +313,13 @@ struct ScanPredicate {
     pub config_path: Option<String>,
 }
 
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct Completions {
+    /// Shell to generate completions for
+    #[clap(possible_values = &["bash", "zsh", "fish"])]
+    pub shell: String,
+}
+
 #[derive(Parser, PartialEq, Clone, Debug)]
 struct CheckPredicate {
     /// Chainhook spec file to check (json format)
@@ -156,6 +339,73 @@ struct CheckPredicate {
     pub config_path: Option<String>,
 }
 
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct TestPredicate {
+    /// Chainhook spec file to test (json format)
+    pub predicate_path: String,
+    /// Block fixture files (json format), holding a block or an array of blocks
+    #[clap(required = true)]
+    pub fixture_paths: Vec<String>,
+    /// Fail unless this number of blocks match the predicate
+    #[clap(long = "expected-matches")]
+    pub expected_matches: Option<usize>,
+    /// Target Testnet network
+    #[clap(long = "testnet", conflicts_with = "mainnet")]
+    pub testnet: bool,
+    /// Target Mainnet network
+    #[clap(long = "mainnet", conflicts_with = "testnet")]
+    pub mainnet: bool,
+    /// Load config file path
+    #[clap(
+        long = "config-path",
+        conflicts_with = "mainnet",
+        conflicts_with = "testnet"
+    )]
+    pub config_path: Option<String>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct NodeConnection {
+    /// Url of the predicates API of the service
+    #[clap(long = "node", default_value = "http://localhost:20456")]
+    pub node_url: String,
+    /// Api key authorizing the requests
+    #[clap(long = "api-key")]
+    pub api_key: Option<String>,
+    /// Namespace of the predicates
+    #[clap(long = "namespace")]
+    pub namespace: Option<String>,
+}
+
+impl NodeConnection {
+    fn client(&self) -> PredicatesApiClient {
+        PredicatesApiClient::new(&self.node_url, self.api_key.clone(), self.namespace.clone())
+    }
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ListPredicates {
+    #[clap(flatten)]
+    pub node: NodeConnection,
+    /// Only list the predicates of this chain (bitcoin or stacks)
+    #[clap(long = "chain")]
+    pub chain: Option<String>,
+    /// Only list the predicates with this status, e.g. streaming
+    #[clap(long = "status")]
+    pub status: Option<String>,
+    /// Print the predicates as json
+    #[clap(long = "json")]
+    pub json: bool,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct ManagePredicate {
+    /// Uuid of the predicate
+    pub predicate_uuid: String,
+    #[clap(flatten)]
+    pub node: NodeConnection,
+}
+
 #[derive(Subcommand, PartialEq, Clone, Debug)]
 enum ServiceCommand {
     /// Start chainhook-cli
@@ -203,6 +453,26 @@ struct StartCommand {
     /// If provided, serves Prometheus metrics at localhost:{port}/metrics. If not specified, does not start Prometheus server.
     #[clap(long = "prometheus-port")]
     pub prometheus_monitoring_port: Option<u16>,
+    /// Write the pid of the process to this file, removed on exit
+    #[clap(long = "pid-file")]
+    pub pid_file: Option<PathBuf>,
+    /// Go through the startup steps, report how they went and exit, without starting the service
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, PartialEq, Clone, Debug)]
+enum DbCommand {
+    /// Upgrade the dbs to the layouts expected by this release
+    #[clap(name = "migrate", bin_name = "migrate")]
+    Migrate(MigrateDbCommand),
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct MigrateDbCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -232,6 +502,58 @@ enum StacksDbCommand {
     /// Update blocks from database
     #[clap(name = "drop", bin_name = "drop")]
     Drop(DropBlockCommand),
+    /// Export the Stacks db to a portable archive
+    #[clap(name = "snapshot", bin_name = "snapshot")]
+    Snapshot(SnapshotDbCommand),
+    /// Replace the Stacks db with the one held by an archive created with `snapshot`
+    #[clap(name = "restore", bin_name = "restore")]
+    Restore(RestoreDbCommand),
+    /// Check that the Stacks db holds a continuous chain of blocks
+    #[clap(name = "verify", bin_name = "verify")]
+    Verify(VerifyDbCommand),
+    /// Ingest again, from the Stacks archive, the blocks from the first inconsistency found by `verify`
+    #[clap(name = "repair", bin_name = "repair")]
+    Repair(RepairDbCommand),
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct VerifyDbCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+    /// Also compare the hashes of the latest blocks with the canonical chain of the Stacks node (--depth 100)
+    #[clap(long = "depth")]
+    pub depth: Option<u64>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct RepairDbCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+    /// Also compare the hashes of the latest blocks with the canonical chain of the Stacks node (--depth 100)
+    #[clap(long = "depth")]
+    pub depth: Option<u64>,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct SnapshotDbCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+    /// Path of the archive to create (--archive-path stacks.tar.gz)
+    #[clap(long = "archive-path")]
+    pub archive_path: String,
+}
+
+#[derive(Parser, PartialEq, Clone, Debug)]
+struct RestoreDbCommand {
+    /// Load config file path
+    #[clap(long = "config-path")]
+    pub config_path: Option<String>,
+    /// Path of the archive to restore
+    #[clap(long = "archive-path")]
+    pub archive_path: String,
 }
 
 #[derive(Parser, PartialEq, Clone, Debug)]
@@ -305,6 +627,10 @@ enum DocsCommand {
     #[clap(subcommand)]
     #[clap(name = "api")]
     Api(ApiDocsCommand),
+    /// Generate the JSON Schemas of predicates and of the payloads delivered to their actions.
+    #[clap(subcommand)]
+    #[clap(name = "schemas")]
+    Schemas(SchemasDocsCommand),
 }
 
 #[derive(Subcommand, PartialEq, Clone, Debug)]
@@ -314,14 +640,14 @@ enum ApiDocsCommand {
     Generate,
 }
 
-pub fn main() {
-    let logger = hiro_system_kit::log::setup_logger();
-    let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
-    let ctx = Context {
-        logger: Some(logger),
-        tracer: false,
-    };
+#[derive(Subcommand, PartialEq, Clone, Debug)]
+enum SchemasDocsCommand {
+    /// Generate a `<name>.schema.json` file for each JSON Schema.
+    #[clap(name = "new", bin_name = "new", aliases = &["generate"])]
+    Generate,
+}
 
+pub fn main() {
     let opts: Opts = match Opts::try_parse() {
         Ok(opts) => opts,
         Err(e) => {
@@ -329,6 +655,20 @@ pub fn main() {
             process::exit(1);
         }
     };
+    let logs = match opts.logs_config() {
+        Ok(logs) => logs,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let logger = build_logger(&logs);
+    let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
+    let ctx = Context {
+        logger: Some(logger),
+        tracer: false,
+    };
 
     if let Err(e) = hiro_system_kit::nestable_block_on(handle_command(opts, ctx.clone())) {
         crit!(ctx.expect_logger(), "{e}");
@@ -336,13 +676,83 @@ pub fn main() {
     }
 }
 
+/// On a first interruption, asks the observers to terminate once the work already queued is done.
+/// A second interruption exits right away.
+fn set_termination_handler(
+    observer_commands_txs: Vec<SyncSender<ObserverCommand>>,
+    ctx: &Context,
+) -> Result<(), String> {
+    let terminating = AtomicBool::new(false);
+    let ctx = ctx.clone();
+    ctrlc::set_handler(move || {
+        if terminating.swap(true, Ordering::SeqCst) {
+            process::exit(1);
+        }
+        info!(
+            ctx.expect_logger(),
+            "Terminating, interrupt again to exit without waiting for pending deliveries"
+        );
+        for observer_commands_tx in observer_commands_txs.iter() {
+            let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+        }
+    })
+    .map_err(|e| format!("unable to set termination handler: {}", e))
+}
+
+/// Prints the outcome of the checks, one per line or as json, failing if any of them failed.
+fn print_config_checks(checks: &[ConfigCheck], json: bool) -> Result<(), String> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks).unwrap());
+    } else {
+        for check in checks.iter() {
+            let mark = if check.is_ok() { "✔️" } else { "✖" };
+            println!("{} {}: {}", mark, check.name, check.detail);
+        }
+    }
+    let failed = checks.iter().filter(|check| !check.is_ok()).count();
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, checks.len()));
+    }
+    Ok(())
+}
+
+/// Writes the pid of the process to `pid_file`, refusing to overwrite the file of a process
+/// still running.
+fn write_pid_file(pid_file: &Path) -> Result<(), String> {
+    if let Ok(content) = std::fs::read_to_string(pid_file) {
+        if let Ok(pid) = content.trim().parse::<u32>() {
+            if pid != process::id() && PathBuf::from(format!("/proc/{}", pid)).exists() {
+                return Err(format!(
+                    "pid file {} belongs to running process {}",
+                    pid_file.display(),
+                    pid
+                ));
+            }
+        }
+    }
+    std::fs::write(pid_file, format!("{}\n", process::id()))
+        .map_err(|e| format!("unable to write pid file {}: {}", pid_file.display(), e))
+}
+
 async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
+    let logs = opts.logs_config()?;
     match opts.command {
         Command::Service(subcmd) => match subcmd {
             ServiceCommand::Start(cmd) => {
                 let mut config =
                     Config::default(cmd.devnet, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
 
+                // The logger is set up before the config is loaded
+                let config_logs = config.logs.overridden_by(&logs);
+                let ctx = if config_logs != logs {
+                    Context {
+                        logger: Some(build_logger(&config_logs)),
+                        tracer: ctx.tracer,
+                    }
+                } else {
+                    ctx
+                };
+
                 if cmd.prometheus_monitoring_port.is_some() {
                     config.monitoring.prometheus_monitoring_port = cmd.prometheus_monitoring_port;
                 }
@@ -353,10 +763,80 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     .map(|p| load_predicate_from_path(p))
                     .collect::<Result<Vec<ChainhookSpecificationNetworkMap>, _>>()?;
 
+                if cmd.dry_run {
+                    let mut checks = check_service_start(&config, predicates.clone(), &ctx).await;
+                    for additional_config in config.additional_networks.iter() {
+                        let network = &additional_config.network.bitcoin_network;
+                        for mut check in
+                            check_service_start(additional_config, predicates.clone(), &ctx).await
+                        {
+                            check.name = format!("{:?} {}", network, check.name);
+                            checks.push(check);
+                        }
+                    }
+                    return print_config_checks(&checks, false);
+                }
+
                 info!(ctx.expect_logger(), "Starting service...",);
 
-                let mut service = Service::new(config, ctx);
-                return service.run(predicates, None).await;
+                // kept until the service stops, for the pending reports to be flushed
+                let _error_reporting_guard =
+                    config.error_reporting.as_ref().and_then(|error_reporting| {
+                        init_error_reporting(error_reporting, &config.network.bitcoin_network, &ctx)
+                    });
+
+                let (observer_commands_tx, observer_commands_rx) =
+                    sync_channel(config.limits.max_number_of_pending_observer_commands);
+                let mut observer_commands_txs = vec![observer_commands_tx.clone()];
+
+                // Each additional network gets its own service, running alongside the main one.
+                for additional_config in std::mem::take(&mut config.additional_networks) {
+                    let (additional_commands_tx, additional_commands_rx) = sync_channel(
+                        additional_config
+                            .limits
+                            .max_number_of_pending_observer_commands,
+                    );
+                    observer_commands_txs.push(additional_commands_tx.clone());
+                    let predicates = predicates.clone();
+                    let ctx = ctx.clone();
+                    let network = additional_config.network.bitcoin_network.clone();
+                    hiro_system_kit::thread_named(&format!("Service {:?}", network))
+                        .spawn(move || {
+                            let mut service = Service::new(additional_config, ctx.clone());
+                            let future = service.run(
+                                predicates,
+                                Some((additional_commands_tx, additional_commands_rx)),
+                            );
+                            if let Err(e) = hiro_system_kit::nestable_block_on(future) {
+                                crit!(ctx.expect_logger(), "Service {:?} stopped: {}", network, e);
+                                process::exit(1);
+                            }
+                        })
+                        .map_err(|e| format!("unable to start service {:?}: {}", network, e))?;
+                }
+                set_termination_handler(observer_commands_txs, &ctx)?;
+
+                if let Some(ref pid_file) = cmd.pid_file {
+                    write_pid_file(pid_file)?;
+                }
+                let mut service = Service::new(config, ctx.clone()).with_systemd_notifications();
+                let res = service
+                    .run(
+                        predicates,
+                        Some((observer_commands_tx, observer_commands_rx)),
+                    )
+                    .await;
+                if let Some(ref pid_file) = cmd.pid_file {
+                    if let Err(e) = std::fs::remove_file(pid_file) {
+                        warn!(
+                            ctx.expect_logger(),
+                            "unable to remove pid file {}: {}",
+                            pid_file.display(),
+                            e
+                        );
+                    }
+                }
+                return res;
             }
         },
         Command::Config(subcmd) => match subcmd {
@@ -373,6 +853,16 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     .map_err(|e| format!("unable to write file {}\n{}", file_path.display(), e))?;
                 println!("Created file Chainhook.toml");
             }
+            ConfigCommand::Validate(cmd) => {
+                let checks = match Config::from_file_path(&cmd.config_path) {
+                    Ok(config) => check_config(&config, &ctx).await,
+                    Err(e) => vec![ConfigCheck::new(
+                        format!("config {}", cmd.config_path),
+                        Err(e),
+                    )],
+                };
+                print_config_checks(&checks, cmd.json)?;
+            }
         },
         Command::Predicates(subcmd) => match subcmd {
             PredicatesCommand::New(cmd) => {
@@ -380,95 +870,137 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
 
                 let id = Uuid::new_v4();
 
-                let predicate = match (cmd.stacks, cmd.bitcoin) {
-                    (true, false) => {
-                        let mut networks = BTreeMap::new();
-
-                        networks.insert(StacksNetwork::Testnet, StacksChainhookSpecification {
-                            start_block: Some(34239),
-                            end_block: Some(50000),
-                            blocks: None,
-                            predicate: StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::Contains {
-                                contract_identifier: "ST1SVA0SST0EDT4MFYGWGP6GNSXMMQJDVP1G8QTTC.arkadiko-freddie-v1-1".into(),
-                                contains: "vault".into(),
-                            }),
-                            expire_after_occurrence: None,
-                            capture_all_events: None,
-                            decode_clarity_values: None,
-                            include_contract_abi: None,
-                            action:  HookAction::FileAppend(FileHook {
-                                path: "arkadiko.txt".into()
-                            })
-                        });
-
-                        networks.insert(StacksNetwork::Mainnet, StacksChainhookSpecification {
-                            start_block: Some(34239),
-                            end_block: Some(50000),
-                            blocks: None,
-                            predicate: StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::Contains {
-                                contract_identifier: "SP2C2YFP12AJZB4MABJBAJ55XECVS7E4PMMZ89YZR.arkadiko-freddie-v1-1".into(),
-                                contains: "vault".into(),
-                            }),
-                            expire_after_occurrence: None,
-                            capture_all_events: None,
-                            decode_clarity_values: None,
-                            include_contract_abi: None,
-                            action:  HookAction::FileAppend(FileHook {
-                                path: "arkadiko.txt".into()
-                            })
-                        });
-
-                        ChainhookSpecificationNetworkMap::Stacks(
-                            StacksChainhookSpecificationNetworkMap {
-                                uuid: id.to_string(),
-                                owner_uuid: None,
-                                name: "Hello world".into(),
-                                version: 1,
-                                networks,
-                            },
-                        )
-                    }
-                    (false, true) => {
-                        let mut networks = BTreeMap::new();
-
-                        networks.insert(
-                            BitcoinNetwork::Mainnet,
-                            BitcoinChainhookSpecification {
-                                start_block: Some(767430),
-                                end_block: Some(767430),
-                                blocks: None,
-                                predicate: BitcoinPredicateType::OrdinalsProtocol(
-                                    OrdinalOperations::InscriptionFeed(InscriptionFeedData {
-                                        meta_protocols: None,
+                let content = match cmd.template {
+                    Some(template) => template.generate(&id.to_string()),
+                    None => {
+                        let predicate = match (cmd.stacks, cmd.bitcoin) {
+                            (true, false) => {
+                                let mut networks = BTreeMap::new();
+
+                                networks.insert(StacksNetwork::Testnet, StacksChainhookSpecification {
+                                    start_block: Some(34239),
+                                    end_block: Some(50000),
+                                    blocks: None,
+                                    predicate: StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::Contains {
+                                        contract_identifier: "ST1SVA0SST0EDT4MFYGWGP6GNSXMMQJDVP1G8QTTC.arkadiko-freddie-v1-1".into(),
+                                        contains: "vault".into(),
                                     }),
-                                ),
-                                expire_after_occurrence: None,
-                                action: HookAction::FileAppend(FileHook {
-                                    path: "ordinals.txt".into(),
-                                }),
-                                include_inputs: None,
-                                include_outputs: None,
-                                include_proof: None,
-                                include_witness: None,
-                            },
-                        );
+                                    expire_after_occurrence: None,
+                                    rollback_action: None,
+                                    payload_envelope: None,
+                                    max_batch_size: None,
+                                    max_batch_wait_ms: None,
+                                    heartbeat_interval_minutes: None,
+                                    include_fields: None,
+                                    transform: None,
+                                    capture_all_events: None,
+                                    decode_clarity_values: None,
+                                    include_contract_abi: None,
+                                    action:  HookAction::FileAppend(FileHook {
+                                        path: "arkadiko.txt".into(),
+                                        rotation: None,
+                                        compression: None,
+                                        delivery: DeliveryOptions::default(),
+                                    })
+                                });
 
-                        ChainhookSpecificationNetworkMap::Bitcoin(
-                            BitcoinChainhookSpecificationNetworkMap {
-                                uuid: id.to_string(),
-                                owner_uuid: None,
-                                name: "Hello world".into(),
-                                version: 1,
-                                networks,
-                            },
-                        )
-                    }
-                    _ => {
-                        return Err("command `predicates new` should either provide the flag --stacks or --bitcoin".into());
+                                networks.insert(StacksNetwork::Mainnet, StacksChainhookSpecification {
+                                    start_block: Some(34239),
+                                    end_block: Some(50000),
+                                    blocks: None,
+                                    predicate: StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::Contains {
+                                        contract_identifier: "SP2C2YFP12AJZB4MABJBAJ55XECVS7E4PMMZ89YZR.arkadiko-freddie-v1-1".into(),
+                                        contains: "vault".into(),
+                                    }),
+                                    expire_after_occurrence: None,
+                                    rollback_action: None,
+                                    payload_envelope: None,
+                                    max_batch_size: None,
+                                    max_batch_wait_ms: None,
+                                    heartbeat_interval_minutes: None,
+                                    include_fields: None,
+                                    transform: None,
+                                    capture_all_events: None,
+                                    decode_clarity_values: None,
+                                    include_contract_abi: None,
+                                    action:  HookAction::FileAppend(FileHook {
+                                        path: "arkadiko.txt".into(),
+                                        rotation: None,
+                                        compression: None,
+                                        delivery: DeliveryOptions::default(),
+                                    })
+                                });
+
+                                ChainhookSpecificationNetworkMap::Stacks(
+                                    StacksChainhookSpecificationNetworkMap {
+                                        uuid: id.to_string(),
+                                        owner_uuid: None,
+                                        labels: None,
+                                        metadata: None,
+                                        namespace: None,
+                                        name: "Hello world".into(),
+                                        version: 1,
+                                        networks,
+                                    },
+                                )
+                            }
+                            (false, true) => {
+                                let mut networks = BTreeMap::new();
+
+                                networks.insert(
+                                    BitcoinNetwork::Mainnet,
+                                    BitcoinChainhookSpecification {
+                                        start_block: Some(767430),
+                                        end_block: Some(767430),
+                                        blocks: None,
+                                        predicate: BitcoinPredicateType::OrdinalsProtocol(
+                                            OrdinalOperations::InscriptionFeed(
+                                                InscriptionFeedData {
+                                                    meta_protocols: None,
+                                                },
+                                            ),
+                                        ),
+                                        expire_after_occurrence: None,
+                                        rollback_action: None,
+                                        payload_envelope: None,
+                                        max_batch_size: None,
+                                        max_batch_wait_ms: None,
+                                        heartbeat_interval_minutes: None,
+                                        include_fields: None,
+                                        transform: None,
+                                        action: HookAction::FileAppend(FileHook {
+                                            path: "ordinals.txt".into(),
+                                            rotation: None,
+                                            compression: None,
+                                            delivery: DeliveryOptions::default(),
+                                        }),
+                                        include_inputs: None,
+                                        include_outputs: None,
+                                        include_proof: None,
+                                        include_witness: None,
+                                    },
+                                );
+
+                                ChainhookSpecificationNetworkMap::Bitcoin(
+                                    BitcoinChainhookSpecificationNetworkMap {
+                                        uuid: id.to_string(),
+                                        owner_uuid: None,
+                                        labels: None,
+                                        metadata: None,
+                                        namespace: None,
+                                        name: "Hello world".into(),
+                                        version: 1,
+                                        networks,
+                                    },
+                                )
+                            }
+                            _ => {
+                                return Err("command `predicates new` should either provide the flag --stacks, --bitcoin or --template".into());
+                            }
+                        };
+                        serde_json::to_string_pretty(&predicate).unwrap()
                     }
                 };
-
-                let content = serde_json::to_string_pretty(&predicate).unwrap();
                 let mut path = PathBuf::new();
                 path.push(cmd.name);
 
@@ -502,127 +1034,222 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
             PredicatesCommand::Scan(cmd) => {
                 let mut config =
                     Config::default(false, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
-                let predicate = load_predicate_from_path(&cmd.predicate_path)?;
-                predicate.validate()?;
-                match predicate {
-                    ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
-                        let predicate_spec = match predicate
-                            .into_specification_for_network(&config.network.bitcoin_network)
-                        {
-                            Ok(predicate) => predicate,
-                            Err(e) => {
-                                return Err(format!(
-                                    "Specification missing for network {:?}: {e}",
-                                    config.network.bitcoin_network
-                                ));
-                            }
-                        };
-
-                        scan_bitcoin_chainstate_via_rpc_using_predicate(
-                            &predicate_spec,
-                            None,
-                            &config,
-                            None,
-                            &ctx,
-                        )
-                        .await?;
+                let mut predicates = vec![];
+                for predicate_path in cmd.predicate_paths.iter() {
+                    let mut predicate = load_predicate_from_path(predicate_path)?;
+                    if cmd.output.is_some() {
+                        set_stdout_action(&mut predicate);
                     }
-                    ChainhookSpecificationNetworkMap::Stacks(predicate) => {
-                        let predicate_spec = match predicate
-                            .into_specification_for_network(&config.network.stacks_network)
-                        {
-                            Ok(predicate) => predicate,
-                            Err(e) => {
-                                return Err(format!(
-                                    "Specification missing for network {:?}: {e}",
-                                    config.network.bitcoin_network
-                                ));
-                            }
-                        };
-                        match open_readonly_stacks_db_conn(&config.expected_cache_path(), &ctx) {
-                            Ok(_) => {
-                                let _ = consolidate_local_stacks_chainstate_using_csv(
-                                    &mut config,
-                                    &ctx,
-                                )
-                                .await;
-                                // Refresh DB connection so it picks up recent changes made by TSV consolidation.
-                                let new_conn = open_readonly_stacks_db_conn_with_retry(
-                                    &config.expected_cache_path(),
-                                    5,
-                                    &ctx,
-                                )?;
-                                scan_stacks_chainstate_via_rocksdb_using_predicate(
-                                    &predicate_spec,
-                                    None,
-                                    &new_conn,
-                                    &config,
-                                    None,
-                                    &ctx,
-                                )
-                                .await?;
-                            }
-                            Err(e) => {
-                                info!(
-                                    ctx.expect_logger(),
-                                    "Could not open db. This will greatly increase scan times. Error: {}", e
-                                );
-                                scan_stacks_chainstate_via_csv_using_predicate(
-                                    &predicate_spec,
-                                    &mut config,
-                                    &ctx,
-                                )
-                                .await?;
-                            }
-                        };
+                    if cmd.start_block.is_some() || cmd.end_block.is_some() {
+                        set_block_range(&mut predicate, cmd.start_block, cmd.end_block);
+                    }
+                    predicate.validate()?;
+                    check_scannable_predicate(&predicate)?;
+                    predicates.push(predicate);
+                }
+                let scans_stacks = predicates.iter().any(|predicate| {
+                    matches!(predicate, ChainhookSpecificationNetworkMap::Stacks(_))
+                });
+                let stacks_db_conn: Option<Arc<dyn StacksStorage>> = if scans_stacks {
+                    match open_readonly_stacks_db_conn(
+                        &config.expected_cache_path(),
+                        &config.storage.stacks_db_backend,
+                        &ctx,
+                    ) {
+                        Ok(_) => {
+                            let _ =
+                                consolidate_local_stacks_chainstate_using_csv(&mut config, &ctx)
+                                    .await;
+                            // Refresh DB connection so it picks up recent changes made by TSV consolidation.
+                            let new_conn = open_readonly_stacks_db_conn_with_retry(
+                                &config.expected_cache_path(),
+                                &config.storage.stacks_db_backend,
+                                5,
+                                &ctx,
+                            )?;
+                            Some(Arc::from(new_conn))
+                        }
+                        Err(e) => {
+                            info!(
+                                ctx.expect_logger(),
+                                "Could not open db. This will greatly increase scan times. Error: {}", e
+                            );
+                            None
+                        }
                     }
+                } else {
+                    None
+                };
+
+                // The jobs are shared by the predicates, the ones left splitting their ranges.
+                // Scans from the TSV file can't run concurrently, as they may download it.
+                let jobs = cmd.jobs.max(1);
+                let concurrent_scans = if scans_stacks && stacks_db_conn.is_none() {
+                    1
+                } else {
+                    jobs.min(predicates.len())
+                };
+                let chunks_per_predicate = (jobs / predicates.len()).max(1);
+                let config = &config;
+                let ctx = &ctx;
+                let results: Vec<Result<(), String>> =
+                    futures_util::stream::iter(predicates.into_iter().map(|predicate| {
+                        scan_predicate(
+                            predicate,
+                            config,
+                            stacks_db_conn.clone(),
+                            jobs > 1,
+                            chunks_per_predicate,
+                            ctx,
+                        )
+                    }))
+                    .buffer_unordered(concurrent_scans)
+                    .collect()
+                    .await;
+                for result in results {
+                    result?;
                 }
             }
             PredicatesCommand::Check(cmd) => {
                 let config = Config::default(false, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
-                let predicate: ChainhookSpecificationNetworkMap =
-                    load_predicate_from_path(&cmd.predicate_path)?;
-                predicate.validate()?;
-
-                match predicate {
-                    ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
-                        let _ = match predicate
-                            .into_specification_for_network(&config.network.bitcoin_network)
-                        {
-                            Ok(predicate) => predicate,
-                            Err(e) => {
-                                return Err(format!(
-                                    "Specification missing for network {:?}: {e}",
-                                    config.network.bitcoin_network
-                                ));
-                            }
-                        };
+                let predicate = load_predicate_json_from_path(&cmd.predicate_path)?;
+                let diagnostics = check_predicate(
+                    &predicate,
+                    &config.network.bitcoin_network,
+                    &config.network.stacks_network,
+                );
+                for diagnostic in diagnostics.iter() {
+                    println!("{}", diagnostic);
+                }
+                let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+                if errors > 0 {
+                    return Err(format!(
+                        "Predicate {} failed to check with {} error(s)",
+                        cmd.predicate_path, errors
+                    ));
+                }
+                println!("✔️ Predicate {} successfully checked", cmd.predicate_path);
+            }
+            PredicatesCommand::Test(cmd) => {
+                let config = Config::default(false, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
+                let predicate = load_predicate_from_path(&cmd.predicate_path)?;
+                let evaluations =
+                    test_predicate_on_fixtures(predicate, &cmd.fixture_paths, &config, &ctx)?;
+                let mut matches = 0;
+                for evaluation in evaluations.iter() {
+                    if evaluation.occurrences.is_empty() {
+                        println!(
+                            "✖ Block #{} {} ({}) did not match",
+                            evaluation.block_identifier.index,
+                            evaluation.block_identifier.hash,
+                            evaluation.fixture_path
+                        );
+                        continue;
                     }
-                    ChainhookSpecificationNetworkMap::Stacks(predicate) => {
-                        let _ = match predicate
-                            .into_specification_for_network(&config.network.stacks_network)
-                        {
-                            Ok(predicate) => predicate,
-                            Err(e) => {
-                                return Err(format!(
-                                    "Specification missing for network {:?}: {e}",
-                                    config.network.bitcoin_network
-                                ));
-                            }
-                        };
+                    matches += 1;
+                    println!(
+                        "✔️ Block #{} {} ({}) matched",
+                        evaluation.block_identifier.index,
+                        evaluation.block_identifier.hash,
+                        evaluation.fixture_path
+                    );
+                    for reason in evaluation.reasons.iter() {
+                        println!("  {}", reason);
+                    }
+                    for occurrence in evaluation.occurrences.iter() {
+                        println!("{}", occurrence);
+                    }
+                }
+                println!(
+                    "{} of {} blocks matched predicate {}",
+                    matches,
+                    evaluations.len(),
+                    cmd.predicate_path
+                );
+                if let Some(expected_matches) = cmd.expected_matches {
+                    if matches != expected_matches {
+                        return Err(format!(
+                            "Predicate {} matched {} blocks, {} expected",
+                            cmd.predicate_path, matches, expected_matches
+                        ));
+                    }
+                }
+            }
+            PredicatesCommand::List(cmd) => {
+                let predicates = cmd
+                    .node
+                    .client()
+                    .list_predicates(cmd.chain.as_deref(), cmd.status.as_deref())
+                    .await?;
+                if cmd.json {
+                    println!("{}", serde_json::to_string_pretty(&predicates).unwrap());
+                } else {
+                    for predicate in predicates.iter() {
+                        println!(
+                            "{}\t{}\t{}\t{}",
+                            predicate["uuid"].as_str().unwrap_or_default(),
+                            predicate["chain"].as_str().unwrap_or_default(),
+                            predicate["status"]["type"].as_str().unwrap_or_default(),
+                            predicate["network"].as_str().unwrap_or_default(),
+                        );
                     }
                 }
-                println!("✔️ Predicate {} successfully checked", cmd.predicate_path);
+            }
+            PredicatesCommand::Get(cmd) => {
+                let predicate = cmd.node.client().get_predicate(&cmd.predicate_uuid).await?;
+                println!("{}", serde_json::to_string_pretty(&predicate).unwrap());
+            }
+            PredicatesCommand::Delete(cmd) => {
+                cmd.node
+                    .client()
+                    .delete_predicate(&cmd.predicate_uuid)
+                    .await?;
+                println!("✔️ Predicate {} deleted", cmd.predicate_uuid);
+            }
+            PredicatesCommand::Enable(cmd) => {
+                cmd.node
+                    .client()
+                    .resume_predicate(&cmd.predicate_uuid)
+                    .await?;
+                println!("✔️ Predicate {} enabled", cmd.predicate_uuid);
+            }
+            PredicatesCommand::Pause(cmd) => {
+                cmd.node
+                    .client()
+                    .pause_predicate(&cmd.predicate_uuid)
+                    .await?;
+                println!("✔️ Predicate {} paused", cmd.predicate_uuid);
+            }
+            PredicatesCommand::Stats(cmd) => {
+                let config = Config::from_file_path(&cmd.config_path)?;
+                let stats = load_predicate_stats(&config, &ctx)?;
+                if cmd.json {
+                    println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+                } else {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    println!("{}", format_stats_table(&stats, now));
+                }
             }
         },
         Command::Stacks(subcmd) => match subcmd {
             StacksCommand::Db(StacksDbCommand::UnconfirmBlock(cmd)) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                let stacks_db_rw =
-                    open_readwrite_stacks_db_conn(&config.expected_cache_path(), &ctx)
-                        .expect("unable to read stacks_db");
+                let stacks_db_rw = open_readwrite_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )
+                .expect("unable to read stacks_db");
 
-                match get_stacks_block_at_block_height(cmd.block_height, true, 3, &stacks_db_rw) {
+                match get_stacks_block_at_block_height(
+                    cmd.block_height,
+                    true,
+                    3,
+                    stacks_db_rw.as_ref(),
+                ) {
                     Ok(Some(block)) => {
                         let mut delete_confirmed = false;
                         let mut insert_unconfirmed = false;
@@ -630,7 +1257,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                             cmd.block_height,
                             false,
                             3,
-                            &stacks_db_rw,
+                            stacks_db_rw.as_ref(),
                         ) {
                             Ok(Some(_)) => {
                                 warn!(ctx.expect_logger(), "Block {} was found in both the confirmed and unconfirmed database. Deleting from confirmed database.", cmd.block_height);
@@ -650,24 +1277,28 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                         }
                         if delete_confirmed {
                             if let Some(last_inserted) =
-                                get_last_block_height_inserted(&stacks_db_rw, &ctx)
+                                get_last_block_height_inserted(stacks_db_rw.as_ref(), &ctx)
                             {
                                 if last_inserted == block.block_identifier.index {
                                     set_last_confirmed_insert_key(
                                         &block.parent_block_identifier,
-                                        &stacks_db_rw,
+                                        stacks_db_rw.as_ref(),
                                         &ctx,
                                     )?;
                                 }
                             }
                             delete_confirmed_entry_from_stacks_blocks(
                                 &block.block_identifier,
-                                &stacks_db_rw,
+                                stacks_db_rw.as_ref(),
                                 &ctx,
                             )?;
                         }
                         if insert_unconfirmed {
-                            insert_unconfirmed_entry_in_stacks_blocks(&block, &stacks_db_rw, &ctx)?;
+                            insert_unconfirmed_entry_in_stacks_blocks(
+                                &block,
+                                stacks_db_rw.as_ref(),
+                                &ctx,
+                            )?;
                         }
                     }
                     Ok(None) => {
@@ -680,10 +1311,14 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
             }
             StacksCommand::Db(StacksDbCommand::GetLatest(cmd)) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                let stacks_db = open_readonly_stacks_db_conn(&config.expected_cache_path(), &ctx)
-                    .expect("unable to read stacks_db");
+                let stacks_db = open_readonly_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )
+                .expect("unable to read stacks_db");
 
-                match get_last_block_height_inserted(&stacks_db, &ctx) {
+                match get_last_block_height_inserted(stacks_db.as_ref(), &ctx) {
                     Some(confirmed_tip) => {
                         let min_block = confirmed_tip - cmd.count;
                         info!(
@@ -693,7 +1328,12 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                         let mut confirmed_blocks = vec![];
                         let mut cursor = confirmed_tip;
                         while cursor > min_block {
-                            match get_stacks_block_at_block_height(cursor, true, 3, &stacks_db) {
+                            match get_stacks_block_at_block_height(
+                                cursor,
+                                true,
+                                3,
+                                stacks_db.as_ref(),
+                            ) {
                                 Ok(Some(block)) => {
                                     confirmed_blocks.push(block.block_identifier.index);
                                     cursor -= 1;
@@ -721,7 +1361,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     }
                 };
 
-                match get_last_unconfirmed_block_height_inserted(&stacks_db, &ctx) {
+                match get_last_unconfirmed_block_height_inserted(stacks_db.as_ref(), &ctx) {
                     Some(unconfirmed_tip) => {
                         let min_block = unconfirmed_tip - cmd.count;
                         info!(
@@ -731,7 +1371,12 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                         let mut confirmed_blocks = vec![];
                         let mut cursor = unconfirmed_tip;
                         while cursor > min_block {
-                            match get_stacks_block_at_block_height(cursor, false, 3, &stacks_db) {
+                            match get_stacks_block_at_block_height(
+                                cursor,
+                                false,
+                                3,
+                                stacks_db.as_ref(),
+                            ) {
                                 Ok(Some(block)) => {
                                     confirmed_blocks.push(block.block_identifier.index);
                                     cursor -= 1;
@@ -761,9 +1406,12 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
             }
             StacksCommand::Db(StacksDbCommand::Drop(cmd)) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                let stacks_db_rw =
-                    open_readwrite_stacks_db_conn(&config.expected_cache_path(), &ctx)
-                        .expect("unable to read stacks_db");
+                let stacks_db_rw = open_readwrite_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )
+                .expect("unable to read stacks_db");
 
                 let block_heights = parse_blocks_heights_spec(&cmd.blocks_interval, &cmd.blocks)
                     .get_sorted_entries()
@@ -783,7 +1431,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     };
                     let _ = delete_unconfirmed_entry_from_stacks_blocks(
                         &block_identifier,
-                        &stacks_db_rw,
+                        stacks_db_rw.as_ref(),
                         &ctx,
                     );
                 }
@@ -792,11 +1440,127 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     "Cleaning stacks_db: {} blocks dropped", total_blocks
                 );
             }
+            StacksCommand::Db(StacksDbCommand::Snapshot(cmd)) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let archive_path = PathBuf::from(&cmd.archive_path);
+                let last_block_height = export_stacks_db_snapshot(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &archive_path,
+                    &ctx,
+                )?;
+                info!(
+                    ctx.expect_logger(),
+                    "Stacks db exported to {} (last confirmed block: {:?})",
+                    archive_path.display(),
+                    last_block_height
+                );
+            }
+            StacksCommand::Db(StacksDbCommand::Restore(cmd)) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let base_dir = config.expected_cache_path();
+                println!(
+                    "The Stacks db in {} will be replaced. Confirm? [Y/n]",
+                    base_dir.display()
+                );
+                let mut buffer = String::new();
+                std::io::stdin().read_line(&mut buffer).unwrap();
+                if buffer.starts_with('n') {
+                    return Err("Restore aborted".to_string());
+                }
+
+                let archive_path = PathBuf::from(&cmd.archive_path);
+                let last_block_height = import_stacks_db_snapshot(
+                    &base_dir,
+                    &config.storage.stacks_db_backend,
+                    &archive_path,
+                    &ctx,
+                )?;
+                info!(
+                    ctx.expect_logger(),
+                    "Stacks db restored from {} (last confirmed block: {:?})",
+                    archive_path.display(),
+                    last_block_height
+                );
+            }
+            StacksCommand::Db(StacksDbCommand::Verify(cmd)) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                let stacks_db = open_readonly_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )?;
+                if let Some(block_height) =
+                    find_first_invalid_stacks_block(&config, stacks_db.as_ref(), cmd.depth, &ctx)
+                        .await?
+                {
+                    return Err(format!(
+                        "Stacks db is inconsistent from block #{block_height}, run `chainhook stacks db repair` to fix it"
+                    ));
+                }
+            }
+            StacksCommand::Db(StacksDbCommand::Repair(cmd)) => {
+                let mut config = Config::default(false, false, false, &cmd.config_path)?;
+                let stacks_db_rw = open_readwrite_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )?;
+                let Some(block_height) = find_first_invalid_stacks_block(
+                    &config,
+                    stacks_db_rw.as_ref(),
+                    cmd.depth,
+                    &ctx,
+                )
+                .await?
+                else {
+                    return Ok(());
+                };
+                println!(
+                    "The blocks of the Stacks db from #{block_height} up will be deleted, then ingested again from the Stacks archive. Confirm? [Y/n]"
+                );
+                let mut buffer = String::new();
+                std::io::stdin().read_line(&mut buffer).unwrap();
+                if buffer.starts_with('n') {
+                    return Err("Repair aborted".to_string());
+                }
+
+                let blocks = rollback_stacks_blocks(block_height, stacks_db_rw.as_ref(), &ctx)?;
+                info!(
+                    ctx.expect_logger(),
+                    "Rolled back {} blocks from the stacks db",
+                    blocks.len()
+                );
+                drop(stacks_db_rw);
+                consolidate_local_stacks_chainstate_using_csv(&mut config, &ctx).await?;
+
+                let stacks_db = open_readonly_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )?;
+                if let Some(block_height) =
+                    find_first_invalid_stacks_block(&config, stacks_db.as_ref(), None, &ctx).await?
+                {
+                    return Err(format!(
+                        "Stacks db is still inconsistent from block #{block_height}, the Stacks archive doesn't include the blocks missing"
+                    ));
+                }
+            }
             StacksCommand::Db(StacksDbCommand::GetBlock(cmd)) => {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
-                let stacks_db = open_readonly_stacks_db_conn(&config.expected_cache_path(), &ctx)
-                    .expect("unable to read stacks_db");
-                match get_stacks_block_at_block_height(cmd.block_height, true, 3, &stacks_db) {
+                let stacks_db = open_readonly_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    &ctx,
+                )
+                .expect("unable to read stacks_db");
+                match get_stacks_block_at_block_height(
+                    cmd.block_height,
+                    true,
+                    3,
+                    stacks_db.as_ref(),
+                ) {
                     Ok(Some(block)) => {
                         info!(ctx.expect_logger(), "{}", json!(block));
                     }
@@ -819,12 +1583,15 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                 let config = Config::default(false, false, false, &cmd.config_path)?;
                 // Delete data, if any
                 {
-                    let stacks_db =
-                        open_readonly_stacks_db_conn(&config.expected_cache_path(), &ctx)?;
+                    let stacks_db = open_readonly_stacks_db_conn(
+                        &config.expected_cache_path(),
+                        &config.storage.stacks_db_backend,
+                        &ctx,
+                    )?;
                     let mut missing_blocks = vec![];
                     let mut min = 0;
                     let mut max = 0;
-                    if let Some(tip) = get_last_block_height_inserted(&stacks_db, &ctx) {
+                    if let Some(tip) = get_last_block_height_inserted(stacks_db.as_ref(), &ctx) {
                         min = 1;
                         max = tip;
                         for index in 1..=tip {
@@ -832,7 +1599,7 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                                 index,
                                 hash: "".into(),
                             };
-                            if !is_stacks_block_present(&block_identifier, 3, &stacks_db) {
+                            if !is_stacks_block_present(&block_identifier, 3, stacks_db.as_ref()) {
                                 missing_blocks.push(index);
                             }
                         }
@@ -853,6 +1620,12 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                 }
             }
         },
+        Command::Db(subcmd) => match subcmd {
+            DbCommand::Migrate(cmd) => {
+                let config = Config::default(false, false, false, &cmd.config_path)?;
+                migrate_dbs(&config, &ctx)?;
+            }
+        },
         Command::Docs(subcmd) => match subcmd {
             DocsCommand::Api(api_docs_cmd) => match api_docs_cmd {
                 ApiDocsCommand::Generate => {
@@ -871,7 +1644,245 @@ async fn handle_command(opts: Opts, ctx: Context) -> Result<(), String> {
                     println!("Created file openapi.json");
                 }
             },
+            DocsCommand::Schemas(schemas_docs_cmd) => match schemas_docs_cmd {
+                SchemasDocsCommand::Generate => {
+                    for name in JSON_SCHEMA_NAMES {
+                        let schema = json_schema(name).expect("schema names are known");
+                        let file_path = PathBuf::from(format!("{}.schema.json", name));
+                        let content = serde_json::to_string_pretty(&schema)
+                            .map_err(|e| format!("unable to serialize schema {}: {}", name, e))?;
+                        std::fs::write(&file_path, content).map_err(|e| {
+                            format!("unable to write file {}\n{}", file_path.display(), e)
+                        })?;
+                        println!("Created file {}", file_path.display());
+                    }
+                }
+            },
         },
+        Command::Replay(cmd) => {
+            let config = Config::default(false, cmd.testnet, cmd.mainnet, &cmd.config_path)?;
+            let mut predicates = load_predicates_to_replay(&config, &ctx)?;
+            if !cmd.predicate_uuids.is_empty() {
+                predicates.retain(|predicate| {
+                    cmd.predicate_uuids
+                        .iter()
+                        .any(|uuid| uuid == predicate.uuid())
+                });
+            }
+            for predicate_path in cmd.predicate_paths.iter() {
+                let predicate = load_predicate_from_path(predicate_path)?;
+                predicate.validate()?;
+                predicates.push(into_chainhook_instance(predicate, &config)?);
+            }
+            if let Some(ref chain) = cmd.chain {
+                predicates.retain(|predicate| match predicate {
+                    ChainhookInstance::Bitcoin(_) => chain == "bitcoin",
+                    ChainhookInstance::Stacks(_) => chain == "stacks",
+                });
+            }
+            if predicates.is_empty() {
+                return Err("no predicate to replay".into());
+            }
+            let replays_bitcoin = predicates
+                .iter()
+                .any(|predicate| matches!(predicate, ChainhookInstance::Bitcoin(_)));
+            let replays_stacks = predicates
+                .iter()
+                .any(|predicate| matches!(predicate, ChainhookInstance::Stacks(_)));
+            if replays_bitcoin && replays_stacks {
+                return Err(
+                    "Bitcoin and Stacks predicates can't be replayed together, set --chain".into(),
+                );
+            }
+            let replayed = replay_blocks(predicates, cmd.from, cmd.to, &config, &ctx).await?;
+            for uuid in replayed.iter() {
+                println!(
+                    "✔️ Predicate {} replayed on blocks #{} to #{}",
+                    uuid, cmd.from, cmd.to
+                );
+            }
+        }
+        Command::Completions(cmd) => {
+            let mut command = Opts::command();
+            let mut stdout = std::io::stdout();
+            match cmd.shell.as_str() {
+                "bash" => generate(Bash, &mut command, "chainhook", &mut stdout),
+                "zsh" => generate(Zsh, &mut command, "chainhook", &mut stdout),
+                _ => generate(Fish, &mut command, "chainhook", &mut stdout),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Selects the specification of the predicate for the network of the config.
+fn into_chainhook_instance(
+    predicate: ChainhookSpecificationNetworkMap,
+    config: &Config,
+) -> Result<ChainhookInstance, String> {
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => predicate
+            .into_specification_for_network(&config.network.bitcoin_network)
+            .map(ChainhookInstance::Bitcoin),
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => predicate
+            .into_specification_for_network(&config.network.stacks_network)
+            .map(ChainhookInstance::Stacks),
+    }
+}
+
+/// Makes the predicate write its occurrences to stdout, one JSON payload per line.
+fn set_stdout_action(predicate: &mut ChainhookSpecificationNetworkMap) {
+    let action = HookAction::FileAppend(FileHook {
+        path: STDOUT_FILE_SINK_PATH.into(),
+        rotation: None,
+        compression: None,
+        delivery: DeliveryOptions::default(),
+    });
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
+            for spec in predicate.networks.values_mut() {
+                spec.action = action.clone();
+                spec.rollback_action = None;
+            }
+        }
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => {
+            for spec in predicate.networks.values_mut() {
+                spec.action = action.clone();
+                spec.rollback_action = None;
+            }
+        }
+    }
+}
+
+/// Overrides the blocks scanned by the predicate with the range starting at `start_block` and
+/// ending at `end_block`. The bounds left unset are kept from the predicate.
+fn set_block_range(
+    predicate: &mut ChainhookSpecificationNetworkMap,
+    start_block: Option<u64>,
+    end_block: Option<u64>,
+) {
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
+            for spec in predicate.networks.values_mut() {
+                spec.blocks = None;
+                spec.start_block = start_block.or(spec.start_block);
+                spec.end_block = end_block.or(spec.end_block);
+            }
+        }
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => {
+            for spec in predicate.networks.values_mut() {
+                spec.blocks = None;
+                spec.start_block = start_block.or(spec.start_block);
+                spec.end_block = end_block.or(spec.end_block);
+            }
+        }
+    }
+}
+
+/// Rejects the predicates that can't trigger when scanning blocks: `bitcoin_anchored` Stacks
+/// predicates are resolved against the Bitcoin blocks tracked while streaming, unknown to scans.
+fn check_scannable_predicate(predicate: &ChainhookSpecificationNetworkMap) -> Result<(), String> {
+    let ChainhookSpecificationNetworkMap::Stacks(predicate) = predicate else {
+        return Ok(());
+    };
+    for spec in predicate.networks.values() {
+        if let StacksPredicate::BitcoinAnchored(_) = spec.predicate {
+            return Err(format!(
+                "Predicate {} can't be scanned: 'bitcoin_anchored' predicates only trigger while streaming blocks",
+                predicate.uuid
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Scans a predicate of `predicates scan`. Unless `in_chunks`, the predicate is scanned as the
+/// service does; otherwise its range is split into `chunks` scanned concurrently. The Stacks
+/// chainstate is scanned from `stacks_db_conn`, or from the TSV file when the db is unavailable.
+async fn scan_predicate(
+    predicate: ChainhookSpecificationNetworkMap,
+    config: &Config,
+    stacks_db_conn: Option<Arc<dyn StacksStorage>>,
+    in_chunks: bool,
+    chunks: usize,
+    ctx: &Context,
+) -> Result<(), String> {
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
+            let predicate_spec =
+                match predicate.into_specification_for_network(&config.network.bitcoin_network) {
+                    Ok(predicate) => predicate,
+                    Err(e) => {
+                        return Err(format!(
+                            "Specification missing for network {:?}: {e}",
+                            config.network.bitcoin_network
+                        ));
+                    }
+                };
+            if in_chunks {
+                scan_bitcoin_chainstate_via_rpc_using_predicate_in_chunks(
+                    &predicate_spec,
+                    config,
+                    chunks,
+                    open_bitcoin_block_cache(config, ctx),
+                    ctx,
+                )
+                .await?;
+            } else {
+                scan_bitcoin_chainstate_via_rpc_using_predicate(
+                    &predicate_spec,
+                    None,
+                    config,
+                    None,
+                    open_bitcoin_block_cache(config, ctx),
+                    ctx,
+                )
+                .await?;
+            }
+        }
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => {
+            let predicate_spec =
+                match predicate.into_specification_for_network(&config.network.stacks_network) {
+                    Ok(predicate) => predicate,
+                    Err(e) => {
+                        return Err(format!(
+                            "Specification missing for network {:?}: {e}",
+                            config.network.bitcoin_network
+                        ));
+                    }
+                };
+            match stacks_db_conn {
+                Some(stacks_db_conn) if in_chunks => {
+                    scan_stacks_chainstate_via_rocksdb_using_predicate_in_chunks(
+                        &predicate_spec,
+                        stacks_db_conn,
+                        config,
+                        chunks,
+                        ctx,
+                    )
+                    .await?;
+                }
+                Some(stacks_db_conn) => {
+                    scan_stacks_chainstate_via_rocksdb_using_predicate(
+                        &predicate_spec,
+                        None,
+                        stacks_db_conn.as_ref(),
+                        config,
+                        None,
+                        ctx,
+                    )
+                    .await?;
+                }
+                None => {
+                    scan_stacks_chainstate_via_csv_using_predicate(
+                        &predicate_spec,
+                        &mut config.clone(),
+                        ctx,
+                    )
+                    .await?;
+                }
+            }
+        }
     }
     Ok(())
 }
@@ -891,6 +1902,83 @@ pub fn load_predicate_from_path(
     Ok(predicate)
 }
 
+/// Loads a predicate as is, e.g. to check the fields that would be ignored by
+/// [load_predicate_from_path].
+pub fn load_predicate_json_from_path(predicate_path: &str) -> Result<JsonValue, String> {
+    let file_buffer = std::fs::read(predicate_path)
+        .map_err(|e| format!("unable to read file {}\n{:?}", predicate_path, e))?;
+    serde_json::from_slice(&file_buffer)
+        .map_err(|e| format!("unable to parse json file {}\n{:?}", predicate_path, e))
+}
+
+/// Verifies the Stacks db, comparing the hashes of its `depth` latest blocks with the canonical
+/// chain of the Stacks node if set, and logs the inconsistencies found. Returns the height from
+/// which the db needs to be ingested again, if any.
+async fn find_first_invalid_stacks_block(
+    config: &Config,
+    stacks_db: &dyn StacksStorage,
+    depth: Option<u64>,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    let report = verify_stacks_db(stacks_db, ctx)?;
+    if !report.missing_blocks.is_empty() {
+        warn!(
+            ctx.expect_logger(),
+            "Stacks db is missing {} blocks: {:?}",
+            report.missing_blocks.len(),
+            report.missing_blocks
+        );
+    }
+    if !report.corrupted_blocks.is_empty() {
+        warn!(
+            ctx.expect_logger(),
+            "Stacks db holds {} corrupted blocks: {:?}",
+            report.corrupted_blocks.len(),
+            report.corrupted_blocks
+        );
+    }
+    if !report.discontinuous_blocks.is_empty() {
+        warn!(
+            ctx.expect_logger(),
+            "Stacks db holds {} blocks whose parent doesn't match the block below them: {:?}",
+            report.discontinuous_blocks.len(),
+            report.discontinuous_blocks
+        );
+    }
+    let mut first_invalid_block_height = report.first_invalid_block_height();
+
+    if let Some(depth) = depth {
+        let BitcoinBlockSignaling::Stacks(ref stacks_node) = config.network.bitcoin_block_signaling
+        else {
+            return Err(
+                "comparing the Stacks db with the canonical chain requires a Stacks node".into(),
+            );
+        };
+        let canonical_blocks = retrieve_canonical_stacks_block_identifiers(
+            &build_http_client(),
+            &stacks_node.rpc_url,
+            depth,
+        )
+        .await?;
+        if let Some(block_height) = find_stacks_db_divergence(&canonical_blocks, stacks_db, ctx)? {
+            warn!(
+                ctx.expect_logger(),
+                "Stacks db diverges from the canonical chain at block #{block_height}"
+            );
+            first_invalid_block_height =
+                Some(first_invalid_block_height.map_or(block_height, |h| h.min(block_height)));
+        }
+    }
+
+    if first_invalid_block_height.is_none() {
+        info!(
+            ctx.expect_logger(),
+            "Stacks db successfully verified (tip: {:?})", report.tip
+        );
+    }
+    Ok(first_invalid_block_height)
+}
+
 fn parse_blocks_heights_spec(
     blocks_interval: &Option<String>,
     blocks: &Option<String>,
@@ -921,3 +2009,6 @@ fn parse_blocks_heights_spec(
     };
     blocks
 }
+
+#[cfg(test)]
+pub mod tests;
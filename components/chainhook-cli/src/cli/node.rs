@@ -0,0 +1,117 @@
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::Value as JsonValue;
+
+/// Client of the predicates API of a running `chainhook service`.
+pub struct PredicatesApiClient {
+    http_client: Client,
+    node_url: String,
+    api_key: Option<String>,
+    namespace: Option<String>,
+}
+
+impl PredicatesApiClient {
+    pub fn new(node_url: &str, api_key: Option<String>, namespace: Option<String>) -> Self {
+        PredicatesApiClient {
+            http_client: Client::new(),
+            node_url: node_url.trim_end_matches('/').to_string(),
+            api_key,
+            namespace,
+        }
+    }
+
+    /// Lists the predicates registered on the node, optionally restricted to a chain and a
+    /// status type.
+    pub async fn list_predicates(
+        &self,
+        chain: Option<&str>,
+        status: Option<&str>,
+    ) -> Result<Vec<JsonValue>, String> {
+        let mut query = vec![];
+        if let Some(chain) = chain {
+            query.push(("chain", chain));
+        }
+        if let Some(status) = status {
+            query.push(("status", status));
+        }
+        let request = self.request(Method::GET, "/v1/chainhooks").query(&query);
+        match self.send(request).await? {
+            JsonValue::Array(predicates) => Ok(predicates),
+            result => Err(format!("unexpected list of predicates: {}", result)),
+        }
+    }
+
+    pub async fn get_predicate(&self, uuid: &str) -> Result<JsonValue, String> {
+        let request = self.request(Method::GET, &format!("/v1/chainhooks/{}", uuid));
+        self.send(request).await
+    }
+
+    /// Deregisters a predicate. Its chain is part of the route, so the predicate is retrieved
+    /// first.
+    pub async fn delete_predicate(&self, uuid: &str) -> Result<(), String> {
+        let predicate = self.get_predicate(uuid).await?;
+        let chain = predicate["chain"].as_str().ok_or(format!(
+            "unable to retrieve the chain of predicate {}",
+            uuid
+        ))?;
+        let request = self.request(
+            Method::DELETE,
+            &format!("/v1/chainhooks/{}/{}", chain, uuid),
+        );
+        self.send(request).await.map(|_| ())
+    }
+
+    pub async fn pause_predicate(&self, uuid: &str) -> Result<(), String> {
+        let request = self.request(Method::POST, &format!("/v1/chainhooks/{}/pause", uuid));
+        self.send(request).await.map(|_| ())
+    }
+
+    pub async fn resume_predicate(&self, uuid: &str) -> Result<(), String> {
+        let request = self.request(Method::POST, &format!("/v1/chainhooks/{}/resume", uuid));
+        self.send(request).await.map(|_| ())
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self
+            .http_client
+            .request(method, format!("{}{}", self.node_url, path))
+            .header("Content-Type", "application/json");
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        if let Some(ref namespace) = self.namespace {
+            request = request.header("Chainhook-Namespace", namespace);
+        }
+        request
+    }
+
+    /// Sends a request and returns the `result` of its response. The API reports failures in
+    /// the `status` of the body as well as in the status of the response.
+    async fn send(&self, request: RequestBuilder) -> Result<JsonValue, String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("unable to reach {}: {}", self.node_url, e))?;
+        let http_status = response.status();
+        let body: JsonValue = response.json().await.map_err(|e| {
+            format!(
+                "unable to parse the response of {} ({}): {}",
+                self.node_url, http_status, e
+            )
+        })?;
+        let status = body["status"]
+            .as_u64()
+            .unwrap_or(http_status.as_u16() as u64);
+        if !http_status.is_success() || status != 200 {
+            let reason = body["error"]
+                .as_str()
+                .or(body["message"].as_str())
+                .map(|reason| format!(": {}", reason))
+                .unwrap_or_default();
+            return match status {
+                404 => Err(format!("predicate not found{}", reason)),
+                _ => Err(format!("request failed with status {}{}", status, reason)),
+            };
+        }
+        Ok(body["result"].clone())
+    }
+}
@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+/// Names of the templates `predicates new --template` accepts.
+pub const PREDICATE_TEMPLATES: &[&str] = &["ft-transfer", "op-return", "contract-call"];
+
+/// Commented predicates covering common use cases, with their networks and block ranges
+/// pre-filled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PredicateTemplate {
+    FtTransfer,
+    OpReturn,
+    ContractCall,
+}
+
+impl FromStr for PredicateTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        match template {
+            "ft-transfer" => Ok(PredicateTemplate::FtTransfer),
+            "op-return" => Ok(PredicateTemplate::OpReturn),
+            "contract-call" => Ok(PredicateTemplate::ContractCall),
+            _ => Err(format!(
+                "unknown template {}, expected one of {}",
+                template,
+                PREDICATE_TEMPLATES.join(", ")
+            )),
+        }
+    }
+}
+
+impl PredicateTemplate {
+    fn content(&self) -> &'static str {
+        match self {
+            PredicateTemplate::FtTransfer => include_str!("templates/ft-transfer.json"),
+            PredicateTemplate::OpReturn => include_str!("templates/op-return.json"),
+            PredicateTemplate::ContractCall => include_str!("templates/contract-call.json"),
+        }
+    }
+
+    /// Returns the json of the template, identified by `uuid`. The template is kept as text so
+    /// that its fields and comments stay in order.
+    pub fn generate(&self, uuid: &str) -> String {
+        self.content().replace("{uuid}", uuid)
+    }
+}
@@ -0,0 +1,26 @@
+use chainhook_sdk::chainhooks::diagnostics::check_predicate;
+use chainhook_sdk::chainhooks::types::ChainhookSpecificationNetworkMap;
+use chainhook_sdk::types::{BitcoinNetwork, StacksNetwork};
+use serde_json::Value as JsonValue;
+use test_case::test_case;
+
+use super::templates::PredicateTemplate;
+
+#[test_case("ft-transfer")]
+#[test_case("op-return")]
+#[test_case("contract-call")]
+fn predicate_templates_check_on_every_network(template: &str) {
+    let template: PredicateTemplate = template.parse().unwrap();
+    let content = template.generate("4ecc-4ecc-435b-9948-d5eeca1c3ce6");
+    let predicate: ChainhookSpecificationNetworkMap = serde_json::from_str(&content).unwrap();
+    assert_eq!(predicate.get_uuid(), "4ecc-4ecc-435b-9948-d5eeca1c3ce6");
+
+    let predicate: JsonValue = serde_json::from_str(&content).unwrap();
+    for (bitcoin_network, stacks_network) in [
+        (BitcoinNetwork::Testnet, StacksNetwork::Testnet),
+        (BitcoinNetwork::Mainnet, StacksNetwork::Mainnet),
+    ] {
+        let diagnostics = check_predicate(&predicate, &bitcoin_network, &stacks_network);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+}
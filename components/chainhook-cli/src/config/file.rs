@@ -1,4 +1,11 @@
-use chainhook_sdk::types::BitcoinNetwork;
+use std::collections::BTreeMap;
+
+use chainhook_sdk::chainhooks::types::HttpTlsConfig;
+use chainhook_sdk::indexer::bitcoin::endpoints::BitcoindRpcEndpoint;
+use chainhook_sdk::indexer::MalformedStacksTransactionPolicy;
+use chainhook_sdk::observer::{CircuitBreakerConfig, FullChannelPolicy};
+use chainhook_sdk::types::{BitcoinNetwork, ServerTlsConfig};
+use chainhook_sdk::utils::rate_limit::RateLimitConfig;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ConfigFile {
@@ -10,6 +17,30 @@ pub struct ConfigFile {
     pub limits: LimitsConfigFile,
     pub network: NetworkConfigFile,
     pub monitoring: Option<MonitoringConfigFile>,
+    pub additional_networks: Option<Vec<AdditionalNetworkConfigFile>>,
+    pub replication: Option<ReplicationConfigFile>,
+    pub logs: Option<LogsConfigFile>,
+    pub error_reporting: Option<ErrorReportingConfigFile>,
+}
+
+/// Another network observed by the same service. The predicates and limits settings are
+/// shared with the main network, the storage, API and monitoring ones must not overlap.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AdditionalNetworkConfigFile {
+    pub storage: StorageConfigFile,
+    pub pox_config: Option<PoxConfigFile>,
+    pub http_api: Option<PredicatesApiConfigFile>,
+    pub network: NetworkConfigFile,
+    pub monitoring: Option<MonitoringConfigFile>,
+}
+
+/// Instances sharing the same storage and predicates db.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReplicationConfigFile {
+    /// Number of instances, the primary included.
+    pub instances: u64,
+    /// Index of this instance, `0` being the primary.
+    pub instance_index: u64,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -23,6 +54,15 @@ pub struct PoxConfigFile {
 #[derive(Deserialize, Debug, Clone)]
 pub struct StorageConfigFile {
     pub working_dir: Option<String>,
+    pub stacks_max_retained_blocks: Option<u64>,
+    pub stacks_max_retained_days: Option<u64>,
+    pub block_cache_size_mb: Option<u64>,
+    pub stacks_verification_depth: Option<u64>,
+    /// Either `rocksdb`, the default, or `sqlite`.
+    pub stacks_db_backend: Option<String>,
+    /// Name of the environment variable holding the encryption key, 32 bytes hex encoded.
+    pub encryption_key_env: Option<String>,
+    pub allow_plaintext_payloads: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -31,6 +71,38 @@ pub struct PredicatesApiConfigFile {
     pub database_uri: Option<String>,
     pub display_logs: Option<bool>,
     pub disabled: Option<bool>,
+    pub api_keys: Option<Vec<ApiKeyConfigFile>>,
+    pub lifecycle_webhook_url: Option<String>,
+    pub tls: Option<ServerTlsConfig>,
+    pub cors: Option<CorsConfigFile>,
+    pub rate_limit: Option<ApiRateLimitConfigFile>,
+    pub audit_log_max_entries: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CorsConfigFile {
+    /// Origins allowed to call the API from a browser, `*` allowing any origin.
+    pub allowed_origins: Vec<String>,
+    pub allowed_headers: Option<Vec<String>>,
+    pub allowed_methods: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiRateLimitConfigFile {
+    /// Requests accepted per minute from each ip address, for requests without an api key.
+    pub requests_per_minute_per_ip: Option<u32>,
+    /// Requests accepted per minute with each api key.
+    pub requests_per_minute_per_key: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiKeyConfigFile {
+    pub key: String,
+    /// Either `read` or `manage`, the default.
+    pub scope: Option<String>,
+    /// Namespace the key is restricted to. Keys without a namespace can select one with the
+    /// `Chainhook-Namespace` header.
+    pub namespace: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -52,6 +124,14 @@ pub struct LimitsConfigFile {
     pub max_number_of_processing_threads: Option<usize>,
     pub max_number_of_networking_threads: Option<usize>,
     pub max_caching_memory_size_mb: Option<usize>,
+    pub max_number_of_pending_observer_commands: Option<usize>,
+    pub max_number_of_pending_observer_events: Option<usize>,
+    pub observer_events_full_policy: Option<FullChannelPolicy>,
+    pub max_number_of_pending_scan_operations: Option<usize>,
+    pub scan_operations_full_policy: Option<FullChannelPolicy>,
+    pub max_number_of_cached_bitcoin_blocks: Option<usize>,
+    pub max_number_of_prefetched_bitcoin_blocks: Option<usize>,
+    pub max_number_of_cached_bitcoin_prevouts: Option<usize>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -60,14 +140,32 @@ pub struct NetworkConfigFile {
     pub bitcoind_rpc_url: String,
     pub bitcoind_rpc_username: String,
     pub bitcoind_rpc_password: String,
+    pub bitcoind_rpc_fallbacks: Option<Vec<BitcoindRpcEndpoint>>,
+    pub esplora_api_url: Option<String>,
     pub bitcoind_zmq_url: Option<String>,
+    pub bitcoind_polling_interval_ms: Option<u64>,
     pub stacks_node_rpc_url: Option<String>,
     pub stacks_events_ingestion_port: Option<u16>,
+    pub stacks_events_ingestion_auth_token: Option<String>,
+    pub stacks_events_ingestion_tls: Option<ServerTlsConfig>,
+    pub bitcoin_confirmation_depth: Option<u64>,
+    pub stacks_confirmation_depth: Option<u64>,
+    /// Magic bytes prefixing the Stacks operations in Bitcoin transactions, e.g. `"id"`, when
+    /// they differ from the canonical ones of the network.
+    pub stacks_magic_bytes: Option<String>,
+    /// Whether a Stacks transaction that can't be standardized is left out of its block
+    /// (`"skip"`, the default) or fails it (`"fail_block"`).
+    pub malformed_stacks_transactions: Option<MalformedStacksTransactionPolicy>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PredicatesConfigFile {
     pub payload_http_request_timeout_ms: Option<u64>,
+    pub http_tls: Option<HttpTlsConfig>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub shutdown_drain_timeout_ms: Option<u64>,
+    pub watch_dir: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -102,3 +200,17 @@ impl NetworkConfigMode {
 pub struct MonitoringConfigFile {
     pub prometheus_monitoring_port: Option<u16>,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LogsConfigFile {
+    pub level: Option<String>,
+    pub format: Option<String>,
+    /// Levels of the modules whose path starts with the key, e.g. `chainhook_sdk::observer`.
+    pub modules: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ErrorReportingConfigFile {
+    pub dsn: String,
+    pub environment: Option<String>,
+}
@@ -23,7 +23,8 @@ bitcoind_rpc_username = "devnet"
 bitcoind_rpc_password = "devnet"
 
 # Chainhook must be able to receive Bitcoin block events.
-# These events can originate from either a Stacks node or a Bitcoin node's ZeroMQ interface.
+# These events can originate from either a Stacks node or a Bitcoin node's ZeroMQ interface,
+# or be polled from the Bitcoin node's RPC interface.
 
 # By default, the service is set to receive Bitcoin block events from the Stacks node:
 stacks_node_rpc_url = "http://localhost:20443"
@@ -32,6 +33,9 @@ stacks_node_rpc_url = "http://localhost:20443"
 # To achieve this, comment out the `stacks_node_rpc_url` line and uncomment the following line:
 # bitcoind_zmq_url = "tcp://0.0.0.0:18543"
 
+# When neither a Stacks node nor ZeroMQ are available, bitcoind can be polled for new blocks instead:
+# bitcoind_polling_interval_ms = 5000
+
 [limits]
 max_number_of_bitcoin_predicates = 100
 max_number_of_concurrent_bitcoin_scans = 100
@@ -50,6 +54,19 @@ tsv_file_url = "https://archive.hiro.so/{network}/stacks-blockchain-api/{network
 # This is disabled by default.
 # [monitoring]
 # prometheus_monitoring_port = 20457
+
+# Verbosity and format of the logs, "info" and "text" by default.
+# The --log-level, --log-format and --log-module flags take precedence over these settings.
+# [logs]
+# level = "info"
+# format = "json"
+# modules = {{ "chainhook_sdk::observer" = "warn" }}
+
+# Reports panics and errors to a Sentry-compatible backend, for builds with the
+# error-reporting feature. This is disabled by default.
+# [error_reporting]
+# dsn = "https://<public_key>@sentry.example.com/<project_id>"
+# environment = "{network}"
 "#,
         mode = mode.as_str(),
         network = network.to_lowercase(),
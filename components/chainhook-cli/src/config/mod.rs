@@ -1,17 +1,31 @@
 pub mod file;
 pub mod generator;
+pub mod report;
 
-use chainhook_sdk::chainhooks::types::{ChainhookStore, PoxConfig};
+use chainhook_sdk::chainhooks::types::{validate_namespace, NetworkParameters, PoxConfig};
+use chainhook_sdk::indexer::stacks::MAX_STACKS_HEADERS_PER_REQUEST;
 pub use chainhook_sdk::indexer::IndexerConfig;
-use chainhook_sdk::observer::{EventObserverConfig, PredicatesConfig};
+use chainhook_sdk::indexer::MalformedStacksTransactionPolicy;
+use chainhook_sdk::observer::{
+    EventObserverConfig, EventObserverConfigBuilder, FullChannelPolicy, PredicatesConfig,
+    DEFAULT_OBSERVER_COMMANDS_CAPACITY, DEFAULT_OBSERVER_EVENTS_CAPACITY,
+};
 use chainhook_sdk::types::{
-    BitcoinBlockSignaling, BitcoinNetwork, StacksNetwork, StacksNodeConfig,
+    BitcoinBlockSignaling, BitcoinNetwork, ServerTlsConfig, StacksNetwork, StacksNodeConfig,
 };
 pub use file::ConfigFile;
+use file::{
+    AdditionalNetworkConfigFile, ApiKeyConfigFile, ApiRateLimitConfigFile, CorsConfigFile,
+    ErrorReportingConfigFile, LogsConfigFile, PoxConfigFile, ReplicationConfigFile,
+};
+use hiro_system_kit::slog::Level;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
 
+use crate::storage::encryption::enable_payload_encryption;
+
 const DEFAULT_MAINNET_STACKS_TSV_ARCHIVE: &str =
     "https://archive.hiro.so/mainnet/stacks-blockchain-api/mainnet-stacks-blockchain-api-latest";
 const DEFAULT_TESTNET_STACKS_TSV_ARCHIVE: &str =
@@ -20,10 +34,18 @@ pub const DEFAULT_REDIS_URI: &str = "redis://localhost:6379/";
 
 pub const DEFAULT_INGESTION_PORT: u16 = 20455;
 pub const DEFAULT_CONTROL_PORT: u16 = 20456;
+pub const DEFAULT_CORS_ALLOWED_HEADERS: [&str; 3] =
+    ["Authorization", "Content-Type", "Chainhook-Namespace"];
+pub const DEFAULT_CORS_ALLOWED_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
 pub const STACKS_SCAN_THREAD_POOL_SIZE: usize = 10;
 pub const BITCOIN_SCAN_THREAD_POOL_SIZE: usize = 10;
+pub const BITCOIN_SCAN_PREFETCH_DEPTH: usize = 8;
+pub const DEFAULT_SCAN_OPERATIONS_CAPACITY: usize = 128;
 pub const STACKS_MAX_PREDICATE_REGISTRATION: usize = 50;
 pub const BITCOIN_MAX_PREDICATE_REGISTRATION: usize = 50;
+/// Confirmation depth used by default on devnet and signet, where deep reorgs aren't expected and
+/// integration tests shouldn't have to mine 7 blocks before seeing confirmations.
+pub const TEST_NETWORK_CONFIRMATION_DEPTH: u64 = 3;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Config {
@@ -31,15 +53,70 @@ pub struct Config {
     pub pox_config: PoxConfig,
     pub http_api: PredicatesApi,
     pub predicates: PredicatesConfig,
+    /// Directory whose predicates, one per JSON file, are registered and deregistered as the
+    /// files are added, modified or removed.
+    pub predicates_watch_dir: Option<PathBuf>,
     pub event_sources: Vec<EventSourceConfig>,
     pub limits: LimitsConfig,
     pub network: IndexerConfig,
     pub monitoring: MonitoringConfig,
+    /// Other networks observed by the same service, each one with its own storage and
+    /// observer. Their own `additional_networks` are always empty.
+    pub additional_networks: Vec<Config>,
+    /// Instances sharing the storage and predicates db of this one. This instance is the only
+    /// one if not set.
+    pub replication: Option<ReplicationConfig>,
+    pub logs: LogsConfig,
+    /// Backend the panics and errors of the service are reported to. Errors are not reported if
+    /// not set.
+    pub error_reporting: Option<ErrorReportingConfig>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct StorageConfig {
     pub working_dir: String,
+    /// Confirmed Stacks blocks kept in the Stacks db by the service. All of them are kept if
+    /// not set.
+    pub stacks_retention: Option<StacksRetentionConfig>,
+    /// Size of the on-disk cache of standardized Bitcoin blocks evaluated by scans, in megabytes.
+    /// Blocks are not cached if not set.
+    pub block_cache_size_mb: Option<u64>,
+    /// Number of most recent Stacks blocks periodically checked against the canonical chain of
+    /// the Stacks node, to detect the reorgs missed while the service was down. Blocks are not
+    /// checked if not set.
+    pub stacks_verification_depth: Option<u64>,
+    /// Storage backing the Stacks db, in `working_dir`.
+    pub stacks_db_backend: StacksDbBackend,
+    /// Environment variable holding the key the stored blocks and deliveries are encrypted with.
+    /// They are stored in clear if not set.
+    pub encryption_key_env: Option<String>,
+    /// Reads the blocks and deliveries stored in clear once encryption is enabled, while the data
+    /// stored before is migrated. They are rejected otherwise.
+    pub allow_plaintext_payloads: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StacksDbBackend {
+    /// `stacks.rocksdb` directory.
+    RocksDb,
+    /// `stacks.sqlite` file, lighter and easier to inspect, for small deployments.
+    Sqlite,
+}
+
+/// Older confirmed Stacks blocks are periodically pruned from the Stacks db, as soon as they fall
+/// out of any of these limits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StacksRetentionConfig {
+    pub max_blocks: Option<u64>,
+    pub max_age_days: Option<u64>,
+}
+
+impl StacksRetentionConfig {
+    /// Unix timestamp before which blocks are pruned, if blocks are pruned by age.
+    pub fn retained_since(&self, now: i64) -> Option<i64> {
+        self.max_age_days
+            .map(|days| now.saturating_sub((days as i64).saturating_mul(24 * 3600)))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -53,6 +130,110 @@ pub struct PredicatesApiConfig {
     pub http_port: u16,
     pub database_uri: String,
     pub display_logs: bool,
+    /// Keys expected in the `Authorization` header of the requests. The API is open if empty.
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Url notified with a POST request whenever a predicate is registered, starts streaming,
+    /// expires, gets interrupted or is deregistered.
+    pub lifecycle_webhook_url: Option<String>,
+    /// Certificate the API is served with, over HTTPS. Plain HTTP is used if absent.
+    pub tls: Option<ServerTlsConfig>,
+    /// Cross-origin requests accepted from browsers. They are rejected by browsers if absent.
+    pub cors: Option<CorsConfig>,
+    /// Requests accepted per minute from each client. Requests are not limited if absent.
+    pub rate_limit: Option<ApiRateLimitConfig>,
+    /// Number of transitions kept in the audit log, the oldest ones being dropped first. The
+    /// log is append-only if absent.
+    pub audit_log_max_entries: Option<usize>,
+}
+
+impl PredicatesApiConfig {
+    /// Returns the scope granted to a request carrying this `Authorization` header, if any.
+    pub fn granted_scope(&self, authorization: Option<&str>) -> Option<ApiKeyScope> {
+        if self.api_keys.is_empty() {
+            return Some(ApiKeyScope::Manage);
+        }
+        self.api_key(authorization)
+            .map(|api_key| api_key.scope.clone())
+    }
+
+    /// Returns the namespace the api key carried by this `Authorization` header is restricted
+    /// to, if any.
+    pub fn restricted_namespace(&self, authorization: Option<&str>) -> Option<&str> {
+        self.api_key(authorization)
+            .and_then(|api_key| api_key.namespace.as_deref())
+    }
+
+    /// Returns the configured api key carried by this `Authorization` header, if any.
+    pub fn api_key(&self, authorization: Option<&str>) -> Option<&ApiKeyConfig> {
+        let authorization = authorization?;
+        self.api_keys
+            .iter()
+            .find(|api_key| is_api_key_valid(authorization, &api_key.key))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    pub scope: ApiKeyScope,
+    pub namespace: Option<String>,
+}
+
+impl ApiKeyConfig {
+    /// Identifies the api key in logs without disclosing it: only the last 4 characters of keys
+    /// longer than 8 characters are kept.
+    pub fn masked_key(&self) -> String {
+        let chars = self.key.chars().collect::<Vec<_>>();
+        match chars.len() {
+            0..=8 => "****".to_string(),
+            len => format!("****{}", chars[len - 4..].iter().collect::<String>()),
+        }
+    }
+}
+
+/// Routes of the predicates API an api key grants access to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiKeyScope {
+    /// Listing and reading predicates, and streaming their occurrences.
+    Read,
+    /// Registering, updating, pausing, resuming and deleting predicates, on top of reading them.
+    Manage,
+}
+
+impl ApiKeyScope {
+    pub fn includes(&self, scope: &ApiKeyScope) -> bool {
+        matches!(
+            (self, scope),
+            (ApiKeyScope::Manage, _) | (ApiKeyScope::Read, ApiKeyScope::Read)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiRateLimitConfig {
+    pub requests_per_minute_per_ip: Option<u32>,
+    pub requests_per_minute_per_key: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allowed_methods: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Returns the value of the `Access-Control-Allow-Origin` header answering a request sent
+    /// from `origin`, if allowed.
+    pub fn allowed_origin<'a>(&'a self, origin: &'a str) -> Option<&'a str> {
+        self.allowed_origins
+            .iter()
+            .find(|allowed| allowed.as_str() == "*" || allowed.eq_ignore_ascii_case(origin))
+            .map(|allowed| match allowed.as_str() {
+                "*" => "*",
+                _ => origin,
+            })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -82,12 +263,168 @@ pub struct LimitsConfig {
     pub max_number_of_processing_threads: usize,
     pub max_number_of_networking_threads: usize,
     pub max_caching_memory_size_mb: usize,
+    /// Number of commands, such as new blocks to evaluate, queued for the observer before
+    /// their ingestion waits for it to catch up.
+    pub max_number_of_pending_observer_commands: usize,
+    /// Number of events, such as evaluated blocks, queued for the service before the observer
+    /// applies `observer_events_full_policy`.
+    pub max_number_of_pending_observer_events: usize,
+    pub observer_events_full_policy: FullChannelPolicy,
+    /// Number of predicate scans queued before the service applies
+    /// `scan_operations_full_policy`.
+    pub max_number_of_pending_scan_operations: usize,
+    pub scan_operations_full_policy: FullChannelPolicy,
+    /// Number of unconfirmed Bitcoin blocks the observer keeps in memory before spilling the
+    /// least recently used ones to disk. Unbounded if not set.
+    pub max_number_of_cached_bitcoin_blocks: Option<usize>,
+    /// Number of blocks each Bitcoin scan downloads concurrently ahead of their evaluation.
+    pub max_number_of_prefetched_bitcoin_blocks: usize,
+    /// Number of Bitcoin outputs kept in memory to resolve the prevouts bitcoind leaves out of
+    /// blocks it lacks the undo data of. Setting it enables their resolution.
+    pub max_number_of_cached_bitcoin_prevouts: Option<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct MonitoringConfig {
     pub prometheus_monitoring_port: Option<u16>,
 }
+
+/// Verbosity and format of the logs. The settings left unset can be set from the command line,
+/// and default to the `info` level and the text format.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogsConfig {
+    pub level: Option<Level>,
+    pub format: Option<LogFormat>,
+    /// Levels overriding `level` for the modules whose path starts with the key, e.g.
+    /// `chainhook_sdk::observer`.
+    pub module_levels: BTreeMap<String, Level>,
+}
+
+/// Sentry-compatible backend, only used by builds with the `error-reporting` feature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorReportingConfig {
+    /// Data source name of the project, e.g. `https://<public_key>@sentry.example.com/<project_id>`.
+    pub dsn: String,
+    /// Environment the reports are tagged with, the name of the Bitcoin network by default.
+    pub environment: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogsConfig {
+    /// Returns these settings, overridden by the ones set in `overrides`.
+    pub fn overridden_by(&self, overrides: &LogsConfig) -> LogsConfig {
+        let mut module_levels = self.module_levels.clone();
+        module_levels.extend(overrides.module_levels.clone());
+        LogsConfig {
+            level: overrides.level.or(self.level),
+            format: overrides.format.or(self.format),
+            module_levels,
+        }
+    }
+
+    /// Returns the level of the logs of `module`: the one of the longest module path it starts
+    /// with, `level` otherwise.
+    pub fn level_of(&self, module: &str) -> Level {
+        self.module_levels
+            .iter()
+            .filter(|(path, _)| {
+                module == path.as_str()
+                    || module
+                        .strip_prefix(path.as_str())
+                        .map_or(false, |rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level.unwrap_or(Level::Info))
+    }
+}
+
+pub fn parse_log_level(level: &str) -> Result<Level, String> {
+    level.parse::<Level>().map_err(|_| {
+        format!(
+            "unsupported level {}, expected critical, error, warn, info, debug or trace",
+            level
+        )
+    })
+}
+
+pub fn parse_log_format(format: &str) -> Result<LogFormat, String> {
+    match format {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => Err(format!(
+            "unsupported format {}, expected text or json",
+            format
+        )),
+    }
+}
+
+/// Parses the level of a module, e.g. `chainhook_sdk::observer=warn`.
+pub fn parse_module_log_level(module_level: &str) -> Result<(String, Level), String> {
+    let (module, level) = module_level.split_once('=').ok_or(format!(
+        "invalid module level {}, expected <module>=<level>",
+        module_level
+    ))?;
+    Ok((module.to_string(), parse_log_level(level)?))
+}
+
+/// Points of each instance on the hash ring the predicates are assigned with.
+const VIRTUAL_NODES_PER_INSTANCE: u64 = 64;
+
+/// Instances sharing the same storage and predicates db. The primary, at index `0`, ingests the
+/// chains, manages the predicates and distributes the chain events to the replicas, which attach
+/// to its storage read-only. Each predicate is evaluated by a single instance, picked from its
+/// uuid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicationConfig {
+    pub instances: u64,
+    pub instance_index: u64,
+}
+
+impl ReplicationConfig {
+    pub fn is_replica(&self) -> bool {
+        self.instance_index > 0
+    }
+
+    /// Returns the index of the instance evaluating the predicate `uuid`. Instances and uuids are
+    /// placed on a hash ring, each instance at several points, and a predicate is evaluated by
+    /// the instance of the first point following its uuid, so that adding an instance only moves
+    /// the predicates it takes over.
+    pub fn get_predicate_instance(&self, uuid: &str) -> u64 {
+        let predicate_hash = fnv1a_hash(uuid.as_bytes());
+        (0..self.instances)
+            .flat_map(|instance| (0..VIRTUAL_NODES_PER_INSTANCE).map(move |node| (instance, node)))
+            .min_by_key(|(instance, node)| {
+                fnv1a_hash(format!("{}:{}", instance, node).as_bytes()).wrapping_sub(predicate_hash)
+            })
+            .map(|(instance, _)| instance)
+            .unwrap_or(0)
+    }
+
+    /// Returns whether the predicate `uuid` is evaluated by this instance.
+    pub fn is_predicate_assigned(&self, uuid: &str) -> bool {
+        self.get_predicate_instance(uuid) == self.instance_index
+    }
+}
+
+/// Hashes with FNV-1a, so that all the instances agree whatever their release or platform. The
+/// hash is then mixed with the finalizer of MurmurHash3, to spread similar inputs over the ring.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes.iter().fold(0xcbf29ce484222325, |hash: u64, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
 impl Config {
     pub fn from_file_path(file_path: &str) -> Result<Config, String> {
         let file = File::open(file_path)
@@ -115,30 +452,125 @@ impl Config {
     }
 
     pub fn get_event_observer_config(&self) -> EventObserverConfig {
-        EventObserverConfig {
-            bitcoin_rpc_proxy_enabled: true,
-            registered_chainhooks: ChainhookStore::new(),
-            predicates_config: PredicatesConfig {
+        self.event_observer_config_builder()
+            .finish()
+            .expect("networks of the config are supported by the observer")
+    }
+
+    /// Returns a builder of the config of the event observer, for the settings of the observer
+    /// not coming from this config, e.g. its predicates, to be set.
+    pub fn event_observer_config_builder(&self) -> EventObserverConfigBuilder {
+        let mut builder = EventObserverConfigBuilder::new();
+        builder
+            .bitcoin_rpc_proxy_enabled(true)
+            .predicates_config(PredicatesConfig {
                 payload_http_request_timeout_ms: self.predicates.payload_http_request_timeout_ms,
-            },
-            bitcoind_rpc_username: self.network.bitcoind_rpc_username.clone(),
-            bitcoind_rpc_password: self.network.bitcoind_rpc_password.clone(),
-            bitcoind_rpc_url: self.network.bitcoind_rpc_url.clone(),
-            bitcoin_block_signaling: self.network.bitcoin_block_signaling.clone(),
-            display_stacks_ingestion_logs: false,
-            bitcoin_network: self.network.bitcoin_network.clone(),
-            stacks_network: self.network.stacks_network.clone(),
-            prometheus_monitoring_port: self.monitoring.prometheus_monitoring_port,
+                http_tls: self.predicates.http_tls.clone(),
+                circuit_breaker: self.predicates.circuit_breaker.clone(),
+                rate_limit: self.predicates.rate_limit.clone(),
+                shutdown_drain_timeout_ms: self.predicates.shutdown_drain_timeout_ms,
+            })
+            .bitcoin_rpc(
+                &self.network.bitcoind_rpc_url,
+                &self.network.bitcoind_rpc_username,
+                &self.network.bitcoind_rpc_password,
+            )
+            .bitcoind_rpc_fallbacks(self.network.bitcoind_rpc_fallbacks.clone())
+            .bitcoin_block_signaling(self.network.bitcoin_block_signaling.clone())
+            .display_stacks_ingestion_logs(false)
+            .bitcoin_network(self.network.bitcoin_network.as_str())
+            .stacks_network(self.network.stacks_network.as_str())
+            .malformed_stacks_transactions(self.network.malformed_stacks_transactions);
+        if let Some(ref url) = self.network.esplora_api_url {
+            builder.esplora_api_url(url);
+        }
+        if let Some(port) = self.monitoring.prometheus_monitoring_port {
+            builder.prometheus_monitoring_port(port);
+        }
+        if let Some(depth) = self.network.bitcoin_confirmation_depth {
+            builder.bitcoin_confirmation_depth(depth);
+        }
+        if let Some(depth) = self.network.stacks_confirmation_depth {
+            builder.stacks_confirmation_depth(depth);
+        }
+        if let Some(size) = self.limits.max_number_of_cached_bitcoin_blocks {
+            builder.bitcoin_block_cache_size(size);
+        }
+        if let Some(size) = self.limits.max_number_of_cached_bitcoin_prevouts {
+            builder.bitcoin_prevout_cache_size(size);
         }
+        if let Some(ref network_parameters) = self.network.network_parameters {
+            builder.network_parameters(network_parameters.clone());
+        }
+        builder
     }
 
-    pub fn from_config_file(config_file: ConfigFile) -> Result<Config, String> {
+    pub fn from_config_file(mut config_file: ConfigFile) -> Result<Config, String> {
         let (stacks_network, bitcoin_network) = match config_file.network.mode.as_str() {
             "devnet" => (StacksNetwork::Devnet, BitcoinNetwork::Regtest),
             "testnet" => (StacksNetwork::Testnet, BitcoinNetwork::Testnet),
             "mainnet" => (StacksNetwork::Mainnet, BitcoinNetwork::Mainnet),
+            "signet" => (StacksNetwork::Testnet, BitcoinNetwork::Signet),
             _ => return Err("network.mode not supported".to_string()),
         };
+        let default_confirmation_depth = match bitcoin_network {
+            BitcoinNetwork::Regtest | BitcoinNetwork::Signet => {
+                Some(TEST_NETWORK_CONFIRMATION_DEPTH)
+            }
+            BitcoinNetwork::Testnet | BitcoinNetwork::Mainnet => None,
+        };
+        for (key, depth) in [
+            (
+                "bitcoin_confirmation_depth",
+                config_file.network.bitcoin_confirmation_depth,
+            ),
+            (
+                "stacks_confirmation_depth",
+                config_file.network.stacks_confirmation_depth,
+            ),
+        ] {
+            if matches!(depth, Some(depth) if depth < 2) {
+                return Err(format!("network.{}: must be at least 2", key));
+            }
+        }
+        let stacks_retention = match (
+            config_file.storage.stacks_max_retained_blocks,
+            config_file.storage.stacks_max_retained_days,
+        ) {
+            (None, None) => None,
+            (Some(0), _) => {
+                return Err("storage.stacks_max_retained_blocks: must be at least 1".into())
+            }
+            (_, Some(0)) => {
+                return Err("storage.stacks_max_retained_days: must be at least 1".into())
+            }
+            (max_blocks, max_age_days) => Some(StacksRetentionConfig {
+                max_blocks,
+                max_age_days,
+            }),
+        };
+        if config_file.storage.block_cache_size_mb == Some(0) {
+            return Err("storage.block_cache_size_mb: must be at least 1".into());
+        }
+        if let Some(depth) = config_file.storage.stacks_verification_depth {
+            if depth == 0 || depth > MAX_STACKS_HEADERS_PER_REQUEST {
+                return Err(format!(
+                    "storage.stacks_verification_depth: must be between 1 and {}",
+                    MAX_STACKS_HEADERS_PER_REQUEST
+                ));
+            }
+        }
+        let additional_networks = config_file
+            .additional_networks
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(i, additional_network)| {
+                Config::from_additional_network_config_file(&config_file, additional_network)
+                    .map_err(|e| format!("additional_networks[{}]: {}", i, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
         let mut event_sources = vec![];
         for source in config_file.event_source.unwrap_or_default().iter_mut() {
@@ -153,6 +585,27 @@ impl Config {
                 continue;
             }
         }
+        let predicates_watch_dir = config_file
+            .predicates
+            .as_ref()
+            .and_then(|predicates| predicates.watch_dir.as_ref())
+            .map(PathBuf::from);
+        let replication = config_file
+            .replication
+            .as_ref()
+            .map(Config::parse_replication)
+            .transpose()?;
+        let logs = config_file
+            .logs
+            .as_ref()
+            .map(Config::parse_logs)
+            .transpose()?
+            .unwrap_or_default();
+        let error_reporting = config_file
+            .error_reporting
+            .as_ref()
+            .map(Config::parse_error_reporting)
+            .transpose()?;
         let prometheus_monitoring_port = if let Some(monitoring) = config_file.monitoring {
             monitoring.prometheus_monitoring_port
         } else {
@@ -163,26 +616,58 @@ impl Config {
             StacksNetwork::Devnet => PoxConfig::testnet_default(),
             _ => PoxConfig::default(),
         };
+        // The canonical parameters are only overridden when the config file asks for it.
+        let network_parameters = match (
+            &config_file.pox_config,
+            &config_file.network.stacks_magic_bytes,
+        ) {
+            (None, None) => None,
+            (pox_config, stacks_magic_bytes) => {
+                let mut network_parameters = NetworkParameters::canonical(&bitcoin_network);
+                if let Some(pox_config) = pox_config {
+                    network_parameters.pox_config =
+                        merge_pox_config(pox_config, network_parameters.pox_config);
+                }
+                if let Some(stacks_magic_bytes) = stacks_magic_bytes {
+                    network_parameters.stacks_magic_bytes = stacks_magic_bytes
+                        .as_bytes()
+                        .try_into()
+                        .map_err(|_| "network.stacks_magic_bytes: must be 2 characters long")?;
+                }
+                Some(network_parameters)
+            }
+        };
+        let stacks_db_backend = match config_file.storage.stacks_db_backend.as_deref() {
+            None | Some("rocksdb") => StacksDbBackend::RocksDb,
+            Some("sqlite") => StacksDbBackend::Sqlite,
+            Some(backend) => {
+                return Err(format!(
+                    "storage.stacks_db_backend: unsupported backend {}, expected rocksdb or sqlite",
+                    backend
+                ))
+            }
+        };
+        let allow_plaintext_payloads = config_file
+            .storage
+            .allow_plaintext_payloads
+            .unwrap_or(false);
+        if let Some(ref key_env) = config_file.storage.encryption_key_env {
+            enable_payload_encryption(key_env, allow_plaintext_payloads)
+                .map_err(|e| format!("storage.encryption_key_env: {}", e))?;
+        }
         let config = Config {
             storage: StorageConfig {
                 working_dir: config_file.storage.working_dir.unwrap_or("cache".into()),
+                stacks_retention,
+                block_cache_size_mb: config_file.storage.block_cache_size_mb,
+                stacks_verification_depth: config_file.storage.stacks_verification_depth,
+                stacks_db_backend,
+                encryption_key_env: config_file.storage.encryption_key_env,
+                allow_plaintext_payloads,
             },
             pox_config: match config_file.pox_config {
                 None => default_pox_config,
-                Some(pox_config) => PoxConfig {
-                    first_burnchain_block_height: pox_config
-                        .first_burnchain_block_height
-                        .unwrap_or(default_pox_config.first_burnchain_block_height),
-                    prepare_phase_len: pox_config
-                        .prepare_phase_len
-                        .unwrap_or(default_pox_config.prepare_phase_len),
-                    reward_phase_len: pox_config
-                        .reward_phase_len
-                        .unwrap_or(default_pox_config.reward_phase_len),
-                    rewarded_addresses_per_block: pox_config
-                        .rewarded_addresses_per_block
-                        .unwrap_or(default_pox_config.rewarded_addresses_per_block),
-                },
+                Some(ref pox_config) => merge_pox_config(pox_config, default_pox_config),
             },
             http_api: match config_file.http_api {
                 None => PredicatesApi::Off,
@@ -194,17 +679,44 @@ impl Config {
                         database_uri: http_api
                             .database_uri
                             .unwrap_or(DEFAULT_REDIS_URI.to_string()),
+                        api_keys: parse_api_keys(http_api.api_keys)?,
+                        lifecycle_webhook_url: http_api.lifecycle_webhook_url,
+                        tls: parse_tls(http_api.tls, "http_api.tls")?,
+                        cors: http_api.cors.map(parse_cors).transpose()?,
+                        rate_limit: http_api.rate_limit.map(parse_api_rate_limit).transpose()?,
+                        audit_log_max_entries: http_api.audit_log_max_entries,
                     }),
                 },
             },
             predicates: match config_file.predicates {
                 None => PredicatesConfig {
                     payload_http_request_timeout_ms: None,
+                    http_tls: None,
+                    circuit_breaker: None,
+                    rate_limit: None,
+                    shutdown_drain_timeout_ms: None,
                 },
-                Some(predicates) => PredicatesConfig {
-                    payload_http_request_timeout_ms: predicates.payload_http_request_timeout_ms,
-                },
+                Some(predicates) => {
+                    if let Some(ref circuit_breaker) = predicates.circuit_breaker {
+                        circuit_breaker
+                            .validate()
+                            .map_err(|e| format!("predicates.circuit_breaker: {}", e))?;
+                    }
+                    if let Some(ref rate_limit) = predicates.rate_limit {
+                        rate_limit
+                            .validate()
+                            .map_err(|e| format!("predicates.rate_limit: {}", e))?;
+                    }
+                    PredicatesConfig {
+                        payload_http_request_timeout_ms: predicates.payload_http_request_timeout_ms,
+                        http_tls: predicates.http_tls,
+                        circuit_breaker: predicates.circuit_breaker,
+                        rate_limit: predicates.rate_limit,
+                        shutdown_drain_timeout_ms: predicates.shutdown_drain_timeout_ms,
+                    }
+                }
             },
+            predicates_watch_dir,
             event_sources,
             limits: LimitsConfig {
                 max_number_of_stacks_predicates: config_file
@@ -235,30 +747,279 @@ impl Config {
                     .limits
                     .max_caching_memory_size_mb
                     .unwrap_or(2048),
+                max_number_of_pending_observer_commands: config_file
+                    .limits
+                    .max_number_of_pending_observer_commands
+                    .unwrap_or(DEFAULT_OBSERVER_COMMANDS_CAPACITY),
+                max_number_of_pending_observer_events: config_file
+                    .limits
+                    .max_number_of_pending_observer_events
+                    .unwrap_or(DEFAULT_OBSERVER_EVENTS_CAPACITY),
+                observer_events_full_policy: config_file
+                    .limits
+                    .observer_events_full_policy
+                    .unwrap_or_default(),
+                max_number_of_pending_scan_operations: config_file
+                    .limits
+                    .max_number_of_pending_scan_operations
+                    .unwrap_or(DEFAULT_SCAN_OPERATIONS_CAPACITY),
+                scan_operations_full_policy: config_file
+                    .limits
+                    .scan_operations_full_policy
+                    .unwrap_or_default(),
+                max_number_of_cached_bitcoin_blocks: config_file
+                    .limits
+                    .max_number_of_cached_bitcoin_blocks,
+                max_number_of_prefetched_bitcoin_blocks: config_file
+                    .limits
+                    .max_number_of_prefetched_bitcoin_blocks
+                    .unwrap_or(BITCOIN_SCAN_PREFETCH_DEPTH),
+                max_number_of_cached_bitcoin_prevouts: config_file
+                    .limits
+                    .max_number_of_cached_bitcoin_prevouts,
             },
             network: IndexerConfig {
                 bitcoind_rpc_url: config_file.network.bitcoind_rpc_url.to_string(),
                 bitcoind_rpc_username: config_file.network.bitcoind_rpc_username.to_string(),
                 bitcoind_rpc_password: config_file.network.bitcoind_rpc_password.to_string(),
-                bitcoin_block_signaling: match config_file.network.bitcoind_zmq_url {
-                    Some(ref zmq_url) => BitcoinBlockSignaling::ZeroMQ(zmq_url.clone()),
-                    None => BitcoinBlockSignaling::Stacks(StacksNodeConfig::default_localhost(
-                        config_file
+                bitcoind_rpc_fallbacks: config_file
+                    .network
+                    .bitcoind_rpc_fallbacks
+                    .clone()
+                    .unwrap_or_default(),
+                esplora_api_url: config_file.network.esplora_api_url.clone(),
+                bitcoin_block_signaling: match (
+                    &config_file.network.bitcoind_zmq_url,
+                    config_file.network.bitcoind_polling_interval_ms,
+                ) {
+                    (Some(ref zmq_url), _) => BitcoinBlockSignaling::ZeroMQ(zmq_url.clone()),
+                    (None, Some(interval_ms)) => BitcoinBlockSignaling::Polling(interval_ms),
+                    (None, None) => {
+                        let mut stacks_node_config = StacksNodeConfig::default_localhost(
+                            config_file
+                                .network
+                                .stacks_events_ingestion_port
+                                .unwrap_or(DEFAULT_INGESTION_PORT),
+                        );
+                        stacks_node_config.ingestion_auth_token = config_file
                             .network
-                            .stacks_events_ingestion_port
-                            .unwrap_or(DEFAULT_INGESTION_PORT),
-                    )),
+                            .stacks_events_ingestion_auth_token
+                            .clone();
+                        stacks_node_config.ingestion_tls = parse_tls(
+                            config_file.network.stacks_events_ingestion_tls.clone(),
+                            "network.stacks_events_ingestion_tls",
+                        )?;
+                        BitcoinBlockSignaling::Stacks(stacks_node_config)
+                    }
                 },
                 stacks_network,
                 bitcoin_network,
+                bitcoin_confirmation_depth: config_file
+                    .network
+                    .bitcoin_confirmation_depth
+                    .or(default_confirmation_depth),
+                stacks_confirmation_depth: config_file
+                    .network
+                    .stacks_confirmation_depth
+                    .or(default_confirmation_depth),
+                network_parameters,
+                malformed_stacks_transactions: config_file
+                    .network
+                    .malformed_stacks_transactions
+                    .unwrap_or_default(),
             },
             monitoring: MonitoringConfig {
                 prometheus_monitoring_port,
             },
+            additional_networks,
+            replication,
+            logs,
+            error_reporting,
         };
+        config.validate_additional_networks()?;
+        config.validate_replication()?;
         Ok(config)
     }
 
+    fn parse_replication(replication: &ReplicationConfigFile) -> Result<ReplicationConfig, String> {
+        if replication.instance_index >= replication.instances {
+            return Err(
+                "replication.instance_index: must be lower than replication.instances".to_string(),
+            );
+        }
+        Ok(ReplicationConfig {
+            instances: replication.instances,
+            instance_index: replication.instance_index,
+        })
+    }
+
+    fn parse_logs(logs: &LogsConfigFile) -> Result<LogsConfig, String> {
+        let level = logs
+            .level
+            .as_deref()
+            .map(parse_log_level)
+            .transpose()
+            .map_err(|e| format!("logs.level: {}", e))?;
+        let format = logs
+            .format
+            .as_deref()
+            .map(parse_log_format)
+            .transpose()
+            .map_err(|e| format!("logs.format: {}", e))?;
+        let mut module_levels = BTreeMap::new();
+        for (module, level) in logs.modules.iter().flatten() {
+            let level =
+                parse_log_level(level).map_err(|e| format!("logs.modules.{}: {}", module, e))?;
+            module_levels.insert(module.clone(), level);
+        }
+        Ok(LogsConfig {
+            level,
+            format,
+            module_levels,
+        })
+    }
+
+    fn parse_error_reporting(
+        error_reporting: &ErrorReportingConfigFile,
+    ) -> Result<ErrorReportingConfig, String> {
+        let dsn = error_reporting.dsn.trim();
+        // <scheme>://<public_key>@<host>/<project_id>
+        let valid = dsn
+            .split_once("://")
+            .and_then(|(_, rest)| rest.split_once('@'))
+            .and_then(|(public_key, rest)| {
+                rest.rsplit_once('/')
+                    .map(|(host, project_id)| (public_key, host, project_id))
+            })
+            .map_or(false, |(public_key, host, project_id)| {
+                !public_key.is_empty() && !host.is_empty() && !project_id.is_empty()
+            });
+        if !valid {
+            return Err(format!(
+                "error_reporting.dsn: invalid dsn {}, expected <scheme>://<public_key>@<host>/<project_id>",
+                dsn
+            ));
+        }
+        Ok(ErrorReportingConfig {
+            dsn: dsn.to_string(),
+            environment: error_reporting.environment.clone(),
+        })
+    }
+
+    /// Instances share their predicates and chain events through the predicates db. Replicas
+    /// don't write to the working directory, and only evaluate the predicates registered through
+    /// the primary and the chain events it distributes.
+    fn validate_replication(&self) -> Result<(), String> {
+        if self.replication.is_none() {
+            return Ok(());
+        }
+        if self.http_api == PredicatesApi::Off {
+            return Err("replication: http_api must be enabled".to_string());
+        }
+        if !self.is_replica() {
+            return Ok(());
+        }
+        if self.predicates_watch_dir.is_some() {
+            return Err(
+                "replication: predicates.watch_dir is not supported by replicas".to_string(),
+            );
+        }
+        if self.storage.block_cache_size_mb.is_some() {
+            return Err(
+                "replication: storage.block_cache_size_mb is not supported by replicas".to_string(),
+            );
+        }
+        if !matches!(
+            self.network.bitcoin_block_signaling,
+            BitcoinBlockSignaling::Stacks(_)
+        ) {
+            return Err(
+                "replication: replicas receive their Bitcoin blocks from the primary, not bitcoind"
+                    .to_string(),
+            );
+        }
+        if self.limits.max_number_of_cached_bitcoin_blocks.is_some() {
+            return Err(
+                "replication: limits.max_number_of_cached_bitcoin_blocks is not supported by replicas"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    fn from_additional_network_config_file(
+        config_file: &ConfigFile,
+        additional_network: AdditionalNetworkConfigFile,
+    ) -> Result<Config, String> {
+        Config::from_config_file(ConfigFile {
+            storage: additional_network.storage,
+            pox_config: additional_network.pox_config,
+            http_api: additional_network.http_api,
+            predicates: config_file.predicates.clone(),
+            event_source: None,
+            limits: config_file.limits.clone(),
+            network: additional_network.network,
+            monitoring: additional_network.monitoring,
+            additional_networks: None,
+            replication: config_file.replication.clone(),
+            logs: config_file.logs.clone(),
+            error_reporting: config_file.error_reporting.clone(),
+        })
+    }
+
+    /// Networks observed by the same service must not share their storage nor any port.
+    fn validate_additional_networks(&self) -> Result<(), String> {
+        if self.additional_networks.is_empty() {
+            return Ok(());
+        }
+        let mut networks = vec![];
+        let mut working_dirs = vec![];
+        let mut ports = vec![];
+        for config in std::iter::once(self).chain(self.additional_networks.iter()) {
+            if networks.contains(&config.network.bitcoin_network) {
+                return Err(format!(
+                    "additional_networks: network {:?} is observed more than once",
+                    config.network.bitcoin_network
+                ));
+            }
+            networks.push(config.network.bitcoin_network.clone());
+            if working_dirs.contains(&config.storage.working_dir) {
+                return Err(format!(
+                    "additional_networks: storage.working_dir {} is used by more than one network",
+                    config.storage.working_dir
+                ));
+            }
+            working_dirs.push(config.storage.working_dir.clone());
+            let ingestion_port = match config.network.bitcoin_block_signaling {
+                BitcoinBlockSignaling::Stacks(ref stacks_node_config) => {
+                    Some(stacks_node_config.ingestion_port)
+                }
+                _ => None,
+            };
+            let http_port = match config.http_api {
+                PredicatesApi::On(ref api_config) => Some(api_config.http_port),
+                PredicatesApi::Off => None,
+            };
+            for port in [
+                ingestion_port,
+                http_port,
+                config.monitoring.prometheus_monitoring_port,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                if ports.contains(&port) {
+                    return Err(format!(
+                        "additional_networks: port {} is used by more than one network",
+                        port
+                    ));
+                }
+                ports.push(port);
+            }
+        }
+        Ok(())
+    }
+
     pub fn is_initial_ingestion_required(&self) -> bool {
         for source in self.event_sources.iter() {
             match source {
@@ -298,12 +1059,40 @@ impl Config {
         Err("could not find expected local tsv source")?
     }
 
+    /// Whether this instance attaches read-only to the storage of a primary instance.
+    pub fn is_replica(&self) -> bool {
+        self.replication
+            .as_ref()
+            .map_or(false, |replication| replication.is_replica())
+    }
+
+    /// Whether this instance distributes the chain events it ingests to replicas.
+    pub fn is_coordinator(&self) -> bool {
+        self.replication.as_ref().map_or(false, |replication| {
+            !replication.is_replica() && replication.instances > 1
+        })
+    }
+
+    /// Whether the predicate `uuid` is evaluated by this instance rather than another one
+    /// sharing its predicates db.
+    pub fn is_predicate_assigned(&self, uuid: &str) -> bool {
+        self.replication
+            .as_ref()
+            .map_or(true, |replication| replication.is_predicate_assigned(uuid))
+    }
+
     pub fn expected_cache_path(&self) -> PathBuf {
         let mut destination_path = PathBuf::new();
         destination_path.push(&self.storage.working_dir);
         destination_path
     }
 
+    pub fn expected_bitcoin_block_cache_path(&self) -> PathBuf {
+        let mut destination_path = self.expected_cache_path();
+        destination_path.push("bitcoin_block_cache");
+        destination_path
+    }
+
     fn expected_remote_stacks_tsv_base_url(&self) -> Result<&String, String> {
         for source in self.event_sources.iter() {
             if let EventSourceConfig::StacksTsvUrl(config) = source {
@@ -366,12 +1155,23 @@ impl Config {
         Config {
             storage: StorageConfig {
                 working_dir: default_cache_path(),
+                stacks_retention: None,
+                block_cache_size_mb: None,
+                stacks_verification_depth: None,
+                stacks_db_backend: StacksDbBackend::RocksDb,
+                encryption_key_env: None,
+                allow_plaintext_payloads: false,
             },
             pox_config: PoxConfig::devnet_default(),
             http_api: PredicatesApi::Off,
             predicates: PredicatesConfig {
                 payload_http_request_timeout_ms: None,
+                http_tls: None,
+                circuit_breaker: None,
+                rate_limit: None,
+                shutdown_drain_timeout_ms: None,
             },
+            predicates_watch_dir: None,
             event_sources: vec![],
             limits: LimitsConfig {
                 max_number_of_bitcoin_predicates: BITCOIN_MAX_PREDICATE_REGISTRATION,
@@ -381,33 +1181,73 @@ impl Config {
                 max_number_of_processing_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_number_of_networking_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_caching_memory_size_mb: 2048,
+                max_number_of_pending_observer_commands: DEFAULT_OBSERVER_COMMANDS_CAPACITY,
+                max_number_of_pending_observer_events: DEFAULT_OBSERVER_EVENTS_CAPACITY,
+                observer_events_full_policy: FullChannelPolicy::Block,
+                max_number_of_pending_scan_operations: DEFAULT_SCAN_OPERATIONS_CAPACITY,
+                scan_operations_full_policy: FullChannelPolicy::Block,
+                max_number_of_cached_bitcoin_blocks: None,
+                max_number_of_prefetched_bitcoin_blocks: BITCOIN_SCAN_PREFETCH_DEPTH,
+                max_number_of_cached_bitcoin_prevouts: None,
             },
             network: IndexerConfig {
                 bitcoind_rpc_url: "http://0.0.0.0:18443".into(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rpc_fallbacks: vec![],
+                esplora_api_url: None,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     StacksNodeConfig::default_localhost(DEFAULT_INGESTION_PORT),
                 ),
                 stacks_network: StacksNetwork::Devnet,
                 bitcoin_network: BitcoinNetwork::Regtest,
+                bitcoin_confirmation_depth: Some(TEST_NETWORK_CONFIRMATION_DEPTH),
+                stacks_confirmation_depth: Some(TEST_NETWORK_CONFIRMATION_DEPTH),
+                network_parameters: None,
+                malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
             },
             monitoring: MonitoringConfig {
                 prometheus_monitoring_port: None,
             },
+            additional_networks: vec![],
+            replication: None,
+            logs: LogsConfig::default(),
+            error_reporting: None,
         }
     }
 
+    /// Preset for integration testing against a signet node, using the canonical
+    /// [NetworkParameters] of signet and fast confirmations.
+    pub fn signet_default() -> Config {
+        let mut config = Config::devnet_default();
+        config.pox_config = PoxConfig::default();
+        config.network.bitcoind_rpc_url = "http://0.0.0.0:38332".into();
+        config.network.stacks_network = StacksNetwork::Testnet;
+        config.network.bitcoin_network = BitcoinNetwork::Signet;
+        config
+    }
+
     pub fn testnet_default() -> Config {
         Config {
             storage: StorageConfig {
                 working_dir: default_cache_path(),
+                stacks_retention: None,
+                block_cache_size_mb: None,
+                stacks_verification_depth: None,
+                stacks_db_backend: StacksDbBackend::RocksDb,
+                encryption_key_env: None,
+                allow_plaintext_payloads: false,
             },
             pox_config: PoxConfig::testnet_default(),
             http_api: PredicatesApi::Off,
             predicates: PredicatesConfig {
                 payload_http_request_timeout_ms: None,
+                http_tls: None,
+                circuit_breaker: None,
+                rate_limit: None,
+                shutdown_drain_timeout_ms: None,
             },
+            predicates_watch_dir: None,
             event_sources: vec![EventSourceConfig::StacksTsvUrl(UrlConfig {
                 file_url: DEFAULT_TESTNET_STACKS_TSV_ARCHIVE.into(),
             })],
@@ -419,20 +1259,38 @@ impl Config {
                 max_number_of_processing_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_number_of_networking_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_caching_memory_size_mb: 2048,
+                max_number_of_pending_observer_commands: DEFAULT_OBSERVER_COMMANDS_CAPACITY,
+                max_number_of_pending_observer_events: DEFAULT_OBSERVER_EVENTS_CAPACITY,
+                observer_events_full_policy: FullChannelPolicy::Block,
+                max_number_of_pending_scan_operations: DEFAULT_SCAN_OPERATIONS_CAPACITY,
+                scan_operations_full_policy: FullChannelPolicy::Block,
+                max_number_of_cached_bitcoin_blocks: None,
+                max_number_of_prefetched_bitcoin_blocks: BITCOIN_SCAN_PREFETCH_DEPTH,
+                max_number_of_cached_bitcoin_prevouts: None,
             },
             network: IndexerConfig {
                 bitcoind_rpc_url: "http://0.0.0.0:18332".into(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rpc_fallbacks: vec![],
+                esplora_api_url: None,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     StacksNodeConfig::default_localhost(DEFAULT_INGESTION_PORT),
                 ),
                 stacks_network: StacksNetwork::Testnet,
                 bitcoin_network: BitcoinNetwork::Testnet,
+                bitcoin_confirmation_depth: None,
+                stacks_confirmation_depth: None,
+                network_parameters: None,
+                malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
             },
             monitoring: MonitoringConfig {
                 prometheus_monitoring_port: None,
             },
+            additional_networks: vec![],
+            replication: None,
+            logs: LogsConfig::default(),
+            error_reporting: None,
         }
     }
 
@@ -440,12 +1298,23 @@ impl Config {
         Config {
             storage: StorageConfig {
                 working_dir: default_cache_path(),
+                stacks_retention: None,
+                block_cache_size_mb: None,
+                stacks_verification_depth: None,
+                stacks_db_backend: StacksDbBackend::RocksDb,
+                encryption_key_env: None,
+                allow_plaintext_payloads: false,
             },
             pox_config: PoxConfig::mainnet_default(),
             http_api: PredicatesApi::Off,
             predicates: PredicatesConfig {
                 payload_http_request_timeout_ms: None,
+                http_tls: None,
+                circuit_breaker: None,
+                rate_limit: None,
+                shutdown_drain_timeout_ms: None,
             },
+            predicates_watch_dir: None,
             event_sources: vec![EventSourceConfig::StacksTsvUrl(UrlConfig {
                 file_url: DEFAULT_MAINNET_STACKS_TSV_ARCHIVE.into(),
             })],
@@ -457,22 +1326,150 @@ impl Config {
                 max_number_of_processing_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_number_of_networking_threads: 1.max(num_cpus::get().saturating_sub(1)),
                 max_caching_memory_size_mb: 2048,
+                max_number_of_pending_observer_commands: DEFAULT_OBSERVER_COMMANDS_CAPACITY,
+                max_number_of_pending_observer_events: DEFAULT_OBSERVER_EVENTS_CAPACITY,
+                observer_events_full_policy: FullChannelPolicy::Block,
+                max_number_of_pending_scan_operations: DEFAULT_SCAN_OPERATIONS_CAPACITY,
+                scan_operations_full_policy: FullChannelPolicy::Block,
+                max_number_of_cached_bitcoin_blocks: None,
+                max_number_of_prefetched_bitcoin_blocks: BITCOIN_SCAN_PREFETCH_DEPTH,
+                max_number_of_cached_bitcoin_prevouts: None,
             },
             network: IndexerConfig {
                 bitcoind_rpc_url: "http://0.0.0.0:8332".into(),
                 bitcoind_rpc_username: "devnet".into(),
                 bitcoind_rpc_password: "devnet".into(),
+                bitcoind_rpc_fallbacks: vec![],
+                esplora_api_url: None,
                 bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
                     StacksNodeConfig::default_localhost(DEFAULT_INGESTION_PORT),
                 ),
                 stacks_network: StacksNetwork::Mainnet,
                 bitcoin_network: BitcoinNetwork::Mainnet,
+                bitcoin_confirmation_depth: None,
+                stacks_confirmation_depth: None,
+                network_parameters: None,
+                malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
             },
             monitoring: MonitoringConfig {
                 prometheus_monitoring_port: None,
             },
+            additional_networks: vec![],
+            replication: None,
+            logs: LogsConfig::default(),
+            error_reporting: None,
+        }
+    }
+}
+
+fn merge_pox_config(pox_config: &PoxConfigFile, default_pox_config: PoxConfig) -> PoxConfig {
+    PoxConfig {
+        first_burnchain_block_height: pox_config
+            .first_burnchain_block_height
+            .unwrap_or(default_pox_config.first_burnchain_block_height),
+        prepare_phase_len: pox_config
+            .prepare_phase_len
+            .unwrap_or(default_pox_config.prepare_phase_len),
+        reward_phase_len: pox_config
+            .reward_phase_len
+            .unwrap_or(default_pox_config.reward_phase_len),
+        rewarded_addresses_per_block: pox_config
+            .rewarded_addresses_per_block
+            .unwrap_or(default_pox_config.rewarded_addresses_per_block),
+    }
+}
+
+fn parse_api_keys(api_keys: Option<Vec<ApiKeyConfigFile>>) -> Result<Vec<ApiKeyConfig>, String> {
+    let mut parsed_api_keys = vec![];
+    for (i, api_key) in api_keys.unwrap_or_default().into_iter().enumerate() {
+        if api_key.key.is_empty() {
+            return Err(format!("http_api.api_keys[{}].key: must not be empty", i));
         }
+        let scope = match api_key.scope.as_deref() {
+            None | Some("manage") => ApiKeyScope::Manage,
+            Some("read") => ApiKeyScope::Read,
+            Some(scope) => {
+                return Err(format!(
+                    "http_api.api_keys[{}].scope: unsupported scope {}, expected read or manage",
+                    i, scope
+                ))
+            }
+        };
+        if let Some(ref namespace) = api_key.namespace {
+            validate_namespace(namespace)
+                .map_err(|e| format!("http_api.api_keys[{}].namespace: {}", i, e))?;
+        }
+        parsed_api_keys.push(ApiKeyConfig {
+            key: api_key.key,
+            scope,
+            namespace: api_key.namespace,
+        });
+    }
+    Ok(parsed_api_keys)
+}
+
+fn parse_api_rate_limit(rate_limit: ApiRateLimitConfigFile) -> Result<ApiRateLimitConfig, String> {
+    if let Some(0) = rate_limit.requests_per_minute_per_ip {
+        return Err(
+            "http_api.rate_limit.requests_per_minute_per_ip: must be greater than 0".into(),
+        );
+    }
+    if let Some(0) = rate_limit.requests_per_minute_per_key {
+        return Err(
+            "http_api.rate_limit.requests_per_minute_per_key: must be greater than 0".into(),
+        );
+    }
+    Ok(ApiRateLimitConfig {
+        requests_per_minute_per_ip: rate_limit.requests_per_minute_per_ip,
+        requests_per_minute_per_key: rate_limit.requests_per_minute_per_key,
+    })
+}
+
+fn parse_cors(cors: CorsConfigFile) -> Result<CorsConfig, String> {
+    if cors.allowed_origins.is_empty() {
+        return Err("http_api.cors.allowed_origins: must not be empty".into());
     }
+    let allowed_methods = match cors.allowed_methods {
+        None => DEFAULT_CORS_ALLOWED_METHODS.map(String::from).to_vec(),
+        Some(methods) => methods
+            .into_iter()
+            .map(|method| match method.to_uppercase().as_str() {
+                method @ ("GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS") => {
+                    Ok(method.to_string())
+                }
+                _ => Err(format!(
+                    "http_api.cors.allowed_methods: unsupported method {}",
+                    method
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(CorsConfig {
+        allowed_origins: cors.allowed_origins,
+        allowed_headers: cors
+            .allowed_headers
+            .unwrap_or_else(|| DEFAULT_CORS_ALLOWED_HEADERS.map(String::from).to_vec()),
+        allowed_methods,
+    })
+}
+
+fn parse_tls(tls: Option<ServerTlsConfig>, field: &str) -> Result<Option<ServerTlsConfig>, String> {
+    if let Some(ref tls) = tls {
+        tls.validate().map_err(|e| format!("{}: {}", field, e))?;
+    }
+    Ok(tls)
+}
+
+/// Compares the `Authorization` header, optionally using the `Bearer` scheme, to the api key in
+/// constant time.
+fn is_api_key_valid(value: &str, key: &str) -> bool {
+    let value = value.strip_prefix("Bearer ").unwrap_or(value);
+    value.len() == key.len()
+        && value
+            .bytes()
+            .zip(key.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
 }
 
 pub fn default_cache_path() -> String {
@@ -0,0 +1,203 @@
+use std::fs;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use chainhook_sdk::indexer::bitcoin::{build_http_client, source::bitcoin_block_source};
+use chainhook_sdk::types::BitcoinBlockSignaling;
+use chainhook_sdk::utils::Context;
+use serde_json::Value as JsonValue;
+
+use crate::service::open_readwrite_predicates_db_conn;
+use crate::service::predicates_db::get_predicates_db_name;
+
+use super::{Config, EventSourceConfig, PredicatesApi};
+
+/// Time given to the Stacks node and bitcoind's ZeroMQ socket to answer.
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigCheckStatus {
+    Ok,
+    Failed,
+}
+
+/// Outcome of one of the checks of `config validate`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigCheck {
+    pub name: String,
+    pub status: ConfigCheckStatus,
+    pub detail: String,
+}
+
+impl ConfigCheck {
+    pub fn new(name: impl Into<String>, result: Result<String, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (ConfigCheckStatus::Ok, detail),
+            Err(detail) => (ConfigCheckStatus::Failed, detail),
+        };
+        ConfigCheck {
+            name: name.into(),
+            status,
+            detail,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == ConfigCheckStatus::Ok
+    }
+}
+
+/// Checks that the nodes and databases the config relies on are reachable, that the ports it
+/// listens on are available and that its paths can be used, so that misconfigurations surface
+/// before `service start`.
+pub async fn check_config(config: &Config, ctx: &Context) -> Vec<ConfigCheck> {
+    let mut checks = vec![];
+
+    let event_observer_config = config.get_event_observer_config();
+    let bitcoin_config = event_observer_config.get_bitcoin_config();
+    let http_client = build_http_client();
+    let block_source = bitcoin_block_source(&http_client, &bitcoin_config, ctx);
+    let bitcoin_source_name = match config.network.esplora_api_url {
+        Some(ref api_url) => format!("esplora {}", api_url),
+        None => format!("bitcoind {}", config.network.bitcoind_rpc_url),
+    };
+    checks.push(ConfigCheck::new(
+        bitcoin_source_name,
+        block_source
+            .retrieve_chain_tip_height()
+            .await
+            .map(|chain_tip| format!("reachable, chain tip at #{}", chain_tip)),
+    ));
+
+    match config.network.bitcoin_block_signaling {
+        BitcoinBlockSignaling::Stacks(ref stacks_node_config) => {
+            checks.push(ConfigCheck::new(
+                format!("stacks node {}", stacks_node_config.rpc_url),
+                check_stacks_node(&stacks_node_config.rpc_url).await,
+            ));
+            checks.push(ConfigCheck::new(
+                format!("ingestion port {}", stacks_node_config.ingestion_port),
+                check_port_available(stacks_node_config.ingestion_port),
+            ));
+        }
+        BitcoinBlockSignaling::ZeroMQ(ref zmq_url) => {
+            checks.push(ConfigCheck::new(
+                format!("bitcoind zmq {}", zmq_url),
+                check_zmq_socket(zmq_url),
+            ));
+        }
+        BitcoinBlockSignaling::Polling(_) => {}
+    }
+
+    if let PredicatesApi::On(ref api_config) = config.http_api {
+        checks.push(ConfigCheck::new(
+            format!("predicates api port {}", api_config.http_port),
+            check_port_available(api_config.http_port),
+        ));
+        checks.push(ConfigCheck::new(
+            format!(
+                "predicates db {}",
+                get_predicates_db_name(&api_config.database_uri)
+            ),
+            open_readwrite_predicates_db_conn(api_config)
+                .and_then(|mut predicates_db_conn| predicates_db_conn.ping())
+                .map(|_| "reachable".to_string()),
+        ));
+    }
+    if let Some(port) = config.monitoring.prometheus_monitoring_port {
+        checks.push(ConfigCheck::new(
+            format!("prometheus port {}", port),
+            check_port_available(port),
+        ));
+    }
+
+    let working_dir = config.expected_cache_path();
+    checks.push(ConfigCheck::new(
+        format!("working_dir {}", working_dir.display()),
+        if config.is_replica() {
+            check_dir_readable(&working_dir)
+        } else {
+            check_dir_writable(&working_dir)
+        },
+    ));
+    if let Some(ref watch_dir) = config.predicates_watch_dir {
+        checks.push(ConfigCheck::new(
+            format!("predicates watch_dir {}", watch_dir.display()),
+            check_dir_readable(watch_dir),
+        ));
+    }
+    for event_source in config.event_sources.iter() {
+        if let EventSourceConfig::StacksTsvPath(ref path_config) = event_source {
+            checks.push(ConfigCheck::new(
+                format!("tsv_file_path {}", path_config.file_path.display()),
+                fs::File::open(&path_config.file_path)
+                    .map(|_| "readable".to_string())
+                    .map_err(|e| format!("unable to read file: {}", e)),
+            ));
+        }
+    }
+
+    checks
+}
+
+async fn check_stacks_node(rpc_url: &str) -> Result<String, String> {
+    let url = format!("{}/v2/info", rpc_url.trim_end_matches('/'));
+    let response = reqwest::Client::new()
+        .get(&url)
+        .timeout(CONNECTIVITY_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("unable to reach {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "{} answered with status {}",
+            url,
+            response.status()
+        ));
+    }
+    let info: JsonValue = response
+        .json()
+        .await
+        .map_err(|e| format!("unable to parse the answer of {}: {}", url, e))?;
+    match info["stacks_tip_height"].as_u64() {
+        Some(tip) => Ok(format!("reachable, chain tip at #{}", tip)),
+        None => Ok("reachable".into()),
+    }
+}
+
+fn check_zmq_socket(zmq_url: &str) -> Result<String, String> {
+    let address = zmq_url
+        .strip_prefix("tcp://")
+        .ok_or(format!("only tcp:// urls can be checked"))?;
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(|e| format!("unable to resolve {}: {}", address, e))?
+        .next()
+        .ok_or(format!("unable to resolve {}", address))?;
+    TcpStream::connect_timeout(&socket_address, CONNECTIVITY_TIMEOUT)
+        .map(|_| "reachable".to_string())
+        .map_err(|e| format!("unable to connect: {}", e))
+}
+
+pub fn check_port_available(port: u16) -> Result<String, String> {
+    TcpListener::bind(("0.0.0.0", port))
+        .map(|_| "available".to_string())
+        .map_err(|e| format!("unable to bind: {}", e))
+}
+
+fn check_dir_readable(dir: &Path) -> Result<String, String> {
+    fs::read_dir(dir)
+        .map(|_| "readable".to_string())
+        .map_err(|e| format!("unable to read directory: {}", e))
+}
+
+/// Creates the directory if missing, then writes and removes a file in it.
+pub fn check_dir_writable(dir: &Path) -> Result<String, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("unable to create directory: {}", e))?;
+    let probe_path = dir.join(".chainhook-write-check");
+    fs::write(&probe_path, b"").map_err(|e| format!("unable to write in directory: {}", e))?;
+    let _ = fs::remove_file(&probe_path);
+    Ok("writable".into())
+}
@@ -1,15 +1,25 @@
 use std::path::PathBuf;
 
 use crate::config::{
-    file::{NetworkConfigMode, PredicatesApiConfigFile},
-    PredicatesApi, PredicatesApiConfig,
+    file::{
+        AdditionalNetworkConfigFile, ApiKeyConfigFile, CorsConfigFile, NetworkConfigMode,
+        PredicatesApiConfigFile, ReplicationConfigFile, StorageConfigFile,
+    },
+    ApiKeyScope, PredicatesApi, PredicatesApiConfig, ReplicationConfig,
 };
 
 use super::{
-    file::MonitoringConfigFile, generator::generate_config, Config, ConfigFile, EventSourceConfig,
-    PathConfig,
+    file::MonitoringConfigFile,
+    generator::generate_config,
+    report::{check_dir_writable, check_port_available},
+    Config, ConfigFile, EventSourceConfig, LogFormat, LogsConfig, PathConfig,
+    TEST_NETWORK_CONFIRMATION_DEPTH,
 };
-use chainhook_sdk::types::{BitcoinNetwork, StacksNetwork};
+use chainhook_sdk::chainhooks::types::NetworkParameters;
+use chainhook_sdk::indexer::MalformedStacksTransactionPolicy;
+use chainhook_sdk::types::{BitcoinBlockSignaling, BitcoinNetwork, ServerTlsConfig, StacksNetwork};
+use hiro_system_kit::slog::Level;
+use std::collections::BTreeMap;
 use test_case::test_case;
 
 const LOCAL_DIR: &str = env!("CARGO_MANIFEST_DIR");
@@ -40,6 +50,12 @@ fn config_from_file_allows_setting_disabled_fields() {
         database_uri: Some(String::new()),
         display_logs: Some(false),
         disabled: Some(false),
+        api_keys: None,
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
     });
     generated_config_file.monitoring = Some(MonitoringConfigFile {
         prometheus_monitoring_port: Some(20457),
@@ -52,6 +68,274 @@ fn config_from_file_allows_setting_disabled_fields() {
     );
 }
 
+#[test]
+fn config_from_file_allows_polling_bitcoind() {
+    let generated_config_str = generate_config(&BitcoinNetwork::Regtest);
+    let mut generated_config_file: ConfigFile = toml::from_str(&generated_config_str).unwrap();
+    generated_config_file.network.stacks_node_rpc_url = None;
+    generated_config_file.network.bitcoind_polling_interval_ms = Some(2000);
+    let generated_config = Config::from_config_file(generated_config_file).unwrap();
+    assert_eq!(
+        generated_config.network.bitcoin_block_signaling,
+        BitcoinBlockSignaling::Polling(2000)
+    );
+}
+
+#[test]
+fn config_from_file_allows_additional_networks() {
+    let generated_config_str = generate_config(&BitcoinNetwork::Mainnet);
+    let mut generated_config_file: ConfigFile = toml::from_str(&generated_config_str).unwrap();
+    let mut testnet_network = generated_config_file.network.clone();
+    testnet_network.mode = NetworkConfigMode::Testnet;
+    testnet_network.stacks_events_ingestion_port = Some(30455);
+    generated_config_file.additional_networks = Some(vec![AdditionalNetworkConfigFile {
+        storage: StorageConfigFile {
+            working_dir: Some("testnet_cache".into()),
+            stacks_max_retained_blocks: None,
+            stacks_max_retained_days: None,
+            block_cache_size_mb: None,
+            stacks_verification_depth: None,
+            stacks_db_backend: None,
+            encryption_key_env: None,
+            allow_plaintext_payloads: None,
+        },
+        pox_config: None,
+        http_api: None,
+        network: testnet_network,
+        monitoring: None,
+    }]);
+    let config = Config::from_config_file(generated_config_file.clone()).unwrap();
+    assert_eq!(config.network.bitcoin_network, BitcoinNetwork::Mainnet);
+    assert_eq!(config.additional_networks.len(), 1);
+    let testnet_config = &config.additional_networks[0];
+    assert_eq!(
+        testnet_config.network.bitcoin_network,
+        BitcoinNetwork::Testnet
+    );
+    assert_eq!(
+        testnet_config.network.stacks_network,
+        StacksNetwork::Testnet
+    );
+    assert_eq!(testnet_config.storage.working_dir, "testnet_cache");
+    assert_eq!(testnet_config.limits, config.limits);
+    assert!(testnet_config.additional_networks.is_empty());
+
+    // Networks can't share their storage.
+    let mut overlapping_config_file = generated_config_file.clone();
+    overlapping_config_file
+        .additional_networks
+        .as_mut()
+        .unwrap()[0]
+        .storage
+        .working_dir = generated_config_file.storage.working_dir.clone();
+    Config::from_config_file(overlapping_config_file)
+        .expect_err("Did not reject overlapping working dirs as expected.");
+
+    // Nor observe the same network twice.
+    let mut overlapping_config_file = generated_config_file;
+    overlapping_config_file
+        .additional_networks
+        .as_mut()
+        .unwrap()[0]
+        .network
+        .mode = NetworkConfigMode::Mainnet;
+    Config::from_config_file(overlapping_config_file)
+        .expect_err("Did not reject duplicated network as expected.");
+}
+
+#[test]
+fn config_from_file_parses_stacks_retention() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert_eq!(config.storage.stacks_retention, None);
+
+    config_file.storage.stacks_max_retained_days = Some(30);
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    let retention = config.storage.stacks_retention.unwrap();
+    assert_eq!(retention.max_blocks, None);
+    assert_eq!(
+        retention.retained_since(100 * 24 * 3600),
+        Some(70 * 24 * 3600)
+    );
+
+    config_file.storage.stacks_max_retained_blocks = Some(0);
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject empty retention window as expected.");
+}
+
+#[test]
+fn config_from_file_bounds_stacks_verification_depth() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    config_file.storage.stacks_verification_depth = Some(100);
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert_eq!(config.storage.stacks_verification_depth, Some(100));
+
+    config_file.storage.stacks_verification_depth = Some(5000);
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject verification depth beyond the node's limit as expected.");
+}
+
+#[test]
+fn config_from_file_parses_replication() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    config_file.replication = Some(ReplicationConfigFile {
+        instances: 3,
+        instance_index: 1,
+    });
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject replication without the predicates db as expected.");
+
+    config_file.http_api = Some(PredicatesApiConfigFile {
+        http_port: None,
+        database_uri: None,
+        display_logs: None,
+        disabled: None,
+        api_keys: None,
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
+    });
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert!(config.is_replica());
+
+    config_file.network.bitcoind_zmq_url = Some("tcp://0.0.0.0:18543".into());
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject bitcoind signaling on a replica as expected.");
+
+    config_file.network.bitcoind_zmq_url = None;
+    config_file.storage.block_cache_size_mb = Some(256);
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject a block cache on a replica as expected.");
+
+    config_file.replication.as_mut().unwrap().instance_index = 0;
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert!(!config.is_replica());
+    assert!(config.is_coordinator());
+
+    config_file.replication.as_mut().unwrap().instance_index = 3;
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject an instance index out of bounds as expected.");
+}
+
+#[test]
+fn config_from_file_parses_logs() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    config_file.logs = Some(
+        toml::from_str(
+            r#"
+            level = "warn"
+            format = "json"
+            modules = { "chainhook_sdk::observer" = "error", "chainhook::service" = "debug" }
+            "#,
+        )
+        .unwrap(),
+    );
+    let logs = Config::from_config_file(config_file.clone()).unwrap().logs;
+    assert_eq!(logs.format, Some(LogFormat::Json));
+    assert_eq!(logs.level_of("chainhook::scan::bitcoin"), Level::Warning);
+    assert_eq!(logs.level_of("chainhook::service::runloops"), Level::Debug);
+    assert_eq!(logs.level_of("chainhook_sdk::observer"), Level::Error);
+    assert_eq!(logs.level_of("chainhook_sdk::observerx"), Level::Warning);
+
+    let flags = LogsConfig {
+        level: Some(Level::Info),
+        format: None,
+        module_levels: BTreeMap::from([("chainhook_sdk::observer".into(), Level::Critical)]),
+    };
+    let logs = logs.overridden_by(&flags);
+    assert_eq!(logs.format, Some(LogFormat::Json));
+    assert_eq!(logs.level_of("chainhook::scan::bitcoin"), Level::Info);
+    assert_eq!(logs.level_of("chainhook::service"), Level::Debug);
+    assert_eq!(logs.level_of("chainhook_sdk::observer"), Level::Critical);
+
+    config_file.logs.as_mut().unwrap().level = Some("verbose".into());
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject unsupported log level as expected.");
+}
+
+#[test]
+fn config_from_file_parses_error_reporting() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    assert_eq!(
+        Config::from_config_file(config_file.clone())
+            .unwrap()
+            .error_reporting,
+        None
+    );
+    config_file.error_reporting = Some(
+        toml::from_str(
+            r#"
+            dsn = "https://abcd1234@sentry.example.com/42"
+            "#,
+        )
+        .unwrap(),
+    );
+    let error_reporting = Config::from_config_file(config_file.clone())
+        .unwrap()
+        .error_reporting
+        .unwrap();
+    assert_eq!(
+        error_reporting.dsn,
+        "https://abcd1234@sentry.example.com/42"
+    );
+    assert_eq!(error_reporting.environment, None);
+
+    config_file.error_reporting.as_mut().unwrap().dsn = "https://sentry.example.com/42".into();
+    Config::from_config_file(config_file).expect_err("Did not reject dsn without a public key.");
+}
+
+#[test]
+fn replication_assigns_each_predicate_to_one_instance() {
+    let instances = (0..3)
+        .map(|instance_index| ReplicationConfig {
+            instances: 3,
+            instance_index,
+        })
+        .collect::<Vec<_>>();
+    let mut assigned_per_instance = vec![0; instances.len()];
+    for i in 0..300 {
+        let uuid = format!("4ecc-4ecc-435b-9948-d5eeca1c{:04}", i);
+        let assigned = instances
+            .iter()
+            .filter(|instance| instance.is_predicate_assigned(&uuid))
+            .map(|instance| instance.instance_index as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(assigned.len(), 1);
+        assigned_per_instance[assigned[0]] += 1;
+    }
+    assert!(assigned_per_instance.iter().all(|count| *count > 0));
+}
+
+#[test]
+fn replication_only_moves_predicates_to_added_instances() {
+    let three_instances = ReplicationConfig {
+        instances: 3,
+        instance_index: 0,
+    };
+    let four_instances = ReplicationConfig {
+        instances: 4,
+        instance_index: 0,
+    };
+    let mut moved = 0;
+    for i in 0..300 {
+        let uuid = format!("4ecc-4ecc-435b-9948-d5eeca1c{:04}", i);
+        let before = three_instances.get_predicate_instance(&uuid);
+        let after = four_instances.get_predicate_instance(&uuid);
+        if before != after {
+            assert_eq!(after, 3);
+            moved += 1;
+        }
+    }
+    assert!(moved > 0 && moved < 150);
+}
+
 #[test]
 fn config_from_file_allows_local_tsv_file() {
     let path = format!(
@@ -72,6 +356,54 @@ fn parse_config_from_file_rejects_config_with_unsupported_mode() {
         .expect_err("Did not reject unsupported network mode as expected.");
 }
 
+#[test]
+fn config_from_file_supports_signet() {
+    let path = format!(
+        "{}/src/config/tests/fixtures/signet_chainhook.toml",
+        LOCAL_DIR
+    );
+    let config = Config::from_file_path(&path).unwrap();
+    assert_eq!(config.network.bitcoin_network, BitcoinNetwork::Signet);
+    assert_eq!(config.network.stacks_network, StacksNetwork::Testnet);
+    assert_eq!(
+        config.network.get_bitcoin_confirmation_depth(),
+        TEST_NETWORK_CONFIRMATION_DEPTH
+    );
+    let network_parameters = config.network.get_network_parameters();
+    assert_eq!(network_parameters.stacks_magic_bytes, *b"id");
+    assert_eq!(
+        network_parameters.pox_config,
+        NetworkParameters::canonical(&BitcoinNetwork::Signet).pox_config
+    );
+
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Signet)).unwrap();
+    config_file.network.stacks_magic_bytes = Some("idx".into());
+    Config::from_config_file(config_file).expect_err("Did not reject invalid magic bytes.");
+}
+
+#[test]
+fn config_from_file_parses_malformed_stacks_transactions_policy() {
+    let generated_config = generate_config(&BitcoinNetwork::Regtest);
+    let config_file: ConfigFile = toml::from_str(&generated_config).unwrap();
+    let config = Config::from_config_file(config_file).unwrap();
+    assert_eq!(
+        config.network.malformed_stacks_transactions,
+        MalformedStacksTransactionPolicy::Skip
+    );
+
+    let config_file: ConfigFile = toml::from_str(&generated_config.replace(
+        "[network]\n",
+        "[network]\nmalformed_stacks_transactions = \"fail_block\"\n",
+    ))
+    .unwrap();
+    let config = Config::from_config_file(config_file).unwrap();
+    assert_eq!(
+        config.network.malformed_stacks_transactions,
+        MalformedStacksTransactionPolicy::FailBlock
+    );
+}
+
 #[test]
 fn is_http_api_enabled_handles_both_modes() {
     let mut config = Config::default(true, false, false, &None).unwrap();
@@ -80,10 +412,193 @@ fn is_http_api_enabled_handles_both_modes() {
         http_port: 0,
         database_uri: String::new(),
         display_logs: false,
+        api_keys: vec![],
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
     });
     assert!(config.is_http_api_enabled());
 }
 
+#[test]
+fn config_from_file_parses_api_keys() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    config_file.http_api = Some(PredicatesApiConfigFile {
+        http_port: None,
+        database_uri: None,
+        display_logs: None,
+        disabled: None,
+        api_keys: Some(vec![
+            ApiKeyConfigFile {
+                key: "reader".into(),
+                scope: Some("read".into()),
+                namespace: None,
+            },
+            ApiKeyConfigFile {
+                key: "manager".into(),
+                scope: None,
+                namespace: Some("team-a".into()),
+            },
+        ]),
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
+    });
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    let api_config = config.expected_api_config();
+    assert_eq!(
+        api_config.granted_scope(Some("Bearer reader")),
+        Some(ApiKeyScope::Read)
+    );
+    assert_eq!(
+        api_config.granted_scope(Some("manager")),
+        Some(ApiKeyScope::Manage)
+    );
+    assert_eq!(api_config.granted_scope(Some("Bearer other")), None);
+    assert_eq!(api_config.granted_scope(None), None);
+    assert!(!ApiKeyScope::Read.includes(&ApiKeyScope::Manage));
+    assert_eq!(api_config.restricted_namespace(Some("reader")), None);
+    assert_eq!(
+        api_config.restricted_namespace(Some("Bearer manager")),
+        Some("team-a")
+    );
+    let masked_keys = api_config
+        .api_keys
+        .iter()
+        .map(|api_key| api_key.masked_key())
+        .collect::<Vec<_>>();
+    assert_eq!(masked_keys, vec!["****", "****"]);
+
+    config_file.http_api.as_mut().unwrap().api_keys = Some(vec![ApiKeyConfigFile {
+        key: "a-longer-api-key".into(),
+        scope: None,
+        namespace: None,
+    }]);
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert_eq!(
+        config.expected_api_config().api_keys[0].masked_key(),
+        "****-key"
+    );
+
+    config_file.http_api.as_mut().unwrap().api_keys = Some(vec![ApiKeyConfigFile {
+        key: "reader".into(),
+        scope: Some("write".into()),
+        namespace: None,
+    }]);
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject unknown scope as expected.");
+
+    config_file.http_api.as_mut().unwrap().api_keys = Some(vec![ApiKeyConfigFile {
+        key: "reader".into(),
+        scope: None,
+        namespace: Some("team:a".into()),
+    }]);
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject invalid namespace as expected.");
+}
+
+#[test]
+fn config_from_file_validates_tls_files() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    let cert_path = format!("{}/Cargo.toml", LOCAL_DIR);
+    let tls = ServerTlsConfig {
+        cert_path: cert_path.clone(),
+        key_path: cert_path.clone(),
+        client_ca_path: None,
+    };
+    config_file.network.stacks_events_ingestion_tls = Some(tls.clone());
+    config_file.http_api = Some(PredicatesApiConfigFile {
+        http_port: None,
+        database_uri: None,
+        display_logs: None,
+        disabled: None,
+        api_keys: None,
+        lifecycle_webhook_url: None,
+        tls: Some(tls.clone()),
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
+    });
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    assert_eq!(config.expected_api_config().tls, Some(tls.clone()));
+    match config.network.bitcoin_block_signaling {
+        BitcoinBlockSignaling::Stacks(ref stacks_node_config) => {
+            assert_eq!(stacks_node_config.ingestion_tls, Some(tls.clone()))
+        }
+        _ => panic!("Expected the Stacks node to be the block signaling source."),
+    }
+
+    config_file.http_api.as_mut().unwrap().tls = Some(ServerTlsConfig {
+        client_ca_path: Some(format!("{}/missing-ca.pem", LOCAL_DIR)),
+        ..tls.clone()
+    });
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject missing client CA as expected.");
+
+    config_file.http_api.as_mut().unwrap().tls = None;
+    config_file.network.stacks_events_ingestion_tls = Some(ServerTlsConfig {
+        key_path: format!("{}/missing-key.pem", LOCAL_DIR),
+        ..tls
+    });
+    Config::from_config_file(config_file).expect_err("Did not reject missing key as expected.");
+}
+
+fn cors_config_file(config_file: &mut ConfigFile) -> &mut CorsConfigFile {
+    let http_api = config_file.http_api.as_mut().unwrap();
+    http_api.cors.as_mut().unwrap()
+}
+
+#[test]
+fn config_from_file_parses_cors() {
+    let mut config_file: ConfigFile =
+        toml::from_str(&generate_config(&BitcoinNetwork::Mainnet)).unwrap();
+    config_file.http_api = Some(PredicatesApiConfigFile {
+        http_port: None,
+        database_uri: None,
+        display_logs: None,
+        disabled: None,
+        api_keys: None,
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: Some(CorsConfigFile {
+            allowed_origins: vec!["https://admin.example.com".into()],
+            allowed_headers: None,
+            allowed_methods: Some(vec!["get".into(), "POST".into()]),
+        }),
+        rate_limit: None,
+        audit_log_max_entries: None,
+    });
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    let cors = config.expected_api_config().cors.clone().unwrap();
+    assert_eq!(cors.allowed_methods, vec!["GET", "POST"]);
+    assert!(cors.allowed_headers.contains(&"Chainhook-Namespace".into()));
+    assert_eq!(
+        cors.allowed_origin("https://admin.example.com"),
+        Some("https://admin.example.com")
+    );
+    assert_eq!(cors.allowed_origin("https://other.example.com"), None);
+
+    cors_config_file(&mut config_file).allowed_origins = vec!["*".into()];
+    let config = Config::from_config_file(config_file.clone()).unwrap();
+    let cors = config.expected_api_config().cors.clone().unwrap();
+    assert_eq!(cors.allowed_origin("https://other.example.com"), Some("*"));
+
+    cors_config_file(&mut config_file).allowed_methods = Some(vec!["CONNECT".into()]);
+    Config::from_config_file(config_file.clone())
+        .expect_err("Did not reject unsupported method as expected.");
+
+    cors_config_file(&mut config_file).allowed_methods = None;
+    cors_config_file(&mut config_file).allowed_origins = vec![];
+    Config::from_config_file(config_file)
+        .expect_err("Did not reject empty allowed origins as expected.");
+}
+
 #[test]
 fn should_download_remote_stacks_tsv_handles_both_modes() {
     let url_src = EventSourceConfig::StacksTsvUrl(super::UrlConfig {
@@ -180,3 +695,21 @@ fn it_has_default_config_for_each_network() {
     assert_eq!(config.network.stacks_network, StacksNetwork::Devnet);
     Config::default(true, true, false, &None).expect_err("expected invalid combination error");
 }
+
+#[test]
+fn config_report_detects_ports_in_use() {
+    let listener = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    check_port_available(port).expect_err("expected port in use error");
+    drop(listener);
+    check_port_available(port).unwrap();
+}
+
+#[test]
+fn config_report_creates_missing_working_dir() {
+    let working_dir =
+        std::env::temp_dir().join(format!("chainhook-config-report-{}", std::process::id()));
+    check_dir_writable(&working_dir).unwrap();
+    assert!(working_dir.is_dir());
+    std::fs::remove_dir_all(&working_dir).unwrap();
+}
@@ -1,3 +1,4 @@
+#[cfg(any(test, not(feature = "axum-ingestion")))]
 #[macro_use]
 extern crate rocket;
 
@@ -12,6 +13,9 @@ extern crate serde_derive;
 
 extern crate serde;
 
+#[cfg(not(any(feature = "rocket", feature = "axum-ingestion")))]
+compile_error!("either the `rocket` or the `axum-ingestion` feature must be enabled");
+
 pub mod archive;
 pub mod cli;
 pub mod config;
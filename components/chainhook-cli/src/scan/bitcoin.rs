@@ -4,50 +4,78 @@ use crate::service::{
     open_readwrite_predicates_db_conn_or_panic, set_confirmed_expiration_status,
     set_predicate_scanning_status, set_unconfirmed_expiration_status, ScanningData,
 };
-use chainhook_sdk::bitcoincore_rpc::RpcApi;
-use chainhook_sdk::bitcoincore_rpc::{Auth, Client};
 use chainhook_sdk::chainhooks::bitcoin::{
     evaluate_bitcoin_chainhooks_on_chain_event, handle_bitcoin_hook_action,
     BitcoinChainhookOccurrence, BitcoinTriggerChainhook,
 };
 use chainhook_sdk::chainhooks::bitcoin::BitcoinChainhookInstance;
+use chainhook_sdk::chainhooks::types::{NetworkParameters, RetryPolicy};
 use chainhook_sdk::indexer;
 use chainhook_sdk::indexer::bitcoin::{
     build_http_client, download_and_parse_block_with_retry, retrieve_block_hash_with_retry,
+    source::bitcoin_block_source,
 };
-use chainhook_sdk::indexer::fork_scratch_pad::CONFIRMED_SEGMENT_MINIMUM_LENGTH;
-use chainhook_sdk::observer::{gather_proofs, EventObserverConfig};
+use chainhook_sdk::observer::{gather_proofs, BitcoinConfig, EventObserverConfig};
 use chainhook_sdk::types::{
-    BitcoinBlockData, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData, BlockIdentifier, Chain,
+    BitcoinBlockData, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData, BitcoinNetwork,
+    BlockIdentifier, Chain,
+};
+use chainhook_sdk::utils::{
+    aws::send_aws_message,
+    dead_letter::dead_letter_failed_delivery,
+    file_sink::append_to_file_sink,
+    kafka::send_kafka_message,
+    nats::send_nats_message,
+    object_storage::send_object_store_upload,
+    postgres::send_postgres_record,
+    rate_limit::action_rate_limiter,
+    retry::{send_http_occurrence_with_retry_policy, send_with_retry_policy},
+    Context,
+};
+use reqwest::Client as HttpClient;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::task::JoinHandle;
+
+use super::cache::BlockCache;
+use super::common::{
+    matching_transaction_positions, split_block_heights, transactions_at_positions, OrderedChunks,
+    PendingBatch, PredicateScanResult,
 };
-use chainhook_sdk::utils::{file_append, send_request, Context};
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
 
-use super::common::PredicateScanResult;
+/// Opens the cache of standardized blocks shared by Bitcoin scans, if enabled.
+pub fn open_bitcoin_block_cache(config: &Config, ctx: &Context) -> Option<BlockCache> {
+    let size_mb = config.storage.block_cache_size_mb?;
+    let cache_path = config.expected_bitcoin_block_cache_path();
+    match BlockCache::new(cache_path, size_mb.saturating_mul(1024 * 1024)) {
+        Ok(block_cache) => Some(block_cache),
+        Err(e) => {
+            warn!(
+                ctx.expect_logger(),
+                "Unable to open Bitcoin block cache, blocks won't be cached: {}", e
+            );
+            None
+        }
+    }
+}
 
 pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
     predicate_spec: &BitcoinChainhookInstance,
     unfinished_scan_data: Option<ScanningData>,
     config: &Config,
     kill_signal: Option<Arc<RwLock<bool>>>,
+    block_cache: Option<BlockCache>,
     ctx: &Context,
 ) -> Result<PredicateScanResult, String> {
     let predicate_uuid = &predicate_spec.uuid;
-    let auth = Auth::UserPass(
-        config.network.bitcoind_rpc_username.clone(),
-        config.network.bitcoind_rpc_password.clone(),
-    );
-
-    let bitcoin_rpc = match Client::new(&config.network.bitcoind_rpc_url, auth) {
-        Ok(con) => con,
-        Err(message) => {
-            return Err(format!("Bitcoin RPC error: {}", message));
-        }
-    };
+    let event_observer_config = config.get_event_observer_config();
+    let bitcoin_config = event_observer_config.get_bitcoin_config();
+    let network_parameters = event_observer_config.get_network_parameters();
+    let http_client = build_http_client();
+    let block_source = bitcoin_block_source(&http_client, &bitcoin_config, ctx);
 
-    let mut chain_tip = match bitcoin_rpc.get_blockchain_info() {
-        Ok(result) => result.blocks,
+    let mut chain_tip = match block_source.retrieve_chain_tip_height().await {
+        Ok(result) => result,
         Err(e) => {
             return Err(format!(
                 "unable to retrieve Bitcoin chain tip ({})",
@@ -85,9 +113,6 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
     let mut actions_triggered = 0;
     let mut err_count = 0;
 
-    let event_observer_config = config.get_event_observer_config();
-    let bitcoin_config = event_observer_config.get_bitcoin_config();
-
     let (mut number_of_blocks_to_scan, mut number_of_blocks_scanned, mut number_of_times_triggered) = {
         let number_of_blocks_to_scan = block_heights_to_scan.len() as u64;
         match &unfinished_scan_data {
@@ -100,8 +125,20 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
         }
     };
     let mut last_scanned_block_confirmations = 0;
-    let http_client = build_http_client();
 
+    let mut pending_batch = PendingBatch::new(
+        predicate_spec.max_batch_size,
+        predicate_spec.max_batch_wait_ms,
+    );
+    let mut prefetcher = BlockPrefetcher::new(
+        config.limits.max_number_of_prefetched_bitcoin_blocks,
+        &http_client,
+        &bitcoin_config,
+        event_observer_config.get_bitcoin_network(),
+        &network_parameters,
+        block_cache,
+        ctx,
+    );
     let mut loop_did_trigger = false;
     while let Some(current_block_height) = block_heights_to_scan.pop_front() {
         if let Some(kill_signal) = kill_signal.clone() {
@@ -135,8 +172,8 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
             let prev_chain_tip = chain_tip;
             // we've scanned up to the chain tip as of the start of this scan
             // so see if the chain has progressed since then
-            chain_tip = match bitcoin_rpc.get_blockchain_info() {
-                Ok(result) => result.blocks,
+            chain_tip = match block_source.retrieve_chain_tip_height().await {
+                Ok(result) => result,
                 Err(e) => {
                     return Err(format!(
                         "unable to retrieve Bitcoin chain tip ({})",
@@ -157,44 +194,43 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
 
         number_of_blocks_scanned += 1;
 
-        let block_hash = retrieve_block_hash_with_retry(
-            &http_client,
-            &current_block_height,
-            &bitcoin_config,
-            ctx,
-        )
-        .await?;
-        let block_breakdown =
-            download_and_parse_block_with_retry(&http_client, &block_hash, &bitcoin_config, ctx)
-                .await?;
-        last_scanned_block_confirmations = block_breakdown.confirmations;
-        let block = match indexer::bitcoin::standardize_bitcoin_block(
-            block_breakdown,
-            &event_observer_config.bitcoin_network,
-            ctx,
-        ) {
+        let prefetched_block = prefetcher
+            .next_block(current_block_height, &block_heights_to_scan, chain_tip)
+            .await?;
+        last_scanned_block_confirmations = prefetched_block.confirmations;
+        let block = match prefetched_block.block {
             Ok(data) => data,
-            Err((e, _)) => {
+            Err(e) => {
                 warn!(
                     ctx.expect_logger(),
-                    "Unable to standardize block #{} {}: {}", current_block_height, block_hash, e
+                    "Unable to standardize block #{} {}: {}",
+                    current_block_height,
+                    prefetched_block.block_hash,
+                    e
                 );
                 continue;
             }
         };
         last_block_scanned = block.block_identifier.clone();
 
-        let res = match process_block_with_predicates(
-            block,
-            &vec![&predicate_spec],
+        if let Some(triggering_block) = retain_block_if_triggering(block, predicate_spec, ctx) {
+            pending_batch.push(triggering_block);
+        }
+        if !pending_batch.is_ready() {
+            continue;
+        }
+
+        let res = match deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
             &event_observer_config,
             ctx,
         )
         .await
         {
-            Ok(actions) => {
+            Ok((blocks_triggering, actions)) => {
                 if actions > 0 {
-                    number_of_times_triggered += 1;
+                    number_of_times_triggered += blocks_triggering;
                     loop_did_trigger = true
                 }
                 actions_triggered += actions;
@@ -218,6 +254,31 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
         }
     }
 
+    // Deliver the matching blocks still waiting for their batch to fill up
+    if !pending_batch.is_empty() {
+        match deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &event_observer_config,
+            ctx,
+        )
+        .await
+        {
+            Ok((blocks_triggering, actions)) => {
+                if actions > 0 {
+                    number_of_times_triggered += blocks_triggering;
+                }
+                actions_triggered += actions;
+            }
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to deliver last batch of predicate {predicate_uuid}: {e}"
+                );
+            }
+        }
+    }
+
     info!(
         ctx.expect_logger(),
         "Predicate {predicate_uuid} scan completed. {number_of_blocks_scanned} blocks scanned, {actions_triggered} actions triggered."
@@ -250,7 +311,9 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
                 predicates_db_conn,
                 ctx,
             );
-            if last_scanned_block_confirmations >= CONFIRMED_SEGMENT_MINIMUM_LENGTH {
+            if last_scanned_block_confirmations
+                >= event_observer_config.get_bitcoin_confirmation_depth() as i32
+            {
                 set_confirmed_expiration_status(&predicate_spec.key(), predicates_db_conn, ctx);
             }
         }
@@ -260,15 +323,381 @@ pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate(
     Ok(PredicateScanResult::ChainTipReached)
 }
 
+/// Scans the blocks of a predicate in `jobs` chunks evaluated concurrently, the blocks triggering
+/// the predicate being delivered in order. Meant for one-off scans: the blocks mined after the
+/// start of the scan are not scanned, and the progress is not recorded in the predicates db.
+pub async fn scan_bitcoin_chainstate_via_rpc_using_predicate_in_chunks(
+    predicate_spec: &BitcoinChainhookInstance,
+    config: &Config,
+    jobs: usize,
+    block_cache: Option<BlockCache>,
+    ctx: &Context,
+) -> Result<PredicateScanResult, String> {
+    let predicate_uuid = &predicate_spec.uuid;
+    let event_observer_config = config.get_event_observer_config();
+    let bitcoin_config = event_observer_config.get_bitcoin_config();
+    let network_parameters = event_observer_config.get_network_parameters();
+    let http_client = build_http_client();
+    let block_source = bitcoin_block_source(&http_client, &bitcoin_config, ctx);
+
+    let chain_tip = match block_source.retrieve_chain_tip_height().await {
+        Ok(result) => result,
+        Err(e) => {
+            return Err(format!(
+                "unable to retrieve Bitcoin chain tip ({})",
+                e
+            ));
+        }
+    };
+    let block_heights_to_scan = match get_block_heights_to_scan(
+        &predicate_spec.blocks,
+        &predicate_spec.start_block,
+        &predicate_spec.end_block,
+        &chain_tip,
+        &None,
+    )? {
+        Some(h) => h,
+        None => return Ok(PredicateScanResult::ChainTipReached),
+    };
+    let number_of_blocks_to_scan = block_heights_to_scan.len();
+    let block_heights_to_scan: VecDeque<u64> = block_heights_to_scan
+        .into_iter()
+        .filter(|height| *height <= chain_tip)
+        .collect();
+    let reaches_last_block = block_heights_to_scan.len() == number_of_blocks_to_scan;
+
+    debug!(
+        ctx.expect_logger(),
+        "Starting predicate evaluation on Bitcoin blocks for predicate {predicate_uuid} with {jobs} jobs",
+    );
+
+    let mut chunks = OrderedChunks::default();
+    for chunk in split_block_heights(block_heights_to_scan, jobs) {
+        let mut prefetcher = BlockPrefetcher::new(
+            config.limits.max_number_of_prefetched_bitcoin_blocks,
+            &http_client,
+            &bitcoin_config,
+            event_observer_config.get_bitcoin_network(),
+            &network_parameters,
+            block_cache.clone(),
+            ctx,
+        );
+        let predicate_spec = predicate_spec.clone();
+        let ctx = ctx.clone();
+        chunks.spawn(chunk, move |mut heights, evaluations_tx| async move {
+            while let Some(height) = heights.pop_front() {
+                let evaluation = match prefetcher.next_block(height, &heights, chain_tip).await {
+                    Ok(PrefetchedBlock {
+                        block: Ok(block), ..
+                    }) => Ok(retain_block_if_triggering(block, &predicate_spec, &ctx)),
+                    Ok(PrefetchedBlock {
+                        block: Err(e),
+                        block_hash,
+                        ..
+                    }) => {
+                        warn!(
+                            ctx.expect_logger(),
+                            "Unable to standardize block #{} {}: {}", height, block_hash, e
+                        );
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                };
+                let failed = evaluation.is_err();
+                if evaluations_tx.send(evaluation).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+
+    let mut number_of_blocks_scanned = 0;
+    let mut actions_triggered = 0;
+    let mut err_count = 0;
+    let mut pending_batch = PendingBatch::new(
+        predicate_spec.max_batch_size,
+        predicate_spec.max_batch_wait_ms,
+    );
+    while let Some(evaluation) = chunks.next().await {
+        number_of_blocks_scanned += 1;
+        if let Some(triggering_block) = evaluation? {
+            pending_batch.push(triggering_block);
+        }
+        if !pending_batch.is_ready() {
+            continue;
+        }
+        match deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &event_observer_config,
+            ctx,
+        )
+        .await
+        {
+            Ok((_, actions)) => {
+                actions_triggered += actions;
+                err_count = 0;
+            }
+            Err(e) => {
+                err_count += 1;
+                if err_count >= 3 {
+                    return Err(format!(
+                        "Scan aborted (consecutive action errors >= 3): {}",
+                        e
+                    ));
+                }
+            }
+        }
+    }
+
+    // Deliver the matching blocks still waiting for their batch to fill up
+    if !pending_batch.is_empty() {
+        match deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &event_observer_config,
+            ctx,
+        )
+        .await
+        {
+            Ok((_, actions)) => actions_triggered += actions,
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to deliver last batch of predicate {predicate_uuid}: {e}"
+                );
+            }
+        }
+    }
+
+    info!(
+        ctx.expect_logger(),
+        "Predicate {predicate_uuid} scan completed. {number_of_blocks_scanned} blocks scanned, {actions_triggered} actions triggered."
+    );
+
+    if (predicate_spec.blocks.is_some() || predicate_spec.end_block.is_some()) && reaches_last_block
+    {
+        return Ok(PredicateScanResult::Expired);
+    }
+    Ok(PredicateScanResult::ChainTipReached)
+}
+
+/// A block downloaded and standardized ahead of its evaluation, or retrieved from the block cache.
+struct PrefetchedBlock {
+    block_hash: String,
+    confirmations: i32,
+    block: Result<BitcoinBlockData, String>,
+}
+
+/// Downloads and standardizes the blocks about to be scanned ahead of their evaluation, keeping
+/// up to `depth` blocks in flight while handing them off in scanning order.
+struct BlockPrefetcher {
+    depth: usize,
+    http_client: HttpClient,
+    bitcoin_config: BitcoinConfig,
+    bitcoin_network: BitcoinNetwork,
+    network_parameters: NetworkParameters,
+    block_cache: Option<BlockCache>,
+    ctx: Context,
+    in_flight: VecDeque<(u64, JoinHandle<Result<PrefetchedBlock, String>>)>,
+}
+
+impl BlockPrefetcher {
+    fn new(
+        depth: usize,
+        http_client: &HttpClient,
+        bitcoin_config: &BitcoinConfig,
+        bitcoin_network: &BitcoinNetwork,
+        network_parameters: &NetworkParameters,
+        block_cache: Option<BlockCache>,
+        ctx: &Context,
+    ) -> Self {
+        BlockPrefetcher {
+            depth: depth.max(1),
+            http_client: http_client.clone(),
+            bitcoin_config: bitcoin_config.clone(),
+            bitcoin_network: bitcoin_network.clone(),
+            network_parameters: network_parameters.clone(),
+            block_cache,
+            ctx: ctx.clone(),
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    fn download(
+        &self,
+        block_height: u64,
+        chain_tip: u64,
+    ) -> JoinHandle<Result<PrefetchedBlock, String>> {
+        let http_client = self.http_client.clone();
+        let bitcoin_config = self.bitcoin_config.clone();
+        let bitcoin_network = self.bitcoin_network.clone();
+        let network_parameters = self.network_parameters.clone();
+        let block_cache = self.block_cache.clone();
+        let ctx = self.ctx.clone();
+        tokio::spawn(async move {
+            let block_hash =
+                retrieve_block_hash_with_retry(&http_client, &block_height, &bitcoin_config, &ctx)
+                    .await?;
+            if let Some(block) = block_cache
+                .as_ref()
+                .and_then(|block_cache| block_cache.get::<BitcoinBlockData>(&block_hash))
+            {
+                return Ok(PrefetchedBlock {
+                    block_hash,
+                    confirmations: (chain_tip.saturating_sub(block_height) + 1) as i32,
+                    block: Ok(block),
+                });
+            }
+            let block_breakdown = download_and_parse_block_with_retry(
+                &http_client,
+                &block_hash,
+                &bitcoin_config,
+                &ctx,
+            )
+            .await?;
+            let confirmations = block_breakdown.confirmations;
+            let block = indexer::bitcoin::standardize_bitcoin_block(
+                block_breakdown,
+                &bitcoin_network,
+                &network_parameters,
+                &ctx,
+            )
+            .map_err(|(e, _)| e);
+            if let (Some(block_cache), Ok(block)) = (&block_cache, &block) {
+                block_cache.insert(&block_hash, block);
+            }
+            Ok(PrefetchedBlock {
+                block_hash,
+                confirmations,
+                block,
+            })
+        })
+    }
+
+    /// Waits for the block at `block_height`, after scheduling the download of the
+    /// `upcoming_heights` that are not beyond `chain_tip`.
+    async fn next_block(
+        &mut self,
+        block_height: u64,
+        upcoming_heights: &VecDeque<u64>,
+        chain_tip: u64,
+    ) -> Result<PrefetchedBlock, String> {
+        let download = match self.in_flight.pop_front() {
+            Some((height, download)) if height == block_height => download,
+            _ => {
+                self.cancel();
+                self.download(block_height, chain_tip)
+            }
+        };
+        // The blocks in flight are always the first upcoming heights, in order
+        let scheduled = self.in_flight.len();
+        for height in upcoming_heights
+            .iter()
+            .skip(scheduled)
+            .take_while(|height| **height <= chain_tip)
+            .take((self.depth - 1).saturating_sub(scheduled))
+        {
+            let upcoming_download = self.download(*height, chain_tip);
+            self.in_flight.push_back((*height, upcoming_download));
+        }
+        download
+            .await
+            .map_err(|e| format!("unable to download block #{} ({})", block_height, e))?
+    }
+
+    fn cancel(&mut self) {
+        for (_, download) in self.in_flight.drain(..) {
+            download.abort();
+        }
+    }
+}
+
+impl Drop for BlockPrefetcher {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Returns the block along with the positions of its transactions matching the predicate if it
+/// triggers the predicate, so that it can be added to a pending batch.
+fn retain_block_if_triggering(
+    block: BitcoinBlockData,
+    predicate_spec: &BitcoinChainhookInstance,
+    ctx: &Context,
+) -> Option<(BitcoinBlockData, Vec<usize>)> {
+    let chain_event =
+        BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+            new_blocks: vec![block],
+            confirmed_blocks: vec![],
+        });
+    let positions = {
+        let (predicates_triggered, _, _) =
+            evaluate_bitcoin_chainhooks_on_chain_event(&chain_event, &vec![predicate_spec], ctx);
+        let (hits, block) = predicates_triggered.first()?.apply.first()?;
+        matching_transaction_positions(&block.transactions, hits)
+    };
+    match chain_event {
+        BitcoinChainEvent::ChainUpdatedWithBlocks(mut data) => {
+            data.new_blocks.pop().map(|block| (block, positions))
+        }
+        BitcoinChainEvent::ChainUpdatedWithReorg(_) => None,
+    }
+}
+
+/// Delivers the blocks of a pending batch in a single occurrence, reusing the transactions
+/// matched when the blocks were added to the batch. Returns the number of blocks delivered
+/// along with the number of actions triggered.
+async fn deliver_pending_batch(
+    pending_batch: &mut PendingBatch<(BitcoinBlockData, Vec<usize>)>,
+    predicate_spec: &BitcoinChainhookInstance,
+    event_observer_config: &EventObserverConfig,
+    ctx: &Context,
+) -> Result<(u64, u32), String> {
+    let blocks = pending_batch.take();
+    let blocks_count = blocks.len() as u64;
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: predicate_spec,
+        apply: blocks
+            .iter()
+            .map(|(block, positions)| {
+                (
+                    transactions_at_positions(&block.transactions, positions),
+                    block,
+                )
+            })
+            .collect(),
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    let actions =
+        execute_predicates_action(trigger.split_by_action(), event_observer_config, ctx).await?;
+    Ok((blocks_count, actions))
+}
+
 pub async fn process_block_with_predicates(
     block: BitcoinBlockData,
     predicates: &Vec<&BitcoinChainhookInstance>,
     event_observer_config: &EventObserverConfig,
     ctx: &Context,
+) -> Result<u32, String> {
+    process_blocks_with_predicates(vec![block], predicates, event_observer_config, ctx).await
+}
+
+/// Evaluates the predicates on several blocks at once, so that each triggered predicate
+/// gets a single occurrence covering all of its matching blocks.
+pub async fn process_blocks_with_predicates(
+    blocks: Vec<BitcoinBlockData>,
+    predicates: &Vec<&BitcoinChainhookInstance>,
+    event_observer_config: &EventObserverConfig,
+    ctx: &Context,
 ) -> Result<u32, String> {
     let chain_event =
         BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
-            new_blocks: vec![block],
+            new_blocks: blocks,
             confirmed_blocks: vec![],
         });
 
@@ -285,29 +714,92 @@ pub async fn execute_predicates_action<'a>(
 ) -> Result<u32, String> {
     let mut actions_triggered = 0;
     let mut proofs = HashMap::new();
-    for trigger in hits.into_iter() {
+    for mut trigger in hits.into_iter() {
+        trigger.confirmation_depth = Some(config.get_bitcoin_confirmation_depth());
         if trigger.chainhook.include_proof {
             gather_proofs(&trigger, &mut proofs, config, ctx);
         }
-        let predicate_uuid = &trigger.chainhook.uuid;
+        let chainhook = trigger.chainhook;
+        let hook_action = trigger.get_action();
+        let retry_policy = hook_action
+            .retry_policy()
+            .cloned()
+            .unwrap_or(RetryPolicy::fixed(10, 3));
+        let delivery_id = trigger.get_delivery_id();
         match handle_bitcoin_hook_action(trigger, &proofs, &config) {
             Err(e) => {
                 warn!(
                     ctx.expect_logger(),
-                    "unable to handle action for predicate {}: {}", predicate_uuid, e
+                    "unable to handle action for predicate {}: {}", chainhook.uuid, e
                 );
             }
             Ok(action) => {
                 actions_triggered += 1;
-                match action {
-                    BitcoinChainhookOccurrence::Http(request, _) => {
-                        send_request(request, 10, 3, ctx).await?
+                let (res, data) = match action {
+                    BitcoinChainhookOccurrence::Http(request, data) => {
+                        let res = send_http_occurrence_with_retry_policy(
+                            request,
+                            hook_action.requires_ack(),
+                            &retry_policy,
+                            &action_rate_limiter(hook_action, config.get_predicates_config()),
+                            ctx,
+                        )
+                        .await
+                        .map(|_| ());
+                        (res, data)
                     }
-                    BitcoinChainhookOccurrence::File(path, bytes) => {
-                        file_append(path, bytes, ctx)?
+                    BitcoinChainhookOccurrence::File(hook, bytes, data) => {
+                        (append_to_file_sink(&hook, bytes, ctx), data)
                     }
-                    BitcoinChainhookOccurrence::Data(_payload) => {}
+                    BitcoinChainhookOccurrence::ObjectStore(upload, data) => {
+                        let res = send_with_retry_policy(&retry_policy, ctx, || {
+                            send_object_store_upload(&upload, 1, 0, ctx)
+                        })
+                        .await;
+                        (res, data)
+                    }
+                    BitcoinChainhookOccurrence::Kafka(message, data) => {
+                        let res = send_with_retry_policy(&retry_policy, ctx, || {
+                            send_kafka_message(&message, 1, 0, ctx)
+                        })
+                        .await;
+                        (res, data)
+                    }
+                    BitcoinChainhookOccurrence::Nats(message, data) => {
+                        let res = send_with_retry_policy(&retry_policy, ctx, || {
+                            send_nats_message(&message, 1, 0, ctx)
+                        })
+                        .await;
+                        (res, data)
+                    }
+                    BitcoinChainhookOccurrence::Aws(message, data) => {
+                        let res = send_with_retry_policy(&retry_policy, ctx, || {
+                            send_aws_message(&message, 1, 0, ctx)
+                        })
+                        .await;
+                        (res, data)
+                    }
+                    BitcoinChainhookOccurrence::Postgres(record, data) => {
+                        let res = send_with_retry_policy(&retry_policy, ctx, || {
+                            send_postgres_record(&record, 1, 0, ctx)
+                        })
+                        .await;
+                        (res, data)
+                    }
+                    BitcoinChainhookOccurrence::Data(data) => (Ok(()), data),
                 };
+                if let Err(e) = res {
+                    dead_letter_failed_delivery(
+                        hook_action.dead_letter(),
+                        &chainhook.uuid,
+                        &delivery_id,
+                        e,
+                        &data,
+                        config.get_predicates_config(),
+                        ctx,
+                    )
+                    .await?;
+                }
             }
         }
     }
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Standardized blocks kept on disk, keyed by block hash, so that scans evaluating the same
+/// blocks don't download and standardize them again. Once the blocks exceed `max_size` bytes,
+/// the least recently used ones are evicted. Clones share the same cache.
+#[derive(Clone)]
+pub struct BlockCache {
+    dir: PathBuf,
+    max_size: u64,
+    state: Arc<Mutex<BlockCacheState>>,
+}
+
+#[derive(Default)]
+struct BlockCacheState {
+    // Size and last use of each cached block
+    entries: HashMap<String, (u64, u64)>,
+    total_size: u64,
+    clock: u64,
+}
+
+impl BlockCache {
+    /// Opens the cache stored in `dir`, picking up the blocks cached by previous runs.
+    pub fn new(dir: PathBuf, max_size: u64) -> Result<BlockCache, String> {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("unable to create {}: {}", dir.display(), e))?;
+        let mut files = vec![];
+        for entry in fs::read_dir(&dir)
+            .map_err(|e| format!("unable to read {}: {}", dir.display(), e))?
+            .flatten()
+        {
+            let path = entry.path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                // Leftover of an interrupted write
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            let (Some(block_hash), Ok(metadata)) = (
+                path.file_stem().and_then(|stem| stem.to_str()),
+                entry.metadata(),
+            ) else {
+                continue;
+            };
+            files.push((
+                metadata.modified().ok(),
+                block_hash.to_string(),
+                metadata.len(),
+            ));
+        }
+        // Older files are the first to be evicted
+        files.sort();
+        let mut state = BlockCacheState::default();
+        for (_, block_hash, size) in files {
+            state.clock += 1;
+            state.total_size += size;
+            state.entries.insert(block_hash, (size, state.clock));
+        }
+        let cache = BlockCache {
+            dir,
+            max_size,
+            state: Arc::new(Mutex::new(state)),
+        };
+        if let Ok(mut state) = cache.state.lock() {
+            cache.evict(&mut state);
+        }
+        Ok(cache)
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, block_hash: &str) -> Option<T> {
+        let path = self.block_path(block_hash)?;
+        {
+            let mut state = self.state.lock().ok()?;
+            state.clock += 1;
+            let clock = state.clock;
+            let (_, last_used) = state.entries.get_mut(block_hash)?;
+            *last_used = clock;
+        }
+        let block = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        if block.is_none() {
+            self.remove(block_hash);
+        }
+        block
+    }
+
+    pub fn insert<T: Serialize>(&self, block_hash: &str, block: &T) {
+        let Some(path) = self.block_path(block_hash) else {
+            return;
+        };
+        let Ok(bytes) = serde_json::to_vec(block) else {
+            return;
+        };
+        let size = bytes.len() as u64;
+        if size > self.max_size {
+            return;
+        }
+        // Written aside first, so that readers never see a partially written block
+        let tmp_path = path.with_extension("tmp");
+        if fs::write(&tmp_path, &bytes).is_err() || fs::rename(&tmp_path, &path).is_err() {
+            let _ = fs::remove_file(&tmp_path);
+            return;
+        }
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.clock += 1;
+        let clock = state.clock;
+        if let Some((previous_size, _)) =
+            state.entries.insert(block_hash.to_string(), (size, clock))
+        {
+            state.total_size -= previous_size;
+        }
+        state.total_size += size;
+        self.evict(&mut state);
+    }
+
+    fn remove(&self, block_hash: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some((size, _)) = state.entries.remove(block_hash) {
+                state.total_size -= size;
+            }
+        }
+        if let Some(path) = self.block_path(block_hash) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn evict(&self, state: &mut BlockCacheState) {
+        while state.total_size > self.max_size {
+            let Some(block_hash) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(block_hash, _)| block_hash.clone())
+            else {
+                break;
+            };
+            if let Some((size, _)) = state.entries.remove(&block_hash) {
+                state.total_size -= size;
+            }
+            if let Some(path) = self.block_path(&block_hash) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    fn block_path(&self, block_hash: &str) -> Option<PathBuf> {
+        // Block hashes are only made of hex digits, with an optional `0x` prefix
+        if block_hash.is_empty() || !block_hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let mut path = self.dir.clone();
+        path.push(format!("{}.json", block_hash));
+        Some(path)
+    }
+}
@@ -1,6 +1,10 @@
 use crate::service::ScanningData;
 use chainhook_sdk::utils::{BlockHeights, BlockHeightsError};
 use std::collections::VecDeque;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
 
 pub fn get_block_heights_to_scan(
     blocks: &Option<Vec<u64>>,
@@ -71,3 +75,148 @@ pub enum PredicateScanResult {
     Expired,
     Deregistered,
 }
+
+/// Blocks matching a predicate, held back by a scan so that they can be delivered together.
+/// See the predicate's `max_batch_size` and `max_batch_wait_ms`.
+pub struct PendingBatch<T> {
+    blocks: Vec<T>,
+    max_batch_size: usize,
+    max_batch_wait: Option<Duration>,
+    oldest_block_received_at: Option<Instant>,
+}
+
+impl<T> PendingBatch<T> {
+    pub fn new(max_batch_size: Option<u64>, max_batch_wait_ms: Option<u64>) -> Self {
+        let max_batch_size = match (max_batch_size, max_batch_wait_ms) {
+            (Some(size), _) => size.max(1) as usize,
+            // Only bounded by time
+            (None, Some(_)) => usize::MAX,
+            // Batching disabled, blocks are delivered one at a time
+            (None, None) => 1,
+        };
+        PendingBatch {
+            blocks: vec![],
+            max_batch_size,
+            max_batch_wait: max_batch_wait_ms.map(Duration::from_millis),
+            oldest_block_received_at: None,
+        }
+    }
+
+    pub fn push(&mut self, block: T) {
+        if self.blocks.is_empty() {
+            self.oldest_block_received_at = Some(Instant::now());
+        }
+        self.blocks.push(block);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns true when the batch is full, or when its oldest block has waited long enough.
+    pub fn is_ready(&self) -> bool {
+        if self.blocks.is_empty() {
+            return false;
+        }
+        if self.blocks.len() >= self.max_batch_size {
+            return true;
+        }
+        match (self.max_batch_wait, self.oldest_block_received_at) {
+            (Some(max_wait), Some(received_at)) => received_at.elapsed() >= max_wait,
+            _ => false,
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<T> {
+        self.oldest_block_received_at = None;
+        std::mem::take(&mut self.blocks)
+    }
+}
+
+/// Positions, within its block, of the transactions matched by a predicate evaluation, so that
+/// a batched block can be delivered without evaluating the predicate again.
+pub fn matching_transaction_positions<T>(transactions: &[T], hits: &[&T]) -> Vec<usize> {
+    transactions
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| hits.iter().any(|hit| std::ptr::eq(*hit, *tx)))
+        .map(|(position, _)| position)
+        .collect()
+}
+
+/// Resolves the transactions recorded by [matching_transaction_positions].
+pub fn transactions_at_positions<'a, T>(transactions: &'a [T], positions: &[usize]) -> Vec<&'a T> {
+    positions
+        .iter()
+        .filter_map(|position| transactions.get(*position))
+        .collect()
+}
+
+/// Splits the heights to scan into up to `chunks` contiguous runs of similar lengths, so that
+/// they can be scanned concurrently.
+pub fn split_block_heights(block_heights: VecDeque<u64>, chunks: usize) -> Vec<VecDeque<u64>> {
+    let chunk_len = block_heights.len().div_ceil(chunks.max(1)).max(1);
+    Vec::from(block_heights)
+        .chunks(chunk_len)
+        .map(|chunk| chunk.iter().copied().collect())
+        .collect()
+}
+
+/// Evaluations a chunk can get ahead of the chunks preceding it.
+const CHUNK_EVALUATIONS_BUFFER: usize = 64;
+
+/// Evaluation of a block of a chunk: the block if it triggers the predicate.
+pub type ChunkEvaluation<T> = Result<Option<T>, String>;
+
+/// Merges the evaluations of chunks scanned concurrently in the order of their heights: the
+/// evaluations of a chunk are all returned before the ones of the next chunk. Chunks hand their
+/// evaluations through bounded channels, so a chunk getting ahead waits for the ones before it.
+pub struct OrderedChunks<T> {
+    evaluations: VecDeque<Receiver<ChunkEvaluation<T>>>,
+    scans: Vec<JoinHandle<()>>,
+}
+
+impl<T> Default for OrderedChunks<T> {
+    fn default() -> Self {
+        OrderedChunks {
+            evaluations: VecDeque::new(),
+            scans: vec![],
+        }
+    }
+}
+
+impl<T: Send + 'static> OrderedChunks<T> {
+    /// Starts scanning `chunk`, which follows the chunks already started. `scan_chunk` sends the
+    /// evaluation of each of the heights of the chunk, in order.
+    pub fn spawn<F, Fut>(&mut self, chunk: VecDeque<u64>, scan_chunk: F)
+    where
+        F: FnOnce(VecDeque<u64>, Sender<ChunkEvaluation<T>>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (evaluations_tx, evaluations_rx) = channel(CHUNK_EVALUATIONS_BUFFER);
+        self.evaluations.push_back(evaluations_rx);
+        self.scans
+            .push(tokio::spawn(scan_chunk(chunk, evaluations_tx)));
+    }
+
+    /// Returns the next evaluation, or `None` once all the chunks are scanned.
+    pub async fn next(&mut self) -> Option<ChunkEvaluation<T>> {
+        while let Some(evaluations_rx) = self.evaluations.front_mut() {
+            match evaluations_rx.recv().await {
+                Some(evaluation) => return Some(evaluation),
+                None => {
+                    self.evaluations.pop_front();
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T> Drop for OrderedChunks<T> {
+    fn drop(&mut self) {
+        for scan in self.scans.iter() {
+            scan.abort();
+        }
+    }
+}
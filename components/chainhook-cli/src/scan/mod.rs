@@ -1,4 +1,5 @@
 pub mod bitcoin;
+pub mod cache;
 pub mod common;
 pub mod stacks;
 
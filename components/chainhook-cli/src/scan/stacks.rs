@@ -2,11 +2,11 @@ use std::{
     collections::{HashMap, VecDeque},
     fs::File,
     io::{BufRead, BufReader},
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
 };
 
 use crate::{
-    archive::download_stacks_dataset_if_required,
+    archive::{download_stacks_dataset_if_required, get_stacks_tsv_fingerprint},
     config::{Config, PredicatesApi},
     scan::common::get_block_heights_to_scan,
     service::{
@@ -14,14 +14,19 @@ use crate::{
         set_predicate_scanning_status, set_unconfirmed_expiration_status, ScanningData,
     },
     storage::{
-        get_last_block_height_inserted, get_last_unconfirmed_block_height_inserted,
-        get_stacks_block_at_block_height, insert_entry_in_stacks_blocks, is_stacks_block_present,
+        get_last_block_height_inserted, get_last_consolidated_archive_block_height,
+        get_last_consolidated_archive_fingerprint, get_last_pruned_block_height,
+        get_last_unconfirmed_block_height_inserted, get_stacks_block_at_block_height,
+        insert_entry_in_stacks_blocks, is_stacks_block_present,
         open_readonly_stacks_db_conn_with_retry, open_readwrite_stacks_db_conn,
+        set_last_consolidated_archive_block_height, set_last_consolidated_archive_fingerprint,
+        StacksStorage,
     },
 };
-use chainhook_sdk::types::{BlockIdentifier, Chain};
+use chainhook_sdk::observer::EventObserverConfig;
+use chainhook_sdk::types::{BlockIdentifier, Chain, StacksBlockData, TransactionIdentifier};
 use chainhook_sdk::{
-    chainhooks::stacks::evaluate_stacks_chainhook_on_blocks,
+    chainhooks::stacks::{evaluate_stacks_chainhook_on_blocks, BitcoinAnchorContext},
     indexer::{self, stacks::standardize_stacks_serialized_block_header, Indexer},
     utils::Context,
 };
@@ -30,11 +35,25 @@ use chainhook_sdk::{
         handle_stacks_hook_action, StacksChainhookInstance, StacksChainhookOccurrence,
         StacksTriggerChainhook,
     },
-    utils::{file_append, send_request, AbstractStacksBlock},
+    chainhooks::types::RetryPolicy,
+    utils::{
+        aws::send_aws_message,
+        dead_letter::dead_letter_failed_delivery,
+        file_sink::append_to_file_sink,
+        kafka::send_kafka_message,
+        nats::send_nats_message,
+        object_storage::send_object_store_upload,
+        postgres::send_postgres_record,
+        rate_limit::action_rate_limiter,
+        retry::{send_http_occurrence_with_retry_policy, send_with_retry_policy},
+        AbstractStacksBlock,
+    },
 };
-use rocksdb::DB;
 
-use super::common::PredicateScanResult;
+use super::common::{
+    matching_transaction_positions, split_block_heights, transactions_at_positions, OrderedChunks,
+    PendingBatch, PredicateScanResult,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum DigestingCommand {
@@ -70,9 +89,13 @@ pub enum RecordKind {
 
 /// Calculates the canonical chain of Stacks blocks based on a Stacks node events TSV file. Returns a `VecDeque` structure of
 /// block hashes along with the line number where we can find the entire block message within the TSV.
+/// Retrieves the canonical fork of the blocks found in the TSV from `start_block`, along with
+/// the blocks below it that are missing from the db. Blocks up to `consolidated_block_height`
+/// are known to be in the db and are not checked.
 pub async fn get_canonical_fork_from_tsv(
     config: &mut Config,
     start_block: Option<u64>,
+    consolidated_block_height: Option<u64>,
     ctx: &Context,
 ) -> Result<VecDeque<(BlockIdentifier, BlockIdentifier, u64)>, String> {
     let seed_tsv_path = config.expected_local_stacks_tsv_file()?.clone();
@@ -107,7 +130,12 @@ pub async fn get_canonical_fork_from_tsv(
         })
         .map_err(|e| format!("unable to spawn thread: {e}"))?;
 
-    let stacks_db = open_readonly_stacks_db_conn_with_retry(&config.expected_cache_path(), 3, ctx)?;
+    let stacks_db = open_readonly_stacks_db_conn_with_retry(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+        3,
+        ctx,
+    )?;
     let canonical_fork = {
         let mut cursor = BlockIdentifier::default();
         let mut tsv_new_blocks = HashMap::new();
@@ -132,7 +160,8 @@ pub async fn get_canonical_fork_from_tsv(
             if start_block > block_identifier.index {
                 // don't insert blocks that are already in the db,
                 // but do fill any gaps in our data
-                if is_stacks_block_present(&block_identifier, 0, &stacks_db)
+                if consolidated_block_height.map_or(false, |h| block_identifier.index <= h)
+                    || is_stacks_block_present(&block_identifier, 0, stacks_db.as_ref())
                     || block_identifier.index == 0
                 {
                     continue;
@@ -180,7 +209,7 @@ pub async fn get_canonical_fork_from_tsv(
 pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
     predicate_spec: &StacksChainhookInstance,
     unfinished_scan_data: Option<ScanningData>,
-    stacks_db_conn: &DB,
+    stacks_db_conn: &dyn StacksStorage,
     config: &Config,
     kill_signal: Option<Arc<RwLock<bool>>>,
     ctx: &Context,
@@ -223,7 +252,6 @@ pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
         PredicatesApi::Off => None,
     };
 
-    let proofs = HashMap::new();
     debug!(
         ctx.expect_logger(),
         "Starting predicate evaluation on Stacks blocks for predicate {}", predicate_uuid
@@ -243,6 +271,10 @@ pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
         }
     };
 
+    let mut pending_batch = PendingBatch::new(
+        predicate_spec.max_batch_size,
+        predicate_spec.max_batch_wait_ms,
+    );
     let mut loop_did_trigger = false;
     while let Some(current_block_height) = block_heights_to_scan.pop_front() {
         if let Some(kill_signal) = kill_signal.clone() {
@@ -299,82 +331,34 @@ pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
 
         number_of_blocks_scanned += 1;
 
-        let block_data =
-            match get_stacks_block_at_block_height(current_block_height, true, 3, stacks_db_conn) {
-                Ok(Some(block)) => block,
-                Ok(None) => match get_stacks_block_at_block_height(
-                    current_block_height,
-                    false,
-                    3,
-                    stacks_db_conn,
-                ) {
-                    Ok(Some(block)) => block,
-                    Ok(None) => {
-                        return Err(format!("Unable to retrieve block {current_block_height}"))
-                    }
-                    Err(e) => {
-                        return Err(format!(
-                            "Unable to retrieve block {current_block_height}: {e}"
-                        ))
-                    }
-                },
-                Err(e) => {
-                    return Err(format!(
-                        "Unable to retrieve block {current_block_height}: {e}"
-                    ))
-                }
-            };
+        let block_data = get_stacks_block_to_scan(current_block_height, stacks_db_conn)?;
         last_block_scanned = block_data.block_identifier.clone();
 
-        let blocks: Vec<&dyn AbstractStacksBlock> = vec![&block_data];
-
-        let (hits_per_blocks, _predicates_expired) =
-            evaluate_stacks_chainhook_on_blocks(blocks, predicate_spec, ctx);
-
-        if hits_per_blocks.is_empty() {
+        if let Some(positions) = triggering_transaction_positions(&block_data, predicate_spec, ctx)
+        {
+            pending_batch.push((block_data, positions));
+        }
+        if !pending_batch.is_ready() {
             continue;
         }
 
-        let trigger = StacksTriggerChainhook {
-            chainhook: predicate_spec,
-            apply: hits_per_blocks,
-            rollback: vec![],
-        };
-        let res = match handle_stacks_hook_action(
-            trigger,
-            &proofs,
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
             &config.get_event_observer_config(),
+            RetryPolicy::fixed(3, 1),
             ctx,
-        ) {
-            Err(e) => {
-                warn!(
-                    ctx.expect_logger(),
-                    "unable to handle action for predicate {}: {}", predicate_uuid, e
-                );
-                Ok(()) // todo: should this error increment our err_count?
-            }
-            Ok(action) => {
-                number_of_times_triggered += 1;
-                loop_did_trigger = true;
-                let res = match action {
-                    StacksChainhookOccurrence::Http(request, _) => {
-                        send_request(request, 3, 1, ctx).await
-                    }
-                    StacksChainhookOccurrence::File(path, bytes) => file_append(path, bytes, ctx),
-                    StacksChainhookOccurrence::Data(_payload) => Ok(()),
-                };
-                match res {
-                    Err(e) => {
-                        err_count += 1;
-                        Err(e)
-                    }
-                    Ok(_) => {
-                        err_count = 0;
-                        Ok(())
-                    }
-                }
-            }
-        };
+        )
+        .await;
+        if blocks_triggering > 0 {
+            number_of_times_triggered += blocks_triggering;
+            loop_did_trigger = true;
+        }
+        if res.is_err() {
+            err_count += 1;
+        } else {
+            err_count = 0;
+        }
         // We abort after 3 consecutive errors
         if err_count >= 3 {
             if res.is_err() {
@@ -387,6 +371,24 @@ pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
             }
         }
     }
+    // Deliver the matching blocks still waiting for their batch to fill up
+    if !pending_batch.is_empty() {
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &config.get_event_observer_config(),
+            RetryPolicy::fixed(3, 1),
+            ctx,
+        )
+        .await;
+        number_of_times_triggered += blocks_triggering;
+        if let Err(e) = res {
+            warn!(
+                ctx.expect_logger(),
+                "Unable to deliver last batch of predicate {predicate_uuid}: {e}"
+            );
+        }
+    }
     info!(
         ctx.expect_logger(),
         "Predicate {predicate_uuid} scan completed. {number_of_blocks_scanned} blocks scanned, {number_of_times_triggered} blocks triggering predicate.",
@@ -446,6 +448,342 @@ pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate(
     Ok(PredicateScanResult::ChainTipReached)
 }
 
+/// Scans the blocks of a predicate in `jobs` chunks evaluated concurrently, the blocks triggering
+/// the predicate being delivered in order. Meant for one-off scans: the blocks inserted after the
+/// start of the scan are not scanned, and the progress is not recorded in the predicates db.
+pub async fn scan_stacks_chainstate_via_rocksdb_using_predicate_in_chunks(
+    predicate_spec: &StacksChainhookInstance,
+    stacks_db_conn: Arc<dyn StacksStorage>,
+    config: &Config,
+    jobs: usize,
+    ctx: &Context,
+) -> Result<PredicateScanResult, String> {
+    let predicate_uuid = &predicate_spec.uuid;
+    let chain_tip = match get_last_unconfirmed_block_height_inserted(stacks_db_conn.as_ref(), ctx)
+        .or_else(|| get_last_block_height_inserted(stacks_db_conn.as_ref(), ctx))
+    {
+        Some(chain_tip) => chain_tip,
+        None => {
+            info!(ctx.expect_logger(), "No blocks inserted in db; cannot determine Stacks chain tip. Skipping scan of predicate {}", predicate_uuid);
+            return Ok(PredicateScanResult::ChainTipReached);
+        }
+    };
+    let block_heights_to_scan = match get_block_heights_to_scan(
+        &predicate_spec.blocks,
+        &predicate_spec.start_block,
+        &predicate_spec.end_block,
+        &chain_tip,
+        &None,
+    )? {
+        Some(h) => h,
+        None => return Ok(PredicateScanResult::ChainTipReached),
+    };
+    let number_of_blocks_to_scan = block_heights_to_scan.len();
+    let block_heights_to_scan: VecDeque<u64> = block_heights_to_scan
+        .into_iter()
+        .filter(|height| *height <= chain_tip)
+        .collect();
+    let reaches_last_block = block_heights_to_scan.len() == number_of_blocks_to_scan;
+
+    debug!(
+        ctx.expect_logger(),
+        "Starting predicate evaluation on Stacks blocks for predicate {predicate_uuid} with {jobs} jobs",
+    );
+
+    let mut chunks = OrderedChunks::default();
+    for chunk in split_block_heights(block_heights_to_scan, jobs) {
+        let stacks_db_conn = stacks_db_conn.clone();
+        let predicate_spec = predicate_spec.clone();
+        let ctx = ctx.clone();
+        // Blocks are read from the db and evaluated on a blocking thread per chunk
+        chunks.spawn(chunk, move |heights, evaluations_tx| async move {
+            let _ = tokio::task::spawn_blocking(move || {
+                for height in heights {
+                    let evaluation =
+                        get_stacks_block_to_scan(height, stacks_db_conn.as_ref()).map(|block| {
+                            triggering_transaction_positions(&block, &predicate_spec, &ctx)
+                                .map(|positions| (block, positions))
+                        });
+                    let failed = evaluation.is_err();
+                    if evaluations_tx.blocking_send(evaluation).is_err() || failed {
+                        break;
+                    }
+                }
+            })
+            .await;
+        });
+    }
+
+    let event_observer_config = config.get_event_observer_config();
+    let mut number_of_blocks_scanned = 0;
+    let mut number_of_times_triggered = 0;
+    let mut err_count = 0;
+    let mut pending_batch = PendingBatch::new(
+        predicate_spec.max_batch_size,
+        predicate_spec.max_batch_wait_ms,
+    );
+    while let Some(evaluation) = chunks.next().await {
+        number_of_blocks_scanned += 1;
+        if let Some(triggering_block) = evaluation? {
+            pending_batch.push(triggering_block);
+        }
+        if !pending_batch.is_ready() {
+            continue;
+        }
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &event_observer_config,
+            RetryPolicy::fixed(3, 1),
+            ctx,
+        )
+        .await;
+        number_of_times_triggered += blocks_triggering;
+        match res {
+            Ok(()) => err_count = 0,
+            Err(e) => {
+                err_count += 1;
+                // We abort after 3 consecutive errors
+                if err_count >= 3 {
+                    return Err(format!(
+                        "Scan aborted (consecutive action errors >= 3): {}",
+                        e
+                    ));
+                }
+            }
+        }
+    }
+    // Deliver the matching blocks still waiting for their batch to fill up
+    if !pending_batch.is_empty() {
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &event_observer_config,
+            RetryPolicy::fixed(3, 1),
+            ctx,
+        )
+        .await;
+        number_of_times_triggered += blocks_triggering;
+        if let Err(e) = res {
+            warn!(
+                ctx.expect_logger(),
+                "Unable to deliver last batch of predicate {predicate_uuid}: {e}"
+            );
+        }
+    }
+    info!(
+        ctx.expect_logger(),
+        "Predicate {predicate_uuid} scan completed. {number_of_blocks_scanned} blocks scanned, {number_of_times_triggered} blocks triggering predicate.",
+    );
+
+    if (predicate_spec.blocks.is_some() || predicate_spec.end_block.is_some()) && reaches_last_block
+    {
+        return Ok(PredicateScanResult::Expired);
+    }
+    Ok(PredicateScanResult::ChainTipReached)
+}
+
+/// Retrieves the block to scan at `block_height`, confirmed or not.
+fn get_stacks_block_to_scan(
+    block_height: u64,
+    stacks_db_conn: &dyn StacksStorage,
+) -> Result<StacksBlockData, String> {
+    match get_stacks_block_at_block_height(block_height, true, 3, stacks_db_conn) {
+        Ok(Some(block)) => Ok(block),
+        Ok(None) => {
+            match get_stacks_block_at_block_height(block_height, false, 3, stacks_db_conn) {
+                Ok(Some(block)) => Ok(block),
+                Ok(None) => Err(format!("Unable to retrieve block {block_height}")),
+                Err(e) => Err(format!("Unable to retrieve block {block_height}: {e}")),
+            }
+        }
+        Err(e) => Err(format!("Unable to retrieve block {block_height}: {e}")),
+    }
+}
+
+/// Returns the positions of the block's transactions matching the predicate if the block
+/// triggers the predicate, so that it can be added to a pending batch.
+fn triggering_transaction_positions(
+    block_data: &StacksBlockData,
+    predicate_spec: &StacksChainhookInstance,
+    ctx: &Context,
+) -> Option<Vec<usize>> {
+    let blocks: Vec<&dyn AbstractStacksBlock> = vec![block_data];
+    // Bitcoin blocks are not available while scanning the Stacks chainstate:
+    // `bitcoin_anchored` predicates only trigger while streaming blocks.
+    let (hits_per_blocks, _predicates_expired) = evaluate_stacks_chainhook_on_blocks(
+        blocks,
+        predicate_spec,
+        &BitcoinAnchorContext::empty(),
+        ctx,
+    );
+    let (hits, _block) = hits_per_blocks.first()?;
+    Some(matching_transaction_positions(
+        &block_data.transactions,
+        hits,
+    ))
+}
+
+/// Delivers the blocks of a pending batch in a single occurrence per action of the predicate,
+/// reusing the transactions matched when the blocks were added to the batch and retrying
+/// according to each action's retry policy or, when it has none, to `default_retry_policy`.
+/// Returns the number of blocks triggering the predicate along with the result of the first
+/// failed delivery, if any.
+async fn deliver_pending_batch(
+    pending_batch: &mut PendingBatch<(StacksBlockData, Vec<usize>)>,
+    predicate_spec: &StacksChainhookInstance,
+    event_observer_config: &EventObserverConfig,
+    default_retry_policy: RetryPolicy,
+    ctx: &Context,
+) -> (u64, Result<(), String>) {
+    let blocks_data = pending_batch.take();
+    if blocks_data.is_empty() {
+        return (0, Ok(()));
+    }
+    let blocks_triggering = blocks_data.len() as u64;
+    let hits_per_blocks = blocks_data
+        .iter()
+        .map(|(block, positions)| {
+            (
+                transactions_at_positions(&block.transactions, positions),
+                block as &dyn AbstractStacksBlock,
+            )
+        })
+        .collect();
+
+    let proofs = HashMap::new();
+    let trigger = StacksTriggerChainhook {
+        chainhook: predicate_spec,
+        apply: hits_per_blocks,
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: Some(event_observer_config.get_stacks_confirmation_depth()),
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    let mut delivered = false;
+    let mut res = Ok(());
+    for trigger in trigger.split_by_action().into_iter() {
+        let Some(delivery) = deliver_stacks_occurrence(
+            trigger,
+            &proofs,
+            event_observer_config,
+            &default_retry_policy,
+            ctx,
+        )
+        .await
+        else {
+            continue;
+        };
+        delivered = true;
+        if res.is_ok() {
+            res = delivery;
+        }
+    }
+    if !delivered {
+        // todo: should this error increment our err_count?
+        return (0, Ok(()));
+    }
+    (blocks_triggering, res)
+}
+
+/// Delivers an occurrence to one of the predicate's actions, routing it to the action's
+/// dead-letter sink if the delivery fails. Returns None if the action could not be handled.
+async fn deliver_stacks_occurrence<'a>(
+    trigger: StacksTriggerChainhook<'a>,
+    proofs: &HashMap<&'a TransactionIdentifier, String>,
+    event_observer_config: &EventObserverConfig,
+    default_retry_policy: &RetryPolicy,
+    ctx: &Context,
+) -> Option<Result<(), String>> {
+    let predicate_spec = trigger.chainhook;
+    let hook_action = trigger.get_action();
+    let retry_policy = hook_action
+        .retry_policy()
+        .cloned()
+        .unwrap_or(default_retry_policy.clone());
+    let delivery_id = trigger.get_delivery_id();
+    let action = match handle_stacks_hook_action(trigger, proofs, event_observer_config, ctx) {
+        Ok(action) => action,
+        Err(e) => {
+            warn!(
+                ctx.expect_logger(),
+                "unable to handle action for predicate {}: {}", predicate_spec.uuid, e
+            );
+            return None;
+        }
+    };
+    let (res, data) = match action {
+        StacksChainhookOccurrence::Http(request, data) => {
+            let res = send_http_occurrence_with_retry_policy(
+                request,
+                hook_action.requires_ack(),
+                &retry_policy,
+                &action_rate_limiter(hook_action, event_observer_config.get_predicates_config()),
+                ctx,
+            )
+            .await
+            .map(|_| ());
+            (res, data)
+        }
+        StacksChainhookOccurrence::File(hook, bytes, data) => {
+            (append_to_file_sink(&hook, bytes, ctx), data)
+        }
+        StacksChainhookOccurrence::ObjectStore(upload, data) => {
+            let res = send_with_retry_policy(&retry_policy, ctx, || {
+                send_object_store_upload(&upload, 1, 0, ctx)
+            })
+            .await;
+            (res, data)
+        }
+        StacksChainhookOccurrence::Kafka(message, data) => {
+            let res = send_with_retry_policy(&retry_policy, ctx, || {
+                send_kafka_message(&message, 1, 0, ctx)
+            })
+            .await;
+            (res, data)
+        }
+        StacksChainhookOccurrence::Nats(message, data) => {
+            let res = send_with_retry_policy(&retry_policy, ctx, || {
+                send_nats_message(&message, 1, 0, ctx)
+            })
+            .await;
+            (res, data)
+        }
+        StacksChainhookOccurrence::Aws(message, data) => {
+            let res = send_with_retry_policy(&retry_policy, ctx, || {
+                send_aws_message(&message, 1, 0, ctx)
+            })
+            .await;
+            (res, data)
+        }
+        StacksChainhookOccurrence::Postgres(record, data) => {
+            let res = send_with_retry_policy(&retry_policy, ctx, || {
+                send_postgres_record(&record, 1, 0, ctx)
+            })
+            .await;
+            (res, data)
+        }
+        StacksChainhookOccurrence::Data(data) => (Ok(()), data),
+    };
+    let res = match res {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            dead_letter_failed_delivery(
+                hook_action.dead_letter(),
+                &predicate_spec.uuid,
+                &delivery_id,
+                e,
+                &data,
+                event_observer_config.get_predicates_config(),
+                ctx,
+            )
+            .await
+        }
+    };
+    Some(res)
+}
+
 pub async fn scan_stacks_chainstate_via_csv_using_predicate(
     predicate_spec: &StacksChainhookInstance,
     config: &mut Config,
@@ -463,11 +801,14 @@ pub async fn scan_stacks_chainstate_via_csv_using_predicate(
 
     let _ = download_stacks_dataset_if_required(config, ctx).await?;
 
-    let mut canonical_fork = get_canonical_fork_from_tsv(config, None, ctx).await?;
+    let mut canonical_fork = get_canonical_fork_from_tsv(config, None, None, ctx).await?;
 
     let mut indexer = Indexer::new(config.network.clone());
 
-    let proofs = HashMap::new();
+    let mut pending_batch = PendingBatch::new(
+        predicate_spec.max_batch_size,
+        predicate_spec.max_batch_wait_ms,
+    );
 
     let mut occurrences_found = 0;
     let mut blocks_scanned = 0;
@@ -521,45 +862,47 @@ pub async fn scan_stacks_chainstate_via_csv_using_predicate(
             }
         };
 
-        let blocks: Vec<&dyn AbstractStacksBlock> = vec![&block_data];
-
-        let (hits_per_blocks, _predicates_expired) =
-            evaluate_stacks_chainhook_on_blocks(blocks, predicate_spec, ctx);
-        if hits_per_blocks.is_empty() {
+        if let Some(positions) = triggering_transaction_positions(&block_data, predicate_spec, ctx)
+        {
+            pending_batch.push((block_data, positions));
+        }
+        if !pending_batch.is_ready() {
             continue;
         }
 
-        let trigger = StacksTriggerChainhook {
-            chainhook: predicate_spec,
-            apply: hits_per_blocks,
-            rollback: vec![],
-        };
-        match handle_stacks_hook_action(trigger, &proofs, &config.get_event_observer_config(), ctx)
-        {
-            Err(e) => {
-                error!(ctx.expect_logger(), "unable to handle action {}", e);
-            }
-            Ok(action) => {
-                occurrences_found += 1;
-                let res = match action {
-                    StacksChainhookOccurrence::Http(request, _) => {
-                        send_request(request, 10, 3, ctx).await
-                    }
-                    StacksChainhookOccurrence::File(path, bytes) => file_append(path, bytes, ctx),
-                    StacksChainhookOccurrence::Data(_payload) => unreachable!(),
-                };
-                if res.is_err() {
-                    err_count += 1;
-                } else {
-                    err_count = 0;
-                }
-            }
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &config.get_event_observer_config(),
+            RetryPolicy::fixed(10, 3),
+            ctx,
+        )
+        .await;
+        occurrences_found += blocks_triggering;
+        if res.is_err() {
+            err_count += 1;
+        } else {
+            err_count = 0;
         }
         // We abort after 3 consecutive errors
         if err_count >= 3 {
             return Err("Scan aborted (consecutive action errors >= 3)".to_string());
         }
     }
+    if !pending_batch.is_empty() {
+        let (blocks_triggering, res) = deliver_pending_batch(
+            &mut pending_batch,
+            predicate_spec,
+            &config.get_event_observer_config(),
+            RetryPolicy::fixed(10, 3),
+            ctx,
+        )
+        .await;
+        occurrences_found += blocks_triggering;
+        if let Err(e) = res {
+            error!(ctx.expect_logger(), "unable to deliver last batch: {}", e);
+        }
+    }
     info!(
         ctx.expect_logger(),
         "{blocks_scanned} blocks scanned, {occurrences_found} occurrences found"
@@ -577,91 +920,144 @@ pub async fn consolidate_local_stacks_chainstate_using_csv(
         "Building local chainstate from Stacks archive file"
     );
 
-    let downloaded_new_dataset = download_stacks_dataset_if_required(config, ctx).await?;
-    if downloaded_new_dataset {
-        let stacks_db =
-            open_readonly_stacks_db_conn_with_retry(&config.expected_cache_path(), 3, ctx)?;
-        let confirmed_tip = get_last_block_height_inserted(&stacks_db, ctx);
-        let mut canonical_fork: VecDeque<(BlockIdentifier, BlockIdentifier, u64)> =
-            get_canonical_fork_from_tsv(config, confirmed_tip, ctx).await?;
-
-        let mut indexer = Indexer::new(config.network.clone());
-        let mut blocks_inserted = 0;
-        let mut blocks_read = 0;
-        let blocks_to_insert = canonical_fork.len();
-        let stacks_db_rw = open_readwrite_stacks_db_conn(&config.expected_cache_path(), ctx)?;
+    // The archive is considered new until it was fully consolidated, which lets an interrupted
+    // consolidation resume even though its archive was already downloaded.
+    download_stacks_dataset_if_required(config, ctx).await?;
+    let Ok(tsv_path) = config.expected_local_stacks_tsv_file().cloned() else {
         info!(
             ctx.expect_logger(),
-            "Beginning import of {} Stacks blocks into rocks db", blocks_to_insert
+            "Skipping database consolidation - no archive to consolidate."
         );
-        // TODO: To avoid repeating code with `scan_stacks_chainstate_via_csv_using_predicate`, we should move this block
-        // retrieval code into a reusable function.
-        let tsv_path = config.expected_local_stacks_tsv_file()?.clone();
-        let mut tsv_reader = BufReader::new(File::open(tsv_path).map_err(|e| e.to_string())?);
-        let mut tsv_current_line = 0;
-        for (block_identifier, _parent_block_identifier, tsv_line_number) in
-            canonical_fork.drain(..)
-        {
-            blocks_read += 1;
+        return Ok(());
+    };
+    let archive_fingerprint = get_stacks_tsv_fingerprint(&tsv_path)?;
+    let stacks_db = open_readonly_stacks_db_conn_with_retry(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+        3,
+        ctx,
+    )?;
+    let consolidated_fingerprint =
+        get_last_consolidated_archive_fingerprint(stacks_db.as_ref(), ctx);
+    if consolidated_fingerprint.as_ref() == Some(&archive_fingerprint) {
+        info!(
+            ctx.expect_logger(),
+            "Skipping database consolidation - no new archive found since last consolidation."
+        );
+        return Ok(());
+    }
 
-            // If blocks already stored, move on
-            if is_stacks_block_present(&block_identifier, 3, &stacks_db_rw) {
-                continue;
+    let confirmed_tip = get_last_block_height_inserted(stacks_db.as_ref(), ctx);
+    // Blocks pruned from the db must not be imported again from the archive
+    let last_pruned_block_height = get_last_pruned_block_height(stacks_db.as_ref(), ctx);
+    let consolidated_block_height =
+        get_last_consolidated_archive_block_height(stacks_db.as_ref(), ctx)
+            .max(last_pruned_block_height);
+    let mut canonical_fork: VecDeque<(BlockIdentifier, BlockIdentifier, u64)> =
+        get_canonical_fork_from_tsv(config, confirmed_tip, consolidated_block_height, ctx).await?;
+    let archive_block_identifiers = match (canonical_fork.front(), canonical_fork.back()) {
+        (Some((_, parent_block_identifier, _)), Some((archive_tip, ..))) => {
+            Some((parent_block_identifier.clone(), archive_tip.clone()))
+        }
+        _ => None,
+    };
+
+    let mut indexer = Indexer::new(config.network.clone());
+    let mut blocks_inserted = 0;
+    let mut blocks_read = 0;
+    let mut blocks_failed = 0;
+    let blocks_to_insert = canonical_fork.len();
+    let stacks_db_rw = open_readwrite_stacks_db_conn(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+        ctx,
+    )?;
+    info!(
+        ctx.expect_logger(),
+        "Beginning import of {} Stacks blocks into rocks db", blocks_to_insert
+    );
+    // TODO: To avoid repeating code with `scan_stacks_chainstate_via_csv_using_predicate`, we should move this block
+    // retrieval code into a reusable function.
+    let mut tsv_reader = BufReader::new(File::open(tsv_path).map_err(|e| e.to_string())?);
+    let mut tsv_current_line = 0;
+    for (block_identifier, _parent_block_identifier, tsv_line_number) in canonical_fork.drain(..) {
+        blocks_read += 1;
+
+        // If blocks already stored, move on
+        if is_stacks_block_present(&block_identifier, 3, stacks_db_rw.as_ref()) {
+            continue;
+        }
+        blocks_inserted += 1;
+
+        // Seek to required line from TSV and retrieve its block payload.
+        let mut tsv_line = String::new();
+        while tsv_current_line < tsv_line_number {
+            tsv_line.clear();
+            let bytes_read = tsv_reader
+                .read_line(&mut tsv_line)
+                .map_err(|e| e.to_string())?;
+            if bytes_read == 0 {
+                return Err("Unexpected EOF when reading TSV".to_string());
             }
-            blocks_inserted += 1;
-
-            // Seek to required line from TSV and retrieve its block payload.
-            let mut tsv_line = String::new();
-            while tsv_current_line < tsv_line_number {
-                tsv_line.clear();
-                let bytes_read = tsv_reader
-                    .read_line(&mut tsv_line)
-                    .map_err(|e| e.to_string())?;
-                if bytes_read == 0 {
-                    return Err("Unexpected EOF when reading TSV".to_string());
-                }
-                tsv_current_line += 1;
+            tsv_current_line += 1;
+        }
+        let Some(serialized_block) = tsv_line.split('\t').last() else {
+            return Err("Unable to retrieve serialized block from TSV line".to_string());
+        };
+
+        let block_data = match indexer::stacks::standardize_stacks_serialized_block(
+            &indexer.config,
+            serialized_block,
+            &mut indexer.stacks_context,
+            ctx,
+        ) {
+            Ok(block) => block,
+            Err(e) => {
+                error!(
+                    &ctx.expect_logger(),
+                    "Failed to standardize stacks block: {e}"
+                );
+                blocks_failed += 1;
+                continue;
             }
-            let Some(serialized_block) = tsv_line.split('\t').last() else {
-                return Err("Unable to retrieve serialized block from TSV line".to_string());
-            };
+        };
 
-            let block_data = match indexer::stacks::standardize_stacks_serialized_block(
-                &indexer.config,
-                serialized_block,
-                &mut indexer.stacks_context,
-                ctx,
-            ) {
-                Ok(block) => block,
-                Err(e) => {
-                    error!(
-                        &ctx.expect_logger(),
-                        "Failed to standardize stacks block: {e}"
-                    );
-                    continue;
-                }
-            };
+        insert_entry_in_stacks_blocks(&block_data, stacks_db_rw.as_ref(), ctx)?;
 
-            insert_entry_in_stacks_blocks(&block_data, &stacks_db_rw, ctx)?;
+        if blocks_inserted % 2500 == 0 {
+            info!(
+                ctx.expect_logger(),
+                "Importing Stacks blocks into rocks db: {}/{}", blocks_read, blocks_to_insert
+            );
+            let _ = stacks_db_rw.flush();
+        }
+    }
 
-            if blocks_inserted % 2500 == 0 {
-                info!(
-                    ctx.expect_logger(),
-                    "Importing Stacks blocks into rocks db: {}/{}", blocks_read, blocks_to_insert
-                );
-                let _ = stacks_db_rw.flush();
+    // Record how far the archive was consolidated, so that the next consolidations only
+    // process what is new, and resume from there if interrupted.
+    if blocks_failed == 0 {
+        if let Some((parent_block_identifier, archive_tip)) = archive_block_identifiers {
+            if parent_block_identifier.index == 0
+                || last_pruned_block_height.map_or(false, |h| parent_block_identifier.index <= h)
+                || is_stacks_block_present(&parent_block_identifier, 3, stacks_db_rw.as_ref())
+            {
+                set_last_consolidated_archive_block_height(
+                    archive_tip.index,
+                    stacks_db_rw.as_ref(),
+                    ctx,
+                )?;
             }
         }
-        let _ = stacks_db_rw.flush();
-        info!(
-            ctx.expect_logger(),
-            "{blocks_read} Stacks blocks read, {blocks_inserted} inserted"
-        );
-    } else {
-        info!(
-            ctx.expect_logger(),
-            "Skipping database consolidation - no new archive found since last consolidation."
-        );
+        set_last_consolidated_archive_fingerprint(
+            &archive_fingerprint,
+            stacks_db_rw.as_ref(),
+            ctx,
+        )?;
     }
+    let _ = stacks_db_rw.flush();
+    info!(
+        ctx.expect_logger(),
+        "{blocks_read} Stacks blocks read, {blocks_inserted} inserted"
+    );
     Ok(())
 }
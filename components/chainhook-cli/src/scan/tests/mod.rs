@@ -5,7 +5,8 @@ use test_case::test_case;
 
 use crate::service::ScanningData;
 
-use super::common::get_block_heights_to_scan;
+use super::cache::BlockCache;
+use super::common::{get_block_heights_to_scan, split_block_heights, OrderedChunks, PendingBatch};
 
 fn expect_exceeded_max_entries_error(
     (result, _expected_entries): (Result<Option<VecDeque<u64>>, String>, Option<VecDeque<u64>>),
@@ -112,3 +113,107 @@ fn test_get_block_heights_to_scan(
         expected,
     )
 }
+
+#[test_case(None, None, 1; "batching disabled delivers every block")]
+#[test_case(Some(3), None, 3; "delivers when max_batch_size is reached")]
+#[test_case(Some(3), Some(60_000), 3; "delivers when max_batch_size is reached before max_batch_wait_ms")]
+fn test_pending_batch_is_ready_when_full(
+    max_batch_size: Option<u64>,
+    max_batch_wait_ms: Option<u64>,
+    expected_len: usize,
+) {
+    let mut batch = PendingBatch::new(max_batch_size, max_batch_wait_ms);
+    assert!(!batch.is_ready());
+    for i in 0..expected_len {
+        assert!(!batch.is_ready());
+        batch.push(i);
+    }
+    assert!(batch.is_ready());
+    assert_eq!(batch.take().len(), expected_len);
+    assert!(batch.is_empty());
+}
+
+#[test]
+fn test_pending_batch_is_ready_after_max_batch_wait_ms() {
+    let mut batch = PendingBatch::new(None, Some(0));
+    assert!(!batch.is_ready());
+    batch.push(1);
+    batch.push(2);
+    assert!(batch.is_ready());
+    assert_eq!(batch.take(), vec![1, 2]);
+    assert!(!batch.is_ready());
+}
+
+#[test_case(10, 3, vec![4, 4, 2]; "splits heights into similar chunks")]
+#[test_case(2, 4, vec![1, 1]; "returns fewer chunks than jobs for short ranges")]
+#[test_case(5, 1, vec![5]; "returns a single chunk for one job")]
+fn test_split_block_heights(heights: u64, chunks: usize, expected_lens: Vec<usize>) {
+    let chunks = split_block_heights((0..heights).collect(), chunks);
+    assert_eq!(
+        chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+        expected_lens
+    );
+    assert_eq!(
+        chunks.into_iter().flatten().collect::<Vec<_>>(),
+        (0..heights).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_ordered_chunks_merges_evaluations_in_order() {
+    let mut chunks = OrderedChunks::default();
+    for (i, chunk) in split_block_heights((0..300).collect(), 3)
+        .into_iter()
+        .enumerate()
+    {
+        chunks.spawn(chunk, move |heights, evaluations_tx| async move {
+            // later chunks finish first
+            tokio::time::sleep(std::time::Duration::from_millis(30 * (3 - i as u64))).await;
+            for height in heights {
+                let _ = evaluations_tx.send(Ok(Some(height))).await;
+            }
+        });
+    }
+    let mut heights = vec![];
+    while let Some(evaluation) = chunks.next().await {
+        heights.push(evaluation.unwrap().unwrap());
+    }
+    assert_eq!(heights, (0..300).collect::<Vec<_>>());
+}
+
+fn get_block_cache_dir() -> std::path::PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("chainhook-block-cache-{}", uuid::Uuid::new_v4()));
+    dir
+}
+
+#[test]
+fn test_block_cache_persists_blocks() {
+    let dir = get_block_cache_dir();
+    let block_cache = BlockCache::new(dir.clone(), 1024).unwrap();
+    assert_eq!(block_cache.get::<Vec<u64>>("00ab"), None);
+    block_cache.insert("00ab", &vec![1u64, 2, 3]);
+    assert_eq!(block_cache.get::<Vec<u64>>("00ab"), Some(vec![1, 2, 3]));
+    // Paths can't escape the cache directory
+    block_cache.insert("../00ab", &vec![1u64]);
+    assert_eq!(block_cache.get::<Vec<u64>>("../00ab"), None);
+
+    let block_cache = BlockCache::new(dir.clone(), 1024).unwrap();
+    assert_eq!(block_cache.get::<Vec<u64>>("00ab"), Some(vec![1, 2, 3]));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_block_cache_evicts_least_recently_used_blocks() {
+    let dir = get_block_cache_dir();
+    // Each block takes 6 bytes, e.g. `"00aa"`
+    let block_cache = BlockCache::new(dir.clone(), 12).unwrap();
+    block_cache.insert("00aa", &"00aa");
+    block_cache.insert("00bb", &"00bb");
+    assert!(block_cache.get::<String>("00aa").is_some());
+    block_cache.insert("00cc", &"00cc");
+    assert!(block_cache.get::<String>("00aa").is_some());
+    assert!(block_cache.get::<String>("00bb").is_none());
+    assert!(block_cache.get::<String>("00cc").is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
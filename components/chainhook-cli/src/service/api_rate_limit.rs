@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::ApiRateLimitConfig;
+
+/// Window the requests of a client are counted over.
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Clients tracked before the ones whose window elapsed are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Client the requests to the predicates API are accounted to: the api key they carry, if
+/// configured, or else their ip address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitedClient {
+    ApiKey(String),
+    Ip(Option<IpAddr>),
+}
+
+/// Outcome of the accounting of a request, exposed in the `RateLimit-*` headers of its response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time left before the window of the client ends, and its requests are accepted again.
+    pub reset_after: Duration,
+}
+
+/// Limits the number of requests accepted from each client of the predicates API, per fixed
+/// window of [RATE_LIMIT_WINDOW].
+pub struct ApiRateLimiter {
+    config: ApiRateLimitConfig,
+    windows: Mutex<HashMap<RateLimitedClient, (Instant, u32)>>,
+}
+
+impl ApiRateLimiter {
+    pub fn new(config: ApiRateLimitConfig) -> Self {
+        ApiRateLimiter {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accounts a request of `client` received at `now`. Returns None if the client isn't
+    /// rate limited.
+    pub fn check(&self, client: RateLimitedClient, now: Instant) -> Option<RateLimitDecision> {
+        let limit = match client {
+            RateLimitedClient::ApiKey(_) => self.config.requests_per_minute_per_key?,
+            RateLimitedClient::Ip(_) => self.config.requests_per_minute_per_ip?,
+        };
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= MAX_TRACKED_CLIENTS {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        }
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_LIMIT_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        let allowed = *count < limit;
+        if allowed {
+            *count += 1;
+        }
+        Some(RateLimitDecision {
+            allowed,
+            limit,
+            remaining: limit - *count,
+            reset_after: RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(*start)),
+        })
+    }
+}
@@ -0,0 +1,223 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chainhook_sdk::chainhooks::types::DEFAULT_NAMESPACE;
+use chainhook_sdk::utils::Context;
+
+use super::predicates_db::PredicatesDb;
+use super::{PausedData, PredicateStatus};
+
+/// Key of the list logging the lifecycle transitions of the predicates, most recent first. The
+/// log is append-only, unless `audit_log_max_entries` is set, see [trim_predicate_audit_log].
+pub const PREDICATE_AUDIT_LOG_KEY: &str = "chainhook:audit_log";
+
+/// Key of the hash holding, by predicate key, the actor of the last request changing the status
+/// of a predicate, until the transition it requested is logged.
+const PENDING_ACTORS_KEY: &str = "chainhook:audit_actors";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PredicateAuditAction {
+    Registered,
+    Enabled,
+    Paused,
+    Expired,
+    Interrupted,
+    Deregistered,
+    /// The specification of the predicate was replaced, its status left unchanged.
+    Updated,
+}
+
+impl PredicateAuditAction {
+    /// Returns the action logged when a predicate's status changes from `previous` to `status`,
+    /// `None` standing for a deregistered predicate. Updates reporting progress, e.g. a scan
+    /// turning into a stream, are not logged.
+    pub fn from_transition(
+        previous: Option<&PredicateStatus>,
+        status: Option<&PredicateStatus>,
+    ) -> Option<Self> {
+        use PredicateStatus::*;
+        let Some(status) = status else {
+            return previous.map(|_| Self::Deregistered);
+        };
+        match (previous, status) {
+            (_, New) => Some(Self::Registered),
+            // predicates paused by the circuit breaker can also be paused through the API
+            (Some(Paused(previous)), Paused(PausedData { requested, .. })) => {
+                (*requested && !previous.requested).then_some(Self::Paused)
+            }
+            (_, Paused(_)) => Some(Self::Paused),
+            (Some(Paused(_)), Scanning(_) | Streaming(_)) => Some(Self::Enabled),
+            (
+                Some(UnconfirmedExpiration(_) | ConfirmedExpiration(_)),
+                UnconfirmedExpiration(_) | ConfirmedExpiration(_),
+            ) => None,
+            (_, UnconfirmedExpiration(_) | ConfirmedExpiration(_)) => Some(Self::Expired),
+            (Some(Interrupted(_)), Interrupted(_)) => None,
+            (_, Interrupted(_)) => Some(Self::Interrupted),
+            (_, Scanning(_) | Streaming(_)) => None,
+        }
+    }
+}
+
+/// Entry of the audit log, recording a transition of a predicate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PredicateAuditEntry {
+    pub uuid: String,
+    pub namespace: String,
+    pub action: PredicateAuditAction,
+    /// Who requested the transition through the predicates API, absent for the transitions
+    /// made by the service itself, e.g. expirations.
+    pub actor: Option<String>,
+    /// Status of the predicate before the transition, absent when first registered.
+    pub before: Option<PredicateStatus>,
+    /// Status of the predicate after the transition, absent once deregistered.
+    pub after: Option<PredicateStatus>,
+    /// Unix timestamp, in seconds, of the transition.
+    pub timestamp: u64,
+}
+
+impl PredicateAuditEntry {
+    pub fn new(
+        predicate_key: &str,
+        action: PredicateAuditAction,
+        actor: Option<String>,
+        before: Option<PredicateStatus>,
+        after: Option<PredicateStatus>,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // Keys are either `predicate:<uuid>` or `predicate:<namespace>:<uuid>`
+        let mut parts = predicate_key.rsplit(':');
+        let uuid = parts.next().unwrap_or_default();
+        let namespace = match (parts.next(), parts.next()) {
+            (Some(namespace), Some(_)) => namespace,
+            _ => DEFAULT_NAMESPACE,
+        };
+        PredicateAuditEntry {
+            uuid: uuid.to_string(),
+            namespace: namespace.to_string(),
+            action,
+            actor,
+            before,
+            after,
+            timestamp,
+        }
+    }
+}
+
+/// Records who requested a change of a predicate's status or specification, for the change to be
+/// attributed to them once the service applies it.
+pub fn record_predicate_audit_actor(
+    predicate_key: &str,
+    actor: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Result<(), String> {
+    predicates_db_conn
+        .hset(PENDING_ACTORS_KEY, predicate_key, actor)
+        .map_err(|e| format!("unable to record audit actor: {}", e))
+}
+
+/// Appends the transition of a predicate from `previous` to `status` to the audit log, if it is
+/// one of the transitions audited, attributing it to the actor who requested it, if any.
+pub fn log_predicate_transition(
+    predicate_key: &str,
+    previous: Option<&PredicateStatus>,
+    status: Option<&PredicateStatus>,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    let Some(action) = PredicateAuditAction::from_transition(previous, status) else {
+        return;
+    };
+    log_predicate_change(
+        predicate_key,
+        action,
+        previous,
+        status,
+        predicates_db_conn,
+        ctx,
+    );
+}
+
+/// Appends the update of the specification of a predicate, whose status is `status`, to the
+/// audit log, attributing it to the actor who requested it, if any.
+pub fn log_predicate_update(
+    predicate_key: &str,
+    status: Option<&PredicateStatus>,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    log_predicate_change(
+        predicate_key,
+        PredicateAuditAction::Updated,
+        status,
+        status,
+        predicates_db_conn,
+        ctx,
+    );
+}
+
+fn log_predicate_change(
+    predicate_key: &str,
+    action: PredicateAuditAction,
+    previous: Option<&PredicateStatus>,
+    status: Option<&PredicateStatus>,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    let mut entry = PredicateAuditEntry::new(
+        predicate_key,
+        action,
+        None,
+        previous.cloned(),
+        status.cloned(),
+    );
+    if let Ok(Some(actor)) = predicates_db_conn.hget(PENDING_ACTORS_KEY, predicate_key) {
+        let _ = predicates_db_conn.hdel(PENDING_ACTORS_KEY, predicate_key);
+        entry.actor = Some(actor);
+    }
+    if let Err(e) = predicates_db_conn.lpush(PREDICATE_AUDIT_LOG_KEY, &json!(entry).to_string()) {
+        warn!(
+            ctx.expect_logger(),
+            "Unable to log transition of predicate {} to the audit log: {}", entry.uuid, e
+        );
+    }
+}
+
+/// Drops the oldest transitions of the audit log beyond `max_entries`.
+pub fn trim_predicate_audit_log(
+    max_entries: usize,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Result<(), String> {
+    predicates_db_conn
+        .ltrim(PREDICATE_AUDIT_LOG_KEY, max_entries)
+        .map_err(|e| format!("unable to trim audit log: {}", e))
+}
+
+/// Returns the last `limit` entries of the audit log for the predicates of `namespace`, most
+/// recent first, optionally restricted to the predicate `predicate_uuid`.
+pub fn get_predicate_audit_entries(
+    namespace: &str,
+    predicate_uuid: Option<&str>,
+    limit: usize,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Result<Vec<PredicateAuditEntry>, String> {
+    let entries = predicates_db_conn
+        .lrange_all(PREDICATE_AUDIT_LOG_KEY)
+        .map_err(|e| format!("unable to read audit log: {}", e))?;
+    let mut matching_entries = vec![];
+    for entry in entries.iter() {
+        let entry: PredicateAuditEntry = serde_json::from_str(entry)
+            .map_err(|e| format!("unable to deserialize audit entry: {}", e))?;
+        if entry.namespace != namespace || predicate_uuid.map_or(false, |uuid| entry.uuid != uuid) {
+            continue;
+        }
+        matching_entries.push(entry);
+        if matching_entries.len() == limit {
+            break;
+        }
+    }
+    Ok(matching_entries)
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use chainhook_sdk::observer::{BitcoinBlockDataCached, BitcoinBlockSpillStore};
+use chainhook_sdk::types::BlockIdentifier;
+use chainhook_sdk::utils::Context;
+use rocksdb::DB;
+
+use crate::storage::encryption::{decrypt_payload, encrypt_payload};
+use crate::storage::open_readwrite_bitcoin_block_spill_db_conn;
+
+/// A [BitcoinBlockSpillStore] writing the Bitcoin blocks evicted from the observer's cache to a
+/// rocksdb in the working directory.
+pub struct RocksDbBitcoinBlockSpillStore {
+    db: DB,
+}
+
+impl RocksDbBitcoinBlockSpillStore {
+    pub fn new(base_dir: &PathBuf, ctx: &Context) -> Result<Self, String> {
+        let db = open_readwrite_bitcoin_block_spill_db_conn(base_dir, ctx)?;
+        Ok(RocksDbBitcoinBlockSpillStore { db })
+    }
+}
+
+fn get_spilled_block_key(block_identifier: &BlockIdentifier) -> String {
+    format!("{}:{}", block_identifier.index, block_identifier.hash)
+}
+
+impl BitcoinBlockSpillStore for RocksDbBitcoinBlockSpillStore {
+    fn put(&self, cache: &BitcoinBlockDataCached) -> Result<(), String> {
+        let bytes =
+            serde_json::to_vec(cache).map_err(|e| format!("unable to serialize block: {}", e))?;
+        let bytes = encrypt_payload(&bytes)?;
+        self.db
+            .put(get_spilled_block_key(&cache.block.block_identifier), bytes)
+            .map_err(|e| format!("unable to spill block: {}", e))
+    }
+
+    fn get(
+        &self,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<Option<BitcoinBlockDataCached>, String> {
+        let Some(bytes) = self
+            .db
+            .get(get_spilled_block_key(block_identifier))
+            .map_err(|e| format!("unable to read spilled block: {}", e))?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&decrypt_payload(&bytes)?)
+            .map(Some)
+            .map_err(|e| format!("unable to deserialize block: {}", e))
+    }
+
+    fn remove(&self, block_identifier: &BlockIdentifier) -> Result<(), String> {
+        self.db
+            .delete(get_spilled_block_key(block_identifier))
+            .map_err(|e| format!("unable to remove spilled block: {}", e))
+    }
+}
@@ -0,0 +1,352 @@
+use std::ops::Range;
+use std::sync::mpsc::SyncSender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chainhook_sdk::observer::ObserverCommand;
+use chainhook_sdk::types::{
+    BitcoinBlockData, BitcoinChainEvent, BlockHeader, BlockchainEvent,
+    BlockchainUpdatedWithHeaders, BlockchainUpdatedWithReorg, StacksChainEvent,
+};
+use chainhook_sdk::utils::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::config::PredicatesApiConfig;
+
+use super::open_readwrite_predicates_db_conn;
+use super::predicates_db::PredicatesDb;
+
+/// Channel of the predicates db the sequence numbers of the distributed chain events are
+/// published to. Events can be larger than what some pub/sub implementations accept, so they are
+/// stored in a hash of the predicates db.
+pub const CHAIN_EVENTS_CHANNEL: &str = "chainhook:chain_events";
+const CHAIN_EVENTS_KEY: &str = "chainhook:chain_events";
+/// Hash holding the sequence number of the next chain event distributed, so that it carries on
+/// across restarts of the primary.
+const CHAIN_EVENTS_SEQUENCE_KEY: &str = "chainhook:chain_events_sequence";
+const NEXT_SEQUENCE_FIELD: &str = "next";
+
+/// Distributed chain events kept in the predicates db, for the replicas lagging behind.
+const RETAINED_CHAIN_EVENTS: u64 = 256;
+
+/// Consecutive attempts of a replica to subscribe to the chain events again, once its connection
+/// to the predicates db is lost.
+const CHAIN_EVENTS_FOLLOWER_ATTEMPTS: u64 = 5;
+
+/// Chain event ingested by the primary, evaluated by every instance against its own predicates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "chain", content = "event", rename_all = "snake_case")]
+pub enum DistributedChainEvent {
+    Bitcoin(BitcoinChainEvent),
+    Stacks(StacksChainEvent),
+}
+
+/// Distributes the chain events ingested by the primary to the replicas.
+pub struct ChainEventsCoordinator {
+    api_config: PredicatesApiConfig,
+}
+
+impl ChainEventsCoordinator {
+    /// The events distributed by a previous run are kept, and their sequence carried on, for the
+    /// replicas that haven't evaluated them yet.
+    pub fn new(api_config: &PredicatesApiConfig) -> Result<Self, String> {
+        open_readwrite_predicates_db_conn(api_config)?.ping()?;
+        Ok(ChainEventsCoordinator {
+            api_config: api_config.clone(),
+        })
+    }
+
+    pub fn distribute(&mut self, event: &DistributedChainEvent, ctx: &Context) {
+        if let Err(e) = self.try_distribute(event) {
+            error!(
+                ctx.expect_logger(),
+                "Unable to distribute chain event to replicas: {e}"
+            );
+        }
+    }
+
+    fn try_distribute(&mut self, event: &DistributedChainEvent) -> Result<(), String> {
+        let payload = serde_json::to_string(event)
+            .map_err(|e| format!("unable to serialize chain event: {e}"))?;
+        let mut predicates_db_conn = open_readwrite_predicates_db_conn(&self.api_config)?;
+        let sequence =
+            predicates_db_conn.hincr(CHAIN_EVENTS_SEQUENCE_KEY, NEXT_SEQUENCE_FIELD)? - 1;
+        predicates_db_conn.hset(CHAIN_EVENTS_KEY, &sequence.to_string(), &payload)?;
+        predicates_db_conn.publish(CHAIN_EVENTS_CHANNEL, &sequence.to_string())?;
+        if let Some(expired_sequence) = sequence.checked_sub(RETAINED_CHAIN_EVENTS) {
+            predicates_db_conn.hdel(CHAIN_EVENTS_KEY, &expired_sequence.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates the chain events distributed by the primary, as they are published. Subscribes again
+/// when the connection to the predicates db is lost, and stops once the db can't be reached.
+pub fn start_chain_events_follower(
+    api_config: &PredicatesApiConfig,
+    observer_command_tx: &SyncSender<ObserverCommand>,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut follower = ChainEventsFollower::new();
+    let mut attempt = 0;
+    loop {
+        let last_sequence = follower.last_sequence;
+        let e = match follow_chain_events(&mut follower, api_config, observer_command_tx, ctx) {
+            Ok(()) => "connection to db lost".to_string(),
+            Err(e) => e,
+        };
+        if follower.last_sequence != last_sequence {
+            attempt = 0;
+        }
+        attempt += 1;
+        if attempt > CHAIN_EVENTS_FOLLOWER_ATTEMPTS {
+            return Err(e);
+        }
+        warn!(
+            ctx.expect_logger(),
+            "Chain events subscription interrupted (attempt {attempt}), subscribing again: {e}"
+        );
+        sleep(Duration::from_secs(attempt));
+    }
+}
+
+fn follow_chain_events(
+    follower: &mut ChainEventsFollower,
+    api_config: &PredicatesApiConfig,
+    observer_command_tx: &SyncSender<ObserverCommand>,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config)?;
+    let mut subscriber_conn = open_readwrite_predicates_db_conn(api_config)?;
+    follower.catch_up(predicates_db_conn.as_mut(), observer_command_tx, ctx)?;
+    subscriber_conn
+        .subscribe(CHAIN_EVENTS_CHANNEL, &mut |sequence| {
+            let Ok(sequence) = sequence.parse::<u64>() else {
+                warn!(
+                    ctx.expect_logger(),
+                    "Invalid chain event sequence {sequence}"
+                );
+                return;
+            };
+            if let Err(e) = follower.on_published(
+                sequence,
+                predicates_db_conn.as_mut(),
+                observer_command_tx,
+                ctx,
+            ) {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to evaluate chain event {sequence}: {e}"
+                );
+            }
+        })
+        .map_err(|e| format!("unable to receive chain events: {e}"))
+}
+
+/// Evaluates the chain events distributed by the primary in sequence. The events missed by a
+/// replica, e.g. while it subscribes again, are replayed from the ones retained in the
+/// predicates db.
+pub struct ChainEventsFollower {
+    /// Sequence of the last chain event evaluated, if any.
+    last_sequence: Option<u64>,
+}
+
+impl Default for ChainEventsFollower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChainEventsFollower {
+    pub fn new() -> Self {
+        ChainEventsFollower {
+            last_sequence: None,
+        }
+    }
+
+    /// Evaluates the events distributed since the last one evaluated. Nothing is replayed
+    /// before a first event is evaluated.
+    pub fn catch_up(
+        &mut self,
+        predicates_db_conn: &mut dyn PredicatesDb,
+        observer_command_tx: &SyncSender<ObserverCommand>,
+        ctx: &Context,
+    ) -> Result<(), String> {
+        let Some(last_sequence) = self.last_sequence else {
+            return Ok(());
+        };
+        let next_sequence = get_next_sequence(predicates_db_conn)?;
+        let first_sequence = if next_sequence > last_sequence {
+            last_sequence + 1
+        } else {
+            info!(
+                ctx.expect_logger(),
+                "Chain events sequence started over by the primary"
+            );
+            0
+        };
+        self.evaluate(
+            first_sequence..next_sequence,
+            predicates_db_conn,
+            observer_command_tx,
+            ctx,
+        );
+        Ok(())
+    }
+
+    /// Evaluates the event `sequence` just published, after the ones missed since the last one
+    /// evaluated.
+    pub fn on_published(
+        &mut self,
+        sequence: u64,
+        predicates_db_conn: &mut dyn PredicatesDb,
+        observer_command_tx: &SyncSender<ObserverCommand>,
+        ctx: &Context,
+    ) -> Result<(), String> {
+        let first_sequence = match self.last_sequence {
+            None => sequence,
+            Some(last_sequence) if sequence > last_sequence => last_sequence + 1,
+            // already evaluated while catching up, unless the primary started the sequence over
+            Some(last_sequence) => {
+                if get_next_sequence(predicates_db_conn)? > last_sequence {
+                    return Ok(());
+                }
+                info!(
+                    ctx.expect_logger(),
+                    "Chain events sequence started over by the primary"
+                );
+                0
+            }
+        };
+        self.evaluate(
+            first_sequence..sequence + 1,
+            predicates_db_conn,
+            observer_command_tx,
+            ctx,
+        );
+        Ok(())
+    }
+
+    fn evaluate(
+        &mut self,
+        sequences: Range<u64>,
+        predicates_db_conn: &mut dyn PredicatesDb,
+        observer_command_tx: &SyncSender<ObserverCommand>,
+        ctx: &Context,
+    ) {
+        let first_retained_sequence = sequences.end.saturating_sub(RETAINED_CHAIN_EVENTS);
+        if sequences.start < first_retained_sequence {
+            warn!(
+                ctx.expect_logger(),
+                "{} chain events expired before they could be evaluated",
+                first_retained_sequence - sequences.start
+            );
+        }
+        for sequence in sequences.start.max(first_retained_sequence)..sequences.end {
+            match retrieve_chain_event(sequence, predicates_db_conn) {
+                Ok(Some(event)) => {
+                    for command in get_observer_commands(event) {
+                        let _ = observer_command_tx.send(command);
+                    }
+                }
+                Ok(None) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Chain event {sequence} expired before it could be evaluated"
+                    );
+                }
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Unable to retrieve chain event {sequence}: {e}"
+                    );
+                }
+            }
+            self.last_sequence = Some(sequence);
+        }
+    }
+}
+
+/// Returns the sequence number of the next chain event the primary distributes.
+fn get_next_sequence(predicates_db_conn: &mut dyn PredicatesDb) -> Result<u64, String> {
+    predicates_db_conn
+        .hget(CHAIN_EVENTS_SEQUENCE_KEY, NEXT_SEQUENCE_FIELD)?
+        .map(|sequence| {
+            sequence
+                .parse::<u64>()
+                .map_err(|e| format!("unable to parse chain events sequence: {e}"))
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+fn retrieve_chain_event(
+    sequence: u64,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Result<Option<DistributedChainEvent>, String> {
+    predicates_db_conn
+        .hget(CHAIN_EVENTS_KEY, &sequence.to_string())?
+        .map(|payload| {
+            serde_json::from_str(&payload).map_err(|e| format!("unable to parse chain event: {e}"))
+        })
+        .transpose()
+}
+
+/// Returns the commands making an observer evaluate `event`. The Bitcoin blocks are cached
+/// before the headers of the chain update are propagated, the observer retrieving them from its
+/// cache.
+pub fn get_observer_commands(event: DistributedChainEvent) -> Vec<ObserverCommand> {
+    let event = match event {
+        DistributedChainEvent::Stacks(event) => {
+            return vec![ObserverCommand::PropagateStacksChainEvent(event)]
+        }
+        DistributedChainEvent::Bitcoin(event) => event,
+    };
+    let (blocks, blockchain_event) = match event {
+        BitcoinChainEvent::ChainUpdatedWithBlocks(data) => {
+            let blockchain_event =
+                BlockchainEvent::BlockchainUpdatedWithHeaders(BlockchainUpdatedWithHeaders {
+                    new_headers: get_headers(&data.new_blocks),
+                    confirmed_headers: get_headers(&data.confirmed_blocks),
+                });
+            (
+                [data.new_blocks, data.confirmed_blocks].concat(),
+                blockchain_event,
+            )
+        }
+        BitcoinChainEvent::ChainUpdatedWithReorg(data) => {
+            let blockchain_event =
+                BlockchainEvent::BlockchainUpdatedWithReorg(BlockchainUpdatedWithReorg {
+                    headers_to_rollback: get_headers(&data.blocks_to_rollback),
+                    headers_to_apply: get_headers(&data.blocks_to_apply),
+                    confirmed_headers: get_headers(&data.confirmed_blocks),
+                });
+            (
+                [
+                    data.blocks_to_rollback,
+                    data.blocks_to_apply,
+                    data.confirmed_blocks,
+                ]
+                .concat(),
+                blockchain_event,
+            )
+        }
+    };
+    blocks
+        .into_iter()
+        .map(ObserverCommand::CacheBitcoinBlock)
+        .chain(std::iter::once(
+            ObserverCommand::PropagateBitcoinChainEvent(blockchain_event),
+        ))
+        .collect()
+}
+
+fn get_headers(blocks: &[BitcoinBlockData]) -> Vec<BlockHeader> {
+    blocks
+        .iter()
+        .map(|block| BlockHeader {
+            block_identifier: block.block_identifier.clone(),
+            parent_block_identifier: block.parent_block_identifier.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use chainhook_sdk::chainhooks::bitcoin::{
+    evaluate_bitcoin_chainhooks_on_chain_event, serialize_bitcoin_payload_for_delivery,
+    BitcoinChainhookInstance,
+};
+use chainhook_sdk::chainhooks::stacks::{
+    evaluate_stacks_chainhook_on_blocks, serialize_stacks_payload_for_delivery,
+    BitcoinAnchorContext, StacksChainhookInstance, StacksTriggerChainhook,
+};
+use chainhook_sdk::chainhooks::types::ChainhookSpecificationNetworkMap;
+use chainhook_sdk::indexer;
+use chainhook_sdk::indexer::bitcoin::{
+    build_http_client, source::bitcoin_block_source, BitcoinBlockFullBreakdown,
+};
+use chainhook_sdk::indexer::Indexer;
+use chainhook_sdk::types::{
+    BitcoinBlockData, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData, BlockIdentifier,
+    StacksBlockData,
+};
+use chainhook_sdk::utils::{AbstractStacksBlock, Context};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::config::Config;
+use crate::storage::{get_stacks_block_at_block_height, open_readonly_stacks_db_conn};
+
+/// Body of `POST /v1/chainhooks/dry_run`: a predicate, along with the block to evaluate it on.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DryRunRequest {
+    pub predicate: ChainhookSpecificationNetworkMap,
+    /// Height of the block to evaluate the predicate on, fetched from bitcoind (or the
+    /// configured Esplora API) for Bitcoin predicates and from the local Stacks db otherwise.
+    pub block_height: Option<u64>,
+    /// Block to evaluate the predicate on, formatted like the blocks of the occurrence payloads.
+    pub block: Option<JsonValue>,
+}
+
+/// Evaluates a predicate on a single block, without registering it, and returns the payloads of
+/// the occurrences it would deliver. `bitcoin_anchored` Stacks predicates never trigger, since
+/// Bitcoin blocks are not available to anchor the Stacks block to, and no proofs get included.
+pub async fn dry_run_predicate(
+    request: DryRunRequest,
+    config: &Config,
+    ctx: &Context,
+) -> Result<Vec<JsonValue>, String> {
+    let DryRunRequest {
+        predicate,
+        block_height,
+        block,
+    } = request;
+    predicate.validate()?;
+    if block_height.is_some() == block.is_some() {
+        return Err("Exactly one of block_height and block must be provided".into());
+    }
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
+            let predicate_spec =
+                predicate.into_specification_for_network(&config.network.bitcoin_network)?;
+            let block = match block {
+                Some(block) => serde_json::from_value(block)
+                    .map_err(|e| format!("invalid Bitcoin block: {}", e))?,
+                None => fetch_bitcoin_block(block_height.unwrap_or_default(), config, ctx).await?,
+            };
+            evaluate_bitcoin_predicate_on_block(&predicate_spec, block, ctx)
+        }
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => {
+            let predicate_spec =
+                predicate.into_specification_for_network(&config.network.stacks_network)?;
+            let block = match block {
+                Some(block) => serde_json::from_value(block)
+                    .map_err(|e| format!("invalid Stacks block: {}", e))?,
+                None => fetch_stacks_block(block_height.unwrap_or_default(), config, ctx)?,
+            };
+            evaluate_stacks_predicate_on_block(&predicate_spec, &block, ctx)
+        }
+    }
+}
+
+/// Evaluation of a predicate on a block of a fixture file.
+pub struct FixtureEvaluation {
+    pub fixture_path: String,
+    pub block_identifier: BlockIdentifier,
+    /// Payloads of the occurrences the predicate would deliver.
+    pub occurrences: Vec<JsonValue>,
+    /// Why the block triggered the predicate, one reason per matching transaction.
+    pub reasons: Vec<String>,
+}
+
+/// Evaluates a predicate on the blocks of fixture files, offline. Each file holds a block or an
+/// array of blocks, either formatted like the blocks of the occurrence payloads or as sent by
+/// the nodes: `getblock` results with a verbosity of 3 for Bitcoin, `/new_block` payloads for
+/// Stacks. The same limitations as [dry_run_predicate] apply.
+pub fn test_predicate_on_fixtures(
+    predicate: ChainhookSpecificationNetworkMap,
+    fixture_paths: &[String],
+    config: &Config,
+    ctx: &Context,
+) -> Result<Vec<FixtureEvaluation>, String> {
+    predicate.validate()?;
+    let mut evaluations = vec![];
+    match predicate {
+        ChainhookSpecificationNetworkMap::Bitcoin(predicate) => {
+            let predicate_spec =
+                predicate.into_specification_for_network(&config.network.bitcoin_network)?;
+            let if_this = serde_json::to_string(&predicate_spec.predicate)
+                .map_err(|e| format!("unable to serialize predicate: {}", e))?;
+            let network_parameters = config.get_event_observer_config().get_network_parameters();
+            for fixture_path in fixture_paths {
+                for fixture in load_fixture_blocks(fixture_path)? {
+                    let block: BitcoinBlockData = if fixture.get("block_identifier").is_some() {
+                        serde_json::from_value(fixture).map_err(|e| {
+                            format!("invalid Bitcoin block in {}: {}", fixture_path, e)
+                        })?
+                    } else {
+                        let block_breakdown: BitcoinBlockFullBreakdown =
+                            serde_json::from_value(fixture).map_err(|e| {
+                                format!("invalid bitcoind block in {}: {}", fixture_path, e)
+                            })?;
+                        indexer::bitcoin::standardize_bitcoin_block(
+                            block_breakdown,
+                            &config.network.bitcoin_network,
+                            &network_parameters,
+                            ctx,
+                        )
+                        .map_err(|(e, _)| e)?
+                    };
+                    let block_identifier = block.block_identifier.clone();
+                    let occurrences =
+                        evaluate_bitcoin_predicate_on_block(&predicate_spec, block, ctx)?;
+                    evaluations.push(FixtureEvaluation {
+                        fixture_path: fixture_path.clone(),
+                        block_identifier,
+                        reasons: get_match_reasons(&occurrences, &if_this),
+                        occurrences,
+                    });
+                }
+            }
+        }
+        ChainhookSpecificationNetworkMap::Stacks(predicate) => {
+            let predicate_spec =
+                predicate.into_specification_for_network(&config.network.stacks_network)?;
+            let if_this = serde_json::to_string(&predicate_spec.predicate)
+                .map_err(|e| format!("unable to serialize predicate: {}", e))?;
+            let mut indexer = Indexer::new(config.network.clone());
+            for fixture_path in fixture_paths {
+                for fixture in load_fixture_blocks(fixture_path)? {
+                    let block: StacksBlockData = if fixture.get("block_identifier").is_some() {
+                        serde_json::from_value(fixture).map_err(|e| {
+                            format!("invalid Stacks block in {}: {}", fixture_path, e)
+                        })?
+                    } else {
+                        indexer::stacks::standardize_stacks_marshalled_block(
+                            &indexer.config,
+                            fixture,
+                            &mut indexer.stacks_context,
+                            ctx,
+                        )?
+                    };
+                    let occurrences =
+                        evaluate_stacks_predicate_on_block(&predicate_spec, &block, ctx)?;
+                    evaluations.push(FixtureEvaluation {
+                        fixture_path: fixture_path.clone(),
+                        block_identifier: block.block_identifier,
+                        reasons: get_match_reasons(&occurrences, &if_this),
+                        occurrences,
+                    });
+                }
+            }
+        }
+    }
+    Ok(evaluations)
+}
+
+fn load_fixture_blocks(fixture_path: &str) -> Result<Vec<JsonValue>, String> {
+    let bytes = std::fs::read(fixture_path)
+        .map_err(|e| format!("unable to read file {}: {}", fixture_path, e))?;
+    match serde_json::from_slice(&bytes)
+        .map_err(|e| format!("unable to parse json file {}: {}", fixture_path, e))?
+    {
+        JsonValue::Array(blocks) => Ok(blocks),
+        block => Ok(vec![block]),
+    }
+}
+
+/// Lists the transactions of the occurrences, which only hold the transactions matching the
+/// predicate. Predicates on blocks rather than transactions match whole blocks.
+fn get_match_reasons(occurrences: &[JsonValue], if_this: &str) -> Vec<String> {
+    let mut reasons = vec![];
+    for block in occurrences
+        .iter()
+        .filter_map(|occurrence| occurrence["apply"].as_array())
+        .flatten()
+    {
+        match block["transactions"].as_array() {
+            Some(transactions) if !transactions.is_empty() => {
+                for transaction in transactions {
+                    reasons.push(format!(
+                        "transaction {} matches {}",
+                        transaction["transaction_identifier"]["hash"]
+                            .as_str()
+                            .unwrap_or_default(),
+                        if_this
+                    ));
+                }
+            }
+            _ => reasons.push(format!("block matches {}", if_this)),
+        }
+    }
+    reasons
+}
+
+async fn fetch_bitcoin_block(
+    block_height: u64,
+    config: &Config,
+    ctx: &Context,
+) -> Result<BitcoinBlockData, String> {
+    let event_observer_config = config.get_event_observer_config();
+    let bitcoin_config = event_observer_config.get_bitcoin_config();
+    let network_parameters = event_observer_config.get_network_parameters();
+    let http_client = build_http_client();
+    let block_source = bitcoin_block_source(&http_client, &bitcoin_config, ctx);
+    let block_hash = block_source.retrieve_block_hash(block_height).await?;
+    let block_breakdown = block_source.download_and_parse_block(&block_hash).await?;
+    indexer::bitcoin::standardize_bitcoin_block(
+        block_breakdown,
+        &config.network.bitcoin_network,
+        &network_parameters,
+        ctx,
+    )
+    .map_err(|(e, _)| e)
+}
+
+/// Retrieves a Stacks block from the local db, confirmed or not.
+fn fetch_stacks_block(
+    block_height: u64,
+    config: &Config,
+    ctx: &Context,
+) -> Result<StacksBlockData, String> {
+    let stacks_db = open_readonly_stacks_db_conn(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+        ctx,
+    )?;
+    match get_stacks_block_at_block_height(block_height, true, 0, stacks_db.as_ref())? {
+        Some(block) => Ok(block),
+        None => get_stacks_block_at_block_height(block_height, false, 0, stacks_db.as_ref())?
+            .ok_or(format!("Stacks block #{} not found", block_height)),
+    }
+}
+
+fn evaluate_bitcoin_predicate_on_block(
+    predicate_spec: &BitcoinChainhookInstance,
+    block: BitcoinBlockData,
+    ctx: &Context,
+) -> Result<Vec<JsonValue>, String> {
+    let chain_event =
+        BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+            new_blocks: vec![block],
+            confirmed_blocks: vec![],
+        });
+    let (predicates_triggered, _, _) =
+        evaluate_bitcoin_chainhooks_on_chain_event(&chain_event, &vec![predicate_spec], ctx);
+    predicates_triggered
+        .iter()
+        .map(|trigger| serialize_bitcoin_payload_for_delivery(trigger, &HashMap::new()))
+        .collect()
+}
+
+fn evaluate_stacks_predicate_on_block(
+    predicate_spec: &StacksChainhookInstance,
+    block: &StacksBlockData,
+    ctx: &Context,
+) -> Result<Vec<JsonValue>, String> {
+    let blocks: Vec<&dyn AbstractStacksBlock> = vec![block];
+    let (hits_per_blocks, _predicates_expired) = evaluate_stacks_chainhook_on_blocks(
+        blocks,
+        predicate_spec,
+        &BitcoinAnchorContext::empty(),
+        ctx,
+    );
+    if hits_per_blocks.is_empty() {
+        return Ok(vec![]);
+    }
+    let trigger = StacksTriggerChainhook {
+        chainhook: predicate_spec,
+        apply: hits_per_blocks,
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    serialize_stacks_payload_for_delivery(trigger, &HashMap::new(), ctx)
+        .map(|payload| vec![payload])
+}
@@ -0,0 +1,160 @@
+use chainhook_sdk::observer::{ObserverEvent, PredicateInterruptedData};
+use chainhook_sdk::types::{BitcoinChainEvent, BitcoinNetwork};
+use chainhook_sdk::utils::Context;
+
+use crate::config::ErrorReportingConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorReportLevel {
+    Error,
+    Fatal,
+}
+
+/// Error reported to the error-reporting backend, with the context it occurred in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorReport {
+    pub level: ErrorReportLevel,
+    pub message: String,
+    pub predicate_uuid: Option<String>,
+    /// Heights of the last blocks observed on each chain when the error was reported.
+    pub bitcoin_block_height: Option<u64>,
+    pub stacks_block_height: Option<u64>,
+}
+
+impl ErrorReport {
+    #[cfg(feature = "error-reporting")]
+    fn send(&self) {
+        let level = match self.level {
+            ErrorReportLevel::Error => sentry::Level::Error,
+            ErrorReportLevel::Fatal => sentry::Level::Fatal,
+        };
+        sentry::with_scope(
+            |scope| {
+                if let Some(ref uuid) = self.predicate_uuid {
+                    scope.set_tag("predicate_uuid", uuid);
+                }
+                if let Some(height) = self.bitcoin_block_height {
+                    scope.set_tag("bitcoin_block_height", height);
+                }
+                if let Some(height) = self.stacks_block_height {
+                    scope.set_tag("stacks_block_height", height);
+                }
+            },
+            || sentry::capture_message(&self.message, level),
+        );
+    }
+
+    #[cfg(not(feature = "error-reporting"))]
+    fn send(&self) {}
+}
+
+/// Reports the errors and fatal errors of the observer, as well as the predicates it
+/// interrupts, keeping track of the chain tips to give them context.
+#[derive(Default)]
+pub struct ErrorReporter {
+    bitcoin_block_height: Option<u64>,
+    stacks_block_height: Option<u64>,
+}
+
+impl ErrorReporter {
+    /// Returns the report of `event` if it is an error, after updating the chain tips the
+    /// reports are given in context.
+    pub fn report_of(&mut self, event: &ObserverEvent) -> Option<ErrorReport> {
+        let (level, message, predicate_uuid) = match event {
+            ObserverEvent::BitcoinChainEvent((chain_event, _)) => {
+                let new_tip = match chain_event {
+                    BitcoinChainEvent::ChainUpdatedWithBlocks(data) => data.new_blocks.last(),
+                    BitcoinChainEvent::ChainUpdatedWithReorg(data) => data.blocks_to_apply.last(),
+                };
+                if let Some(block) = new_tip {
+                    self.bitcoin_block_height = Some(block.block_identifier.index);
+                }
+                return None;
+            }
+            ObserverEvent::StacksChainEvent((chain_event, _)) => {
+                if let Some(block_identifier) = chain_event.get_latest_block_identifier() {
+                    self.stacks_block_height = Some(block_identifier.index);
+                }
+                return None;
+            }
+            ObserverEvent::Error(message) => (ErrorReportLevel::Error, message.clone(), None),
+            ObserverEvent::Fatal(message) => (ErrorReportLevel::Fatal, message.clone(), None),
+            ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
+                predicate_key,
+                error,
+            }) => {
+                // keys are either `predicate:<uuid>` or `predicate:<namespace>:<uuid>`
+                let uuid = predicate_key.rsplit(':').next().unwrap_or_default();
+                (
+                    ErrorReportLevel::Error,
+                    format!("Predicate {} interrupted: {}", uuid, error),
+                    Some(uuid.to_string()),
+                )
+            }
+            _ => return None,
+        };
+        Some(ErrorReport {
+            level,
+            message,
+            predicate_uuid,
+            bitcoin_block_height: self.bitcoin_block_height,
+            stacks_block_height: self.stacks_block_height,
+        })
+    }
+
+    /// Sends the report of `event` to the error-reporting backend, if it is an error.
+    pub fn capture(&mut self, event: &ObserverEvent) {
+        if let Some(report) = self.report_of(event) {
+            report.send();
+        }
+    }
+}
+
+/// Keeps the client reporting errors alive, flushing the pending reports once dropped.
+pub struct ErrorReportingGuard {
+    #[cfg(feature = "error-reporting")]
+    _client: sentry::ClientInitGuard,
+}
+
+/// Starts reporting panics, and the errors captured by the [ErrorReporter] of the services, to
+/// the configured backend. Reporting requires a build with the `error-reporting` feature.
+pub fn init_error_reporting(
+    config: &ErrorReportingConfig,
+    network: &BitcoinNetwork,
+    ctx: &Context,
+) -> Option<ErrorReportingGuard> {
+    #[cfg(feature = "error-reporting")]
+    {
+        let dsn = match config.dsn.parse::<sentry::types::Dsn>() {
+            Ok(dsn) => dsn,
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to start error reporting, invalid dsn: {}", e
+                );
+                return None;
+            }
+        };
+        let environment = config
+            .environment
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", network).to_lowercase());
+        let client = sentry::init(sentry::ClientOptions {
+            dsn: Some(dsn),
+            environment: Some(environment.into()),
+            release: sentry::release_name!(),
+            ..Default::default()
+        });
+        info!(ctx.expect_logger(), "Reporting panics and errors");
+        Some(ErrorReportingGuard { _client: client })
+    }
+    #[cfg(not(feature = "error-reporting"))]
+    {
+        let _ = (config, network);
+        warn!(
+            ctx.expect_logger(),
+            "Errors are not reported: chainhook was built without the error-reporting feature"
+        );
+        None
+    }
+}
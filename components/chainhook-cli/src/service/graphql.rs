@@ -0,0 +1,258 @@
+use chainhook_sdk::chainhooks::types::ChainhookInstance;
+use chainhook_sdk::observer::{DeliveryHistory, DeliveryRecord};
+use juniper::{
+    graphql_object, EmptyMutation, EmptySubscription, FieldResult, GraphQLObject, RootNode,
+};
+
+use super::http_api::{PredicatesQuery, RegisteredPredicate};
+use super::PredicateStatus;
+
+/// Schema of the GraphQL endpoint of the predicates API, read-only.
+pub type PredicatesSchema = RootNode<
+    'static,
+    Query,
+    EmptyMutation<PredicatesGraphQLContext>,
+    EmptySubscription<PredicatesGraphQLContext>,
+>;
+
+pub fn build_predicates_schema() -> PredicatesSchema {
+    PredicatesSchema::new(Query, EmptyMutation::new(), EmptySubscription::new())
+}
+
+/// Data a GraphQL query is resolved against: the predicates of the namespace of the request,
+/// sorted by registration time, and the recent deliveries of each predicate.
+pub struct PredicatesGraphQLContext {
+    pub predicates: Vec<RegisteredPredicate>,
+    pub delivery_history: DeliveryHistory,
+}
+
+impl juniper::Context for PredicatesGraphQLContext {}
+
+/// GraphQL only has 32 bits integers, larger values are capped.
+fn to_int(value: u64) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+pub struct Query;
+
+#[graphql_object(context = PredicatesGraphQLContext)]
+impl Query {
+    /// Registered predicates, filtered like the ones listed by `GET /v1/chainhooks`.
+    fn predicates(
+        context: &PredicatesGraphQLContext,
+        chain: Option<String>,
+        status: Option<String>,
+        label: Option<String>,
+        metadata: Option<String>,
+    ) -> FieldResult<Vec<Predicate>> {
+        let metadata = match metadata.as_deref().map(|m| m.split_once(':')) {
+            None => None,
+            Some(Some((key, value))) => Some((key.to_string(), value.to_string())),
+            Some(None) => return Err("invalid metadata filter, expected key:value".into()),
+        };
+        let query = PredicatesQuery {
+            chain,
+            status,
+            label,
+            metadata,
+            ..Default::default()
+        };
+        let predicates = context
+            .predicates
+            .iter()
+            .filter(|(predicate, status, _)| query.matches(predicate, status))
+            .cloned()
+            .map(Predicate)
+            .collect();
+        Ok(predicates)
+    }
+
+    fn predicate(context: &PredicatesGraphQLContext, uuid: String) -> Option<Predicate> {
+        context
+            .predicates
+            .iter()
+            .find(|(predicate, _, _)| predicate.uuid() == uuid)
+            .cloned()
+            .map(Predicate)
+    }
+}
+
+pub struct Predicate(RegisteredPredicate);
+
+#[graphql_object(context = PredicatesGraphQLContext)]
+impl Predicate {
+    fn uuid(&self) -> &str {
+        self.0 .0.uuid()
+    }
+
+    fn name(&self) -> &str {
+        match &self.0 .0 {
+            ChainhookInstance::Bitcoin(spec) => &spec.name,
+            ChainhookInstance::Stacks(spec) => &spec.name,
+        }
+    }
+
+    fn chain(&self) -> &str {
+        match &self.0 .0 {
+            ChainhookInstance::Bitcoin(_) => "bitcoin",
+            ChainhookInstance::Stacks(_) => "stacks",
+        }
+    }
+
+    fn network(&self) -> &str {
+        match &self.0 .0 {
+            ChainhookInstance::Bitcoin(spec) => spec.network.as_str(),
+            ChainhookInstance::Stacks(spec) => spec.network.as_str(),
+        }
+    }
+
+    fn namespace(&self) -> &str {
+        self.0 .0.namespace()
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.0 .0.labels().to_vec()
+    }
+
+    fn metadata(&self) -> Vec<MetadataEntry> {
+        let Some(metadata) = self.0 .0.metadata() else {
+            return vec![];
+        };
+        metadata
+            .iter()
+            .map(|(key, value)| MetadataEntry {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect()
+    }
+
+    fn enabled(&self) -> bool {
+        self.0 .0.enabled()
+    }
+
+    /// Unix timestamp, in seconds, at which the predicate was registered, if recorded.
+    fn registered_at(&self) -> Option<f64> {
+        self.0 .2.map(|registered_at| registered_at as f64)
+    }
+
+    fn status(&self) -> PredicateStatusView {
+        PredicateStatusView::from(&self.0 .1)
+    }
+
+    /// Most recent deliveries of the predicate, most recent first.
+    fn deliveries(&self, context: &PredicatesGraphQLContext, limit: Option<i32>) -> Vec<Delivery> {
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        context
+            .delivery_history
+            .get(self.0 .0.uuid())
+            .into_iter()
+            .take(limit)
+            .map(Delivery::from)
+            .collect()
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// Status of a predicate, with the progress of its scan or its streaming.
+#[derive(GraphQLObject)]
+#[graphql(name = "PredicateStatus")]
+pub struct PredicateStatusView {
+    /// Type of the status, e.g. `scanning` or `streaming`.
+    pub r#type: String,
+    /// Why the predicate was interrupted or paused.
+    pub reason: Option<String>,
+    pub number_of_blocks_to_scan: Option<i32>,
+    pub number_of_blocks_evaluated: Option<i32>,
+    pub number_of_times_triggered: Option<i32>,
+    pub last_occurrence: Option<f64>,
+    pub last_evaluated_block_height: Option<i32>,
+    pub expired_at_block_height: Option<i32>,
+}
+
+impl From<&PredicateStatus> for PredicateStatusView {
+    fn from(status: &PredicateStatus) -> Self {
+        let mut view = PredicateStatusView {
+            r#type: json!(status)["type"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            reason: None,
+            number_of_blocks_to_scan: None,
+            number_of_blocks_evaluated: None,
+            number_of_times_triggered: None,
+            last_occurrence: None,
+            last_evaluated_block_height: None,
+            expired_at_block_height: None,
+        };
+        match status {
+            PredicateStatus::Scanning(data) => {
+                view.number_of_blocks_to_scan = Some(to_int(data.number_of_blocks_to_scan));
+                view.number_of_blocks_evaluated = Some(to_int(data.number_of_blocks_evaluated));
+                view.number_of_times_triggered = Some(to_int(data.number_of_times_triggered));
+                view.last_occurrence = data.last_occurrence.map(|t| t as f64);
+                view.last_evaluated_block_height = Some(to_int(data.last_evaluated_block_height));
+            }
+            PredicateStatus::Streaming(data) => {
+                view.number_of_blocks_evaluated = Some(to_int(data.number_of_blocks_evaluated));
+                view.number_of_times_triggered = Some(to_int(data.number_of_times_triggered));
+                view.last_occurrence = data.last_occurrence.map(|t| t as f64);
+                view.last_evaluated_block_height = Some(to_int(data.last_evaluated_block_height));
+            }
+            PredicateStatus::UnconfirmedExpiration(data)
+            | PredicateStatus::ConfirmedExpiration(data) => {
+                view.number_of_blocks_evaluated = Some(to_int(data.number_of_blocks_evaluated));
+                view.number_of_times_triggered = Some(to_int(data.number_of_times_triggered));
+                view.last_occurrence = data.last_occurrence.map(|t| t as f64);
+                view.last_evaluated_block_height = Some(to_int(data.last_evaluated_block_height));
+                view.expired_at_block_height = Some(to_int(data.expired_at_block_height));
+            }
+            PredicateStatus::Paused(data) => {
+                view.reason = Some(data.reason.clone());
+                view.number_of_blocks_evaluated = Some(to_int(data.number_of_blocks_evaluated));
+                view.number_of_times_triggered = Some(to_int(data.number_of_times_triggered));
+                view.last_occurrence = data.last_occurrence.map(|t| t as f64);
+                view.last_evaluated_block_height = Some(to_int(data.last_evaluated_block_height));
+            }
+            PredicateStatus::Interrupted(reason) => {
+                view.reason = Some(reason.clone());
+            }
+            PredicateStatus::New => {}
+        }
+        view
+    }
+}
+
+/// Outcome of a delivery of an occurrence, see [DeliveryRecord].
+#[derive(GraphQLObject)]
+pub struct Delivery {
+    /// Unix timestamp, in seconds, at which the delivery completed or gave up.
+    pub delivered_at: f64,
+    pub delivery_id: String,
+    pub target: String,
+    pub success: bool,
+    pub http_status: Option<i32>,
+    pub latency_ms: f64,
+    pub payload_size: i32,
+    pub error: Option<String>,
+}
+
+impl From<DeliveryRecord> for Delivery {
+    fn from(record: DeliveryRecord) -> Self {
+        Delivery {
+            delivered_at: record.delivered_at as f64,
+            delivery_id: record.delivery_id,
+            target: record.target,
+            success: record.success,
+            http_status: record.http_status.map(i32::from),
+            latency_ms: record.latency_ms as f64,
+            payload_size: to_int(record.payload_size as u64),
+            error: record.error,
+        }
+    }
+}
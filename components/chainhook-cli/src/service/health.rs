@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use chainhook_sdk::observer::DependencyHealthCheck;
+use chainhook_sdk::utils::Context;
+
+use crate::config::{PredicatesApiConfig, StacksDbBackend};
+use crate::storage::open_readonly_stacks_db_conn;
+
+use super::open_readwrite_predicates_db_conn;
+use super::predicates_db::{get_predicates_db_name, PredicatesDb};
+
+/// Checks that the predicates database answers to a `PING`, or a `SELECT 1` for Postgres. The
+/// embedded db only has to open.
+pub struct PredicatesDbHealthCheck {
+    config: PredicatesApiConfig,
+}
+
+impl PredicatesDbHealthCheck {
+    pub fn new(config: &PredicatesApiConfig) -> Self {
+        PredicatesDbHealthCheck {
+            config: config.clone(),
+        }
+    }
+}
+
+impl DependencyHealthCheck for PredicatesDbHealthCheck {
+    fn name(&self) -> String {
+        get_predicates_db_name(&self.config.database_uri).into()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let mut predicates_db_conn = open_readwrite_predicates_db_conn(&self.config)?;
+        predicates_db_conn
+            .ping()
+            .map_err(|e| format!("unable to ping db: {}", e))
+    }
+}
+
+/// Checks that the Stacks database can be opened.
+pub struct StacksDbHealthCheck {
+    cache_path: PathBuf,
+    backend: StacksDbBackend,
+    ctx: Context,
+}
+
+impl StacksDbHealthCheck {
+    pub fn new(cache_path: PathBuf, backend: &StacksDbBackend, ctx: &Context) -> Self {
+        StacksDbHealthCheck {
+            cache_path,
+            backend: backend.clone(),
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+impl DependencyHealthCheck for StacksDbHealthCheck {
+    fn name(&self) -> String {
+        match self.backend {
+            StacksDbBackend::RocksDb => "rocksdb".into(),
+            StacksDbBackend::Sqlite => "sqlite".into(),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        open_readonly_stacks_db_conn(&self.cache_path, &self.backend, &self.ctx).map(|_| ())
+    }
+}
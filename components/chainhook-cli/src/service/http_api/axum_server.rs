@@ -0,0 +1,808 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, OnceLock};
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::ws::{Message, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, FromRequestParts, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{async_trait, Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use chainhook_sdk::{
+    chainhooks::types::ChainhookSpecificationNetworkMap,
+    observer::{DeliveryHistory, ObserverCommand, OccurrenceStreams, StreamedOccurrence},
+    utils::{rustls_server_config, Context},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use hiro_system_kit::slog;
+use juniper::http::{GraphQLBatchRequest, GraphQLResponse};
+use juniper::DefaultScalarValue;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::config::{ApiKeyScope, Config as ChainhookConfig, CorsConfig, PredicatesApiConfig};
+use crate::service::api_rate_limit::RateLimitDecision;
+use crate::service::dry_run::DryRunRequest;
+
+use super::{
+    log_skipped_occurrences, Actor, ListPredicatesParams, Namespace, PredicateApiShutdown,
+    PredicatePatch, PredicatesApiState, RescanRequest,
+};
+
+/// Launches the predicates API on axum, serving the same routes as the Rocket server, over
+/// HTTPS when `api_config.tls` is set.
+pub async fn start_predicate_api_server(
+    api_config: PredicatesApiConfig,
+    config: ChainhookConfig,
+    observer_commands_tx: SyncSender<ObserverCommand>,
+    occurrence_streams: OccurrenceStreams,
+    delivery_history: DeliveryHistory,
+    ctx: Context,
+) -> Result<PredicateApiShutdown, Box<dyn Error + Send + Sync>> {
+    let tls_config = match api_config.tls {
+        Some(ref tls) => Some(RustlsConfig::from_config(rustls_server_config(tls)?)),
+        None => None,
+    };
+    let http_port = api_config.http_port;
+    let cors = api_config.cors.clone();
+    let state = PredicatesApiState::new(
+        api_config,
+        config,
+        observer_commands_tx,
+        occurrence_streams,
+        delivery_history,
+        ctx,
+    );
+    let rate_limited = state.rate_limiter.is_some();
+
+    let mut router = Router::new()
+        .route("/ping", get(handle_ping))
+        .route("/v1/openapi.json", get(handle_get_openapi_spec))
+        .route("/v1/schemas/:name", get(handle_get_json_schema))
+        .route("/v1/graphql", post(handle_graphql))
+        .route(
+            "/v1/chainhooks",
+            get(handle_get_predicates).post(handle_create_predicate),
+        )
+        .route("/v1/chainhooks/dry_run", post(handle_dry_run_predicate))
+        .route("/v1/chainhooks/check", post(handle_check_predicate))
+        .route(
+            "/v1/chainhooks/:predicate_uuid",
+            get(handle_get_predicate)
+                .put(handle_update_predicate)
+                .patch(handle_patch_predicate),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/deliveries",
+            get(handle_get_predicate_deliveries),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/deliveries/:delivery_id/redeliver",
+            post(handle_redeliver_predicate_occurrence),
+        )
+        .route(
+            "/v1/chainhooks/stacks/:predicate_uuid",
+            delete(handle_delete_stacks_predicate),
+        )
+        .route(
+            "/v1/chainhooks/bitcoin/:predicate_uuid",
+            delete(handle_delete_bitcoin_predicate),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/pause",
+            post(handle_pause_predicate),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/resume",
+            post(handle_resume_predicate),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/rescan",
+            post(handle_rescan_predicate),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/stream",
+            get(handle_stream_predicate_occurrences),
+        )
+        .route(
+            "/v1/chainhooks/:predicate_uuid/events",
+            get(handle_predicate_occurrences_events),
+        )
+        .route("/v1/audit_log", get(handle_get_audit_log))
+        .with_state(state);
+    if rate_limited {
+        router = router.layer(middleware::from_fn(add_rate_limit_headers));
+    }
+    if let Some(cors) = cors {
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(cors),
+            add_cors_headers,
+        ));
+    }
+
+    // Binding before spawning the server surfaces the port being unavailable to the caller.
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], http_port)))?;
+    listener.set_nonblocking(true)?;
+    let handle = Handle::new();
+    let server_handle = handle.clone();
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+    let _ = std::thread::spawn(move || {
+        let _ = hiro_system_kit::nestable_block_on(async move {
+            match tls_config {
+                Some(tls_config) => {
+                    axum_server::from_tcp_rustls(listener, tls_config)
+                        .handle(server_handle)
+                        .serve(service)
+                        .await
+                }
+                None => {
+                    axum_server::from_tcp(listener)
+                        .handle(server_handle)
+                        .serve(service)
+                        .await
+                }
+            }
+        });
+    });
+    Ok(PredicateApiShutdown::new(move || {
+        handle.graceful_shutdown(None)
+    }))
+}
+
+/// Route of the predicates API described by the openapi spec, like the ones the Rocket server
+/// documents with `#[openapi]`. Websocket upgrades, event streams, graphql and the schemas
+/// are left out.
+struct DocumentedRoute {
+    method: &'static str,
+    path: &'static str,
+    operation_id: &'static str,
+    tag: &'static str,
+    query_params: Vec<(&'static str, JsonValue)>,
+    request_body: Option<JsonValue>,
+}
+
+impl DocumentedRoute {
+    fn new(method: &'static str, path: &'static str, operation_id: &'static str) -> Self {
+        DocumentedRoute {
+            method,
+            path,
+            operation_id,
+            tag: "Managing Predicates",
+            query_params: vec![],
+            request_body: None,
+        }
+    }
+
+    fn tag(mut self, tag: &'static str) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    fn query_param<T: JsonSchema>(mut self, name: &'static str, gen: &mut SchemaGenerator) -> Self {
+        self.query_params
+            .push((name, json!(gen.subschema_for::<T>())));
+        self
+    }
+
+    fn request_body<T: JsonSchema>(mut self, gen: &mut SchemaGenerator) -> Self {
+        self.request_body = Some(json!(gen.subschema_for::<T>()));
+        self
+    }
+
+    /// Request body of any JSON value, documented with an empty schema.
+    fn any_request_body(mut self) -> Self {
+        self.request_body = Some(json!({}));
+        self
+    }
+
+    fn operation(&self) -> JsonValue {
+        let mut parameters = vec![];
+        for segment in self.path.split('/') {
+            if let Some(name) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                parameters.push(json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                }));
+            }
+        }
+        for (name, schema) in self.query_params.iter() {
+            parameters.push(json!({ "name": name, "in": "query", "schema": schema }));
+        }
+        let mut operation = json!({
+            "tags": [self.tag],
+            "operationId": self.operation_id,
+            "responses": {
+                "200": {
+                    "description": "",
+                    "content": { "application/json": { "schema": {} } },
+                },
+            },
+        });
+        if !parameters.is_empty() {
+            operation["parameters"] = json!(parameters);
+        }
+        if let Some(ref schema) = self.request_body {
+            operation["requestBody"] = json!({
+                "content": { "application/json": { "schema": schema } },
+                "required": true,
+            });
+        }
+        operation
+    }
+}
+
+fn build_openapi_spec() -> JsonValue {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let predicate_path = "/v1/chainhooks/{predicate_uuid}";
+    let routes = vec![
+        DocumentedRoute::new("get", "/ping", "handle_ping").tag("Health Check"),
+        DocumentedRoute::new("get", "/v1/chainhooks", "handle_get_predicates")
+            .query_param::<String>("chain", &mut gen)
+            .query_param::<String>("status", &mut gen)
+            .query_param::<String>("label", &mut gen)
+            .query_param::<String>("metadata", &mut gen)
+            .query_param::<String>("order", &mut gen)
+            .query_param::<usize>("offset", &mut gen)
+            .query_param::<usize>("limit", &mut gen),
+        DocumentedRoute::new("get", predicate_path, "handle_get_predicate"),
+        DocumentedRoute::new(
+            "get",
+            "/v1/chainhooks/{predicate_uuid}/deliveries",
+            "handle_get_predicate_deliveries",
+        ),
+        DocumentedRoute::new("get", "/v1/audit_log", "handle_get_audit_log")
+            .query_param::<String>("predicate_uuid", &mut gen)
+            .query_param::<usize>("limit", &mut gen),
+        DocumentedRoute::new(
+            "post",
+            "/v1/chainhooks/{predicate_uuid}/deliveries/{delivery_id}/redeliver",
+            "handle_redeliver_predicate_occurrence",
+        ),
+        DocumentedRoute::new("post", "/v1/chainhooks", "handle_create_predicate")
+            .request_body::<ChainhookSpecificationNetworkMap>(&mut gen),
+        DocumentedRoute::new("post", "/v1/chainhooks/dry_run", "handle_dry_run_predicate")
+            .request_body::<DryRunRequest>(&mut gen),
+        DocumentedRoute::new("post", "/v1/chainhooks/check", "handle_check_predicate")
+            .any_request_body(),
+        DocumentedRoute::new("put", predicate_path, "handle_update_predicate")
+            .request_body::<ChainhookSpecificationNetworkMap>(&mut gen),
+        DocumentedRoute::new("patch", predicate_path, "handle_patch_predicate")
+            .request_body::<PredicatePatch>(&mut gen),
+        DocumentedRoute::new(
+            "delete",
+            "/v1/chainhooks/bitcoin/{predicate_uuid}",
+            "handle_delete_bitcoin_predicate",
+        ),
+        DocumentedRoute::new(
+            "delete",
+            "/v1/chainhooks/stacks/{predicate_uuid}",
+            "handle_delete_stacks_predicate",
+        ),
+        DocumentedRoute::new(
+            "post",
+            "/v1/chainhooks/{predicate_uuid}/pause",
+            "handle_pause_predicate",
+        ),
+        DocumentedRoute::new(
+            "post",
+            "/v1/chainhooks/{predicate_uuid}/resume",
+            "handle_resume_predicate",
+        ),
+        DocumentedRoute::new(
+            "post",
+            "/v1/chainhooks/{predicate_uuid}/rescan",
+            "handle_rescan_predicate",
+        )
+        .request_body::<RescanRequest>(&mut gen),
+    ];
+
+    let mut paths = serde_json::Map::new();
+    for route in routes.iter() {
+        let path = paths.entry(route.path).or_insert_with(|| json!({}));
+        path[route.method] = route.operation();
+    }
+    json!({
+        "openapi": "3.0.0",
+        "info": {
+            "title": "chainhook",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.take_definitions(),
+        },
+    })
+}
+
+/// Openapi spec of the predicates API, generated once from [DocumentedRoute]s since the axum
+/// routes don't describe themselves.
+fn openapi_spec() -> &'static JsonValue {
+    static OPENAPI_SPEC: OnceLock<JsonValue> = OnceLock::new();
+    OPENAPI_SPEC.get_or_init(build_openapi_spec)
+}
+
+pub fn document_predicate_api_server() -> Result<String, String> {
+    serde_json::to_string_pretty(openapi_spec())
+        .map_err(|e| format!("failed to serialize openapi spec: {}", e))
+}
+
+fn status_code(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn authorization(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Ip address of the client, read from the `X-Real-IP` header first, like Rocket does.
+fn client_ip(parts: &Parts) -> Option<IpAddr> {
+    parts
+        .headers
+        .get("X-Real-IP")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|ip| ip.trim().parse().ok())
+        .or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+}
+
+/// Rate limit decision of a request, accounted once by its access extractor and read back by
+/// [add_rate_limit_headers].
+#[derive(Clone, Default)]
+struct RateLimitSlot(Arc<OnceLock<Option<RateLimitDecision>>>);
+
+fn authorize(
+    parts: &Parts,
+    state: &PredicatesApiState,
+    scope: ApiKeyScope,
+) -> Result<(), StatusCode> {
+    let authorization = authorization(parts);
+    let rate_limit = || {
+        let account = || state.rate_limit(authorization, client_ip(parts));
+        match parts.extensions.get::<RateLimitSlot>() {
+            Some(slot) => *slot.0.get_or_init(account),
+            None => account(),
+        }
+    };
+    state
+        .authorize(scope, authorization, rate_limit)
+        .map_err(status_code)
+}
+
+/// Extractor of the routes reading predicates, see [ApiKeyScope::Read].
+struct ReadAccess;
+
+/// Extractor of the routes managing predicates, see [ApiKeyScope::Manage].
+struct ManageAccess;
+
+#[async_trait]
+impl FromRequestParts<PredicatesApiState> for ReadAccess {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &PredicatesApiState,
+    ) -> Result<Self, Self::Rejection> {
+        authorize(parts, state, ApiKeyScope::Read).map(|_| ReadAccess)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<PredicatesApiState> for ManageAccess {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &PredicatesApiState,
+    ) -> Result<Self, Self::Rejection> {
+        authorize(parts, state, ApiKeyScope::Manage).map(|_| ManageAccess)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<PredicatesApiState> for Namespace {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &PredicatesApiState,
+    ) -> Result<Self, Self::Rejection> {
+        let requested_namespace = parts
+            .headers
+            .get("Chainhook-Namespace")
+            .and_then(|value| value.to_str().ok());
+        state
+            .namespace(authorization(parts), requested_namespace)
+            .map_err(status_code)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<PredicatesApiState> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &PredicatesApiState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(state.actor(authorization(parts), client_ip(parts)))
+    }
+}
+
+/// Id of the last event received by a reconnecting `EventSource`.
+struct LastEventId(Option<u64>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LastEventId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let last_event_id = parts
+            .headers
+            .get("Last-Event-ID")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|id| id.trim().parse::<u64>().ok());
+        Ok(LastEventId(last_event_id))
+    }
+}
+
+/// Adds the `RateLimit-*` headers to the responses to the requests accounted by [authorize].
+async fn add_rate_limit_headers<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let slot = RateLimitSlot::default();
+    request.extensions_mut().insert(slot.clone());
+    let mut response = next.run(request).await;
+    let Some(decision) = slot.0.get().copied().flatten() else {
+        return response;
+    };
+    let reset_after = decision.reset_after.as_secs_f64().ceil() as u64;
+    let headers = response.headers_mut();
+    headers.insert("RateLimit-Limit", HeaderValue::from(decision.limit));
+    headers.insert("RateLimit-Remaining", HeaderValue::from(decision.remaining));
+    headers.insert("RateLimit-Reset", HeaderValue::from(reset_after));
+    if !decision.allowed {
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(reset_after));
+    }
+    response
+}
+
+/// Adds the CORS headers to the responses to the requests sent from an allowed origin, and
+/// answers the preflight requests browsers send before cross-origin requests.
+async fn add_cors_headers<B>(
+    State(cors): State<Arc<CorsConfig>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .map(|origin| origin.to_string());
+    let mut response = if request.method() == Method::OPTIONS {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        next.run(request).await
+    };
+    let Some(allowed_origin) = origin.as_deref().and_then(|o| cors.allowed_origin(o)) else {
+        return response;
+    };
+    let cors_headers = [
+        (
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            allowed_origin.to_string(),
+        ),
+        (header::VARY, "Origin".to_string()),
+        (
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            cors.allowed_methods.join(", "),
+        ),
+        (
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            cors.allowed_headers.join(", "),
+        ),
+    ];
+    for (name, value) in cors_headers {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+/// Occurrences received by a subscriber, until the predicate is deregistered.
+fn live_occurrences(
+    predicate_uuid: String,
+    occurrences: broadcast::Receiver<StreamedOccurrence>,
+    ctx: Context,
+) -> impl Stream<Item = StreamedOccurrence> {
+    stream::unfold(
+        (occurrences, predicate_uuid, ctx),
+        |(mut occurrences, predicate_uuid, ctx)| async move {
+            loop {
+                match occurrences.recv().await {
+                    Ok(occurrence) => {
+                        return Some((occurrence, (occurrences, predicate_uuid, ctx)))
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log_skipped_occurrences(&predicate_uuid, skipped, &ctx);
+                    }
+                    // The predicate was deregistered
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    )
+}
+
+async fn handle_ping(State(state): State<PredicatesApiState>) -> Json<JsonValue> {
+    Json(state.ping())
+}
+
+async fn handle_get_openapi_spec(State(state): State<PredicatesApiState>) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::info!(logger, "Handling HTTP GET /v1/openapi.json"));
+    Json(openapi_spec().clone())
+}
+
+async fn handle_get_json_schema(
+    State(state): State<PredicatesApiState>,
+    Path(name): Path<String>,
+) -> Result<Json<JsonValue>, StatusCode> {
+    state
+        .get_json_schema(&name)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn handle_graphql(
+    State(state): State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+    Json(request): Json<GraphQLBatchRequest>,
+) -> Response {
+    let context = match state.graphql_context(namespace) {
+        Ok(context) => context,
+        Err(e) => {
+            let response = GraphQLResponse::<DefaultScalarValue>::error(e.into());
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+    };
+    let response = request.execute(&state.schema, &context).await;
+    let status = if response.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    (status, Json(response)).into_response()
+}
+
+async fn handle_get_predicates(
+    State(state): State<PredicatesApiState>,
+    Query(params): Query<ListPredicatesParams>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_predicates(params, namespace))
+}
+
+async fn handle_create_predicate(
+    State(state): State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+    predicate: Result<Json<ChainhookSpecificationNetworkMap>, JsonRejection>,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(|Json(p)| p).map_err(|e| e.body_text());
+    Json(state.create_predicate(predicate, namespace, actor))
+}
+
+async fn handle_dry_run_predicate(
+    State(state): State<PredicatesApiState>,
+    _access: ReadAccess,
+    request: Result<Json<DryRunRequest>, JsonRejection>,
+) -> Json<JsonValue> {
+    let request = request.map(|Json(r)| r).map_err(|e| e.body_text());
+    Json(state.dry_run_predicate(request).await)
+}
+
+async fn handle_check_predicate(
+    State(state): State<PredicatesApiState>,
+    _access: ReadAccess,
+    predicate: Result<Json<JsonValue>, JsonRejection>,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(|Json(p)| p).map_err(|e| e.body_text());
+    Json(state.check_predicate(predicate))
+}
+
+async fn handle_get_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_predicate(&predicate_uuid, namespace))
+}
+
+async fn handle_get_predicate_deliveries(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_predicate_deliveries(&predicate_uuid, namespace))
+}
+
+/// Query parameters of `GET /v1/audit_log`.
+#[derive(Deserialize)]
+struct AuditLogParams {
+    predicate_uuid: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn handle_get_audit_log(
+    State(state): State<PredicatesApiState>,
+    Query(params): Query<AuditLogParams>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_audit_log(params.predicate_uuid.as_deref(), params.limit, namespace))
+}
+
+async fn handle_redeliver_predicate_occurrence(
+    State(state): State<PredicatesApiState>,
+    Path((predicate_uuid, delivery_id)): Path<(String, String)>,
+    _access: ManageAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(
+        state
+            .redeliver_predicate_occurrence(&predicate_uuid, &delivery_id, namespace)
+            .await,
+    )
+}
+
+async fn handle_update_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+    predicate: Result<Json<ChainhookSpecificationNetworkMap>, JsonRejection>,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(|Json(p)| p).map_err(|e| e.body_text());
+    Json(state.update_predicate(&predicate_uuid, predicate, namespace, actor))
+}
+
+async fn handle_patch_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+    patch: Result<Json<PredicatePatch>, JsonRejection>,
+) -> Json<JsonValue> {
+    let patch = patch.map(|Json(p)| p).map_err(|e| e.body_text());
+    Json(state.patch_predicate(&predicate_uuid, patch, namespace, actor))
+}
+
+async fn handle_delete_stacks_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.delete_stacks_predicate(&predicate_uuid, namespace, actor))
+}
+
+async fn handle_delete_bitcoin_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.delete_bitcoin_predicate(&predicate_uuid, namespace, actor))
+}
+
+async fn handle_pause_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.pause_predicate(&predicate_uuid, namespace, actor))
+}
+
+async fn handle_resume_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.resume_predicate(&predicate_uuid, namespace, actor))
+}
+
+async fn handle_rescan_predicate(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    request: Result<Json<RescanRequest>, JsonRejection>,
+) -> Json<JsonValue> {
+    let request = request.map(|Json(r)| r).map_err(|e| e.body_text());
+    Json(state.rescan_predicate(&predicate_uuid, request, namespace))
+}
+
+async fn handle_stream_predicate_occurrences(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    _access: ReadAccess,
+    namespace: Namespace,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let occurrences = state
+        .stream_predicate_occurrences(&predicate_uuid, namespace)
+        .map_err(status_code)?;
+    let occurrences = live_occurrences(predicate_uuid, occurrences, state.ctx.clone());
+    Ok(ws.on_upgrade(move |mut socket| async move {
+        futures_util::pin_mut!(occurrences);
+        while let Some(occurrence) = occurrences.next().await {
+            if socket
+                .send(Message::Text(occurrence.payload))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }))
+}
+
+async fn handle_predicate_occurrences_events(
+    State(state): State<PredicatesApiState>,
+    Path(predicate_uuid): Path<String>,
+    last_event_id: LastEventId,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Result<Response, StatusCode> {
+    let (missed_occurrences, occurrences) = state
+        .predicate_occurrences_events(&predicate_uuid, last_event_id.0, namespace)
+        .map_err(status_code)?;
+    let events = stream::iter(missed_occurrences)
+        .chain(live_occurrences(
+            predicate_uuid,
+            occurrences,
+            state.ctx.clone(),
+        ))
+        .map(|occurrence| {
+            Ok::<_, Infallible>(
+                Event::default()
+                    .data(occurrence.payload)
+                    .id(occurrence.id.to_string()),
+            )
+        });
+    Ok(Sse::new(events).into_response())
+}
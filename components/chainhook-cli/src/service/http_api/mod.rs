@@ -0,0 +1,1370 @@
+#[cfg(feature = "axum-ingestion")]
+mod axum_server;
+#[cfg(not(feature = "axum-ingestion"))]
+mod rocket_server;
+
+use std::{
+    net::IpAddr,
+    sync::{
+        mpsc::{SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use chainhook_sdk::{
+    chainhooks::diagnostics::check_predicate,
+    chainhooks::schemas::json_schema,
+    chainhooks::types::{
+        validate_namespace, ChainhookInstance, ChainhookSpecificationNetworkMap, DEFAULT_NAMESPACE,
+    },
+    observer::{
+        DeliveryHistory, DeliveryOutbox, DeliveryRecord, ObserverCommand, OccurrenceStreams,
+        RescanPredicateData, StreamedOccurrence,
+    },
+    utils::Context,
+};
+use hiro_system_kit::slog;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use tokio::sync::broadcast;
+
+use crate::config::{ApiKeyScope, Config as ChainhookConfig, PredicatesApiConfig};
+
+use super::api_rate_limit::{ApiRateLimiter, RateLimitDecision, RateLimitedClient};
+use super::audit::{get_predicate_audit_entries, record_predicate_audit_actor};
+use super::dry_run::{dry_run_predicate, DryRunRequest};
+use super::graphql::{build_predicates_schema, PredicatesGraphQLContext, PredicatesSchema};
+use super::outbox::PredicatesDbDeliveryOutbox;
+use super::predicates_db::{open_predicates_db, PredicatesDb};
+use super::{
+    is_predicate_uuid_in_use, open_readwrite_predicates_db_conn, retrieve_predicate_key,
+    retrieve_predicate_registration_time, PausedData, PredicateStatus,
+};
+
+#[cfg(feature = "axum-ingestion")]
+pub use self::axum_server::{document_predicate_api_server, start_predicate_api_server};
+#[cfg(not(feature = "axum-ingestion"))]
+pub use rocket_server::{document_predicate_api_server, start_predicate_api_server};
+
+/// Number of entries returned by `GET /v1/audit_log` when no limit is requested.
+const DEFAULT_AUDIT_LOG_LIMIT: usize = 100;
+
+/// Handle stopping the predicates API server, whichever HTTP server runs it.
+#[derive(Clone)]
+pub struct PredicateApiShutdown(Arc<dyn Fn() + Send + Sync>);
+
+impl PredicateApiShutdown {
+    pub fn new<F: Fn() + Send + Sync + 'static>(notify: F) -> Self {
+        PredicateApiShutdown(Arc::new(notify))
+    }
+
+    pub fn notify(&self) {
+        (self.0)()
+    }
+}
+
+/// Namespace of the predicates a request operates on: the one its api key is restricted to,
+/// or else the one selected by the `Chainhook-Namespace` header.
+struct Namespace(String);
+
+impl Namespace {
+    fn key(&self, predicate_uuid: &str) -> String {
+        ChainhookInstance::namespaced_key(Some(&self.0), predicate_uuid)
+    }
+
+    /// Assigns the namespace of the request to a predicate, which can only name it explicitly.
+    fn assign_to(&self, predicate: &mut ChainhookSpecificationNetworkMap) -> Result<(), String> {
+        let namespace = predicate.get_namespace();
+        if namespace != DEFAULT_NAMESPACE && namespace != self.0 {
+            return Err(format!(
+                "Predicate namespace {} doesn't match the namespace {} of the request",
+                namespace, self.0
+            ));
+        }
+        predicate.set_namespace(&self.0);
+        Ok(())
+    }
+}
+
+/// Who sends a request, as recorded in the audit log: the api key it carries, masked, or else
+/// the ip address of the client.
+struct Actor(String);
+
+impl Actor {
+    /// Records the actor of a request changing the status or the specification of a predicate,
+    /// for the change to be attributed to them in the audit log.
+    fn record_for(&self, predicate_key: &str, api_config: &PredicatesApiConfig, ctx: &Context) {
+        let res = open_readwrite_predicates_db_conn(api_config).and_then(|mut conn| {
+            record_predicate_audit_actor(predicate_key, &self.0, conn.as_mut())
+        });
+        if let Err(e) = res {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Predicate {predicate_key} change not attributed: {e}"
+                )
+            });
+        }
+    }
+}
+
+/// Query parameters of `GET /v1/chainhooks`.
+#[derive(Debug, Default, Deserialize)]
+struct ListPredicatesParams {
+    chain: Option<String>,
+    status: Option<String>,
+    label: Option<String>,
+    metadata: Option<String>,
+    order: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Body of `PATCH /v1/chainhooks/<predicate_uuid>`: the changes to the `if_this` and `then_that`
+/// of a predicate, merged into the registered ones as JSON merge patches (RFC 7386), `null`
+/// removing a field.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PredicatePatch {
+    pub if_this: Option<JsonValue>,
+    pub then_that: Option<JsonValue>,
+}
+
+/// Applies the JSON merge patch `patch` (RFC 7386) to `target`.
+pub fn merge_json_patch(target: &mut JsonValue, patch: JsonValue) {
+    let JsonValue::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let target = target.as_object_mut().expect("target is an object");
+    for (field, value) in patch {
+        if value.is_null() {
+            target.remove(&field);
+        } else {
+            merge_json_patch(target.entry(field).or_insert(JsonValue::Null), value);
+        }
+    }
+}
+
+/// Body of `POST /v1/chainhooks/<predicate_uuid>/rescan`: the range of blocks to evaluate the
+/// predicate against again, bounds included.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RescanRequest {
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
+/// State shared by the handlers of the predicates API. The logic of the routes lives here, the
+/// HTTP servers only decode the requests and encode the responses. Failures are reported in the
+/// `status` of the JSON responses, the status codes returned by the methods are the ones of the
+/// requests rejected before reaching a route.
+#[derive(Clone)]
+pub struct PredicatesApiState {
+    pub api_config: PredicatesApiConfig,
+    pub config: ChainhookConfig,
+    pub background_job_tx: Arc<Mutex<SyncSender<ObserverCommand>>>,
+    pub occurrence_streams: OccurrenceStreams,
+    pub delivery_history: DeliveryHistory,
+    pub schema: Arc<PredicatesSchema>,
+    /// Set when rate limits are configured.
+    pub rate_limiter: Option<Arc<ApiRateLimiter>>,
+    pub ctx: Context,
+}
+
+impl PredicatesApiState {
+    pub fn new(
+        api_config: PredicatesApiConfig,
+        config: ChainhookConfig,
+        observer_commands_tx: SyncSender<ObserverCommand>,
+        occurrence_streams: OccurrenceStreams,
+        delivery_history: DeliveryHistory,
+        ctx: Context,
+    ) -> Self {
+        let rate_limiter = api_config
+            .rate_limit
+            .clone()
+            .map(|rate_limit| Arc::new(ApiRateLimiter::new(rate_limit)));
+        PredicatesApiState {
+            api_config,
+            config,
+            background_job_tx: Arc::new(Mutex::new(observer_commands_tx)),
+            occurrence_streams,
+            delivery_history,
+            schema: Arc::new(build_predicates_schema()),
+            rate_limiter,
+            ctx,
+        }
+    }
+
+    /// Accounts a request to its client, when rate limits are configured. Returns None if the
+    /// client isn't rate limited. Servers account each request once.
+    fn rate_limit(
+        &self,
+        authorization: Option<&str>,
+        client_ip: Option<IpAddr>,
+    ) -> Option<RateLimitDecision> {
+        let rate_limiter = self.rate_limiter.as_ref()?;
+        let client = match self.api_config.api_key(authorization) {
+            Some(api_key) => RateLimitedClient::ApiKey(api_key.key.clone()),
+            None => RateLimitedClient::Ip(client_ip),
+        };
+        rate_limiter.check(client, Instant::now())
+    }
+
+    /// Rejects the requests not carrying an api key granting `scope`, when api keys are
+    /// configured, and the requests of clients exceeding their rate limit.
+    fn authorize(
+        &self,
+        scope: ApiKeyScope,
+        authorization: Option<&str>,
+        rate_limit: impl FnOnce() -> Option<RateLimitDecision>,
+    ) -> Result<(), u16> {
+        // predicates are managed through the primary, replicas only evaluate them
+        if scope == ApiKeyScope::Manage && self.config.is_replica() {
+            return Err(421);
+        }
+        if let Some(RateLimitDecision { allowed: false, .. }) = rate_limit() {
+            return Err(429);
+        }
+        match self.api_config.granted_scope(authorization) {
+            None => Err(401),
+            Some(granted_scope) if !granted_scope.includes(&scope) => Err(403),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn namespace(
+        &self,
+        authorization: Option<&str>,
+        requested_namespace: Option<&str>,
+    ) -> Result<Namespace, u16> {
+        let restricted_namespace = self.api_config.restricted_namespace(authorization);
+        match (restricted_namespace, requested_namespace) {
+            (Some(restricted), Some(requested)) if restricted != requested => Err(403),
+            (Some(namespace), _) => Ok(Namespace(namespace.to_string())),
+            (None, Some(namespace)) => match validate_namespace(namespace) {
+                Ok(()) => Ok(Namespace(namespace.to_string())),
+                Err(_) => Err(400),
+            },
+            (None, None) => Ok(Namespace(DEFAULT_NAMESPACE.to_string())),
+        }
+    }
+
+    fn actor(&self, authorization: Option<&str>, client_ip: Option<IpAddr>) -> Actor {
+        let actor = match (self.api_config.api_key(authorization), client_ip) {
+            (Some(api_key), _) => format!("api_key:{}", api_key.masked_key()),
+            (None, Some(ip)) => format!("ip:{}", ip),
+            (None, None) => "anonymous".to_string(),
+        };
+        Actor(actor)
+    }
+
+    fn ping(&self) -> JsonValue {
+        self.ctx
+            .try_log(|logger| slog::info!(logger, "Handling HTTP GET /ping"));
+        json!({
+            "status": 200,
+            "result": "chainhook service up and running",
+        })
+    }
+
+    /// Returns the JSON Schema `name`, either `predicate`, `bitcoin_payload` or `stacks_payload`.
+    fn get_json_schema(&self, name: &str) -> Option<JsonValue> {
+        self.ctx
+            .try_log(|logger| slog::info!(logger, "Handling HTTP GET /v1/schemas/{}", name));
+        json_schema(name)
+    }
+
+    /// Builds the context a GraphQL query is resolved against: the predicates of the namespace
+    /// of the request, see [super::graphql].
+    fn graphql_context(&self, namespace: Namespace) -> Result<PredicatesGraphQLContext, String> {
+        self.ctx
+            .try_log(|logger| slog::info!(logger, "Handling HTTP POST /v1/graphql"));
+        let query = PredicatesQuery {
+            namespace: Some(namespace.0),
+            ..Default::default()
+        };
+        let predicates =
+            open_readwrite_predicates_db_conn(&self.api_config).and_then(|mut conn| {
+                let predicates = get_entries_from_predicates_db(&mut conn, &self.ctx)?
+                    .into_iter()
+                    .filter(|(p, s)| query.matches(p, s))
+                    .map(|(p, s)| {
+                        let registered_at =
+                            retrieve_predicate_registration_time(&p.key(), &mut conn);
+                        (p, s, registered_at)
+                    })
+                    .collect::<Vec<_>>();
+                Ok(query.paginate(predicates).0)
+            });
+        let predicates = match predicates {
+            Ok(predicates) => predicates,
+            Err(e) => {
+                self.ctx
+                    .try_log(|logger| slog::warn!(logger, "unable to retrieve predicates: {e}"));
+                return Err("unable to retrieve predicates".into());
+            }
+        };
+        Ok(PredicatesGraphQLContext {
+            predicates,
+            delivery_history: self.delivery_history.clone(),
+        })
+    }
+
+    fn get_predicates(&self, params: ListPredicatesParams, namespace: Namespace) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| slog::info!(logger, "Handling HTTP GET /v1/chainhooks"));
+        let ListPredicatesParams {
+            chain,
+            status,
+            label,
+            metadata,
+            order,
+            offset,
+            limit,
+        } = params;
+        if let Some(ref chain) = chain {
+            if chain != "bitcoin" && chain != "stacks" {
+                return json!({
+                    "status": 400,
+                    "error": format!("invalid chain {}, expected bitcoin or stacks", chain),
+                });
+            }
+        }
+        let metadata = match metadata.as_deref().map(|m| m.split_once(':')) {
+            None => None,
+            Some(Some((key, value))) => Some((key.to_string(), value.to_string())),
+            Some(None) => {
+                return json!({
+                    "status": 400,
+                    "error": "invalid metadata filter, expected key:value",
+                })
+            }
+        };
+        let descending = match order.as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(order) => {
+                return json!({
+                    "status": 400,
+                    "error": format!("invalid order {}, expected asc or desc", order),
+                })
+            }
+        };
+        let query = PredicatesQuery {
+            namespace: Some(namespace.0),
+            chain,
+            status,
+            label,
+            metadata,
+            descending,
+            offset: offset.unwrap_or(0),
+            limit,
+        };
+
+        match open_readwrite_predicates_db_conn(&self.api_config) {
+            Ok(mut predicates_db_conn) => {
+                let predicates = match get_entries_from_predicates_db(&mut predicates_db_conn, ctx)
+                {
+                    Ok(predicates) => predicates,
+                    Err(e) => {
+                        ctx.try_log(|logger| {
+                            slog::warn!(logger, "unable to retrieve predicates: {e}")
+                        });
+                        return json!({
+                            "status": 500,
+                            "message": "unable to retrieve predicates",
+                        });
+                    }
+                };
+                let predicates = predicates
+                    .into_iter()
+                    .filter(|(p, s)| query.matches(p, s))
+                    .map(|(p, s)| {
+                        let registered_at =
+                            retrieve_predicate_registration_time(&p.key(), &mut predicates_db_conn);
+                        (p, s, registered_at)
+                    })
+                    .collect::<Vec<_>>();
+                let (page, total) = query.paginate(predicates);
+
+                let serialized_predicates = page
+                    .iter()
+                    .map(|(p, s, registered_at)| {
+                        let mut serialized_predicate = serialized_predicate_with_status(p, s);
+                        serialized_predicate["registered_at"] = json!(registered_at);
+                        serialized_predicate
+                    })
+                    .collect::<Vec<_>>();
+
+                json!({
+                    "status": 200,
+                    "result": serialized_predicates,
+                    "total": total,
+                })
+            }
+            Err(e) => json!({
+                "status": 500,
+                "message": e,
+            }),
+        }
+    }
+
+    fn create_predicate(
+        &self,
+        predicate: Result<ChainhookSpecificationNetworkMap, String>,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| slog::info!(logger, "Handling HTTP POST /v1/chainhooks"));
+        let predicate = match predicate {
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+            Ok(mut predicate) => {
+                if let Err(e) = predicate
+                    .validate()
+                    .and_then(|_| namespace.assign_to(&mut predicate))
+                {
+                    return json!({
+                        "status": 422,
+                        "error": e,
+                    });
+                }
+                predicate
+            }
+        };
+
+        let predicate_uuid = predicate.get_uuid().to_string();
+
+        if let Ok(mut predicates_db_conn) = open_readwrite_predicates_db_conn(&self.api_config) {
+            if is_predicate_uuid_in_use(&predicate_uuid, &mut predicates_db_conn, ctx) {
+                return json!({
+                    "status": 409,
+                    "error": "Predicate uuid already in use",
+                });
+            }
+        }
+
+        if let Err(response) =
+            self.send_observer_command(ObserverCommand::RegisterPredicate(predicate))
+        {
+            return response;
+        }
+        actor.record_for(&namespace.key(&predicate_uuid), &self.api_config, ctx);
+
+        json!({
+            "status": 200,
+            "result": predicate_uuid,
+        })
+    }
+
+    async fn dry_run_predicate(&self, request: Result<DryRunRequest, String>) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| slog::info!(logger, "Handling HTTP POST /v1/chainhooks/dry_run"));
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+        };
+        match dry_run_predicate(request, &self.config, ctx).await {
+            Ok(occurrences) => json!({
+                "status": 200,
+                "result": {
+                    "occurrences": occurrences,
+                },
+            }),
+            Err(e) => json!({
+                "status": 422,
+                "error": e,
+            }),
+        }
+    }
+
+    fn check_predicate(&self, predicate: Result<JsonValue, String>) -> JsonValue {
+        self.ctx
+            .try_log(|logger| slog::info!(logger, "Handling HTTP POST /v1/chainhooks/check"));
+        let predicate = match predicate {
+            Ok(predicate) => predicate,
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+        };
+        let diagnostics = check_predicate(
+            &predicate,
+            &self.config.network.bitcoin_network,
+            &self.config.network.stacks_network,
+        );
+        json!({
+            "status": 200,
+            "result": {
+                "valid": !diagnostics.iter().any(|d| d.is_error()),
+                "diagnostics": diagnostics,
+            },
+        })
+    }
+
+    fn get_predicate(&self, predicate_uuid: &str, namespace: Namespace) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP GET /v1/chainhooks/{}",
+                predicate_uuid
+            )
+        });
+
+        match open_readwrite_predicates_db_conn(&self.api_config) {
+            Ok(mut predicates_db_conn) => {
+                let (predicate, status) = match get_entry_from_predicates_db(
+                    &namespace.key(predicate_uuid),
+                    &mut predicates_db_conn,
+                    ctx,
+                ) {
+                    Ok(Some(predicate_with_status)) => predicate_with_status,
+                    _ => {
+                        return json!({
+                            "status": 404,
+                        })
+                    }
+                };
+                let result = serialized_predicate_with_status(&predicate, &status);
+                json!({
+                    "status": 200,
+                    "result": result
+                })
+            }
+            Err(e) => json!({
+                "status": 500,
+                "message": e,
+            }),
+        }
+    }
+
+    fn get_predicate_deliveries(&self, predicate_uuid: &str, namespace: Namespace) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP GET /v1/chainhooks/{}/deliveries",
+                predicate_uuid
+            )
+        });
+
+        match open_readwrite_predicates_db_conn(&self.api_config) {
+            Ok(mut predicates_db_conn) => {
+                match get_entry_from_predicates_db(
+                    &namespace.key(predicate_uuid),
+                    &mut predicates_db_conn,
+                    ctx,
+                ) {
+                    Ok(Some(_)) => {}
+                    _ => {
+                        return json!({
+                            "status": 404,
+                        })
+                    }
+                };
+                json!({
+                    "status": 200,
+                    "result": self.delivery_history.get(predicate_uuid)
+                })
+            }
+            Err(e) => json!({
+                "status": 500,
+                "message": e,
+            }),
+        }
+    }
+
+    fn get_audit_log(
+        &self,
+        predicate_uuid: Option<&str>,
+        limit: Option<usize>,
+        namespace: Namespace,
+    ) -> JsonValue {
+        self.ctx
+            .try_log(|logger| slog::info!(logger, "Handling HTTP GET /v1/audit_log"));
+
+        let entries = open_readwrite_predicates_db_conn(&self.api_config).and_then(|mut conn| {
+            get_predicate_audit_entries(
+                &namespace.0,
+                predicate_uuid,
+                limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT),
+                conn.as_mut(),
+            )
+        });
+        match entries {
+            Ok(entries) => json!({
+                "status": 200,
+                "result": entries,
+            }),
+            Err(e) => json!({
+                "status": 500,
+                "message": e,
+            }),
+        }
+    }
+
+    async fn redeliver_predicate_occurrence(
+        &self,
+        predicate_uuid: &str,
+        delivery_id: &str,
+        namespace: Namespace,
+    ) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP POST /v1/chainhooks/{}/deliveries/{}/redeliver",
+                predicate_uuid,
+                delivery_id
+            )
+        });
+
+        if let Err(response) = self.get_predicate_status(predicate_uuid, &namespace) {
+            return response;
+        }
+        let outbox = PredicatesDbDeliveryOutbox::new(&self.api_config);
+        let delivery = match outbox.logged_delivery(predicate_uuid, delivery_id) {
+            Ok(Some(delivery)) => delivery,
+            Ok(None) => {
+                return json!({
+                    "status": 404,
+                    "error": format!("Delivery {} not found", delivery_id),
+                })
+            }
+            Err(e) => {
+                return json!({
+                    "status": 500,
+                    "message": e,
+                })
+            }
+        };
+
+        // Redeliveries are not routed to the dead-letter sink of the predicate
+        let started_at = Instant::now();
+        let res = delivery
+            .deliver(&self.config.get_event_observer_config(), ctx)
+            .await;
+        let payload_size = serde_json::to_vec(&delivery.payload)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0);
+        let record = DeliveryRecord::new(
+            &delivery.id,
+            delivery.target(),
+            res.as_ref().err().cloned(),
+            None,
+            started_at.elapsed().as_millis() as u64,
+            payload_size,
+        );
+        self.delivery_history.record(predicate_uuid, record.clone());
+        match res {
+            Ok(_) => json!({
+                "status": 200,
+                "result": record,
+            }),
+            Err(e) => json!({
+                "status": 502,
+                "error": e,
+                "result": record,
+            }),
+        }
+    }
+
+    fn update_predicate(
+        &self,
+        predicate_uuid: &str,
+        predicate: Result<ChainhookSpecificationNetworkMap, String>,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP PUT /v1/chainhooks/{}",
+                predicate_uuid
+            )
+        });
+        self.replace_predicate(predicate_uuid, predicate, namespace, actor)
+    }
+
+    fn patch_predicate(
+        &self,
+        predicate_uuid: &str,
+        patch: Result<PredicatePatch, String>,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        let ctx = &self.ctx;
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP PATCH /v1/chainhooks/{}",
+                predicate_uuid
+            )
+        });
+        let patch = match patch {
+            Ok(patch) => patch,
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+        };
+        let registered_predicate = match open_readwrite_predicates_db_conn(&self.api_config) {
+            Ok(mut predicates_db_conn) => match get_entry_from_predicates_db(
+                &namespace.key(predicate_uuid),
+                &mut predicates_db_conn,
+                ctx,
+            ) {
+                Ok(Some((registered_predicate, _))) => registered_predicate,
+                _ => {
+                    return json!({
+                        "status": 404,
+                    })
+                }
+            },
+            Err(e) => {
+                return json!({
+                    "status": 500,
+                    "message": e,
+                })
+            }
+        };
+
+        let mut predicate = json!(registered_predicate.into_specification_network_map());
+        // the registered specification only holds the network the predicate is evaluated on
+        if let Some(JsonValue::Object(networks)) = predicate.get_mut("networks") {
+            for spec in networks.values_mut() {
+                if let Some(ref if_this) = patch.if_this {
+                    merge_json_patch(&mut spec["if_this"], if_this.clone());
+                }
+                if let Some(ref then_that) = patch.then_that {
+                    merge_json_patch(&mut spec["then_that"], then_that.clone());
+                }
+            }
+        }
+        let predicate = serde_json::from_value(predicate).map_err(|e| e.to_string());
+        self.replace_predicate(predicate_uuid, predicate, namespace, actor)
+    }
+
+    /// Replaces the specification of a registered predicate, keeping its status and scan
+    /// progress.
+    fn replace_predicate(
+        &self,
+        predicate_uuid: &str,
+        predicate: Result<ChainhookSpecificationNetworkMap, String>,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        let ctx = &self.ctx;
+        let predicate = match predicate {
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+            Ok(mut predicate) => {
+                if let Err(e) = predicate
+                    .validate()
+                    .and_then(|_| namespace.assign_to(&mut predicate))
+                {
+                    return json!({
+                        "status": 422,
+                        "error": e,
+                    });
+                }
+                predicate
+            }
+        };
+
+        if predicate.get_uuid() != predicate_uuid {
+            return json!({
+                "status": 422,
+                "error": "Predicate uuid can't be updated",
+            });
+        }
+
+        match open_readwrite_predicates_db_conn(&self.api_config) {
+            Ok(mut predicates_db_conn) => {
+                let registered_predicate = match get_entry_from_predicates_db(
+                    &namespace.key(predicate_uuid),
+                    &mut predicates_db_conn,
+                    ctx,
+                ) {
+                    Ok(Some((registered_predicate, _))) => registered_predicate,
+                    _ => {
+                        return json!({
+                            "status": 404,
+                        })
+                    }
+                };
+                let same_chain = matches!(
+                    (&registered_predicate, &predicate),
+                    (
+                        ChainhookInstance::Stacks(_),
+                        ChainhookSpecificationNetworkMap::Stacks(_)
+                    ) | (
+                        ChainhookInstance::Bitcoin(_),
+                        ChainhookSpecificationNetworkMap::Bitcoin(_)
+                    )
+                );
+                if !same_chain {
+                    return json!({
+                        "status": 422,
+                        "error": "Predicate chain can't be updated",
+                    });
+                }
+            }
+            Err(e) => {
+                return json!({
+                    "status": 500,
+                    "message": e,
+                })
+            }
+        }
+
+        if let Err(response) =
+            self.send_observer_command(ObserverCommand::UpdatePredicate(predicate))
+        {
+            return response;
+        }
+        actor.record_for(&namespace.key(predicate_uuid), &self.api_config, ctx);
+
+        json!({
+            "status": 200,
+            "result": predicate_uuid,
+        })
+    }
+
+    fn delete_stacks_predicate(
+        &self,
+        predicate_uuid: &str,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP DELETE /v1/chainhooks/stacks/{}",
+                predicate_uuid
+            )
+        });
+        self.delete_predicate(
+            predicate_uuid,
+            ObserverCommand::DeregisterStacksPredicate(predicate_uuid.to_string()),
+            namespace,
+            actor,
+        )
+    }
+
+    fn delete_bitcoin_predicate(
+        &self,
+        predicate_uuid: &str,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP DELETE /v1/chainhooks/bitcoin/{}",
+                predicate_uuid
+            )
+        });
+        self.delete_predicate(
+            predicate_uuid,
+            ObserverCommand::DeregisterBitcoinPredicate(predicate_uuid.to_string()),
+            namespace,
+            actor,
+        )
+    }
+
+    fn delete_predicate(
+        &self,
+        predicate_uuid: &str,
+        command: ObserverCommand,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        if let Err(response) =
+            self.ensure_predicate_not_in_other_namespace(predicate_uuid, &namespace)
+        {
+            return response;
+        }
+
+        if let Err(response) = self.send_observer_command(command) {
+            return response;
+        }
+        actor.record_for(&namespace.key(predicate_uuid), &self.api_config, &self.ctx);
+
+        json!({
+            "status": 200,
+            "result": "Ok",
+        })
+    }
+
+    fn pause_predicate(
+        &self,
+        predicate_uuid: &str,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP POST /v1/chainhooks/{}/pause",
+                predicate_uuid
+            )
+        });
+
+        match self.get_predicate_status(predicate_uuid, &namespace) {
+            // predicates paused by the circuit breaker can be paused until they are resumed
+            Ok(PredicateStatus::Streaming(_))
+            | Ok(PredicateStatus::Paused(PausedData {
+                requested: false, ..
+            })) => {}
+            Ok(PredicateStatus::Paused(_)) => {
+                return json!({
+                    "status": 200,
+                    "result": "Ok",
+                })
+            }
+            Ok(_) => {
+                return json!({
+                    "status": 409,
+                    "error": "Only streaming predicates can be paused",
+                })
+            }
+            Err(response) => return response,
+        }
+
+        if let Err(response) =
+            self.send_observer_command(ObserverCommand::PausePredicate(predicate_uuid.to_string()))
+        {
+            return response;
+        }
+        actor.record_for(&namespace.key(predicate_uuid), &self.api_config, &self.ctx);
+
+        json!({
+            "status": 200,
+            "result": "Ok",
+        })
+    }
+
+    fn resume_predicate(
+        &self,
+        predicate_uuid: &str,
+        namespace: Namespace,
+        actor: Actor,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP POST /v1/chainhooks/{}/resume",
+                predicate_uuid
+            )
+        });
+
+        match self.get_predicate_status(predicate_uuid, &namespace) {
+            Ok(PredicateStatus::Paused(_)) => {}
+            Ok(_) => {
+                return json!({
+                    "status": 409,
+                    "error": "Predicate is not paused",
+                })
+            }
+            Err(response) => return response,
+        }
+
+        if let Err(response) =
+            self.send_observer_command(ObserverCommand::ResumePredicate(predicate_uuid.to_string()))
+        {
+            return response;
+        }
+        actor.record_for(&namespace.key(predicate_uuid), &self.api_config, &self.ctx);
+
+        json!({
+            "status": 200,
+            "result": "Ok",
+        })
+    }
+
+    fn rescan_predicate(
+        &self,
+        predicate_uuid: &str,
+        request: Result<RescanRequest, String>,
+        namespace: Namespace,
+    ) -> JsonValue {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP POST /v1/chainhooks/{}/rescan",
+                predicate_uuid
+            )
+        });
+
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                return json!({
+                    "status": 422,
+                    "error": e,
+                })
+            }
+        };
+        if request.start_block > request.end_block {
+            return json!({
+                "status": 422,
+                "error": "start_block must not be greater than end_block",
+            });
+        }
+
+        if let Err(response) = self.get_predicate_status(predicate_uuid, &namespace) {
+            return response;
+        }
+
+        let command = ObserverCommand::RescanPredicate(RescanPredicateData {
+            predicate_uuid: predicate_uuid.to_string(),
+            start_block: request.start_block,
+            end_block: request.end_block,
+        });
+        if let Err(response) = self.send_observer_command(command) {
+            return response;
+        }
+
+        json!({
+            "status": 200,
+            "result": "Ok",
+        })
+    }
+
+    /// Subscribes to the occurrences of a predicate, streamed over a websocket.
+    fn stream_predicate_occurrences(
+        &self,
+        predicate_uuid: &str,
+        namespace: Namespace,
+    ) -> Result<broadcast::Receiver<StreamedOccurrence>, u16> {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP GET /v1/chainhooks/{}/stream",
+                predicate_uuid
+            )
+        });
+        self.ensure_predicate_exists(predicate_uuid, &namespace)?;
+        Ok(self.occurrence_streams.subscribe(predicate_uuid))
+    }
+
+    /// Subscribes to the occurrences of a predicate, streamed as server-sent events, along with
+    /// the buffered occurrences a reconnecting `EventSource` missed.
+    fn predicate_occurrences_events(
+        &self,
+        predicate_uuid: &str,
+        last_event_id: Option<u64>,
+        namespace: Namespace,
+    ) -> Result<
+        (
+            Vec<StreamedOccurrence>,
+            broadcast::Receiver<StreamedOccurrence>,
+        ),
+        u16,
+    > {
+        self.ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Handling HTTP GET /v1/chainhooks/{}/events",
+                predicate_uuid
+            )
+        });
+        self.ensure_predicate_exists(predicate_uuid, &namespace)?;
+        Ok(match last_event_id {
+            Some(last_id) => self
+                .occurrence_streams
+                .subscribe_after(predicate_uuid, last_id),
+            None => (vec![], self.occurrence_streams.subscribe(predicate_uuid)),
+        })
+    }
+
+    /// Forwards a command to the observer without blocking the server's workers, or returns the
+    /// response to send if the observer can't take it in.
+    fn send_observer_command(&self, command: ObserverCommand) -> Result<(), JsonValue> {
+        let error = match self.background_job_tx.lock() {
+            Ok(tx) => match tx.try_send(command) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(_)) => "Observer busy, retry later",
+                Err(TrySendError::Disconnected(_)) => "Observer unavailable",
+            },
+            Err(_) => "Observer unavailable",
+        };
+        Err(json!({
+            "status": 503,
+            "error": error,
+        }))
+    }
+
+    /// Retrieves the status of a predicate, or the response to send if it can't be retrieved.
+    fn get_predicate_status(
+        &self,
+        predicate_uuid: &str,
+        namespace: &Namespace,
+    ) -> Result<PredicateStatus, JsonValue> {
+        let mut predicates_db_conn =
+            open_readwrite_predicates_db_conn(&self.api_config).map_err(|e| {
+                json!({
+                    "status": 500,
+                    "message": e,
+                })
+            })?;
+        let predicate_key = namespace.key(predicate_uuid);
+        match get_entry_from_predicates_db(&predicate_key, &mut predicates_db_conn, &self.ctx) {
+            Ok(Some((_, status))) => Ok(status),
+            _ => Err(json!({
+                "status": 404,
+            })),
+        }
+    }
+
+    /// Rejects the requests targeting a predicate registered in another namespace, as if it
+    /// didn't exist.
+    fn ensure_predicate_not_in_other_namespace(
+        &self,
+        predicate_uuid: &str,
+        namespace: &Namespace,
+    ) -> Result<(), JsonValue> {
+        let mut predicates_db_conn =
+            open_readwrite_predicates_db_conn(&self.api_config).map_err(|e| {
+                json!({
+                    "status": 500,
+                    "message": e,
+                })
+            })?;
+        if retrieve_predicate_key(predicate_uuid, &mut predicates_db_conn)
+            != namespace.key(predicate_uuid)
+            && is_predicate_uuid_in_use(predicate_uuid, &mut predicates_db_conn, &self.ctx)
+        {
+            return Err(json!({
+                "status": 404,
+            }));
+        }
+        Ok(())
+    }
+
+    fn ensure_predicate_exists(
+        &self,
+        predicate_uuid: &str,
+        namespace: &Namespace,
+    ) -> Result<(), u16> {
+        let mut predicates_db_conn =
+            open_readwrite_predicates_db_conn(&self.api_config).map_err(|_| 500u16)?;
+        let predicate_key = namespace.key(predicate_uuid);
+        match get_entry_from_predicates_db(&predicate_key, &mut predicates_db_conn, &self.ctx) {
+            Ok(Some(_)) => Ok(()),
+            _ => Err(404),
+        }
+    }
+}
+
+fn log_skipped_occurrences(predicate_uuid: &str, skipped: u64, ctx: &Context) {
+    ctx.try_log(|logger| {
+        slog::warn!(
+            logger,
+            "Stream subscriber for predicate {} skipped {} occurrences",
+            predicate_uuid,
+            skipped
+        )
+    });
+}
+
+/// Filters, order and page of the predicates listed by `GET /v1/chainhooks`.
+#[derive(Debug, Default)]
+pub struct PredicatesQuery {
+    pub namespace: Option<String>,
+    pub chain: Option<String>,
+    /// Type of the status of the predicates, e.g. `streaming`.
+    pub status: Option<String>,
+    pub label: Option<String>,
+    /// Key and value of an entry the metadata of the predicates must contain.
+    pub metadata: Option<(String, String)>,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// A predicate, along with its status and the time it was registered at, if recorded.
+pub type RegisteredPredicate = (ChainhookInstance, PredicateStatus, Option<u64>);
+
+impl PredicatesQuery {
+    pub fn matches(&self, predicate: &ChainhookInstance, status: &PredicateStatus) -> bool {
+        let chain = match predicate {
+            ChainhookInstance::Bitcoin(_) => "bitcoin",
+            ChainhookInstance::Stacks(_) => "stacks",
+        };
+        if let Some(ref namespace) = self.namespace {
+            if namespace != predicate.namespace() {
+                return false;
+            }
+        }
+        if self.chain.as_ref().is_some_and(|c| c != chain) {
+            return false;
+        }
+        if let Some(ref status_type) = self.status {
+            if json!(status).get("type").and_then(|t| t.as_str()) != Some(status_type.as_str()) {
+                return false;
+            }
+        }
+        if let Some(ref label) = self.label {
+            if !predicate.labels().contains(label) {
+                return false;
+            }
+        }
+        if let Some((ref key, ref value)) = self.metadata {
+            if predicate.metadata().and_then(|m| m.get(key)) != Some(value) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Sorts `predicates` by registration time and returns the requested page, along with the
+    /// total number of predicates. Predicates registered before their registration time was
+    /// recorded come first.
+    pub fn paginate(
+        &self,
+        mut predicates: Vec<RegisteredPredicate>,
+    ) -> (Vec<RegisteredPredicate>, usize) {
+        predicates.sort_by(|(a, _, a_time), (b, _, b_time)| {
+            a_time.cmp(b_time).then_with(|| a.uuid().cmp(b.uuid()))
+        });
+        if self.descending {
+            predicates.reverse();
+        }
+        let total = predicates.len();
+        let page = predicates
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        (page, total)
+    }
+}
+
+pub fn get_entry_from_predicates_db(
+    predicate_key: &str,
+    predicate_db_conn: &mut dyn PredicatesDb,
+    _ctx: &Context,
+) -> Result<Option<(ChainhookInstance, PredicateStatus)>, String> {
+    let entry = predicate_db_conn.hgetall(predicate_key).map_err(|e| {
+        format!(
+            "unable to load chainhook associated with key {}: {}",
+            predicate_key,
+            e
+        )
+    })?;
+
+    let encoded_spec = match entry.get("specification") {
+        None => return Ok(None),
+        Some(payload) => payload,
+    };
+
+    let spec = ChainhookInstance::deserialize_specification(encoded_spec)?;
+
+    let encoded_status = match entry.get("status") {
+        None => Err(format!(
+            "found predicate specification with no status for predicate {}",
+            predicate_key
+        )),
+        Some(payload) => Ok(payload),
+    }?;
+
+    let status = serde_json::from_str(encoded_status).map_err(|e| format!("{}", e))?;
+
+    Ok(Some((spec, status)))
+}
+
+pub fn get_entries_from_predicates_db(
+    predicate_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) -> Result<Vec<(ChainhookInstance, PredicateStatus)>, String> {
+    let chainhooks_to_load = predicate_db_conn
+        .scan_keys(&ChainhookInstance::either_stx_or_btc_key("*"))
+        .map_err(|e| format!("unable to connect to predicates db: {}", e))?;
+
+    let mut predicates = vec![];
+    for predicate_key in chainhooks_to_load.iter() {
+        let chainhook = match get_entry_from_predicates_db(predicate_key, predicate_db_conn, ctx) {
+            Ok(Some((spec, status))) => (spec, status),
+            Ok(None) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "unable to load chainhook associated with key {}", predicate_key,
+                );
+                continue;
+            }
+            Err(e) => {
+                error!(
+                    ctx.expect_logger(),
+                    "unable to load chainhook associated with key {}: {}",
+                    predicate_key,
+                    e.to_string()
+                );
+                continue;
+            }
+        };
+        predicates.push(chainhook);
+    }
+    Ok(predicates)
+}
+
+pub fn load_predicates_from_db(
+    config: &crate::config::Config,
+    ctx: &Context,
+) -> Result<Vec<(ChainhookInstance, PredicateStatus)>, String> {
+    let mut predicate_db_conn = open_predicates_db(config.expected_api_database_uri())?;
+    get_entries_from_predicates_db(&mut predicate_db_conn, ctx)
+}
+
+fn serialized_predicate_with_status(
+    predicate: &ChainhookInstance,
+    status: &PredicateStatus,
+) -> JsonValue {
+    match (predicate, status) {
+        (ChainhookInstance::Stacks(spec), status) => json!({
+            "chain": "stacks",
+            "uuid": spec.uuid,
+            "labels": spec.labels,
+            "metadata": spec.metadata,
+            "network": spec.network,
+            "predicate": spec.predicate,
+            "status": status,
+            "enabled": spec.enabled,
+        }),
+        (ChainhookInstance::Bitcoin(spec), status) => json!({
+            "chain": "bitcoin",
+            "uuid": spec.uuid,
+            "labels": spec.labels,
+            "metadata": spec.metadata,
+            "network": spec.network,
+            "predicate": spec.predicate,
+            "status": status,
+            "enabled": spec.enabled,
+        }),
+    }
+}
@@ -0,0 +1,664 @@
+use std::{
+    error::Error,
+    net::{IpAddr, Ipv4Addr},
+    sync::mpsc::SyncSender,
+};
+
+use chainhook_sdk::{
+    chainhooks::types::ChainhookSpecificationNetworkMap,
+    observer::{DeliveryHistory, ObserverCommand, OccurrenceStreams},
+    utils::{rocket_tls_config, Context},
+};
+use hiro_system_kit::slog;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::{Json, Value as JsonValue};
+use rocket::State;
+use rocket::{
+    config::{self, Config, LogLevel},
+    Response,
+};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use rocket_okapi::{okapi::openapi3::OpenApi, openapi, openapi_get_routes_spec};
+use rocket_ws::{Message, WebSocket};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::{ApiKeyScope, Config as ChainhookConfig, CorsConfig, PredicatesApiConfig};
+use crate::service::api_rate_limit::RateLimitDecision;
+use crate::service::dry_run::DryRunRequest;
+
+use super::{
+    log_skipped_occurrences, Actor, ListPredicatesParams, Namespace, PredicateApiShutdown,
+    PredicatePatch, PredicatesApiState, RescanRequest,
+};
+
+/// Request guard of the routes reading predicates, see [ApiKeyScope::Read].
+struct ReadAccess;
+
+/// Request guard of the routes managing predicates, see [ApiKeyScope::Manage].
+struct ManageAccess;
+
+/// Accounts a request to its client, once, when rate limits are configured. Returns None if the
+/// client isn't rate limited.
+fn rate_limit(request: &Request<'_>) -> Option<RateLimitDecision> {
+    *request.local_cache(|| {
+        let state = request.rocket().state::<PredicatesApiState>()?;
+        state.rate_limit(
+            request.headers().get_one("Authorization"),
+            request.client_ip(),
+        )
+    })
+}
+
+async fn authorize(request: &Request<'_>, scope: ApiKeyScope) -> request::Outcome<(), ()> {
+    let state = try_outcome!(request.guard::<&State<PredicatesApiState>>().await);
+    let authorization = request.headers().get_one("Authorization");
+    match state.authorize(scope, authorization, || rate_limit(request)) {
+        Ok(()) => request::Outcome::Success(()),
+        Err(code) => request::Outcome::Error((Status::new(code), ())),
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ReadAccess {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        authorize(request, ApiKeyScope::Read)
+            .await
+            .map(|_| ReadAccess)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ManageAccess {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        authorize(request, ApiKeyScope::Manage)
+            .await
+            .map(|_| ManageAccess)
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for ReadAccess {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for ManageAccess {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Namespace {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let state = try_outcome!(request.guard::<&State<PredicatesApiState>>().await);
+        let authorization = request.headers().get_one("Authorization");
+        let requested_namespace = request.headers().get_one("Chainhook-Namespace");
+        match state.namespace(authorization, requested_namespace) {
+            Ok(namespace) => request::Outcome::Success(namespace),
+            Err(code) => request::Outcome::Error((Status::new(code), ())),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Namespace {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Actor {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let state = try_outcome!(request.guard::<&State<PredicatesApiState>>().await);
+        let authorization = request.headers().get_one("Authorization");
+        request::Outcome::Success(state.actor(authorization, request.client_ip()))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Actor {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// Adds the CORS headers to the responses to the requests sent from an allowed origin.
+struct Cors(CorsConfig);
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(origin) = request.headers().get_one("Origin") else {
+            return;
+        };
+        let Some(allowed_origin) = self.0.allowed_origin(origin) else {
+            return;
+        };
+        response.set_raw_header("Access-Control-Allow-Origin", allowed_origin.to_string());
+        response.set_raw_header("Vary", "Origin");
+        response.set_raw_header(
+            "Access-Control-Allow-Methods",
+            self.0.allowed_methods.join(", "),
+        );
+        response.set_raw_header(
+            "Access-Control-Allow-Headers",
+            self.0.allowed_headers.join(", "),
+        );
+    }
+}
+
+/// Adds the `RateLimit-*` headers to the responses to the requests accounted by [rate_limit].
+struct RateLimitHeaders;
+
+#[rocket::async_trait]
+impl Fairing for RateLimitHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limit headers",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(decision) = *request.local_cache(|| None::<RateLimitDecision>) else {
+            return;
+        };
+        let reset_after = decision.reset_after.as_secs_f64().ceil() as u64;
+        response.set_raw_header("RateLimit-Limit", decision.limit.to_string());
+        response.set_raw_header("RateLimit-Remaining", decision.remaining.to_string());
+        response.set_raw_header("RateLimit-Reset", reset_after.to_string());
+        if !decision.allowed {
+            response.set_raw_header("Retry-After", reset_after.to_string());
+        }
+    }
+}
+
+pub async fn start_predicate_api_server(
+    api_config: PredicatesApiConfig,
+    config: ChainhookConfig,
+    observer_commands_tx: SyncSender<ObserverCommand>,
+    occurrence_streams: OccurrenceStreams,
+    delivery_history: DeliveryHistory,
+    ctx: Context,
+) -> Result<PredicateApiShutdown, Box<dyn Error + Send + Sync>> {
+    let log_level = LogLevel::Off;
+
+    let mut shutdown_config = config::Shutdown::default();
+    shutdown_config.ctrlc = false;
+    shutdown_config.grace = 1;
+    shutdown_config.mercy = 1;
+
+    let control_config = Config {
+        port: api_config.http_port,
+        workers: 1,
+        address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        keep_alive: 5,
+        temp_dir: std::env::temp_dir().into(),
+        log_level,
+        cli_colors: false,
+        shutdown: shutdown_config,
+        tls: api_config.tls.as_ref().map(rocket_tls_config),
+        ..Config::default()
+    };
+
+    let (routes, openapi_spec) = get_routes_spec();
+
+    let cors = api_config.cors.clone();
+    let state = PredicatesApiState::new(
+        api_config,
+        config,
+        observer_commands_tx,
+        occurrence_streams,
+        delivery_history,
+        ctx,
+    );
+    let rate_limited = state.rate_limiter.is_some();
+
+    let mut predicate_api = rocket::custom(control_config)
+        .manage(state)
+        .manage(openapi_spec)
+        .mount("/", routes)
+        .mount(
+            "/",
+            routes![
+                handle_get_openapi_spec,
+                handle_get_json_schema,
+                handle_graphql,
+                handle_stream_predicate_occurrences,
+                handle_predicate_occurrences_events
+            ],
+        );
+    if let Some(cors) = cors {
+        predicate_api = predicate_api
+            .attach(Cors(cors))
+            .mount("/", routes![handle_cors_preflight]);
+    }
+    if rate_limited {
+        predicate_api = predicate_api.attach(RateLimitHeaders);
+    }
+    let ignite = predicate_api.ignite().await?;
+
+    let predicate_api_shutdown = ignite.shutdown();
+
+    let _ = std::thread::spawn(move || {
+        let _ = hiro_system_kit::nestable_block_on(ignite.launch());
+    });
+    Ok(PredicateApiShutdown::new(move || {
+        predicate_api_shutdown.clone().notify()
+    }))
+}
+
+#[openapi(tag = "Health Check")]
+#[get("/ping")]
+fn handle_ping(state: &State<PredicatesApiState>) -> Json<JsonValue> {
+    Json(state.ping())
+}
+
+// The spec describes the routes generated by `get_routes_spec`, which can't include this one.
+#[get("/v1/openapi.json")]
+fn handle_get_openapi_spec(
+    openapi_spec: &State<OpenApi>,
+    state: &State<PredicatesApiState>,
+) -> Json<OpenApi> {
+    state
+        .ctx
+        .try_log(|logger| slog::info!(logger, "Handling HTTP GET /v1/openapi.json"));
+    Json(openapi_spec.inner().clone())
+}
+
+#[get("/v1/schemas/<name>")]
+fn handle_get_json_schema(
+    name: &str,
+    state: &State<PredicatesApiState>,
+) -> Option<Json<JsonValue>> {
+    state.get_json_schema(name).map(Json)
+}
+
+/// Answers the preflight requests browsers send before cross-origin requests, the CORS headers
+/// being added by [Cors].
+#[options("/<_..>")]
+fn handle_cors_preflight() -> Status {
+    Status::NoContent
+}
+
+#[post("/v1/graphql", data = "<request>")]
+async fn handle_graphql(
+    request: juniper_rocket::GraphQLRequest,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> juniper_rocket::GraphQLResponse {
+    match state.graphql_context(namespace) {
+        Ok(context) => request.execute(&state.schema, &context).await,
+        Err(e) => juniper_rocket::GraphQLResponse::error(e.into()),
+    }
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[get(
+    "/v1/chainhooks?<chain>&<status>&<label>&<metadata>&<order>&<offset>&<limit>",
+    format = "application/json"
+)]
+#[allow(clippy::too_many_arguments)]
+fn handle_get_predicates(
+    chain: Option<String>,
+    status: Option<String>,
+    label: Option<String>,
+    metadata: Option<String>,
+    order: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    let params = ListPredicatesParams {
+        chain,
+        status,
+        label,
+        metadata,
+        order,
+        offset,
+        limit,
+    };
+    Json(state.get_predicates(params, namespace))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post("/v1/chainhooks", format = "application/json", data = "<predicate>")]
+fn handle_create_predicate(
+    predicate: Result<Json<ChainhookSpecificationNetworkMap>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.create_predicate(predicate, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post(
+    "/v1/chainhooks/dry_run",
+    format = "application/json",
+    data = "<request>"
+)]
+async fn handle_dry_run_predicate(
+    request: Result<Json<DryRunRequest>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+) -> Json<JsonValue> {
+    let request = request.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.dry_run_predicate(request).await)
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post(
+    "/v1/chainhooks/check",
+    format = "application/json",
+    data = "<predicate>"
+)]
+fn handle_check_predicate(
+    predicate: Result<Json<JsonValue>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.check_predicate(predicate))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[get("/v1/chainhooks/<predicate_uuid>", format = "application/json")]
+fn handle_get_predicate(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_predicate(&predicate_uuid, namespace))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[get(
+    "/v1/chainhooks/<predicate_uuid>/deliveries",
+    format = "application/json"
+)]
+fn handle_get_predicate_deliveries(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_predicate_deliveries(&predicate_uuid, namespace))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[get("/v1/audit_log?<predicate_uuid>&<limit>", format = "application/json")]
+fn handle_get_audit_log(
+    predicate_uuid: Option<String>,
+    limit: Option<usize>,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(state.get_audit_log(predicate_uuid.as_deref(), limit, namespace))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post(
+    "/v1/chainhooks/<predicate_uuid>/deliveries/<delivery_id>/redeliver",
+    format = "application/json"
+)]
+async fn handle_redeliver_predicate_occurrence(
+    predicate_uuid: String,
+    delivery_id: String,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    Json(
+        state
+            .redeliver_predicate_occurrence(&predicate_uuid, &delivery_id, namespace)
+            .await,
+    )
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[put(
+    "/v1/chainhooks/<predicate_uuid>",
+    format = "application/json",
+    data = "<predicate>"
+)]
+fn handle_update_predicate(
+    predicate_uuid: String,
+    predicate: Result<Json<ChainhookSpecificationNetworkMap>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    let predicate = predicate.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.update_predicate(&predicate_uuid, predicate, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[patch(
+    "/v1/chainhooks/<predicate_uuid>",
+    format = "application/json",
+    data = "<patch>"
+)]
+fn handle_patch_predicate(
+    predicate_uuid: String,
+    patch: Result<Json<PredicatePatch>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    let patch = patch.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.patch_predicate(&predicate_uuid, patch, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[delete("/v1/chainhooks/stacks/<predicate_uuid>", format = "application/json")]
+fn handle_delete_stacks_predicate(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.delete_stacks_predicate(&predicate_uuid, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[delete("/v1/chainhooks/bitcoin/<predicate_uuid>", format = "application/json")]
+fn handle_delete_bitcoin_predicate(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.delete_bitcoin_predicate(&predicate_uuid, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post("/v1/chainhooks/<predicate_uuid>/pause", format = "application/json")]
+fn handle_pause_predicate(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.pause_predicate(&predicate_uuid, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post("/v1/chainhooks/<predicate_uuid>/resume", format = "application/json")]
+fn handle_resume_predicate(
+    predicate_uuid: String,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+    actor: Actor,
+) -> Json<JsonValue> {
+    Json(state.resume_predicate(&predicate_uuid, namespace, actor))
+}
+
+#[openapi(tag = "Managing Predicates")]
+#[post(
+    "/v1/chainhooks/<predicate_uuid>/rescan",
+    format = "application/json",
+    data = "<request>"
+)]
+fn handle_rescan_predicate(
+    predicate_uuid: String,
+    request: Result<Json<RescanRequest>, rocket::serde::json::Error>,
+    state: &State<PredicatesApiState>,
+    _access: ManageAccess,
+    namespace: Namespace,
+) -> Json<JsonValue> {
+    let request = request.map(Json::into_inner).map_err(|e| e.to_string());
+    Json(state.rescan_predicate(&predicate_uuid, request, namespace))
+}
+
+// Websocket upgrades and event streams can't be described by the openapi spec, so these
+// routes are mounted separately.
+#[get("/v1/chainhooks/<predicate_uuid>/stream")]
+fn handle_stream_predicate_occurrences(
+    predicate_uuid: String,
+    ws: WebSocket,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Result<rocket_ws::Stream!['static], Status> {
+    let mut occurrences = state
+        .stream_predicate_occurrences(&predicate_uuid, namespace)
+        .map_err(Status::new)?;
+    let ctx = state.ctx.clone();
+    Ok(rocket_ws::Stream! { ws =>
+        let _ = ws;
+        loop {
+            match occurrences.recv().await {
+                Ok(occurrence) => yield Message::Text(occurrence.payload),
+                Err(RecvError::Lagged(skipped)) => {
+                    log_skipped_occurrences(&predicate_uuid, skipped, &ctx);
+                }
+                // The predicate was deregistered
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Id of the last event received by a reconnecting `EventSource`.
+struct LastEventId(Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let last_event_id = request
+            .headers()
+            .get_one("Last-Event-ID")
+            .and_then(|id| id.trim().parse::<u64>().ok());
+        request::Outcome::Success(LastEventId(last_event_id))
+    }
+}
+
+#[get("/v1/chainhooks/<predicate_uuid>/events")]
+fn handle_predicate_occurrences_events(
+    predicate_uuid: String,
+    last_event_id: LastEventId,
+    state: &State<PredicatesApiState>,
+    _access: ReadAccess,
+    namespace: Namespace,
+) -> Result<EventStream![Event + 'static], Status> {
+    let (missed_occurrences, mut occurrences) = state
+        .predicate_occurrences_events(&predicate_uuid, last_event_id.0, namespace)
+        .map_err(Status::new)?;
+    let ctx = state.ctx.clone();
+    Ok(EventStream! {
+        for occurrence in missed_occurrences {
+            yield Event::data(occurrence.payload).id(occurrence.id.to_string());
+        }
+        loop {
+            match occurrences.recv().await {
+                Ok(occurrence) => {
+                    yield Event::data(occurrence.payload).id(occurrence.id.to_string())
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log_skipped_occurrences(&predicate_uuid, skipped, &ctx);
+                }
+                // The predicate was deregistered
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+pub fn document_predicate_api_server() -> Result<String, String> {
+    let (_, spec) = get_routes_spec();
+    let json_spec = serde_json::to_string_pretty(&spec)
+        .map_err(|e| format!("failed to serialize openapi spec: {}", e))?;
+    Ok(json_spec)
+}
+
+pub fn get_routes_spec() -> (Vec<rocket::Route>, OpenApi) {
+    openapi_get_routes_spec![
+        handle_ping,
+        handle_get_predicates,
+        handle_get_predicate,
+        handle_get_predicate_deliveries,
+        handle_get_audit_log,
+        handle_redeliver_predicate_occurrence,
+        handle_create_predicate,
+        handle_dry_run_predicate,
+        handle_check_predicate,
+        handle_update_predicate,
+        handle_patch_predicate,
+        handle_delete_bitcoin_predicate,
+        handle_delete_stacks_predicate,
+        handle_pause_predicate,
+        handle_resume_predicate,
+        handle_rescan_predicate
+    ]
+}
@@ -0,0 +1,218 @@
+use std::sync::mpsc::SyncSender;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chainhook_sdk::observer::ObserverCommand;
+use chainhook_sdk::utils::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, PredicatesApiConfig};
+
+use super::http_api::get_entry_from_predicates_db;
+use super::predicates_db::PredicatesDb;
+use super::{open_readwrite_predicates_db_conn, retrieve_predicate_key, PredicateStatus};
+
+/// Channel of the predicates db the lifecycle events of the predicates are published to.
+pub const PREDICATE_LIFECYCLE_CHANNEL: &str = "chainhook:predicate_lifecycle";
+
+/// Attempts made to deliver an event to the lifecycle webhook before dropping it.
+const LIFECYCLE_WEBHOOK_ATTEMPTS: u64 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PredicateLifecycleEventType {
+    Registered,
+    Streaming,
+    Expired,
+    Interrupted,
+    Deregistered,
+}
+
+impl PredicateLifecycleEventType {
+    /// Returns the event notified when a predicate's status changes from `previous` to `status`,
+    /// if any. Updates keeping the predicate in the same stage of its lifecycle, e.g. a streaming
+    /// predicate evaluating a new block, are not notified.
+    pub fn from_transition(
+        previous: Option<&PredicateStatus>,
+        status: &PredicateStatus,
+    ) -> Option<Self> {
+        let event_type = Self::from_status(status)?;
+        if previous.and_then(Self::from_status) == Some(event_type) {
+            return None;
+        }
+        Some(event_type)
+    }
+
+    fn from_status(status: &PredicateStatus) -> Option<Self> {
+        match status {
+            PredicateStatus::New => Some(Self::Registered),
+            PredicateStatus::Streaming(_) => Some(Self::Streaming),
+            PredicateStatus::UnconfirmedExpiration(_) | PredicateStatus::ConfirmedExpiration(_) => {
+                Some(Self::Expired)
+            }
+            PredicateStatus::Interrupted(_) => Some(Self::Interrupted),
+            PredicateStatus::Scanning(_) | PredicateStatus::Paused(_) => None,
+        }
+    }
+}
+
+/// Body of the requests sent to the lifecycle webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PredicateLifecycleEvent {
+    pub uuid: String,
+    pub event: PredicateLifecycleEventType,
+    /// Status the predicate moved to, absent once deregistered.
+    pub status: Option<PredicateStatus>,
+    /// Unix timestamp, in seconds, of the transition.
+    pub timestamp: u64,
+}
+
+impl PredicateLifecycleEvent {
+    pub fn new(
+        predicate_key: &str,
+        event: PredicateLifecycleEventType,
+        status: Option<PredicateStatus>,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // Keys are either `predicate:<uuid>` or `predicate:<namespace>:<uuid>`
+        let uuid = predicate_key.rsplit(':').next().unwrap_or_default();
+        PredicateLifecycleEvent {
+            uuid: uuid.to_string(),
+            event,
+            status,
+            timestamp,
+        }
+    }
+}
+
+/// Publishes the lifecycle event of a predicate, for the lifecycle notifier to deliver it.
+/// Events published while no notifier is subscribed are dropped.
+pub fn publish_lifecycle_event(
+    event: &PredicateLifecycleEvent,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    let payload = json!(event).to_string();
+    if let Err(e) = predicates_db_conn.publish(PREDICATE_LIFECYCLE_CHANNEL, &payload) {
+        warn!(
+            ctx.expect_logger(),
+            "Unable to publish lifecycle event of predicate {}: {}", event.uuid, e
+        );
+    }
+}
+
+/// Delivers the lifecycle events of the predicates to `webhook_url`, as they are published.
+/// Runs until the predicates database becomes unreachable.
+pub fn start_lifecycle_notifier(
+    api_config: &PredicatesApiConfig,
+    webhook_url: &str,
+    ctx: &Context,
+) -> Result<(), String> {
+    let http_client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("unable to build http client: {e}"))?;
+    let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config)?;
+    predicates_db_conn
+        .subscribe(PREDICATE_LIFECYCLE_CHANNEL, &mut |payload| {
+            deliver_lifecycle_event(&http_client, webhook_url, payload, ctx)
+        })
+        .map_err(|e| format!("unable to receive predicate lifecycle events: {e}"))
+}
+
+fn deliver_lifecycle_event(
+    http_client: &reqwest::blocking::Client,
+    webhook_url: &str,
+    payload: String,
+    ctx: &Context,
+) {
+    for attempt in 1..=LIFECYCLE_WEBHOOK_ATTEMPTS {
+        let res = http_client
+            .post(webhook_url)
+            .header("Content-Type", "application/json")
+            .body(payload.clone())
+            .send()
+            .and_then(|res| res.error_for_status());
+        match res {
+            Ok(_) => break,
+            Err(e) if attempt < LIFECYCLE_WEBHOOK_ATTEMPTS => {
+                debug!(
+                    ctx.expect_logger(),
+                    "Lifecycle webhook {webhook_url} failed (attempt {attempt}): {e}"
+                );
+                sleep(Duration::from_secs(attempt));
+            }
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to deliver lifecycle event to {webhook_url}, dropping it: {e}"
+                );
+            }
+        }
+    }
+}
+
+/// Keeps the predicates of a replica in sync with the ones managed by the primary: the
+/// predicates assigned to this replica are registered as the primary registers them, and
+/// deregistered as the primary deregisters them. Runs until the predicates database becomes
+/// unreachable.
+pub fn start_replica_predicates_follower(
+    config: &Config,
+    api_config: &PredicatesApiConfig,
+    observer_command_tx: &SyncSender<ObserverCommand>,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config)?;
+    let mut subscriber_conn = open_readwrite_predicates_db_conn(api_config)?;
+    subscriber_conn
+        .subscribe(PREDICATE_LIFECYCLE_CHANNEL, &mut |payload| {
+            let event: PredicateLifecycleEvent = match serde_json::from_str(&payload) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!(ctx.expect_logger(), "Unable to parse lifecycle event: {e}");
+                    return;
+                }
+            };
+            if !config.is_predicate_assigned(&event.uuid) {
+                return;
+            }
+            match event.event {
+                PredicateLifecycleEventType::Registered => {
+                    let predicate_key =
+                        retrieve_predicate_key(&event.uuid, predicates_db_conn.as_mut());
+                    match get_entry_from_predicates_db(
+                        &predicate_key,
+                        predicates_db_conn.as_mut(),
+                        ctx,
+                    ) {
+                        Ok(Some((predicate, _))) => {
+                            let _ = observer_command_tx
+                                .send(ObserverCommand::RegisterPredicateInstance(predicate));
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!(
+                                ctx.expect_logger(),
+                                "Unable to retrieve predicate {}: {e}", event.uuid
+                            );
+                        }
+                    }
+                }
+                // the chain of a deregistered predicate is no longer recorded
+                PredicateLifecycleEventType::Deregistered => {
+                    let _ = observer_command_tx.send(ObserverCommand::DeregisterStacksPredicate(
+                        event.uuid.clone(),
+                    ));
+                    let _ = observer_command_tx
+                        .send(ObserverCommand::DeregisterBitcoinPredicate(event.uuid));
+                }
+                PredicateLifecycleEventType::Streaming
+                | PredicateLifecycleEventType::Expired
+                | PredicateLifecycleEventType::Interrupted => {}
+            }
+        })
+        .map_err(|e| format!("unable to receive predicate lifecycle events: {e}"))
+}
@@ -1,57 +1,135 @@
+mod api_rate_limit;
+mod audit;
+mod block_cache;
+mod coordinator;
+pub(crate) mod dry_run;
+pub(crate) mod error_reporting;
+mod graphql;
+mod health;
 pub(crate) mod http_api;
+mod lifecycle;
+mod occurrences;
+mod outbox;
+pub(crate) mod predicates_db;
+mod predicates_watcher;
+pub(crate) mod replay;
 mod runloops;
+pub(crate) mod startup;
+pub(crate) mod stats;
+mod systemd;
 
-use crate::config::{Config, PredicatesApi, PredicatesApiConfig};
+use crate::config::{Config, PredicatesApi, PredicatesApiConfig, StacksRetentionConfig};
 use crate::scan::stacks::consolidate_local_stacks_chainstate_using_csv;
-use crate::service::http_api::{load_predicates_from_redis, start_predicate_api_server};
+use crate::service::audit::{
+    log_predicate_transition, log_predicate_update, trim_predicate_audit_log,
+};
+use crate::service::block_cache::RocksDbBitcoinBlockSpillStore;
+use crate::service::coordinator::{
+    start_chain_events_follower, ChainEventsCoordinator, DistributedChainEvent,
+};
+use crate::service::error_reporting::ErrorReporter;
+use crate::service::health::{PredicatesDbHealthCheck, StacksDbHealthCheck};
+use crate::service::http_api::{load_predicates_from_db, start_predicate_api_server};
+use crate::service::lifecycle::{
+    publish_lifecycle_event, start_lifecycle_notifier, start_replica_predicates_follower,
+    PredicateLifecycleEvent, PredicateLifecycleEventType,
+};
+use crate::service::occurrences::PredicatesDbOccurrenceCountStore;
+use crate::service::outbox::{
+    delivery_log_key, PredicatesDbDeliveryOutbox, PredicatesDbDeliveryRecordStore,
+};
+use crate::service::predicates_watcher::{
+    start_predicates_dir_watcher, PredicateFileChange, PredicatesDirWatcher,
+};
 use crate::service::runloops::{start_bitcoin_scan_runloop, start_stacks_scan_runloop};
+use crate::storage::encryption::get_payload_cipher;
+use crate::storage::migrations::migrate_dbs;
 use crate::storage::{
-    confirm_entries_in_stacks_blocks, draft_entries_in_stacks_blocks, get_all_unconfirmed_blocks,
-    get_last_block_height_inserted, open_readonly_stacks_db_conn_with_retry,
-    open_readwrite_stacks_db_conn,
+    confirm_entries_in_stacks_blocks, draft_entries_in_stacks_blocks, find_stacks_db_divergence,
+    get_all_unconfirmed_blocks, get_last_block_height_inserted,
+    open_readonly_stacks_db_conn_with_retry, open_readwrite_stacks_db_conn, prune_stacks_blocks,
+    rollback_stacks_blocks,
 };
 
 use chainhook_sdk::chainhooks::types::{ChainhookSpecificationNetworkMap, ChainhookStore};
 
-use chainhook_sdk::chainhooks::types::ChainhookInstance;
+use chainhook_sdk::chainhooks::types::{ChainhookInstance, DEFAULT_NAMESPACE};
+use chainhook_sdk::indexer::bitcoin::build_http_client;
+use chainhook_sdk::indexer::stacks::retrieve_canonical_stacks_block_identifiers;
 use chainhook_sdk::observer::{
-    start_event_observer, HookExpirationData, ObserverCommand, ObserverEvent,
-    PredicateDeregisteredEvent, PredicateEvaluationReport, PredicateInterruptedData,
+    bounded_channel, start_event_observer, BitcoinBlockSpillStore, DeliveryHistory, DeliveryOutbox,
+    DependencyHealthCheck, DependencyHealthChecks, HookExpirationData, ObserverCommand,
+    ObserverDependencies, ObserverEvent, OccurrenceCountStore, OccurrenceStreams,
+    PredicateAcknowledgedData, PredicateDeliveryFailedData, PredicateDeregisteredEvent,
+    PredicateEvaluationReport, PredicateInterruptedData, PredicatePausedData,
     StacksObserverStartupContext,
 };
-use chainhook_sdk::types::{Chain, StacksBlockData, StacksChainEvent};
+use chainhook_sdk::types::{
+    BitcoinBlockSignaling, Chain, StacksBlockData, StacksBlockUpdate, StacksChainEvent,
+    StacksChainUpdatedWithReorgData,
+};
 use chainhook_sdk::utils::Context;
-use redis::{Commands, Connection};
+use crossbeam_channel::RecvTimeoutError;
 
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::VecDeque;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use self::http_api::get_entry_from_predicates_db;
+use self::predicates_db::{open_predicates_db, PredicatesDb};
 use self::runloops::{BitcoinScanOp, StacksScanOp};
+use self::systemd::SystemdNotifier;
 
 pub struct Service {
     config: Config,
     ctx: Context,
+    systemd_notifier: Option<SystemdNotifier>,
+    error_reporter: ErrorReporter,
 }
 
 impl Service {
     pub fn new(config: Config, ctx: Context) -> Self {
-        Self { config, ctx }
+        Self {
+            config,
+            ctx,
+            systemd_notifier: None,
+            error_reporter: ErrorReporter::default(),
+        }
+    }
+
+    /// Notifies systemd once the service is ready, and pings its watchdog from the event loop,
+    /// when started by a unit of `Type=notify`.
+    pub fn with_systemd_notifications(mut self) -> Self {
+        self.systemd_notifier = SystemdNotifier::from_env();
+        self
     }
 
     pub async fn run(
         &mut self,
-        predicates_from_startup: Vec<ChainhookSpecificationNetworkMap>,
-        observer_commands_tx_rx: Option<(Sender<ObserverCommand>, Receiver<ObserverCommand>)>,
+        mut predicates_from_startup: Vec<ChainhookSpecificationNetworkMap>,
+        observer_commands_tx_rx: Option<(SyncSender<ObserverCommand>, Receiver<ObserverCommand>)>,
     ) -> Result<(), String> {
+        // replicas leave the ingestion of the chains and the management of the predicates to the
+        // primary, they only read its storage
+        let is_replica = self.config.is_replica();
+        if is_replica && !predicates_from_startup.is_empty() {
+            return Err("predicates must be registered through the primary, not replicas".into());
+        }
+
+        // upgrade the layouts of the dbs written by previous releases before reading them
+        if !is_replica {
+            migrate_dbs(&self.config, &self.ctx)?;
+        }
+
         let mut chainhook_store = ChainhookStore::new();
 
         // store all predicates from Redis that were in the process of scanning when
         // chainhook was shutdown - we need to resume where we left off
         let mut leftover_scans = vec![];
         // retrieve predicates from Redis, and register each in memory
-        if self.config.is_http_api_enabled() {
-            let registered_predicates = match load_predicates_from_redis(&self.config, &self.ctx) {
+        if let PredicatesApi::On(ref api_config) = self.config.http_api {
+            let registered_predicates = match load_predicates_from_db(&self.config, &self.ctx) {
                 Ok(predicates) => predicates,
                 Err(e) => {
                     error!(
@@ -62,11 +140,28 @@ impl Service {
                     vec![]
                 }
             };
-            for (predicate, status) in registered_predicates.into_iter() {
+            let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config).ok();
+            for (mut predicate, status) in registered_predicates.into_iter() {
                 let predicate_uuid = predicate.uuid().to_string();
+                // predicates evaluated by other instances stay registered, to be managed through
+                // the API, but are never enabled by this one
+                if !self.config.is_predicate_assigned(&predicate_uuid) {
+                    match predicate {
+                        ChainhookInstance::Stacks(ref mut spec) => spec.enabled = false,
+                        ChainhookInstance::Bitcoin(ref mut spec) => spec.enabled = false,
+                    }
+                    let _ = chainhook_store.register_instance(predicate);
+                    continue;
+                }
                 match status {
                     PredicateStatus::Scanning(scanning_data) => {
-                        leftover_scans.push((predicate.clone(), Some(scanning_data)));
+                        let scanning_data = match predicates_db_conn {
+                            Some(ref mut conn) => {
+                                rewind_to_ack_cursor(&predicate, Some(scanning_data), conn)
+                            }
+                            None => Some(scanning_data),
+                        };
+                        leftover_scans.push((predicate.clone(), scanning_data));
                     }
                     PredicateStatus::New => {
                         leftover_scans.push((predicate.clone(), None));
@@ -81,7 +176,29 @@ impl Service {
                             last_occurrence: streaming_data.last_occurrence,
                             last_evaluated_block_height: streaming_data.last_evaluated_block_height,
                         };
-                        leftover_scans.push((predicate.clone(), Some(scanning_data)));
+                        let scanning_data = match predicates_db_conn {
+                            Some(ref mut conn) => {
+                                rewind_to_ack_cursor(&predicate, Some(scanning_data), conn)
+                            }
+                            None => Some(scanning_data),
+                        };
+                        leftover_scans.push((predicate.clone(), scanning_data));
+                    }
+                    // predicates paused through the API stay registered but disabled until
+                    // they are resumed
+                    PredicateStatus::Paused(PausedData {
+                        requested: true, ..
+                    }) => {}
+                    // paused predicates are scanned again from where they were paused, their
+                    // endpoint is probed again by this run
+                    PredicateStatus::Paused(paused_data) => {
+                        let scanning_data = match predicates_db_conn {
+                            Some(ref mut conn) => {
+                                rewind_to_ack_cursor(&predicate, Some(paused_data.into()), conn)
+                            }
+                            None => Some(paused_data.into()),
+                        };
+                        leftover_scans.push((predicate.clone(), scanning_data));
                     }
                     PredicateStatus::UnconfirmedExpiration(_) => {}
                     PredicateStatus::ConfirmedExpiration(_) | PredicateStatus::Interrupted(_) => {
@@ -108,17 +225,25 @@ impl Service {
             }
         }
 
+        // predicates found in the watched directory at startup are registered along with the
+        // ones passed on the command line, the next changes are sent to the observer
+        let predicates_dir_watcher = self.config.predicates_watch_dir.as_ref().map(|dir| {
+            let mut watcher = PredicatesDirWatcher::new(dir.clone());
+            for change in watcher.scan(&self.ctx).into_iter() {
+                if let PredicateFileChange::Added(predicate) = change {
+                    predicates_from_startup.push(predicate);
+                }
+            }
+            watcher
+        });
+
         let mut newly_registered_predicates = vec![];
         // For each predicate found, register in memory.
         for predicate in predicates_from_startup.into_iter() {
             if let PredicatesApi::On(api_config) = &self.config.http_api {
                 if let Ok(mut predicates_db_conn) = open_readwrite_predicates_db_conn(api_config) {
                     let uuid = predicate.get_uuid();
-                    if let Ok(Some(_)) = get_entry_from_predicates_db(
-                        &ChainhookInstance::either_stx_or_btc_key(uuid),
-                        &mut predicates_db_conn,
-                        &self.ctx,
-                    ) {
+                    if is_predicate_uuid_in_use(uuid, &mut predicates_db_conn, &self.ctx) {
                         warn!(
                             self.ctx.expect_logger(),
                             "Predicate uuid already in use: {uuid}",
@@ -152,21 +277,45 @@ impl Service {
             }
         }
 
-        let (observer_command_tx, observer_command_rx) =
-            observer_commands_tx_rx.unwrap_or(channel());
-        let (observer_event_tx, observer_event_rx) = crossbeam_channel::unbounded();
+        let (observer_command_tx, observer_command_rx) = observer_commands_tx_rx.unwrap_or(
+            sync_channel(self.config.limits.max_number_of_pending_observer_commands),
+        );
+        // This loop doesn't wait for room in the observer's commands channel, see
+        // send_pending_observer_commands, so that the observer can wait for room in this one
+        let (observer_event_tx, observer_event_rx) = bounded_channel(
+            self.config.limits.max_number_of_pending_observer_events,
+            self.config.limits.observer_events_full_policy,
+        );
+        let mut pending_observer_commands = VecDeque::new();
         // let (ordinal_indexer_command_tx, ordinal_indexer_command_rx) = channel();
 
-        let mut event_observer_config = self.config.get_event_observer_config();
-        event_observer_config.registered_chainhooks = chainhook_store;
+        let event_observer_config = self
+            .config
+            .event_observer_config_builder()
+            .registered_chainhooks(chainhook_store)
+            .finish()?;
+
+        // Blocks diverging from the canonical chain are rolled back before the archive is
+        // consolidated, so that the canonical ones can be imported from it
+        let mut last_stacks_db_verification: Option<Instant> = None;
+        let mut startup_stacks_reorg = None;
+        if let Some(depth) = self.config.storage.stacks_verification_depth {
+            if !is_replica {
+                last_stacks_db_verification = Some(Instant::now());
+                startup_stacks_reorg = self.verify_stacks_db(depth).await;
+            }
+        }
 
         // Download and ingest a Stacks dump
-        if self.config.rely_on_remote_stacks_tsv() {
+        if self.config.rely_on_remote_stacks_tsv() && !is_replica {
             consolidate_local_stacks_chainstate_using_csv(&mut self.config, &self.ctx).await?;
         }
 
         // Stacks scan operation threadpool
-        let (stacks_scan_op_tx, stacks_scan_op_rx) = crossbeam_channel::unbounded();
+        let (stacks_scan_op_tx, stacks_scan_op_rx) = bounded_channel(
+            self.config.limits.max_number_of_pending_scan_operations,
+            self.config.limits.scan_operations_full_policy,
+        );
         let ctx = self.ctx.clone();
         let config = self.config.clone();
         let observer_command_tx_moved = observer_command_tx.clone();
@@ -185,7 +334,10 @@ impl Service {
             .expect("unable to spawn thread");
 
         // Bitcoin scan operation threadpool
-        let (bitcoin_scan_op_tx, bitcoin_scan_op_rx) = crossbeam_channel::unbounded();
+        let (bitcoin_scan_op_tx, bitcoin_scan_op_rx) = bounded_channel(
+            self.config.limits.max_number_of_pending_scan_operations,
+            self.config.limits.scan_operations_full_policy,
+        );
         let ctx = self.ctx.clone();
         let config = self.config.clone();
         let observer_command_tx_moved = observer_command_tx.clone();
@@ -203,6 +355,32 @@ impl Service {
             })
             .expect("unable to spawn thread");
 
+        if let Some(watcher) = predicates_dir_watcher {
+            info!(
+                self.ctx.expect_logger(),
+                "Watching {} for predicates",
+                watcher.dir().display()
+            );
+            let ctx = self.ctx.clone();
+            let observer_command_tx_moved = observer_command_tx.clone();
+            let _ = hiro_system_kit::thread_named("Predicates directory watcher")
+                .spawn(move || {
+                    start_predicates_dir_watcher(watcher, observer_command_tx_moved, &ctx);
+                })
+                .expect("unable to spawn thread");
+        }
+
+        // Occurrences streamed to the predicates API websocket subscribers
+        let occurrence_streams = OccurrenceStreams::new();
+        // Recent deliveries of each predicate, served by the predicates API and logged in the
+        // predicates db for `predicates stats`
+        let delivery_history = match self.config.http_api {
+            PredicatesApi::On(ref api_config) => DeliveryHistory::with_store(Arc::new(
+                PredicatesDbDeliveryRecordStore::new(api_config),
+            )),
+            PredicatesApi::Off => DeliveryHistory::new(),
+        };
+
         // Enable HTTP Predicates API, if required
         let config = self.config.clone();
         let predicate_api_shutdown = if let PredicatesApi::On(ref api_config) = config.http_api {
@@ -213,12 +391,18 @@ impl Service {
             let ctx = self.ctx.clone();
             let api_config = api_config.clone();
             let moved_observer_command_tx = observer_command_tx.clone();
+            let moved_occurrence_streams = occurrence_streams.clone();
+            let moved_delivery_history = delivery_history.clone();
+            let moved_config = self.config.clone();
             // Test and initialize a database connection
             let res = hiro_system_kit::thread_named("HTTP Predicate API")
                 .spawn(move || {
                     let future = start_predicate_api_server(
                         api_config,
+                        moved_config,
                         moved_observer_command_tx.clone(),
+                        moved_occurrence_streams,
+                        moved_delivery_history,
                         ctx.clone(),
                     );
                     hiro_system_kit::nestable_block_on(future)
@@ -238,11 +422,90 @@ impl Service {
             None
         };
 
+        // Notify the lifecycle webhook of the predicates' transitions, if configured. The
+        // transitions of the predicates evaluated by replicas are notified by the primary.
+        if let PredicatesApi::On(ref api_config) = config.http_api {
+            if let Some(webhook_url) = api_config
+                .lifecycle_webhook_url
+                .as_ref()
+                .filter(|_| !is_replica)
+            {
+                let ctx = self.ctx.clone();
+                let api_config = api_config.clone();
+                let webhook_url = webhook_url.clone();
+                let _ = hiro_system_kit::thread_named("Predicate lifecycle notifier")
+                    .spawn(move || {
+                        if let Err(e) = start_lifecycle_notifier(&api_config, &webhook_url, &ctx) {
+                            error!(ctx.expect_logger(), "Lifecycle notifier stopped: {e}");
+                        }
+                    })
+                    .expect("unable to spawn thread");
+            }
+        }
+
+        // Register and deregister the predicates assigned to this replica as the primary does
+        if let PredicatesApi::On(ref api_config) = config.http_api {
+            if is_replica {
+                let ctx = self.ctx.clone();
+                let config = config.clone();
+                let api_config = api_config.clone();
+                let observer_command_tx_moved = observer_command_tx.clone();
+                let _ = hiro_system_kit::thread_named("Replica predicates follower")
+                    .spawn(move || {
+                        if let Err(e) = start_replica_predicates_follower(
+                            &config,
+                            &api_config,
+                            &observer_command_tx_moved,
+                            &ctx,
+                        ) {
+                            error!(
+                                ctx.expect_logger(),
+                                "Replica predicates follower stopped: {e}"
+                            );
+                        }
+                    })
+                    .expect("unable to spawn thread");
+
+                // Evaluate the chain events distributed by the primary
+                let ctx = self.ctx.clone();
+                let api_config = api_config.clone();
+                let observer_command_tx_moved = observer_command_tx.clone();
+                let _ = hiro_system_kit::thread_named("Replica chain events follower")
+                    .spawn(move || {
+                        if let Err(e) = start_chain_events_follower(
+                            &api_config,
+                            &observer_command_tx_moved,
+                            &ctx,
+                        ) {
+                            error!(
+                                ctx.expect_logger(),
+                                "Replica chain events follower stopped: {e}"
+                            );
+                        }
+                    })
+                    .expect("unable to spawn thread");
+            }
+        }
+
         let ctx = self.ctx.clone();
-        let stacks_db =
-            open_readonly_stacks_db_conn_with_retry(&config.expected_cache_path(), 3, &ctx)?;
-        let confirmed_tip = get_last_block_height_inserted(&stacks_db, &ctx).unwrap_or(0);
-        let stacks_startup_context = match get_all_unconfirmed_blocks(&stacks_db, &ctx) {
+        let stacks_db = open_readonly_stacks_db_conn_with_retry(
+            &config.expected_cache_path(),
+            &config.storage.stacks_db_backend,
+            3,
+            &ctx,
+        )?;
+        let confirmed_tip = get_last_block_height_inserted(stacks_db.as_ref(), &ctx).unwrap_or(0);
+        let mut health_checks: Vec<Arc<dyn DependencyHealthCheck>> =
+            vec![Arc::new(StacksDbHealthCheck::new(
+                config.expected_cache_path(),
+                &config.storage.stacks_db_backend,
+                &ctx,
+            ))];
+        if let PredicatesApi::On(ref api_config) = self.config.http_api {
+            health_checks.push(Arc::new(PredicatesDbHealthCheck::new(api_config)));
+        }
+        let health_checks = DependencyHealthChecks(health_checks);
+        let stacks_startup_context = match get_all_unconfirmed_blocks(stacks_db.as_ref(), &ctx) {
             Ok(blocks) => {
                 // any unconfirmed blocks that are earlier than confirmed blocks are invalid
 
@@ -262,6 +525,7 @@ impl Service {
                 StacksObserverStartupContext {
                     block_pool_seed: unconfirmed_blocks,
                     last_block_height_appended: highest_appended,
+                    health_checks,
                 }
             }
             Err(e) => {
@@ -272,10 +536,38 @@ impl Service {
                 StacksObserverStartupContext {
                     block_pool_seed: vec![],
                     last_block_height_appended: confirmed_tip,
+                    health_checks,
                 }
             }
         };
 
+        // Deliveries in flight are recorded in the predicates db, to be resumed after a restart
+        let delivery_outbox: Option<Arc<dyn DeliveryOutbox>> = match self.config.http_api {
+            PredicatesApi::On(ref api_config) => {
+                Some(Arc::new(PredicatesDbDeliveryOutbox::new(api_config)))
+            }
+            PredicatesApi::Off => None,
+        };
+
+        // Occurrences are counted in the predicates db, so that they survive a restart
+        let occurrence_count_store: Option<Arc<dyn OccurrenceCountStore>> =
+            match self.config.http_api {
+                PredicatesApi::On(ref api_config) => {
+                    Some(Arc::new(PredicatesDbOccurrenceCountStore::new(api_config)))
+                }
+                PredicatesApi::Off => None,
+            };
+
+        // Bitcoin blocks evicted from the observer's cache are spilled to the working directory
+        let bitcoin_block_spill_store: Option<Arc<dyn BitcoinBlockSpillStore>> =
+            match self.config.limits.max_number_of_cached_bitcoin_blocks {
+                Some(_) => Some(Arc::new(RocksDbBitcoinBlockSpillStore::new(
+                    &self.config.expected_cache_path(),
+                    &self.ctx,
+                )?)),
+                None => None,
+            };
+
         let observer_event_tx_moved = observer_event_tx.clone();
         let moved_observer_command_tx = observer_command_tx.clone();
         let _ = start_event_observer(
@@ -284,16 +576,29 @@ impl Service {
             observer_command_rx,
             Some(observer_event_tx_moved),
             None,
+            ObserverDependencies {
+                occurrence_streams: Some(occurrence_streams),
+                delivery_history: Some(delivery_history),
+                delivery_outbox,
+                occurrence_count_store,
+                bitcoin_block_spill_store,
+            },
             Some(stacks_startup_context),
             self.ctx.clone(),
         );
+        if let Some(chain_event) = startup_stacks_reorg {
+            pending_observer_commands
+                .push_back(ObserverCommand::PropagateStacksChainEvent(chain_event));
+        }
 
         let mut stacks_event = 0;
+        let mut last_stacks_db_pruning: Option<Instant> = None;
+        let mut last_audit_log_trim: Option<Instant> = None;
 
         let ctx = self.ctx.clone();
         match self.config.http_api {
             PredicatesApi::On(ref api_config) => {
-                // Test redis connection
+                // Test predicates db connection
                 open_readwrite_predicates_db_conn(api_config)?;
             }
             PredicatesApi::Off => {}
@@ -316,13 +621,86 @@ impl Service {
             }
         }
 
-        for new_predicate in newly_registered_predicates {
-            let _ = observer_event_tx.send(ObserverEvent::PredicateRegistered(new_predicate));
+        // handled ahead of the events of the observer, rather than sent to a channel this loop
+        // would wait for room in
+        let mut pending_observer_events: VecDeque<_> = newly_registered_predicates
+            .into_iter()
+            .map(ObserverEvent::PredicateRegistered)
+            .collect();
+        let mut dropped_observer_events = 0;
+        let mut plaintext_payloads_read = 0;
+
+        // the registrations are recorded by the primary, replicas only evaluate the predicates
+        let registrations_api = match self.config.http_api {
+            PredicatesApi::On(_) if is_replica => PredicatesApi::Off,
+            ref http_api => http_api.clone(),
+        };
+
+        // the chain events ingested by the primary are evaluated by the replicas as well
+        let mut chain_events_coordinator = match self.config.http_api {
+            PredicatesApi::On(ref api_config) if self.config.is_coordinator() => {
+                Some(ChainEventsCoordinator::new(api_config)?)
+            }
+            _ => None,
+        };
+
+        if let Some(ref systemd_notifier) = self.systemd_notifier {
+            match systemd_notifier.notify_ready() {
+                Ok(()) => info!(self.ctx.expect_logger(), "Notified systemd of readiness"),
+                Err(e) => warn!(self.ctx.expect_logger(), "{}", e),
+            }
         }
+        let watchdog_interval = self
+            .systemd_notifier
+            .as_ref()
+            .and_then(|notifier| notifier.watchdog_interval());
 
         loop {
-            let event = match observer_event_rx.recv() {
+            // the watchdog is pinged from the event loop, so that systemd restarts the service
+            // when the loop is stuck
+            if let Some(ref mut systemd_notifier) = self.systemd_notifier {
+                if let Err(e) = systemd_notifier.ping_watchdog() {
+                    warn!(self.ctx.expect_logger(), "{}", e);
+                }
+            }
+            send_pending_observer_commands(&observer_command_tx, &mut pending_observer_commands);
+            // the commands left pending are retried even when no event comes in
+            let mut timeout = watchdog_interval;
+            if !pending_observer_commands.is_empty() {
+                timeout = Some(
+                    timeout.map_or(PENDING_OBSERVER_COMMANDS_RETRY_INTERVAL, |interval| {
+                        interval.min(PENDING_OBSERVER_COMMANDS_RETRY_INTERVAL)
+                    }),
+                );
+            }
+            let received = match pending_observer_events.pop_front() {
+                Some(event) => Ok(event),
+                None => match timeout {
+                    Some(interval) => observer_event_rx.recv_timeout(interval),
+                    None => observer_event_rx.recv().map_err(RecvTimeoutError::from),
+                },
+            };
+            if observer_event_tx.dropped() > dropped_observer_events {
+                warn!(
+                    self.ctx.expect_logger(),
+                    "{} observer events dropped, the service lagging behind the observer",
+                    observer_event_tx.dropped() - dropped_observer_events
+                );
+                dropped_observer_events = observer_event_tx.dropped();
+            }
+            if let Some(cipher) = get_payload_cipher() {
+                if cipher.plaintext_payloads_read() > plaintext_payloads_read {
+                    warn!(
+                        self.ctx.expect_logger(),
+                        "{} payloads stored in clear read, storage.allow_plaintext_payloads being set",
+                        cipher.plaintext_payloads_read() - plaintext_payloads_read
+                    );
+                    plaintext_payloads_read = cipher.plaintext_payloads_read();
+                }
+            }
+            let event = match received {
                 Ok(cmd) => cmd,
+                Err(RecvTimeoutError::Timeout) => continue,
                 Err(e) => {
                     crit!(
                         self.ctx.expect_logger(),
@@ -332,12 +710,30 @@ impl Service {
                     break;
                 }
             };
+            self.error_reporter.capture(&event);
+            // the audit log is append-only unless a retention is set
+            if let PredicatesApi::On(ref api_config) = registrations_api {
+                if let Some(max_entries) = api_config.audit_log_max_entries {
+                    if last_audit_log_trim
+                        .map_or(true, |last| last.elapsed() >= AUDIT_LOG_TRIM_INTERVAL)
+                    {
+                        last_audit_log_trim = Some(Instant::now());
+                        let res =
+                            open_readwrite_predicates_db_conn(api_config).and_then(|mut conn| {
+                                trim_predicate_audit_log(max_entries, conn.as_mut())
+                            });
+                        if let Err(e) = res {
+                            warn!(self.ctx.expect_logger(), "{}", e);
+                        }
+                    }
+                }
+            }
             match event {
                 ObserverEvent::PredicateRegistered(spec) => {
                     // If start block specified, use it.
                     // If no start block specified, depending on the nature the hook, we'd like to retrieve:
                     // - contract-id
-                    if let PredicatesApi::On(ref config) = self.config.http_api {
+                    if let PredicatesApi::On(ref config) = registrations_api {
                         let Ok(mut predicates_db_conn) =
                             open_readwrite_predicates_db_conn_verbose(config, &ctx)
                         else {
@@ -355,6 +751,12 @@ impl Service {
                             &mut predicates_db_conn,
                             &self.ctx,
                         );
+                        set_predicate_registration_time(
+                            &spec.key(),
+                            &mut predicates_db_conn,
+                            &self.ctx,
+                        );
+                        set_predicate_namespace(&spec, &mut predicates_db_conn, &self.ctx);
                     }
                     match spec {
                         ChainhookInstance::Stacks(predicate_spec) => {
@@ -371,6 +773,57 @@ impl Service {
                         }
                     }
                 }
+                ObserverEvent::PredicateUpdated(spec) => {
+                    info!(
+                        self.ctx.expect_logger(),
+                        "Predicate {} updated",
+                        spec.uuid()
+                    );
+                    if let PredicatesApi::On(ref config) = self.config.http_api {
+                        let Ok(mut predicates_db_conn) =
+                            open_readwrite_predicates_db_conn_verbose(config, &ctx)
+                        else {
+                            continue;
+                        };
+                        update_predicate_spec(
+                            &spec.key(),
+                            &spec,
+                            &mut predicates_db_conn,
+                            &self.ctx,
+                        );
+                        let status =
+                            retrieve_predicate_status(&spec.key(), &mut predicates_db_conn);
+                        log_predicate_update(
+                            &spec.key(),
+                            status.as_ref(),
+                            &mut predicates_db_conn,
+                            &self.ctx,
+                        );
+                        // A scan in flight keeps evaluating the previous specification, so it is
+                        // restarted from its progress with the new one
+                        let Some(PredicateStatus::Scanning(scanning_data)) = status else {
+                            continue;
+                        };
+                        match spec {
+                            ChainhookInstance::Stacks(predicate_spec) => {
+                                let _ = stacks_scan_op_tx
+                                    .send(StacksScanOp::KillScan(predicate_spec.uuid.clone()));
+                                let _ = stacks_scan_op_tx.send(StacksScanOp::StartScan {
+                                    predicate_spec,
+                                    unfinished_scan_data: Some(scanning_data),
+                                });
+                            }
+                            ChainhookInstance::Bitcoin(predicate_spec) => {
+                                let _ = bitcoin_scan_op_tx
+                                    .send(BitcoinScanOp::KillScan(predicate_spec.uuid.clone()));
+                                let _ = bitcoin_scan_op_tx.send(BitcoinScanOp::StartScan {
+                                    predicate_spec,
+                                    unfinished_scan_data: Some(scanning_data),
+                                });
+                            }
+                        }
+                    }
+                }
                 ObserverEvent::PredicateEnabled(spec) => {
                     if let PredicatesApi::On(ref config) = self.config.http_api {
                         let Ok(mut predicates_db_conn) =
@@ -396,28 +849,36 @@ impl Service {
                     predicate_uuid,
                     chain,
                 }) => {
-                    if let PredicatesApi::On(ref config) = self.config.http_api {
+                    match chain {
+                        Chain::Bitcoin => {
+                            let _ = bitcoin_scan_op_tx
+                                .send(BitcoinScanOp::KillScan(predicate_uuid.clone()));
+                        }
+                        Chain::Stacks => {
+                            let _ = stacks_scan_op_tx
+                                .send(StacksScanOp::KillScan(predicate_uuid.clone()));
+                        }
+                    };
+
+                    if let PredicatesApi::On(ref config) = registrations_api {
                         let Ok(mut predicates_db_conn) =
                             open_readwrite_predicates_db_conn_verbose(config, &ctx)
                         else {
                             continue;
                         };
 
-                        match chain {
-                            Chain::Bitcoin => {
-                                let _ = bitcoin_scan_op_tx
-                                    .send(BitcoinScanOp::KillScan(predicate_uuid.clone()));
-                            }
-                            Chain::Stacks => {
-                                let _ = stacks_scan_op_tx
-                                    .send(StacksScanOp::KillScan(predicate_uuid.clone()));
-                            }
-                        };
-
                         let predicate_key =
-                            ChainhookInstance::either_stx_or_btc_key(&predicate_uuid);
-                        let res: Result<(), redis::RedisError> =
-                            predicates_db_conn.del(predicate_key.clone());
+                            retrieve_predicate_key(&predicate_uuid, &mut predicates_db_conn);
+                        let previous_status =
+                            retrieve_predicate_status(&predicate_key, &mut predicates_db_conn);
+                        let res = predicates_db_conn
+                            .del(&predicate_key)
+                            .and_then(|_| {
+                                predicates_db_conn.hdel(PREDICATE_NAMESPACES_KEY, &predicate_uuid)
+                            })
+                            .and_then(|_| {
+                                predicates_db_conn.del(&delivery_log_key(&predicate_uuid))
+                            });
                         if let Err(e) = res {
                             warn!(
                                 self.ctx.expect_logger(),
@@ -425,10 +886,29 @@ impl Service {
                                 e.to_string()
                             );
                         }
+                        log_predicate_transition(
+                            &predicate_key,
+                            previous_status.as_ref(),
+                            None,
+                            &mut predicates_db_conn,
+                            &self.ctx,
+                        );
+                        let event = PredicateLifecycleEvent::new(
+                            &predicate_key,
+                            PredicateLifecycleEventType::Deregistered,
+                            None,
+                        );
+                        publish_lifecycle_event(&event, &mut predicates_db_conn, &self.ctx);
                     }
                 }
                 ObserverEvent::BitcoinChainEvent((chain_update, report)) => {
                     debug!(self.ctx.expect_logger(), "Bitcoin update not stored");
+                    if let Some(ref mut coordinator) = chain_events_coordinator {
+                        coordinator.distribute(
+                            &DistributedChainEvent::Bitcoin(chain_update.clone()),
+                            &self.ctx,
+                        );
+                    }
                     if let PredicatesApi::On(ref config) = self.config.http_api {
                         let Ok(mut predicates_db_conn) =
                             open_readwrite_predicates_db_conn_verbose(config, &ctx)
@@ -448,7 +928,7 @@ impl Service {
                                         &ctx,
                                     ) {
                                         for uuid in expired_predicate_uuids.into_iter() {
-                                            let _ = observer_command_tx.send(
+                                            pending_observer_commands.push_back(
                                                 ObserverCommand::ExpireBitcoinPredicate(
                                                     HookExpirationData {
                                                         hook_uuid: uuid,
@@ -473,7 +953,7 @@ impl Service {
                                         &ctx,
                                     ) {
                                         for uuid in expired_predicate_uuids.into_iter() {
-                                            let _ = observer_command_tx.send(
+                                            pending_observer_commands.push_back(
                                                 ObserverCommand::ExpireBitcoinPredicate(
                                                     HookExpirationData {
                                                         hook_uuid: uuid,
@@ -497,67 +977,77 @@ impl Service {
                     }
                 }
                 ObserverEvent::StacksChainEvent((chain_event, report)) => {
-                    match open_readwrite_stacks_db_conn(
-                        &self.config.expected_cache_path(),
-                        &self.ctx,
-                    ) {
-                        Ok(stacks_db_conn_rw) => match &chain_event {
-                            StacksChainEvent::ChainUpdatedWithBlocks(data) => {
-                                if let Err(e) = confirm_entries_in_stacks_blocks(
-                                    &data.confirmed_blocks,
-                                    &stacks_db_conn_rw,
-                                    &self.ctx,
-                                ) {
-                                    error!(
-                                        self.ctx.expect_logger(),
-                                        "unable to add confirmed entries to stacks db: {}", e
-                                    );
-                                };
-                                if let Err(e) = draft_entries_in_stacks_blocks(
-                                    &data.new_blocks,
-                                    &stacks_db_conn_rw,
-                                    &self.ctx,
-                                ) {
-                                    error!(
-                                        self.ctx.expect_logger(),
-                                        "unable to add unconfirmed entries to stacks db: {}", e
-                                    );
-                                };
-                            }
-                            StacksChainEvent::ChainUpdatedWithReorg(data) => {
-                                if let Err(e) = confirm_entries_in_stacks_blocks(
-                                    &data.confirmed_blocks,
-                                    &stacks_db_conn_rw,
-                                    &self.ctx,
-                                ) {
-                                    error!(
-                                        self.ctx.expect_logger(),
-                                        "unable to add confirmed entries to stacks db: {}", e
-                                    );
-                                };
-                                if let Err(e) = draft_entries_in_stacks_blocks(
-                                    &data.blocks_to_apply,
-                                    &stacks_db_conn_rw,
-                                    &self.ctx,
-                                ) {
-                                    error!(
-                                        self.ctx.expect_logger(),
-                                        "unable to add unconfirmed entries to stacks db: {}", e
-                                    );
-                                };
+                    if let Some(ref mut coordinator) = chain_events_coordinator {
+                        coordinator.distribute(
+                            &DistributedChainEvent::Stacks(chain_event.clone()),
+                            &self.ctx,
+                        );
+                    }
+                    // the Stacks db is written by the primary only
+                    if !is_replica {
+                        match open_readwrite_stacks_db_conn(
+                            &self.config.expected_cache_path(),
+                            &self.config.storage.stacks_db_backend,
+                            &self.ctx,
+                        ) {
+                            Ok(stacks_db_conn_rw) => match &chain_event {
+                                StacksChainEvent::ChainUpdatedWithBlocks(data) => {
+                                    if let Err(e) = confirm_entries_in_stacks_blocks(
+                                        &data.confirmed_blocks,
+                                        stacks_db_conn_rw.as_ref(),
+                                        &self.ctx,
+                                    ) {
+                                        error!(
+                                            self.ctx.expect_logger(),
+                                            "unable to add confirmed entries to stacks db: {}", e
+                                        );
+                                    };
+                                    if let Err(e) = draft_entries_in_stacks_blocks(
+                                        &data.new_blocks,
+                                        stacks_db_conn_rw.as_ref(),
+                                        &self.ctx,
+                                    ) {
+                                        error!(
+                                            self.ctx.expect_logger(),
+                                            "unable to add unconfirmed entries to stacks db: {}", e
+                                        );
+                                    };
+                                }
+                                StacksChainEvent::ChainUpdatedWithReorg(data) => {
+                                    if let Err(e) = confirm_entries_in_stacks_blocks(
+                                        &data.confirmed_blocks,
+                                        stacks_db_conn_rw.as_ref(),
+                                        &self.ctx,
+                                    ) {
+                                        error!(
+                                            self.ctx.expect_logger(),
+                                            "unable to add confirmed entries to stacks db: {}", e
+                                        );
+                                    };
+                                    if let Err(e) = draft_entries_in_stacks_blocks(
+                                        &data.blocks_to_apply,
+                                        stacks_db_conn_rw.as_ref(),
+                                        &self.ctx,
+                                    ) {
+                                        error!(
+                                            self.ctx.expect_logger(),
+                                            "unable to add unconfirmed entries to stacks db: {}", e
+                                        );
+                                    };
+                                }
+                                StacksChainEvent::ChainUpdatedWithMicroblocks(_)
+                                | StacksChainEvent::ChainUpdatedWithMicroblocksReorg(_) => {}
+                            },
+                            Err(e) => {
+                                error!(
+                                    self.ctx.expect_logger(),
+                                    "unable to open stacks db: {}",
+                                    e.to_string()
+                                );
+                                continue;
                             }
-                            StacksChainEvent::ChainUpdatedWithMicroblocks(_)
-                            | StacksChainEvent::ChainUpdatedWithMicroblocksReorg(_) => {}
-                        },
-                        Err(e) => {
-                            error!(
-                                self.ctx.expect_logger(),
-                                "unable to open stacks db: {}",
-                                e.to_string()
-                            );
-                            continue;
-                        }
-                    };
+                        };
+                    }
 
                     if let PredicatesApi::On(ref config) = self.config.http_api {
                         let Ok(mut predicates_db_conn) =
@@ -577,7 +1067,7 @@ impl Service {
                                         &ctx,
                                     ) {
                                         for uuid in expired_predicate_uuids.into_iter() {
-                                            let _ = observer_command_tx.send(
+                                            pending_observer_commands.push_back(
                                                 ObserverCommand::ExpireStacksPredicate(
                                                     HookExpirationData {
                                                         hook_uuid: uuid,
@@ -600,7 +1090,7 @@ impl Service {
                                         &ctx,
                                     ) {
                                         for uuid in expired_predicate_uuids.into_iter() {
-                                            let _ = observer_command_tx.send(
+                                            pending_observer_commands.push_back(
                                                 ObserverCommand::ExpireStacksPredicate(
                                                     HookExpirationData {
                                                         hook_uuid: uuid,
@@ -628,7 +1118,7 @@ impl Service {
                     // Every 32 blocks, we will check if there's a new Stacks file archive to ingest
                     if stacks_event > 32 {
                         stacks_event = 0;
-                        if self.config.rely_on_remote_stacks_tsv() {
+                        if self.config.rely_on_remote_stacks_tsv() && !is_replica {
                             if let Err(e) = consolidate_local_stacks_chainstate_using_csv(
                                 &mut self.config,
                                 &self.ctx,
@@ -641,6 +1131,30 @@ impl Service {
                             };
                         }
                     }
+
+                    if !is_replica {
+                        if let Some(ref retention) = self.config.storage.stacks_retention {
+                            if last_stacks_db_pruning
+                                .map_or(true, |last| last.elapsed() >= STACKS_DB_PRUNING_INTERVAL)
+                            {
+                                last_stacks_db_pruning = Some(Instant::now());
+                                self.prune_stacks_db(retention);
+                            }
+                        }
+
+                        if let Some(depth) = self.config.storage.stacks_verification_depth {
+                            if last_stacks_db_verification.map_or(true, |last| {
+                                last.elapsed() >= STACKS_DB_VERIFICATION_INTERVAL
+                            }) {
+                                last_stacks_db_verification = Some(Instant::now());
+                                if let Some(chain_event) = self.verify_stacks_db(depth).await {
+                                    pending_observer_commands.push_back(
+                                        ObserverCommand::PropagateStacksChainEvent(chain_event),
+                                    );
+                                }
+                            }
+                        }
+                    }
                 }
                 ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
                     predicate_key,
@@ -660,11 +1174,182 @@ impl Service {
                         );
                     }
                 }
+                ObserverEvent::PredicateAcknowledged(PredicateAcknowledgedData {
+                    predicate_key,
+                    cursor,
+                }) => {
+                    if let PredicatesApi::On(ref config) = self.config.http_api {
+                        let Ok(mut predicates_db_conn) =
+                            open_readwrite_predicates_db_conn_verbose(config, &ctx)
+                        else {
+                            continue;
+                        };
+                        set_predicate_ack_cursor(
+                            &predicate_key,
+                            cursor,
+                            &mut predicates_db_conn,
+                            &ctx,
+                        );
+                    }
+                }
+                ObserverEvent::PredicateDeliveryFailed(PredicateDeliveryFailedData {
+                    predicate,
+                    error,
+                }) => {
+                    warn!(
+                        self.ctx.expect_logger(),
+                        "Delivery of predicate {} failed, resuming from its last acknowledged cursor: {}",
+                        predicate.uuid(),
+                        error
+                    );
+                    // Scan again from the block following the last acknowledged cursor. The
+                    // predicate is enabled again once the scan reaches the chain tip.
+                    let unfinished_scan_data = match self.config.http_api {
+                        PredicatesApi::On(ref config) => {
+                            match open_readwrite_predicates_db_conn_verbose(config, &ctx) {
+                                Ok(mut predicates_db_conn) => {
+                                    rewind_to_ack_cursor(&predicate, None, &mut predicates_db_conn)
+                                }
+                                Err(_) => None,
+                            }
+                        }
+                        PredicatesApi::Off => None,
+                    };
+                    match predicate {
+                        ChainhookInstance::Stacks(predicate_spec) => {
+                            let _ = stacks_scan_op_tx.send(StacksScanOp::StartScan {
+                                predicate_spec,
+                                unfinished_scan_data,
+                            });
+                        }
+                        ChainhookInstance::Bitcoin(predicate_spec) => {
+                            let _ = bitcoin_scan_op_tx.send(BitcoinScanOp::StartScan {
+                                predicate_spec,
+                                unfinished_scan_data,
+                            });
+                        }
+                    }
+                }
+                ObserverEvent::PredicatePaused(PredicatePausedData {
+                    predicate,
+                    error,
+                    requested,
+                }) => {
+                    if requested {
+                        info!(
+                            self.ctx.expect_logger(),
+                            "Predicate {} paused until it is resumed",
+                            predicate.uuid()
+                        );
+                    } else {
+                        warn!(
+                            self.ctx.expect_logger(),
+                            "Predicate {} paused until its endpoint recovers: {}",
+                            predicate.uuid(),
+                            error
+                        );
+                    }
+                    if let PredicatesApi::On(ref config) = self.config.http_api {
+                        let Ok(mut predicates_db_conn) =
+                            open_readwrite_predicates_db_conn_verbose(config, &ctx)
+                        else {
+                            continue;
+                        };
+                        update_predicate_spec(
+                            &predicate.key(),
+                            &predicate,
+                            &mut predicates_db_conn,
+                            &self.ctx,
+                        );
+                        set_predicate_paused_status(
+                            error,
+                            requested,
+                            &predicate.key(),
+                            &mut predicates_db_conn,
+                            &ctx,
+                        );
+                    }
+                }
+                ObserverEvent::PredicateResumed(predicate) => {
+                    info!(
+                        self.ctx.expect_logger(),
+                        "Endpoint of predicate {} recovered, resuming it",
+                        predicate.uuid()
+                    );
+                    // Scan again from the block the predicate was paused at. The predicate is
+                    // enabled again once the scan reaches the chain tip.
+                    let unfinished_scan_data = match self.config.http_api {
+                        PredicatesApi::On(ref config) => {
+                            match open_readwrite_predicates_db_conn_verbose(config, &ctx) {
+                                Ok(mut predicates_db_conn) => {
+                                    let scanning_data = match retrieve_predicate_status(
+                                        &predicate.key(),
+                                        &mut predicates_db_conn,
+                                    ) {
+                                        Some(PredicateStatus::Paused(paused_data)) => {
+                                            Some(paused_data.into())
+                                        }
+                                        _ => None,
+                                    };
+                                    rewind_to_ack_cursor(
+                                        &predicate,
+                                        scanning_data,
+                                        &mut predicates_db_conn,
+                                    )
+                                }
+                                Err(_) => None,
+                            }
+                        }
+                        PredicatesApi::Off => None,
+                    };
+                    match predicate {
+                        ChainhookInstance::Stacks(predicate_spec) => {
+                            let _ = stacks_scan_op_tx.send(StacksScanOp::StartScan {
+                                predicate_spec,
+                                unfinished_scan_data,
+                            });
+                        }
+                        ChainhookInstance::Bitcoin(predicate_spec) => {
+                            let _ = bitcoin_scan_op_tx.send(BitcoinScanOp::StartScan {
+                                predicate_spec,
+                                unfinished_scan_data,
+                            });
+                        }
+                    }
+                }
+                ObserverEvent::PredicateRescanRequested(data) => {
+                    info!(
+                        self.ctx.expect_logger(),
+                        "Rescanning blocks {} to {} for predicate {}",
+                        data.start_block,
+                        data.end_block,
+                        data.predicate.uuid()
+                    );
+                    match data.predicate {
+                        ChainhookInstance::Stacks(predicate_spec) => {
+                            let _ = stacks_scan_op_tx.send(StacksScanOp::Rescan {
+                                predicate_spec,
+                                start_block: data.start_block,
+                                end_block: data.end_block,
+                            });
+                        }
+                        ChainhookInstance::Bitcoin(predicate_spec) => {
+                            let _ = bitcoin_scan_op_tx.send(BitcoinScanOp::Rescan {
+                                predicate_spec,
+                                start_block: data.start_block,
+                                end_block: data.end_block,
+                            });
+                        }
+                    }
+                }
                 ObserverEvent::Terminate => {
                     info!(
                         self.ctx.expect_logger(),
                         "Terminating ObserverEvent runloop"
                     );
+                    if let Some(ref systemd_notifier) = self.systemd_notifier {
+                        let _ = systemd_notifier.notify_stopping();
+                    }
                     if let Some(predicate_api_shutdown) = predicate_api_shutdown {
                         info!(
                             self.ctx.expect_logger(),
@@ -679,6 +1364,140 @@ impl Service {
         }
         Ok(())
     }
+
+    /// Prunes the blocks of the Stacks db falling out of the retention window, from the service
+    /// loop as it is the only one writing to the db.
+    fn prune_stacks_db(&self, retention: &StacksRetentionConfig) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or(0);
+        let res = open_readwrite_stacks_db_conn(
+            &self.config.expected_cache_path(),
+            &self.config.storage.stacks_db_backend,
+            &self.ctx,
+        )
+        .and_then(|stacks_db_rw| {
+            prune_stacks_blocks(
+                retention.max_blocks,
+                retention.retained_since(now),
+                stacks_db_rw.as_ref(),
+                &self.ctx,
+            )
+        });
+        match res {
+            Ok(0) => {}
+            Ok(blocks_pruned) => {
+                info!(
+                    self.ctx.expect_logger(),
+                    "Pruned {blocks_pruned} blocks from the stacks db"
+                );
+            }
+            Err(e) => {
+                error!(self.ctx.expect_logger(), "Failed to prune stacks db: {e}");
+            }
+        }
+    }
+
+    /// Checks the last `depth` blocks of the Stacks db against the canonical chain of the Stacks
+    /// node, and rolls back the blocks diverging from it, e.g. after a reorg missed while the
+    /// service was down. Returns the reorg to propagate to the predicates, if any.
+    async fn verify_stacks_db(&self, depth: u64) -> Option<StacksChainEvent> {
+        let BitcoinBlockSignaling::Stacks(ref stacks_node) =
+            self.config.network.bitcoin_block_signaling
+        else {
+            return None;
+        };
+        let http_client = build_http_client();
+        let canonical_blocks = match retrieve_canonical_stacks_block_identifiers(
+            &http_client,
+            &stacks_node.rpc_url,
+            depth,
+        )
+        .await
+        {
+            Ok(canonical_blocks) => canonical_blocks,
+            Err(e) => {
+                warn!(
+                    self.ctx.expect_logger(),
+                    "Unable to retrieve canonical chain from stacks-node: {e}"
+                );
+                return None;
+            }
+        };
+        let res = open_readwrite_stacks_db_conn(
+&self.config.expected_cache_path(), &self.config.storage.stacks_db_backend, &self.ctx)
+            .and_then(|stacks_db_rw| {
+                match find_stacks_db_divergence(&canonical_blocks, stacks_db_rw.as_ref(), &self.ctx)? {
+                    Some(block_height) => {
+                        warn!(
+                            self.ctx.expect_logger(),
+                            "Stacks db diverges from the canonical chain at block #{block_height}, rolling back"
+                        );
+                        rollback_stacks_blocks(block_height, stacks_db_rw.as_ref(), &self.ctx)
+                    }
+                    None => Ok(vec![]),
+                }
+            });
+        match res {
+            Ok(blocks) if blocks.is_empty() => None,
+            Ok(blocks) => {
+                info!(
+                    self.ctx.expect_logger(),
+                    "Rolled back {} blocks from the stacks db",
+                    blocks.len()
+                );
+                Some(StacksChainEvent::ChainUpdatedWithReorg(
+                    StacksChainUpdatedWithReorgData {
+                        blocks_to_rollback: blocks
+                            .into_iter()
+                            .map(StacksBlockUpdate::new)
+                            .collect(),
+                        blocks_to_apply: vec![],
+                        confirmed_blocks: vec![],
+                    },
+                ))
+            }
+            Err(e) => {
+                error!(self.ctx.expect_logger(), "Failed to verify stacks db: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// Time between two prunings of the Stacks db, when a retention policy is set.
+const STACKS_DB_PRUNING_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Time between two verifications of the Stacks db against the Stacks node, when enabled.
+const STACKS_DB_VERIFICATION_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Time between two trims of the audit log, when a retention is set.
+const AUDIT_LOG_TRIM_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Time between two attempts to send the commands left pending for the observer.
+const PENDING_OBSERVER_COMMANDS_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sends the commands queued for the observer until its channel is full, leaving the rest
+/// queued. The service never waits for room in this channel, the observer possibly waiting for
+/// room in the events channel the service reads.
+fn send_pending_observer_commands(
+    observer_command_tx: &SyncSender<ObserverCommand>,
+    pending_observer_commands: &mut VecDeque<ObserverCommand>,
+) {
+    while let Some(command) = pending_observer_commands.pop_front() {
+        match observer_command_tx.try_send(command) {
+            Ok(()) => {}
+            Err(TrySendError::Full(command)) => {
+                pending_observer_commands.push_front(command);
+                return;
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                pending_observer_commands.clear();
+                return;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -691,6 +1510,7 @@ pub enum PredicateStatus {
     UnconfirmedExpiration(ExpiredData),
     ConfirmedExpiration(ExpiredData),
     Interrupted(String),
+    Paused(PausedData),
     New,
 }
 
@@ -721,10 +1541,37 @@ pub struct ExpiredData {
     pub expired_at_block_height: u64,
 }
 
+/// Progress of a predicate paused because deliveries to its endpoint keep failing, or paused
+/// through the predicates API.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PausedData {
+    pub reason: String,
+    /// Whether the pause was requested through the predicates API, in which case the predicate
+    /// stays paused across restarts until it is resumed through the API.
+    #[serde(default)]
+    pub requested: bool,
+    pub number_of_blocks_evaluated: u64,
+    pub number_of_times_triggered: u64,
+    pub last_occurrence: Option<u64>,
+    pub last_evaluated_block_height: u64,
+}
+
+impl From<PausedData> for ScanningData {
+    fn from(paused_data: PausedData) -> Self {
+        ScanningData {
+            number_of_blocks_to_scan: 0,
+            number_of_blocks_evaluated: paused_data.number_of_blocks_evaluated,
+            number_of_times_triggered: paused_data.number_of_times_triggered,
+            last_occurrence: paused_data.last_occurrence,
+            last_evaluated_block_height: paused_data.last_evaluated_block_height,
+        }
+    }
+}
+
 fn update_status_from_report(
     chain: Chain,
     report: PredicateEvaluationReport,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     for (predicate_uuid, blocks_ids) in report.predicates_triggered.iter() {
@@ -735,7 +1582,7 @@ fn update_status_from_report(
                     last_triggered_height,
                     triggered_count,
                 },
-                &(ChainhookInstance::either_stx_or_btc_key(predicate_uuid)),
+                &retrieve_predicate_key(predicate_uuid, predicates_db_conn),
                 predicates_db_conn,
                 ctx,
             );
@@ -764,7 +1611,7 @@ fn update_status_from_report(
                     last_evaluated_height,
                     evaluated_count,
                 },
-                &(ChainhookInstance::either_stx_or_btc_key(predicate_uuid)),
+                &retrieve_predicate_key(predicate_uuid, predicates_db_conn),
                 predicates_db_conn,
                 ctx,
             );
@@ -777,7 +1624,7 @@ fn update_status_from_report(
                 &chain,
                 evaluated_count,
                 last_evaluated_height,
-                &(ChainhookInstance::either_stx_or_btc_key(predicate_uuid)),
+                &retrieve_predicate_key(predicate_uuid, predicates_db_conn),
                 predicates_db_conn,
                 ctx,
             );
@@ -788,13 +1635,94 @@ fn update_status_from_report(
 fn set_predicate_interrupted_status(
     error: String,
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let status = PredicateStatus::Interrupted(error);
     update_predicate_status(predicate_key, status, predicates_db_conn, ctx);
 }
 
+/// Updates a predicate's status to `Paused`, keeping the progress of its previous status.
+fn set_predicate_paused_status(
+    reason: String,
+    requested: bool,
+    predicate_key: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    let (
+        number_of_blocks_evaluated,
+        number_of_times_triggered,
+        last_occurrence,
+        last_evaluated_block_height,
+    ) = match retrieve_predicate_status(predicate_key, predicates_db_conn) {
+        Some(PredicateStatus::Streaming(StreamingData {
+            last_occurrence,
+            last_evaluation: _,
+            number_of_times_triggered,
+            number_of_blocks_evaluated,
+            last_evaluated_block_height,
+        })) => (
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        ),
+        Some(PredicateStatus::Scanning(ScanningData {
+            number_of_blocks_to_scan: _,
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        })) => (
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        ),
+        Some(PredicateStatus::Paused(PausedData {
+            reason: _,
+            requested: _,
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        })) => (
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        ),
+        Some(PredicateStatus::New) | None => (0, 0, None, 0),
+        Some(
+            status @ (PredicateStatus::UnconfirmedExpiration(_)
+            | PredicateStatus::ConfirmedExpiration(_)
+            | PredicateStatus::Interrupted(_)),
+        ) => {
+            warn!(
+                ctx.expect_logger(),
+                "Attempting to set Paused status when previous status was {:?} for predicate {}",
+                status,
+                predicate_key
+            );
+            return;
+        }
+    };
+    update_predicate_status(
+        predicate_key,
+        PredicateStatus::Paused(PausedData {
+            reason,
+            requested,
+            number_of_blocks_evaluated,
+            number_of_times_triggered,
+            last_occurrence,
+            last_evaluated_block_height,
+        }),
+        predicates_db_conn,
+        ctx,
+    );
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StreamingDataType {
     Occurrence {
@@ -816,7 +1744,7 @@ pub enum StreamingDataType {
 fn set_predicate_streaming_status(
     streaming_data_type: StreamingDataType,
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let now_secs = SystemTime::now()
@@ -869,6 +1797,10 @@ fn set_predicate_streaming_status(
                     last_evaluated_block_height,
                 ),
                 PredicateStatus::New => (None, 0, 0, 0),
+                PredicateStatus::Paused(_) => {
+                    // Blocks observed while paused are evaluated again once resumed
+                    return;
+                }
                 PredicateStatus::Interrupted(_) | PredicateStatus::ConfirmedExpiration(_) => {
                     warn!(ctx.expect_logger(), "Attempting to set Streaming status when previous status was {:?} for predicate {}", status, predicate_key);
                     return;
@@ -932,7 +1864,7 @@ pub fn set_predicate_scanning_status(
     number_of_blocks_evaluated: u64,
     number_of_times_triggered: u64,
     current_block_height: u64,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let now_secs = SystemTime::now()
@@ -963,6 +1895,13 @@ pub fn set_predicate_scanning_status(
                     expired_data.last_occurrence
                 }
             }
+            PredicateStatus::Paused(paused_data) => {
+                if number_of_times_triggered > paused_data.number_of_times_triggered {
+                    Some(now_secs)
+                } else {
+                    paused_data.last_occurrence
+                }
+            }
             PredicateStatus::New => {
                 if number_of_times_triggered > 0 {
                     Some(now_secs)
@@ -998,7 +1937,7 @@ pub fn set_unconfirmed_expiration_status(
     number_of_new_blocks_evaluated: u64,
     last_evaluated_block_height: u64,
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let current_status = retrieve_predicate_status(predicate_key, predicates_db_conn);
@@ -1023,6 +1962,19 @@ pub fn set_unconfirmed_expiration_status(
                 last_evaluated_block_height,
             ),
             PredicateStatus::New => (0, 0, None, 0),
+            PredicateStatus::Paused(PausedData {
+                reason: _,
+                requested: _,
+                number_of_blocks_evaluated,
+                number_of_times_triggered,
+                last_occurrence,
+                last_evaluated_block_height,
+            }) => (
+                number_of_blocks_evaluated + number_of_new_blocks_evaluated,
+                number_of_times_triggered,
+                last_occurrence,
+                last_evaluated_block_height,
+            ),
             PredicateStatus::Streaming(StreamingData {
                 last_occurrence,
                 last_evaluation: _,
@@ -1083,7 +2035,7 @@ pub fn set_unconfirmed_expiration_status(
 
 pub fn set_confirmed_expiration_status(
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let current_status = retrieve_predicate_status(predicate_key, predicates_db_conn);
@@ -1094,7 +2046,8 @@ pub fn set_confirmed_expiration_status(
             | PredicateStatus::Interrupted(_)
             | PredicateStatus::New
             | PredicateStatus::Scanning(_)
-            | PredicateStatus::Streaming(_) => {
+            | PredicateStatus::Streaming(_)
+            | PredicateStatus::Paused(_) => {
                 warn!(ctx.expect_logger(), "Attempting to set ConfirmedExpiration status when previous status was {:?} for predicate {}", status, predicate_key);
                 return;
             }
@@ -1121,7 +2074,7 @@ fn get_predicate_expiration_key(chain: &Chain, block_height: u64) -> String {
 fn expire_predicates_for_block(
     chain: &Chain,
     confirmed_block_index: u64,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) -> Option<Vec<String>> {
     match get_predicates_expiring_at_block(chain, confirmed_block_index, predicates_db_conn, ctx) {
@@ -1139,7 +2092,7 @@ fn insert_predicate_expiration(
     chain: &Chain,
     expired_at_block_height: u64,
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let key = get_predicate_expiration_key(chain, expired_at_block_height);
@@ -1148,9 +2101,7 @@ fn insert_predicate_expiration(
             .unwrap_or_default();
     predicates_expiring_at_block.push(predicate_key.to_owned());
     let serialized_expiring_predicates = json!(predicates_expiring_at_block).to_string();
-    if let Err(e) =
-        predicates_db_conn.hset::<_, _, _, ()>(&key, "predicates", &serialized_expiring_predicates)
-    {
+    if let Err(e) = predicates_db_conn.hset(&key, "predicates", &serialized_expiring_predicates) {
         warn!(
             ctx.expect_logger(),
             "Error updating expired predicates index: {}",
@@ -1167,15 +2118,14 @@ fn insert_predicate_expiration(
 fn get_predicates_expiring_at_block(
     chain: &Chain,
     block_index: u64,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) -> Option<Vec<String>> {
     let key = get_predicate_expiration_key(chain, block_index);
-    match predicates_db_conn.hget::<_, _, String>(key.to_string(), "predicates") {
-        Ok(ref payload) => match serde_json::from_str(payload) {
+    match predicates_db_conn.hget(&key, "predicates") {
+        Ok(Some(ref payload)) => match serde_json::from_str(payload) {
             Ok(data) => {
-                if let Err(e) = predicates_db_conn.hdel::<_, _, u64>(key.to_string(), "predicates")
-                {
+                if let Err(e) = predicates_db_conn.hdel(&key, "predicates") {
                     warn!(
                         ctx.expect_logger(),
                         "Error removing expired predicates index: {}",
@@ -1193,13 +2143,12 @@ fn get_predicates_expiring_at_block(
 pub fn update_predicate_status(
     predicate_key: &str,
     status: PredicateStatus,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
+    let previous_status = retrieve_predicate_status(predicate_key, predicates_db_conn);
     let serialized_status = json!(status).to_string();
-    if let Err(e) =
-        predicates_db_conn.hset::<_, _, _, ()>(&predicate_key, "status", &serialized_status)
-    {
+    if let Err(e) = predicates_db_conn.hset(predicate_key, "status", &serialized_status) {
         warn!(
             ctx.expect_logger(),
             "Error updating status for {}: {}",
@@ -1211,19 +2160,30 @@ pub fn update_predicate_status(
             ctx.expect_logger(),
             "Updating predicate {predicate_key} status: {serialized_status}"
         );
+        log_predicate_transition(
+            predicate_key,
+            previous_status.as_ref(),
+            Some(&status),
+            predicates_db_conn,
+            ctx,
+        );
+        if let Some(event_type) =
+            PredicateLifecycleEventType::from_transition(previous_status.as_ref(), &status)
+        {
+            let event = PredicateLifecycleEvent::new(predicate_key, event_type, Some(status));
+            publish_lifecycle_event(&event, predicates_db_conn, ctx);
+        }
     }
 }
 
 fn update_predicate_spec(
     predicate_key: &str,
     spec: &ChainhookInstance,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
     ctx: &Context,
 ) {
     let serialized_spec = json!(spec).to_string();
-    if let Err(e) =
-        predicates_db_conn.hset::<_, _, _, ()>(&predicate_key, "specification", &serialized_spec)
-    {
+    if let Err(e) = predicates_db_conn.hset(predicate_key, "specification", &serialized_spec) {
         warn!(
             ctx.expect_logger(),
             "Error updating status for {}: {}",
@@ -1238,12 +2198,157 @@ fn update_predicate_spec(
     }
 }
 
+const ACK_CURSOR_FIELD: &str = "ack_cursor";
+
+/// Records the cursor acknowledged by the receiver of a predicate requiring acks.
+fn set_predicate_ack_cursor(
+    predicate_key: &str,
+    cursor: u64,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    if let Err(e) = predicates_db_conn.hset(predicate_key, ACK_CURSOR_FIELD, &cursor.to_string()) {
+        warn!(
+            ctx.expect_logger(),
+            "Error updating ack cursor for {}: {}",
+            predicate_key,
+            e.to_string()
+        );
+    }
+}
+
+fn retrieve_predicate_ack_cursor(
+    predicate_key: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Option<u64> {
+    predicates_db_conn
+        .hget(predicate_key, ACK_CURSOR_FIELD)
+        .ok()
+        .flatten()
+        .and_then(|cursor| cursor.parse().ok())
+}
+
+const REGISTERED_AT_FIELD: &str = "registered_at";
+
+/// Records when a predicate was registered, in seconds since the epoch. Predicates registered
+/// again on startup keep their original registration time.
+fn set_predicate_registration_time(
+    predicate_key: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    let now_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Could not get current time in ms")
+        .as_secs();
+    if let Err(e) =
+        predicates_db_conn.hset_nx(predicate_key, REGISTERED_AT_FIELD, &now_secs.to_string())
+    {
+        warn!(
+            ctx.expect_logger(),
+            "Error recording registration time for {}: {}",
+            predicate_key,
+            e.to_string()
+        );
+    }
+}
+
+pub fn retrieve_predicate_registration_time(
+    predicate_key: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Option<u64> {
+    predicates_db_conn
+        .hget(predicate_key, REGISTERED_AT_FIELD)
+        .ok()
+        .flatten()
+        .and_then(|registered_at| registered_at.parse().ok())
+}
+
+/// Hash mapping the uuids of the predicates registered outside of the default namespace to
+/// their namespace, so that their keys can be found from their uuid.
+const PREDICATE_NAMESPACES_KEY: &str = "predicate_namespaces";
+
+fn set_predicate_namespace(
+    predicate: &ChainhookInstance,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) {
+    if predicate.namespace() == DEFAULT_NAMESPACE {
+        return;
+    }
+    if let Err(e) = predicates_db_conn.hset(
+        PREDICATE_NAMESPACES_KEY,
+        predicate.uuid(),
+        predicate.namespace(),
+    ) {
+        warn!(
+            ctx.expect_logger(),
+            "Error recording namespace of predicate {}: {}",
+            predicate.uuid(),
+            e.to_string()
+        );
+    }
+}
+
+/// Returns the key of the predicate registered with this uuid, in whichever namespace.
+pub fn retrieve_predicate_key(
+    predicate_uuid: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> String {
+    let namespace = predicates_db_conn
+        .hget(PREDICATE_NAMESPACES_KEY, predicate_uuid)
+        .ok()
+        .flatten();
+    ChainhookInstance::namespaced_key(namespace.as_deref(), predicate_uuid)
+}
+
+/// Uuids identify predicates across namespaces, so a uuid registered in a namespace can't be
+/// used in another one.
+pub fn is_predicate_uuid_in_use(
+    predicate_uuid: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+    ctx: &Context,
+) -> bool {
+    let predicate_key = retrieve_predicate_key(predicate_uuid, predicates_db_conn);
+    matches!(
+        get_entry_from_predicates_db(&predicate_key, predicates_db_conn, ctx),
+        Ok(Some(_))
+    )
+}
+
+/// For predicates requiring acks, moves the scan of a predicate back to the block following
+/// its last acknowledged cursor, so that the occurrences left unacknowledged are delivered
+/// again. Other predicates keep their `scanning_data`.
+fn rewind_to_ack_cursor(
+    predicate: &ChainhookInstance,
+    scanning_data: Option<ScanningData>,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Option<ScanningData> {
+    let requires_ack = match predicate {
+        ChainhookInstance::Bitcoin(spec) => spec.action.requires_ack(),
+        ChainhookInstance::Stacks(spec) => spec.action.requires_ack(),
+    };
+    if !requires_ack {
+        return scanning_data;
+    }
+    let Some(cursor) = retrieve_predicate_ack_cursor(&predicate.key(), predicates_db_conn) else {
+        return scanning_data;
+    };
+    let mut scanning_data = scanning_data.unwrap_or_default();
+    if scanning_data.last_evaluated_block_height == 0
+        || cursor + 1 < scanning_data.last_evaluated_block_height
+    {
+        scanning_data.last_evaluated_block_height = cursor + 1;
+    }
+    Some(scanning_data)
+}
+
 fn retrieve_predicate_status(
     predicate_key: &str,
-    predicates_db_conn: &mut Connection,
+    predicates_db_conn: &mut dyn PredicatesDb,
 ) -> Option<PredicateStatus> {
-    match predicates_db_conn.hget::<_, _, String>(predicate_key.to_string(), "status") {
-        Ok(ref payload) => match serde_json::from_str(payload) {
+    match predicates_db_conn.hget(predicate_key, "status") {
+        Ok(Some(ref payload)) => match serde_json::from_str(payload) {
             Ok(data) => Some(data),
             Err(_) => None,
         },
@@ -1253,18 +2358,14 @@ fn retrieve_predicate_status(
 
 pub fn open_readwrite_predicates_db_conn(
     config: &PredicatesApiConfig,
-) -> Result<Connection, String> {
-    let redis_uri = &config.database_uri;
-    let client = redis::Client::open(redis_uri.clone()).unwrap();
-    client
-        .get_connection()
-        .map_err(|e| format!("unable to connect to db: {}", e))
+) -> Result<Box<dyn PredicatesDb>, String> {
+    open_predicates_db(&config.database_uri)
 }
 
 pub fn open_readwrite_predicates_db_conn_verbose(
     config: &PredicatesApiConfig,
     ctx: &Context,
-) -> Result<Connection, String> {
+) -> Result<Box<dyn PredicatesDb>, String> {
     let res = open_readwrite_predicates_db_conn(config);
     if let Err(ref e) = res {
         error!(ctx.expect_logger(), "{}", e.to_string());
@@ -1276,8 +2377,9 @@ pub fn open_readwrite_predicates_db_conn_verbose(
 pub fn open_readwrite_predicates_db_conn_or_panic(
     config: &PredicatesApiConfig,
     ctx: &Context,
-) -> Connection {
-    open_readwrite_predicates_db_conn_verbose(config, ctx).expect("unable to open redis conn")
+) -> Box<dyn PredicatesDb> {
+    open_readwrite_predicates_db_conn_verbose(config, ctx)
+        .expect("unable to open predicates db conn")
 }
 
 #[cfg(test)]
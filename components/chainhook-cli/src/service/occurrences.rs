@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use chainhook_sdk::observer::OccurrenceCountStore;
+
+use crate::config::PredicatesApiConfig;
+
+use super::open_readwrite_predicates_db_conn;
+use super::predicates_db::PredicatesDb;
+
+pub const OCCURRENCE_COUNTS_KEY: &str = "chainhook:occurrences";
+
+/// An [OccurrenceCountStore] storing the number of occurrences of each predicate in the
+/// predicates database, so that they survive a restart of the service.
+pub struct PredicatesDbOccurrenceCountStore {
+    config: PredicatesApiConfig,
+}
+
+impl PredicatesDbOccurrenceCountStore {
+    pub fn new(config: &PredicatesApiConfig) -> Self {
+        PredicatesDbOccurrenceCountStore {
+            config: config.clone(),
+        }
+    }
+}
+
+impl OccurrenceCountStore for PredicatesDbOccurrenceCountStore {
+    fn load(&self) -> Result<HashMap<String, u64>, String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let entries = conn
+            .hgetall(OCCURRENCE_COUNTS_KEY)
+            .map_err(|e| format!("unable to read occurrences: {}", e))?;
+        entries
+            .into_iter()
+            .map(|(predicate_uuid, occurrences)| {
+                occurrences
+                    .parse()
+                    .map(|occurrences| (predicate_uuid, occurrences))
+                    .map_err(|e| format!("unable to read occurrences: {}", e))
+            })
+            .collect()
+    }
+
+    fn set(&self, predicate_uuid: &str, occurrences: u64) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        conn.hset(
+            OCCURRENCE_COUNTS_KEY,
+            predicate_uuid,
+            &occurrences.to_string(),
+        )
+        .map_err(|e| format!("unable to record occurrences: {}", e))
+    }
+
+    fn remove(&self, predicate_uuid: &str) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        conn.hdel(OCCURRENCE_COUNTS_KEY, predicate_uuid)
+            .map_err(|e| format!("unable to remove occurrences: {}", e))
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chainhook_sdk::observer::{
+    DeliveryOutbox, DeliveryRecord, DeliveryRecordStore, PendingDelivery, DELIVERY_HISTORY_SIZE,
+    MAX_DELIVERED_IDS,
+};
+
+use crate::config::PredicatesApiConfig;
+
+use crate::storage::encryption::{decrypt_string_payload, encrypt_string_payload};
+
+use super::open_readwrite_predicates_db_conn;
+use super::predicates_db::PredicatesDb;
+
+pub const DELIVERY_OUTBOX_KEY: &str = "chainhook:outbox";
+pub const DELIVERED_IDS_KEY: &str = "chainhook:delivered";
+/// Key of the hash counting the reorgs of each chain.
+pub const REORG_SEQUENCES_KEY: &str = "chainhook:reorg_sequences";
+
+/// Key of the list logging the last deliveries of a predicate, most recent first.
+pub fn delivery_log_key(predicate_uuid: &str) -> String {
+    format!("chainhook:deliveries:{}", predicate_uuid)
+}
+
+/// Key of the list logging the outcomes of the last deliveries of a predicate, most recent
+/// first.
+pub fn delivery_records_key(predicate_uuid: &str) -> String {
+    format!("chainhook:delivery_records:{}", predicate_uuid)
+}
+
+/// Returns the outcomes of the last deliveries of a predicate, most recent first.
+pub fn get_delivery_records(
+    predicate_uuid: &str,
+    predicates_db_conn: &mut dyn PredicatesDb,
+) -> Result<Vec<DeliveryRecord>, String> {
+    let entries = predicates_db_conn
+        .lrange_all(&delivery_records_key(predicate_uuid))
+        .map_err(|e| format!("unable to read delivery records: {}", e))?;
+    entries
+        .iter()
+        .map(|entry| {
+            serde_json::from_str(&decrypt_string_payload(entry)?)
+                .map_err(|e| format!("unable to deserialize delivery record: {}", e))
+        })
+        .collect()
+}
+
+/// A [DeliveryRecordStore] logging the outcomes of the deliveries in the predicates database,
+/// so that they can be reported once the service stopped.
+pub struct PredicatesDbDeliveryRecordStore {
+    config: PredicatesApiConfig,
+}
+
+impl PredicatesDbDeliveryRecordStore {
+    pub fn new(config: &PredicatesApiConfig) -> Self {
+        PredicatesDbDeliveryRecordStore {
+            config: config.clone(),
+        }
+    }
+}
+
+impl DeliveryRecordStore for PredicatesDbDeliveryRecordStore {
+    fn log(&self, uuid: &str, record: &DeliveryRecord) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let serialized = serde_json::to_string(record)
+            .map_err(|e| format!("unable to serialize delivery record: {}", e))?;
+        let serialized = encrypt_string_payload(&serialized)?;
+        conn.lpush_capped(
+            &delivery_records_key(uuid),
+            &serialized,
+            DELIVERY_HISTORY_SIZE,
+        )
+        .map_err(|e| format!("unable to log delivery record: {}", e))
+    }
+
+    fn remove(&self, uuid: &str) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        conn.del(&delivery_records_key(uuid))
+            .map_err(|e| format!("unable to remove delivery records: {}", e))
+    }
+}
+
+/// A [DeliveryOutbox] storing the deliveries in flight in the predicates database, so that
+/// they survive a restart of the service.
+pub struct PredicatesDbDeliveryOutbox {
+    config: PredicatesApiConfig,
+}
+
+impl PredicatesDbDeliveryOutbox {
+    pub fn new(config: &PredicatesApiConfig) -> Self {
+        PredicatesDbDeliveryOutbox {
+            config: config.clone(),
+        }
+    }
+}
+
+impl DeliveryOutbox for PredicatesDbDeliveryOutbox {
+    fn insert(&self, delivery: &PendingDelivery) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let serialized = serde_json::to_string(delivery)
+            .map_err(|e| format!("unable to serialize delivery: {}", e))?;
+        let serialized = encrypt_string_payload(&serialized)?;
+        conn.hset(DELIVERY_OUTBOX_KEY, &delivery.id, &serialized)
+            .map_err(|e| format!("unable to record delivery: {}", e))
+    }
+
+    fn remove(&self, delivery_id: &str) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        conn.hdel(DELIVERY_OUTBOX_KEY, delivery_id)
+            .map_err(|e| format!("unable to remove delivery: {}", e))
+    }
+
+    fn pending(&self) -> Result<Vec<PendingDelivery>, String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let entries = conn
+            .hgetall(DELIVERY_OUTBOX_KEY)
+            .map_err(|e| format!("unable to read deliveries: {}", e))?;
+        entries
+            .values()
+            .map(|entry| {
+                serde_json::from_str(&decrypt_string_payload(entry)?)
+                    .map_err(|e| format!("unable to deserialize delivery: {}", e))
+            })
+            .collect()
+    }
+
+    fn mark_delivered(&self, delivery_id: &str) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        // Only keep the most recent ids
+        conn.zadd_capped(DELIVERED_IDS_KEY, delivery_id, now_ms, MAX_DELIVERED_IDS)
+            .map_err(|e| format!("unable to mark delivery as completed: {}", e))
+    }
+
+    fn is_delivered(&self, delivery_id: &str) -> Result<bool, String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let score = conn
+            .zscore(DELIVERED_IDS_KEY, delivery_id)
+            .map_err(|e| format!("unable to read completed deliveries: {}", e))?;
+        Ok(score.is_some())
+    }
+
+    fn log_delivery(&self, delivery: &PendingDelivery) -> Result<(), String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let key = delivery_log_key(&delivery.predicate_uuid);
+        let serialized = serde_json::to_string(delivery)
+            .map_err(|e| format!("unable to serialize delivery: {}", e))?;
+        let serialized = encrypt_string_payload(&serialized)?;
+        conn.lpush_capped(&key, &serialized, DELIVERY_HISTORY_SIZE)
+            .map_err(|e| format!("unable to log delivery: {}", e))
+    }
+
+    fn logged_delivery(
+        &self,
+        predicate_uuid: &str,
+        delivery_id: &str,
+    ) -> Result<Option<PendingDelivery>, String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        let entries = conn
+            .lrange_all(&delivery_log_key(predicate_uuid))
+            .map_err(|e| format!("unable to read logged deliveries: {}", e))?;
+        for entry in entries.iter() {
+            let delivery: PendingDelivery =
+                serde_json::from_str(&decrypt_string_payload(entry)?)
+                    .map_err(|e| format!("unable to deserialize delivery: {}", e))?;
+            if delivery.id == delivery_id {
+                return Ok(Some(delivery));
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_reorg_sequence(&self, chain: &str) -> Result<u64, String> {
+        let mut conn = open_readwrite_predicates_db_conn(&self.config)?;
+        conn.hincr(REORG_SEQUENCES_KEY, chain)
+            .map_err(|e| format!("unable to record reorg: {}", e))
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crossbeam_channel::{unbounded, Sender};
+use rocksdb::{Direction, IteratorMode, WriteBatch, DB};
+
+use crate::storage::open_readwrite_predicates_db_conn;
+
+use super::PredicatesDb;
+
+const HASH_PREFIX: u8 = b'h';
+const LIST_PREFIX: u8 = b'l';
+const LIST_SEQ_PREFIX: u8 = b'L';
+const SORTED_SET_PREFIX: u8 = b'z';
+const SORTED_SET_INDEX_PREFIX: u8 = b's';
+
+/// Prefixes of the records holding the data of a key, as opposed to its metadata.
+const DATA_PREFIXES: [u8; 3] = [HASH_PREFIX, LIST_PREFIX, SORTED_SET_PREFIX];
+
+/// The embedded dbs opened by this process. RocksDB can't be opened twice, so every connection
+/// to a given path shares the same handle.
+static EMBEDDED_DBS: OnceLock<Mutex<HashMap<PathBuf, Arc<EmbeddedDbHandle>>>> = OnceLock::new();
+
+struct EmbeddedDbHandle {
+    db: DB,
+    /// Serializes the read-modify-write operations.
+    write_lock: Mutex<()>,
+    subscribers: Mutex<HashMap<String, Vec<Sender<String>>>>,
+}
+
+/// Predicates db held in a RocksDB instance embedded in the process, for deployments without
+/// any Redis or Postgres. Channels are only delivered within the process.
+///
+/// Records are keyed by a prefix identifying their Redis data type, followed by the key and,
+/// after a `0` separator, the field, the position or the member they hold:
+/// - `h{key}\0{field}`: hash entries;
+/// - `l{key}\0{position}`: list entries, `L{key}` holding the next position;
+/// - `z{key}\0{member}`: sorted set scores, indexed by `s{key}\0{score}{member}`.
+pub struct EmbeddedPredicatesDb {
+    handle: Arc<EmbeddedDbHandle>,
+}
+
+impl EmbeddedPredicatesDb {
+    pub fn open(path: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(path)
+            .map_err(|e| format!("unable to create predicates db directory {}: {}", path, e))?;
+        let path = Path::new(path)
+            .canonicalize()
+            .map_err(|e| format!("unable to resolve predicates db path {}: {}", path, e))?;
+        let mut dbs = EMBEDDED_DBS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .map_err(|_| "unable to lock predicates dbs".to_string())?;
+        let handle = match dbs.get(&path) {
+            Some(handle) => handle.clone(),
+            None => {
+                let handle = Arc::new(EmbeddedDbHandle {
+                    db: open_readwrite_predicates_db_conn(&path)?,
+                    write_lock: Mutex::new(()),
+                    subscribers: Mutex::new(HashMap::new()),
+                });
+                dbs.insert(path, handle.clone());
+                handle
+            }
+        };
+        Ok(EmbeddedPredicatesDb { handle })
+    }
+
+    fn db(&self) -> &DB {
+        &self.handle.db
+    }
+
+    fn lock_writes(&self) -> Result<std::sync::MutexGuard<'_, ()>, String> {
+        self.handle
+            .write_lock
+            .lock()
+            .map_err(|_| "unable to lock predicates db".to_string())
+    }
+
+    fn get_string(&self, key: &[u8]) -> Result<Option<String>, String> {
+        self.db()
+            .get(key)
+            .map_err(|e| format!("unable to read predicates db: {}", e))?
+            .map(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    fn get_score(&self, key: &str, member: &str) -> Result<Option<u64>, String> {
+        self.db()
+            .get(get_record_key(SORTED_SET_PREFIX, key, member.as_bytes()))
+            .map_err(|e| format!("unable to read predicates db: {}", e))?
+            .map(|bytes| read_u64(&bytes))
+            .transpose()
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), String> {
+        self.db()
+            .write(batch)
+            .map_err(|e| format!("unable to write predicates db: {}", e))
+    }
+
+    /// Adds to `batch` the writes appending `value` to the list `key`.
+    fn push_list_entry(
+        &self,
+        batch: &mut WriteBatch,
+        key: &str,
+        value: &str,
+    ) -> Result<(), String> {
+        let seq_key = get_record_prefix(LIST_SEQ_PREFIX, key);
+        let position = match self
+            .db()
+            .get(&seq_key)
+            .map_err(|e| format!("unable to read predicates db: {}", e))?
+        {
+            Some(bytes) => read_u64(&bytes)?,
+            None => 0,
+        };
+        batch.put(&seq_key, (position + 1).to_be_bytes());
+        batch.put(
+            get_record_key(LIST_PREFIX, key, &position.to_be_bytes()),
+            value,
+        );
+        Ok(())
+    }
+
+    /// Returns the records whose key starts with `prefix`, in key order.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String> {
+        let mut entries = vec![];
+        for entry in self
+            .db()
+            .iterator(IteratorMode::From(prefix, Direction::Forward))
+        {
+            let (key, value) = entry.map_err(|e| format!("unable to read predicates db: {}", e))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+        Ok(entries)
+    }
+}
+
+fn get_record_prefix(prefix: u8, key: &str) -> Vec<u8> {
+    let mut record_key = Vec::with_capacity(key.len() + 2);
+    record_key.push(prefix);
+    record_key.extend_from_slice(key.as_bytes());
+    record_key.push(0);
+    record_key
+}
+
+fn get_record_key(prefix: u8, key: &str, suffix: &[u8]) -> Vec<u8> {
+    let mut record_key = get_record_prefix(prefix, key);
+    record_key.extend_from_slice(suffix);
+    record_key
+}
+
+fn read_u64(bytes: &[u8]) -> Result<u64, String> {
+    let bytes: [u8; 8] = bytes
+        .try_into()
+        .map_err(|_| "unable to read predicates db: corrupted number".to_string())?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn to_string(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+}
+
+/// Matches `value` against a glob `pattern`, as accepted by Redis' `SCAN`, where `*` matches any
+/// sequence of characters.
+pub fn matches_glob_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(head) = parts.next() else {
+        return value.is_empty();
+    };
+    let Some(mut rest) = value.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(tail) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= tail.len() && rest.ends_with(tail)
+}
+
+impl PredicatesDb for EmbeddedPredicatesDb {
+    fn ping(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>, String> {
+        self.get_string(&get_record_key(HASH_PREFIX, key, field.as_bytes()))
+    }
+
+    fn hgetall(&mut self, key: &str) -> Result<HashMap<String, String>, String> {
+        let prefix = get_record_prefix(HASH_PREFIX, key);
+        self.scan_prefix(&prefix)?
+            .into_iter()
+            .map(|(field, value)| Ok((to_string(&field[prefix.len()..])?, to_string(&value)?)))
+            .collect()
+    }
+
+    fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        self.db()
+            .put(get_record_key(HASH_PREFIX, key, field.as_bytes()), value)
+            .map_err(|e| format!("unable to write predicates db: {}", e))
+    }
+
+    fn hset_nx(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let field_key = get_record_key(HASH_PREFIX, key, field.as_bytes());
+        if self.get_string(&field_key)?.is_some() {
+            return Ok(());
+        }
+        self.db()
+            .put(field_key, value)
+            .map_err(|e| format!("unable to write predicates db: {}", e))
+    }
+
+    fn hdel(&mut self, key: &str, field: &str) -> Result<(), String> {
+        self.db()
+            .delete(get_record_key(HASH_PREFIX, key, field.as_bytes()))
+            .map_err(|e| format!("unable to write predicates db: {}", e))
+    }
+
+    fn hincr(&mut self, key: &str, field: &str) -> Result<u64, String> {
+        let _guard = self.lock_writes()?;
+        let field_key = get_record_key(HASH_PREFIX, key, field.as_bytes());
+        let value = match self.get_string(&field_key)? {
+            Some(value) => value
+                .parse::<u64>()
+                .map_err(|_| "unable to read predicates db: corrupted number".to_string())?,
+            None => 0,
+        } + 1;
+        self.db()
+            .put(field_key, value.to_string())
+            .map_err(|e| format!("unable to write predicates db: {}", e))?;
+        Ok(value)
+    }
+
+    fn del(&mut self, key: &str) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let mut batch = WriteBatch::default();
+        for prefix in DATA_PREFIXES.iter().chain([&SORTED_SET_INDEX_PREFIX]) {
+            let from = get_record_prefix(*prefix, key);
+            let mut to = from.clone();
+            *to.last_mut().unwrap() = 1;
+            batch.delete_range(from, to);
+        }
+        batch.delete(get_record_prefix(LIST_SEQ_PREFIX, key));
+        self.write(batch)
+    }
+
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>, String> {
+        let literal_prefix = pattern.split('*').next().unwrap_or_default();
+        let mut keys = BTreeSet::new();
+        for prefix in DATA_PREFIXES {
+            let mut scan_prefix = vec![prefix];
+            scan_prefix.extend_from_slice(literal_prefix.as_bytes());
+            for (record_key, _) in self.scan_prefix(&scan_prefix)? {
+                let Some(end) = record_key.iter().position(|b| *b == 0) else {
+                    continue;
+                };
+                let key = to_string(&record_key[1..end])?;
+                if matches_glob_pattern(pattern, &key) {
+                    keys.insert(key);
+                }
+            }
+        }
+        Ok(keys.into_iter().collect())
+    }
+
+    fn lpush(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let mut batch = WriteBatch::default();
+        self.push_list_entry(&mut batch, key, value)?;
+        self.write(batch)
+    }
+
+    fn lpush_capped(&mut self, key: &str, value: &str, max_len: usize) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let mut batch = WriteBatch::default();
+        self.push_list_entry(&mut batch, key, value)?;
+        // Entries are ordered by position, oldest first
+        let entries = self.scan_prefix(&get_record_prefix(LIST_PREFIX, key))?;
+        let excess = (entries.len() + 1).saturating_sub(max_len);
+        for (record_key, _) in entries.into_iter().take(excess) {
+            batch.delete(record_key);
+        }
+        self.write(batch)
+    }
+
+    fn ltrim(&mut self, key: &str, max_len: usize) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let mut batch = WriteBatch::default();
+        // Entries are ordered by position, oldest first
+        let entries = self.scan_prefix(&get_record_prefix(LIST_PREFIX, key))?;
+        let excess = entries.len().saturating_sub(max_len);
+        for (record_key, _) in entries.into_iter().take(excess) {
+            batch.delete(record_key);
+        }
+        self.write(batch)
+    }
+
+    fn lrange_all(&mut self, key: &str) -> Result<Vec<String>, String> {
+        self.scan_prefix(&get_record_prefix(LIST_PREFIX, key))?
+            .into_iter()
+            .rev()
+            .map(|(_, value)| to_string(&value))
+            .collect()
+    }
+
+    fn zadd_capped(
+        &mut self,
+        key: &str,
+        member: &str,
+        score: u64,
+        max_len: usize,
+    ) -> Result<(), String> {
+        let _guard = self.lock_writes()?;
+        let mut batch = WriteBatch::default();
+        let mut members_count = self
+            .scan_prefix(&get_record_prefix(SORTED_SET_PREFIX, key))?
+            .len();
+        match self.get_score(key, member)? {
+            Some(previous_score) => {
+                let mut index_suffix = previous_score.to_be_bytes().to_vec();
+                index_suffix.extend_from_slice(member.as_bytes());
+                batch.delete(get_record_key(SORTED_SET_INDEX_PREFIX, key, &index_suffix));
+            }
+            None => members_count += 1,
+        }
+        let mut index_suffix = score.to_be_bytes().to_vec();
+        index_suffix.extend_from_slice(member.as_bytes());
+        batch.put(
+            get_record_key(SORTED_SET_PREFIX, key, member.as_bytes()),
+            score.to_be_bytes(),
+        );
+        batch.put(
+            get_record_key(SORTED_SET_INDEX_PREFIX, key, &index_suffix),
+            b"",
+        );
+        self.write(batch)?;
+
+        // The index is ordered by score, lowest first
+        let index_prefix = get_record_prefix(SORTED_SET_INDEX_PREFIX, key);
+        let excess = members_count.saturating_sub(max_len);
+        let mut batch = WriteBatch::default();
+        for (index_key, _) in self.scan_prefix(&index_prefix)?.into_iter().take(excess) {
+            let evicted_member = &index_key[index_prefix.len() + 8..];
+            batch.delete(get_record_key(SORTED_SET_PREFIX, key, evicted_member));
+            batch.delete(index_key);
+        }
+        self.write(batch)
+    }
+
+    fn zscore(&mut self, key: &str, member: &str) -> Result<Option<u64>, String> {
+        self.get_score(key, member)
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        let mut subscribers = self
+            .handle
+            .subscribers
+            .lock()
+            .map_err(|_| "unable to lock predicates db subscribers".to_string())?;
+        if let Some(channel_subscribers) = subscribers.get_mut(channel) {
+            channel_subscribers.retain(|tx| tx.send(payload.to_string()).is_ok());
+        }
+        Ok(())
+    }
+
+    fn subscribe(
+        &mut self,
+        channel: &str,
+        on_message: &mut dyn FnMut(String),
+    ) -> Result<(), String> {
+        let (messages_tx, messages_rx) = unbounded();
+        self.handle
+            .subscribers
+            .lock()
+            .map_err(|_| "unable to lock predicates db subscribers".to_string())?
+            .entry(channel.to_string())
+            .or_default()
+            .push(messages_tx);
+        for message in messages_rx.iter() {
+            on_message(message);
+        }
+        Err("connection to db lost".to_string())
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use redis::{Commands, Connection};
+
+use self::embedded::EmbeddedPredicatesDb;
+
+mod embedded;
+#[cfg(feature = "postgres")]
+mod postgres;
+
+/// Store of the predicates registered through the HTTP API, their status, and the state of
+/// their deliveries. Its operations follow the Redis data types the predicates db was first
+/// built on: hashes, capped lists, capped sorted sets, and channels.
+pub trait PredicatesDb: Send {
+    /// Checks that the db answers.
+    fn ping(&mut self) -> Result<(), String>;
+
+    fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>, String>;
+
+    fn hgetall(&mut self, key: &str) -> Result<HashMap<String, String>, String>;
+
+    fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<(), String>;
+
+    /// Sets `field` only if it isn't set yet.
+    fn hset_nx(&mut self, key: &str, field: &str, value: &str) -> Result<(), String>;
+
+    fn hdel(&mut self, key: &str, field: &str) -> Result<(), String>;
+
+    /// Increments the number held by `field`, starting from 0, and returns it.
+    fn hincr(&mut self, key: &str, field: &str) -> Result<u64, String>;
+
+    /// Removes `key`, whichever its type.
+    fn del(&mut self, key: &str) -> Result<(), String>;
+
+    /// Returns the keys matching `pattern`, where `*` matches any sequence of characters.
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>, String>;
+
+    /// Pushes `value` at the head of the list `key`.
+    fn lpush(&mut self, key: &str, value: &str) -> Result<(), String>;
+
+    /// Pushes `value` at the head of the list `key`, then drops the entries beyond `max_len`.
+    fn lpush_capped(&mut self, key: &str, value: &str, max_len: usize) -> Result<(), String>;
+
+    /// Drops the entries of the list `key` beyond `max_len`, the oldest first.
+    fn ltrim(&mut self, key: &str, max_len: usize) -> Result<(), String>;
+
+    /// Returns the entries of the list `key`, most recent first.
+    fn lrange_all(&mut self, key: &str) -> Result<Vec<String>, String>;
+
+    /// Adds `member` to the sorted set `key`, then drops the members with the lowest scores
+    /// beyond `max_len`.
+    fn zadd_capped(
+        &mut self,
+        key: &str,
+        member: &str,
+        score: u64,
+        max_len: usize,
+    ) -> Result<(), String>;
+
+    fn zscore(&mut self, key: &str, member: &str) -> Result<Option<u64>, String>;
+
+    /// Publishes `payload` to the subscribers of `channel`. Payloads published while no one is
+    /// subscribed are dropped.
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String>;
+
+    /// Calls `on_message` with the payloads published to `channel`, until the connection to the
+    /// db is lost.
+    fn subscribe(
+        &mut self,
+        channel: &str,
+        on_message: &mut dyn FnMut(String),
+    ) -> Result<(), String>;
+}
+
+const EMBEDDED_URI_SCHEME: &str = "rocksdb://";
+
+/// Opens a connection to the predicates db at `database_uri`: Postgres for `postgres://` and
+/// `postgresql://` uris, an embedded RocksDB instance for `rocksdb://<path>` uris, Redis
+/// otherwise.
+pub fn open_predicates_db(database_uri: &str) -> Result<Box<dyn PredicatesDb>, String> {
+    if is_postgres_uri(database_uri) {
+        return open_postgres_predicates_db(database_uri);
+    }
+    if let Some(path) = get_embedded_db_path(database_uri) {
+        return Ok(Box::new(EmbeddedPredicatesDb::open(path)?));
+    }
+    let client =
+        redis::Client::open(database_uri).map_err(|e| format!("unable to connect to db: {}", e))?;
+    let conn = client
+        .get_connection()
+        .map_err(|e| format!("unable to connect to db: {}", e))?;
+    Ok(Box::new(conn))
+}
+
+pub fn is_postgres_uri(database_uri: &str) -> bool {
+    database_uri.starts_with("postgres://") || database_uri.starts_with("postgresql://")
+}
+
+/// Returns the path of the embedded predicates db designated by `database_uri`, if any.
+pub fn get_embedded_db_path(database_uri: &str) -> Option<&str> {
+    database_uri.strip_prefix(EMBEDDED_URI_SCHEME)
+}
+
+/// Returns the name of the predicates db's engine, as reported by the health checks.
+pub fn get_predicates_db_name(database_uri: &str) -> &'static str {
+    if is_postgres_uri(database_uri) {
+        "postgres"
+    } else if get_embedded_db_path(database_uri).is_some() {
+        "predicates_rocksdb"
+    } else {
+        "redis"
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn open_postgres_predicates_db(database_uri: &str) -> Result<Box<dyn PredicatesDb>, String> {
+    let db = postgres::PostgresPredicatesDb::open(database_uri)?;
+    Ok(Box::new(db))
+}
+
+#[cfg(not(feature = "postgres"))]
+fn open_postgres_predicates_db(_database_uri: &str) -> Result<Box<dyn PredicatesDb>, String> {
+    Err("postgres support is not enabled (requires the `postgres` feature)".to_string())
+}
+
+impl PredicatesDb for Connection {
+    fn ping(&mut self) -> Result<(), String> {
+        redis::cmd("PING")
+            .query::<String>(self)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>, String> {
+        Commands::hget(self, key, field).map_err(|e| e.to_string())
+    }
+
+    fn hgetall(&mut self, key: &str) -> Result<HashMap<String, String>, String> {
+        Commands::hgetall(self, key).map_err(|e| e.to_string())
+    }
+
+    fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        Commands::hset(self, key, field, value).map_err(|e| e.to_string())
+    }
+
+    fn hset_nx(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        Commands::hset_nx(self, key, field, value).map_err(|e| e.to_string())
+    }
+
+    fn hdel(&mut self, key: &str, field: &str) -> Result<(), String> {
+        Commands::hdel(self, key, field).map_err(|e| e.to_string())
+    }
+
+    fn hincr(&mut self, key: &str, field: &str) -> Result<u64, String> {
+        Commands::hincr(self, key, field, 1u64).map_err(|e| e.to_string())
+    }
+
+    fn del(&mut self, key: &str) -> Result<(), String> {
+        Commands::del(self, key).map_err(|e| e.to_string())
+    }
+
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>, String> {
+        Commands::scan_match(self, pattern)
+            .map(|keys| keys.collect())
+            .map_err(|e| e.to_string())
+    }
+
+    fn lpush(&mut self, key: &str, value: &str) -> Result<(), String> {
+        Commands::lpush(self, key, value).map_err(|e| e.to_string())
+    }
+
+    fn lpush_capped(&mut self, key: &str, value: &str, max_len: usize) -> Result<(), String> {
+        Commands::lpush::<_, _, ()>(self, key, value).map_err(|e| e.to_string())?;
+        Commands::ltrim(self, key, 0, max_len as isize - 1).map_err(|e| e.to_string())
+    }
+
+    fn ltrim(&mut self, key: &str, max_len: usize) -> Result<(), String> {
+        Commands::ltrim(self, key, 0, max_len as isize - 1).map_err(|e| e.to_string())
+    }
+
+    fn lrange_all(&mut self, key: &str) -> Result<Vec<String>, String> {
+        Commands::lrange(self, key, 0, -1).map_err(|e| e.to_string())
+    }
+
+    fn zadd_capped(
+        &mut self,
+        key: &str,
+        member: &str,
+        score: u64,
+        max_len: usize,
+    ) -> Result<(), String> {
+        Commands::zadd::<_, _, _, ()>(self, key, member, score).map_err(|e| e.to_string())?;
+        Commands::zremrangebyrank(self, key, 0, -(max_len as isize) - 1).map_err(|e| e.to_string())
+    }
+
+    fn zscore(&mut self, key: &str, member: &str) -> Result<Option<u64>, String> {
+        Commands::zscore(self, key, member).map_err(|e| e.to_string())
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        Commands::publish(self, channel, payload).map_err(|e| e.to_string())
+    }
+
+    fn subscribe(
+        &mut self,
+        channel: &str,
+        on_message: &mut dyn FnMut(String),
+    ) -> Result<(), String> {
+        let mut pubsub = self.as_pubsub();
+        pubsub.subscribe(channel).map_err(|e| e.to_string())?;
+        loop {
+            let payload = pubsub
+                .get_message()
+                .and_then(|message| message.get_payload())
+                .map_err(|e| e.to_string())?;
+            on_message(payload);
+        }
+    }
+}
+
+impl<T: PredicatesDb + ?Sized> PredicatesDb for Box<T> {
+    fn ping(&mut self) -> Result<(), String> {
+        (**self).ping()
+    }
+
+    fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>, String> {
+        (**self).hget(key, field)
+    }
+
+    fn hgetall(&mut self, key: &str) -> Result<HashMap<String, String>, String> {
+        (**self).hgetall(key)
+    }
+
+    fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        (**self).hset(key, field, value)
+    }
+
+    fn hset_nx(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        (**self).hset_nx(key, field, value)
+    }
+
+    fn hdel(&mut self, key: &str, field: &str) -> Result<(), String> {
+        (**self).hdel(key, field)
+    }
+
+    fn hincr(&mut self, key: &str, field: &str) -> Result<u64, String> {
+        (**self).hincr(key, field)
+    }
+
+    fn del(&mut self, key: &str) -> Result<(), String> {
+        (**self).del(key)
+    }
+
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>, String> {
+        (**self).scan_keys(pattern)
+    }
+
+    fn lpush(&mut self, key: &str, value: &str) -> Result<(), String> {
+        (**self).lpush(key, value)
+    }
+
+    fn lpush_capped(&mut self, key: &str, value: &str, max_len: usize) -> Result<(), String> {
+        (**self).lpush_capped(key, value, max_len)
+    }
+
+    fn ltrim(&mut self, key: &str, max_len: usize) -> Result<(), String> {
+        (**self).ltrim(key, max_len)
+    }
+
+    fn lrange_all(&mut self, key: &str) -> Result<Vec<String>, String> {
+        (**self).lrange_all(key)
+    }
+
+    fn zadd_capped(
+        &mut self,
+        key: &str,
+        member: &str,
+        score: u64,
+        max_len: usize,
+    ) -> Result<(), String> {
+        (**self).zadd_capped(key, member, score, max_len)
+    }
+
+    fn zscore(&mut self, key: &str, member: &str) -> Result<Option<u64>, String> {
+        (**self).zscore(key, member)
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        (**self).publish(channel, payload)
+    }
+
+    fn subscribe(
+        &mut self,
+        channel: &str,
+        on_message: &mut dyn FnMut(String),
+    ) -> Result<(), String> {
+        (**self).subscribe(channel, on_message)
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use crossbeam_channel::{bounded, unbounded, Sender};
+use postgres::fallible_iterator::FallibleIterator;
+use postgres::{Client, NoTls};
+
+use super::PredicatesDb;
+
+/// Advisory lock serializing the creation of the tables, across the instances sharing the db.
+const SCHEMA_LOCK_ID: i64 = 0x63686169;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS chainhook_hashes (
+        key TEXT NOT NULL,
+        field TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (key, field)
+    );
+    CREATE TABLE IF NOT EXISTS chainhook_lists (
+        key TEXT NOT NULL,
+        position BIGSERIAL NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (key, position)
+    );
+    CREATE TABLE IF NOT EXISTS chainhook_sorted_sets (
+        key TEXT NOT NULL,
+        member TEXT NOT NULL,
+        score BIGINT NOT NULL,
+        PRIMARY KEY (key, member)
+    );";
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// Predicates db held in Postgres tables, one per Redis data type.
+///
+/// The synchronous Postgres client can't be used from the async handlers of the HTTP API, so
+/// each connection is owned by a dedicated thread running the queries it is sent.
+pub struct PostgresPredicatesDb {
+    jobs_tx: Sender<Job>,
+}
+
+impl PostgresPredicatesDb {
+    pub fn open(database_uri: &str) -> Result<Self, String> {
+        let database_uri = database_uri.to_string();
+        let (jobs_tx, jobs_rx) = unbounded::<Job>();
+        let (connected_tx, connected_rx) = bounded(1);
+        hiro_system_kit::thread_named("Predicates db connection")
+            .spawn(move || {
+                let mut client = match connect(&database_uri) {
+                    Ok(client) => client,
+                    Err(e) => {
+                        let _ = connected_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = connected_tx.send(Ok(()));
+                while let Ok(job) = jobs_rx.recv() {
+                    job(&mut client);
+                }
+            })
+            .map_err(|e| format!("unable to spawn thread: {e}"))?;
+        connected_rx
+            .recv()
+            .map_err(|_| "unable to connect to db".to_string())??;
+        Ok(PostgresPredicatesDb { jobs_tx })
+    }
+
+    fn run<T, F>(&self, job: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> Result<T, postgres::Error> + Send + 'static,
+    {
+        let (result_tx, result_rx) = bounded(1);
+        self.jobs_tx
+            .send(Box::new(move |client| {
+                let _ = result_tx.send(job(client));
+            }))
+            .map_err(|_| "connection to db lost".to_string())?;
+        result_rx
+            .recv()
+            .map_err(|_| "connection to db lost".to_string())?
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn connect(database_uri: &str) -> Result<Client, String> {
+    let mut client = Client::connect(database_uri, NoTls)
+        .map_err(|e| format!("unable to connect to db: {}", e))?;
+    create_tables(&mut client).map_err(|e| format!("unable to initialize db: {}", e))?;
+    Ok(client)
+}
+
+fn create_tables(client: &mut Client) -> Result<(), postgres::Error> {
+    let mut transaction = client.transaction()?;
+    transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&SCHEMA_LOCK_ID])?;
+    transaction.batch_execute(SCHEMA)?;
+    transaction.commit()
+}
+
+/// Turns a glob `pattern`, as accepted by Redis' `SCAN`, into a `LIKE` pattern.
+fn get_like_pattern(pattern: &str) -> String {
+    pattern
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+        .replace('*', "%")
+}
+
+impl PredicatesDb for PostgresPredicatesDb {
+    fn ping(&mut self) -> Result<(), String> {
+        self.run(|client| client.simple_query("SELECT 1").map(|_| ()))
+    }
+
+    fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>, String> {
+        let (key, field) = (key.to_string(), field.to_string());
+        self.run(move |client| {
+            client
+                .query_opt(
+                    "SELECT value FROM chainhook_hashes WHERE key = $1 AND field = $2",
+                    &[&key, &field],
+                )
+                .map(|row| row.map(|row| row.get(0)))
+        })
+    }
+
+    fn hgetall(&mut self, key: &str) -> Result<HashMap<String, String>, String> {
+        let key = key.to_string();
+        self.run(move |client| {
+            client
+                .query(
+                    "SELECT field, value FROM chainhook_hashes WHERE key = $1",
+                    &[&key],
+                )
+                .map(|rows| rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+        })
+    }
+
+    fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        let (key, field, value) = (key.to_string(), field.to_string(), value.to_string());
+        self.run(move |client| {
+            client
+                .execute(
+                    "INSERT INTO chainhook_hashes (key, field, value) VALUES ($1, $2, $3)
+                    ON CONFLICT (key, field) DO UPDATE SET value = EXCLUDED.value",
+                    &[&key, &field, &value],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn hset_nx(&mut self, key: &str, field: &str, value: &str) -> Result<(), String> {
+        let (key, field, value) = (key.to_string(), field.to_string(), value.to_string());
+        self.run(move |client| {
+            client
+                .execute(
+                    "INSERT INTO chainhook_hashes (key, field, value) VALUES ($1, $2, $3)
+                    ON CONFLICT (key, field) DO NOTHING",
+                    &[&key, &field, &value],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn hdel(&mut self, key: &str, field: &str) -> Result<(), String> {
+        let (key, field) = (key.to_string(), field.to_string());
+        self.run(move |client| {
+            client
+                .execute(
+                    "DELETE FROM chainhook_hashes WHERE key = $1 AND field = $2",
+                    &[&key, &field],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn hincr(&mut self, key: &str, field: &str) -> Result<u64, String> {
+        let (key, field) = (key.to_string(), field.to_string());
+        self.run(move |client| {
+            client
+                .query_one(
+                    "INSERT INTO chainhook_hashes (key, field, value) VALUES ($1, $2, '1')
+                    ON CONFLICT (key, field)
+                    DO UPDATE SET value = (chainhook_hashes.value::BIGINT + 1)::TEXT
+                    RETURNING value::BIGINT",
+                    &[&key, &field],
+                )
+                .map(|row| row.get::<_, i64>(0) as u64)
+        })
+    }
+
+    fn del(&mut self, key: &str) -> Result<(), String> {
+        let key = key.to_string();
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            for table in [
+                "chainhook_hashes",
+                "chainhook_lists",
+                "chainhook_sorted_sets",
+            ] {
+                let statement = format!("DELETE FROM {} WHERE key = $1", table);
+                transaction.execute(&statement, &[&key])?;
+            }
+            transaction.commit()
+        })
+    }
+
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>, String> {
+        let pattern = get_like_pattern(pattern);
+        self.run(move |client| {
+            client
+                .query(
+                    "SELECT key FROM chainhook_hashes WHERE key LIKE $1
+                    UNION SELECT key FROM chainhook_lists WHERE key LIKE $1
+                    UNION SELECT key FROM chainhook_sorted_sets WHERE key LIKE $1",
+                    &[&pattern],
+                )
+                .map(|rows| rows.iter().map(|row| row.get(0)).collect())
+        })
+    }
+
+    fn lpush(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let (key, value) = (key.to_string(), value.to_string());
+        self.run(move |client| {
+            client
+                .execute(
+                    "INSERT INTO chainhook_lists (key, value) VALUES ($1, $2)",
+                    &[&key, &value],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn lpush_capped(&mut self, key: &str, value: &str, max_len: usize) -> Result<(), String> {
+        let (key, value, max_len) = (key.to_string(), value.to_string(), max_len as i64);
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            transaction.execute(
+                "INSERT INTO chainhook_lists (key, value) VALUES ($1, $2)",
+                &[&key, &value],
+            )?;
+            transaction.execute(
+                "DELETE FROM chainhook_lists WHERE key = $1 AND position NOT IN (
+                    SELECT position FROM chainhook_lists WHERE key = $1
+                    ORDER BY position DESC LIMIT $2
+                )",
+                &[&key, &max_len],
+            )?;
+            transaction.commit()
+        })
+    }
+
+    fn ltrim(&mut self, key: &str, max_len: usize) -> Result<(), String> {
+        let (key, max_len) = (key.to_string(), max_len as i64);
+        self.run(move |client| {
+            client
+                .execute(
+                    "DELETE FROM chainhook_lists WHERE key = $1 AND position NOT IN (
+                    SELECT position FROM chainhook_lists WHERE key = $1
+                    ORDER BY position DESC LIMIT $2
+                )",
+                    &[&key, &max_len],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn lrange_all(&mut self, key: &str) -> Result<Vec<String>, String> {
+        let key = key.to_string();
+        self.run(move |client| {
+            client
+                .query(
+                    "SELECT value FROM chainhook_lists WHERE key = $1 ORDER BY position DESC",
+                    &[&key],
+                )
+                .map(|rows| rows.iter().map(|row| row.get(0)).collect())
+        })
+    }
+
+    fn zadd_capped(
+        &mut self,
+        key: &str,
+        member: &str,
+        score: u64,
+        max_len: usize,
+    ) -> Result<(), String> {
+        let (key, member) = (key.to_string(), member.to_string());
+        let (score, max_len) = (score as i64, max_len as i64);
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            transaction.execute(
+                "INSERT INTO chainhook_sorted_sets (key, member, score) VALUES ($1, $2, $3)
+                ON CONFLICT (key, member) DO UPDATE SET score = EXCLUDED.score",
+                &[&key, &member, &score],
+            )?;
+            transaction.execute(
+                "DELETE FROM chainhook_sorted_sets WHERE key = $1 AND member NOT IN (
+                    SELECT member FROM chainhook_sorted_sets WHERE key = $1
+                    ORDER BY score DESC LIMIT $2
+                )",
+                &[&key, &max_len],
+            )?;
+            transaction.commit()
+        })
+    }
+
+    fn zscore(&mut self, key: &str, member: &str) -> Result<Option<u64>, String> {
+        let (key, member) = (key.to_string(), member.to_string());
+        self.run(move |client| {
+            client
+                .query_opt(
+                    "SELECT score FROM chainhook_sorted_sets WHERE key = $1 AND member = $2",
+                    &[&key, &member],
+                )
+                .map(|row| row.map(|row| row.get::<_, i64>(0) as u64))
+        })
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        let (channel, payload) = (channel.to_string(), payload.to_string());
+        self.run(move |client| {
+            client
+                .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+                .map(|_| ())
+        })
+    }
+
+    fn subscribe(
+        &mut self,
+        channel: &str,
+        on_message: &mut dyn FnMut(String),
+    ) -> Result<(), String> {
+        let listen = format!("LISTEN \"{}\"", channel.replace('"', "\"\""));
+        let (messages_tx, messages_rx) = unbounded();
+        self.jobs_tx
+            .send(Box::new(move |client| {
+                if let Err(e) = client.batch_execute(&listen) {
+                    let _ = messages_tx.send(Err(e.to_string()));
+                    return;
+                }
+                let mut notifications = client.notifications();
+                let mut notifications = notifications.blocking_iter();
+                loop {
+                    let message = match notifications.next() {
+                        Ok(Some(notification)) => Ok(notification.payload().to_string()),
+                        Ok(None) => Err("connection to db lost".to_string()),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    let lost = message.is_err();
+                    if messages_tx.send(message).is_err() || lost {
+                        return;
+                    }
+                }
+            }))
+            .map_err(|_| "connection to db lost".to_string())?;
+        for message in messages_rx.iter() {
+            on_message(message?);
+        }
+        Err("connection to db lost".to_string())
+    }
+}
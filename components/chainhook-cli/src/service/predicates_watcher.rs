@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+
+use chainhook_sdk::chainhooks::types::ChainhookSpecificationNetworkMap;
+use chainhook_sdk::observer::ObserverCommand;
+use chainhook_sdk::types::Chain;
+use chainhook_sdk::utils::Context;
+
+/// Delay between two scans of the watched predicates directory.
+pub const PREDICATES_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+pub enum PredicateFileChange {
+    Added(ChainhookSpecificationNetworkMap),
+    Updated(ChainhookSpecificationNetworkMap),
+    Removed(String, Chain),
+}
+
+impl PredicateFileChange {
+    pub fn into_observer_command(self) -> ObserverCommand {
+        match self {
+            PredicateFileChange::Added(predicate) => ObserverCommand::RegisterPredicate(predicate),
+            PredicateFileChange::Updated(predicate) => ObserverCommand::UpdatePredicate(predicate),
+            PredicateFileChange::Removed(uuid, Chain::Stacks) => {
+                ObserverCommand::DeregisterStacksPredicate(uuid)
+            }
+            PredicateFileChange::Removed(uuid, Chain::Bitcoin) => {
+                ObserverCommand::DeregisterBitcoinPredicate(uuid)
+            }
+        }
+    }
+}
+
+/// Keeps track of the predicates defined by the JSON files of a directory.
+pub struct PredicatesDirWatcher {
+    dir: PathBuf,
+    /// Last contents read from each file, valid or not, so that unchanged files are skipped.
+    contents: HashMap<PathBuf, Vec<u8>>,
+    /// Uuid and chain of the predicate registered from each file.
+    registered: HashMap<PathBuf, (String, Chain)>,
+}
+
+impl PredicatesDirWatcher {
+    pub fn new(dir: PathBuf) -> Self {
+        PredicatesDirWatcher {
+            dir,
+            contents: HashMap::new(),
+            registered: HashMap::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Scans the directory and returns the changes since the previous scan. A file being
+    /// modified updates its predicate, or deregisters it before registering the new one if the
+    /// uuid or the chain changed. If the new contents are invalid, the previous predicate is kept.
+    pub fn scan(&mut self, ctx: &Context) -> Vec<PredicateFileChange> {
+        let mut changes = vec![];
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    ctx.expect_logger(),
+                    "Unable to read predicates directory {}: {}",
+                    self.dir.display(),
+                    e
+                );
+                return changes;
+            }
+        };
+        let mut paths = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut present_paths = HashSet::new();
+        for path in paths.into_iter() {
+            present_paths.insert(path.clone());
+            let contents = match std::fs::read(&path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Unable to read predicate file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if self.contents.get(&path) == Some(&contents) {
+                continue;
+            }
+            let predicate = serde_json::from_slice::<ChainhookSpecificationNetworkMap>(&contents)
+                .map_err(|e| format!("unable to parse json: {}", e))
+                .and_then(|predicate| predicate.validate().map(|_| predicate));
+            self.contents.insert(path.clone(), contents);
+            let predicate = match predicate {
+                Ok(predicate) => predicate,
+                Err(e) => {
+                    warn!(
+                        ctx.expect_logger(),
+                        "Ignoring predicate file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let uuid = predicate.get_uuid().to_string();
+            let chain = match predicate {
+                ChainhookSpecificationNetworkMap::Bitcoin(_) => Chain::Bitcoin,
+                ChainhookSpecificationNetworkMap::Stacks(_) => Chain::Stacks,
+            };
+            match self.registered.remove(&path) {
+                Some((previous_uuid, previous_chain)) => {
+                    let same_chain = matches!(
+                        (&previous_chain, &chain),
+                        (Chain::Bitcoin, Chain::Bitcoin) | (Chain::Stacks, Chain::Stacks)
+                    );
+                    if previous_uuid == uuid && same_chain {
+                        changes.push(PredicateFileChange::Updated(predicate));
+                    } else {
+                        changes.push(PredicateFileChange::Removed(previous_uuid, previous_chain));
+                        changes.push(PredicateFileChange::Added(predicate));
+                    }
+                }
+                None => changes.push(PredicateFileChange::Added(predicate)),
+            }
+            self.registered.insert(path, (uuid, chain));
+        }
+
+        let removed_paths = self
+            .contents
+            .keys()
+            .filter(|path| !present_paths.contains(*path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in removed_paths.into_iter() {
+            self.contents.remove(&path);
+            if let Some((uuid, chain)) = self.registered.remove(&path) {
+                changes.push(PredicateFileChange::Removed(uuid, chain));
+            }
+        }
+        changes
+    }
+}
+
+/// Scans the watched directory forever, sending the registrations and deregistrations of the
+/// predicates added, modified or removed to the observer.
+pub fn start_predicates_dir_watcher(
+    mut watcher: PredicatesDirWatcher,
+    observer_command_tx: SyncSender<ObserverCommand>,
+    ctx: &Context,
+) {
+    loop {
+        std::thread::sleep(PREDICATES_WATCH_INTERVAL);
+        for change in watcher.scan(ctx).into_iter() {
+            match change {
+                PredicateFileChange::Added(ref predicate) => info!(
+                    ctx.expect_logger(),
+                    "Predicate {} loaded from watched directory",
+                    predicate.get_uuid()
+                ),
+                PredicateFileChange::Updated(ref predicate) => info!(
+                    ctx.expect_logger(),
+                    "Predicate {} updated from watched directory",
+                    predicate.get_uuid()
+                ),
+                PredicateFileChange::Removed(ref uuid, _) => info!(
+                    ctx.expect_logger(),
+                    "Predicate {} removed from watched directory", uuid
+                ),
+            }
+            if observer_command_tx
+                .send(change.into_observer_command())
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
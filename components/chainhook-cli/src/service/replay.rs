@@ -0,0 +1,121 @@
+use chainhook_sdk::chainhooks::bitcoin::BitcoinChainhookInstance;
+use chainhook_sdk::chainhooks::stacks::StacksChainhookInstance;
+use chainhook_sdk::chainhooks::types::ChainhookInstance;
+use chainhook_sdk::utils::Context;
+
+use crate::config::{Config, PredicatesApi};
+use crate::scan::bitcoin::{
+    open_bitcoin_block_cache, scan_bitcoin_chainstate_via_rpc_using_predicate,
+};
+use crate::scan::stacks::scan_stacks_chainstate_via_rocksdb_using_predicate;
+use crate::storage::open_readonly_stacks_db_conn;
+
+use super::http_api::get_entries_from_predicates_db;
+use super::open_readwrite_predicates_db_conn;
+use super::runloops::rescan_config;
+use super::PredicateStatus;
+
+/// Loads the predicates registered in the predicates db, leaving out the paused ones: their
+/// occurrences are not meant to be delivered.
+pub fn load_predicates_to_replay(
+    config: &Config,
+    ctx: &Context,
+) -> Result<Vec<ChainhookInstance>, String> {
+    let PredicatesApi::On(ref api_config) = config.http_api else {
+        return Ok(vec![]);
+    };
+    let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config)?;
+    let predicates = get_entries_from_predicates_db(predicates_db_conn.as_mut(), ctx)?
+        .into_iter()
+        .filter(|(_, status)| !matches!(status, PredicateStatus::Paused(_)))
+        .map(|(predicate, _)| predicate)
+        .collect();
+    Ok(predicates)
+}
+
+/// Evaluates the predicates against the blocks `from` to `to` again, executing their actions,
+/// e.g. to recover from an outage of a consumer. Stacks blocks are read from the local Stacks
+/// db, Bitcoin blocks from the block cache if enabled, from bitcoind otherwise. As with rescans,
+/// the status of the predicates is left untouched. Returns the uuids of the predicates replayed.
+pub async fn replay_blocks(
+    predicates: Vec<ChainhookInstance>,
+    from: u64,
+    to: u64,
+    config: &Config,
+    ctx: &Context,
+) -> Result<Vec<String>, String> {
+    if from > to {
+        return Err("--from must not be greater than --to".into());
+    }
+    let config = rescan_config(config);
+    let block_cache = open_bitcoin_block_cache(&config, ctx);
+
+    let mut replayed = vec![];
+    for predicate in predicates.into_iter() {
+        match predicate {
+            ChainhookInstance::Bitcoin(predicate_spec) => {
+                let predicate_spec = BitcoinChainhookInstance {
+                    blocks: None,
+                    start_block: Some(from),
+                    end_block: Some(to),
+                    ..predicate_spec
+                };
+                info!(
+                    ctx.expect_logger(),
+                    "Replaying Bitcoin blocks #{} to #{} for predicate {}",
+                    from,
+                    to,
+                    predicate_spec.uuid
+                );
+                scan_bitcoin_chainstate_via_rpc_using_predicate(
+                    &predicate_spec,
+                    None,
+                    &config,
+                    None,
+                    block_cache.clone(),
+                    ctx,
+                )
+                .await
+                .map_err(|e| {
+                    format!("unable to replay predicate {}: {}", predicate_spec.uuid, e)
+                })?;
+                replayed.push(predicate_spec.uuid);
+            }
+            ChainhookInstance::Stacks(predicate_spec) => {
+                let predicate_spec = StacksChainhookInstance {
+                    blocks: None,
+                    start_block: Some(from),
+                    end_block: Some(to),
+                    ..predicate_spec
+                };
+                info!(
+                    ctx.expect_logger(),
+                    "Replaying Stacks blocks #{} to #{} for predicate {}",
+                    from,
+                    to,
+                    predicate_spec.uuid
+                );
+                let stacks_db_conn = open_readonly_stacks_db_conn(
+                    &config.expected_cache_path(),
+                    &config.storage.stacks_db_backend,
+                    ctx,
+                )
+                .map_err(|e| format!("unable to open the Stacks db: {}", e))?;
+                scan_stacks_chainstate_via_rocksdb_using_predicate(
+                    &predicate_spec,
+                    None,
+                    stacks_db_conn.as_ref(),
+                    &config,
+                    None,
+                    ctx,
+                )
+                .await
+                .map_err(|e| {
+                    format!("unable to replay predicate {}: {}", predicate_spec.uuid, e)
+                })?;
+                replayed.push(predicate_spec.uuid);
+            }
+        }
+    }
+    Ok(replayed)
+}
@@ -1,6 +1,6 @@
 use std::{
     collections::HashMap,
-    sync::{mpsc::Sender, Arc, RwLock},
+    sync::{mpsc::SyncSender, Arc, RwLock},
 };
 
 use chainhook_sdk::{
@@ -16,7 +16,8 @@ use threadpool::ThreadPool;
 use crate::{
     config::{Config, PredicatesApi},
     scan::{
-        bitcoin::scan_bitcoin_chainstate_via_rpc_using_predicate, common::PredicateScanResult,
+        bitcoin::{open_bitcoin_block_cache, scan_bitcoin_chainstate_via_rpc_using_predicate},
+        common::PredicateScanResult,
         stacks::scan_stacks_chainstate_via_rocksdb_using_predicate,
     },
     service::{open_readwrite_predicates_db_conn_or_panic, set_predicate_interrupted_status},
@@ -30,13 +31,20 @@ pub enum StacksScanOp {
         predicate_spec: StacksChainhookInstance,
         unfinished_scan_data: Option<ScanningData>,
     },
+    /// Evaluates the predicate against the blocks `start_block` to `end_block` again, leaving
+    /// its status untouched.
+    Rescan {
+        predicate_spec: StacksChainhookInstance,
+        start_block: u64,
+        end_block: u64,
+    },
     KillScan(String),
 }
 
 pub fn start_stacks_scan_runloop(
     config: &Config,
     stacks_scan_op_rx: crossbeam_channel::Receiver<StacksScanOp>,
-    observer_command_tx: Sender<ObserverCommand>,
+    observer_command_tx: SyncSender<ObserverCommand>,
     ctx: &Context,
 ) {
     let stacks_scan_pool = ThreadPool::new(config.limits.max_number_of_concurrent_stacks_scans);
@@ -48,6 +56,14 @@ pub fn start_stacks_scan_runloop(
                 predicate_spec,
                 unfinished_scan_data,
             } => {
+                // predicates assigned to other instances are never enabled by this one
+                if !config.is_predicate_assigned(&predicate_spec.uuid) {
+                    debug!(
+                        ctx.expect_logger(),
+                        "Predicate {} is evaluated by another instance", predicate_spec.uuid
+                    );
+                    continue;
+                }
                 let moved_ctx = ctx.clone();
                 let moved_config = config.clone();
                 let observer_command_tx = observer_command_tx.clone();
@@ -56,6 +72,7 @@ pub fn start_stacks_scan_runloop(
                 stacks_scan_pool.execute(move || {
                     let stacks_db_conn = match open_readonly_stacks_db_conn(
                         &moved_config.expected_cache_path(),
+                        &moved_config.storage.stacks_db_backend,
                         &moved_ctx,
                     ) {
                         Ok(db_conn) => db_conn,
@@ -75,7 +92,7 @@ pub fn start_stacks_scan_runloop(
                     let op = scan_stacks_chainstate_via_rocksdb_using_predicate(
                         &predicate_spec,
                         unfinished_scan_data,
-                        &stacks_db_conn,
+                        stacks_db_conn.as_ref(),
                         &moved_config,
                         Some(kill_signal),
                         &moved_ctx,
@@ -115,6 +132,55 @@ pub fn start_stacks_scan_runloop(
                     }
                 });
             }
+            StacksScanOp::Rescan {
+                predicate_spec,
+                start_block,
+                end_block,
+            } => {
+                let moved_ctx = ctx.clone();
+                let moved_config = rescan_config(config);
+                let predicate_spec = StacksChainhookInstance {
+                    blocks: None,
+                    start_block: Some(start_block),
+                    end_block: Some(end_block),
+                    ..predicate_spec
+                };
+                // Rescans are interrupted along with the scans of a deregistered predicate
+                let kill_signal = kill_signals
+                    .entry(predicate_spec.uuid.clone())
+                    .or_insert_with(|| Arc::new(RwLock::new(false)))
+                    .clone();
+                stacks_scan_pool.execute(move || {
+                    let stacks_db_conn = match open_readonly_stacks_db_conn(
+                        &moved_config.expected_cache_path(),
+                        &moved_config.storage.stacks_db_backend,
+                        &moved_ctx,
+                    ) {
+                        Ok(db_conn) => db_conn,
+                        Err(e) => {
+                            warn!(
+                                moved_ctx.expect_logger(),
+                                "Unable to rescan predicate {}: {e}", predicate_spec.uuid
+                            );
+                            return;
+                        }
+                    };
+                    let op = scan_stacks_chainstate_via_rocksdb_using_predicate(
+                        &predicate_spec,
+                        None,
+                        stacks_db_conn.as_ref(),
+                        &moved_config,
+                        Some(kill_signal),
+                        &moved_ctx,
+                    );
+                    if let Err(e) = hiro_system_kit::nestable_block_on(op) {
+                        warn!(
+                            moved_ctx.expect_logger(),
+                            "Unable to rescan predicate {}: {e}", predicate_spec.uuid
+                        );
+                    }
+                });
+            }
             StacksScanOp::KillScan(predicate_uuid) => {
                 let Some(kill_signal) = kill_signals.remove(&predicate_uuid) else {
                     continue;
@@ -132,16 +198,24 @@ pub enum BitcoinScanOp {
         predicate_spec: BitcoinChainhookInstance,
         unfinished_scan_data: Option<ScanningData>,
     },
+    /// Evaluates the predicate against the blocks `start_block` to `end_block` again, leaving
+    /// its status untouched.
+    Rescan {
+        predicate_spec: BitcoinChainhookInstance,
+        start_block: u64,
+        end_block: u64,
+    },
     KillScan(String),
 }
 
 pub fn start_bitcoin_scan_runloop(
     config: &Config,
     bitcoin_scan_op_rx: crossbeam_channel::Receiver<BitcoinScanOp>,
-    observer_command_tx: Sender<ObserverCommand>,
+    observer_command_tx: SyncSender<ObserverCommand>,
     ctx: &Context,
 ) {
     let bitcoin_scan_pool = ThreadPool::new(config.limits.max_number_of_concurrent_bitcoin_scans);
+    let block_cache = open_bitcoin_block_cache(config, ctx);
     let mut kill_signals = HashMap::new();
 
     while let Ok(op) = bitcoin_scan_op_rx.recv() {
@@ -150,9 +224,18 @@ pub fn start_bitcoin_scan_runloop(
                 predicate_spec,
                 unfinished_scan_data,
             } => {
+                // predicates assigned to other instances are never enabled by this one
+                if !config.is_predicate_assigned(&predicate_spec.uuid) {
+                    debug!(
+                        ctx.expect_logger(),
+                        "Predicate {} is evaluated by another instance", predicate_spec.uuid
+                    );
+                    continue;
+                }
                 let moved_ctx = ctx.clone();
                 let moved_config = config.clone();
                 let observer_command_tx = observer_command_tx.clone();
+                let block_cache = block_cache.clone();
                 let kill_signal = Arc::new(RwLock::new(false));
                 kill_signals.insert(predicate_spec.uuid.clone(), kill_signal.clone());
 
@@ -162,6 +245,7 @@ pub fn start_bitcoin_scan_runloop(
                         unfinished_scan_data,
                         &moved_config,
                         Some(kill_signal),
+                        block_cache,
                         &moved_ctx,
                     );
 
@@ -199,6 +283,42 @@ pub fn start_bitcoin_scan_runloop(
                     }
                 });
             }
+            BitcoinScanOp::Rescan {
+                predicate_spec,
+                start_block,
+                end_block,
+            } => {
+                let moved_ctx = ctx.clone();
+                let moved_config = rescan_config(config);
+                let block_cache = block_cache.clone();
+                let predicate_spec = BitcoinChainhookInstance {
+                    blocks: None,
+                    start_block: Some(start_block),
+                    end_block: Some(end_block),
+                    ..predicate_spec
+                };
+                // Rescans are interrupted along with the scans of a deregistered predicate
+                let kill_signal = kill_signals
+                    .entry(predicate_spec.uuid.clone())
+                    .or_insert_with(|| Arc::new(RwLock::new(false)))
+                    .clone();
+                bitcoin_scan_pool.execute(move || {
+                    let op = scan_bitcoin_chainstate_via_rpc_using_predicate(
+                        &predicate_spec,
+                        None,
+                        &moved_config,
+                        Some(kill_signal),
+                        block_cache,
+                        &moved_ctx,
+                    );
+                    if let Err(e) = hiro_system_kit::nestable_block_on(op) {
+                        warn!(
+                            moved_ctx.expect_logger(),
+                            "Unable to rescan predicate {}: {e}", predicate_spec.uuid
+                        );
+                    }
+                });
+            }
             BitcoinScanOp::KillScan(predicate_uuid) => {
                 let Some(kill_signal) = kill_signals.remove(&predicate_uuid) else {
                     continue;
@@ -210,3 +330,11 @@ pub fn start_bitcoin_scan_runloop(
     }
     bitcoin_scan_pool.join();
 }
+
+/// Returns the config used by rescans. The predicates API is left out of it, so that rescans
+/// don't overwrite the status of predicates streaming blocks.
+pub fn rescan_config(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.http_api = PredicatesApi::Off;
+    config
+}
@@ -0,0 +1,82 @@
+use chainhook_sdk::chainhooks::types::{ChainhookSpecificationNetworkMap, ChainhookStore};
+use chainhook_sdk::utils::Context;
+
+use crate::config::report::{check_config, ConfigCheck};
+use crate::config::{Config, PredicatesApi};
+use crate::storage::{get_default_stacks_db_file_path, open_readonly_stacks_db_conn};
+
+use super::http_api::load_predicates_from_db;
+use super::predicates_watcher::{PredicateFileChange, PredicatesDirWatcher};
+
+/// Goes through the steps `service start` takes before entering its run loop, without writing
+/// anything: on top of the checks of `config validate`, opens the Stacks db and loads the
+/// predicates registered in the predicates db, passed on the command line and found in the
+/// watched directory.
+pub async fn check_service_start(
+    config: &Config,
+    predicates_from_startup: Vec<ChainhookSpecificationNetworkMap>,
+    ctx: &Context,
+) -> Vec<ConfigCheck> {
+    let mut checks = check_config(config, ctx).await;
+
+    let stacks_db_path = get_default_stacks_db_file_path(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+    );
+    let stacks_db_check = if stacks_db_path.exists() {
+        open_readonly_stacks_db_conn(
+            &config.expected_cache_path(),
+            &config.storage.stacks_db_backend,
+            ctx,
+        )
+        .map(|_| "opened".to_string())
+    } else if config.is_replica() {
+        Err("not found, replicas read the db of the primary".into())
+    } else {
+        Ok("not found, created on start".into())
+    };
+    checks.push(ConfigCheck::new(
+        format!("stacks db {}", stacks_db_path.display()),
+        stacks_db_check,
+    ));
+
+    if let PredicatesApi::On(_) = config.http_api {
+        checks.push(ConfigCheck::new(
+            "registered predicates",
+            load_predicates_from_db(config, ctx)
+                .map(|predicates| format!("{} loaded from the predicates db", predicates.len())),
+        ));
+    }
+
+    let mut predicates = predicates_from_startup
+        .into_iter()
+        .map(|predicate| ("predicate-path".to_string(), predicate))
+        .collect::<Vec<_>>();
+    if let Some(ref watch_dir) = config.predicates_watch_dir {
+        let mut watcher = PredicatesDirWatcher::new(watch_dir.clone());
+        for change in watcher.scan(ctx).into_iter() {
+            if let PredicateFileChange::Added(predicate) = change {
+                predicates.push((format!("watch_dir {}", watch_dir.display()), predicate));
+            }
+        }
+    }
+    // registering in a store of its own validates the predicates against the networks
+    let mut chainhook_store = ChainhookStore::new();
+    for (source, predicate) in predicates.into_iter() {
+        let uuid = predicate.get_uuid().to_string();
+        checks.push(ConfigCheck::new(
+            format!("predicate {} ({})", uuid, source),
+            chainhook_store
+                .register_instance_from_network_map(
+                    (
+                        &config.network.bitcoin_network,
+                        &config.network.stacks_network,
+                    ),
+                    predicate,
+                )
+                .map(|_| "loaded".to_string()),
+        ));
+    }
+
+    checks
+}
@@ -0,0 +1,167 @@
+use chainhook_sdk::chainhooks::types::ChainhookInstance;
+use chainhook_sdk::observer::DeliveryRecord;
+use chainhook_sdk::utils::Context;
+
+use crate::config::{Config, PredicatesApi};
+
+use super::http_api::get_entries_from_predicates_db;
+use super::open_readwrite_predicates_db_conn;
+use super::outbox::get_delivery_records;
+use super::PredicateStatus;
+
+/// Activity of a registered predicate, from its status in the predicates db and the outcomes of
+/// its last deliveries.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PredicateStats {
+    pub uuid: String,
+    pub name: String,
+    pub chain: String,
+    pub status: String,
+    /// Left out for the predicates whose status doesn't track their progress, e.g. interrupted.
+    pub number_of_times_triggered: Option<u64>,
+    pub number_of_blocks_evaluated: Option<u64>,
+    /// Unix timestamp, in seconds, of the last occurrence.
+    pub last_occurrence: Option<u64>,
+    /// Number of deliveries recorded, up to the last 50.
+    pub deliveries: usize,
+    pub successful_deliveries: usize,
+    /// Share of the recorded deliveries that succeeded, between 0 and 1.
+    pub delivery_success_rate: Option<f64>,
+}
+
+impl PredicateStats {
+    pub fn new(
+        predicate: &ChainhookInstance,
+        status: &PredicateStatus,
+        delivery_records: &[DeliveryRecord],
+    ) -> Self {
+        let (name, chain) = match predicate {
+            ChainhookInstance::Bitcoin(spec) => (spec.name.clone(), "bitcoin"),
+            ChainhookInstance::Stacks(spec) => (spec.name.clone(), "stacks"),
+        };
+        let progress = match status {
+            PredicateStatus::Scanning(data) => Some((
+                data.number_of_times_triggered,
+                data.number_of_blocks_evaluated,
+                data.last_occurrence,
+            )),
+            PredicateStatus::Streaming(data) => Some((
+                data.number_of_times_triggered,
+                data.number_of_blocks_evaluated,
+                data.last_occurrence,
+            )),
+            PredicateStatus::UnconfirmedExpiration(data)
+            | PredicateStatus::ConfirmedExpiration(data) => Some((
+                data.number_of_times_triggered,
+                data.number_of_blocks_evaluated,
+                data.last_occurrence,
+            )),
+            PredicateStatus::Paused(data) => Some((
+                data.number_of_times_triggered,
+                data.number_of_blocks_evaluated,
+                data.last_occurrence,
+            )),
+            PredicateStatus::Interrupted(_) | PredicateStatus::New => None,
+        };
+        let deliveries = delivery_records.len();
+        let successful_deliveries = delivery_records.iter().filter(|r| r.success).count();
+        PredicateStats {
+            uuid: predicate.uuid().to_string(),
+            name,
+            chain: chain.to_string(),
+            status: json!(status)["type"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            number_of_times_triggered: progress.map(|(triggered, _, _)| triggered),
+            number_of_blocks_evaluated: progress.map(|(_, evaluated, _)| evaluated),
+            last_occurrence: progress.and_then(|(_, _, last_occurrence)| last_occurrence),
+            deliveries,
+            successful_deliveries,
+            delivery_success_rate: match deliveries {
+                0 => None,
+                _ => Some(successful_deliveries as f64 / deliveries as f64),
+            },
+        }
+    }
+}
+
+/// Returns the stats of the predicates registered in the predicates db, sorted by uuid.
+pub fn load_predicate_stats(config: &Config, ctx: &Context) -> Result<Vec<PredicateStats>, String> {
+    let PredicatesApi::On(ref api_config) = config.http_api else {
+        return Err("the predicates api, whose db holds the stats, is disabled".into());
+    };
+    let mut predicates_db_conn = open_readwrite_predicates_db_conn(api_config)?;
+    let mut stats = vec![];
+    for (predicate, status) in get_entries_from_predicates_db(predicates_db_conn.as_mut(), ctx)? {
+        let delivery_records = get_delivery_records(predicate.uuid(), predicates_db_conn.as_mut())?;
+        stats.push(PredicateStats::new(&predicate, &status, &delivery_records));
+    }
+    stats.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+    Ok(stats)
+}
+
+/// Formats the time elapsed since `timestamp`, e.g. `3h ago`.
+fn format_age(timestamp: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(timestamp);
+    match elapsed {
+        0..=59 => format!("{}s ago", elapsed),
+        60..=3599 => format!("{}m ago", elapsed / 60),
+        3600..=86399 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
+}
+
+/// Formats the stats as a table, one predicate per row. `now` is the unix timestamp, in
+/// seconds, the last occurrences are reported relatively to.
+pub fn format_stats_table(stats: &[PredicateStats], now: u64) -> String {
+    let mut rows = vec![[
+        "UUID",
+        "NAME",
+        "CHAIN",
+        "STATUS",
+        "TRIGGERS",
+        "EVALUATIONS",
+        "LAST OCCURRENCE",
+        "DELIVERIES",
+        "SUCCESS RATE",
+    ]
+    .map(String::from)];
+    for predicate_stats in stats.iter() {
+        let or_dash = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+        rows.push([
+            predicate_stats.uuid.clone(),
+            predicate_stats.name.clone(),
+            predicate_stats.chain.clone(),
+            predicate_stats.status.clone(),
+            or_dash(predicate_stats.number_of_times_triggered),
+            or_dash(predicate_stats.number_of_blocks_evaluated),
+            predicate_stats
+                .last_occurrence
+                .map_or("-".to_string(), |t| format_age(t, now)),
+            predicate_stats.deliveries.to_string(),
+            predicate_stats
+                .delivery_success_rate
+                .map_or("-".to_string(), |rate| format!("{:.1}%", rate * 100.0)),
+        ]);
+    }
+
+    let mut widths = [0; 9];
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
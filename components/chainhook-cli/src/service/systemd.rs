@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+/// Notifies systemd of the state of the service, following the `sd_notify` protocol: the
+/// messages are datagrams sent to the socket systemd exposes through `NOTIFY_SOCKET` when the
+/// unit is of `Type=notify`.
+pub struct SystemdNotifier {
+    socket_path: String,
+    watchdog_interval: Option<Duration>,
+    last_watchdog_ping: Option<Instant>,
+}
+
+impl SystemdNotifier {
+    /// Returns a notifier if the process was started by systemd with a notification socket.
+    /// When `WatchdogSec` is set on the unit, the watchdog is pinged at half the interval.
+    pub fn from_env() -> Option<SystemdNotifier> {
+        let socket_path = std::env::var("NOTIFY_SOCKET").ok()?;
+        if socket_path.is_empty() {
+            return None;
+        }
+        // the watchdog is meant for this process only, not for the ones it would spawn
+        let watchdog_pid = std::env::var("WATCHDOG_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok());
+        let watchdog_interval = match watchdog_pid {
+            Some(pid) if pid != std::process::id() => None,
+            _ => std::env::var("WATCHDOG_USEC")
+                .ok()
+                .and_then(|usec| usec.parse::<u64>().ok())
+                .filter(|usec| *usec > 0)
+                .map(|usec| Duration::from_micros(usec / 2)),
+        };
+        Some(SystemdNotifier {
+            socket_path,
+            watchdog_interval,
+            last_watchdog_ping: None,
+        })
+    }
+
+    /// The interval at which the watchdog must be pinged, if enabled.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog_interval
+    }
+
+    /// Tells systemd the service finished starting up.
+    pub fn notify_ready(&self) -> Result<(), String> {
+        self.notify(&format!("READY=1\nMAINPID={}", std::process::id()))
+    }
+
+    /// Tells systemd the service is shutting down.
+    pub fn notify_stopping(&self) -> Result<(), String> {
+        self.notify("STOPPING=1")
+    }
+
+    /// Pings the watchdog if it is enabled and the last ping is older than its interval.
+    pub fn ping_watchdog(&mut self) -> Result<(), String> {
+        let Some(interval) = self.watchdog_interval else {
+            return Ok(());
+        };
+        if let Some(last_ping) = self.last_watchdog_ping {
+            if last_ping.elapsed() < interval {
+                return Ok(());
+            }
+        }
+        self.last_watchdog_ping = Some(Instant::now());
+        self.notify("WATCHDOG=1")
+    }
+
+    #[cfg(target_os = "linux")]
+    fn notify(&self, state: &str) -> Result<(), String> {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let socket = UnixDatagram::unbound()
+            .map_err(|e| format!("unable to create notification socket: {}", e))?;
+        // names starting with @ designate sockets of the abstract namespace
+        let address = match self.socket_path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+            None => SocketAddr::from_pathname(&self.socket_path),
+        }
+        .map_err(|e| format!("invalid NOTIFY_SOCKET {}: {}", self.socket_path, e))?;
+        socket
+            .send_to_addr(state.as_bytes(), &address)
+            .map_err(|e| format!("unable to notify systemd: {}", e))?;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn notify(&self, _state: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -217,6 +217,17 @@ fn handle_rpc(
                 "result": serde_json::to_value(&blockchain_info).unwrap()
             })
         }
+        "getblockcount" => {
+            let (_, (chain_tip, _)) = fork_tracker
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1))
+                .unwrap();
+            json!({
+                "id": rpc.id,
+                "jsonrpc": rpc.jsonrpc,
+                "result": chain_tip
+            })
+        }
         "getnetworkinfo" => {
             let network_info = GetNetworkInfoResult {
                 version: 190000,
@@ -1,18 +1,22 @@
 use crate::config::{
-    Config, EventSourceConfig, LimitsConfig, MonitoringConfig, PathConfig, PredicatesApi,
-    PredicatesApiConfig, StorageConfig, DEFAULT_REDIS_URI,
+    Config, EventSourceConfig, LimitsConfig, LogsConfig, MonitoringConfig, PathConfig,
+    PredicatesApi, PredicatesApiConfig, StacksDbBackend, StorageConfig,
+    BITCOIN_SCAN_PREFETCH_DEPTH, DEFAULT_REDIS_URI, DEFAULT_SCAN_OPERATIONS_CAPACITY,
 };
 use crate::scan::stacks::consolidate_local_stacks_chainstate_using_csv;
 use crate::service::{
-    http_api::start_predicate_api_server, update_predicate_spec, update_predicate_status,
-    PredicateStatus, Service,
+    http_api::{start_predicate_api_server, PredicateApiShutdown},
+    update_predicate_spec, update_predicate_status, PredicateStatus, Service,
 };
 use chainhook_sdk::chainhooks::types::PoxConfig;
-use chainhook_sdk::observer::PredicatesConfig;
+use chainhook_sdk::observer::{
+    DeliveryHistory, FullChannelPolicy, OccurrenceStreams, PredicatesConfig,
+    DEFAULT_OBSERVER_COMMANDS_CAPACITY, DEFAULT_OBSERVER_EVENTS_CAPACITY,
+};
 use chainhook_sdk::{
     chainhooks::stacks::StacksChainhookSpecificationNetworkMap,
     chainhooks::types::{ChainhookInstance, ChainhookSpecificationNetworkMap},
-    indexer::IndexerConfig,
+    indexer::{IndexerConfig, MalformedStacksTransactionPolicy},
     observer::ObserverCommand,
     types::{BitcoinBlockSignaling, BitcoinNetwork, Chain, StacksNetwork, StacksNodeConfig},
     utils::Context,
@@ -20,14 +24,13 @@ use chainhook_sdk::{
 use redis::Commands;
 use reqwest::Method;
 use rocket::serde::json::Value as JsonValue;
-use rocket::Shutdown;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::process::{Child, Command};
 use std::sync::mpsc;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::sync_channel;
 use std::sync::mpsc::Receiver;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::SyncSender;
 
 use super::get_free_port;
 use super::mock_bitcoin_rpc::mock_bitcoin_rpc;
@@ -149,6 +152,21 @@ pub async fn call_get_predicates(port: u16) -> Result<JsonValue, String> {
     call_observer_svc(&url, Method::GET, None).await
 }
 
+pub async fn call_dry_run_predicate(request: &JsonValue, port: u16) -> Result<JsonValue, String> {
+    let url = format!("http://localhost:{port}/v1/chainhooks/dry_run");
+    call_observer_svc(&url, Method::POST, Some(request)).await
+}
+
+pub async fn call_check_predicate(predicate: &JsonValue, port: u16) -> Result<JsonValue, String> {
+    let url = format!("http://localhost:{port}/v1/chainhooks/check");
+    call_observer_svc(&url, Method::POST, Some(predicate)).await
+}
+
+pub async fn call_get_openapi_spec(port: u16) -> Result<JsonValue, String> {
+    let url = format!("http://localhost:{port}/v1/openapi.json");
+    call_observer_svc(&url, Method::GET, None).await
+}
+
 pub async fn call_observer_svc(
     url: &str,
     method: Method,
@@ -198,7 +216,9 @@ pub async fn call_prometheus(port: u16) -> Result<String, String> {
         .map_err(|e| format!("Failed to deserialize response of GET request to {url}: {e}",))
 }
 
-pub async fn build_predicate_api_server(port: u16) -> (Receiver<ObserverCommand>, Shutdown) {
+pub async fn build_predicate_api_server(
+    port: u16,
+) -> (Receiver<ObserverCommand>, PredicateApiShutdown) {
     let ctx = Context {
         logger: None,
         tracer: false,
@@ -207,12 +227,25 @@ pub async fn build_predicate_api_server(port: u16) -> (Receiver<ObserverCommand>
         http_port: port,
         display_logs: true,
         database_uri: DEFAULT_REDIS_URI.to_string(),
+        api_keys: vec![],
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
     };
 
-    let (tx, rx) = channel();
-    let shutdown = start_predicate_api_server(api_config, tx, ctx)
-        .await
-        .unwrap();
+    let (tx, rx) = sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let shutdown = start_predicate_api_server(
+        api_config,
+        Config::devnet_default(),
+        tx,
+        OccurrenceStreams::new(),
+        DeliveryHistory::new(),
+        ctx,
+    )
+    .await
+    .unwrap();
 
     // Loop to check if the server is ready
     let mut attempts = 0;
@@ -290,13 +323,26 @@ pub fn get_chainhook_config(
         http_port: chainhook_port,
         display_logs: true,
         database_uri: format!("redis://localhost:{redis_port}/"),
+        api_keys: vec![],
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
     };
     Config {
         http_api: PredicatesApi::On(api_config),
         predicates: PredicatesConfig::default(),
+        predicates_watch_dir: None,
         pox_config: PoxConfig::devnet_default(),
         storage: StorageConfig {
             working_dir: working_dir.into(),
+            stacks_retention: None,
+            block_cache_size_mb: None,
+            stacks_verification_depth: None,
+            stacks_db_backend: StacksDbBackend::RocksDb,
+            encryption_key_env: None,
+            allow_plaintext_payloads: false,
         },
         event_sources: vec![EventSourceConfig::StacksTsvPath(PathConfig {
             file_path: PathBuf::from(tsv_dir),
@@ -309,21 +355,41 @@ pub fn get_chainhook_config(
             max_number_of_processing_threads: 16,
             max_number_of_networking_threads: 16,
             max_caching_memory_size_mb: 32000,
+            max_number_of_pending_observer_commands: DEFAULT_OBSERVER_COMMANDS_CAPACITY,
+            max_number_of_pending_observer_events: DEFAULT_OBSERVER_EVENTS_CAPACITY,
+            observer_events_full_policy: FullChannelPolicy::Block,
+            max_number_of_pending_scan_operations: DEFAULT_SCAN_OPERATIONS_CAPACITY,
+            scan_operations_full_policy: FullChannelPolicy::Block,
+            max_number_of_cached_bitcoin_blocks: None,
+            max_number_of_prefetched_bitcoin_blocks: BITCOIN_SCAN_PREFETCH_DEPTH,
+            max_number_of_cached_bitcoin_prevouts: None,
         },
         network: IndexerConfig {
             bitcoin_network: BitcoinNetwork::Regtest,
             stacks_network: StacksNetwork::Devnet,
             bitcoind_rpc_username: "".into(),
             bitcoind_rpc_password: "".into(),
+            bitcoind_rpc_fallbacks: vec![],
+            esplora_api_url: None,
             bitcoind_rpc_url: format!("http://0.0.0.0:{bitcoin_rpc_port}"),
             bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(StacksNodeConfig {
                 rpc_url: format!("http://localhost:{stacks_rpc_port}"),
                 ingestion_port: stacks_ingestion_port,
+                ingestion_auth_token: None,
+                ingestion_tls: None,
             }),
+            bitcoin_confirmation_depth: None,
+            stacks_confirmation_depth: None,
+            network_parameters: None,
+            malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
         },
         monitoring: MonitoringConfig {
             prometheus_monitoring_port: prometheus_port,
         },
+        additional_networks: vec![],
+        replication: None,
+        logs: LogsConfig::default(),
+        error_reporting: None,
     }
 }
 
@@ -332,9 +398,10 @@ pub async fn start_chainhook_service(
     ping_startup_port: u16,
     startup_predicates: Option<Vec<ChainhookSpecificationNetworkMap>>,
     ctx: &Context,
-) -> Result<Sender<ObserverCommand>, String> {
+) -> Result<SyncSender<ObserverCommand>, String> {
     let mut service = Service::new(config, ctx.clone());
-    let (observer_command_tx, observer_command_rx) = mpsc::channel();
+    let (observer_command_tx, observer_command_rx) =
+        mpsc::sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let moved_observer_command_tx = observer_command_tx.clone();
     let _ = hiro_system_kit::thread_named("Chainhook service")
         .spawn(move || {
@@ -376,7 +443,7 @@ pub struct TestSetupResult {
     pub stacks_rpc_port: u16,
     pub bitcoin_rpc_port: u16,
     pub prometheus_port: u16,
-    pub observer_command_tx: Sender<ObserverCommand>,
+    pub observer_command_tx: SyncSender<ObserverCommand>,
 }
 
 pub async fn setup_stacks_chainhook_test(
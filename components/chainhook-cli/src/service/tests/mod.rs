@@ -1,17 +1,23 @@
-use chainhook_sdk::chainhooks::types::ChainhookSpecificationNetworkMap;
-use chainhook_sdk::types::Chain;
+use chainhook_sdk::chainhooks::types::{ChainhookSpecificationNetworkMap, ChainhookStore};
+use chainhook_sdk::types::{
+    BitcoinBlockData, BitcoinBlockMetadata, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData,
+    BitcoinNetwork, BlockIdentifier, Chain, StacksChainEvent,
+    StacksChainUpdatedWithMicroblocksData, StacksNetwork,
+};
 use chainhook_sdk::utils::Context;
 use rocket::serde::json::Value as JsonValue;
-use rocket::Shutdown;
 use std::fs::{self};
 use std::net::TcpListener;
 use std::path::PathBuf;
 use std::process::Child;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use test_case::test_case;
 
-use chainhook_sdk::observer::ObserverCommand;
+use chainhook_sdk::observer::{
+    DeliveryHistory, DeliveryRecord, ObserverCommand, ObserverEvent, PredicateEvaluationReport,
+    PredicateInterruptedData,
+};
 
 use self::helpers::build_predicates::{build_bitcoin_payload, build_stacks_payload, DEFAULT_UUID};
 use self::helpers::mock_service::{
@@ -19,24 +25,50 @@ use self::helpers::mock_service::{
     start_chainhook_service,
 };
 use self::helpers::mock_stacks_node::{mine_burn_block, mine_stacks_block};
-use crate::config::PredicatesApi;
+use crate::config::{
+    ApiRateLimitConfig, Config, PredicatesApi, PredicatesApiConfig, StacksDbBackend,
+};
 use crate::service::tests::helpers::build_predicates::get_random_uuid;
 use crate::service::tests::helpers::mock_service::{
-    build_predicate_api_server, call_get_predicate, call_ping, call_register_predicate,
+    build_predicate_api_server, call_check_predicate, call_dry_run_predicate,
+    call_get_openapi_spec, call_get_predicate, call_ping, call_register_predicate,
     get_chainhook_config, get_predicate_status, setup_bitcoin_chainhook_test,
     setup_stacks_chainhook_test, TestSetupResult,
 };
 use crate::service::tests::helpers::mock_stacks_node::create_burn_fork_at;
-use crate::service::{PredicateStatus, PredicateStatus::*, ScanningData, StreamingData};
+use crate::service::{
+    PausedData, PredicateStatus, PredicateStatus::*, ScanningData, StreamingData,
+};
 use crate::storage::{get_all_unconfirmed_blocks, open_readonly_stacks_db_conn};
 
-use super::http_api::document_predicate_api_server;
+use super::api_rate_limit::{ApiRateLimiter, RateLimitedClient, RATE_LIMIT_WINDOW};
+use super::audit::{
+    get_predicate_audit_entries, log_predicate_transition, log_predicate_update,
+    record_predicate_audit_actor, trim_predicate_audit_log, PredicateAuditAction,
+};
+use super::coordinator::{
+    start_chain_events_follower, ChainEventsCoordinator, ChainEventsFollower, DistributedChainEvent,
+};
+use super::dry_run::test_predicate_on_fixtures;
+use super::error_reporting::{ErrorReportLevel, ErrorReporter};
+use super::graphql::{build_predicates_schema, PredicatesGraphQLContext};
+use super::http_api::{
+    document_predicate_api_server, merge_json_patch, PredicateApiShutdown, PredicatesQuery,
+};
+use super::lifecycle::{PredicateLifecycleEvent, PredicateLifecycleEventType};
+use super::predicates_db::{
+    get_predicates_db_name, is_postgres_uri, open_predicates_db, PredicatesDb,
+};
+use super::stats::{format_stats_table, PredicateStats};
 
 pub mod helpers;
 mod observer_tests;
+mod predicates_watcher_tests;
 mod runloop_tests;
 
-async fn test_register_predicate(predicate: JsonValue) -> Result<(), (String, Shutdown)> {
+async fn test_register_predicate(
+    predicate: JsonValue,
+) -> Result<(), (String, PredicateApiShutdown)> {
     // perhaps a little janky, we bind to the port 0 to find an open one, then
     // drop the listener to free up that port
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind to port 0");
@@ -145,7 +177,9 @@ async fn it_handles_bitcoin_if_this_predicates(if_this: JsonValue) {
 
 #[test_case(json!("noop") ; "with noop action")]
 #[test_case(json!({"http_post": {"url": "http://localhost:1234", "authorization_header": "Bearer FYRPnz2KHj6HueFmaJ8GGD3YMbirEFfh"}}) ; "with http_post action")]
+#[test_case(json!({"http_post": {"url": "http://localhost:1234", "authorization_header": "Bearer FYRPnz2KHj6HueFmaJ8GGD3YMbirEFfh", "require_ack": true}}) ; "with http_post action requiring acks")]
 #[test_case(json!({"file_append": {"path": "./path"}}) ; "with file_append action")]
+#[test_case(json!(["noop", {"file_append": {"path": "./path"}}]) ; "with multiple actions")]
 #[tokio::test]
 async fn it_handles_bitcoin_then_that_predicates(then_that: JsonValue) {
     let predicate = build_bitcoin_payload(None, None, Some(then_that), None, None);
@@ -228,7 +262,9 @@ async fn it_handles_stacks_if_this_predicates(if_this: JsonValue) {
 
 #[test_case(json!("noop") ; "with noop action")]
 #[test_case(json!({"http_post": {"url": "http://localhost:1234", "authorization_header": "Bearer FYRPnz2KHj6HueFmaJ8GGD3YMbirEFfh"}}) ; "with http_post action")]
+#[test_case(json!({"http_post": {"url": "http://localhost:1234", "authorization_header": "Bearer FYRPnz2KHj6HueFmaJ8GGD3YMbirEFfh", "require_ack": true}}) ; "with http_post action requiring acks")]
 #[test_case(json!({"file_append": {"path": "./path"}}) ; "with file_append action")]
+#[test_case(json!(["noop", {"file_append": {"path": "./path"}}]) ; "with multiple actions")]
 #[tokio::test]
 async fn it_handles_stacks_then_that_predicates(then_that: JsonValue) {
     let predicate = build_stacks_payload(None, None, Some(then_that), None, None);
@@ -840,6 +876,7 @@ async fn register_predicate_responds_409_if_uuid_in_use() -> Result<(), String>
     Ok(())
 }
 
+#[cfg(not(feature = "axum-ingestion"))]
 #[test]
 fn it_generates_open_api_spec() {
     let new_spec = document_predicate_api_server().unwrap();
@@ -854,6 +891,900 @@ fn it_generates_open_api_spec() {
     )
 }
 
+// The components of the documented spec are generated by Rocket, only the paths are compared.
+#[cfg(feature = "axum-ingestion")]
+#[test]
+fn it_documents_the_axum_routes_like_the_open_api_spec() {
+    let new_spec: JsonValue =
+        serde_json::from_str(&document_predicate_api_server().unwrap()).unwrap();
+
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("../../docs/chainhook-openapi.json");
+    let current_spec: JsonValue = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+
+    assert_eq!(current_spec["paths"], new_spec["paths"]);
+    assert_eq!(current_spec["info"], new_spec["info"]);
+}
+
+#[tokio::test]
+async fn it_serves_open_api_spec() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind to port 0");
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let (_rx, shutdown) = build_predicate_api_server(port).await;
+    let served_spec = call_get_openapi_spec(port).await;
+    shutdown.notify();
+
+    let spec: JsonValue = serde_json::from_str(&document_predicate_api_server().unwrap()).unwrap();
+    assert_eq!(served_spec.unwrap(), spec);
+}
+
+#[tokio::test]
+async fn it_dry_runs_predicates_on_a_block() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind to port 0");
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let block = json!({
+        "block_identifier": { "index": 1, "hash": "0x01" },
+        "parent_block_identifier": { "index": 0, "hash": "0x00" },
+        "timestamp": 0,
+        "transactions": [{
+            "transaction_identifier": { "hash": "0x0a" },
+            "operations": [],
+            "metadata": {
+                "inputs": [],
+                "outputs": [],
+                "stacks_operations": [],
+                "ordinal_operations": [],
+                "brc20_operation": null,
+                "proof": null,
+                "fee": 0,
+                "index": 0
+            }
+        }],
+        "metadata": { "network": "regtest" }
+    });
+    let predicate =
+        |if_this| build_bitcoin_payload(Some("regtest"), Some(if_this), None, None, None);
+
+    let (rx, shutdown) = build_predicate_api_server(port).await;
+    let triggered = call_dry_run_predicate(
+        &json!({ "predicate": predicate(json!({"scope": "block"})), "block": block }),
+        port,
+    )
+    .await;
+    let not_triggered = call_dry_run_predicate(
+        &json!({
+            "predicate": predicate(json!({"scope": "txid", "equals": "0x0b"})),
+            "block": block
+        }),
+        port,
+    )
+    .await;
+    let missing_block = call_dry_run_predicate(
+        &json!({ "predicate": predicate(json!({"scope": "block"})) }),
+        port,
+    )
+    .await;
+    shutdown.notify();
+
+    let occurrences = triggered.unwrap()["result"]["occurrences"].clone();
+    assert_eq!(occurrences.as_array().unwrap().len(), 1);
+    assert_eq!(occurrences[0]["apply"][0]["block_identifier"]["index"], 1);
+    assert_eq!(
+        occurrences[0]["apply"][0]["transactions"][0]["transaction_identifier"]["hash"],
+        "0x0a"
+    );
+    assert_eq!(not_triggered.unwrap()["result"]["occurrences"], json!([]));
+    assert_eq!(missing_block.unwrap()["status"], 422);
+    assert!(
+        rx.try_recv().is_err(),
+        "dry runs must not register predicates"
+    );
+}
+
+#[test]
+fn it_tests_predicates_on_block_fixtures() {
+    let block = json!({
+        "block_identifier": { "index": 1, "hash": "0x01" },
+        "parent_block_identifier": { "index": 0, "hash": "0x00" },
+        "timestamp": 0,
+        "transactions": [{
+            "transaction_identifier": { "hash": "0x0a" },
+            "operations": [],
+            "metadata": {
+                "inputs": [],
+                "outputs": [],
+                "stacks_operations": [],
+                "ordinal_operations": [],
+                "brc20_operation": null,
+                "proof": null,
+                "fee": 0,
+                "index": 0
+            }
+        }],
+        "metadata": { "network": "regtest" }
+    });
+    let mut empty_block = block.clone();
+    empty_block["block_identifier"] = json!({ "index": 2, "hash": "0x02" });
+    empty_block["transactions"] = json!([]);
+    let fixture_path = std::env::temp_dir().join(format!("chainhook-{}.json", get_random_uuid()));
+    fs::write(&fixture_path, json!([block, empty_block]).to_string()).unwrap();
+
+    let predicate = serde_json::from_value(build_bitcoin_payload(
+        Some("regtest"),
+        Some(json!({"scope": "txid", "equals": "0x0a"})),
+        None,
+        None,
+        None,
+    ))
+    .unwrap();
+    let evaluations = test_predicate_on_fixtures(
+        predicate,
+        &[fixture_path.display().to_string()],
+        &Config::devnet_default(),
+        &Context::empty(),
+    )
+    .unwrap();
+    fs::remove_file(&fixture_path).unwrap();
+
+    assert_eq!(evaluations.len(), 2);
+    assert_eq!(evaluations[0].occurrences.len(), 1);
+    assert_eq!(evaluations[0].reasons.len(), 1);
+    assert!(evaluations[0].reasons[0].starts_with("transaction 0x0a matches"));
+    assert!(evaluations[1].occurrences.is_empty());
+    assert!(evaluations[1].reasons.is_empty());
+}
+
+#[tokio::test]
+async fn it_checks_predicates() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind to port 0");
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let valid = build_bitcoin_payload(Some("regtest"), None, None, None, None);
+    let mut invalid = build_bitcoin_payload(
+        Some("regtest"),
+        Some(
+            json!({"scope": "outputs", "p2pkh": {"equals": "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"}}),
+        ),
+        None,
+        None,
+        None,
+    );
+    invalid["networks"]["regtest"]["end_blok"] = json!(10);
+
+    let (rx, shutdown) = build_predicate_api_server(port).await;
+    let valid = call_check_predicate(&valid, port).await.unwrap();
+    let invalid = call_check_predicate(&invalid, port).await.unwrap();
+    let malformed = call_check_predicate(&json!({"chain": "bitcoin"}), port)
+        .await
+        .unwrap();
+    shutdown.notify();
+
+    assert_eq!(valid["result"], json!({"valid": true, "diagnostics": []}));
+    assert_eq!(invalid["result"]["valid"], false);
+    let diagnostics = invalid["result"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0]["severity"], "warning");
+    assert_eq!(diagnostics[0]["path"], "networks.regtest.end_blok");
+    assert_eq!(diagnostics[1]["severity"], "error");
+    assert_eq!(
+        diagnostics[1]["path"],
+        "networks.regtest.if_this.p2pkh.equals"
+    );
+    assert_eq!(malformed["result"]["valid"], false);
+    assert!(
+        rx.try_recv().is_err(),
+        "checks must not register predicates"
+    );
+}
+
+#[test]
+fn it_filters_sorts_and_paginates_predicates() {
+    let mut store = ChainhookStore::new();
+    let mut predicates = vec![];
+    let bitcoin = |uuid| build_bitcoin_payload(None, None, None, None, Some(uuid));
+    let stacks = |uuid| build_stacks_payload(None, None, None, None, Some(uuid));
+    let interrupted = Interrupted("".into());
+    // Registered in reverse order
+    for (mut payload, label, team, status, registered_at) in [
+        (bitcoin("a"), "x", "payments", New, 3),
+        (stacks("b"), "x", "payments", interrupted.clone(), 2),
+        (bitcoin("c"), "y", "indexing", interrupted, 1),
+    ] {
+        payload["labels"] = json!([label]);
+        payload["metadata"] = json!({ "team": team });
+        let network_map: ChainhookSpecificationNetworkMap =
+            serde_json::from_value(payload).unwrap();
+        let predicate = store
+            .register_instance_from_network_map(
+                (&BitcoinNetwork::Mainnet, &StacksNetwork::Mainnet),
+                network_map,
+            )
+            .unwrap();
+        predicates.push((predicate, status, Some(registered_at)));
+    }
+    let list = |query: PredicatesQuery| {
+        let matching = predicates
+            .iter()
+            .filter(|(p, s, _)| query.matches(p, s))
+            .cloned()
+            .collect();
+        let (page, total) = query.paginate(matching);
+        let uuids = page
+            .iter()
+            .map(|(p, _, _)| p.uuid().to_string())
+            .collect::<Vec<_>>();
+        (uuids, total)
+    };
+
+    assert_eq!(
+        list(PredicatesQuery::default()),
+        (vec!["c".into(), "b".into(), "a".into()], 3)
+    );
+    assert_eq!(
+        list(PredicatesQuery {
+            chain: Some("bitcoin".into()),
+            descending: true,
+            ..Default::default()
+        }),
+        (vec!["a".into(), "c".into()], 2)
+    );
+    assert_eq!(
+        list(PredicatesQuery {
+            label: Some("x".into()),
+            status: Some("interrupted".into()),
+            ..Default::default()
+        }),
+        (vec!["b".into()], 1)
+    );
+    assert_eq!(
+        list(PredicatesQuery {
+            metadata: Some(("team".into(), "payments".into())),
+            ..Default::default()
+        }),
+        (vec!["b".into(), "a".into()], 2)
+    );
+    assert_eq!(
+        list(PredicatesQuery {
+            metadata: Some(("owner".into(), "payments".into())),
+            ..Default::default()
+        }),
+        (vec![], 0)
+    );
+    assert_eq!(
+        list(PredicatesQuery {
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        }),
+        (vec!["b".into()], 3)
+    );
+}
+
+#[test]
+fn it_scopes_predicates_to_their_namespace() {
+    let mut store = ChainhookStore::new();
+    let mut predicates = vec![];
+    for (uuid, namespace) in [("a", Some("team-a")), ("b", None), ("c", Some("default"))] {
+        let mut payload = build_bitcoin_payload(None, None, None, None, Some(uuid));
+        if let Some(namespace) = namespace {
+            payload["namespace"] = json!(namespace);
+        }
+        let network_map: ChainhookSpecificationNetworkMap =
+            serde_json::from_value(payload).unwrap();
+        network_map.validate().unwrap();
+        let predicate = store
+            .register_instance_from_network_map(
+                (&BitcoinNetwork::Mainnet, &StacksNetwork::Mainnet),
+                network_map,
+            )
+            .unwrap();
+        predicates.push((predicate, New));
+    }
+    let keys = predicates.iter().map(|(p, _)| p.key()).collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        vec!["predicate:team-a:a", "predicate:b", "predicate:c"]
+    );
+
+    let list = |namespace: &str| {
+        let query = PredicatesQuery {
+            namespace: Some(namespace.into()),
+            ..Default::default()
+        };
+        predicates
+            .iter()
+            .filter(|(p, s)| query.matches(p, s))
+            .map(|(p, _)| p.uuid().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(list("team-a"), vec!["a"]);
+    assert_eq!(list("default"), vec!["b", "c"]);
+    assert!(list("team-b").is_empty());
+
+    let mut payload = build_bitcoin_payload(None, None, None, None, Some("d"));
+    payload["namespace"] = json!("team:a");
+    let network_map: ChainhookSpecificationNetworkMap = serde_json::from_value(payload).unwrap();
+    network_map
+        .validate()
+        .expect_err("Did not reject invalid namespace as expected.");
+}
+
+#[test]
+fn it_resolves_graphql_queries() {
+    let mut store = ChainhookStore::new();
+    let mut predicates = vec![];
+    let streaming = Streaming(StreamingData {
+        last_occurrence: Some(1700000000),
+        last_evaluation: 1700000000,
+        number_of_times_triggered: 2,
+        number_of_blocks_evaluated: 10,
+        last_evaluated_block_height: 840000,
+    });
+    let bitcoin = |uuid| build_bitcoin_payload(None, None, None, None, Some(uuid));
+    let stacks = |uuid| build_stacks_payload(None, None, None, None, Some(uuid));
+    for (payload, status) in [(bitcoin("a"), New), (stacks("b"), streaming)] {
+        let network_map: ChainhookSpecificationNetworkMap =
+            serde_json::from_value(payload).unwrap();
+        let predicate = store
+            .register_instance_from_network_map(
+                (&BitcoinNetwork::Mainnet, &StacksNetwork::Mainnet),
+                network_map,
+            )
+            .unwrap();
+        predicates.push((predicate, status, None));
+    }
+    let delivery_history = DeliveryHistory::new();
+    delivery_history.record(
+        "b",
+        DeliveryRecord::new("1", "http://localhost:3000".into(), None, Some(200), 5, 64),
+    );
+    let context = PredicatesGraphQLContext {
+        predicates,
+        delivery_history,
+    };
+    let schema = build_predicates_schema();
+    let query = r#"{
+        predicates(chain: "stacks") {
+            uuid
+            status { type lastEvaluatedBlockHeight numberOfTimesTriggered }
+            deliveries { deliveryId success httpStatus }
+        }
+        predicate(uuid: "a") { chain status { type } }
+    }"#;
+    let variables = juniper::Variables::new();
+    let (result, errors) =
+        juniper::execute_sync(query, None, &schema, &variables, &context).unwrap();
+
+    assert!(errors.is_empty());
+    assert_eq!(
+        json!(result),
+        json!({
+            "predicates": [{
+                "uuid": "b",
+                "status": {
+                    "type": "streaming",
+                    "lastEvaluatedBlockHeight": 840000,
+                    "numberOfTimesTriggered": 2,
+                },
+                "deliveries": [{ "deliveryId": "1", "success": true, "httpStatus": 200 }],
+            }],
+            "predicate": { "chain": "bitcoin", "status": { "type": "new" } },
+        })
+    );
+}
+
+#[test]
+fn it_reports_predicate_stats() {
+    let mut store = ChainhookStore::new();
+    let network_map: ChainhookSpecificationNetworkMap =
+        serde_json::from_value(build_bitcoin_payload(None, None, None, None, Some("a"))).unwrap();
+    let predicate = store
+        .register_instance_from_network_map(
+            (&BitcoinNetwork::Mainnet, &StacksNetwork::Mainnet),
+            network_map,
+        )
+        .unwrap();
+    let streaming = Streaming(StreamingData {
+        last_occurrence: Some(1700000000),
+        last_evaluation: 1700000000,
+        number_of_times_triggered: 2,
+        number_of_blocks_evaluated: 10,
+        last_evaluated_block_height: 840000,
+    });
+    let records = [
+        None,
+        Some("Trigger failed with status 500".to_string()),
+        None,
+        None,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, error)| {
+        DeliveryRecord::new(
+            &i.to_string(),
+            "http://localhost:3000".into(),
+            error,
+            None,
+            5,
+            64,
+        )
+    })
+    .collect::<Vec<_>>();
+
+    let stats = PredicateStats::new(&predicate, &streaming, &records);
+    assert_eq!(stats.status, "streaming");
+    assert_eq!(stats.number_of_times_triggered, Some(2));
+    assert_eq!(stats.number_of_blocks_evaluated, Some(10));
+    assert_eq!(stats.successful_deliveries, 3);
+    assert_eq!(stats.delivery_success_rate, Some(0.75));
+
+    let interrupted = PredicateStats::new(&predicate, &Interrupted("error".into()), &[]);
+    assert_eq!(interrupted.number_of_times_triggered, None);
+    assert_eq!(interrupted.delivery_success_rate, None);
+
+    let table = format_stats_table(&[stats, interrupted], 1700007200);
+    let rows = table.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].starts_with("UUID  NAME"));
+    assert!(rows[1].contains("2h ago"));
+    assert!(rows[1].ends_with("75.0%"));
+    assert!(rows[2].contains("interrupted"));
+    assert!(rows[2].ends_with("-"));
+}
+
+#[test]
+fn it_notifies_predicate_lifecycle_transitions() {
+    let streaming = Streaming(StreamingData {
+        last_occurrence: None,
+        last_evaluation: 0,
+        number_of_times_triggered: 0,
+        number_of_blocks_evaluated: 1,
+        last_evaluated_block_height: 100,
+    });
+    let scanning = Scanning(ScanningData::default());
+    let interrupted = Interrupted("unable to reach endpoint".into());
+    let transition = PredicateLifecycleEventType::from_transition;
+
+    assert_eq!(
+        transition(None, &New),
+        Some(PredicateLifecycleEventType::Registered)
+    );
+    assert_eq!(transition(Some(&New), &scanning), None);
+    assert_eq!(
+        transition(Some(&scanning), &streaming),
+        Some(PredicateLifecycleEventType::Streaming)
+    );
+    assert_eq!(transition(Some(&streaming), &streaming), None);
+    assert_eq!(
+        transition(Some(&streaming), &interrupted),
+        Some(PredicateLifecycleEventType::Interrupted)
+    );
+
+    let event = PredicateLifecycleEvent::new(
+        "predicate:team-a:1234",
+        PredicateLifecycleEventType::Interrupted,
+        Some(interrupted),
+    );
+    assert_eq!(event.uuid, "1234");
+    assert_eq!(json!(event)["event"], "interrupted");
+}
+
+#[test]
+fn it_audits_predicate_lifecycle_transitions() {
+    let scanning = Scanning(ScanningData::default());
+    let paused = |requested| {
+        Paused(PausedData {
+            reason: "paused".into(),
+            requested,
+            number_of_blocks_evaluated: 0,
+            number_of_times_triggered: 0,
+            last_occurrence: None,
+            last_evaluated_block_height: 0,
+        })
+    };
+    let transition = PredicateAuditAction::from_transition;
+    assert_eq!(
+        transition(None, Some(&New)),
+        Some(PredicateAuditAction::Registered)
+    );
+    assert_eq!(transition(Some(&New), Some(&scanning)), None);
+    assert_eq!(
+        transition(Some(&scanning), Some(&paused(false))),
+        Some(PredicateAuditAction::Paused)
+    );
+    assert_eq!(
+        transition(Some(&paused(false)), Some(&paused(true))),
+        Some(PredicateAuditAction::Paused)
+    );
+    assert_eq!(transition(Some(&paused(true)), Some(&paused(true))), None);
+    assert_eq!(
+        transition(Some(&paused(true)), Some(&scanning)),
+        Some(PredicateAuditAction::Enabled)
+    );
+    assert_eq!(
+        transition(Some(&scanning), None),
+        Some(PredicateAuditAction::Deregistered)
+    );
+    assert_eq!(transition(None, None), None);
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("chainhook-{}", uuid::Uuid::new_v4()));
+    let mut predicates_db = open_predicates_db(&format!("rocksdb://{}", path.display())).unwrap();
+    let ctx = Context::empty();
+    record_predicate_audit_actor("predicate:1234", "api_key:****abcd", predicates_db.as_mut())
+        .unwrap();
+    record_predicate_audit_actor(
+        "predicate:team-a:1234",
+        "api_key:****efgh",
+        predicates_db.as_mut(),
+    )
+    .unwrap();
+    log_predicate_transition(
+        "predicate:1234",
+        None,
+        Some(&New),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+    log_predicate_transition(
+        "predicate:1234",
+        Some(&New),
+        Some(&scanning),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+    log_predicate_transition(
+        "predicate:1234",
+        Some(&scanning),
+        Some(&paused(false)),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+    log_predicate_transition(
+        "predicate:team-a:5678",
+        None,
+        Some(&New),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+    log_predicate_transition(
+        "predicate:team-a:1234",
+        None,
+        Some(&New),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+
+    let entries =
+        get_predicate_audit_entries("default", Some("1234"), 10, predicates_db.as_mut()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].action, PredicateAuditAction::Paused);
+    assert_eq!(entries[0].actor, None);
+    assert_eq!(entries[0].before, Some(scanning));
+    assert_eq!(entries[1].action, PredicateAuditAction::Registered);
+    assert_eq!(entries[1].actor.as_deref(), Some("api_key:****abcd"));
+    let entries = get_predicate_audit_entries("team-a", None, 10, predicates_db.as_mut()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].uuid, "1234");
+    assert_eq!(entries[0].actor.as_deref(), Some("api_key:****efgh"));
+    assert_eq!(entries[1].uuid, "5678");
+    let entries = get_predicate_audit_entries("default", None, 1, predicates_db.as_mut()).unwrap();
+    assert_eq!(entries.len(), 1);
+
+    trim_predicate_audit_log(2, predicates_db.as_mut()).unwrap();
+    let entries = get_predicate_audit_entries("team-a", None, 10, predicates_db.as_mut()).unwrap();
+    assert_eq!(entries.len(), 2);
+    let entries = get_predicate_audit_entries("default", None, 10, predicates_db.as_mut()).unwrap();
+    assert!(entries.is_empty());
+
+    // updates keep the status of the predicate
+    record_predicate_audit_actor("predicate:1234", "api_key:****abcd", predicates_db.as_mut())
+        .unwrap();
+    log_predicate_update(
+        "predicate:1234",
+        Some(&scanning),
+        predicates_db.as_mut(),
+        &ctx,
+    );
+    let entries = get_predicate_audit_entries("default", None, 10, predicates_db.as_mut()).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, PredicateAuditAction::Updated);
+    assert_eq!(entries[0].actor.as_deref(), Some("api_key:****abcd"));
+    assert_eq!(entries[0].before, entries[0].after);
+    let _ = fs::remove_dir_all(&path);
+}
+
+#[test]
+fn it_merges_predicate_patches() {
+    let mut then_that = json!({
+        "http_post": {
+            "url": "http://localhost:3000/api/v1/wrapBtc",
+            "authorization_header": "Bearer cn389ncoiwuencr",
+        }
+    });
+    merge_json_patch(
+        &mut then_that,
+        json!({
+            "http_post": {
+                "url": "http://localhost:4000/api/v1/wrapBtc",
+                "authorization_header": null,
+            }
+        }),
+    );
+    assert_eq!(
+        then_that,
+        json!({
+            "http_post": {
+                "url": "http://localhost:4000/api/v1/wrapBtc",
+            }
+        })
+    );
+    merge_json_patch(&mut then_that, json!("noop"));
+    assert_eq!(then_that, json!("noop"));
+}
+
+#[test]
+fn it_reports_errors_with_their_context() {
+    let mut error_reporter = ErrorReporter::default();
+    let report = error_reporter
+        .report_of(&ObserverEvent::Fatal("observer failed".into()))
+        .unwrap();
+    assert_eq!(report.level, ErrorReportLevel::Fatal);
+    assert_eq!(report.predicate_uuid, None);
+    assert_eq!(report.bitcoin_block_height, None);
+
+    let block_identifier = BlockIdentifier {
+        index: 840000,
+        hash: "0x00".into(),
+    };
+    let chain_event =
+        BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+            new_blocks: vec![BitcoinBlockData {
+                block_identifier: block_identifier.clone(),
+                parent_block_identifier: block_identifier,
+                timestamp: 0,
+                transactions: vec![],
+                metadata: BitcoinBlockMetadata {
+                    network: BitcoinNetwork::Regtest,
+                },
+            }],
+            confirmed_blocks: vec![],
+        });
+    assert_eq!(
+        error_reporter.report_of(&ObserverEvent::BitcoinChainEvent((
+            chain_event,
+            PredicateEvaluationReport::new()
+        ))),
+        None
+    );
+    let report = error_reporter
+        .report_of(&ObserverEvent::PredicateInterrupted(
+            PredicateInterruptedData {
+                predicate_key: "predicate:team-a:1234".into(),
+                error: "unable to evaluate".into(),
+            },
+        ))
+        .unwrap();
+    assert_eq!(report.level, ErrorReportLevel::Error);
+    assert_eq!(report.predicate_uuid.as_deref(), Some("1234"));
+    assert_eq!(report.bitcoin_block_height, Some(840000));
+    assert_eq!(report.stacks_block_height, None);
+    assert!(error_reporter
+        .report_of(&ObserverEvent::Info("started".into()))
+        .is_none());
+}
+
+#[test]
+fn it_rate_limits_api_clients() {
+    let rate_limiter = ApiRateLimiter::new(ApiRateLimitConfig {
+        requests_per_minute_per_ip: Some(2),
+        requests_per_minute_per_key: None,
+    });
+    let client = RateLimitedClient::Ip(Some([127, 0, 0, 1].into()));
+    let other_client = RateLimitedClient::Ip(Some([127, 0, 0, 2].into()));
+    let now = Instant::now();
+
+    let decision = rate_limiter.check(client.clone(), now).unwrap();
+    assert!(decision.allowed);
+    assert_eq!(decision.remaining, 1);
+    assert!(rate_limiter.check(client.clone(), now).unwrap().allowed);
+    let decision = rate_limiter.check(client.clone(), now).unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.remaining, 0);
+    assert_eq!(decision.reset_after, RATE_LIMIT_WINDOW);
+    assert!(rate_limiter.check(other_client, now).unwrap().allowed);
+    assert_eq!(
+        rate_limiter.check(RateLimitedClient::ApiKey("key".into()), now),
+        None
+    );
+
+    let decision = rate_limiter.check(client, now + RATE_LIMIT_WINDOW).unwrap();
+    assert!(decision.allowed);
+    assert_eq!(decision.remaining, 1);
+}
+
+#[test]
+fn it_selects_the_predicates_db_from_its_uri() {
+    assert!(is_postgres_uri("postgres://localhost:5432/chainhook"));
+    assert!(is_postgres_uri("postgresql://localhost:5432/chainhook"));
+    assert!(!is_postgres_uri("redis://localhost:6379/"));
+    assert!(open_predicates_db("postgres://localhost:1/chainhook").is_err());
+    assert_eq!(get_predicates_db_name("redis://localhost:6379/"), "redis");
+    assert_eq!(
+        get_predicates_db_name("postgres://localhost:5432/chainhook"),
+        "postgres"
+    );
+    assert_eq!(
+        get_predicates_db_name("rocksdb://./cache/predicates.rocksdb"),
+        "predicates_rocksdb"
+    );
+}
+
+#[test]
+fn it_stores_predicates_in_the_embedded_db() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("chainhook-{}", uuid::Uuid::new_v4()));
+    let uri = format!("rocksdb://{}", path.display());
+    let mut predicates_db = open_predicates_db(&uri).unwrap();
+    // Connections to the same path share the instance already opened
+    let mut other_conn = open_predicates_db(&uri).unwrap();
+
+    predicates_db
+        .hset("predicate:stacks-1", "status", "new")
+        .unwrap();
+    predicates_db
+        .hset_nx("predicate:stacks-1", "status", "streaming")
+        .unwrap();
+    predicates_db
+        .hset("predicate:bitcoin-2", "status", "new")
+        .unwrap();
+    assert_eq!(
+        other_conn.hget("predicate:stacks-1", "status").unwrap(),
+        Some("new".to_string())
+    );
+    assert_eq!(
+        predicates_db.scan_keys("predicate:stacks-*").unwrap(),
+        vec!["predicate:stacks-1".to_string()]
+    );
+
+    for value in ["a", "b", "c"] {
+        predicates_db.lpush_capped("history", value, 2).unwrap();
+    }
+    assert_eq!(predicates_db.lrange_all("history").unwrap(), vec!["c", "b"]);
+
+    for (member, score) in [("x", 3), ("y", 1), ("z", 2)] {
+        predicates_db
+            .zadd_capped("delivered", member, score, 2)
+            .unwrap();
+    }
+    assert_eq!(predicates_db.zscore("delivered", "y").unwrap(), None);
+    assert_eq!(predicates_db.zscore("delivered", "x").unwrap(), Some(3));
+
+    assert_eq!(
+        predicates_db.hincr("predicate:stacks-1", "reorgs").unwrap(),
+        1
+    );
+    assert_eq!(other_conn.hincr("predicate:stacks-1", "reorgs").unwrap(), 2);
+
+    predicates_db.del("predicate:stacks-1").unwrap();
+    assert!(predicates_db
+        .hgetall("predicate:stacks-1")
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        predicates_db.scan_keys("*").unwrap(),
+        vec!["delivered", "history", "predicate:bitcoin-2"]
+    );
+}
+
+#[test]
+fn it_distributes_chain_events_to_replicas() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("chainhook-{}", uuid::Uuid::new_v4()));
+    let api_config = PredicatesApiConfig {
+        http_port: 0,
+        display_logs: false,
+        database_uri: format!("rocksdb://{}", path.display()),
+        api_keys: vec![],
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
+    };
+    let mut coordinator = ChainEventsCoordinator::new(&api_config).unwrap();
+
+    let (observer_command_tx, observer_command_rx) = std::sync::mpsc::sync_channel(10);
+    let moved_api_config = api_config.clone();
+    std::thread::spawn(move || {
+        let _ =
+            start_chain_events_follower(&moved_api_config, &observer_command_tx, &Context::empty());
+    });
+    // events published before the replica subscribes are not received
+    sleep(Duration::from_millis(500));
+
+    let event =
+        StacksChainEvent::ChainUpdatedWithMicroblocks(StacksChainUpdatedWithMicroblocksData {
+            new_microblocks: vec![],
+        });
+    coordinator.distribute(
+        &DistributedChainEvent::Stacks(event.clone()),
+        &Context::empty(),
+    );
+    match observer_command_rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(ObserverCommand::PropagateStacksChainEvent(received)) => assert_eq!(received, event),
+        _ => panic!("chain event was not distributed"),
+    }
+}
+
+#[test]
+fn it_replays_missed_chain_events_to_replicas() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("chainhook-{}", uuid::Uuid::new_v4()));
+    let database_uri = format!("rocksdb://{}", path.display());
+    let api_config = PredicatesApiConfig {
+        http_port: 0,
+        display_logs: false,
+        database_uri: database_uri.clone(),
+        api_keys: vec![],
+        lifecycle_webhook_url: None,
+        tls: None,
+        cors: None,
+        rate_limit: None,
+        audit_log_max_entries: None,
+    };
+    let ctx = Context::empty();
+    let mut predicates_db = open_predicates_db(&database_uri).unwrap();
+    let (observer_command_tx, observer_command_rx) = std::sync::mpsc::sync_channel(10);
+    let event = DistributedChainEvent::Stacks(StacksChainEvent::ChainUpdatedWithMicroblocks(
+        StacksChainUpdatedWithMicroblocksData {
+            new_microblocks: vec![],
+        },
+    ));
+    let mut coordinator = ChainEventsCoordinator::new(&api_config).unwrap();
+    let mut follower = ChainEventsFollower::new();
+
+    coordinator.distribute(&event, &ctx);
+    follower
+        .on_published(0, predicates_db.as_mut(), &observer_command_tx, &ctx)
+        .unwrap();
+    assert_eq!(observer_command_rx.try_iter().count(), 1);
+
+    // events 1 and 2 are distributed while the replica isn't subscribed
+    coordinator.distribute(&event, &ctx);
+    coordinator.distribute(&event, &ctx);
+    follower
+        .catch_up(predicates_db.as_mut(), &observer_command_tx, &ctx)
+        .unwrap();
+    assert_eq!(observer_command_rx.try_iter().count(), 2);
+
+    // event 3 is published before the replica subscribes again, event 4 after
+    coordinator.distribute(&event, &ctx);
+    coordinator.distribute(&event, &ctx);
+    follower
+        .on_published(4, predicates_db.as_mut(), &observer_command_tx, &ctx)
+        .unwrap();
+    assert_eq!(observer_command_rx.try_iter().count(), 2);
+    follower
+        .on_published(3, predicates_db.as_mut(), &observer_command_tx, &ctx)
+        .unwrap();
+    assert_eq!(observer_command_rx.try_iter().count(), 0);
+
+    // a restarted primary carries on with the sequence
+    drop(coordinator);
+    let mut coordinator = ChainEventsCoordinator::new(&api_config).unwrap();
+    coordinator.distribute(&event, &ctx);
+    follower
+        .on_published(5, predicates_db.as_mut(), &observer_command_tx, &ctx)
+        .unwrap();
+    assert_eq!(observer_command_rx.try_iter().count(), 1);
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "redis_tests"), ignore)]
 async fn it_seeds_block_pool_on_startup() -> Result<(), String> {
@@ -896,9 +1827,10 @@ async fn it_seeds_block_pool_on_startup() -> Result<(), String> {
         destination_path.push(&working_dir);
         destination_path
     };
-    let stacks_db = open_readonly_stacks_db_conn(&db_path, &ctx).expect("unable to read stacks_db");
+    let stacks_db = open_readonly_stacks_db_conn(&db_path, &StacksDbBackend::RocksDb, &ctx)
+        .expect("unable to read stacks_db");
     // validate that all blocks we just mined are saved as unconfirmed blocks in the database
-    let unconfirmed_blocks = get_all_unconfirmed_blocks(&stacks_db, &ctx)
+    let unconfirmed_blocks = get_all_unconfirmed_blocks(stacks_db.as_ref(), &ctx)
         .map_err(|e| cleanup_err(e, &working_dir, redis_port, &mut redis_process))?;
     let mut unconfirmed_height = starting_chain_tip + 1;
     assert_eq!(
@@ -934,7 +1866,7 @@ async fn it_seeds_block_pool_on_startup() -> Result<(), String> {
     config.http_api = PredicatesApi::Off;
     let _ = start_chainhook_service(config, stacks_ingestion_port, None, &ctx).await;
     // validate that all of the unconfirmed blocks we just saved are still available after a restart
-    let unconfirmed_blocks = get_all_unconfirmed_blocks(&stacks_db, &ctx).unwrap();
+    let unconfirmed_blocks = get_all_unconfirmed_blocks(stacks_db.as_ref(), &ctx).unwrap();
     let mut unconfirmed_height = starting_chain_tip + 1;
     assert_eq!(
         blocks_to_mine,
@@ -1,9 +1,11 @@
-use std::{sync::mpsc::channel, thread::sleep, time::Duration};
+use std::{sync::mpsc::sync_channel, thread::sleep, time::Duration};
 
 use chainhook_sdk::{
-    chainhooks::types::ChainhookStore,
-    observer::{start_event_observer, EventObserverConfig, PredicatesConfig},
-    types::{BitcoinNetwork, StacksNodeConfig},
+    observer::{
+        start_event_observer, EventObserverConfig, EventObserverConfigBuilder,
+        ObserverDependencies, DEFAULT_OBSERVER_COMMANDS_CAPACITY,
+    },
+    types::{BitcoinBlockSignaling, StacksNodeConfig},
     utils::Context,
 };
 use reqwest::Method;
@@ -155,7 +157,8 @@ async fn bitcoin_rpc_requests_are_forwarded(endpoint: &str, body: Value) {
 }
 
 async fn start_and_ping_event_observer(config: EventObserverConfig, ingestion_port: u16) {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let logger = hiro_system_kit::log::setup_logger();
     let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
     let ctx = Context {
@@ -168,6 +171,7 @@ async fn start_and_ping_event_observer(config: EventObserverConfig, ingestion_po
         observer_commands_rx,
         None,
         None,
+        ObserverDependencies::default(),
         None,
         ctx,
     )
@@ -188,26 +192,47 @@ async fn it_responds_200_for_unimplemented_endpoints(
     let (_working_dir, _tsv_dir) = create_tmp_working_dir().unwrap_or_else(|e| {
         panic!("test failed with error: {e}");
     });
-    let config = EventObserverConfig {
-        registered_chainhooks: ChainhookStore::new(),
-        predicates_config: PredicatesConfig::default(),
-        bitcoin_rpc_proxy_enabled: false,
-        bitcoind_rpc_username: String::new(),
-        bitcoind_rpc_password: String::new(),
-        bitcoind_rpc_url: String::new(),
-        bitcoin_block_signaling: chainhook_sdk::types::BitcoinBlockSignaling::Stacks(
-            StacksNodeConfig {
-                rpc_url: String::new(),
-                ingestion_port,
-            },
-        ),
-        display_stacks_ingestion_logs: false,
-        bitcoin_network: BitcoinNetwork::Regtest,
-        stacks_network: chainhook_sdk::types::StacksNetwork::Devnet,
-        prometheus_monitoring_port: None,
-    };
+    let config = build_ingestion_test_config(ingestion_port, None);
     start_and_ping_event_observer(config, ingestion_port).await;
     let url = format!("http://localhost:{ingestion_port}{endpoint}");
     let response = call_observer_svc(&url, method, body).await.unwrap();
     assert_eq!(response.get("status").unwrap(), &json!(200));
 }
+
+#[tokio::test]
+async fn it_rejects_ingestion_without_auth_token() {
+    let ingestion_port = get_free_port().unwrap();
+    let config = build_ingestion_test_config(ingestion_port, Some("secret".into()));
+    start_and_ping_event_observer(config, ingestion_port).await;
+    let url = format!("http://localhost:{ingestion_port}/new_mempool_tx");
+    let client = reqwest::Client::new();
+    for (authorization, expected_status) in [
+        (None, 401),
+        (Some("wrong"), 401),
+        (Some("secret"), 200),
+        (Some("Bearer secret"), 200),
+    ] {
+        let mut req = client.post(&url).json(&json!([]));
+        if let Some(authorization) = authorization {
+            req = req.header("Authorization", authorization);
+        }
+        let response = req.send().await.unwrap();
+        assert_eq!(response.status().as_u16(), expected_status);
+    }
+}
+
+fn build_ingestion_test_config(
+    ingestion_port: u16,
+    ingestion_auth_token: Option<String>,
+) -> EventObserverConfig {
+    EventObserverConfigBuilder::new()
+        .bitcoin_rpc("http://localhost:18443", "", "")
+        .bitcoin_block_signaling(BitcoinBlockSignaling::Stacks(StacksNodeConfig {
+            rpc_url: "http://localhost:20443".into(),
+            ingestion_port,
+            ingestion_auth_token,
+            ingestion_tls: None,
+        }))
+        .build()
+        .unwrap()
+}
@@ -0,0 +1,61 @@
+use std::fs;
+
+use chainhook_sdk::types::Chain;
+use chainhook_sdk::utils::Context;
+
+use super::helpers::build_predicates::{
+    build_bitcoin_payload, build_stacks_payload, get_random_uuid,
+};
+use crate::service::predicates_watcher::{PredicateFileChange, PredicatesDirWatcher};
+
+#[test]
+fn it_tracks_predicates_added_modified_and_removed() {
+    let dir = std::env::temp_dir().join(get_random_uuid());
+    fs::create_dir_all(&dir).unwrap();
+    let logger = hiro_system_kit::log::setup_logger();
+    let _guard = hiro_system_kit::log::setup_global_logger(logger.clone());
+    let ctx = Context {
+        logger: Some(logger),
+        tracer: false,
+    };
+    let mut watcher = PredicatesDirWatcher::new(dir.clone());
+
+    let predicate = build_stacks_payload(Some("devnet"), None, None, None, Some("watched"));
+    fs::write(dir.join("stacks.json"), predicate.to_string()).unwrap();
+    fs::write(dir.join("notes.txt"), "not a predicate").unwrap();
+    let changes = watcher.scan(&ctx);
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(&changes[0], PredicateFileChange::Added(p) if p.get_uuid() == "watched"));
+    assert!(watcher.scan(&ctx).is_empty());
+
+    // an invalid modification keeps the predicate registered
+    fs::write(dir.join("stacks.json"), "{").unwrap();
+    assert!(watcher.scan(&ctx).is_empty());
+
+    let predicate = build_stacks_payload(Some("testnet"), None, None, None, Some("watched"));
+    fs::write(dir.join("stacks.json"), predicate.to_string()).unwrap();
+    let changes = watcher.scan(&ctx);
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(&changes[0], PredicateFileChange::Updated(p) if p.get_uuid() == "watched"));
+
+    // changing the chain of the predicate registers it again
+    let predicate = build_bitcoin_payload(Some("regtest"), None, None, None, Some("watched"));
+    fs::write(dir.join("stacks.json"), predicate.to_string()).unwrap();
+    let changes = watcher.scan(&ctx);
+    assert_eq!(changes.len(), 2);
+    assert!(matches!(
+        &changes[0],
+        PredicateFileChange::Removed(uuid, Chain::Stacks) if uuid == "watched"
+    ));
+    assert!(matches!(&changes[1], PredicateFileChange::Added(_)));
+
+    fs::remove_file(dir.join("stacks.json")).unwrap();
+    let changes = watcher.scan(&ctx);
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(
+        &changes[0],
+        PredicateFileChange::Removed(uuid, Chain::Bitcoin) if uuid == "watched"
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,4 +1,4 @@
-use std::{path::PathBuf, sync::mpsc::channel, thread::sleep, time::Duration};
+use std::{path::PathBuf, sync::mpsc::sync_channel, thread::sleep, time::Duration};
 
 use chainhook_sdk::{
     chainhooks::{
@@ -6,6 +6,7 @@ use chainhook_sdk::{
         stacks::{StacksChainhookInstance, StacksPredicate},
         types::{BlockIdentifierIndexRule, HookAction},
     },
+    observer::DEFAULT_OBSERVER_COMMANDS_CAPACITY,
     types::{BitcoinNetwork, StacksNetwork},
     utils::Context,
 };
@@ -57,7 +58,8 @@ async fn test_stacks_runloop_kill_scan() {
         });
 
     let (scan_op_tx, scan_op_rx) = crossbeam_channel::unbounded();
-    let (observer_command_tx, _observer_command_rx) = channel();
+    let (observer_command_tx, _observer_command_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
 
     let _ = hiro_system_kit::thread_named("Stacks scan runloop")
         .spawn(move || {
@@ -69,6 +71,9 @@ async fn test_stacks_runloop_kill_scan() {
     let predicate_spec = StacksChainhookInstance {
         uuid: uuid.clone(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "idc".to_string(),
         network: StacksNetwork::Devnet,
         version: 0,
@@ -76,6 +81,13 @@ async fn test_stacks_runloop_kill_scan() {
         start_block: Some(1),
         end_block: Some(1_000),
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: None,
@@ -125,7 +137,8 @@ async fn test_stacks_bitcoin_kill_scan() {
     };
 
     let (scan_op_tx, scan_op_rx) = crossbeam_channel::unbounded();
-    let (observer_command_tx, _observer_command_rx) = channel();
+    let (observer_command_tx, _observer_command_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
 
     let _ = hiro_system_kit::thread_named("Stacks scan runloop")
         .spawn(move || {
@@ -137,6 +150,9 @@ async fn test_stacks_bitcoin_kill_scan() {
     let predicate_spec = BitcoinChainhookInstance {
         uuid: uuid.clone(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "idc".to_string(),
         network: BitcoinNetwork::Regtest,
         version: 0,
@@ -144,6 +160,13 @@ async fn test_stacks_bitcoin_kill_scan() {
         start_block: Some(1),
         end_block: Some(1_000),
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         predicate: BitcoinPredicateType::Block,
         action: HookAction::Noop,
         enabled: false,
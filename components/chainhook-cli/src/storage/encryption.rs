@@ -0,0 +1,267 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use chainhook_sdk::types::BlockIdentifier;
+
+use super::{StacksDbCursor, StacksStorage};
+
+/// Marks the payloads encrypted by a [PayloadCipher], so that the ones stored before encryption
+/// was enabled can be told apart.
+const ENCRYPTED_PAYLOAD_MAGIC: &[u8; 4] = b"CHE1";
+const NONCE_LEN: usize = 12;
+
+/// Prefix of the encrypted payloads stored as strings, hex encoded.
+const ENCRYPTED_STRING_PREFIX: &str = "enc:";
+
+static PAYLOAD_CIPHER: OnceLock<PayloadCipher> = OnceLock::new();
+
+/// Encrypts the payloads written to disk with AES-256-GCM, each with its own random nonce.
+/// Payloads read without encryption are rejected, unless plaintext payloads are allowed while
+/// migrating the data stored before encryption was enabled.
+pub struct PayloadCipher {
+    key: [u8; 32],
+    cipher: Aes256Gcm,
+    allow_plaintext_payloads: bool,
+    plaintext_payloads_read: AtomicU64,
+}
+
+impl PayloadCipher {
+    /// Builds a cipher from a 32 bytes key, hex encoded.
+    pub fn from_hex_key(hex_key: &str) -> Result<Self, String> {
+        let key: [u8; 32] = hex::decode(hex_key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or("key must be 32 bytes, hex encoded".to_string())?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+        Ok(PayloadCipher {
+            key,
+            cipher,
+            allow_plaintext_payloads: false,
+            plaintext_payloads_read: AtomicU64::new(0),
+        })
+    }
+
+    /// Reads the payloads stored without encryption as is instead of rejecting them.
+    pub fn allow_plaintext_payloads(mut self, allow: bool) -> Self {
+        self.allow_plaintext_payloads = allow;
+        self
+    }
+
+    /// Number of payloads read without encryption since the cipher was built.
+    pub fn plaintext_payloads_read(&self) -> u64 {
+        self.plaintext_payloads_read.load(Ordering::Relaxed)
+    }
+
+    fn read_plaintext_payload(&self) -> Result<(), String> {
+        if !self.allow_plaintext_payloads {
+            return Err(
+                "unable to decrypt payload: not encrypted, storage.allow_plaintext_payloads is not set"
+                    .into(),
+            );
+        }
+        self.plaintext_payloads_read.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn encrypt(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|e| format!("unable to encrypt payload: {}", e))?;
+        let mut encrypted = Vec::with_capacity(4 + NONCE_LEN + ciphertext.len());
+        encrypted.extend_from_slice(ENCRYPTED_PAYLOAD_MAGIC);
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypts `payload`, or returns it as is if it wasn't encrypted and plaintext payloads are
+    /// allowed.
+    pub fn decrypt(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let Some(encrypted) = payload.strip_prefix(ENCRYPTED_PAYLOAD_MAGIC) else {
+            self.read_plaintext_payload()?;
+            return Ok(payload.to_vec());
+        };
+        if encrypted.len() < NONCE_LEN {
+            return Err("unable to decrypt payload: truncated".into());
+        }
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "unable to decrypt payload: wrong key or corrupted payload".into())
+    }
+
+    pub fn encrypt_string(&self, payload: &str) -> Result<String, String> {
+        let encrypted = self.encrypt(payload.as_bytes())?;
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_STRING_PREFIX,
+            hex::encode(encrypted)
+        ))
+    }
+
+    /// Decrypts a payload encrypted with [PayloadCipher::encrypt_string], or returns it as is if
+    /// it wasn't encrypted and plaintext payloads are allowed.
+    pub fn decrypt_string(&self, payload: &str) -> Result<String, String> {
+        let Some(encrypted) = payload.strip_prefix(ENCRYPTED_STRING_PREFIX) else {
+            self.read_plaintext_payload()?;
+            return Ok(payload.to_string());
+        };
+        let encrypted =
+            hex::decode(encrypted).map_err(|e| format!("unable to decrypt payload: {}", e))?;
+        String::from_utf8(self.decrypt(&encrypted)?)
+            .map_err(|e| format!("unable to decrypt payload: {}", e))
+    }
+}
+
+/// Enables the encryption of the payloads stored by this process, with the key held by the
+/// environment variable `key_env`. The Stacks db, the Bitcoin blocks spilled to disk and the
+/// deliveries recorded in the predicates db are then encrypted, and the payloads stored in clear
+/// are only read if `allow_plaintext_payloads` is set.
+pub fn enable_payload_encryption(
+    key_env: &str,
+    allow_plaintext_payloads: bool,
+) -> Result<(), String> {
+    let hex_key = std::env::var(key_env)
+        .map_err(|_| format!("environment variable {} is not set", key_env))?;
+    let cipher =
+        PayloadCipher::from_hex_key(&hex_key)?.allow_plaintext_payloads(allow_plaintext_payloads);
+    let key = cipher.key;
+    if PAYLOAD_CIPHER.get_or_init(|| cipher).key != key {
+        return Err("payloads are already encrypted with another key".into());
+    }
+    Ok(())
+}
+
+/// Returns the cipher the stored payloads are encrypted with, if encryption is enabled.
+pub fn get_payload_cipher() -> Option<&'static PayloadCipher> {
+    PAYLOAD_CIPHER.get()
+}
+
+/// Encrypts `payload` if encryption is enabled.
+pub fn encrypt_payload(payload: &[u8]) -> Result<Vec<u8>, String> {
+    match get_payload_cipher() {
+        Some(cipher) => cipher.encrypt(payload),
+        None => Ok(payload.to_vec()),
+    }
+}
+
+/// Decrypts `payload` if encryption is enabled.
+pub fn decrypt_payload(payload: &[u8]) -> Result<Vec<u8>, String> {
+    match get_payload_cipher() {
+        Some(cipher) => cipher.decrypt(payload),
+        None => Ok(payload.to_vec()),
+    }
+}
+
+/// Encrypts the string `payload` if encryption is enabled.
+pub fn encrypt_string_payload(payload: &str) -> Result<String, String> {
+    match get_payload_cipher() {
+        Some(cipher) => cipher.encrypt_string(payload),
+        None => Ok(payload.to_string()),
+    }
+}
+
+/// Decrypts the string `payload` if encryption is enabled.
+pub fn decrypt_string_payload(payload: &str) -> Result<String, String> {
+    match get_payload_cipher() {
+        Some(cipher) => cipher.decrypt_string(payload),
+        None => Ok(payload.to_string()),
+    }
+}
+
+/// A [StacksStorage] encrypting the blocks it stores with a [PayloadCipher]. Its metadata is
+/// stored in clear.
+pub struct EncryptedStacksStorage {
+    inner: Box<dyn StacksStorage>,
+    cipher: &'static PayloadCipher,
+}
+
+impl EncryptedStacksStorage {
+    pub fn new(inner: Box<dyn StacksStorage>, cipher: &'static PayloadCipher) -> Self {
+        EncryptedStacksStorage { inner, cipher }
+    }
+}
+
+impl StacksStorage for EncryptedStacksStorage {
+    fn get_block(&self, block_height: u64, confirmed: bool) -> Result<Option<Vec<u8>>, String> {
+        self.inner
+            .get_block(block_height, confirmed)?
+            .map(|block| self.cipher.decrypt(&block))
+            .transpose()
+    }
+
+    fn put_block(
+        &self,
+        block_identifier: &BlockIdentifier,
+        confirmed: bool,
+        block: &[u8],
+    ) -> Result<(), String> {
+        let block = self.cipher.encrypt(block)?;
+        self.inner.put_block(block_identifier, confirmed, &block)
+    }
+
+    fn delete_block(&self, block_height: u64, confirmed: bool) -> Result<(), String> {
+        self.inner.delete_block(block_height, confirmed)
+    }
+
+    fn scan_blocks(
+        &self,
+        block_height: u64,
+        confirmed: bool,
+        f: &mut dyn FnMut(u64, &[u8]) -> Result<bool, String>,
+    ) -> Result<(), String> {
+        self.inner
+            .scan_blocks(block_height, confirmed, &mut |block_height, block| {
+                f(block_height, &self.cipher.decrypt(block)?)
+            })
+    }
+
+    fn get_cursor(&self, cursor: StacksDbCursor) -> Result<Option<u64>, String> {
+        self.inner.get_cursor(cursor)
+    }
+
+    fn set_cursor(&self, cursor: StacksDbCursor, block_height: u64) -> Result<(), String> {
+        self.inner.set_cursor(cursor, block_height)
+    }
+
+    fn delete_cursor(&self, cursor: StacksDbCursor) -> Result<(), String> {
+        self.inner.delete_cursor(cursor)
+    }
+
+    fn get_archive_fingerprint(&self) -> Result<Option<String>, String> {
+        self.inner.get_archive_fingerprint()
+    }
+
+    fn set_archive_fingerprint(&self, fingerprint: &str) -> Result<(), String> {
+        self.inner.set_archive_fingerprint(fingerprint)
+    }
+
+    fn delete_archive_fingerprint(&self) -> Result<(), String> {
+        self.inner.delete_archive_fingerprint()
+    }
+
+    fn compact_blocks(&self, block_height: u64) {
+        self.inner.compact_blocks(block_height)
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        self.inner.flush()
+    }
+
+    fn create_snapshot(&self, path: &Path) -> Result<(), String> {
+        self.inner.create_snapshot(path)
+    }
+}
+
+/// Wraps `storage` into an [EncryptedStacksStorage] if encryption is enabled.
+pub fn with_payload_encryption(storage: Box<dyn StacksStorage>) -> Box<dyn StacksStorage> {
+    match get_payload_cipher() {
+        Some(cipher) => Box::new(EncryptedStacksStorage::new(storage, cipher)),
+        None => storage,
+    }
+}
@@ -0,0 +1,137 @@
+use chainhook_sdk::utils::Context;
+
+use crate::config::{Config, PredicatesApi};
+use crate::service::open_readwrite_predicates_db_conn;
+use crate::service::predicates_db::PredicatesDb;
+
+use super::{open_readwrite_stacks_db_conn, StacksDbCursor, StacksStorage};
+
+const PREDICATES_DB_METADATA_KEY: &str = "chainhook:metadata";
+const PREDICATES_DB_SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// A change to the layout of a db, upgrading it from `version - 1` to `version`.
+///
+/// Dbs created before migrations were introduced have no version, and are considered to be at
+/// version `0`. New dbs are upgraded the same way, so migrations must also apply to empty dbs.
+pub struct Migration<D: ?Sized> {
+    pub version: u64,
+    pub description: &'static str,
+    pub apply: fn(&mut D, &Context) -> Result<(), String>,
+}
+
+/// A db whose layout is versioned.
+pub trait VersionedDb {
+    fn get_schema_version(&mut self) -> Result<Option<u64>, String>;
+
+    fn set_schema_version(&mut self, version: u64) -> Result<(), String>;
+}
+
+impl VersionedDb for dyn StacksStorage {
+    fn get_schema_version(&mut self) -> Result<Option<u64>, String> {
+        self.get_cursor(StacksDbCursor::SchemaVersion)
+    }
+
+    fn set_schema_version(&mut self, version: u64) -> Result<(), String> {
+        self.set_cursor(StacksDbCursor::SchemaVersion, version)
+    }
+}
+
+impl VersionedDb for dyn PredicatesDb {
+    fn get_schema_version(&mut self) -> Result<Option<u64>, String> {
+        self.hget(
+            PREDICATES_DB_METADATA_KEY,
+            PREDICATES_DB_SCHEMA_VERSION_FIELD,
+        )?
+        .map(|version| {
+            version
+                .parse()
+                .map_err(|e| format!("unable to parse schema version: {}", e))
+        })
+        .transpose()
+    }
+
+    fn set_schema_version(&mut self, version: u64) -> Result<(), String> {
+        self.hset(
+            PREDICATES_DB_METADATA_KEY,
+            PREDICATES_DB_SCHEMA_VERSION_FIELD,
+            &version.to_string(),
+        )
+    }
+}
+
+/// Migrations of the Stacks db, ordered by version.
+pub const STACKS_DB_MIGRATIONS: &[Migration<dyn StacksStorage>] = &[Migration {
+    version: 1,
+    description: "initial layout",
+    apply: |_, _| Ok(()),
+}];
+
+/// Migrations of the predicates db, ordered by version.
+pub(crate) const PREDICATES_DB_MIGRATIONS: &[Migration<dyn PredicatesDb>] = &[Migration {
+    version: 1,
+    description: "initial layout",
+    apply: |_, _| Ok(()),
+}];
+
+/// Returns the version `migrations` upgrade a db to.
+pub fn get_latest_schema_version<D: ?Sized>(migrations: &[Migration<D>]) -> u64 {
+    migrations.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Applies to `db` the migrations it is missing, recording its version after each of them so
+/// that an interrupted upgrade resumes where it stopped. Fails if `db` was upgraded by a more
+/// recent release of chainhook. Returns the version `db` is at.
+pub fn run_migrations<D: VersionedDb + ?Sized>(
+    db: &mut D,
+    migrations: &[Migration<D>],
+    db_name: &str,
+    ctx: &Context,
+) -> Result<u64, String> {
+    let latest_version = get_latest_schema_version(migrations);
+    let mut version = db.get_schema_version()?.unwrap_or(0);
+    if version > latest_version {
+        return Err(format!(
+            "{} is at version {}, which is more recent than the latest version supported by this release ({})",
+            db_name, version, latest_version
+        ));
+    }
+    for migration in migrations.iter().filter(|m| m.version > version) {
+        info!(
+            ctx.expect_logger(),
+            "Migrating {} to version {} ({})", db_name, migration.version, migration.description
+        );
+        (migration.apply)(db, ctx).map_err(|e| {
+            format!(
+                "unable to migrate {} to version {}: {}",
+                db_name, migration.version, e
+            )
+        })?;
+        db.set_schema_version(migration.version)?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Upgrades the Stacks db and, when the HTTP API is enabled, the predicates db to the latest
+/// versions of their layouts.
+pub fn migrate_dbs(config: &Config, ctx: &Context) -> Result<(), String> {
+    let mut stacks_db = open_readwrite_stacks_db_conn(
+        &config.expected_cache_path(),
+        &config.storage.stacks_db_backend,
+        ctx,
+    )?;
+    let version = run_migrations(stacks_db.as_mut(), STACKS_DB_MIGRATIONS, "stacks db", ctx)?;
+    info!(ctx.expect_logger(), "Stacks db at version {}", version);
+
+    if let PredicatesApi::On(ref api_config) = config.http_api {
+        let mut predicates_db = open_readwrite_predicates_db_conn(api_config)?;
+        let version = run_migrations(
+            predicates_db.as_mut(),
+            PREDICATES_DB_MIGRATIONS,
+            "predicates db",
+            ctx,
+        )?;
+        info!(ctx.expect_logger(), "Predicates db at version {}", version);
+    }
+    Ok(())
+}
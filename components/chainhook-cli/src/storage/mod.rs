@@ -1,15 +1,250 @@
 use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
 
 use chainhook_sdk::types::{BlockIdentifier, StacksBlockData, StacksBlockUpdate};
 use chainhook_sdk::utils::Context;
-use rocksdb::{Options, DB};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rocksdb::checkpoint::Checkpoint;
+use rocksdb::{Direction, IteratorMode, Options, DB};
+
+use crate::config::StacksDbBackend;
+
+use self::encryption::with_payload_encryption;
+use self::sqlite::SqliteStacksStorage;
+
+pub mod encryption;
+pub mod migrations;
+mod sqlite;
 
 const UNCONFIRMED_KEY_PREFIX: &[u8; 2] = b"~:";
 const CONFIRMED_KEY_PREFIX: &[u8; 2] = b"b:";
 const KEY_SUFFIX: &[u8; 2] = b":d";
 const LAST_UNCONFIRMED_KEY_PREFIX: &[u8; 3] = b"m:~";
 const LAST_CONFIRMED_KEY_PREFIX: &[u8; 3] = b"m:t";
+const CONSOLIDATED_ARCHIVE_HEIGHT_KEY: &[u8; 3] = b"m:a";
+const CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY: &[u8; 3] = b"m:f";
+const LAST_PRUNED_KEY: &[u8; 3] = b"m:p";
+const SCHEMA_VERSION_KEY: &[u8; 3] = b"m:v";
+
+/// Block heights tracking the ingestion of the Stacks blocks into the Stacks db, along with the
+/// version of its layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StacksDbCursor {
+    /// Highest confirmed block inserted.
+    LastConfirmedBlock,
+    /// Highest unconfirmed block inserted.
+    LastUnconfirmedBlock,
+    /// Height up to which every block of the Stacks archive is known to be in the db.
+    ConsolidatedArchive,
+    /// Last confirmed block deleted by [prune_stacks_blocks].
+    LastPrunedBlock,
+    /// Version of the db's layout, see [migrations].
+    SchemaVersion,
+}
+
+impl StacksDbCursor {
+    fn key(&self) -> &'static [u8] {
+        match self {
+            StacksDbCursor::LastConfirmedBlock => LAST_CONFIRMED_KEY_PREFIX,
+            StacksDbCursor::LastUnconfirmedBlock => LAST_UNCONFIRMED_KEY_PREFIX,
+            StacksDbCursor::ConsolidatedArchive => CONSOLIDATED_ARCHIVE_HEIGHT_KEY,
+            StacksDbCursor::LastPrunedBlock => LAST_PRUNED_KEY,
+            StacksDbCursor::SchemaVersion => SCHEMA_VERSION_KEY,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StacksDbCursor::LastConfirmedBlock => "last_confirmed_block_height",
+            StacksDbCursor::LastUnconfirmedBlock => "last_unconfirmed_block_height",
+            StacksDbCursor::ConsolidatedArchive => "consolidated_archive_block_height",
+            StacksDbCursor::LastPrunedBlock => "last_pruned_block_height",
+            StacksDbCursor::SchemaVersion => "schema_version",
+        }
+    }
+}
+
+/// Storage backing the Stacks db: the serialized Stacks blocks, confirmed or not, keyed by
+/// height, and the cursors tracking their ingestion. See [StacksDbBackend] for the available
+/// implementations.
+pub trait StacksStorage: Send + Sync {
+    fn get_block(&self, block_height: u64, confirmed: bool) -> Result<Option<Vec<u8>>, String>;
+
+    fn put_block(
+        &self,
+        block_identifier: &BlockIdentifier,
+        confirmed: bool,
+        block: &[u8],
+    ) -> Result<(), String>;
+
+    fn delete_block(&self, block_height: u64, confirmed: bool) -> Result<(), String>;
+
+    /// Calls `f` with the height and the content of the blocks from `block_height` up, by
+    /// ascending height, until it returns false. `f` must not access the db.
+    fn scan_blocks(
+        &self,
+        block_height: u64,
+        confirmed: bool,
+        f: &mut dyn FnMut(u64, &[u8]) -> Result<bool, String>,
+    ) -> Result<(), String>;
+
+    fn get_cursor(&self, cursor: StacksDbCursor) -> Result<Option<u64>, String>;
+
+    fn set_cursor(&self, cursor: StacksDbCursor, block_height: u64) -> Result<(), String>;
+
+    fn delete_cursor(&self, cursor: StacksDbCursor) -> Result<(), String>;
+
+    /// Fingerprint of the last Stacks archive fully consolidated into the db.
+    fn get_archive_fingerprint(&self) -> Result<Option<String>, String>;
+
+    fn set_archive_fingerprint(&self, fingerprint: &str) -> Result<(), String>;
+
+    fn delete_archive_fingerprint(&self) -> Result<(), String>;
+
+    /// Reclaims the space used by the confirmed blocks deleted up to `block_height`.
+    fn compact_blocks(&self, block_height: u64);
+
+    fn flush(&self) -> Result<(), String>;
+
+    /// Writes a consistent copy of the db to `path`, even if the db is written to meanwhile.
+    fn create_snapshot(&self, path: &Path) -> Result<(), String>;
+}
+
+fn read_u64(bytes: &[u8]) -> Result<u64, String> {
+    bytes
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or_else(|| "unable to decode metadata".to_string())
+}
+
+impl StacksStorage for DB {
+    fn get_block(&self, block_height: u64, confirmed: bool) -> Result<Option<Vec<u8>>, String> {
+        let block_identifier = BlockIdentifier {
+            hash: "".to_string(),
+            index: block_height,
+        };
+        let key = match confirmed {
+            true => get_block_key(&block_identifier),
+            false => get_unconfirmed_block_key(&block_identifier),
+        };
+        self.get(key)
+            .map_err(|e| format!("unable to read stacks db: {}", e))
+    }
+
+    fn put_block(
+        &self,
+        block_identifier: &BlockIdentifier,
+        confirmed: bool,
+        block: &[u8],
+    ) -> Result<(), String> {
+        let key = match confirmed {
+            true => get_block_key(block_identifier),
+            false => get_unconfirmed_block_key(block_identifier),
+        };
+        self.put(key, block)
+            .map_err(|e| format!("unable to insert blocks: {}", e))
+    }
+
+    fn delete_block(&self, block_height: u64, confirmed: bool) -> Result<(), String> {
+        let block_identifier = BlockIdentifier {
+            hash: "".to_string(),
+            index: block_height,
+        };
+        let key = match confirmed {
+            true => get_block_key(&block_identifier),
+            false => get_unconfirmed_block_key(&block_identifier),
+        };
+        self.delete(key)
+            .map_err(|e| format!("unable to delete blocks: {}", e))
+    }
+
+    fn scan_blocks(
+        &self,
+        block_height: u64,
+        confirmed: bool,
+        f: &mut dyn FnMut(u64, &[u8]) -> Result<bool, String>,
+    ) -> Result<(), String> {
+        let block_identifier = BlockIdentifier {
+            hash: "".to_string(),
+            index: block_height,
+        };
+        let (prefix, from_key) = match confirmed {
+            true => (CONFIRMED_KEY_PREFIX, get_block_key(&block_identifier)),
+            false => (
+                UNCONFIRMED_KEY_PREFIX,
+                get_unconfirmed_block_key(&block_identifier),
+            ),
+        };
+        // Keys are ordered by block height
+        for entry in self.iterator(IteratorMode::From(&from_key, Direction::Forward)) {
+            let (key, value) = entry.map_err(|e| format!("unable to read stacks db: {}", e))?;
+            if key.len() != 12 || !key.starts_with(prefix) {
+                break;
+            }
+            let block_height = read_u64(&key[2..10])?;
+            if !f(block_height, &value)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_cursor(&self, cursor: StacksDbCursor) -> Result<Option<u64>, String> {
+        self.get(cursor.key())
+            .map_err(|e| format!("unable to read metadata: {}", e))?
+            .map(|bytes| read_u64(&bytes))
+            .transpose()
+    }
+
+    fn set_cursor(&self, cursor: StacksDbCursor, block_height: u64) -> Result<(), String> {
+        self.put(cursor.key(), block_height.to_be_bytes())
+            .map_err(|e| format!("unable to insert metadata: {}", e))
+    }
+
+    fn delete_cursor(&self, cursor: StacksDbCursor) -> Result<(), String> {
+        self.delete(cursor.key())
+            .map_err(|e| format!("unable to delete metadata: {}", e))
+    }
+
+    fn get_archive_fingerprint(&self) -> Result<Option<String>, String> {
+        let fingerprint = self
+            .get(CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY)
+            .map_err(|e| format!("unable to read metadata: {}", e))?;
+        Ok(fingerprint.and_then(|bytes| String::from_utf8(bytes).ok()))
+    }
+
+    fn set_archive_fingerprint(&self, fingerprint: &str) -> Result<(), String> {
+        self.put(CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY, fingerprint.as_bytes())
+            .map_err(|e| format!("unable to insert metadata: {}", e))
+    }
+
+    fn delete_archive_fingerprint(&self) -> Result<(), String> {
+        self.delete(CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY)
+            .map_err(|e| format!("unable to delete metadata: {}", e))
+    }
+
+    fn compact_blocks(&self, block_height: u64) {
+        let end_key = get_block_key(&BlockIdentifier {
+            index: block_height + 1,
+            hash: "".into(),
+        });
+        self.compact_range(Some(CONFIRMED_KEY_PREFIX), Some(end_key));
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        DB::flush(self).map_err(|e| format!("unable to flush stacks db: {}", e))
+    }
+
+    fn create_snapshot(&self, path: &Path) -> Result<(), String> {
+        Checkpoint::new(self)
+            .and_then(|checkpoint| checkpoint.create_checkpoint(path))
+            .map_err(|e| format!("unable to create stacks.rocksdb checkpoint: {}", e))
+    }
+}
 
 fn get_db_default_options() -> Options {
     let mut opts = Options::default();
@@ -29,25 +264,34 @@ fn get_db_default_options() -> Options {
     opts
 }
 
-fn get_default_stacks_db_file_path(base_dir: &PathBuf) -> PathBuf {
+pub fn get_default_stacks_db_file_path(base_dir: &PathBuf, backend: &StacksDbBackend) -> PathBuf {
     let mut destination_path = base_dir.clone();
-    destination_path.push("stacks.rocksdb");
+    destination_path.push(get_stacks_db_file_name(backend));
     destination_path
 }
 
+fn get_stacks_db_file_name(backend: &StacksDbBackend) -> &'static str {
+    match backend {
+        StacksDbBackend::RocksDb => "stacks.rocksdb",
+        StacksDbBackend::Sqlite => "stacks.sqlite",
+    }
+}
+
 pub fn open_readonly_stacks_db_conn_with_retry(
     base_dir: &PathBuf,
+    backend: &StacksDbBackend,
     retry: u8,
     ctx: &Context,
-) -> Result<DB, String> {
+) -> Result<Box<dyn StacksStorage>, String> {
     let mut attempt = 0;
     loop {
-        match open_readonly_stacks_db_conn(base_dir, ctx) {
+        match open_readonly_stacks_db_conn(base_dir, backend, ctx) {
             Ok(conn) => return Ok(conn),
             Err(e) => {
                 debug!(
                     ctx.expect_logger(),
-                    "Failed to open stadcks.rocksdb. Trying again in a few seconds."
+                    "Failed to open {}. Trying again in a few seconds.",
+                    get_stacks_db_file_name(backend)
                 );
                 attempt += 1;
                 std::thread::sleep(std::time::Duration::from_secs(2));
@@ -59,21 +303,32 @@ pub fn open_readonly_stacks_db_conn_with_retry(
     }
 }
 
-pub fn open_readonly_stacks_db_conn(base_dir: &PathBuf, ctx: &Context) -> Result<DB, String> {
-    let path = get_default_stacks_db_file_path(base_dir);
+pub fn open_readonly_stacks_db_conn(
+    base_dir: &PathBuf,
+    backend: &StacksDbBackend,
+    ctx: &Context,
+) -> Result<Box<dyn StacksStorage>, String> {
+    let path = get_default_stacks_db_file_path(base_dir, backend);
+    if let StacksDbBackend::Sqlite = backend {
+        if !path.exists() {
+            open_readwrite_stacks_db_conn(base_dir, backend, ctx)?;
+        }
+        let storage = SqliteStacksStorage::open_readonly(&path)?;
+        return Ok(with_payload_encryption(Box::new(storage)));
+    }
     let opts = get_db_default_options();
     match DB::open_for_read_only(&opts, path.clone(), false) {
-        Ok(db) => Ok(db),
+        Ok(db) => Ok(with_payload_encryption(Box::new(db))),
         Err(e) => {
             if e.to_string()
                 .contains("IO error: No such file or directory")
             {
-                match open_readwrite_stacks_db_conn(base_dir, ctx) {
+                match open_readwrite_stacks_db_conn(base_dir, backend, ctx) {
                     Ok(_) => {
                         let db = DB::open_for_read_only(&opts, path, false).map_err(|e| {
                             format!("unable to open stacks.rocksdb: {}", e)
                         })?;
-                        Ok(db)
+                        Ok(with_payload_encryption(Box::new(db)))
                     }
                     Err(e) => Err(e),
                 }
@@ -84,11 +339,160 @@ pub fn open_readonly_stacks_db_conn(base_dir: &PathBuf, ctx: &Context) -> Result
     }
 }
 
-pub fn open_readwrite_stacks_db_conn(base_dir: &PathBuf, _ctx: &Context) -> Result<DB, String> {
-    let path = get_default_stacks_db_file_path(base_dir);
+pub fn open_readwrite_stacks_db_conn(
+    base_dir: &PathBuf,
+    backend: &StacksDbBackend,
+    _ctx: &Context,
+) -> Result<Box<dyn StacksStorage>, String> {
+    let path = get_default_stacks_db_file_path(base_dir, backend);
+    if let StacksDbBackend::Sqlite = backend {
+        let storage = SqliteStacksStorage::open_readwrite(&path)?;
+        return Ok(with_payload_encryption(Box::new(storage)));
+    }
     let opts = get_db_default_options();
     let db = DB::open(&opts, path)
         .map_err(|e| format!("unable to open stacks.rocksdb: {}", e))?;
+    Ok(with_payload_encryption(Box::new(db)))
+}
+
+/// Exports the Stacks db to a gzipped tar archive at `archive_path`. The archive is built from a
+/// snapshot of the db, so that it is consistent even if the db is written to meanwhile.
+/// Returns the last confirmed block height held by the snapshot.
+pub fn export_stacks_db_snapshot(
+    base_dir: &PathBuf,
+    backend: &StacksDbBackend,
+    archive_path: &PathBuf,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    let stacks_db = open_readwrite_stacks_db_conn(base_dir, backend, ctx)?;
+    let last_block_height = get_last_block_height_inserted(stacks_db.as_ref(), ctx);
+
+    let mut snapshot_path = base_dir.clone();
+    snapshot_path.push(format!("{}.snapshot", get_stacks_db_file_name(backend)));
+    remove_stacks_db_files(&snapshot_path);
+    stacks_db.create_snapshot(&snapshot_path)?;
+
+    let res = write_stacks_db_archive(&snapshot_path, backend, archive_path);
+    remove_stacks_db_files(&snapshot_path);
+    res?;
+    Ok(last_block_height)
+}
+
+fn write_stacks_db_archive(
+    db_path: &PathBuf,
+    backend: &StacksDbBackend,
+    archive_path: &PathBuf,
+) -> Result<(), String> {
+    let archive_file = File::create(archive_path)
+        .map_err(|e| format!("unable to create {}: {}", archive_path.display(), e))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(archive_file, Compression::default()));
+    let name = get_stacks_db_file_name(backend);
+    match backend {
+        StacksDbBackend::RocksDb => builder.append_dir_all(name, db_path),
+        StacksDbBackend::Sqlite => builder.append_path_with_name(db_path, name),
+    }
+    .and_then(|_| builder.into_inner())
+    .and_then(|encoder| encoder.finish())
+    .map_err(|e| format!("unable to write {}: {}", archive_path.display(), e))?;
+    Ok(())
+}
+
+/// Removes a Stacks db, a directory for RocksDB and files for SQLite, ignoring missing files.
+fn remove_stacks_db_files(path: &PathBuf) -> Option<std::io::Error> {
+    if path.is_dir() {
+        return fs::remove_dir_all(path).err();
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    fs::remove_file(path).err()
+}
+
+/// Replaces the Stacks db with the one held by an archive created with
+/// [export_stacks_db_snapshot], with the same backend. The archive is unpacked and checked
+/// before the current db is removed. Returns the last confirmed block height held by the
+/// restored db.
+pub fn import_stacks_db_snapshot(
+    base_dir: &PathBuf,
+    backend: &StacksDbBackend,
+    archive_path: &PathBuf,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    let archive_file = File::open(archive_path)
+        .map_err(|e| format!("unable to open {}: {}", archive_path.display(), e))?;
+    let mut staging_path = base_dir.clone();
+    staging_path.push(format!("{}.restore", get_stacks_db_file_name(backend)));
+    let _ = fs::remove_dir_all(&staging_path);
+    fs::create_dir_all(&staging_path)
+        .map_err(|e| format!("unable to create {}: {}", staging_path.display(), e))?;
+
+    let res = restore_stacks_db_archive(archive_file, base_dir, backend, &staging_path, ctx);
+    let _ = fs::remove_dir_all(&staging_path);
+    res
+}
+
+fn restore_stacks_db_archive(
+    archive_file: File,
+    base_dir: &PathBuf,
+    backend: &StacksDbBackend,
+    staging_path: &PathBuf,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    let name = get_stacks_db_file_name(backend);
+    tar::Archive::new(GzDecoder::new(archive_file))
+        .unpack(staging_path)
+        .map_err(|e| format!("unable to unpack {} snapshot: {}", name, e))?;
+    let restored_path = get_default_stacks_db_file_path(staging_path, backend);
+    let last_block_height = {
+        let restored_db: Box<dyn StacksStorage> = match backend {
+            StacksDbBackend::RocksDb => Box::new(
+                DB::open_for_read_only(&get_db_default_options(), &restored_path, false)
+                    .map_err(|e| format!("invalid {} snapshot: {}", name, e))?,
+            ),
+            StacksDbBackend::Sqlite => Box::new(
+                SqliteStacksStorage::open_readonly(&restored_path)
+                    .map_err(|e| format!("invalid {} snapshot: {}", name, e))?,
+            ),
+        };
+        get_last_block_height_inserted(restored_db.as_ref(), ctx)
+    };
+
+    let db_path = get_default_stacks_db_file_path(base_dir, backend);
+    if db_path.exists() {
+        if let Some(e) = remove_stacks_db_files(&db_path) {
+            return Err(format!("unable to remove {}: {}", db_path.display(), e));
+        }
+    }
+    fs::rename(&restored_path, &db_path)
+        .map_err(|e| format!("unable to move restored {}: {}", name, e))?;
+    Ok(last_block_height)
+}
+
+fn get_default_bitcoin_block_spill_db_file_path(base_dir: &PathBuf) -> PathBuf {
+    let mut destination_path = base_dir.clone();
+    destination_path.push("bitcoin_block_spill.rocksdb");
+    destination_path
+}
+
+/// Opens the db the Bitcoin blocks evicted from the observer's cache are spilled to. Blocks
+/// spilled before a restart are discarded, as the observer's cache starts empty.
+pub fn open_readwrite_bitcoin_block_spill_db_conn(
+    base_dir: &PathBuf,
+    _ctx: &Context,
+) -> Result<DB, String> {
+    let path = get_default_bitcoin_block_spill_db_file_path(base_dir);
+    let opts = get_db_default_options();
+    let _ = DB::destroy(&opts, &path);
+    let db = DB::open(&opts, path)
+        .map_err(|e| format!("unable to open bitcoin_block_spill.rocksdb: {}", e))?;
+    Ok(db)
+}
+
+/// Opens the embedded predicates db at `path`, creating it if missing.
+pub fn open_readwrite_predicates_db_conn(path: &Path) -> Result<DB, String> {
+    let opts = get_db_default_options();
+    let db = DB::open(&opts, path)
+        .map_err(|e| format!("unable to open predicates db {}: {}", path.display(), e))?;
     Ok(db)
 }
 
@@ -108,24 +512,17 @@ fn get_unconfirmed_block_key(block_identifier: &BlockIdentifier) -> [u8; 12] {
     key
 }
 
-fn get_last_confirmed_insert_key() -> [u8; 3] {
-    *LAST_CONFIRMED_KEY_PREFIX
-}
-
-fn get_last_unconfirmed_insert_key() -> [u8; 3] {
-    *LAST_UNCONFIRMED_KEY_PREFIX
-}
-
 pub fn insert_entry_in_stacks_blocks(
     block: &StacksBlockData,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     ctx: &Context,
 ) -> Result<(), String> {
-    let key = get_block_key(&block.block_identifier);
     let block_bytes = json!(block);
-    stacks_db_rw
-        .put(key, block_bytes.to_string().as_bytes())
-        .map_err(|e| format!("unable to insert blocks: {}", e))?;
+    stacks_db_rw.put_block(
+        &block.block_identifier,
+        true,
+        block_bytes.to_string().as_bytes(),
+    )?;
     let previous_last_inserted = get_last_block_height_inserted(stacks_db_rw, ctx).unwrap_or(0);
     if block.block_identifier.index > previous_last_inserted {
         set_last_confirmed_insert_key(&block.block_identifier, stacks_db_rw, ctx)?;
@@ -135,73 +532,61 @@ pub fn insert_entry_in_stacks_blocks(
 
 pub fn set_last_confirmed_insert_key(
     block_identifier: &BlockIdentifier,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     _ctx: &Context,
 ) -> Result<(), String> {
-    stacks_db_rw
-        .put(
-            get_last_confirmed_insert_key(),
-            block_identifier.index.to_be_bytes(),
-        )
-        .map_err(|e| format!("unable to insert metadata: {}", e))?;
-    Ok(())
+    stacks_db_rw.set_cursor(StacksDbCursor::LastConfirmedBlock, block_identifier.index)
 }
 
 pub fn insert_unconfirmed_entry_in_stacks_blocks(
     block: &StacksBlockData,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     _ctx: &Context,
 ) -> Result<(), String> {
-    let key = get_unconfirmed_block_key(&block.block_identifier);
     let block_bytes = json!(block);
-    stacks_db_rw
-        .put(key, block_bytes.to_string().as_bytes())
-        .map_err(|e| format!("unable to insert blocks: {}", e))?;
+    stacks_db_rw.put_block(
+        &block.block_identifier,
+        false,
+        block_bytes.to_string().as_bytes(),
+    )?;
     let previous_last_inserted =
         get_last_unconfirmed_block_height_inserted(stacks_db_rw, _ctx).unwrap_or(0);
     if block.block_identifier.index > previous_last_inserted {
-        stacks_db_rw
-            .put(
-                get_last_unconfirmed_insert_key(),
-                block.block_identifier.index.to_be_bytes(),
-            )
-            .map_err(|e| format!("unable to insert metadata: {}", e))?;
+        stacks_db_rw.set_cursor(
+            StacksDbCursor::LastUnconfirmedBlock,
+            block.block_identifier.index,
+        )?;
     }
     Ok(())
 }
 
 pub fn delete_unconfirmed_entry_from_stacks_blocks(
     block_identifier: &BlockIdentifier,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     _ctx: &Context,
 ) -> Result<(), String> {
-    let key = get_unconfirmed_block_key(block_identifier);
-    stacks_db_rw
-        .delete(key)
-        .map_err(|e| format!("unable to delete blocks: {}", e))
+    stacks_db_rw.delete_block(block_identifier.index, false)
 }
 
 pub fn delete_confirmed_entry_from_stacks_blocks(
     block_identifier: &BlockIdentifier,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     _ctx: &Context,
 ) -> Result<(), String> {
-    let key = get_block_key(block_identifier);
-    stacks_db_rw
-        .delete(key)
-        .map_err(|e| format!("unable to delete blocks: {}", e))
+    stacks_db_rw.delete_block(block_identifier.index, true)
 }
 
-pub fn get_last_unconfirmed_block_height_inserted(stacks_db: &DB, _ctx: &Context) -> Option<u64> {
+pub fn get_last_unconfirmed_block_height_inserted(
+    stacks_db: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Option<u64> {
     stacks_db
-        .get(get_last_unconfirmed_insert_key())
-        .unwrap_or(None).map(|bytes| u64::from_be_bytes([
-                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-            ]))
+        .get_cursor(StacksDbCursor::LastUnconfirmedBlock)
+        .unwrap_or(None)
 }
 
 pub fn get_all_unconfirmed_blocks(
-    stacks_db: &DB,
+    stacks_db: &dyn StacksStorage,
     ctx: &Context,
 ) -> Result<VecDeque<StacksBlockData>, String> {
     let mut blocks = VecDeque::new();
@@ -223,17 +608,260 @@ pub fn get_all_unconfirmed_blocks(
     Ok(blocks)
 }
 
-pub fn get_last_block_height_inserted(stacks_db: &DB, _ctx: &Context) -> Option<u64> {
+pub fn get_last_block_height_inserted(
+    stacks_db: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Option<u64> {
+    stacks_db
+        .get_cursor(StacksDbCursor::LastConfirmedBlock)
+        .unwrap_or(None)
+}
+
+/// Returns the height up to which every block of the Stacks archive is known to be in the db,
+/// so that they don't need to be checked again when consolidating a newer archive.
+pub fn get_last_consolidated_archive_block_height(
+    stacks_db: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Option<u64> {
+    stacks_db
+        .get_cursor(StacksDbCursor::ConsolidatedArchive)
+        .unwrap_or(None)
+}
+
+pub fn set_last_consolidated_archive_block_height(
+    block_height: u64,
+    stacks_db_rw: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Result<(), String> {
+    stacks_db_rw.set_cursor(StacksDbCursor::ConsolidatedArchive, block_height)
+}
+
+/// Returns the fingerprint of the last Stacks archive fully consolidated into the db.
+pub fn get_last_consolidated_archive_fingerprint(
+    stacks_db: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Option<String> {
+    stacks_db.get_archive_fingerprint().unwrap_or(None)
+}
+
+pub fn set_last_consolidated_archive_fingerprint(
+    fingerprint: &str,
+    stacks_db_rw: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Result<(), String> {
+    stacks_db_rw.set_archive_fingerprint(fingerprint)
+}
+
+/// Returns the height of the last confirmed block deleted by [prune_stacks_blocks].
+pub fn get_last_pruned_block_height(stacks_db: &dyn StacksStorage, _ctx: &Context) -> Option<u64> {
     stacks_db
-        .get(get_last_confirmed_insert_key())
-        .unwrap_or(None).map(|bytes| u64::from_be_bytes([
-                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-            ]))
+        .get_cursor(StacksDbCursor::LastPrunedBlock)
+        .unwrap_or(None)
+}
+
+#[derive(Deserialize)]
+struct StacksBlockTimestamp {
+    timestamp: i64,
+}
+
+/// Deletes the confirmed blocks falling out of the retention window: those older than the last
+/// `retained_blocks` blocks, or than the `retained_since` unix timestamp. The last confirmed
+/// block and the unconfirmed ones are always kept. Returns the number of blocks deleted.
+pub fn prune_stacks_blocks(
+    retained_blocks: Option<u64>,
+    retained_since: Option<i64>,
+    stacks_db_rw: &dyn StacksStorage,
+    ctx: &Context,
+) -> Result<u64, String> {
+    let Some(confirmed_tip) = get_last_block_height_inserted(stacks_db_rw, ctx) else {
+        return Ok(0);
+    };
+    let min_retained_block_height = retained_blocks
+        .and_then(|retained_blocks| confirmed_tip.checked_sub(retained_blocks))
+        .map(|block_height| block_height + 1)
+        .unwrap_or(0);
+
+    let mut expired_block_heights = vec![];
+    // Blocks are scanned by ascending height, so the blocks to prune come first
+    stacks_db_rw.scan_blocks(0, true, &mut |block_height, block| {
+        if block_height >= confirmed_tip {
+            return Ok(false);
+        }
+        let expired = block_height < min_retained_block_height
+            || match retained_since {
+                Some(retained_since) => serde_json::from_slice::<StacksBlockTimestamp>(block)
+                    .map(|block| block.timestamp < retained_since)
+                    .unwrap_or(false),
+                None => false,
+            };
+        if expired {
+            expired_block_heights.push(block_height);
+        }
+        Ok(expired)
+    })?;
+    for block_height in expired_block_heights.iter() {
+        stacks_db_rw.delete_block(*block_height, true)?;
+    }
+
+    if let Some(block_height) = expired_block_heights.last() {
+        stacks_db_rw.set_cursor(StacksDbCursor::LastPrunedBlock, *block_height)?;
+        // Reclaim the space used by the deleted blocks right away
+        stacks_db_rw.compact_blocks(*block_height);
+    }
+    Ok(expired_block_heights.len() as u64)
+}
+
+/// Returns the lowest height at which the blocks of the Stacks db, confirmed or not, differ from
+/// the `canonical_blocks` of the Stacks node. Heights missing from the db are not compared.
+pub fn find_stacks_db_divergence(
+    canonical_blocks: &[BlockIdentifier],
+    stacks_db: &dyn StacksStorage,
+    _ctx: &Context,
+) -> Result<Option<u64>, String> {
+    let mut canonical_blocks = canonical_blocks.to_vec();
+    canonical_blocks.sort_by_key(|block_identifier| block_identifier.index);
+    for canonical_block in canonical_blocks.iter() {
+        let block =
+            match get_stacks_block_at_block_height(canonical_block.index, true, 0, stacks_db)? {
+                Some(block) => Some(block),
+                None => {
+                    get_stacks_block_at_block_height(canonical_block.index, false, 0, stacks_db)?
+                }
+            };
+        if let Some(block) = block {
+            if block.block_identifier.hash != canonical_block.hash {
+                return Ok(Some(canonical_block.index));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Inconsistencies found by [verify_stacks_db] among the confirmed blocks of the Stacks db.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StacksDbVerificationReport {
+    /// Highest confirmed block verified.
+    pub tip: Option<u64>,
+    /// Heights without any block, up to the tip.
+    pub missing_blocks: Vec<u64>,
+    /// Heights holding a block that can't be deserialized, or that was stored at another height.
+    pub corrupted_blocks: Vec<u64>,
+    /// Heights holding a block whose parent isn't the block stored right below it.
+    pub discontinuous_blocks: Vec<u64>,
+}
+
+impl StacksDbVerificationReport {
+    /// Lowest height from which the db needs to be ingested again, if any.
+    pub fn first_invalid_block_height(&self) -> Option<u64> {
+        self.missing_blocks
+            .iter()
+            .chain(self.corrupted_blocks.iter())
+            .chain(self.discontinuous_blocks.iter())
+            .min()
+            .copied()
+    }
+}
+
+/// Checks that the confirmed blocks of the Stacks db form a chain: every height above the last
+/// pruned block, up to the tip, holds a block that can be deserialized and whose parent is the
+/// block stored right below it.
+pub fn verify_stacks_db(
+    stacks_db: &dyn StacksStorage,
+    ctx: &Context,
+) -> Result<StacksDbVerificationReport, String> {
+    let mut report = StacksDbVerificationReport::default();
+    let Some(tip) = get_last_block_height_inserted(stacks_db, ctx) else {
+        return Ok(report);
+    };
+    report.tip = Some(tip);
+    let first_block_height = get_last_pruned_block_height(stacks_db, ctx).map_or(1, |h| h + 1);
+    let mut expected_block_height = first_block_height;
+    let mut previous_block: Option<BlockIdentifier> = None;
+    stacks_db.scan_blocks(first_block_height, true, &mut |block_height, block| {
+        if block_height > tip {
+            return Ok(false);
+        }
+        if block_height != expected_block_height {
+            report
+                .missing_blocks
+                .extend(expected_block_height..block_height);
+            previous_block = None;
+        }
+        expected_block_height = block_height + 1;
+        match serde_json::from_slice::<StacksBlockData>(block) {
+            Ok(block) if block.block_identifier.index == block_height => {
+                if let Some(previous_block) = previous_block.as_ref() {
+                    if block.parent_block_identifier.hash != previous_block.hash {
+                        report.discontinuous_blocks.push(block_height);
+                    }
+                }
+                previous_block = Some(block.block_identifier);
+            }
+            _ => {
+                report.corrupted_blocks.push(block_height);
+                previous_block = None;
+            }
+        }
+        Ok(true)
+    })?;
+    if expected_block_height <= tip {
+        report.missing_blocks.extend(expected_block_height..=tip);
+    }
+    Ok(report)
+}
+
+/// Deletes the blocks of the Stacks db, confirmed or not, from `block_height` up, and rewinds its
+/// metadata accordingly, so that the next consolidation imports the blocks of the Stacks archive
+/// from `block_height` up again. Returns the deleted blocks, from the highest one, leaving out the
+/// corrupted ones.
+pub fn rollback_stacks_blocks(
+    block_height: u64,
+    stacks_db_rw: &dyn StacksStorage,
+    ctx: &Context,
+) -> Result<Vec<StacksBlockData>, String> {
+    let mut blocks = vec![];
+    for confirmed in [true, false] {
+        let mut block_heights = vec![];
+        stacks_db_rw.scan_blocks(block_height, confirmed, &mut |block_height, block| {
+            block_heights.push(block_height);
+            if let Ok(block) = serde_json::from_slice::<StacksBlockData>(block) {
+                blocks.push(block);
+            }
+            Ok(true)
+        })?;
+        for block_height in block_heights.into_iter() {
+            stacks_db_rw.delete_block(block_height, confirmed)?;
+        }
+    }
+    blocks.sort_by(|a, b| b.block_identifier.index.cmp(&a.block_identifier.index));
+    blocks.dedup_by_key(|block| block.block_identifier.clone());
+
+    let rewind = |cursor: StacksDbCursor, inserted: Option<u64>| match inserted {
+        Some(h) if h >= block_height && block_height > 0 => {
+            stacks_db_rw.set_cursor(cursor, block_height - 1)
+        }
+        Some(h) if h >= block_height => stacks_db_rw.delete_cursor(cursor),
+        _ => Ok(()),
+    };
+    rewind(
+        StacksDbCursor::LastConfirmedBlock,
+        get_last_block_height_inserted(stacks_db_rw, ctx),
+    )?;
+    rewind(
+        StacksDbCursor::LastUnconfirmedBlock,
+        get_last_unconfirmed_block_height_inserted(stacks_db_rw, ctx),
+    )?;
+    rewind(
+        StacksDbCursor::ConsolidatedArchive,
+        get_last_consolidated_archive_block_height(stacks_db_rw, ctx),
+    )?;
+    stacks_db_rw.delete_archive_fingerprint()?;
+    Ok(blocks)
 }
 
 pub fn confirm_entries_in_stacks_blocks(
     blocks: &Vec<StacksBlockData>,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     ctx: &Context,
 ) -> Result<(), String> {
     for block in blocks.iter() {
@@ -245,7 +873,7 @@ pub fn confirm_entries_in_stacks_blocks(
 
 pub fn draft_entries_in_stacks_blocks(
     block_updates: &Vec<StacksBlockUpdate>,
-    stacks_db_rw: &DB,
+    stacks_db_rw: &dyn StacksStorage,
     ctx: &Context,
 ) -> Result<(), String> {
     for update in block_updates.iter() {
@@ -259,18 +887,11 @@ pub fn get_stacks_block_at_block_height(
     block_height: u64,
     confirmed: bool,
     retry: u8,
-    stacks_db: &DB,
+    stacks_db: &dyn StacksStorage,
 ) -> Result<Option<StacksBlockData>, String> {
     let mut attempt = 0;
     loop {
-        let block_identifier = &BlockIdentifier {
-            hash: "".to_string(),
-            index: block_height,
-        };
-        match stacks_db.get(match confirmed {
-            true => get_block_key(block_identifier),
-            false => get_unconfirmed_block_key(block_identifier),
-        }) {
+        match stacks_db.get_block(block_height, confirmed) {
             Ok(Some(entry)) => {
                 return Ok(Some({
                     let spec: StacksBlockData =
@@ -295,11 +916,11 @@ pub fn get_stacks_block_at_block_height(
 pub fn is_stacks_block_present(
     block_identifier: &BlockIdentifier,
     retry: u8,
-    stacks_db: &DB,
+    stacks_db: &dyn StacksStorage,
 ) -> bool {
     let mut attempt = 0;
     loop {
-        match stacks_db.get(get_block_key(block_identifier)) {
+        match stacks_db.get_block(block_identifier.index, true) {
             Ok(Some(_)) => return true,
             Ok(None) => return false,
             _ => {
@@ -312,3 +933,6 @@ pub fn is_stacks_block_present(
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests;
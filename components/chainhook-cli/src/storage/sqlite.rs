@@ -0,0 +1,237 @@
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use chainhook_sdk::types::BlockIdentifier;
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+
+use super::{StacksDbCursor, StacksStorage};
+
+const CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY: &str = "consolidated_archive_fingerprint";
+
+/// Stacks db held in a single SQLite file, lighter than RocksDB for small deployments and easy
+/// to inspect with the `sqlite3` shell: blocks are stored as JSON in the `blocks` table, or as
+/// blobs once encrypted, and the cursors in the `metadata` table.
+pub struct SqliteStacksStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStacksStorage {
+    /// Opens the db at `path`, creating it if missing.
+    pub fn open_readwrite(path: &Path) -> Result<Self, String> {
+        let conn =
+            Connection::open(path).map_err(|e| format!("unable to open stacks.sqlite: {}", e))?;
+        // auto_vacuum only applies to dbs created after it is set
+        conn.execute_batch(
+            "PRAGMA auto_vacuum = INCREMENTAL;
+            PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            CREATE TABLE IF NOT EXISTS blocks (
+                confirmed INTEGER NOT NULL,
+                block_height INTEGER NOT NULL,
+                block_hash TEXT NOT NULL,
+                block TEXT NOT NULL,
+                PRIMARY KEY (confirmed, block_height)
+            ) WITHOUT ROWID;
+            CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value NOT NULL
+            );",
+        )
+        .map_err(|e| format!("unable to initialize stacks.sqlite: {}", e))?;
+        SqliteStacksStorage::new(conn)
+    }
+
+    /// Opens the db at `path`, which must exist.
+    pub fn open_readonly(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("unable to open stacks.sqlite: {}", e))?;
+        SqliteStacksStorage::new(conn)
+    }
+
+    fn new(conn: Connection) -> Result<Self, String> {
+        // Readers and the writer of the db live in different threads, or processes
+        conn.busy_timeout(Duration::from_secs(10))
+            .map_err(|e| format!("unable to open stacks.sqlite: {}", e))?;
+        Ok(SqliteStacksStorage {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StacksStorage for SqliteStacksStorage {
+    fn get_block(&self, block_height: u64, confirmed: bool) -> Result<Option<Vec<u8>>, String> {
+        self.conn()
+            .prepare_cached("SELECT block FROM blocks WHERE confirmed = ?1 AND block_height = ?2")
+            .and_then(|mut stmt| {
+                stmt.query_row(params![confirmed, block_height as i64], |row| {
+                    get_block_bytes(row, 0)
+                })
+                .optional()
+            })
+            .map_err(|e| format!("unable to read stacks db: {}", e))
+    }
+
+    fn put_block(
+        &self,
+        block_identifier: &BlockIdentifier,
+        confirmed: bool,
+        block: &[u8],
+    ) -> Result<(), String> {
+        // Plain blocks are kept readable as JSON text, encrypted ones are stored as blobs
+        let block = match std::str::from_utf8(block) {
+            Ok(_) => ToSqlOutput::Borrowed(ValueRef::Text(block)),
+            Err(_) => ToSqlOutput::Borrowed(ValueRef::Blob(block)),
+        };
+        self.conn()
+            .prepare_cached(
+                "INSERT OR REPLACE INTO blocks (confirmed, block_height, block_hash, block)
+                VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut stmt| {
+                stmt.execute(params![
+                    confirmed,
+                    block_identifier.index as i64,
+                    block_identifier.hash,
+                    block
+                ])
+            })
+            .map_err(|e| format!("unable to insert blocks: {}", e))?;
+        Ok(())
+    }
+
+    fn delete_block(&self, block_height: u64, confirmed: bool) -> Result<(), String> {
+        self.conn()
+            .prepare_cached("DELETE FROM blocks WHERE confirmed = ?1 AND block_height = ?2")
+            .and_then(|mut stmt| stmt.execute(params![confirmed, block_height as i64]))
+            .map_err(|e| format!("unable to delete blocks: {}", e))?;
+        Ok(())
+    }
+
+    fn scan_blocks(
+        &self,
+        block_height: u64,
+        confirmed: bool,
+        f: &mut dyn FnMut(u64, &[u8]) -> Result<bool, String>,
+    ) -> Result<(), String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT block_height, block FROM blocks
+                WHERE confirmed = ?1 AND block_height >= ?2 ORDER BY block_height",
+            )
+            .map_err(|e| format!("unable to read stacks db: {}", e))?;
+        let mut rows = stmt
+            .query(params![confirmed, block_height as i64])
+            .map_err(|e| format!("unable to read stacks db: {}", e))?;
+        while let Some(row) = rows
+            .next()
+            .map_err(|e| format!("unable to read stacks db: {}", e))?
+        {
+            let (block_height, block) = row
+                .get::<_, i64>(0)
+                .and_then(|block_height| Ok((block_height, get_block_bytes(row, 1)?)))
+                .map_err(|e| format!("unable to read stacks db: {}", e))?;
+            if !f(block_height as u64, &block)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_cursor(&self, cursor: StacksDbCursor) -> Result<Option<u64>, String> {
+        self.conn()
+            .prepare_cached("SELECT value FROM metadata WHERE key = ?1")
+            .and_then(|mut stmt| {
+                stmt.query_row(params![cursor.name()], |row| row.get::<_, i64>(0))
+                    .optional()
+            })
+            .map(|block_height| block_height.map(|block_height| block_height as u64))
+            .map_err(|e| format!("unable to read metadata: {}", e))
+    }
+
+    fn set_cursor(&self, cursor: StacksDbCursor, block_height: u64) -> Result<(), String> {
+        self.conn()
+            .prepare_cached("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)")
+            .and_then(|mut stmt| stmt.execute(params![cursor.name(), block_height as i64]))
+            .map_err(|e| format!("unable to insert metadata: {}", e))?;
+        Ok(())
+    }
+
+    fn delete_cursor(&self, cursor: StacksDbCursor) -> Result<(), String> {
+        self.conn()
+            .prepare_cached("DELETE FROM metadata WHERE key = ?1")
+            .and_then(|mut stmt| stmt.execute(params![cursor.name()]))
+            .map_err(|e| format!("unable to delete metadata: {}", e))?;
+        Ok(())
+    }
+
+    fn get_archive_fingerprint(&self) -> Result<Option<String>, String> {
+        self.conn()
+            .prepare_cached("SELECT value FROM metadata WHERE key = ?1")
+            .and_then(|mut stmt| {
+                stmt.query_row(params![CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY], |row| {
+                    row.get::<_, String>(0)
+                })
+                .optional()
+            })
+            .map_err(|e| format!("unable to read metadata: {}", e))
+    }
+
+    fn set_archive_fingerprint(&self, fingerprint: &str) -> Result<(), String> {
+        self.conn()
+            .prepare_cached("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)")
+            .and_then(|mut stmt| {
+                stmt.execute(params![CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY, fingerprint])
+            })
+            .map_err(|e| format!("unable to insert metadata: {}", e))?;
+        Ok(())
+    }
+
+    fn delete_archive_fingerprint(&self) -> Result<(), String> {
+        self.conn()
+            .prepare_cached("DELETE FROM metadata WHERE key = ?1")
+            .and_then(|mut stmt| stmt.execute(params![CONSOLIDATED_ARCHIVE_FINGERPRINT_KEY]))
+            .map_err(|e| format!("unable to delete metadata: {}", e))?;
+        Ok(())
+    }
+
+    fn compact_blocks(&self, _block_height: u64) {
+        let _ = self.conn().execute_batch("PRAGMA incremental_vacuum;");
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        // Every statement is committed right away
+        Ok(())
+    }
+
+    fn create_snapshot(&self, path: &Path) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "VACUUM INTO ?1",
+                params![path.to_string_lossy().to_string()],
+            )
+            .map_err(|e| format!("unable to create stacks.sqlite snapshot: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Reads the block stored in column `idx` of `row`, either as JSON text or as an encrypted blob.
+fn get_block_bytes(row: &Row, idx: usize) -> rusqlite::Result<Vec<u8>> {
+    match row.get_ref(idx)? {
+        ValueRef::Text(block) | ValueRef::Blob(block) => Ok(block.to_vec()),
+        value => Err(rusqlite::Error::InvalidColumnType(
+            idx,
+            "block".into(),
+            value.data_type(),
+        )),
+    }
+}
@@ -0,0 +1,401 @@
+use std::path::PathBuf;
+
+use chainhook_sdk::types::{BlockIdentifier, StacksBlockData};
+use chainhook_sdk::utils::Context;
+use test_case::test_case;
+
+use crate::config::StacksDbBackend;
+use crate::storage::encryption::{EncryptedStacksStorage, PayloadCipher};
+use crate::storage::migrations::{
+    get_latest_schema_version, run_migrations, Migration, VersionedDb, STACKS_DB_MIGRATIONS,
+};
+use crate::storage::{
+    export_stacks_db_snapshot, find_stacks_db_divergence, get_last_block_height_inserted,
+    get_last_consolidated_archive_block_height, get_last_consolidated_archive_fingerprint,
+    get_last_pruned_block_height, get_last_unconfirmed_block_height_inserted,
+    import_stacks_db_snapshot, insert_entry_in_stacks_blocks,
+    insert_unconfirmed_entry_in_stacks_blocks, is_stacks_block_present,
+    open_readonly_stacks_db_conn, open_readwrite_stacks_db_conn, prune_stacks_blocks,
+    rollback_stacks_blocks, set_last_confirmed_insert_key,
+    set_last_consolidated_archive_block_height, set_last_consolidated_archive_fingerprint,
+    verify_stacks_db, StacksDbVerificationReport, StacksStorage,
+};
+
+fn get_tmp_dir() -> PathBuf {
+    let mut tmp_dir = std::env::temp_dir();
+    tmp_dir.push(format!("chainhook-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&tmp_dir).unwrap();
+    tmp_dir
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_restores_exported_stacks_db_snapshots(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let source_dir = get_tmp_dir();
+    let destination_dir = get_tmp_dir();
+    let archive_dir = get_tmp_dir();
+    let mut archive_path = archive_dir.clone();
+    archive_path.push("stacks.tar.gz");
+
+    {
+        let stacks_db = open_readwrite_stacks_db_conn(&source_dir, &backend, &ctx).unwrap();
+        let block_identifier = BlockIdentifier {
+            index: 42,
+            hash: "0x42".into(),
+        };
+        set_last_confirmed_insert_key(&block_identifier, stacks_db.as_ref(), &ctx).unwrap();
+    }
+    // The db being replaced is discarded
+    let _ = open_readwrite_stacks_db_conn(&destination_dir, &backend, &ctx).unwrap();
+
+    let exported = export_stacks_db_snapshot(&source_dir, &backend, &archive_path, &ctx).unwrap();
+    assert_eq!(exported, Some(42));
+    let restored =
+        import_stacks_db_snapshot(&destination_dir, &backend, &archive_path, &ctx).unwrap();
+    assert_eq!(restored, Some(42));
+
+    let stacks_db = open_readonly_stacks_db_conn(&destination_dir, &backend, &ctx).unwrap();
+    assert_eq!(
+        get_last_block_height_inserted(stacks_db.as_ref(), &ctx),
+        Some(42)
+    );
+
+    for dir in [source_dir, destination_dir, archive_dir] {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_rejects_invalid_stacks_db_snapshots(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let mut archive_path = base_dir.clone();
+    archive_path.push("invalid.tar.gz");
+    std::fs::write(&archive_path, b"not an archive").unwrap();
+
+    assert!(import_stacks_db_snapshot(&base_dir, &backend, &archive_path, &ctx).is_err());
+    let _ = std::fs::remove_dir_all(base_dir);
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_tracks_consolidated_archives(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    assert_eq!(
+        get_last_consolidated_archive_block_height(stacks_db.as_ref(), &ctx),
+        None
+    );
+    assert_eq!(
+        get_last_consolidated_archive_fingerprint(stacks_db.as_ref(), &ctx),
+        None
+    );
+
+    set_last_consolidated_archive_block_height(128, stacks_db.as_ref(), &ctx).unwrap();
+    set_last_consolidated_archive_fingerprint("1024-1700000000", stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(
+        get_last_consolidated_archive_block_height(stacks_db.as_ref(), &ctx),
+        Some(128)
+    );
+    assert_eq!(
+        get_last_consolidated_archive_fingerprint(stacks_db.as_ref(), &ctx),
+        Some("1024-1700000000".to_string())
+    );
+    // The confirmed chain tip is tracked separately
+    assert_eq!(
+        get_last_block_height_inserted(stacks_db.as_ref(), &ctx),
+        None
+    );
+    let _ = std::fs::remove_dir_all(base_dir);
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_prunes_blocks_out_of_the_retention_window(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    let block_identifier = |index: u64| BlockIdentifier {
+        index,
+        hash: format!("0x{index}"),
+    };
+    for index in 1..=10 {
+        let block = format!(r#"{{"timestamp":{}}}"#, index * 100);
+        stacks_db
+            .put_block(&block_identifier(index), true, block.as_bytes())
+            .unwrap();
+    }
+    set_last_confirmed_insert_key(&block_identifier(10), stacks_db.as_ref(), &ctx).unwrap();
+
+    let pruned = prune_stacks_blocks(Some(5), None, stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(pruned, 5);
+    assert_eq!(
+        get_last_pruned_block_height(stacks_db.as_ref(), &ctx),
+        Some(5)
+    );
+    assert!(!is_stacks_block_present(
+        &block_identifier(5),
+        0,
+        stacks_db.as_ref()
+    ));
+    assert!(is_stacks_block_present(
+        &block_identifier(6),
+        0,
+        stacks_db.as_ref()
+    ));
+
+    let pruned = prune_stacks_blocks(None, Some(801), stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(pruned, 3);
+    assert_eq!(
+        get_last_pruned_block_height(stacks_db.as_ref(), &ctx),
+        Some(8)
+    );
+    assert!(is_stacks_block_present(
+        &block_identifier(9),
+        0,
+        stacks_db.as_ref()
+    ));
+
+    // The last confirmed block is always kept
+    let pruned = prune_stacks_blocks(Some(1), Some(i64::MAX), stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(pruned, 1);
+    assert!(is_stacks_block_present(
+        &block_identifier(10),
+        0,
+        stacks_db.as_ref()
+    ));
+    let _ = std::fs::remove_dir_all(base_dir);
+}
+
+fn get_stacks_block(index: u64, fork: &str) -> StacksBlockData {
+    serde_json::from_value(serde_json::json!({
+        "block_identifier": { "index": index, "hash": format!("0x{fork}{index}") },
+        "parent_block_identifier": { "index": index - 1, "hash": format!("0x{fork}{}", index - 1) },
+        "timestamp": 0,
+        "transactions": [],
+        "metadata": {
+            "bitcoin_anchor_block_identifier": { "index": index, "hash": "0x00" },
+            "pox_cycle_index": 0,
+            "pox_cycle_position": 0,
+            "pox_cycle_length": 0,
+            "stacks_block_hash": "0x00",
+        },
+    }))
+    .unwrap()
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_rolls_back_blocks_diverging_from_the_canonical_chain(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    for index in 1..=8 {
+        let fork = if index < 6 { "a" } else { "b" };
+        insert_entry_in_stacks_blocks(&get_stacks_block(index, fork), stacks_db.as_ref(), &ctx)
+            .unwrap();
+    }
+    insert_unconfirmed_entry_in_stacks_blocks(&get_stacks_block(9, "b"), stacks_db.as_ref(), &ctx)
+        .unwrap();
+    set_last_consolidated_archive_block_height(7, stacks_db.as_ref(), &ctx).unwrap();
+    set_last_consolidated_archive_fingerprint("1-1", stacks_db.as_ref(), &ctx).unwrap();
+
+    let canonical_blocks = (4..=10)
+        .map(|index| get_stacks_block(index, "a").block_identifier)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        find_stacks_db_divergence(&canonical_blocks[..2], stacks_db.as_ref(), &ctx).unwrap(),
+        None
+    );
+    let divergence =
+        find_stacks_db_divergence(&canonical_blocks, stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(divergence, Some(6));
+
+    let blocks = rollback_stacks_blocks(6, stacks_db.as_ref(), &ctx).unwrap();
+    let rolled_back = blocks
+        .iter()
+        .map(|block| block.block_identifier.index)
+        .collect::<Vec<_>>();
+    assert_eq!(rolled_back, vec![9, 8, 7, 6]);
+    assert!(is_stacks_block_present(
+        &get_stacks_block(5, "a").block_identifier,
+        0,
+        stacks_db.as_ref()
+    ));
+    assert_eq!(
+        get_last_block_height_inserted(stacks_db.as_ref(), &ctx),
+        Some(5)
+    );
+    assert_eq!(
+        get_last_unconfirmed_block_height_inserted(stacks_db.as_ref(), &ctx),
+        Some(5)
+    );
+    assert_eq!(
+        get_last_consolidated_archive_block_height(stacks_db.as_ref(), &ctx),
+        Some(5)
+    );
+    assert_eq!(
+        get_last_consolidated_archive_fingerprint(stacks_db.as_ref(), &ctx),
+        None
+    );
+    assert_eq!(
+        find_stacks_db_divergence(&canonical_blocks, stacks_db.as_ref(), &ctx).unwrap(),
+        None
+    );
+    let _ = std::fs::remove_dir_all(base_dir);
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_migrates_the_stacks_db_to_the_latest_version(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let mut stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    let migrations: &[Migration<dyn StacksStorage>] = &[
+        Migration {
+            version: 1,
+            description: "first",
+            apply: |db, ctx| set_last_consolidated_archive_block_height(1, db, ctx),
+        },
+        Migration {
+            version: 2,
+            description: "second",
+            apply: |db, ctx| {
+                let height = get_last_consolidated_archive_block_height(db, ctx).unwrap();
+                set_last_consolidated_archive_block_height(height + 1, db, ctx)
+            },
+        },
+    ];
+
+    assert_eq!(
+        run_migrations(stacks_db.as_mut(), &migrations[..1], "stacks db", &ctx),
+        Ok(1)
+    );
+    assert_eq!(
+        run_migrations(stacks_db.as_mut(), migrations, "stacks db", &ctx),
+        Ok(2)
+    );
+    // Migrations already applied are skipped
+    assert_eq!(
+        run_migrations(stacks_db.as_mut(), migrations, "stacks db", &ctx),
+        Ok(2)
+    );
+    assert_eq!(
+        get_last_consolidated_archive_block_height(stacks_db.as_ref(), &ctx),
+        Some(2)
+    );
+    assert_eq!(stacks_db.as_mut().get_schema_version(), Ok(Some(2)));
+    // A db upgraded by a more recent release is rejected
+    assert!(run_migrations(stacks_db.as_mut(), STACKS_DB_MIGRATIONS, "stacks db", &ctx).is_err());
+
+    let fresh_dir = get_tmp_dir();
+    let mut fresh_db = open_readwrite_stacks_db_conn(&fresh_dir, &backend, &ctx).unwrap();
+    assert_eq!(
+        run_migrations(fresh_db.as_mut(), STACKS_DB_MIGRATIONS, "stacks db", &ctx),
+        Ok(get_latest_schema_version(STACKS_DB_MIGRATIONS))
+    );
+    let _ = std::fs::remove_dir_all(base_dir);
+    let _ = std::fs::remove_dir_all(fresh_dir);
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_reports_inconsistent_blocks(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    for index in [1, 2, 3, 5, 6] {
+        insert_entry_in_stacks_blocks(&get_stacks_block(index, "a"), stacks_db.as_ref(), &ctx)
+            .unwrap();
+    }
+    assert_eq!(
+        verify_stacks_db(stacks_db.as_ref(), &ctx)
+            .unwrap()
+            .first_invalid_block_height(),
+        Some(4)
+    );
+
+    insert_entry_in_stacks_blocks(&get_stacks_block(4, "a"), stacks_db.as_ref(), &ctx).unwrap();
+    insert_entry_in_stacks_blocks(&get_stacks_block(7, "b"), stacks_db.as_ref(), &ctx).unwrap();
+    let block_identifier = get_stacks_block(8, "a").block_identifier;
+    stacks_db
+        .put_block(&block_identifier, true, b"not a block")
+        .unwrap();
+    set_last_confirmed_insert_key(&block_identifier, stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(
+        verify_stacks_db(stacks_db.as_ref(), &ctx).unwrap(),
+        StacksDbVerificationReport {
+            tip: Some(8),
+            missing_blocks: vec![],
+            corrupted_blocks: vec![8],
+            discontinuous_blocks: vec![7],
+        }
+    );
+
+    rollback_stacks_blocks(7, stacks_db.as_ref(), &ctx).unwrap();
+    let report = verify_stacks_db(stacks_db.as_ref(), &ctx).unwrap();
+    assert_eq!(report.tip, Some(6));
+    assert_eq!(report.first_invalid_block_height(), None);
+    let _ = std::fs::remove_dir_all(base_dir);
+}
+
+#[test]
+fn it_encrypts_payloads() {
+    let cipher = PayloadCipher::from_hex_key(&"11".repeat(32)).unwrap();
+    let encrypted = cipher.encrypt(b"block").unwrap();
+    assert_ne!(encrypted, b"block");
+    assert_eq!(cipher.decrypt(&encrypted).unwrap(), b"block");
+    // Payloads stored in clear are rejected
+    assert!(cipher.decrypt(b"block").is_err());
+    let encrypted = cipher.encrypt_string("delivery").unwrap();
+    assert_eq!(cipher.decrypt_string(&encrypted).unwrap(), "delivery");
+    assert!(cipher.decrypt_string("delivery").is_err());
+    assert_eq!(cipher.plaintext_payloads_read(), 0);
+
+    // unless they are allowed, while the data stored before encryption was enabled is migrated
+    let cipher = PayloadCipher::from_hex_key(&"11".repeat(32))
+        .unwrap()
+        .allow_plaintext_payloads(true);
+    assert_eq!(cipher.decrypt(b"block").unwrap(), b"block");
+    assert_eq!(cipher.decrypt_string("delivery").unwrap(), "delivery");
+    assert_eq!(cipher.plaintext_payloads_read(), 2);
+
+    let other_cipher = PayloadCipher::from_hex_key(&"22".repeat(32)).unwrap();
+    assert!(other_cipher
+        .decrypt(&cipher.encrypt(b"block").unwrap())
+        .is_err());
+    assert!(PayloadCipher::from_hex_key("11").is_err());
+}
+
+#[test_case(StacksDbBackend::RocksDb)]
+#[test_case(StacksDbBackend::Sqlite)]
+fn it_stores_encrypted_blocks(backend: StacksDbBackend) {
+    let ctx = Context::empty();
+    let base_dir = get_tmp_dir();
+    let cipher = Box::leak(Box::new(
+        PayloadCipher::from_hex_key(&"11".repeat(32)).unwrap(),
+    ));
+    let stacks_db = EncryptedStacksStorage::new(
+        open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap(),
+        cipher,
+    );
+    let block = get_stacks_block(1, "a");
+    insert_entry_in_stacks_blocks(&block, &stacks_db, &ctx).unwrap();
+    assert!(is_stacks_block_present(
+        &block.block_identifier,
+        0,
+        &stacks_db
+    ));
+    drop(stacks_db);
+
+    let stacks_db = open_readwrite_stacks_db_conn(&base_dir, &backend, &ctx).unwrap();
+    let stored_block = stacks_db.get_block(1, true).unwrap().unwrap();
+    assert!(serde_json::from_slice::<StacksBlockData>(&stored_block).is_err());
+    assert!(
+        serde_json::from_slice::<StacksBlockData>(&cipher.decrypt(&stored_block).unwrap()).is_ok()
+    );
+    let _ = std::fs::remove_dir_all(base_dir);
+}
@@ -1,13 +1,25 @@
 use super::types::{
-    append_error_context, validate_txid, ChainhookInstance, ExactMatchingRule, HookAction,
-    MatchingRule, PoxConfig, TxinPredicate,
+    append_error_context, build_delivery_id, validate_txid, ChainhookInstance, ExactMatchingRule,
+    FileHook, HookAction, KafkaMessageKey, MatchingRule, NetworkParameters, PayloadEnvelope,
+    PoxConfig, TxinPredicate,
+};
+use crate::{
+    observer::EventObserverConfig,
+    utils::{
+        aws::AwsMessage, kafka::KafkaMessage, nats::NatsMessage,
+        cloud_events::{payload_content_type, wrap_payload},
+        object_storage::ObjectStoreUpload,
+        postgres::PostgresRecord,
+        projection::{project_payload_fields, validate_include_fields},
+        transform::{apply_transform, validate_transform},
+        build_http_post_request, Context, MAX_BLOCK_HEIGHTS_ENTRIES,
+    },
 };
-use crate::{observer::EventObserverConfig, utils::{Context, MAX_BLOCK_HEIGHTS_ENTRIES}};
 
 use bitcoincore_rpc_json::bitcoin::{address::Payload, Address};
 use chainhook_types::{
     BitcoinBlockData, BitcoinChainEvent, BitcoinNetwork, BitcoinTransactionData, BlockIdentifier,
-    StacksBaseChainOperation, TransactionIdentifier,
+    Chain, StacksBaseChainOperation, TransactionIdentifier,
 };
 use schemars::JsonSchema;
 
@@ -16,14 +28,15 @@ use hiro_system_kit::slog;
 use miniscript::bitcoin::secp256k1::Secp256k1;
 use miniscript::Descriptor;
 
-use reqwest::{Client, Method};
 use serde::{de, Deserialize, Deserializer};
 use serde_json::Value as JsonValue;
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    str::FromStr, time::Duration,
+    str::FromStr,
+    sync::OnceLock,
 };
 
+use rayon::prelude::*;
 use reqwest::RequestBuilder;
 
 use hex::FromHex;
@@ -46,10 +59,37 @@ pub struct BitcoinChainhookSpecification {
     pub include_outputs: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include_witness: Option<bool>,
+    /// Dot separated paths of the block fields to keep in the delivered payload.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_fields: Option<Vec<String>>,
+    /// jq expression applied to the occurrence payload before it is delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    /// Maximum number of matching blocks coalesced into a single delivery while scanning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+    /// Maximum time, in milliseconds, a matching block is held back waiting for its batch
+    /// to fill up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_batch_wait_ms: Option<u64>,
+    /// Minutes without any occurrence delivered after which a heartbeat, carrying the height of
+    /// the last block evaluated, is posted to the `http_post` action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_minutes: Option<u64>,
     #[serde(rename = "if_this")]
     pub predicate: BitcoinPredicateType,
     #[serde(rename = "then_that")]
     pub action: HookAction,
+    /// Action receiving the blocks rolled back, delivered apart from the blocks applied.
+    /// Defaults to `then_that`.
+    #[serde(
+        rename = "then_that_on_rollback",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rollback_action: Option<HookAction>,
+    /// Envelope wrapping the delivered payloads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_envelope: Option<PayloadEnvelope>,
 }
 
 impl BitcoinChainhookSpecification {
@@ -59,12 +99,19 @@ impl BitcoinChainhookSpecification {
             start_block: None,
             end_block: None,
             expire_after_occurrence: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
             include_proof: None,
             include_inputs: None,
             include_outputs: None,
             include_witness: None,
             predicate,
             action,
+            rollback_action: None,
+            payload_envelope: None,
         }
     }
 
@@ -108,14 +155,84 @@ impl BitcoinChainhookSpecification {
         self
     }
 
+    pub fn transform(&mut self, filter: &str) -> &mut Self {
+        self.transform = Some(filter.to_string());
+        self
+    }
+
+    pub fn include_fields(&mut self, fields: Vec<String>) -> &mut Self {
+        self.include_fields = Some(fields);
+        self
+    }
+
+    pub fn max_batch_size(&mut self, max_batch_size: u64) -> &mut Self {
+        self.max_batch_size = Some(max_batch_size);
+        self
+    }
+
+    pub fn max_batch_wait_ms(&mut self, max_batch_wait_ms: u64) -> &mut Self {
+        self.max_batch_wait_ms = Some(max_batch_wait_ms);
+        self
+    }
+
+    pub fn heartbeat_interval_minutes(&mut self, heartbeat_interval_minutes: u64) -> &mut Self {
+        self.heartbeat_interval_minutes = Some(heartbeat_interval_minutes);
+        self
+    }
+
+    pub fn rollback_action(&mut self, rollback_action: HookAction) -> &mut Self {
+        self.rollback_action = Some(rollback_action);
+        self
+    }
+
+    pub fn payload_envelope(&mut self, payload_envelope: PayloadEnvelope) -> &mut Self {
+        self.payload_envelope = Some(payload_envelope);
+        self
+    }
+
     pub fn validate(&self) -> Result<(), Vec<String>> {
         let mut errors = vec![];
         if let Err(e) = self.action.validate() {
             errors.append(&mut append_error_context("invalid 'then_that' value", e));
         }
+        if let Some(ref rollback_action) = self.rollback_action {
+            if let Err(e) = rollback_action.validate() {
+                errors.append(&mut append_error_context(
+                    "invalid 'then_that_on_rollback' value",
+                    e,
+                ));
+            }
+        }
         if let Err(e) = self.predicate.validate() {
             errors.append(&mut append_error_context("invalid 'if_this' value", e));
         }
+        if let Some(ref fields) = self.include_fields {
+            if let Err(e) = validate_include_fields(fields) {
+                errors.push(e);
+            }
+        }
+        if let Some(ref transform) = self.transform {
+            if let Err(e) = validate_transform(transform) {
+                errors.push(e);
+            }
+        }
+        if let Some(0) = self.max_batch_size {
+            errors.push(
+                "Chainhook specification field `max_batch_size` should be greater than 0.".into(),
+            );
+        }
+        if let Some(heartbeat_interval_minutes) = self.heartbeat_interval_minutes {
+            if heartbeat_interval_minutes == 0 {
+                errors.push(
+                    "Chainhook specification field `heartbeat_interval_minutes` should be greater than 0.".into(),
+                );
+            }
+            if self.action.http_hooks().is_empty() {
+                errors.push(
+                    "Chainhook specification field `heartbeat_interval_minutes` requires an `http_post` action.".into(),
+                );
+            }
+        }
 
         if let Some(end_block) = self.end_block {
             let start_block = self.start_block.unwrap_or(0);
@@ -180,6 +297,17 @@ pub struct BitcoinChainhookSpecificationNetworkMap {
     pub uuid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner_uuid: Option<String>,
+    /// Free-form labels predicates can be filtered by when listed through the predicates API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    /// Key-value pairs recording e.g. the owner or the purpose of the predicate, which
+    /// predicates can be filtered by when listed through the predicates API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+    /// Namespace the predicate is registered under, isolating it from the predicates of other
+    /// namespaces. Predicates without a namespace belong to the `default` one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     pub name: String,
     pub version: u32,
     pub networks: BTreeMap<BitcoinNetwork, BitcoinChainhookSpecification>,
@@ -197,6 +325,9 @@ impl BitcoinChainhookSpecificationNetworkMap {
         Ok(BitcoinChainhookInstance {
             uuid: self.uuid,
             owner_uuid: self.owner_uuid,
+            labels: self.labels,
+            metadata: self.metadata,
+            namespace: self.namespace,
             name: self.name,
             network: network.clone(),
             version: self.version,
@@ -204,8 +335,15 @@ impl BitcoinChainhookSpecificationNetworkMap {
             end_block: spec.end_block,
             blocks: spec.blocks,
             expire_after_occurrence: spec.expire_after_occurrence,
+            max_batch_size: spec.max_batch_size,
+            max_batch_wait_ms: spec.max_batch_wait_ms,
+            heartbeat_interval_minutes: spec.heartbeat_interval_minutes,
+            include_fields: spec.include_fields,
+            transform: spec.transform,
             predicate: spec.predicate,
             action: spec.action,
+            rollback_action: spec.rollback_action,
+            payload_envelope: spec.payload_envelope,
             include_proof: spec.include_proof.unwrap_or(false),
             include_inputs: spec.include_inputs.unwrap_or(false),
             include_outputs: spec.include_outputs.unwrap_or(false),
@@ -221,6 +359,12 @@ pub struct BitcoinChainhookInstance {
     pub uuid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     pub name: String,
     pub network: BitcoinNetwork,
     pub version: u32,
@@ -238,13 +382,60 @@ pub struct BitcoinChainhookInstance {
     pub include_inputs: bool,
     pub include_outputs: bool,
     pub include_witness: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_fields: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_wait_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_action: Option<HookAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_envelope: Option<PayloadEnvelope>,
     pub enabled: bool,
     pub expired_at: Option<u64>,
 }
 
 impl BitcoinChainhookInstance {
     pub fn key(&self) -> String {
-        ChainhookInstance::bitcoin_key(&self.uuid)
+        ChainhookInstance::namespaced_key(self.namespace.as_deref(), &self.uuid)
+    }
+
+    /// Returns the specification the predicate was registered with, for its network only.
+    pub fn into_specification_network_map(self) -> BitcoinChainhookSpecificationNetworkMap {
+        let spec = BitcoinChainhookSpecification {
+            blocks: self.blocks,
+            start_block: self.start_block,
+            end_block: self.end_block,
+            expire_after_occurrence: self.expire_after_occurrence,
+            include_proof: Some(self.include_proof),
+            include_inputs: Some(self.include_inputs),
+            include_outputs: Some(self.include_outputs),
+            include_witness: Some(self.include_witness),
+            include_fields: self.include_fields,
+            transform: self.transform,
+            max_batch_size: self.max_batch_size,
+            max_batch_wait_ms: self.max_batch_wait_ms,
+            heartbeat_interval_minutes: self.heartbeat_interval_minutes,
+            predicate: self.predicate,
+            action: self.action,
+            rollback_action: self.rollback_action,
+            payload_envelope: self.payload_envelope,
+        };
+        BitcoinChainhookSpecificationNetworkMap {
+            uuid: self.uuid,
+            owner_uuid: self.owner_uuid,
+            labels: self.labels,
+            metadata: self.metadata,
+            namespace: self.namespace,
+            name: self.name,
+            version: self.version,
+            networks: BTreeMap::from([(self.network, spec)]),
+        }
     }
 }
 
@@ -306,10 +497,121 @@ impl BitcoinPredicateType {
     }
 }
 
+#[derive(Clone)]
 pub struct BitcoinTriggerChainhook<'a> {
     pub chainhook: &'a BitcoinChainhookInstance,
     pub apply: Vec<(Vec<&'a BitcoinTransactionData>, &'a BitcoinBlockData)>,
     pub rollback: Vec<(Vec<&'a BitcoinTransactionData>, &'a BitcoinBlockData)>,
+    /// Position of the delivering action among the predicate's actions, see
+    /// [HookAction::Multiple].
+    pub action_index: usize,
+    /// Number of blocks, including itself, a block needs in the canonical chain to be confirmed
+    /// by the observer, reported in the payload.
+    pub confirmation_depth: Option<u64>,
+    /// Sequence of the reorg the occurrence was evaluated on, or 0 outside of reorgs. See
+    /// [build_delivery_id].
+    pub reorg_sequence: u64,
+    /// Action delivering the occurrence, resolved on the first call to `get_action` so that
+    /// routed actions are only evaluated once per occurrence.
+    pub resolved_action: OnceLock<&'a HookAction>,
+}
+
+impl<'a> BitcoinTriggerChainhook<'a> {
+    /// Returns the most recent block applied (or rolled back, if no block was applied).
+    pub fn get_tip_block_identifier(&self) -> Option<&BlockIdentifier> {
+        self.apply
+            .last()
+            .or(self.rollback.last())
+            .map(|(_, block)| &block.block_identifier)
+    }
+
+    /// Returns the id of the delivery of this occurrence. See [build_delivery_id]. The ids of the
+    /// deliveries to the actions following the first one of a predicate are suffixed with the
+    /// position of the action.
+    pub fn get_delivery_id(&self) -> String {
+        let delivery_id = build_delivery_id(
+            &self.chainhook.uuid,
+            self.get_tip_block_identifier(),
+            self.reorg_sequence,
+        );
+        match self.action_index {
+            0 => delivery_id,
+            action_index => format!("{}:{}", delivery_id, action_index),
+        }
+    }
+
+    /// Returns the predicate's `rollback_action` if the occurrence only rolls blocks back, its
+    /// `action` otherwise.
+    fn get_predicate_action(&self) -> &'a HookAction {
+        let chainhook = self.chainhook;
+        match chainhook.rollback_action {
+            Some(ref rollback_action) if self.apply.is_empty() => rollback_action,
+            _ => &chainhook.action,
+        }
+    }
+
+    /// Returns the action delivering this occurrence: the predicate's `rollback_action` if the
+    /// occurrence only rolls blocks back, its `action` otherwise, or the action at `action_index`
+    /// when the predicate delivers its occurrences to several actions. Routed actions are
+    /// resolved against the payload of the occurrence, once.
+    pub fn get_action(&self) -> &'a HookAction {
+        self.resolved_action.get_or_init(|| {
+            let action = self.get_predicate_action();
+            match action.actions().get(self.action_index).unwrap_or(action) {
+                HookAction::Route(routes) => {
+                    routes.route(&serialize_bitcoin_payload_to_json(self, &HashMap::new()))
+                }
+                action => action,
+            }
+        })
+    }
+
+    /// Splits an occurrence in one occurrence per delivering action. An occurrence rolling blocks
+    /// back and applying others is split in two occurrences, the rollback one first, when the
+    /// predicate delivers rollbacks to a `rollback_action`. Each occurrence is then repeated for
+    /// every action of the predicate, so that each action retries its deliveries on its own.
+    pub fn split_by_action(self) -> Vec<BitcoinTriggerChainhook<'a>> {
+        let occurrences = if self.chainhook.rollback_action.is_none()
+            || self.apply.is_empty()
+            || self.rollback.is_empty()
+        {
+            vec![self]
+        } else {
+            vec![
+                BitcoinTriggerChainhook {
+                    chainhook: self.chainhook,
+                    apply: vec![],
+                    rollback: self.rollback,
+                    action_index: 0,
+                    confirmation_depth: self.confirmation_depth,
+                    reorg_sequence: self.reorg_sequence,
+                    resolved_action: OnceLock::new(),
+                },
+                BitcoinTriggerChainhook {
+                    chainhook: self.chainhook,
+                    apply: self.apply,
+                    rollback: vec![],
+                    action_index: 0,
+                    confirmation_depth: self.confirmation_depth,
+                    reorg_sequence: self.reorg_sequence,
+                    resolved_action: OnceLock::new(),
+                },
+            ]
+        };
+        let mut triggers = vec![];
+        for occurrence in occurrences.into_iter() {
+            let fan_out = (1..occurrence.get_predicate_action().actions().len())
+                .map(|action_index| BitcoinTriggerChainhook {
+                    action_index,
+                    resolved_action: OnceLock::new(),
+                    ..occurrence.clone()
+                })
+                .collect::<Vec<_>>();
+            triggers.push(occurrence);
+            triggers.extend(fan_out);
+        }
+        triggers
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -395,21 +697,11 @@ pub enum OrdinalOperations {
 }
 
 pub fn get_stacks_canonical_magic_bytes(network: &BitcoinNetwork) -> [u8; 2] {
-    match network {
-        BitcoinNetwork::Mainnet => *b"X2",
-        BitcoinNetwork::Testnet => *b"T2",
-        BitcoinNetwork::Regtest => *b"id",
-        BitcoinNetwork::Signet => unreachable!(),
-    }
+    NetworkParameters::canonical(network).stacks_magic_bytes
 }
 
 pub fn get_canonical_pox_config(network: &BitcoinNetwork) -> PoxConfig {
-    match network {
-        BitcoinNetwork::Mainnet => PoxConfig::mainnet_default(),
-        BitcoinNetwork::Testnet => PoxConfig::testnet_default(),
-        BitcoinNetwork::Regtest => PoxConfig::devnet_default(),
-        BitcoinNetwork::Signet => unreachable!(),
-    }
+    NetworkParameters::canonical(network).pox_config
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -541,10 +833,113 @@ impl BitcoinChainhookOccurrencePayload {
 
 pub enum BitcoinChainhookOccurrence {
     Http(RequestBuilder, BitcoinChainhookOccurrencePayload),
-    File(String, Vec<u8>),
+    File(FileHook, Vec<u8>, BitcoinChainhookOccurrencePayload),
+    ObjectStore(ObjectStoreUpload, BitcoinChainhookOccurrencePayload),
+    Kafka(KafkaMessage, BitcoinChainhookOccurrencePayload),
+    Nats(NatsMessage, BitcoinChainhookOccurrencePayload),
+    Aws(AwsMessage, BitcoinChainhookOccurrencePayload),
+    Postgres(PostgresRecord, BitcoinChainhookOccurrencePayload),
     Data(BitcoinChainhookOccurrencePayload),
 }
 
+/// Number of active predicates from which a chain event is evaluated by rayon's global pool of
+/// worker threads rather than by the calling thread.
+pub const PARALLEL_EVALUATION_MIN_PREDICATES: usize = 64;
+
+struct BitcoinChainhookEvaluation<'a> {
+    chainhook: &'a BitcoinChainhookInstance,
+    trigger: Option<BitcoinTriggerChainhook<'a>>,
+    last_evaluated_block: Option<&'a BlockIdentifier>,
+    last_expired_block: Option<&'a BlockIdentifier>,
+}
+
+fn evaluate_bitcoin_block<'a>(
+    chainhook: &BitcoinChainhookInstance,
+    block: &'a BitcoinBlockData,
+    ctx: &Context,
+) -> Vec<&'a BitcoinTransactionData> {
+    block
+        .transactions
+        .iter()
+        .filter(|tx| chainhook.predicate.evaluate_transaction_predicate(tx, ctx))
+        .collect()
+}
+
+fn evaluate_bitcoin_chainhook_on_chain_event<'a>(
+    chain_event: &'a BitcoinChainEvent,
+    chainhook: &'a BitcoinChainhookInstance,
+    ctx: &Context,
+) -> BitcoinChainhookEvaluation<'a> {
+    let mut apply = vec![];
+    let mut rollback = vec![];
+    let mut last_evaluated_block = None;
+    let mut last_expired_block = None;
+    let end_block = chainhook.end_block.unwrap_or(u64::MAX);
+
+    let (blocks_to_rollback, blocks_to_apply) = match chain_event {
+        BitcoinChainEvent::ChainUpdatedWithBlocks(event) => (&[][..], &event.new_blocks),
+        BitcoinChainEvent::ChainUpdatedWithReorg(event) => {
+            (&event.blocks_to_rollback[..], &event.blocks_to_apply)
+        }
+    };
+    for block in blocks_to_rollback.iter() {
+        if end_block >= block.block_identifier.index {
+            let hits = evaluate_bitcoin_block(chainhook, block, ctx);
+            if !hits.is_empty() {
+                rollback.push((hits, block));
+            }
+        } else {
+            last_expired_block = Some(&block.block_identifier);
+        }
+    }
+    for block in blocks_to_apply.iter() {
+        last_evaluated_block = Some(&block.block_identifier);
+        if end_block >= block.block_identifier.index {
+            let hits = evaluate_bitcoin_block(chainhook, block, ctx);
+            if !hits.is_empty() {
+                apply.push((hits, block));
+            }
+        } else {
+            last_expired_block = Some(&block.block_identifier);
+        }
+    }
+
+    let trigger = if !apply.is_empty() || !rollback.is_empty() {
+        Some(BitcoinTriggerChainhook {
+            chainhook,
+            apply,
+            rollback,
+            action_index: 0,
+            confirmation_depth: None,
+            reorg_sequence: 0,
+            resolved_action: OnceLock::new(),
+        })
+    } else {
+        None
+    };
+    BitcoinChainhookEvaluation {
+        chainhook,
+        trigger,
+        last_evaluated_block,
+        last_expired_block,
+    }
+}
+
+fn evaluate_bitcoin_chainhooks_chunk<'a>(
+    chain_event: &'a BitcoinChainEvent,
+    chainhooks: &[&'a BitcoinChainhookInstance],
+    ctx: &Context,
+) -> Vec<BitcoinChainhookEvaluation<'a>> {
+    chainhooks
+        .iter()
+        .map(|chainhook| evaluate_bitcoin_chainhook_on_chain_event(chain_event, chainhook, ctx))
+        .collect()
+}
+
+/// Evaluates the active predicates against a chain event. When there are at least
+/// [PARALLEL_EVALUATION_MIN_PREDICATES] predicates, they are split in contiguous chunks evaluated
+/// by the threads of rayon's global pool; the results are merged in chunk order, so that the
+/// occurrences are returned in the order of `active_chainhooks` whichever path is taken.
 pub fn evaluate_bitcoin_chainhooks_on_chain_event<'a>(
     chain_event: &'a BitcoinChainEvent,
     active_chainhooks: &Vec<&'a BitcoinChainhookInstance>,
@@ -554,92 +949,38 @@ pub fn evaluate_bitcoin_chainhooks_on_chain_event<'a>(
     BTreeMap<&'a str, &'a BlockIdentifier>,
     BTreeMap<&'a str, &'a BlockIdentifier>,
 ) {
+    let workers = rayon::current_num_threads();
+    let evaluations =
+        if workers > 1 && active_chainhooks.len() >= PARALLEL_EVALUATION_MIN_PREDICATES {
+            let chunk_size = active_chainhooks.len().div_ceil(workers);
+            active_chainhooks
+                .par_chunks(chunk_size)
+                .flat_map_iter(|chunk| evaluate_bitcoin_chainhooks_chunk(chain_event, chunk, ctx))
+                .collect::<Vec<_>>()
+        } else {
+            evaluate_bitcoin_chainhooks_chunk(chain_event, active_chainhooks, ctx)
+        };
+
     let mut evaluated_predicates = BTreeMap::new();
     let mut triggered_predicates = vec![];
     let mut expired_predicates = BTreeMap::new();
-
-    match chain_event {
-        BitcoinChainEvent::ChainUpdatedWithBlocks(event) => {
-            for chainhook in active_chainhooks.iter() {
-                let mut apply = vec![];
-                let rollback = vec![];
-                let end_block = chainhook.end_block.unwrap_or(u64::MAX);
-
-                for block in event.new_blocks.iter() {
-                    evaluated_predicates.insert(chainhook.uuid.as_str(), &block.block_identifier);
-                    if end_block >= block.block_identifier.index {
-                        let mut hits = vec![];
-                        for tx in block.transactions.iter() {
-                            if chainhook.predicate.evaluate_transaction_predicate(tx, ctx) {
-                                hits.push(tx);
-                            }
-                        }
-                        if !hits.is_empty() {
-                            apply.push((hits, block));
-                        }
-                    } else {
-                        expired_predicates.insert(chainhook.uuid.as_str(), &block.block_identifier);
-                    }
-                }
-
-                if !apply.is_empty() {
-                    triggered_predicates.push(BitcoinTriggerChainhook {
-                        chainhook,
-                        apply,
-                        rollback,
-                    })
-                }
-            }
+    for evaluation in evaluations.into_iter() {
+        let uuid = evaluation.chainhook.uuid.as_str();
+        if let Some(block_identifier) = evaluation.last_evaluated_block {
+            evaluated_predicates.insert(uuid, block_identifier);
         }
-        BitcoinChainEvent::ChainUpdatedWithReorg(event) => {
-            for chainhook in active_chainhooks.iter() {
-                let mut apply = vec![];
-                let mut rollback = vec![];
-                let end_block = chainhook.end_block.unwrap_or(u64::MAX);
-
-                for block in event.blocks_to_rollback.iter() {
-                    if end_block >= block.block_identifier.index {
-                        let mut hits = vec![];
-                        for tx in block.transactions.iter() {
-                            if chainhook.predicate.evaluate_transaction_predicate(tx, ctx) {
-                                hits.push(tx);
-                            }
-                        }
-                        if !hits.is_empty() {
-                            rollback.push((hits, block));
-                        }
-                    } else {
-                        expired_predicates.insert(chainhook.uuid.as_str(), &block.block_identifier);
-                    }
-                }
-                for block in event.blocks_to_apply.iter() {
-                    evaluated_predicates.insert(chainhook.uuid.as_str(), &block.block_identifier);
-                    if end_block >= block.block_identifier.index {
-                        let mut hits = vec![];
-                        for tx in block.transactions.iter() {
-                            if chainhook.predicate.evaluate_transaction_predicate(tx, ctx) {
-                                hits.push(tx);
-                            }
-                        }
-                        if !hits.is_empty() {
-                            apply.push((hits, block));
-                        }
-                    } else {
-                        expired_predicates.insert(chainhook.uuid.as_str(), &block.block_identifier);
-                    }
-                }
-                if !apply.is_empty() || !rollback.is_empty() {
-                    triggered_predicates.push(BitcoinTriggerChainhook {
-                        chainhook,
-                        apply,
-                        rollback,
-                    })
-                }
-            }
+        if let Some(block_identifier) = evaluation.last_expired_block {
+            expired_predicates.insert(uuid, block_identifier);
+        }
+        if let Some(trigger) = evaluation.trigger {
+            triggered_predicates.push(trigger);
         }
     }
     (
-        triggered_predicates,
+        triggered_predicates
+            .into_iter()
+            .flat_map(|trigger| trigger.split_by_action())
+            .collect(),
         evaluated_predicates,
         expired_predicates,
     )
@@ -669,10 +1010,13 @@ pub fn serialize_bitcoin_payload_to_json<'a>(
                 "metadata": block.metadata,
             })
         }).collect::<Vec<_>>(),
+        "delivery_id": trigger.get_delivery_id(),
         "chainhook": {
             "uuid": trigger.chainhook.uuid,
+            "network": trigger.chainhook.network,
             "predicate": trigger.chainhook.predicate,
-            "is_streaming_blocks": trigger.chainhook.enabled
+            "is_streaming_blocks": trigger.chainhook.enabled,
+            "confirmation_depth": trigger.confirmation_depth
         }
     })
 }
@@ -757,44 +1101,124 @@ pub fn serialize_bitcoin_transactions_to_json(
         .collect::<Vec<_>>()
 }
 
+/// Serializes the payload delivered for a triggered predicate, keeping its `include_fields`
+/// then applying its `transform` and wrapping it in its `payload_envelope`, if any.
+pub fn serialize_bitcoin_payload_for_delivery<'a>(
+    trigger: &BitcoinTriggerChainhook<'a>,
+    proofs: &HashMap<&'a TransactionIdentifier, String>,
+) -> Result<JsonValue, String> {
+    let mut payload = serialize_bitcoin_payload_to_json(trigger, proofs);
+    if let Some(ref fields) = trigger.chainhook.include_fields {
+        payload = project_payload_fields(payload, fields);
+    }
+    if let Some(ref transform) = trigger.chainhook.transform {
+        payload = apply_transform(transform, payload)?;
+    }
+    Ok(wrap_payload(
+        &trigger.chainhook.payload_envelope,
+        payload,
+        &Chain::Bitcoin,
+        &trigger.chainhook.uuid,
+        &trigger.get_delivery_id(),
+    ))
+}
+
 pub fn handle_bitcoin_hook_action<'a>(
     trigger: BitcoinTriggerChainhook<'a>,
     proofs: &HashMap<&'a TransactionIdentifier, String>,
     config: &EventObserverConfig,
 ) -> Result<BitcoinChainhookOccurrence, String> {
-    match &trigger.chainhook.action {
+    match trigger.get_action() {
         HookAction::HttpPost(http) => {
-            let mut client_builder = Client::builder();
-            if let Some(timeout) = config.predicates_config.payload_http_request_timeout_ms {
-                client_builder = client_builder.timeout(Duration::from_millis(timeout));
-            }
-            let client = client_builder
-                .build()
-                .map_err(|e| format!("unable to build http client: {}", e))?;
-            let host = http.url.to_string();
-            let method = Method::POST;
-            let body = serde_json::to_vec(&serialize_bitcoin_payload_to_json(&trigger, proofs))
-                .map_err(|e| format!("unable to serialize payload {}", e))?;
-            let request = client
-                .request(method, &host)
-                .header("Content-Type", "application/json")
-                .header("Authorization", http.authorization_header.clone())
-                .body(body);
+            let body =
+                serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                    .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let request = build_http_post_request(
+                http,
+                body,
+                &trigger.get_delivery_id(),
+                payload_content_type(&trigger.chainhook.payload_envelope),
+                &config.predicates_config,
+            )?;
 
             let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
             Ok(BitcoinChainhookOccurrence::Http(request, data))
         }
         HookAction::FileAppend(disk) => {
-            let bytes = serde_json::to_vec(&serialize_bitcoin_payload_to_json(&trigger, proofs))
+            let bytes = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
                 .map_err(|e| format!("unable to serialize payload {}", e))?;
-            Ok(BitcoinChainhookOccurrence::File(
-                disk.path.to_string(),
-                bytes,
-            ))
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::File(disk.clone(), bytes, data))
+        }
+        HookAction::ObjectStore(object_store) => {
+            let upload = ObjectStoreUpload::new(
+                object_store,
+                &trigger.chainhook.uuid,
+                &serialize_bitcoin_payload_for_delivery(&trigger, proofs)?,
+            )?;
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::ObjectStore(upload, data))
+        }
+        HookAction::Kafka(kafka) => {
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let key = match kafka.key {
+                KafkaMessageKey::PredicateUuid => trigger.chainhook.uuid.clone(),
+                KafkaMessageKey::BlockHash => trigger
+                    .get_tip_block_identifier()
+                    .map(|b| b.hash.as_str())
+                    .unwrap_or(&trigger.chainhook.uuid)
+                    .to_string(),
+            };
+            let message = KafkaMessage::new(kafka, key, payload);
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::Kafka(message, data))
+        }
+        HookAction::Nats(nats) => {
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let message = NatsMessage::new(
+                nats,
+                &trigger.chainhook.uuid,
+                trigger.get_tip_block_identifier(),
+                payload,
+            );
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::Nats(message, data))
+        }
+        HookAction::Sqs(sqs) => {
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let message = AwsMessage::sqs(&sqs.queue_url, &sqs.region, payload);
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::Aws(message, data))
+        }
+        HookAction::Sns(sns) => {
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let message = AwsMessage::sns(&sns.topic_arn, &sns.region, payload);
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::Aws(message, data))
+        }
+        HookAction::Postgres(postgres) => {
+            let payload = serde_json::to_vec(&serialize_bitcoin_payload_for_delivery(&trigger, proofs)?)
+                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let record = PostgresRecord::new(
+                &postgres.dsn,
+                &postgres.table,
+                postgres.get_column(),
+                payload,
+            );
+            let data = BitcoinChainhookOccurrencePayload::from_trigger(trigger);
+            Ok(BitcoinChainhookOccurrence::Postgres(record, data))
         }
         HookAction::Noop => Ok(BitcoinChainhookOccurrence::Data(
             BitcoinChainhookOccurrencePayload::from_trigger(trigger),
         )),
+        HookAction::Route(_) | HookAction::Multiple(_) => Err(format!(
+            "unable to resolve the action delivering an occurrence of predicate {}",
+            trigger.chainhook.uuid
+        )),
     }
 }
 
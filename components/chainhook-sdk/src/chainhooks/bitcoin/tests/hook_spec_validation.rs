@@ -2,7 +2,10 @@ use super::*;
 use crate::chainhooks::{bitcoin::InscriptionFeedData, types::ChainhookSpecificationNetworkMap};
 use chainhook_types::BitcoinNetwork;
 use test_case::test_case;
-use crate::chainhooks::types::HttpHook;
+use crate::chainhooks::types::{
+    DeadLetterSink, DeliveryOptions, FileHook, HookRoute, HookRoutes, HttpHook, HttpTlsConfig,
+    KafkaHook, KafkaMessageKey, NatsHook, PostgresHook, RedisListHook, RetryPolicy, SnsHook,
+};
 
 lazy_static! {
     static ref TXID_NO_PREFIX: String = "1234567890123456789012345678901234567890123456789012345678901234".into();
@@ -22,13 +25,59 @@ lazy_static! {
     static ref INVALID_TXID_PREDICATE: BitcoinPredicateType =
         BitcoinPredicateType::Txid(ExactMatchingRule::Equals("test".into()));
     static ref INVALID_HOOK_ACTION: HookAction = 
-        HookAction::HttpPost(HttpHook { url: "".into(), authorization_header: "\n".into() });
+        HookAction::HttpPost(HttpHook { url: "".into(), authorization_header: "\n".into(), signing_secret: None, tls: None, compression: None, require_ack: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_SIGNING_SECRET_HOOK_ACTION: HookAction =
+        HookAction::HttpPost(HttpHook { url: "http://localhost:3000".into(), authorization_header: "Bearer 1234".into(), signing_secret: Some("".into()), tls: None, compression: None, require_ack: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_SIGNING_SECRET_ERR: String = "invalid 'http_post' data: signing secret must not be empty".into();
+    static ref INVALID_TLS_HOOK_ACTION: HookAction =
+        HookAction::HttpPost(HttpHook { url: "https://localhost:3000".into(), authorization_header: "Bearer 1234".into(), signing_secret: None, tls: Some(HttpTlsConfig { ca_cert_path: None, client_cert_path: Some("client.pem".into()), client_key_path: None }), compression: None, require_ack: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_TLS_ERR: String = "invalid 'http_post' data: client_cert_path and client_key_path must be provided together".into();
+    static ref VALID_HOOK_ACTION: HookAction =
+        HookAction::HttpPost(HttpHook { url: "http://localhost:3000".into(), authorization_header: "Bearer 1234".into(), signing_secret: None, tls: None, compression: None, require_ack: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_KAFKA_HOOK_ACTION: HookAction =
+        HookAction::Kafka(KafkaHook { brokers: vec!["localhost".into()], topic: "".into(), key: KafkaMessageKey::PredicateUuid, delivery: DeliveryOptions::default() });
+    static ref VALID_KAFKA_HOOK_ACTION: HookAction =
+        HookAction::Kafka(KafkaHook { brokers: vec!["localhost:9092".into()], topic: "chainhook".into(), key: KafkaMessageKey::BlockHash, delivery: DeliveryOptions::default() });
+    static ref INVALID_NATS_HOOK_ACTION: HookAction =
+        HookAction::Nats(NatsHook { server_url: "nats://localhost:4222".into(), subject: "chainhook {uuid}".into(), jetstream: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_NATS_SUBJECT_ERR: String = "invalid 'nats' data: subject must be a non empty string without whitespaces".into();
+    static ref INVALID_SNS_HOOK_ACTION: HookAction =
+        HookAction::Sns(SnsHook { topic_arn: "chainhook-topic".into(), region: None, delivery: DeliveryOptions::default() });
+    static ref INVALID_SNS_TOPIC_ERR: String = "invalid 'sns' data: topic_arn must be a valid SNS topic ARN".into();
+    static ref INVALID_POSTGRES_HOOK_ACTION: HookAction =
+        HookAction::Postgres(PostgresHook { dsn: "postgres://localhost/chainhook".into(), table: "occurrences; DROP TABLE users".into(), column: Some("public.payload".into()), delivery: DeliveryOptions::default() });
+    static ref INVALID_POSTGRES_TABLE_ERR: String = "invalid 'postgres' data: table occurrences; DROP TABLE users is not a valid table name".into();
+    static ref INVALID_POSTGRES_COLUMN_ERR: String = "invalid 'postgres' data: column public.payload is not a valid column name".into();
+    static ref INVALID_KAFKA_BROKER_ERR: String = "invalid 'kafka' data: broker localhost must be formatted as <host>:<port>".into();
+    static ref INVALID_KAFKA_TOPIC_ERR: String = "invalid 'kafka' data: topic must not be empty".into();
+    static ref KAFKA_NOT_ENABLED_ERR: String = "invalid 'kafka' data: kafka support is not enabled on this chainhook instance".into();
+    static ref INVALID_MULTIPLE_HOOK_ACTION: HookAction =
+        HookAction::Multiple(vec![VALID_KAFKA_HOOK_ACTION.clone(), INVALID_KAFKA_HOOK_ACTION.clone()]);
+    static ref INVALID_MULTIPLE_KAFKA_BROKER_ERR: String = format!("invalid action #1: {}", *INVALID_KAFKA_BROKER_ERR);
+    static ref INVALID_MULTIPLE_KAFKA_TOPIC_ERR: String = format!("invalid action #1: {}", *INVALID_KAFKA_TOPIC_ERR);
+    static ref NESTED_MULTIPLE_HOOK_ACTION: HookAction =
+        HookAction::Multiple(vec![HookAction::Multiple(vec![HookAction::Noop])]);
+    static ref NESTED_MULTIPLE_ERR: String = "invalid action #0: actions cannot be nested".into();
+    static ref EMPTY_MULTIPLE_HOOK_ACTION: HookAction = HookAction::Multiple(vec![]);
+    static ref EMPTY_MULTIPLE_ERR: String = "at least one action must be specified".into();
+    static ref INVALID_ROUTE_HOOK_ACTION: HookAction =
+        HookAction::Route(HookRoutes { rules: vec![HookRoute { when: ".apply".into(), action: HookAction::Multiple(vec![HookAction::Noop]) }], otherwise: None });
+    static ref INVALID_ROUTE_ERRS: Vec<String> = vec![
+        "invalid 'route' data: invalid rule #0: routed actions cannot be nested".into(),
+    ];
+    static ref VALID_ROUTE_HOOK_ACTION: HookAction =
+        HookAction::Route(HookRoutes { rules: vec![HookRoute { when: ".apply | length > 1".into(), action: VALID_HOOK_ACTION.clone() }], otherwise: Some(Box::new(HookAction::Noop)) });
+    static ref VALID_MULTIPLE_HOOK_ACTION: HookAction =
+        HookAction::Multiple(vec![VALID_HOOK_ACTION.clone(), HookAction::Noop]);
     static ref ALL_INVALID_SPEC: BitcoinChainhookSpecification = BitcoinChainhookSpecification::new(INVALID_TXID_PREDICATE.clone(), INVALID_HOOK_ACTION.clone());
     static ref ALL_INVALID_SPEC_NETWORK_MAP: ChainhookSpecificationNetworkMap = 
         ChainhookSpecificationNetworkMap::Bitcoin(
             BitcoinChainhookSpecificationNetworkMap { 
                 uuid: "test".into(), 
                 owner_uuid: None, 
+                labels: None,
+                metadata: None,
+                namespace: None,
                 name: "test".into(), 
                 version: 1, 
                 networks: BTreeMap::from([
@@ -159,6 +208,20 @@ fn it_validates_bitcoin_predicates(predicate: &BitcoinPredicateType, expected_er
 
 #[test_case(&INVALID_HOOK_ACTION, Some(vec![INVALID_URL_ERR.clone(), INVALID_HTTP_HEADER_ERR.clone()]); "invalid http_post action"
 )]
+#[test_case(&INVALID_SIGNING_SECRET_HOOK_ACTION, Some(vec![INVALID_SIGNING_SECRET_ERR.clone()]); "invalid http_post signing secret")]
+#[test_case(&INVALID_TLS_HOOK_ACTION, Some(vec![INVALID_TLS_ERR.clone()]); "invalid http_post tls config")]
+#[cfg_attr(feature = "kafka", test_case(&INVALID_KAFKA_HOOK_ACTION, Some(vec![INVALID_KAFKA_BROKER_ERR.clone(), INVALID_KAFKA_TOPIC_ERR.clone()]); "invalid kafka action"))]
+#[cfg_attr(feature = "kafka", test_case(&VALID_KAFKA_HOOK_ACTION, None; "valid kafka action"))]
+#[cfg_attr(not(feature = "kafka"), test_case(&VALID_KAFKA_HOOK_ACTION, Some(vec![KAFKA_NOT_ENABLED_ERR.clone()]); "kafka action without kafka support"))]
+#[cfg_attr(feature = "kafka", test_case(&INVALID_MULTIPLE_HOOK_ACTION, Some(vec![INVALID_MULTIPLE_KAFKA_BROKER_ERR.clone(), INVALID_MULTIPLE_KAFKA_TOPIC_ERR.clone()]); "invalid action among multiple actions"))]
+#[test_case(&NESTED_MULTIPLE_HOOK_ACTION, Some(vec![NESTED_MULTIPLE_ERR.clone()]); "nested multiple actions")]
+#[test_case(&EMPTY_MULTIPLE_HOOK_ACTION, Some(vec![EMPTY_MULTIPLE_ERR.clone()]); "empty multiple actions")]
+#[test_case(&VALID_MULTIPLE_HOOK_ACTION, None; "valid multiple actions")]
+#[test_case(&INVALID_ROUTE_HOOK_ACTION, Some(INVALID_ROUTE_ERRS.clone()); "invalid route action")]
+#[test_case(&VALID_ROUTE_HOOK_ACTION, None; "valid route action")]
+#[cfg_attr(feature = "nats", test_case(&INVALID_NATS_HOOK_ACTION, Some(vec![INVALID_NATS_SUBJECT_ERR.clone()]); "invalid nats action"))]
+#[cfg_attr(feature = "aws", test_case(&INVALID_SNS_HOOK_ACTION, Some(vec![INVALID_SNS_TOPIC_ERR.clone()]); "invalid sns action"))]
+#[cfg_attr(feature = "postgres", test_case(&INVALID_POSTGRES_HOOK_ACTION, Some(vec![INVALID_POSTGRES_TABLE_ERR.clone(), INVALID_POSTGRES_COLUMN_ERR.clone()]); "invalid postgres action"))]
 fn it_validates_hook_actions(action: &HookAction, expected_err: Option<Vec<String>>) {
     if let Err(e) = action.validate() {
         if let Some(expected) = expected_err {
@@ -178,6 +241,54 @@ fn it_validates_hook_actions(action: &HookAction, expected_err: Option<Vec<Strin
     }
 }
 
+#[test]
+fn it_validates_delivery_options() {
+    let spec = BitcoinChainhookSpecification::new(
+        BitcoinPredicateType::Block,
+        HookAction::HttpPost(HttpHook {
+            url: "http://localhost:3000".into(),
+            authorization_header: "Bearer 1234".into(),
+            signing_secret: None,
+            tls: None,
+            compression: None,
+            require_ack: None,
+            delivery: DeliveryOptions {
+                retry_policy: Some(RetryPolicy {
+                    max_attempts: Some(0),
+                    ..Default::default()
+                }),
+                dead_letter: Some(Box::new(DeadLetterSink::RedisList(RedisListHook {
+                    redis_url: "localhost:6379".into(),
+                    key: "".into(),
+                }))),
+            },
+        }),
+    );
+    assert_eq!(
+        spec.validate().unwrap_err(),
+        vec![
+            "invalid 'then_that' value: invalid 'http_post' data: invalid 'retry_policy' value: max_attempts must be greater than 0".to_string(),
+            "invalid 'then_that' value: invalid 'http_post' data: invalid 'dead_letter' value: invalid 'redis_list' data: redis_url must use the redis:// or rediss:// scheme".to_string(),
+            "invalid 'then_that' value: invalid 'http_post' data: invalid 'dead_letter' value: invalid 'redis_list' data: key must not be empty".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn it_rejects_nested_dead_letter_sinks() {
+    let mut sink = FileHook {
+        path: "dead-letter.json".into(),
+        rotation: None,
+        compression: None,
+        delivery: DeliveryOptions::default(),
+    };
+    sink.delivery.dead_letter = Some(Box::new(DeadLetterSink::FileAppend(sink.clone())));
+    assert_eq!(
+        DeadLetterSink::FileAppend(sink).validate().unwrap_err(),
+        vec!["dead-letter sinks can't have a dead_letter".to_string()]
+    );
+}
+
 #[test_case(&ALL_INVALID_SPEC_NETWORK_MAP, INVALID_SPEC_NETWORK_MAP_ERR.clone())]
 fn it_validates_bitcoin_chainhook_specs(
     predicate: &ChainhookSpecificationNetworkMap,
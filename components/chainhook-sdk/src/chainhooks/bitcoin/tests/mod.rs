@@ -3,13 +3,17 @@ use std::collections::HashSet;
 use super::super::types::MatchingRule;
 use super::*;
 use crate::chainhooks::bitcoin::InscriptionFeedData;
+use crate::chainhooks::tests::assert_payload_matches_json_schema;
+use crate::chainhooks::types::{DeliveryOptions, FileHook};
 use crate::indexer::tests::helpers::accounts;
 use crate::indexer::tests::helpers::bitcoin_blocks::generate_test_bitcoin_block;
 use crate::indexer::tests::helpers::transactions::generate_test_tx_bitcoin_p2pkh_transfer;
 use crate::types::BitcoinTransactionMetadata;
 use chainhook_types::bitcoin::TxOut;
 
-use chainhook_types::{BitcoinNetwork, Brc20Operation, Brc20TokenDeployData};
+use chainhook_types::{
+    BitcoinChainUpdatedWithBlocksData, BitcoinNetwork, Brc20Operation, Brc20TokenDeployData,
+};
 use test_case::test_case;
 mod hook_spec_validation;
 
@@ -168,6 +172,9 @@ fn it_serdes_occurrence_payload(
     let chainhook = &BitcoinChainhookInstance {
         uuid: "uuid".into(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "name".into(),
         network: BitcoinNetwork::Mainnet,
         version: 0,
@@ -175,6 +182,13 @@ fn it_serdes_occurrence_payload(
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         predicate: BitcoinPredicateType::Block,
         action: HookAction::Noop,
         include_proof,
@@ -188,12 +202,14 @@ fn it_serdes_occurrence_payload(
         chainhook,
         apply: vec![(vec![&transaction], &block)],
         rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
     };
-    let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(
-        &trigger,
-        &HashMap::new(),
-    ))
-    .unwrap();
+    let payload = serialize_bitcoin_payload_to_json(&trigger, &HashMap::new());
+    assert_payload_matches_json_schema("bitcoin_payload", &payload);
+    let payload = serde_json::to_vec(&payload).unwrap();
 
     let _: BitcoinChainhookOccurrencePayload = serde_json::from_slice(&payload[..]).unwrap();
 }
@@ -234,6 +250,9 @@ fn it_serdes_brc20_payload(tick: String) {
     let chainhook = &BitcoinChainhookInstance {
         uuid: "uuid".into(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "name".into(),
         network: BitcoinNetwork::Mainnet,
         version: 0,
@@ -241,6 +260,13 @@ fn it_serdes_brc20_payload(tick: String) {
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         predicate: BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionFeed(
             InscriptionFeedData {
                 meta_protocols: Some(meta_protocols),
@@ -258,12 +284,14 @@ fn it_serdes_brc20_payload(tick: String) {
         chainhook,
         apply: vec![(vec![&transaction], &block)],
         rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
     };
-    let payload = serde_json::to_vec(&serialize_bitcoin_payload_to_json(
-        &trigger,
-        &HashMap::new(),
-    ))
-    .unwrap();
+    let payload = serialize_bitcoin_payload_to_json(&trigger, &HashMap::new());
+    assert_payload_matches_json_schema("bitcoin_payload", &payload);
+    let payload = serde_json::to_vec(&payload).unwrap();
 
     let deserialized: BitcoinChainhookOccurrencePayload =
         serde_json::from_slice(&payload[..]).unwrap();
@@ -272,3 +300,313 @@ fn it_serdes_brc20_payload(tick: String) {
         .brc20_operation
         .is_some());
 }
+
+#[test]
+fn it_delivers_rollbacks_to_the_rollback_action() {
+    let transaction = generate_test_tx_bitcoin_p2pkh_transfer(
+        0,
+        &accounts::wallet_1_btc_address(),
+        &accounts::wallet_3_btc_address(),
+        3,
+    );
+    let rolled_back = generate_test_bitcoin_block(0, 1, vec![transaction.clone()], None);
+    let applied = generate_test_bitcoin_block(1, 1, vec![transaction.clone()], None);
+    let rollback_action = HookAction::FileAppend(FileHook {
+        path: "rollbacks.json".into(),
+        rotation: None,
+        compression: None,
+        delivery: DeliveryOptions::default(),
+    });
+    let mut chainhook = BitcoinChainhookInstance {
+        uuid: "uuid".into(),
+        owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
+        name: "name".into(),
+        network: BitcoinNetwork::Mainnet,
+        version: 0,
+        blocks: None,
+        start_block: None,
+        end_block: None,
+        expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
+        predicate: BitcoinPredicateType::Block,
+        action: HookAction::Noop,
+        include_proof: false,
+        include_inputs: false,
+        include_outputs: false,
+        include_witness: false,
+        enabled: true,
+        expired_at: None,
+    };
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: &chainhook,
+        apply: vec![(vec![&transaction], &applied)],
+        rollback: vec![(vec![&transaction], &rolled_back)],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    assert_eq!(trigger.split_by_action().len(), 1);
+
+    chainhook.rollback_action = Some(rollback_action.clone());
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: &chainhook,
+        apply: vec![(vec![&transaction], &applied)],
+        rollback: vec![(vec![&transaction], &rolled_back)],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    let triggers = trigger.split_by_action();
+    assert_eq!(triggers.len(), 2);
+    assert!(triggers[0].apply.is_empty());
+    assert_eq!(triggers[0].get_action(), &rollback_action);
+    assert!(triggers[1].rollback.is_empty());
+    assert_eq!(triggers[1].get_action(), &HookAction::Noop);
+    assert_ne!(triggers[0].get_delivery_id(), triggers[1].get_delivery_id());
+}
+
+#[test]
+fn it_delivers_occurrences_to_every_action() {
+    let transaction = generate_test_tx_bitcoin_p2pkh_transfer(
+        0,
+        &accounts::wallet_1_btc_address(),
+        &accounts::wallet_3_btc_address(),
+        3,
+    );
+    let block = generate_test_bitcoin_block(0, 1, vec![transaction.clone()], None);
+    let file_action = HookAction::FileAppend(FileHook {
+        path: "occurrences.json".into(),
+        rotation: None,
+        compression: None,
+        delivery: DeliveryOptions::default(),
+    });
+    let action: HookAction = serde_json::from_value(serde_json::json!([
+        "noop",
+        { "file_append": { "path": "occurrences.json" } }
+    ]))
+    .unwrap();
+    assert_eq!(
+        action,
+        HookAction::Multiple(vec![HookAction::Noop, file_action.clone()])
+    );
+    let chainhook = BitcoinChainhookInstance {
+        uuid: "uuid".into(),
+        owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
+        name: "name".into(),
+        network: BitcoinNetwork::Mainnet,
+        version: 0,
+        blocks: None,
+        start_block: None,
+        end_block: None,
+        expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
+        predicate: BitcoinPredicateType::Block,
+        action,
+        include_proof: false,
+        include_inputs: false,
+        include_outputs: false,
+        include_witness: false,
+        enabled: true,
+        expired_at: None,
+    };
+
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: &chainhook,
+        apply: vec![(vec![&transaction], &block)],
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    let triggers = trigger.split_by_action();
+    assert_eq!(triggers.len(), 2);
+    assert_eq!(triggers[0].get_action(), &HookAction::Noop);
+    assert_eq!(triggers[1].get_action(), &file_action);
+    let block_hash = &block.block_identifier.hash;
+    assert_eq!(triggers[0].get_delivery_id(), format!("uuid:{}:0", block_hash));
+    assert_eq!(triggers[1].get_delivery_id(), format!("uuid:{}:0:1", block_hash));
+}
+
+#[test]
+fn it_routes_occurrences_to_the_action_of_the_matching_rule() {
+    let transaction = generate_test_tx_bitcoin_p2pkh_transfer(
+        0,
+        &accounts::wallet_1_btc_address(),
+        &accounts::wallet_3_btc_address(),
+        3,
+    );
+    let low_block = generate_test_bitcoin_block(0, 10, vec![transaction.clone()], None);
+    let high_block = generate_test_bitcoin_block(0, 1000, vec![transaction.clone()], None);
+    let high_action = HookAction::FileAppend(FileHook {
+        path: "high.json".into(),
+        rotation: None,
+        compression: None,
+        delivery: DeliveryOptions::default(),
+    });
+    let action: HookAction = serde_json::from_value(serde_json::json!({
+        "route": {
+            "rules": [{
+                "when": "any(.apply[]; .block_identifier.index >= 100)",
+                "then_that": { "file_append": { "path": "high.json" } }
+            }]
+        }
+    }))
+    .unwrap();
+    assert!(action.validate().is_ok());
+    let chainhook = BitcoinChainhookInstance {
+        uuid: "uuid".into(),
+        owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
+        name: "name".into(),
+        network: BitcoinNetwork::Mainnet,
+        version: 0,
+        blocks: None,
+        start_block: None,
+        end_block: None,
+        expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
+        predicate: BitcoinPredicateType::Block,
+        action,
+        include_proof: false,
+        include_inputs: false,
+        include_outputs: false,
+        include_witness: false,
+        enabled: true,
+        expired_at: None,
+    };
+
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: &chainhook,
+        apply: vec![(vec![&transaction], &high_block)],
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    assert_eq!(trigger.get_action(), &high_action);
+    let trigger = BitcoinTriggerChainhook {
+        chainhook: &chainhook,
+        apply: vec![(vec![&transaction], &low_block)],
+        rollback: vec![],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+    assert_eq!(trigger.get_action(), &HookAction::Noop);
+}
+
+#[test]
+fn it_evaluates_predicates_in_parallel_in_a_deterministic_order() {
+    let transaction = generate_test_tx_bitcoin_p2pkh_transfer(
+        0,
+        &accounts::wallet_1_btc_address(),
+        &accounts::wallet_3_btc_address(),
+        3,
+    );
+    let block = generate_test_bitcoin_block(0, 10, vec![transaction], None);
+    let chain_event =
+        BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+            new_blocks: vec![block],
+            confirmed_blocks: vec![],
+        });
+    let chainhooks = (0..PARALLEL_EVALUATION_MIN_PREDICATES * 2)
+        .map(|i| BitcoinChainhookInstance {
+            uuid: format!("uuid-{:03}", i),
+            owner_uuid: None,
+            labels: None,
+            metadata: None,
+            namespace: None,
+            name: "name".into(),
+            network: BitcoinNetwork::Mainnet,
+            version: 0,
+            blocks: None,
+            start_block: None,
+            // every third predicate expired before the evaluated block
+            end_block: if i % 3 == 0 { Some(5) } else { None },
+            expire_after_occurrence: None,
+            rollback_action: None,
+            payload_envelope: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
+            predicate: BitcoinPredicateType::Block,
+            action: HookAction::Noop,
+            include_proof: false,
+            include_inputs: false,
+            include_outputs: false,
+            include_witness: false,
+            enabled: true,
+            expired_at: None,
+        })
+        .collect::<Vec<_>>();
+    let active_chainhooks = chainhooks.iter().rev().collect::<Vec<_>>();
+
+    let (triggered, evaluated, expired) = evaluate_bitcoin_chainhooks_on_chain_event(
+        &chain_event,
+        &active_chainhooks,
+        &Context::empty(),
+    );
+    let expected_uuids = active_chainhooks
+        .iter()
+        .filter(|chainhook| chainhook.end_block.is_none())
+        .map(|chainhook| chainhook.uuid.as_str())
+        .collect::<Vec<_>>();
+    let triggered_uuids = triggered
+        .iter()
+        .map(|trigger| trigger.chainhook.uuid.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(triggered_uuids, expected_uuids);
+    assert_eq!(evaluated.len(), chainhooks.len());
+    assert_eq!(expired.len(), chainhooks.len() - expected_uuids.len());
+}
+
+#[test_case(BitcoinNetwork::Mainnet, *b"X2", 666050)]
+#[test_case(BitcoinNetwork::Testnet, *b"T2", 2000000)]
+#[test_case(BitcoinNetwork::Regtest, *b"id", 100)]
+#[test_case(BitcoinNetwork::Signet, *b"T2", 100)]
+fn it_gets_canonical_network_parameters(
+    network: BitcoinNetwork,
+    magic_bytes: [u8; 2],
+    first_burnchain_block_height: u64,
+) {
+    let parameters = NetworkParameters::canonical(&network);
+    assert_eq!(parameters.stacks_magic_bytes, magic_bytes);
+    assert_eq!(
+        parameters.pox_config.first_burnchain_block_height,
+        first_burnchain_block_height
+    );
+    assert_eq!(get_stacks_canonical_magic_bytes(&network), magic_bytes);
+}
@@ -0,0 +1,527 @@
+use std::str::FromStr;
+
+use bitcoincore_rpc_json::bitcoin::{Address, AddressType, Network};
+use chainhook_types::{BitcoinNetwork, StacksNetwork};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ClarityName;
+use schemars::JsonSchema;
+use serde_json::Value as JsonValue;
+
+use super::bitcoin::{
+    BitcoinChainhookSpecification, BitcoinPredicateType, InputPredicate, OutputPredicate,
+    StacksOperations,
+};
+use super::stacks::{
+    StacksChainhookSpecification, StacksContractDeploymentPredicate, StacksPredicate,
+    StacksPrintEventBasedPredicate,
+};
+use super::types::{
+    validate_namespace, BlockIdentifierIndexRule, ChainhookSpecificationNetworkMap,
+    ExactMatchingRule,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The predicate is rejected at registration, or part of it can never match.
+    Error,
+    /// The predicate is accepted, but part of it is ignored, deprecated or unlikely to match.
+    Warning,
+}
+
+/// A problem found in a predicate by [check_predicate].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PredicateDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// Dot separated path of the field the diagnostic is about, e.g. `networks.mainnet.if_this`.
+    /// Empty when it is about the whole predicate.
+    pub path: String,
+    pub message: String,
+}
+
+impl PredicateDiagnostic {
+    pub fn error(path: &str, message: impl Into<String>) -> Self {
+        PredicateDiagnostic {
+            severity: DiagnosticSeverity::Error,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(path: &str, message: impl Into<String>) -> Self {
+        PredicateDiagnostic {
+            severity: DiagnosticSeverity::Warning,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+impl std::fmt::Display for PredicateDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+        };
+        match self.path.as_str() {
+            "" => write!(f, "{}: {}", severity, self.message),
+            path => write!(f, "{}: {}: {}", severity, path, self.message),
+        }
+    }
+}
+
+/// Checks a predicate in depth before it gets registered on `bitcoin_network` or
+/// `stacks_network`. On top of the errors [ChainhookSpecificationNetworkMap::validate] rejects
+/// predicates with, reports the fields that get ignored, the block ranges that can't match, the
+/// addresses and contract identifiers that are malformed or belong to another network, and the
+/// deprecated or unsupported scopes.
+pub fn check_predicate(
+    predicate: &JsonValue,
+    bitcoin_network: &BitcoinNetwork,
+    stacks_network: &StacksNetwork,
+) -> Vec<PredicateDiagnostic> {
+    let spec: ChainhookSpecificationNetworkMap = match serde_json::from_value(predicate.clone()) {
+        Ok(spec) => spec,
+        Err(e) => {
+            return vec![PredicateDiagnostic::error(
+                "",
+                format!("invalid predicate: {}", e),
+            )]
+        }
+    };
+    let mut diagnostics = vec![];
+    if let Ok(known_fields) = serde_json::to_value(&spec) {
+        check_unknown_fields(predicate, &known_fields, "", &mut diagnostics);
+    }
+    if let Err(e) = validate_namespace(spec.get_namespace()) {
+        diagnostics.push(PredicateDiagnostic::error("namespace", e));
+    }
+    match &spec {
+        ChainhookSpecificationNetworkMap::Bitcoin(data) => {
+            if !data.networks.contains_key(bitcoin_network) {
+                diagnostics.push(PredicateDiagnostic::error(
+                    "networks",
+                    format!("no specification for network {}", bitcoin_network),
+                ));
+            }
+            for (network, spec) in data.networks.iter() {
+                let path = format!("networks.{}", network);
+                check_bitcoin_specification(spec, network, &path, &mut diagnostics);
+            }
+        }
+        ChainhookSpecificationNetworkMap::Stacks(data) => {
+            if !data.networks.contains_key(stacks_network) {
+                diagnostics.push(PredicateDiagnostic::error(
+                    "networks",
+                    format!("no specification for network {}", stacks_network),
+                ));
+            }
+            for (network, spec) in data.networks.iter() {
+                let path = format!("networks.{}", network);
+                check_stacks_specification(spec, network, &path, &mut diagnostics);
+            }
+        }
+    }
+    diagnostics
+}
+
+fn join_path(path: &str, field: &str) -> String {
+    match path {
+        "" => field.to_string(),
+        path => format!("{}.{}", path, field),
+    }
+}
+
+/// Reports the fields of `value` that are missing from `known_fields`, its re-serialization:
+/// unknown fields are silently dropped when predicates get deserialized. Fields starting with
+/// `//` are comments and are not reported.
+fn check_unknown_fields(
+    value: &JsonValue,
+    known_fields: &JsonValue,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    match (value, known_fields) {
+        (JsonValue::Object(fields), JsonValue::Object(known_fields)) => {
+            for (field, value) in fields.iter() {
+                let field_path = join_path(path, field);
+                match known_fields.get(field) {
+                    Some(known_value) => {
+                        check_unknown_fields(value, known_value, &field_path, diagnostics)
+                    }
+                    // Optional fields set to null are not serialized back
+                    None if value.is_null() => {}
+                    // Comments, e.g. in the predicates generated from templates
+                    None if field.starts_with("//") => {}
+                    None => diagnostics.push(PredicateDiagnostic::warning(
+                        &field_path,
+                        format!("unknown field '{}' is ignored", field),
+                    )),
+                }
+            }
+        }
+        (JsonValue::Array(items), JsonValue::Array(known_items)) => {
+            for (index, (item, known_item)) in items.iter().zip(known_items.iter()).enumerate() {
+                let item_path = join_path(path, &index.to_string());
+                check_unknown_fields(item, known_item, &item_path, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_block_range(
+    blocks: &Option<Vec<u64>>,
+    start_block: &Option<u64>,
+    end_block: &Option<u64>,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    let Some(blocks) = blocks else {
+        return;
+    };
+    if blocks.is_empty() {
+        diagnostics.push(PredicateDiagnostic::warning(
+            &join_path(path, "blocks"),
+            "no block is listed, nothing will be scanned",
+        ));
+    }
+    if start_block.is_some() {
+        diagnostics.push(PredicateDiagnostic::warning(
+            &join_path(path, "start_block"),
+            "'start_block' is ignored when 'blocks' are listed",
+        ));
+    }
+    if let Some(end_block) = end_block {
+        if blocks.iter().any(|block| block > end_block) {
+            diagnostics.push(PredicateDiagnostic::warning(
+                &join_path(path, "blocks"),
+                format!(
+                    "the blocks higher than 'end_block' ({}) are never evaluated",
+                    end_block
+                ),
+            ));
+        }
+    }
+}
+
+fn check_bitcoin_specification(
+    spec: &BitcoinChainhookSpecification,
+    network: &BitcoinNetwork,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    if let Err(errors) = spec.validate() {
+        for e in errors.into_iter() {
+            diagnostics.push(PredicateDiagnostic::error(path, e));
+        }
+    }
+    check_block_range(
+        &spec.blocks,
+        &spec.start_block,
+        &spec.end_block,
+        path,
+        diagnostics,
+    );
+    check_bitcoin_predicate(
+        &spec.predicate,
+        network,
+        &join_path(path, "if_this"),
+        diagnostics,
+    );
+}
+
+fn check_bitcoin_predicate(
+    predicate: &BitcoinPredicateType,
+    network: &BitcoinNetwork,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    let (address, address_type, field) = match predicate {
+        BitcoinPredicateType::Outputs(OutputPredicate::P2pkh(ExactMatchingRule::Equals(
+            address,
+        ))) => (address, AddressType::P2pkh, "p2pkh"),
+        BitcoinPredicateType::Outputs(OutputPredicate::P2sh(ExactMatchingRule::Equals(
+            address,
+        ))) => (address, AddressType::P2sh, "p2sh"),
+        BitcoinPredicateType::Outputs(OutputPredicate::P2wpkh(ExactMatchingRule::Equals(
+            address,
+        ))) => (address, AddressType::P2wpkh, "p2wpkh"),
+        BitcoinPredicateType::Outputs(OutputPredicate::P2wsh(ExactMatchingRule::Equals(
+            address,
+        ))) => (address, AddressType::P2wsh, "p2wsh"),
+        BitcoinPredicateType::Inputs(InputPredicate::WitnessScript(_)) => {
+            diagnostics.push(PredicateDiagnostic::error(
+                &join_path(path, "witness_script"),
+                "inputs can't be matched by witness script yet",
+            ));
+            return;
+        }
+        BitcoinPredicateType::StacksProtocol(StacksOperations::StackerRewarded) => {
+            diagnostics.push(PredicateDiagnostic::warning(
+                &join_path(path, "operation"),
+                "operation 'stacker_rewarded' is deprecated, it matches the same transactions as 'block_committed'",
+            ));
+            return;
+        }
+        BitcoinPredicateType::OrdinalsProtocol(_) => {
+            diagnostics.push(PredicateDiagnostic::warning(
+                &join_path(path, "scope"),
+                "scope 'ordinals_protocol' is deprecated, inscriptions are not indexed by chainhook: use ordhook to observe them",
+            ));
+            return;
+        }
+        _ => return,
+    };
+    let path = join_path(path, &format!("{}.equals", field));
+    let address = match Address::from_str(address) {
+        Ok(address) => address,
+        Err(e) => {
+            diagnostics.push(PredicateDiagnostic::error(
+                &path,
+                format!("invalid Bitcoin address {}: {}", address, e),
+            ));
+            return;
+        }
+    };
+    if !address.is_valid_for_network(get_bitcoin_network(network)) {
+        diagnostics.push(PredicateDiagnostic::error(
+            &path,
+            format!("address is not valid on network {}", network),
+        ));
+        return;
+    }
+    if address.assume_checked().address_type() != Some(address_type) {
+        diagnostics.push(PredicateDiagnostic::error(
+            &path,
+            format!("address is not a {} address", address_type),
+        ));
+    }
+}
+
+fn get_bitcoin_network(network: &BitcoinNetwork) -> Network {
+    match network {
+        BitcoinNetwork::Mainnet => Network::Bitcoin,
+        BitcoinNetwork::Testnet => Network::Testnet,
+        BitcoinNetwork::Signet => Network::Signet,
+        BitcoinNetwork::Regtest => Network::Regtest,
+    }
+}
+
+fn check_stacks_specification(
+    spec: &StacksChainhookSpecification,
+    network: &StacksNetwork,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    if let Err(errors) = spec.validate() {
+        for e in errors.into_iter() {
+            diagnostics.push(PredicateDiagnostic::error(path, e));
+        }
+    }
+    check_block_range(
+        &spec.blocks,
+        &spec.start_block,
+        &spec.end_block,
+        path,
+        diagnostics,
+    );
+    check_stacks_predicate(
+        &spec.predicate,
+        network,
+        &spec.end_block,
+        &join_path(path, "if_this"),
+        diagnostics,
+    );
+}
+
+fn check_stacks_predicate(
+    predicate: &StacksPredicate,
+    network: &StacksNetwork,
+    end_block: &Option<u64>,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    match predicate {
+        StacksPredicate::BlockHeight(rule) => {
+            let lowest_matching_height = match rule {
+                BlockIdentifierIndexRule::Equals(height) => Some(*height),
+                BlockIdentifierIndexRule::HigherThan(height) => height.checked_add(1),
+                BlockIdentifierIndexRule::LowerThan(_) => Some(0),
+                BlockIdentifierIndexRule::Between(lhs, rhs) if rhs.saturating_sub(*lhs) > 1 => {
+                    lhs.checked_add(1)
+                }
+                BlockIdentifierIndexRule::Between(_, _) => None,
+            };
+            let never_matching = match (lowest_matching_height, end_block) {
+                (None, _) => true,
+                (Some(height), Some(end_block)) => height > *end_block,
+                (Some(_), None) => false,
+            };
+            if never_matching {
+                diagnostics.push(PredicateDiagnostic::warning(
+                    path,
+                    "no block height evaluated by the predicate matches this rule",
+                ));
+            }
+        }
+        StacksPredicate::ContractDeployment(StacksContractDeploymentPredicate::Deployer(
+            deployer,
+        )) if deployer != "*" => check_stacks_address_network(
+            deployer,
+            network,
+            &join_path(path, "deployer"),
+            diagnostics,
+        ),
+        StacksPredicate::ContractCall(predicate) => check_stacks_address_network(
+            &predicate.contract_identifier,
+            network,
+            &join_path(path, "contract_identifier"),
+            diagnostics,
+        ),
+        StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::Contains {
+            contract_identifier,
+            ..
+        })
+        | StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::MatchesRegex {
+            contract_identifier,
+            ..
+        }) if contract_identifier != "*" => check_stacks_address_network(
+            contract_identifier,
+            network,
+            &join_path(path, "contract_identifier"),
+            diagnostics,
+        ),
+        StacksPredicate::FtEvent(predicate) => {
+            check_asset_identifier(&predicate.asset_identifier, network, path, diagnostics);
+            check_event_actions(
+                &predicate.actions,
+                &["mint", "transfer", "burn"],
+                path,
+                diagnostics,
+            );
+        }
+        StacksPredicate::NftEvent(predicate) => {
+            check_asset_identifier(&predicate.asset_identifier, network, path, diagnostics);
+            check_event_actions(
+                &predicate.actions,
+                &["mint", "transfer", "burn"],
+                path,
+                diagnostics,
+            );
+        }
+        StacksPredicate::StxEvent(predicate) => check_event_actions(
+            &predicate.actions,
+            &["mint", "transfer", "lock", "burn"],
+            path,
+            diagnostics,
+        ),
+        StacksPredicate::BitcoinAnchored(predicate) => {
+            let bitcoin_network = match network {
+                StacksNetwork::Mainnet => BitcoinNetwork::Mainnet,
+                StacksNetwork::Testnet => BitcoinNetwork::Testnet,
+                StacksNetwork::Devnet | StacksNetwork::Simnet => BitcoinNetwork::Regtest,
+            };
+            check_bitcoin_predicate(
+                &predicate.bitcoin,
+                &bitcoin_network,
+                &join_path(path, "bitcoin"),
+                diagnostics,
+            );
+            check_stacks_predicate(
+                &predicate.stacks,
+                network,
+                end_block,
+                &join_path(path, "stacks"),
+                diagnostics,
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Reports the Stacks addresses, or contract identifiers, of another network than `network`.
+/// Malformed ones are already rejected by the validation of the predicate.
+fn check_stacks_address_network(
+    principal: &str,
+    network: &StacksNetwork,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    let expected_prefixes = match network {
+        StacksNetwork::Mainnet => ["SP", "SM"],
+        StacksNetwork::Testnet | StacksNetwork::Devnet | StacksNetwork::Simnet => ["ST", "SN"],
+    };
+    if !expected_prefixes
+        .iter()
+        .any(|prefix| principal.starts_with(prefix))
+    {
+        diagnostics.push(PredicateDiagnostic::error(
+            path,
+            format!("{} doesn't belong to network {}", principal, network),
+        ));
+    }
+}
+
+/// Checks an asset identifier formatted as `<contract identifier>::<asset name>`.
+fn check_asset_identifier(
+    asset_identifier: &str,
+    network: &StacksNetwork,
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    let path = join_path(path, "asset_identifier");
+    let Some((contract_identifier, asset_name)) = asset_identifier.split_once("::") else {
+        diagnostics.push(PredicateDiagnostic::error(
+            &path,
+            "expected an asset identifier formatted as <contract identifier>::<asset name>",
+        ));
+        return;
+    };
+    if let Err(e) = QualifiedContractIdentifier::parse(contract_identifier) {
+        diagnostics.push(PredicateDiagnostic::error(
+            &path,
+            format!("invalid contract identifier: {}", e),
+        ));
+        return;
+    }
+    if let Err(e) = ClarityName::try_from(asset_name.to_string()) {
+        diagnostics.push(PredicateDiagnostic::error(
+            &path,
+            format!("invalid asset name: {:?}", e),
+        ));
+        return;
+    }
+    check_stacks_address_network(contract_identifier, network, &path, diagnostics);
+}
+
+fn check_event_actions(
+    actions: &[String],
+    known_actions: &[&str],
+    path: &str,
+    diagnostics: &mut Vec<PredicateDiagnostic>,
+) {
+    let path = join_path(path, "actions");
+    if actions.is_empty() {
+        diagnostics.push(PredicateDiagnostic::warning(
+            &path,
+            "no action is listed, no event will match",
+        ));
+    }
+    for action in actions.iter() {
+        if !known_actions.contains(&action.as_str()) {
+            diagnostics.push(PredicateDiagnostic::warning(
+                &path,
+                format!(
+                    "unknown action '{}', expected one of {}",
+                    action,
+                    known_actions.join(", ")
+                ),
+            ));
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod bitcoin;
+pub mod diagnostics;
+pub mod schemas;
 pub mod stacks;
 pub mod types;
 
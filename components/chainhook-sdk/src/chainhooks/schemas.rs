@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+
+use chainhook_types::{
+    BitcoinBlockMetadata, BitcoinNetwork, BlockIdentifier, Brc20Operation, ContractInterface,
+    FTBurnEventData, FTMintEventData, FTTransferEventData, Operation, OrdinalOperation,
+    STXBurnEventData, STXLockEventData, STXMintEventData, STXTransferEventData,
+    StacksBaseChainOperation, StacksNetwork, StacksTransactionEvent,
+    StacksTransactionEventPosition, StacksTransactionExecutionCost, StacksTransactionKind,
+    StacksTransactionPosition, TransactionIdentifier, TxOut,
+};
+use schemars::{schema_for, JsonSchema};
+use serde_json::Value as JsonValue;
+
+use super::bitcoin::BitcoinPredicateType;
+use super::stacks::StacksPredicate;
+use super::types::ChainhookSpecificationNetworkMap;
+
+/// Names of the JSON Schemas returned by [json_schema].
+pub const JSON_SCHEMA_NAMES: [&str; 3] = ["predicate", "bitcoin_payload", "stacks_payload"];
+
+/// Returns the JSON Schema `name`, describing either the predicates registered with chainhook or
+/// the payloads delivered to their actions, before any `include_fields`, `transform` or
+/// `payload_envelope` is applied.
+pub fn json_schema(name: &str) -> Option<JsonValue> {
+    let schema = match name {
+        "predicate" => schema_for!(ChainhookSpecificationNetworkMap),
+        "bitcoin_payload" => schema_for!(BitcoinOccurrenceSchema),
+        "stacks_payload" => schema_for!(StacksOccurrenceSchema),
+        _ => return None,
+    };
+    Some(json!(schema))
+}
+
+/// Payload delivered for an occurrence of a Bitcoin predicate, see
+/// [super::bitcoin::serialize_bitcoin_payload_to_json].
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrence")]
+pub struct BitcoinOccurrenceSchema {
+    pub apply: Vec<BitcoinOccurrenceBlockSchema>,
+    pub rollback: Vec<BitcoinOccurrenceBlockSchema>,
+    /// Id of the delivery, identical for the deliveries of the same occurrence.
+    pub delivery_id: String,
+    pub chainhook: BitcoinOccurrenceChainhookSchema,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceChainhook")]
+pub struct BitcoinOccurrenceChainhookSchema {
+    pub uuid: String,
+    pub network: BitcoinNetwork,
+    pub predicate: BitcoinPredicateType,
+    /// Whether the predicate was streaming new blocks when the occurrence was delivered, rather
+    /// than scanning past ones.
+    pub is_streaming_blocks: bool,
+    pub confirmation_depth: Option<u64>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceBlock")]
+pub struct BitcoinOccurrenceBlockSchema {
+    pub block_identifier: BlockIdentifier,
+    pub parent_block_identifier: BlockIdentifier,
+    pub timestamp: u32,
+    /// Transactions of the block matching the predicate.
+    pub transactions: Vec<BitcoinOccurrenceTransactionSchema>,
+    pub metadata: BitcoinBlockMetadata,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceTransaction")]
+pub struct BitcoinOccurrenceTransactionSchema {
+    pub transaction_identifier: TransactionIdentifier,
+    pub operations: Vec<Operation>,
+    pub metadata: BitcoinOccurrenceTransactionMetadataSchema,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceTransactionMetadata")]
+pub struct BitcoinOccurrenceTransactionMetadataSchema {
+    pub fee: u64,
+    pub index: u32,
+    /// Empty unless the predicate sets `include_inputs`.
+    pub inputs: Vec<BitcoinOccurrenceInputSchema>,
+    /// Empty unless the predicate sets `include_outputs`.
+    pub outputs: Vec<TxOut>,
+    pub stacks_operations: Vec<StacksBaseChainOperation>,
+    pub ordinal_operations: Vec<OrdinalOperation>,
+    pub brc20_operation: Option<Brc20Operation>,
+    /// Set when the predicate sets `include_proof`.
+    pub proof: Option<String>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceInput")]
+pub struct BitcoinOccurrenceInputSchema {
+    pub previous_output: BitcoinOccurrenceOutPointSchema,
+    pub script_sig: String,
+    pub sequence: u32,
+    /// Empty unless the predicate sets `include_witness`.
+    pub witness: Vec<String>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "BitcoinOccurrenceOutPoint")]
+pub struct BitcoinOccurrenceOutPointSchema {
+    /// Hash of the transaction of the output spent.
+    pub txin: String,
+    pub vout: u32,
+    pub value: u64,
+    pub block_height: u64,
+}
+
+/// Payload delivered for an occurrence of a Stacks predicate, see
+/// [super::stacks::serialize_stacks_payload_to_json].
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrence")]
+pub struct StacksOccurrenceSchema {
+    pub apply: Vec<StacksOccurrenceBlockSchema>,
+    pub rollback: Vec<StacksOccurrenceBlockSchema>,
+    /// Id of the delivery, identical for the deliveries of the same occurrence.
+    pub delivery_id: String,
+    pub chainhook: StacksOccurrenceChainhookSchema,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceChainhook")]
+pub struct StacksOccurrenceChainhookSchema {
+    pub uuid: String,
+    pub network: StacksNetwork,
+    pub predicate: StacksPredicate,
+    /// Whether the predicate was streaming new blocks when the occurrence was delivered, rather
+    /// than scanning past ones.
+    pub is_streaming_blocks: bool,
+    pub confirmation_depth: Option<u64>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceBlock")]
+pub struct StacksOccurrenceBlockSchema {
+    pub block_identifier: BlockIdentifier,
+    pub parent_block_identifier: BlockIdentifier,
+    pub timestamp: i64,
+    /// Transactions of the block matching the predicate.
+    pub transactions: Vec<StacksOccurrenceTransactionSchema>,
+    /// Metadata of the block, or of the microblock.
+    pub metadata: JsonValue,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceTransaction")]
+pub struct StacksOccurrenceTransactionSchema {
+    pub transaction_identifier: TransactionIdentifier,
+    pub operations: Vec<Operation>,
+    pub metadata: StacksOccurrenceTransactionMetadataSchema,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceTransactionMetadata")]
+pub struct StacksOccurrenceTransactionMetadataSchema {
+    pub success: bool,
+    pub raw_tx: String,
+    /// Hex encoded Clarity value, decoded to JSON when the predicate sets
+    /// `decode_clarity_values`.
+    pub result: JsonValue,
+    pub sender: String,
+    pub nonce: u64,
+    pub fee: u64,
+    pub kind: StacksTransactionKind,
+    pub receipt: StacksOccurrenceReceiptSchema,
+    pub description: String,
+    pub sponsor: Option<String>,
+    pub execution_cost: Option<StacksTransactionExecutionCost>,
+    pub position: StacksTransactionPosition,
+    /// Set when the predicate sets `include_contract_abi`.
+    pub contract_abi: Option<ContractInterface>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceReceipt")]
+pub struct StacksOccurrenceReceiptSchema {
+    pub mutated_contracts_radius: HashSet<String>,
+    pub mutated_assets_radius: HashSet<String>,
+    pub contract_calls_stack: HashSet<String>,
+    pub events: Vec<StacksOccurrenceEventSchema>,
+}
+
+/// Event of a transaction, whose Clarity values are decoded to JSON when the predicate sets
+/// `decode_clarity_values`.
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceEvent", untagged)]
+pub enum StacksOccurrenceEventSchema {
+    Raw(StacksTransactionEvent),
+    Decoded(StacksOccurrenceDecodedEventSchema),
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceDecodedEvent")]
+pub struct StacksOccurrenceDecodedEventSchema {
+    #[schemars(flatten)]
+    pub event_payload: StacksOccurrenceDecodedEventPayloadSchema,
+    pub position: StacksTransactionEventPosition,
+}
+
+#[derive(JsonSchema)]
+#[schemars(
+    rename = "StacksOccurrenceDecodedEventPayload",
+    tag = "type",
+    content = "data"
+)]
+pub enum StacksOccurrenceDecodedEventPayloadSchema {
+    STXTransferEvent(STXTransferEventData),
+    STXMintEvent(STXMintEventData),
+    STXLockEvent(STXLockEventData),
+    STXBurnEvent(STXBurnEventData),
+    NFTTransferEvent(StacksOccurrenceNFTTransferEventSchema),
+    NFTMintEvent(StacksOccurrenceNFTMintEventSchema),
+    NFTBurnEvent(StacksOccurrenceNFTBurnEventSchema),
+    FTTransferEvent(FTTransferEventData),
+    FTMintEvent(FTMintEventData),
+    FTBurnEvent(FTBurnEventData),
+    DataVarSetEvent(StacksOccurrenceDataVarSetEventSchema),
+    DataMapInsertEvent(StacksOccurrenceDataMapInsertEventSchema),
+    DataMapUpdateEvent(StacksOccurrenceDataMapUpdateEventSchema),
+    DataMapDeleteEvent(StacksOccurrenceDataMapDeleteEventSchema),
+    SmartContractEvent(StacksOccurrenceSmartContractEventSchema),
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceNFTTransferEvent")]
+pub struct StacksOccurrenceNFTTransferEventSchema {
+    pub asset_class_identifier: String,
+    pub asset_identifier: JsonValue,
+    pub sender: String,
+    pub recipient: String,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceNFTMintEvent")]
+pub struct StacksOccurrenceNFTMintEventSchema {
+    pub asset_class_identifier: String,
+    pub asset_identifier: JsonValue,
+    pub recipient: String,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceNFTBurnEvent")]
+pub struct StacksOccurrenceNFTBurnEventSchema {
+    pub asset_class_identifier: String,
+    pub asset_identifier: JsonValue,
+    pub sender: String,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceDataVarSetEvent")]
+pub struct StacksOccurrenceDataVarSetEventSchema {
+    pub contract_identifier: String,
+    pub var: String,
+    pub new_value: JsonValue,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceDataMapInsertEvent")]
+pub struct StacksOccurrenceDataMapInsertEventSchema {
+    pub contract_identifier: String,
+    pub map: String,
+    pub inserted_key: JsonValue,
+    pub inserted_value: JsonValue,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceDataMapUpdateEvent")]
+pub struct StacksOccurrenceDataMapUpdateEventSchema {
+    pub contract_identifier: String,
+    pub map: String,
+    pub key: JsonValue,
+    pub new_value: JsonValue,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceDataMapDeleteEvent")]
+pub struct StacksOccurrenceDataMapDeleteEventSchema {
+    pub contract_identifier: String,
+    pub map: String,
+    pub deleted_key: JsonValue,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "StacksOccurrenceSmartContractEvent")]
+pub struct StacksOccurrenceSmartContractEventSchema {
+    pub contract_identifier: String,
+    pub topic: String,
+    pub value: JsonValue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_schema, JSON_SCHEMA_NAMES};
+
+    #[test]
+    fn it_generates_json_schemas() {
+        for name in JSON_SCHEMA_NAMES {
+            let schema = json_schema(name).unwrap();
+            assert!(schema["definitions"].is_object());
+        }
+        let schema = json_schema("bitcoin_payload").unwrap();
+        assert!(
+            schema["definitions"]["BitcoinOccurrenceOutPoint"]["properties"]["txin"].is_object()
+        );
+        assert!(json_schema("unknown").is_none());
+    }
+}
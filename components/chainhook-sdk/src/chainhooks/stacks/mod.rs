@@ -1,13 +1,24 @@
 use crate::observer::EventObserverConfig;
-use crate::utils::{AbstractStacksBlock, Context, MAX_BLOCK_HEIGHTS_ENTRIES};
+use crate::utils::aws::AwsMessage;
+use crate::utils::cloud_events::{payload_content_type, wrap_payload};
+use crate::utils::kafka::KafkaMessage;
+use crate::utils::nats::NatsMessage;
+use crate::utils::object_storage::ObjectStoreUpload;
+use crate::utils::postgres::PostgresRecord;
+use crate::utils::projection::{project_payload_fields, validate_include_fields};
+use crate::utils::transform::{apply_transform, validate_transform};
+use crate::utils::{
+    build_http_post_request, AbstractStacksBlock, Context, MAX_BLOCK_HEIGHTS_ENTRIES,
+};
 
+use super::bitcoin::BitcoinPredicateType;
 use super::types::{
-    append_error_context, BlockIdentifierIndexRule, ChainhookInstance, ExactMatchingRule,
-    HookAction,
+    append_error_context, build_delivery_id, BlockIdentifierIndexRule, ChainhookInstance,
+    ExactMatchingRule, FileHook, HookAction, KafkaMessageKey, PayloadEnvelope,
 };
 use super::types::validate_txid;
 use chainhook_types::{
-    BlockIdentifier, StacksChainEvent, StacksNetwork, StacksTransactionData,
+    BitcoinBlockData, BlockIdentifier, Chain, StacksChainEvent, StacksNetwork, StacksTransactionData,
     StacksTransactionEvent, StacksTransactionEventPayload, StacksTransactionKind,
     TransactionIdentifier,
 };
@@ -18,12 +29,11 @@ use clarity::vm::types::{
 use clarity::vm::ClarityName;
 use hiro_system_kit::slog;
 use regex::Regex;
-use reqwest::{Client, Method};
 use schemars::JsonSchema;
 use serde_json::Value as JsonValue;
 use std::collections::{BTreeMap, HashMap};
 use std::io::Cursor;
-use std::time::Duration;
+use std::sync::OnceLock;
 
 use reqwest::RequestBuilder;
 
@@ -43,10 +53,37 @@ pub struct StacksChainhookSpecification {
     pub decode_clarity_values: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include_contract_abi: Option<bool>,
+    /// Dot separated paths of the block fields to keep in the delivered payload.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_fields: Option<Vec<String>>,
+    /// jq expression applied to the occurrence payload before it is delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    /// Maximum number of matching blocks coalesced into a single delivery while scanning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+    /// Maximum time, in milliseconds, a matching block is held back waiting for its batch
+    /// to fill up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_batch_wait_ms: Option<u64>,
+    /// Minutes without any occurrence delivered after which a heartbeat, carrying the height of
+    /// the last block evaluated, is posted to the `http_post` action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_minutes: Option<u64>,
     #[serde(rename = "if_this")]
     pub predicate: StacksPredicate,
     #[serde(rename = "then_that")]
     pub action: HookAction,
+    /// Action receiving the blocks rolled back, delivered apart from the blocks applied.
+    /// Defaults to `then_that`.
+    #[serde(
+        rename = "then_that_on_rollback",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rollback_action: Option<HookAction>,
+    /// Envelope wrapping the delivered payloads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_envelope: Option<PayloadEnvelope>,
 }
 
 impl StacksChainhookSpecification {
@@ -56,11 +93,18 @@ impl StacksChainhookSpecification {
             start_block: None,
             end_block: None,
             expire_after_occurrence: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
             capture_all_events: None,
             include_contract_abi: None,
             decode_clarity_values: None,
             predicate,
             action,
+            rollback_action: None,
+            payload_envelope: None,
         }
     }
 
@@ -99,14 +143,84 @@ impl StacksChainhookSpecification {
         self
     }
 
+    pub fn transform(&mut self, filter: &str) -> &mut Self {
+        self.transform = Some(filter.to_string());
+        self
+    }
+
+    pub fn include_fields(&mut self, fields: Vec<String>) -> &mut Self {
+        self.include_fields = Some(fields);
+        self
+    }
+
+    pub fn max_batch_size(&mut self, max_batch_size: u64) -> &mut Self {
+        self.max_batch_size = Some(max_batch_size);
+        self
+    }
+
+    pub fn max_batch_wait_ms(&mut self, max_batch_wait_ms: u64) -> &mut Self {
+        self.max_batch_wait_ms = Some(max_batch_wait_ms);
+        self
+    }
+
+    pub fn heartbeat_interval_minutes(&mut self, heartbeat_interval_minutes: u64) -> &mut Self {
+        self.heartbeat_interval_minutes = Some(heartbeat_interval_minutes);
+        self
+    }
+
+    pub fn rollback_action(&mut self, rollback_action: HookAction) -> &mut Self {
+        self.rollback_action = Some(rollback_action);
+        self
+    }
+
+    pub fn payload_envelope(&mut self, payload_envelope: PayloadEnvelope) -> &mut Self {
+        self.payload_envelope = Some(payload_envelope);
+        self
+    }
+
     pub fn validate(&self) -> Result<(), Vec<String>> {
         let mut errors = vec![];
         if let Err(e) = self.action.validate() {
             errors.append(&mut append_error_context("invalid 'then_that' value", e));
         }
+        if let Some(ref rollback_action) = self.rollback_action {
+            if let Err(e) = rollback_action.validate() {
+                errors.append(&mut append_error_context(
+                    "invalid 'then_that_on_rollback' value",
+                    e,
+                ));
+            }
+        }
         if let Err(e) = self.predicate.validate() {
             errors.append(&mut append_error_context("invalid 'if_this' value", e));
         }
+        if let Some(ref fields) = self.include_fields {
+            if let Err(e) = validate_include_fields(fields) {
+                errors.push(e);
+            }
+        }
+        if let Some(ref transform) = self.transform {
+            if let Err(e) = validate_transform(transform) {
+                errors.push(e);
+            }
+        }
+        if let Some(0) = self.max_batch_size {
+            errors.push(
+                "Chainhook specification field `max_batch_size` should be greater than 0.".into(),
+            );
+        }
+        if let Some(heartbeat_interval_minutes) = self.heartbeat_interval_minutes {
+            if heartbeat_interval_minutes == 0 {
+                errors.push(
+                    "Chainhook specification field `heartbeat_interval_minutes` should be greater than 0.".into(),
+                );
+            }
+            if self.action.http_hooks().is_empty() {
+                errors.push(
+                    "Chainhook specification field `heartbeat_interval_minutes` requires an `http_post` action.".into(),
+                );
+            }
+        }
 
         if let Some(end_block) = self.end_block {
             let start_block = self.start_block.unwrap_or(0);
@@ -171,6 +285,17 @@ pub struct StacksChainhookSpecificationNetworkMap {
     pub uuid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner_uuid: Option<String>,
+    /// Free-form labels predicates can be filtered by when listed through the predicates API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    /// Key-value pairs recording e.g. the owner or the purpose of the predicate, which
+    /// predicates can be filtered by when listed through the predicates API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+    /// Namespace the predicate is registered under, isolating it from the predicates of other
+    /// namespaces. Predicates without a namespace belong to the `default` one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     pub name: String,
     pub version: u32,
     pub networks: BTreeMap<StacksNetwork, StacksChainhookSpecification>,
@@ -188,6 +313,9 @@ impl StacksChainhookSpecificationNetworkMap {
         Ok(StacksChainhookInstance {
             uuid: self.uuid,
             owner_uuid: self.owner_uuid,
+            labels: self.labels,
+            metadata: self.metadata,
+            namespace: self.namespace,
             name: self.name,
             network: network.clone(),
             version: self.version,
@@ -197,9 +325,16 @@ impl StacksChainhookSpecificationNetworkMap {
             capture_all_events: spec.capture_all_events,
             decode_clarity_values: spec.decode_clarity_values,
             expire_after_occurrence: spec.expire_after_occurrence,
+            max_batch_size: spec.max_batch_size,
+            max_batch_wait_ms: spec.max_batch_wait_ms,
+            heartbeat_interval_minutes: spec.heartbeat_interval_minutes,
+            include_fields: spec.include_fields,
+            transform: spec.transform,
             include_contract_abi: spec.include_contract_abi,
             predicate: spec.predicate,
             action: spec.action,
+            rollback_action: spec.rollback_action,
+            payload_envelope: spec.payload_envelope,
             enabled: false,
             expired_at: None,
         })
@@ -211,6 +346,12 @@ pub struct StacksChainhookInstance {
     pub uuid: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     pub name: String,
     pub network: StacksNetwork,
     pub version: u32,
@@ -227,6 +368,20 @@ pub struct StacksChainhookInstance {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub decode_clarity_values: Option<bool>,
     pub include_contract_abi: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_fields: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_wait_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_action: Option<HookAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_envelope: Option<PayloadEnvelope>,
     #[serde(rename = "predicate")]
     pub predicate: StacksPredicate,
     pub action: HookAction,
@@ -236,15 +391,44 @@ pub struct StacksChainhookInstance {
 
 impl StacksChainhookInstance {
     pub fn key(&self) -> String {
-        ChainhookInstance::stacks_key(&self.uuid)
+        ChainhookInstance::namespaced_key(self.namespace.as_deref(), &self.uuid)
     }
 
-    pub fn is_predicate_targeting_block_header(&self) -> bool {
-        match &self.predicate {
-            StacksPredicate::BlockHeight(_) => true,
-            _ => false,
+    /// Returns the specification the predicate was registered with, for its network only.
+    pub fn into_specification_network_map(self) -> StacksChainhookSpecificationNetworkMap {
+        let spec = StacksChainhookSpecification {
+            blocks: self.blocks,
+            start_block: self.start_block,
+            end_block: self.end_block,
+            expire_after_occurrence: self.expire_after_occurrence,
+            capture_all_events: self.capture_all_events,
+            decode_clarity_values: self.decode_clarity_values,
+            include_contract_abi: self.include_contract_abi,
+            include_fields: self.include_fields,
+            transform: self.transform,
+            max_batch_size: self.max_batch_size,
+            max_batch_wait_ms: self.max_batch_wait_ms,
+            heartbeat_interval_minutes: self.heartbeat_interval_minutes,
+            predicate: self.predicate,
+            action: self.action,
+            rollback_action: self.rollback_action,
+            payload_envelope: self.payload_envelope,
+        };
+        StacksChainhookSpecificationNetworkMap {
+            uuid: self.uuid,
+            owner_uuid: self.owner_uuid,
+            labels: self.labels,
+            metadata: self.metadata,
+            namespace: self.namespace,
+            name: self.name,
+            version: self.version,
+            networks: BTreeMap::from([(self.network, spec)]),
         }
     }
+
+    pub fn is_predicate_targeting_block_header(&self) -> bool {
+        self.predicate.is_targeting_block_header()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -259,9 +443,20 @@ pub enum StacksPredicate {
     NftEvent(StacksNftEventBasedPredicate),
     StxEvent(StacksStxEventBasedPredicate),
     Txid(ExactMatchingRule),
+    BitcoinAnchored(StacksBitcoinAnchoredPredicate),
 }
 
 impl StacksPredicate {
+    pub fn is_targeting_block_header(&self) -> bool {
+        match self {
+            StacksPredicate::BlockHeight(_) => true,
+            StacksPredicate::BitcoinAnchored(predicate) => {
+                predicate.stacks.is_targeting_block_header()
+            }
+            _ => false,
+        }
+    }
+
     pub fn validate(&self) -> Result<(), Vec<String>> {
         match self {
             StacksPredicate::BlockHeight(height) => {
@@ -307,11 +502,112 @@ impl StacksPredicate {
                     ));
                 }
             }
+            StacksPredicate::BitcoinAnchored(predicate) => {
+                if let Err(e) = predicate.validate() {
+                    return Err(append_error_context(
+                        "invalid predicate for scope 'bitcoin_anchored'",
+                        e,
+                    ));
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Triggers on Stacks blocks whose Bitcoin anchor block contains at least one transaction
+/// matching `bitcoin`, and evaluates `stacks` against the Stacks block itself.
+///
+/// The Bitcoin side is resolved using the blocks tracked by the observer (see [BitcoinAnchorContext]).
+/// When the anchor block is not known, the Stacks block is not considered a match.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StacksBitcoinAnchoredPredicate {
+    pub bitcoin: BitcoinPredicateType,
+    pub stacks: Box<StacksPredicate>,
+}
+
+impl StacksBitcoinAnchoredPredicate {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if let Err(e) = self.bitcoin.validate() {
+            errors.append(&mut append_error_context("invalid 'bitcoin' value", e));
+        }
+        if let StacksPredicate::BitcoinAnchored(_) = self.stacks.as_ref() {
+            errors.push("invalid 'stacks' value: 'bitcoin_anchored' predicates can not be nested".into());
+        } else if let Err(e) = self.stacks.validate() {
+            errors.append(&mut append_error_context("invalid 'stacks' value", e));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Bitcoin blocks available when evaluating Stacks predicates, indexed by block identifier.
+/// Used to resolve the burnchain side of [StacksPredicate::BitcoinAnchored] predicates.
+#[derive(Default)]
+pub struct BitcoinAnchorContext<'a> {
+    bitcoin_blocks: HashMap<&'a BlockIdentifier, &'a BitcoinBlockData>,
+}
+
+impl<'a> BitcoinAnchorContext<'a> {
+    pub fn empty() -> BitcoinAnchorContext<'a> {
+        BitcoinAnchorContext::default()
+    }
+
+    pub fn new<I>(blocks: I) -> BitcoinAnchorContext<'a>
+    where
+        I: IntoIterator<Item = &'a BitcoinBlockData>,
+    {
+        BitcoinAnchorContext {
+            bitcoin_blocks: blocks
+                .into_iter()
+                .map(|block| (&block.block_identifier, block))
+                .collect(),
+        }
+    }
+
+    pub fn get_block(&self, block_identifier: &BlockIdentifier) -> Option<&'a BitcoinBlockData> {
+        self.bitcoin_blocks.get(block_identifier).copied()
+    }
+
+    /// Returns true if the Bitcoin block anchoring `block` is known and includes a transaction
+    /// matching `predicate`.
+    pub fn is_anchor_matching(
+        &self,
+        block: &dyn AbstractStacksBlock,
+        predicate: &BitcoinPredicateType,
+        ctx: &Context,
+    ) -> bool {
+        let Some(anchor_identifier) = block.get_bitcoin_anchor_block_identifier() else {
+            return false;
+        };
+        match self.get_block(anchor_identifier) {
+            Some(anchor_block) => match predicate {
+                BitcoinPredicateType::Block => true,
+                _ => anchor_block
+                    .transactions
+                    .iter()
+                    .any(|tx| predicate.evaluate_transaction_predicate(tx, ctx)),
+            },
+            None => {
+                ctx.try_log(|logger| {
+                    slog::debug!(
+                        logger,
+                        "Bitcoin anchor block {} unknown, skipping evaluation of block {}",
+                        anchor_identifier,
+                        block.get_identifier()
+                    )
+                });
+                false
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct StacksContractCallBasedPredicate {
@@ -457,26 +753,138 @@ pub struct StacksTriggerChainhook<'a> {
     pub chainhook: &'a StacksChainhookInstance,
     pub apply: Vec<(Vec<&'a StacksTransactionData>, &'a dyn AbstractStacksBlock)>,
     pub rollback: Vec<(Vec<&'a StacksTransactionData>, &'a dyn AbstractStacksBlock)>,
+    /// Position of the delivering action among the predicate's actions, see
+    /// [HookAction::Multiple].
+    pub action_index: usize,
+    /// Number of blocks, including itself, a block needs in the canonical chain to be confirmed
+    /// by the observer, reported in the payload.
+    pub confirmation_depth: Option<u64>,
+    /// Sequence of the reorg the occurrence was evaluated on, or 0 outside of reorgs. See
+    /// [build_delivery_id].
+    pub reorg_sequence: u64,
+    /// Action delivering the occurrence, resolved on the first call to `get_action` so that
+    /// routed actions are only evaluated once per occurrence.
+    pub resolved_action: OnceLock<&'a HookAction>,
 }
 
-#[derive(Clone, Debug)]
+impl<'a> StacksTriggerChainhook<'a> {
+    /// Returns the most recent block applied (or rolled back, if no block was applied).
+    pub fn get_tip_block_identifier(&self) -> Option<&BlockIdentifier> {
+        self.apply
+            .last()
+            .or(self.rollback.last())
+            .map(|(_, block)| block.get_identifier())
+    }
+
+    /// Returns the id of the delivery of this occurrence. See [build_delivery_id]. The ids of the
+    /// deliveries to the actions following the first one of a predicate are suffixed with the
+    /// position of the action.
+    pub fn get_delivery_id(&self) -> String {
+        let delivery_id = build_delivery_id(
+            &self.chainhook.uuid,
+            self.get_tip_block_identifier(),
+            self.reorg_sequence,
+        );
+        match self.action_index {
+            0 => delivery_id,
+            action_index => format!("{}:{}", delivery_id, action_index),
+        }
+    }
+
+    /// Returns the predicate's `rollback_action` if the occurrence only rolls blocks back, its
+    /// `action` otherwise.
+    fn get_predicate_action(&self) -> &'a HookAction {
+        let chainhook = self.chainhook;
+        match chainhook.rollback_action {
+            Some(ref rollback_action) if self.apply.is_empty() => rollback_action,
+            _ => &chainhook.action,
+        }
+    }
+
+    /// Returns the action delivering this occurrence: the predicate's `rollback_action` if the
+    /// occurrence only rolls blocks back, its `action` otherwise, or the action at `action_index`
+    /// when the predicate delivers its occurrences to several actions. Routed actions are
+    /// resolved against the payload of the occurrence, once.
+    pub fn get_action(&self) -> &'a HookAction {
+        self.resolved_action.get_or_init(|| {
+            let action = self.get_predicate_action();
+            match action.actions().get(self.action_index).unwrap_or(action) {
+                HookAction::Route(routes) => routes.route(&serialize_stacks_payload_to_json(
+                    self.clone(),
+                    &HashMap::new(),
+                    &Context::empty(),
+                )),
+                action => action,
+            }
+        })
+    }
+
+    /// Splits an occurrence in one occurrence per delivering action. An occurrence rolling blocks
+    /// back and applying others is split in two occurrences, the rollback one first, when the
+    /// predicate delivers rollbacks to a `rollback_action`. Each occurrence is then repeated for
+    /// every action of the predicate, so that each action retries its deliveries on its own.
+    pub fn split_by_action(self) -> Vec<StacksTriggerChainhook<'a>> {
+        let occurrences = if self.chainhook.rollback_action.is_none()
+            || self.apply.is_empty()
+            || self.rollback.is_empty()
+        {
+            vec![self]
+        } else {
+            vec![
+                StacksTriggerChainhook {
+                    chainhook: self.chainhook,
+                    apply: vec![],
+                    rollback: self.rollback,
+                    action_index: 0,
+                    confirmation_depth: self.confirmation_depth,
+                    reorg_sequence: self.reorg_sequence,
+                    resolved_action: OnceLock::new(),
+                },
+                StacksTriggerChainhook {
+                    chainhook: self.chainhook,
+                    apply: self.apply,
+                    rollback: vec![],
+                    action_index: 0,
+                    confirmation_depth: self.confirmation_depth,
+                    reorg_sequence: self.reorg_sequence,
+                    resolved_action: OnceLock::new(),
+                },
+            ]
+        };
+        let mut triggers = vec![];
+        for occurrence in occurrences.into_iter() {
+            let fan_out = (1..occurrence.get_predicate_action().actions().len())
+                .map(|action_index| StacksTriggerChainhook {
+                    action_index,
+                    resolved_action: OnceLock::new(),
+                    ..occurrence.clone()
+                })
+                .collect::<Vec<_>>();
+            triggers.push(occurrence);
+            triggers.extend(fan_out);
+        }
+        triggers
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StacksApplyTransactionPayload {
     pub block_identifier: BlockIdentifier,
     pub transactions: Vec<StacksTransactionData>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StacksRollbackTransactionPayload {
     pub block_identifier: BlockIdentifier,
     pub transactions: Vec<StacksTransactionData>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StacksChainhookPayload {
     pub uuid: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StacksChainhookOccurrencePayload {
     pub apply: Vec<StacksApplyTransactionPayload>,
     pub rollback: Vec<StacksRollbackTransactionPayload>,
@@ -522,7 +930,12 @@ impl StacksChainhookOccurrencePayload {
 }
 pub enum StacksChainhookOccurrence {
     Http(RequestBuilder, StacksChainhookOccurrencePayload),
-    File(String, Vec<u8>),
+    File(FileHook, Vec<u8>, StacksChainhookOccurrencePayload),
+    ObjectStore(ObjectStoreUpload, StacksChainhookOccurrencePayload),
+    Kafka(KafkaMessage, StacksChainhookOccurrencePayload),
+    Nats(NatsMessage, StacksChainhookOccurrencePayload),
+    Aws(AwsMessage, StacksChainhookOccurrencePayload),
+    Postgres(PostgresRecord, StacksChainhookOccurrencePayload),
     Data(StacksChainhookOccurrencePayload),
 }
 
@@ -535,6 +948,7 @@ impl<'a> StacksTriggerChainhook<'a> {
 pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
     chain_event: &'a StacksChainEvent,
     active_chainhooks: Vec<&'a StacksChainhookInstance>,
+    bitcoin_anchors: &BitcoinAnchorContext,
     ctx: &Context,
 ) -> (
     Vec<StacksTriggerChainhook<'a>>,
@@ -562,6 +976,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                             evaluate_stacks_chainhook_on_blocks(
                                 vec![parents_microblock_to_apply],
                                 chainhook,
+                                bitcoin_anchors,
                                 ctx,
                             );
                         apply.append(&mut occurrences);
@@ -574,6 +989,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                             evaluate_stacks_chainhook_on_blocks(
                                 vec![parents_microblock_to_rolllback],
                                 chainhook,
+                                bitcoin_anchors,
                                 ctx,
                             );
                         rollback.append(&mut occurrences);
@@ -583,6 +999,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![&block_update.block],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     apply.append(&mut occurrences);
@@ -593,6 +1010,10 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                         chainhook,
                         apply,
                         rollback,
+                        action_index: 0,
+                        confirmation_depth: None,
+                        reorg_sequence: 0,
+                        resolved_action: OnceLock::new(),
                     })
                 }
             }
@@ -611,6 +1032,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![microblock_to_apply],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     apply.append(&mut occurrences);
@@ -621,6 +1043,10 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                         chainhook,
                         apply,
                         rollback,
+                        action_index: 0,
+                        confirmation_depth: None,
+                        reorg_sequence: 0,
+                        resolved_action: OnceLock::new(),
                     })
                 }
             }
@@ -638,6 +1064,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![microblock_to_apply],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     apply.append(&mut occurrences);
@@ -647,6 +1074,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![microblock_to_rollback],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     rollback.append(&mut occurrences);
@@ -657,6 +1085,10 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                         chainhook,
                         apply,
                         rollback,
+                        action_index: 0,
+                        confirmation_depth: None,
+                        reorg_sequence: 0,
+                        resolved_action: OnceLock::new(),
                     })
                 }
             }
@@ -678,6 +1110,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                             evaluate_stacks_chainhook_on_blocks(
                                 vec![parents_microblock_to_apply],
                                 chainhook,
+                                bitcoin_anchors,
                                 ctx,
                             );
                         apply.append(&mut occurrences);
@@ -687,6 +1120,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![&block_update.block],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     apply.append(&mut occurrences);
@@ -700,6 +1134,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                             evaluate_stacks_chainhook_on_blocks(
                                 vec![parents_microblock_to_rollback],
                                 chainhook,
+                                bitcoin_anchors,
                                 ctx,
                             );
                         rollback.append(&mut occurrences);
@@ -708,6 +1143,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                     let (mut occurrences, mut expirations) = evaluate_stacks_chainhook_on_blocks(
                         vec![&block_update.block],
                         chainhook,
+                        bitcoin_anchors,
                         ctx,
                     );
                     rollback.append(&mut occurrences);
@@ -718,13 +1154,20 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
                         chainhook,
                         apply,
                         rollback,
+                        action_index: 0,
+                        confirmation_depth: None,
+                        reorg_sequence: 0,
+                        resolved_action: OnceLock::new(),
                     })
                 }
             }
         }
     }
     (
-        triggered_predicates,
+        triggered_predicates
+            .into_iter()
+            .flat_map(|trigger| trigger.split_by_action())
+            .collect(),
         evaluated_predicates,
         expired_predicates,
     )
@@ -733,6 +1176,7 @@ pub fn evaluate_stacks_chainhooks_on_chain_event<'a>(
 pub fn evaluate_stacks_chainhook_on_blocks<'a>(
     blocks: Vec<&'a dyn AbstractStacksBlock>,
     chainhook: &'a StacksChainhookInstance,
+    bitcoin_anchors: &BitcoinAnchorContext,
     ctx: &Context,
 ) -> (
     Vec<(Vec<&'a StacksTransactionData>, &'a dyn AbstractStacksBlock)>,
@@ -744,6 +1188,11 @@ pub fn evaluate_stacks_chainhook_on_blocks<'a>(
     for block in blocks {
         if end_block >= block.get_identifier().index {
             let mut hits = vec![];
+            if let StacksPredicate::BitcoinAnchored(predicate) = &chainhook.predicate {
+                if !bitcoin_anchors.is_anchor_matching(block, &predicate.bitcoin, ctx) {
+                    continue;
+                }
+            }
             if chainhook.is_predicate_targeting_block_header() {
                 if evaluate_stacks_predicate_on_block(block, chainhook, ctx) {
                     for tx in block.get_transactions().iter() {
@@ -770,9 +1219,17 @@ pub fn evaluate_stacks_chainhook_on_blocks<'a>(
 pub fn evaluate_stacks_predicate_on_block<'a>(
     block: &'a dyn AbstractStacksBlock,
     chainhook: &'a StacksChainhookInstance,
-    _ctx: &Context,
+    ctx: &Context,
+) -> bool {
+    evaluate_predicate_on_block(&chainhook.predicate, block, ctx)
+}
+
+fn evaluate_predicate_on_block(
+    predicate: &StacksPredicate,
+    block: &dyn AbstractStacksBlock,
+    ctx: &Context,
 ) -> bool {
-    match &chainhook.predicate {
+    match predicate {
         StacksPredicate::BlockHeight(BlockIdentifierIndexRule::Between(a, b)) => {
             block.get_identifier().index.gt(a) && block.get_identifier().index.lt(b)
         }
@@ -792,6 +1249,9 @@ pub fn evaluate_stacks_predicate_on_block<'a>(
         | StacksPredicate::StxEvent(_)
         | StacksPredicate::PrintEvent(_)
         | StacksPredicate::Txid(_) => unreachable!(),
+        StacksPredicate::BitcoinAnchored(predicate) => {
+            evaluate_predicate_on_block(&predicate.stacks, block, ctx)
+        }
     }
 }
 
@@ -800,7 +1260,15 @@ pub fn evaluate_stacks_predicate_on_transaction<'a>(
     chainhook: &'a StacksChainhookInstance,
     ctx: &Context,
 ) -> bool {
-    match &chainhook.predicate {
+    evaluate_predicate_on_transaction(&chainhook.predicate, transaction, ctx)
+}
+
+fn evaluate_predicate_on_transaction(
+    predicate: &StacksPredicate,
+    transaction: &StacksTransactionData,
+    ctx: &Context,
+) -> bool {
+    match predicate {
         StacksPredicate::ContractDeployment(StacksContractDeploymentPredicate::Deployer(
             expected_deployer,
         )) => match &transaction.metadata.kind {
@@ -1002,6 +1470,9 @@ pub fn evaluate_stacks_predicate_on_transaction<'a>(
         StacksPredicate::Txid(ExactMatchingRule::Equals(txid)) => {
             txid.eq(&transaction.transaction_identifier.hash)
         }
+        StacksPredicate::BitcoinAnchored(predicate) => {
+            evaluate_predicate_on_transaction(&predicate.stacks, transaction, ctx)
+        }
         StacksPredicate::BlockHeight(_) => unreachable!(),
     }
 }
@@ -1309,6 +1780,7 @@ pub fn serialize_stacks_payload_to_json<'a>(
 ) -> JsonValue {
     let decode_clarity_values = trigger.should_decode_clarity_value();
     let include_contract_abi = trigger.chainhook.include_contract_abi.unwrap_or(false);
+    let delivery_id = trigger.get_delivery_id();
     json!({
         "apply": trigger.apply.into_iter().map(|(transactions, block)| {
             serialize_stacks_block(block, transactions, decode_clarity_values, include_contract_abi, ctx)
@@ -1316,57 +1788,177 @@ pub fn serialize_stacks_payload_to_json<'a>(
         "rollback": trigger.rollback.into_iter().map(|(transactions, block)| {
             serialize_stacks_block(block, transactions, decode_clarity_values, include_contract_abi, ctx)
         }).collect::<Vec<_>>(),
+        "delivery_id": delivery_id,
         "chainhook": {
             "uuid": trigger.chainhook.uuid,
+            "network": trigger.chainhook.network,
             "predicate": trigger.chainhook.predicate,
-            "is_streaming_blocks": trigger.chainhook.enabled
+            "is_streaming_blocks": trigger.chainhook.enabled,
+            "confirmation_depth": trigger.confirmation_depth
         }
     })
 }
 
+/// Serializes the payload delivered for a triggered predicate, keeping its `include_fields`
+/// then applying its `transform` and wrapping it in its `payload_envelope`, if any.
+pub fn serialize_stacks_payload_for_delivery<'a>(
+    trigger: StacksTriggerChainhook<'a>,
+    proofs: &HashMap<&'a TransactionIdentifier, String>,
+    ctx: &Context,
+) -> Result<JsonValue, String> {
+    let chainhook = trigger.chainhook;
+    let delivery_id = trigger.get_delivery_id();
+    let mut payload = serialize_stacks_payload_to_json(trigger, proofs, ctx);
+    if let Some(ref fields) = chainhook.include_fields {
+        payload = project_payload_fields(payload, fields);
+    }
+    if let Some(ref transform) = chainhook.transform {
+        payload = apply_transform(transform, payload)?;
+    }
+    Ok(wrap_payload(
+        &chainhook.payload_envelope,
+        payload,
+        &Chain::Stacks,
+        &chainhook.uuid,
+        &delivery_id,
+    ))
+}
+
 pub fn handle_stacks_hook_action<'a>(
     trigger: StacksTriggerChainhook<'a>,
     proofs: &HashMap<&'a TransactionIdentifier, String>,
     config: &EventObserverConfig,
     ctx: &Context,
 ) -> Result<StacksChainhookOccurrence, String> {
-    match &trigger.chainhook.action {
+    match trigger.get_action() {
         HookAction::HttpPost(http) => {
-            let mut client_builder = Client::builder();
-            if let Some(timeout) = config.predicates_config.payload_http_request_timeout_ms {
-                client_builder = client_builder.timeout(Duration::from_millis(timeout));
-            }
-            let client = client_builder
-                .build()
-                .map_err(|e| format!("unable to build http client: {}", e))?;
-            let host = http.url.to_string();
-            let method = Method::POST;
-            let body = serde_json::to_vec(&serialize_stacks_payload_to_json(
+            let body = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
                 trigger.clone(),
                 proofs,
                 ctx,
-            ))
+            )?)
             .map_err(|e| format!("unable to serialize payload {}", e))?;
             Ok(StacksChainhookOccurrence::Http(
-                client
-                    .request(method, &host)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", http.authorization_header.clone())
-                    .body(body),
+                build_http_post_request(
+                    http,
+                    body,
+                    &trigger.get_delivery_id(),
+                    payload_content_type(&trigger.chainhook.payload_envelope),
+                    &config.predicates_config,
+                )?,
                 StacksChainhookOccurrencePayload::from_trigger(trigger),
             ))
         }
         HookAction::FileAppend(disk) => {
-            let bytes = serde_json::to_vec(&serialize_stacks_payload_to_json(trigger, proofs, ctx))
-                .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let bytes = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
             Ok(StacksChainhookOccurrence::File(
-                disk.path.to_string(),
+                disk.clone(),
                 bytes,
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::ObjectStore(object_store) => {
+            let upload = ObjectStoreUpload::new(
+                object_store,
+                &trigger.chainhook.uuid,
+                &serialize_stacks_payload_for_delivery(trigger.clone(), proofs, ctx)?,
+            )?;
+            Ok(StacksChainhookOccurrence::ObjectStore(
+                upload,
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::Kafka(kafka) => {
+            let payload = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let key = match kafka.key {
+                KafkaMessageKey::PredicateUuid => trigger.chainhook.uuid.clone(),
+                KafkaMessageKey::BlockHash => trigger
+                    .get_tip_block_identifier()
+                    .map(|b| b.hash.as_str())
+                    .unwrap_or(&trigger.chainhook.uuid)
+                    .to_string(),
+            };
+            Ok(StacksChainhookOccurrence::Kafka(
+                KafkaMessage::new(kafka, key, payload),
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::Nats(nats) => {
+            let payload = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+            let message = NatsMessage::new(
+                nats,
+                &trigger.chainhook.uuid,
+                trigger.get_tip_block_identifier(),
+                payload,
+            );
+            Ok(StacksChainhookOccurrence::Nats(
+                message,
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::Sqs(sqs) => {
+            let payload = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+            Ok(StacksChainhookOccurrence::Aws(
+                AwsMessage::sqs(&sqs.queue_url, &sqs.region, payload),
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::Sns(sns) => {
+            let payload = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+            Ok(StacksChainhookOccurrence::Aws(
+                AwsMessage::sns(&sns.topic_arn, &sns.region, payload),
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
+            ))
+        }
+        HookAction::Postgres(postgres) => {
+            let payload = serde_json::to_vec(&serialize_stacks_payload_for_delivery(
+                trigger.clone(),
+                proofs,
+                ctx,
+            )?)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+            Ok(StacksChainhookOccurrence::Postgres(
+                PostgresRecord::new(
+                    &postgres.dsn,
+                    &postgres.table,
+                    postgres.get_column(),
+                    payload,
+                ),
+                StacksChainhookOccurrencePayload::from_trigger(trigger),
             ))
         }
         HookAction::Noop => Ok(StacksChainhookOccurrence::Data(
             StacksChainhookOccurrencePayload::from_trigger(trigger),
         )),
+        HookAction::Route(_) | HookAction::Multiple(_) => Err(format!(
+            "unable to resolve the action delivering an occurrence of predicate {}",
+            trigger.chainhook.uuid
+        )),
     }
 }
 
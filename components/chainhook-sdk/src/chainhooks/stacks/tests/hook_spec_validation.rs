@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 use crate::chainhooks::stacks::{StacksChainhookSpecification, StacksChainhookSpecificationNetworkMap, StacksContractCallBasedPredicate, StacksContractDeploymentPredicate, StacksPredicate, StacksPrintEventBasedPredicate};
 use crate::chainhooks::types::*;
-use crate::chainhooks::types::HttpHook;
+use crate::chainhooks::types::{DeliveryOptions, HttpHook};
 use chainhook_types::StacksNetwork;
 use test_case::test_case;
 
@@ -35,13 +35,16 @@ lazy_static! {
     
     static ref INVALID_PREDICATE: StacksPredicate = StacksPredicate::PrintEvent(StacksPrintEventBasedPredicate::MatchesRegex { contract_identifier: CONTRACT_ID_INVALID_ADDRESS.clone(), regex:  INVALID_REGEX.clone() });
     static ref INVALID_HOOK_ACTION: HookAction = 
-        HookAction::HttpPost(HttpHook { url: "".into(), authorization_header: "\n".into() });
+        HookAction::HttpPost(HttpHook { url: "".into(), authorization_header: "\n".into(), signing_secret: None, tls: None, compression: None, require_ack: None, delivery: DeliveryOptions::default() });
     static ref ALL_INVALID_SPEC: StacksChainhookSpecification = StacksChainhookSpecification::new(INVALID_PREDICATE.clone(), INVALID_HOOK_ACTION.clone());
     static ref ALL_INVALID_SPEC_NETWORK_MAP: ChainhookSpecificationNetworkMap = 
         ChainhookSpecificationNetworkMap::Stacks(
             StacksChainhookSpecificationNetworkMap { 
                 uuid: "test".into(), 
                 owner_uuid: None, 
+                labels: None,
+                metadata: None,
+                namespace: None,
                 name: "test".into(), 
                 version: 1, 
                 networks: BTreeMap::from([
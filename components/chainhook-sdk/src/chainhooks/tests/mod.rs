@@ -1,16 +1,25 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 use self::fixtures::get_all_event_payload_types;
 
 use super::{
+    bitcoin::BitcoinPredicateType,
+    diagnostics::{check_predicate, DiagnosticSeverity},
+    schemas::json_schema,
     stacks::{
-        evaluate_stacks_chainhooks_on_chain_event, handle_stacks_hook_action,
-        StacksChainhookInstance, StacksChainhookOccurrence, StacksContractCallBasedPredicate,
-        StacksContractDeploymentPredicate, StacksFtEventBasedPredicate,
-        StacksNftEventBasedPredicate, StacksPredicate, StacksPrintEventBasedPredicate,
-        StacksStxEventBasedPredicate, StacksTrait, StacksTriggerChainhook,
+        evaluate_stacks_chainhooks_on_chain_event, handle_stacks_hook_action, BitcoinAnchorContext,
+        StacksBitcoinAnchoredPredicate, StacksChainhookInstance, StacksChainhookOccurrence,
+        StacksContractCallBasedPredicate, StacksContractDeploymentPredicate,
+        StacksFtEventBasedPredicate, StacksNftEventBasedPredicate, StacksPredicate,
+        StacksPrintEventBasedPredicate, StacksStxEventBasedPredicate, StacksTrait,
+        StacksTriggerChainhook,
+    },
+    types::{
+        build_delivery_id, BitcoinPredicateBuilder, BlockIdentifierIndexRule,
+        ChainhookSpecificationNetworkMap, DeliveryOptions, ExactMatchingRule, FileHook,
+        StacksPredicateBuilder,
     },
-    types::{ExactMatchingRule, FileHook},
 };
 use crate::{
     chainhooks::stacks::serialize_stacks_payload_to_json,
@@ -26,10 +35,11 @@ use crate::{
 };
 use assert_json_diff::assert_json_eq;
 use chainhook_types::{
-    StacksBlockUpdate, StacksChainEvent, StacksChainUpdatedWithBlocksData, StacksNetwork,
-    StacksTransactionData, StacksTransactionEvent, StacksTransactionEventPayload,
-    StacksTransactionEventPosition,
+    BitcoinBlockData, BitcoinBlockMetadata, BitcoinNetwork, BlockIdentifier, StacksBlockUpdate,
+    StacksChainEvent, StacksChainUpdatedWithBlocksData, StacksNetwork, StacksTransactionData,
+    StacksTransactionEvent, StacksTransactionEventPayload, StacksTransactionEventPosition,
 };
+use jsonschema::JSONSchema;
 use serde_json::Value as JsonValue;
 use test_case::test_case;
 
@@ -395,6 +405,9 @@ fn test_stacks_predicates(
     let chainhook = StacksChainhookInstance {
         uuid: "".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -402,6 +415,13 @@ fn test_stacks_predicates(
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: None,
@@ -412,8 +432,12 @@ fn test_stacks_predicates(
     };
 
     let predicates = vec![&chainhook];
-    let (triggered, _predicates_evaluated, _expired) =
-        evaluate_stacks_chainhooks_on_chain_event(&event, predicates, &Context::empty());
+    let (triggered, _predicates_evaluated, _expired) = evaluate_stacks_chainhooks_on_chain_event(
+        &event,
+        predicates,
+        &BitcoinAnchorContext::empty(),
+        &Context::empty(),
+    );
 
     if expected_applies == 0 {
         assert_eq!(triggered.len(), 0)
@@ -475,6 +499,9 @@ fn test_stacks_predicate_contract_deploy(predicate: StacksPredicate, expected_ap
     let chainhook = StacksChainhookInstance {
         uuid: "".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -482,6 +509,13 @@ fn test_stacks_predicate_contract_deploy(predicate: StacksPredicate, expected_ap
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: None,
@@ -493,7 +527,12 @@ fn test_stacks_predicate_contract_deploy(predicate: StacksPredicate, expected_ap
 
     let predicates = vec![&chainhook];
     let (triggered, _predicates_evaluated, _predicates_expired) =
-        evaluate_stacks_chainhooks_on_chain_event(&event, predicates, &Context::empty());
+        evaluate_stacks_chainhooks_on_chain_event(
+            &event,
+            predicates,
+            &BitcoinAnchorContext::empty(),
+            &Context::empty(),
+        );
 
     if expected_applies == 0 {
         assert_eq!(triggered.len(), 0)
@@ -530,6 +569,9 @@ fn verify_optional_addition_of_contract_abi() {
     let mut contract_deploy_chainhook = StacksChainhookInstance {
         uuid: "contract-deploy".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -537,6 +579,13 @@ fn verify_optional_addition_of_contract_abi() {
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: Some(true),
@@ -550,6 +599,9 @@ fn verify_optional_addition_of_contract_abi() {
     let contract_call_chainhook = StacksChainhookInstance {
         uuid: "contract-call".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -557,6 +609,13 @@ fn verify_optional_addition_of_contract_abi() {
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: Some(true),
@@ -570,12 +629,17 @@ fn verify_optional_addition_of_contract_abi() {
     };
 
     let predicates = vec![&contract_deploy_chainhook, &contract_call_chainhook];
-    let (triggered, _blocks, _) =
-        evaluate_stacks_chainhooks_on_chain_event(&event, predicates, &Context::empty());
+    let (triggered, _blocks, _) = evaluate_stacks_chainhooks_on_chain_event(
+        &event,
+        predicates,
+        &BitcoinAnchorContext::empty(),
+        &Context::empty(),
+    );
     assert_eq!(triggered.len(), 2);
 
     for t in triggered.into_iter() {
         let result = serialize_stacks_payload_to_json(t, &HashMap::new(), &Context::empty());
+        assert_payload_matches_json_schema("stacks_payload", &result);
         let result = result.as_object().unwrap();
         let uuid = result.get("chainhook").unwrap().get("uuid").unwrap();
         let apply_blocks = result.get("apply").unwrap();
@@ -595,12 +659,17 @@ fn verify_optional_addition_of_contract_abi() {
     }
     contract_deploy_chainhook.include_contract_abi = Some(false);
     let predicates = vec![&contract_deploy_chainhook, &contract_call_chainhook];
-    let (triggered, _blocks, _) =
-        evaluate_stacks_chainhooks_on_chain_event(&event, predicates, &Context::empty());
+    let (triggered, _blocks, _) = evaluate_stacks_chainhooks_on_chain_event(
+        &event,
+        predicates,
+        &BitcoinAnchorContext::empty(),
+        &Context::empty(),
+    );
     assert_eq!(triggered.len(), 2);
 
     for t in triggered.into_iter() {
         let result = serialize_stacks_payload_to_json(t, &HashMap::new(), &Context::empty());
+        assert_payload_matches_json_schema("stacks_payload", &result);
         let result = result.as_object().unwrap();
         let apply_blocks = result.get("apply").unwrap();
         for block in apply_blocks.as_array().unwrap() {
@@ -669,6 +738,9 @@ fn test_stacks_predicate_contract_call(predicate: StacksPredicate, expected_appl
     let chainhook = StacksChainhookInstance {
         uuid: "".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -676,6 +748,13 @@ fn test_stacks_predicate_contract_call(predicate: StacksPredicate, expected_appl
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: None,
@@ -687,7 +766,12 @@ fn test_stacks_predicate_contract_call(predicate: StacksPredicate, expected_appl
 
     let predicates = vec![&chainhook];
     let (triggered, _predicates_evaluated, _predicates_expired) =
-        evaluate_stacks_chainhooks_on_chain_event(&event, predicates, &Context::empty());
+        evaluate_stacks_chainhooks_on_chain_event(
+            &event,
+            predicates,
+            &BitcoinAnchorContext::empty(),
+            &Context::empty(),
+        );
 
     if expected_applies == 0 {
         assert_eq!(triggered.len(), 0)
@@ -704,6 +788,9 @@ fn test_stacks_hook_action_noop() {
     let chainhook = StacksChainhookInstance {
         uuid: "".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -711,6 +798,13 @@ fn test_stacks_hook_action_noop() {
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: None,
         include_contract_abi: None,
@@ -733,6 +827,10 @@ fn test_stacks_hook_action_noop() {
         chainhook: &chainhook,
         apply: vec![(apply_transactions, apply_blocks)],
         rollback: vec![(rollback_transactions, rollback_blocks)],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
     };
 
     let proofs = HashMap::new();
@@ -763,6 +861,9 @@ fn test_stacks_hook_action_file_append() {
     let chainhook = StacksChainhookInstance {
         uuid: "".to_string(),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         name: "".to_string(),
         network: StacksNetwork::Testnet,
         version: 1,
@@ -770,6 +871,13 @@ fn test_stacks_hook_action_file_append() {
         start_block: None,
         end_block: None,
         expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
         capture_all_events: None,
         decode_clarity_values: Some(true),
         include_contract_abi: None,
@@ -778,6 +886,9 @@ fn test_stacks_hook_action_file_append() {
         )),
         action: HookAction::FileAppend(FileHook {
             path: "./".to_string(),
+            rotation: None,
+            compression: None,
+            delivery: DeliveryOptions::default(),
         }),
         enabled: true,
         expired_at: None,
@@ -811,6 +922,10 @@ fn test_stacks_hook_action_file_append() {
         chainhook: &chainhook,
         apply,
         rollback: vec![(rollback_transactions, rollback_block)],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
     };
 
     let proofs = HashMap::new();
@@ -820,8 +935,8 @@ fn test_stacks_hook_action_file_append() {
     };
     let occurrence =
         handle_stacks_hook_action(trigger, &proofs, &EventObserverConfig::default(), &ctx).unwrap();
-    if let StacksChainhookOccurrence::File(path, bytes) = occurrence {
-        assert_eq!(path, "./".to_string());
+    if let StacksChainhookOccurrence::File(hook, bytes, _) = occurrence {
+        assert_eq!(hook.path, "./".to_string());
         let actual: JsonValue = serde_json::from_slice(&bytes).unwrap();
         let expected: JsonValue = serde_json::from_str(&get_expected_occurrence()).unwrap();
         assert_json_eq!(expected, actual);
@@ -829,3 +944,361 @@ fn test_stacks_hook_action_file_append() {
         panic!("wrong occurrence type");
     }
 }
+
+#[test_case(false; "with raw Clarity values")]
+#[test_case(true; "with decoded Clarity values")]
+fn test_stacks_payload_matches_json_schema(decode_clarity_values: bool) {
+    let chainhook = StacksChainhookInstance {
+        uuid: "".to_string(),
+        owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
+        name: "".to_string(),
+        network: StacksNetwork::Testnet,
+        version: 1,
+        blocks: None,
+        start_block: None,
+        end_block: None,
+        expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
+        capture_all_events: None,
+        decode_clarity_values: Some(decode_clarity_values),
+        include_contract_abi: Some(true),
+        predicate: StacksPredicate::Txid(ExactMatchingRule::Equals(
+            "0xb92c2ade84a8b85f4c72170680ae42e65438aea4db72ba4b2d6a6960f4141ce8".to_string(),
+        )),
+        action: HookAction::Noop,
+        enabled: true,
+        expired_at: None,
+    };
+    let apply_blocks = get_all_event_payload_types()
+        .into_iter()
+        .map(|payload| {
+            fixtures::build_stacks_testnet_block_from_smart_contract_event_data(&vec![
+                StacksTransactionEvent {
+                    event_payload: payload,
+                    position: StacksTransactionEventPosition { index: 0 },
+                },
+            ])
+        })
+        .collect::<Vec<_>>();
+    let apply: Vec<(Vec<&StacksTransactionData>, &dyn AbstractStacksBlock)> = apply_blocks
+        .iter()
+        .map(|b| {
+            (
+                b.transactions.iter().collect(),
+                b as &dyn AbstractStacksBlock,
+            )
+        })
+        .collect();
+    let rollback_block_data = fixtures::build_stacks_testnet_block_with_contract_deployment();
+    let rollback_transactions = rollback_block_data.transactions.iter().collect();
+    let rollback_block: &dyn AbstractStacksBlock = &rollback_block_data;
+    let trigger = StacksTriggerChainhook {
+        chainhook: &chainhook,
+        apply,
+        rollback: vec![(rollback_transactions, rollback_block)],
+        action_index: 0,
+        confirmation_depth: None,
+        reorg_sequence: 0,
+        resolved_action: OnceLock::new(),
+    };
+
+    let payload = serialize_stacks_payload_to_json(trigger, &HashMap::new(), &Context::empty());
+    assert_payload_matches_json_schema("stacks_payload", &payload);
+}
+
+#[test_case(false, 0; "BitcoinAnchored predicate does not match when anchor block is unknown")]
+#[test_case(true, 1; "BitcoinAnchored predicate matches when anchor block is known")]
+fn test_stacks_predicate_bitcoin_anchored(anchor_known: bool, expected_applies: usize) {
+    let block = fixtures::build_stacks_testnet_block_with_contract_call();
+    let anchor_block = BitcoinBlockData {
+        block_identifier: block.metadata.bitcoin_anchor_block_identifier.clone(),
+        parent_block_identifier: block.metadata.bitcoin_anchor_block_identifier.clone(),
+        timestamp: 0,
+        transactions: vec![],
+        metadata: BitcoinBlockMetadata {
+            network: BitcoinNetwork::Testnet,
+        },
+    };
+    let event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate {
+            block,
+            parent_microblocks_to_apply: vec![],
+            parent_microblocks_to_rollback: vec![],
+        }],
+        confirmed_blocks: vec![],
+    });
+    let chainhook = StacksChainhookInstance {
+        uuid: "".to_string(),
+        owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
+        name: "".to_string(),
+        network: StacksNetwork::Testnet,
+        version: 1,
+        blocks: None,
+        start_block: None,
+        end_block: None,
+        expire_after_occurrence: None,
+        rollback_action: None,
+        payload_envelope: None,
+        max_batch_size: None,
+        max_batch_wait_ms: None,
+        heartbeat_interval_minutes: None,
+        include_fields: None,
+        transform: None,
+        capture_all_events: None,
+        decode_clarity_values: None,
+        include_contract_abi: None,
+        predicate: StacksPredicate::BitcoinAnchored(StacksBitcoinAnchoredPredicate {
+            bitcoin: BitcoinPredicateType::Block,
+            stacks: Box::new(StacksPredicate::ContractCall(
+                StacksContractCallBasedPredicate {
+                    contract_identifier: "ST13F481SBR0R7Z6NMMH8YV2FJJYXA5JPA0AD3HP9.subnet-v1"
+                        .to_string(),
+                    method: "commit-block".to_string(),
+                },
+            )),
+        }),
+        action: HookAction::Noop,
+        enabled: true,
+        expired_at: None,
+    };
+    let bitcoin_anchors = if anchor_known {
+        BitcoinAnchorContext::new(vec![&anchor_block])
+    } else {
+        BitcoinAnchorContext::empty()
+    };
+
+    let (triggered, _predicates_evaluated, _predicates_expired) =
+        evaluate_stacks_chainhooks_on_chain_event(
+            &event,
+            vec![&chainhook],
+            &bitcoin_anchors,
+            &Context::empty(),
+        );
+    assert_eq!(triggered.len(), expected_applies);
+}
+
+#[test]
+fn test_delivery_id_is_deterministic() {
+    let block = BlockIdentifier {
+        index: 1,
+        hash: "0x01".to_string(),
+    };
+    let delivery_id = build_delivery_id("1234", Some(&block), 0);
+    assert_eq!(delivery_id, "1234:0x01:0");
+    assert_eq!(delivery_id, build_delivery_id("1234", Some(&block), 0));
+    assert_ne!(delivery_id, build_delivery_id("1234", Some(&block), 1));
+    assert_ne!(delivery_id, build_delivery_id("5678", Some(&block), 0));
+}
+
+#[test]
+fn test_predicate_builders_match_json_specifications() {
+    let predicate = BitcoinPredicateBuilder::p2wpkh("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+        .between(800_000, 850_000)
+        .include_outputs(true)
+        .http_post("https://example.com/api/events", "Bearer 1234")
+        .build("bitcoin-predicate")
+        .unwrap();
+    let expected: ChainhookSpecificationNetworkMap = serde_json::from_value(json!({
+        "uuid": "bitcoin-predicate",
+        "name": "bitcoin-predicate",
+        "chain": "bitcoin",
+        "version": 1,
+        "networks": {
+            "mainnet": {
+                "start_block": 800000,
+                "end_block": 850000,
+                "include_outputs": true,
+                "if_this": {
+                    "scope": "outputs",
+                    "p2wpkh": { "equals": "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq" }
+                },
+                "then_that": {
+                    "http_post": {
+                        "url": "https://example.com/api/events",
+                        "authorization_header": "Bearer 1234"
+                    }
+                }
+            }
+        }
+    }))
+    .unwrap();
+    assert_eq!(predicate, expected);
+
+    let predicate =
+        StacksPredicateBuilder::contract_call("SP000000000000000000002Q6VF78.pox-3", "stack-stx")
+            .name("Stackers")
+            .network(StacksNetwork::Testnet)
+            .decode_clarity_values(true)
+            .build("stacks-predicate")
+            .unwrap();
+    let expected: ChainhookSpecificationNetworkMap = serde_json::from_value(json!({
+        "uuid": "stacks-predicate",
+        "name": "Stackers",
+        "chain": "stacks",
+        "version": 1,
+        "networks": {
+            "testnet": {
+                "decode_clarity_values": true,
+                "if_this": {
+                    "scope": "contract_call",
+                    "contract_identifier": "SP000000000000000000002Q6VF78.pox-3",
+                    "method": "stack-stx"
+                },
+                "then_that": "noop"
+            }
+        }
+    }))
+    .unwrap();
+    assert_eq!(predicate, expected);
+
+    // typos are caught when the predicate is built
+    assert!(BitcoinPredicateBuilder::txid("1234")
+        .build("invalid-predicate")
+        .is_err());
+    assert!(
+        StacksPredicateBuilder::block_height(BlockIdentifierIndexRule::Between(10, 5))
+            .build("invalid-predicate")
+            .is_err()
+    );
+}
+
+/// Panics with every violation when `payload` doesn't match the JSON Schema `name`.
+pub fn assert_payload_matches_json_schema(name: &str, payload: &JsonValue) {
+    let schema = json_schema(name).unwrap();
+    let compiled = JSONSchema::compile(&schema).unwrap();
+    if let Err(errors) = compiled.validate(payload) {
+        let errors = errors
+            .map(|e| format!("{} at {}", e, e.instance_path))
+            .collect::<Vec<_>>();
+        panic!("payload doesn't match the {} schema: {:?}", name, errors);
+    }
+}
+
+fn build_predicate_to_check(chain: &str, network: &str, spec: JsonValue) -> JsonValue {
+    let mut network_spec = json!({ "then_that": "noop" });
+    for (k, v) in spec.as_object().unwrap().iter() {
+        network_spec[k] = v.clone();
+    }
+    json!({
+        "chain": chain,
+        "uuid": "1",
+        "name": "test",
+        "version": 1,
+        "networks": { network: network_spec }
+    })
+}
+
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "block"}}),
+    vec![];
+    "valid predicate"
+)]
+#[test_case(
+    "testnet",
+    json!({"if_this": {"scope": "block"}}),
+    vec![(DiagnosticSeverity::Error, "networks")];
+    "missing target network"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "block"}, "end_blok": 10}),
+    vec![(DiagnosticSeverity::Warning, "networks.mainnet.end_blok")];
+    "unknown field"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "block"}, "blocks": [1, 20], "start_block": 1, "end_block": 10}),
+    vec![
+        (DiagnosticSeverity::Warning, "networks.mainnet.start_block"),
+        (DiagnosticSeverity::Warning, "networks.mainnet.blocks"),
+    ];
+    "block range"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "outputs", "p2pkh": {"equals": "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn"}}}),
+    vec![(DiagnosticSeverity::Error, "networks.mainnet.if_this.p2pkh.equals")];
+    "address of another network"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "outputs", "p2wpkh": {"equals": "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"}}}),
+    vec![(DiagnosticSeverity::Error, "networks.mainnet.if_this.p2wpkh.equals")];
+    "address of another type"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "outputs", "p2pkh": {"equals": "not-an-address"}}}),
+    vec![(DiagnosticSeverity::Error, "networks.mainnet.if_this.p2pkh.equals")];
+    "malformed address"
+)]
+#[test_case(
+    "mainnet",
+    json!({"if_this": {"scope": "ordinals_protocol", "operation": "inscription_feed"}}),
+    vec![(DiagnosticSeverity::Warning, "networks.mainnet.if_this.scope")];
+    "deprecated scope"
+)]
+fn test_check_bitcoin_predicate(
+    network: &str,
+    spec: JsonValue,
+    expected: Vec<(DiagnosticSeverity, &str)>,
+) {
+    let predicate = build_predicate_to_check("bitcoin", network, spec);
+    let diagnostics = check_predicate(
+        &predicate,
+        &BitcoinNetwork::Mainnet,
+        &StacksNetwork::Mainnet,
+    );
+    let diagnostics: Vec<(DiagnosticSeverity, &str)> = diagnostics
+        .iter()
+        .map(|d| (d.severity.clone(), d.path.as_str()))
+        .collect();
+    assert_eq!(diagnostics, expected);
+}
+
+#[test_case(
+    json!({"if_this": {"scope": "contract_call", "contract_identifier": "SP000000000000000000002Q6VF78.pox", "method": "stack-stx"}}),
+    vec![];
+    "valid predicate"
+)]
+#[test_case(
+    json!({"if_this": {"scope": "contract_call", "contract_identifier": "ST000000000000000000002AMW42H.pox", "method": "stack-stx"}}),
+    vec![(DiagnosticSeverity::Error, "networks.mainnet.if_this.contract_identifier")];
+    "contract of another network"
+)]
+#[test_case(
+    json!({"if_this": {"scope": "ft_event", "asset_identifier": "SP000000000000000000002Q6VF78.token", "actions": ["burn"]}}),
+    vec![(DiagnosticSeverity::Error, "networks.mainnet.if_this.asset_identifier")];
+    "malformed asset identifier"
+)]
+#[test_case(
+    json!({"if_this": {"scope": "block_height", "higher_than": 100}, "end_block": 50}),
+    vec![(DiagnosticSeverity::Warning, "networks.mainnet.if_this")];
+    "block height out of range"
+)]
+fn test_check_stacks_predicate(spec: JsonValue, expected: Vec<(DiagnosticSeverity, &str)>) {
+    let predicate = build_predicate_to_check("stacks", "mainnet", spec);
+    let diagnostics = check_predicate(
+        &predicate,
+        &BitcoinNetwork::Mainnet,
+        &StacksNetwork::Mainnet,
+    );
+    let diagnostics: Vec<(DiagnosticSeverity, &str)> = diagnostics
+        .iter()
+        .map(|d| (d.severity.clone(), d.path.as_str()))
+        .collect();
+    assert_eq!(diagnostics, expected);
+}
@@ -1,15 +1,28 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
-use chainhook_types::{BitcoinNetwork, StacksNetwork};
+use chainhook_types::{BitcoinNetwork, BlockIdentifier, StacksNetwork};
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
 
 use schemars::JsonSchema;
+use serde_json::Value as JsonValue;
 
 use crate::chainhooks::bitcoin::BitcoinChainhookInstance;
 use crate::chainhooks::bitcoin::BitcoinChainhookSpecificationNetworkMap;
+use crate::chainhooks::bitcoin::{
+    BitcoinChainhookSpecification, BitcoinPredicateType, DescriptorMatchingRule, InputPredicate,
+    InscriptionFeedData, OrdinalOperations, OutputPredicate, StacksOperations,
+};
 use crate::chainhooks::stacks::StacksChainhookInstance;
 use crate::chainhooks::stacks::StacksChainhookSpecificationNetworkMap;
+use crate::chainhooks::stacks::{
+    StacksChainhookSpecification, StacksContractCallBasedPredicate,
+    StacksContractDeploymentPredicate, StacksFtEventBasedPredicate, StacksNftEventBasedPredicate,
+    StacksPredicate, StacksPrintEventBasedPredicate, StacksStxEventBasedPredicate, StacksTrait,
+};
+use crate::utils::file_sink::STDOUT_FILE_SINK_PATH;
+use crate::utils::transform::{evaluate_condition, validate_transform};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ChainhookStore {
@@ -51,6 +64,52 @@ impl ChainhookStore {
         Ok(spec)
     }
 
+    /// Replaces a registered predicate with a new version of its specification, keeping its
+    /// uuid, chain, and whether it is enabled or expired.
+    pub fn update_instance_from_network_map(
+        &mut self,
+        networks: (&BitcoinNetwork, &StacksNetwork),
+        hook: ChainhookSpecificationNetworkMap,
+    ) -> Result<ChainhookInstance, String> {
+        let spec = match hook {
+            ChainhookSpecificationNetworkMap::Stacks(hook) => {
+                let mut spec = hook.into_specification_for_network(networks.1)?;
+                let Some(registered) = self
+                    .stacks_chainhooks
+                    .iter_mut()
+                    .find(|h| h.uuid == spec.uuid)
+                else {
+                    return Err(format!(
+                        "no Stacks predicate registered with uuid {}",
+                        spec.uuid
+                    ));
+                };
+                spec.enabled = registered.enabled;
+                spec.expired_at = registered.expired_at;
+                *registered = spec.clone();
+                ChainhookInstance::Stacks(spec)
+            }
+            ChainhookSpecificationNetworkMap::Bitcoin(hook) => {
+                let mut spec = hook.into_specification_for_network(networks.0)?;
+                let Some(registered) = self
+                    .bitcoin_chainhooks
+                    .iter_mut()
+                    .find(|h| h.uuid == spec.uuid)
+                else {
+                    return Err(format!(
+                        "no Bitcoin predicate registered with uuid {}",
+                        spec.uuid
+                    ));
+                };
+                spec.enabled = registered.enabled;
+                spec.expired_at = registered.expired_at;
+                *registered = spec.clone();
+                ChainhookInstance::Bitcoin(spec)
+            }
+        };
+        Ok(spec)
+    }
+
     pub fn enable_instance(&mut self, predicate_spec: &mut ChainhookInstance) {
         match predicate_spec {
             ChainhookInstance::Stacks(spec_to_enable) => {
@@ -117,10 +176,50 @@ impl ChainhookStore {
         None
     }
 
+    /// Disables a Stacks predicate, leaving it registered, and returns it.
+    pub fn disable_stacks_hook(&mut self, hook_uuid: &str) -> Option<StacksChainhookInstance> {
+        let hook = self
+            .stacks_chainhooks
+            .iter_mut()
+            .find(|hook| hook.uuid == hook_uuid)?;
+        hook.enabled = false;
+        Some(hook.clone())
+    }
+
+    /// Disables a Bitcoin predicate, leaving it registered, and returns it.
+    pub fn disable_bitcoin_hook(&mut self, hook_uuid: &str) -> Option<BitcoinChainhookInstance> {
+        let hook = self
+            .bitcoin_chainhooks
+            .iter_mut()
+            .find(|hook| hook.uuid == hook_uuid)?;
+        hook.enabled = false;
+        Some(hook.clone())
+    }
+
+    /// Returns the predicate registered with the given uuid, on either chain.
+    pub fn get_instance(&self, hook_uuid: &str) -> Option<ChainhookInstance> {
+        if let Some(hook) = self.stacks_chainhooks.iter().find(|h| h.uuid == hook_uuid) {
+            return Some(ChainhookInstance::Stacks(hook.clone()));
+        }
+        self.bitcoin_chainhooks
+            .iter()
+            .find(|h| h.uuid == hook_uuid)
+            .map(|hook| ChainhookInstance::Bitcoin(hook.clone()))
+    }
+
+    /// Disables the predicate registered with the given uuid, on either chain, and returns it.
+    pub fn disable_instance(&mut self, hook_uuid: &str) -> Option<ChainhookInstance> {
+        if let Some(hook) = self.disable_stacks_hook(hook_uuid) {
+            return Some(ChainhookInstance::Stacks(hook));
+        }
+        self.disable_bitcoin_hook(hook_uuid)
+            .map(ChainhookInstance::Bitcoin)
+    }
+
     pub fn expire_stacks_hook(&mut self, hook_uuid: String, block_height: u64) {
         let mut i = 0;
         while i < self.stacks_chainhooks.len() {
-            if ChainhookInstance::stacks_key(&self.stacks_chainhooks[i].uuid) == hook_uuid {
+            if self.stacks_chainhooks[i].key() == hook_uuid {
                 self.stacks_chainhooks[i].expired_at = Some(block_height);
                 break;
             } else {
@@ -132,7 +231,7 @@ impl ChainhookStore {
     pub fn expire_bitcoin_hook(&mut self, hook_uuid: String, block_height: u64) {
         let mut i = 0;
         while i < self.bitcoin_chainhooks.len() {
-            if ChainhookInstance::bitcoin_key(&self.bitcoin_chainhooks[i].uuid) == hook_uuid {
+            if self.bitcoin_chainhooks[i].key() == hook_uuid {
                 self.bitcoin_chainhooks[i].expired_at = Some(block_height);
                 break;
             } else {
@@ -142,173 +241,1489 @@ impl ChainhookStore {
     }
 }
 
-impl Serialize for ChainhookStore {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut seq = serializer.serialize_seq(Some(
-            self.bitcoin_chainhooks.len() + self.stacks_chainhooks.len(),
-        ))?;
-        for chainhook in self.bitcoin_chainhooks.iter() {
-            seq.serialize_element(chainhook)?;
+impl Serialize for ChainhookStore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(
+            self.bitcoin_chainhooks.len() + self.stacks_chainhooks.len(),
+        ))?;
+        for chainhook in self.bitcoin_chainhooks.iter() {
+            seq.serialize_element(chainhook)?;
+        }
+        for chainhook in self.stacks_chainhooks.iter() {
+            seq.serialize_element(chainhook)?;
+        }
+        seq.end()
+    }
+}
+
+/// Namespace of the predicates registered without one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Namespaces are part of the keys of the predicates, so they are restricted to alphanumeric
+/// characters, `-` and `_`.
+pub fn validate_namespace(namespace: &str) -> Result<(), String> {
+    if namespace.is_empty() || namespace.len() > 64 {
+        return Err(format!(
+            "invalid namespace '{}': expected between 1 and 64 characters",
+            namespace
+        ));
+    }
+    if !namespace
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid namespace '{}': expected alphanumeric characters, '-' or '_'",
+            namespace
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainhookInstance {
+    Bitcoin(BitcoinChainhookInstance),
+    Stacks(StacksChainhookInstance),
+}
+
+impl ChainhookInstance {
+    pub fn either_stx_or_btc_key(uuid: &str) -> String {
+        format!("predicate:{}", uuid)
+    }
+
+    pub fn stacks_key(uuid: &str) -> String {
+        format!("predicate:{}", uuid)
+    }
+
+    pub fn bitcoin_key(uuid: &str) -> String {
+        format!("predicate:{}", uuid)
+    }
+
+    /// Key of a predicate registered under `namespace`. Predicates of the default namespace keep
+    /// the keys they had before namespaces were introduced.
+    pub fn namespaced_key(namespace: Option<&str>, uuid: &str) -> String {
+        match namespace {
+            None | Some(DEFAULT_NAMESPACE) => Self::either_stx_or_btc_key(uuid),
+            Some(namespace) => format!("predicate:{}:{}", namespace, uuid),
+        }
+    }
+
+    pub fn key(&self) -> String {
+        match &self {
+            Self::Bitcoin(data) => data.key(),
+            Self::Stacks(data) => data.key(),
+        }
+    }
+
+    pub fn deserialize_specification(spec: &str) -> Result<ChainhookInstance, String> {
+        let spec: ChainhookInstance = serde_json::from_str(spec)
+            .map_err(|e| format!("unable to deserialize predicate {}", e))?;
+        Ok(spec)
+    }
+
+    pub fn uuid(&self) -> &str {
+        match &self {
+            Self::Bitcoin(data) => &data.uuid,
+            Self::Stacks(data) => &data.uuid,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match &self {
+            Self::Bitcoin(data) => data.enabled,
+            Self::Stacks(data) => data.enabled,
+        }
+    }
+
+    pub fn labels(&self) -> &[String] {
+        let labels = match &self {
+            Self::Bitcoin(data) => &data.labels,
+            Self::Stacks(data) => &data.labels,
+        };
+        labels.as_deref().unwrap_or_default()
+    }
+
+    pub fn metadata(&self) -> Option<&BTreeMap<String, String>> {
+        match &self {
+            Self::Bitcoin(data) => data.metadata.as_ref(),
+            Self::Stacks(data) => data.metadata.as_ref(),
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        let namespace = match &self {
+            Self::Bitcoin(data) => &data.namespace,
+            Self::Stacks(data) => &data.namespace,
+        };
+        namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE)
+    }
+
+    /// Returns the specification the predicate was registered with, for its network only.
+    pub fn into_specification_network_map(self) -> ChainhookSpecificationNetworkMap {
+        match self {
+            Self::Bitcoin(data) => {
+                ChainhookSpecificationNetworkMap::Bitcoin(data.into_specification_network_map())
+            }
+            Self::Stacks(data) => {
+                ChainhookSpecificationNetworkMap::Stacks(data.into_specification_network_map())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "chain")]
+pub enum ChainhookSpecificationNetworkMap {
+    Bitcoin(BitcoinChainhookSpecificationNetworkMap),
+    Stacks(StacksChainhookSpecificationNetworkMap),
+}
+
+impl ChainhookSpecificationNetworkMap {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_namespace(self.get_namespace())?;
+        match &self {
+            Self::Bitcoin(data) => {
+                let mut errors = vec![];
+                for (network, spec) in data.networks.iter() {
+                    if let Err(e) = spec.validate() {
+                        errors.append(&mut append_error_context(
+                            &format!(
+                                "invalid Bitcoin predicate '{}' for network {}",
+                                data.name, network
+                            ),
+                            e,
+                        ));
+                    }
+                }
+                if !errors.is_empty() {
+                    return Err(errors.join("\n"));
+                }
+            }
+            Self::Stacks(data) => {
+                let mut errors = vec![];
+                for (network, spec) in data.networks.iter() {
+                    if let Err(e) = spec.validate() {
+                        errors.append(&mut append_error_context(
+                            &format!(
+                                "invalid Stacks predicate '{}' for network {}",
+                                data.name, network
+                            ),
+                            e,
+                        ));
+                    }
+                }
+                if !errors.is_empty() {
+                    return Err(errors.join("\n"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_uuid(&self) -> &str {
+        match &self {
+            Self::Bitcoin(data) => &data.uuid,
+            Self::Stacks(data) => &data.uuid,
+        }
+    }
+
+    pub fn get_namespace(&self) -> &str {
+        let namespace = match &self {
+            Self::Bitcoin(data) => &data.namespace,
+            Self::Stacks(data) => &data.namespace,
+        };
+        namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE)
+    }
+
+    pub fn set_namespace(&mut self, namespace: &str) {
+        let namespace = match namespace {
+            DEFAULT_NAMESPACE => None,
+            namespace => Some(namespace.to_string()),
+        };
+        match self {
+            Self::Bitcoin(data) => data.namespace = namespace,
+            Self::Stacks(data) => data.namespace = namespace,
+        }
+    }
+
+    pub fn deserialize_specification(
+        spec: &str,
+        _key: &str,
+    ) -> Result<ChainhookSpecificationNetworkMap, String> {
+        let spec: ChainhookSpecificationNetworkMap = serde_json::from_str(spec)
+            .map_err(|e| format!("unable to deserialize predicate {}", e))?;
+        Ok(spec)
+    }
+}
+
+/// Typed builder of Bitcoin predicates, producing the same [ChainhookSpecificationNetworkMap] as
+/// their JSON definition:
+///
+/// ```
+/// use chainhook_sdk::chainhooks::types::BitcoinPredicateBuilder;
+///
+/// let predicate = BitcoinPredicateBuilder::p2wpkh("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+///     .between(800_000, 850_000)
+///     .http_post("https://example.com/api/events", "Bearer 1234")
+///     .build("my-predicate")
+///     .expect("invalid predicate");
+/// ```
+///
+/// Predicates target mainnet and have a `noop` action unless specified otherwise.
+#[derive(Clone, Debug)]
+pub struct BitcoinPredicateBuilder {
+    name: Option<String>,
+    namespace: Option<String>,
+    network: BitcoinNetwork,
+    spec: BitcoinChainhookSpecification,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl BitcoinPredicateBuilder {
+    pub fn new(predicate: BitcoinPredicateType) -> Self {
+        BitcoinPredicateBuilder {
+            name: None,
+            namespace: None,
+            network: BitcoinNetwork::Mainnet,
+            spec: BitcoinChainhookSpecification::new(predicate, HookAction::Noop),
+            retry_policy: None,
+        }
+    }
+
+    /// Matches every block.
+    pub fn block() -> Self {
+        Self::new(BitcoinPredicateType::Block)
+    }
+
+    /// Matches the transaction `txid`, a hex string prefixed with `0x`.
+    pub fn txid(txid: &str) -> Self {
+        Self::new(BitcoinPredicateType::Txid(ExactMatchingRule::Equals(
+            txid.to_string(),
+        )))
+    }
+
+    /// Matches the transactions spending the output `vout` of the transaction `txid`.
+    pub fn spending(txid: &str, vout: u32) -> Self {
+        Self::new(BitcoinPredicateType::Inputs(InputPredicate::Txid(
+            TxinPredicate {
+                txid: txid.to_string(),
+                vout,
+            },
+        )))
+    }
+
+    /// Matches the transactions with an `OP_RETURN` output whose data matches `rule`.
+    pub fn op_return(rule: MatchingRule) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::OpReturn(
+            rule,
+        )))
+    }
+
+    pub fn p2pkh(address: &str) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::P2pkh(
+            ExactMatchingRule::Equals(address.to_string()),
+        )))
+    }
+
+    pub fn p2sh(address: &str) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::P2sh(
+            ExactMatchingRule::Equals(address.to_string()),
+        )))
+    }
+
+    pub fn p2wpkh(address: &str) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::P2wpkh(
+            ExactMatchingRule::Equals(address.to_string()),
+        )))
+    }
+
+    pub fn p2wsh(address: &str) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::P2wsh(
+            ExactMatchingRule::Equals(address.to_string()),
+        )))
+    }
+
+    /// Matches the transactions paying to the scripts derived from the descriptor `expression`,
+    /// over the derivation indexes `range` when it has a wildcard.
+    pub fn descriptor(expression: &str, range: Option<[u32; 2]>) -> Self {
+        Self::new(BitcoinPredicateType::Outputs(OutputPredicate::Descriptor(
+            DescriptorMatchingRule {
+                expression: expression.to_string(),
+                range,
+            },
+        )))
+    }
+
+    pub fn stacks_protocol(operation: StacksOperations) -> Self {
+        Self::new(BitcoinPredicateType::StacksProtocol(operation))
+    }
+
+    pub fn inscription_feed() -> Self {
+        Self::new(BitcoinPredicateType::OrdinalsProtocol(
+            OrdinalOperations::InscriptionFeed(InscriptionFeedData {
+                meta_protocols: None,
+            }),
+        ))
+    }
+
+    /// Sets the name of the predicate, its uuid by default.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn namespace(&mut self, namespace: &str) -> &mut Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn network(&mut self, network: BitcoinNetwork) -> &mut Self {
+        self.network = network;
+        self
+    }
+
+    /// Evaluates the predicate on the blocks from `start_block` to `end_block`, both included.
+    pub fn between(&mut self, start_block: u64, end_block: u64) -> &mut Self {
+        self.spec.start_block(start_block).end_block(end_block);
+        self
+    }
+
+    pub fn start_block(&mut self, start_block: u64) -> &mut Self {
+        self.spec.start_block(start_block);
+        self
+    }
+
+    pub fn blocks(&mut self, blocks: Vec<u64>) -> &mut Self {
+        self.spec.blocks(blocks);
+        self
+    }
+
+    pub fn expire_after_occurrence(&mut self, occurrence: u64) -> &mut Self {
+        self.spec.expire_after_occurrence(occurrence);
+        self
+    }
+
+    pub fn include_proof(&mut self, do_include: bool) -> &mut Self {
+        self.spec.include_proof(do_include);
+        self
+    }
+
+    pub fn include_inputs(&mut self, do_include: bool) -> &mut Self {
+        self.spec.include_inputs(do_include);
+        self
+    }
+
+    pub fn include_outputs(&mut self, do_include: bool) -> &mut Self {
+        self.spec.include_outputs(do_include);
+        self
+    }
+
+    pub fn include_witness(&mut self, do_include: bool) -> &mut Self {
+        self.spec.include_witness(do_include);
+        self
+    }
+
+    /// Retries the deliveries of the actions which don't set their own `retry_policy`.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Posts the occurrences to `url`, with the `Authorization` header `authorization_header`.
+    pub fn http_post(&mut self, url: &str, authorization_header: &str) -> &mut Self {
+        self.spec.action = HookAction::HttpPost(HttpHook {
+            url: url.to_string(),
+            authorization_header: authorization_header.to_string(),
+            signing_secret: None,
+            tls: None,
+            compression: None,
+            require_ack: None,
+            delivery: DeliveryOptions::default(),
+        });
+        self
+    }
+
+    /// Appends the occurrences to the file `path`.
+    pub fn file_append(&mut self, path: &str) -> &mut Self {
+        self.spec.action = HookAction::FileAppend(FileHook {
+            path: path.to_string(),
+            rotation: None,
+            compression: None,
+            delivery: DeliveryOptions::default(),
+        });
+        self
+    }
+
+    pub fn then_that(&mut self, action: HookAction) -> &mut Self {
+        self.spec.action = action;
+        self
+    }
+
+    /// Returns the predicate `uuid`, once validated.
+    pub fn build(&self, uuid: &str) -> Result<ChainhookSpecificationNetworkMap, String> {
+        let mut network_spec = self.spec.clone();
+        if let Some(ref retry_policy) = self.retry_policy {
+            network_spec.action.set_default_retry_policy(retry_policy);
+            if let Some(ref mut rollback_action) = network_spec.rollback_action {
+                rollback_action.set_default_retry_policy(retry_policy);
+            }
+        }
+        let spec =
+            ChainhookSpecificationNetworkMap::Bitcoin(BitcoinChainhookSpecificationNetworkMap {
+                uuid: uuid.to_string(),
+                owner_uuid: None,
+                labels: None,
+                metadata: None,
+                namespace: self.namespace.clone(),
+                name: self.name.clone().unwrap_or_else(|| uuid.to_string()),
+                version: 1,
+                networks: BTreeMap::from([(self.network.clone(), network_spec)]),
+            });
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+/// Typed builder of Stacks predicates, producing the same [ChainhookSpecificationNetworkMap] as
+/// their JSON definition:
+///
+/// ```
+/// use chainhook_sdk::chainhooks::types::StacksPredicateBuilder;
+///
+/// let predicate = StacksPredicateBuilder::contract_call(
+///     "SP000000000000000000002Q6VF78.pox-3",
+///     "stack-stx",
+/// )
+/// .start_block(100_000)
+/// .decode_clarity_values(true)
+/// .http_post("https://example.com/api/events", "Bearer 1234")
+/// .build("my-predicate")
+/// .expect("invalid predicate");
+/// ```
+///
+/// Predicates target mainnet and have a `noop` action unless specified otherwise.
+#[derive(Clone, Debug)]
+pub struct StacksPredicateBuilder {
+    name: Option<String>,
+    namespace: Option<String>,
+    network: StacksNetwork,
+    spec: StacksChainhookSpecification,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl StacksPredicateBuilder {
+    pub fn new(predicate: StacksPredicate) -> Self {
+        StacksPredicateBuilder {
+            name: None,
+            namespace: None,
+            network: StacksNetwork::Mainnet,
+            spec: StacksChainhookSpecification::new(predicate, HookAction::Noop),
+            retry_policy: None,
+        }
+    }
+
+    pub fn block_height(rule: BlockIdentifierIndexRule) -> Self {
+        Self::new(StacksPredicate::BlockHeight(rule))
+    }
+
+    /// Matches the transaction `txid`, a hex string prefixed with `0x`.
+    pub fn txid(txid: &str) -> Self {
+        Self::new(StacksPredicate::Txid(ExactMatchingRule::Equals(
+            txid.to_string(),
+        )))
+    }
+
+    pub fn contract_call(contract_identifier: &str, method: &str) -> Self {
+        Self::new(StacksPredicate::ContractCall(
+            StacksContractCallBasedPredicate {
+                contract_identifier: contract_identifier.to_string(),
+                method: method.to_string(),
+            },
+        ))
+    }
+
+    /// Matches the contracts deployed by `deployer`, or by anyone with `*`.
+    pub fn contract_deployment(deployer: &str) -> Self {
+        Self::new(StacksPredicate::ContractDeployment(
+            StacksContractDeploymentPredicate::Deployer(deployer.to_string()),
+        ))
+    }
+
+    pub fn contract_deployment_implementing(stacks_trait: StacksTrait) -> Self {
+        Self::new(StacksPredicate::ContractDeployment(
+            StacksContractDeploymentPredicate::ImplementTrait(stacks_trait),
+        ))
+    }
+
+    /// Matches the print events of `contract_identifier` containing `contains`.
+    pub fn print_event(contract_identifier: &str, contains: &str) -> Self {
+        Self::new(StacksPredicate::PrintEvent(
+            StacksPrintEventBasedPredicate::Contains {
+                contract_identifier: contract_identifier.to_string(),
+                contains: contains.to_string(),
+            },
+        ))
+    }
+
+    /// Matches the print events of `contract_identifier` matching the regular expression `regex`.
+    pub fn print_event_matching(contract_identifier: &str, regex: &str) -> Self {
+        Self::new(StacksPredicate::PrintEvent(
+            StacksPrintEventBasedPredicate::MatchesRegex {
+                contract_identifier: contract_identifier.to_string(),
+                regex: regex.to_string(),
+            },
+        ))
+    }
+
+    pub fn ft_event(asset_identifier: &str, actions: &[&str]) -> Self {
+        Self::new(StacksPredicate::FtEvent(StacksFtEventBasedPredicate {
+            asset_identifier: asset_identifier.to_string(),
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+        }))
+    }
+
+    pub fn nft_event(asset_identifier: &str, actions: &[&str]) -> Self {
+        Self::new(StacksPredicate::NftEvent(StacksNftEventBasedPredicate {
+            asset_identifier: asset_identifier.to_string(),
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+        }))
+    }
+
+    pub fn stx_event(actions: &[&str]) -> Self {
+        Self::new(StacksPredicate::StxEvent(StacksStxEventBasedPredicate {
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+        }))
+    }
+
+    /// Sets the name of the predicate, its uuid by default.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn namespace(&mut self, namespace: &str) -> &mut Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn network(&mut self, network: StacksNetwork) -> &mut Self {
+        self.network = network;
+        self
+    }
+
+    /// Evaluates the predicate on the blocks from `start_block` to `end_block`, both included.
+    pub fn between(&mut self, start_block: u64, end_block: u64) -> &mut Self {
+        self.spec.start_block(start_block).end_block(end_block);
+        self
+    }
+
+    pub fn start_block(&mut self, start_block: u64) -> &mut Self {
+        self.spec.start_block(start_block);
+        self
+    }
+
+    pub fn blocks(&mut self, blocks: Vec<u64>) -> &mut Self {
+        self.spec.blocks(blocks);
+        self
+    }
+
+    pub fn expire_after_occurrence(&mut self, occurrence: u64) -> &mut Self {
+        self.spec.expire_after_occurrence(occurrence);
+        self
+    }
+
+    pub fn capture_all_events(&mut self, do_capture: bool) -> &mut Self {
+        self.spec.capture_all_events(do_capture);
+        self
+    }
+
+    pub fn decode_clarity_values(&mut self, do_decode: bool) -> &mut Self {
+        self.spec.decode_clarity_values(do_decode);
+        self
+    }
+
+    pub fn include_contract_abi(&mut self, do_include: bool) -> &mut Self {
+        self.spec.include_contract_abi(do_include);
+        self
+    }
+
+    /// Retries the deliveries of the actions which don't set their own `retry_policy`.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Posts the occurrences to `url`, with the `Authorization` header `authorization_header`.
+    pub fn http_post(&mut self, url: &str, authorization_header: &str) -> &mut Self {
+        self.spec.action = HookAction::HttpPost(HttpHook {
+            url: url.to_string(),
+            authorization_header: authorization_header.to_string(),
+            signing_secret: None,
+            tls: None,
+            compression: None,
+            require_ack: None,
+            delivery: DeliveryOptions::default(),
+        });
+        self
+    }
+
+    /// Appends the occurrences to the file `path`.
+    pub fn file_append(&mut self, path: &str) -> &mut Self {
+        self.spec.action = HookAction::FileAppend(FileHook {
+            path: path.to_string(),
+            rotation: None,
+            compression: None,
+            delivery: DeliveryOptions::default(),
+        });
+        self
+    }
+
+    pub fn then_that(&mut self, action: HookAction) -> &mut Self {
+        self.spec.action = action;
+        self
+    }
+
+    /// Returns the predicate `uuid`, once validated.
+    pub fn build(&self, uuid: &str) -> Result<ChainhookSpecificationNetworkMap, String> {
+        let mut network_spec = self.spec.clone();
+        if let Some(ref retry_policy) = self.retry_policy {
+            network_spec.action.set_default_retry_policy(retry_policy);
+            if let Some(ref mut rollback_action) = network_spec.rollback_action {
+                rollback_action.set_default_retry_policy(retry_policy);
+            }
+        }
+        let spec =
+            ChainhookSpecificationNetworkMap::Stacks(StacksChainhookSpecificationNetworkMap {
+                uuid: uuid.to_string(),
+                owner_uuid: None,
+                labels: None,
+                metadata: None,
+                namespace: self.namespace.clone(),
+                name: self.name.clone().unwrap_or_else(|| uuid.to_string()),
+                version: 1,
+                networks: BTreeMap::from([(self.network.clone(), network_spec)]),
+            });
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    HttpPost(HttpHook),
+    FileAppend(FileHook),
+    ObjectStore(ObjectStoreHook),
+    Kafka(KafkaHook),
+    Nats(NatsHook),
+    Sqs(SqsHook),
+    Sns(SnsHook),
+    Postgres(PostgresHook),
+    Noop,
+    Route(HookRoutes),
+    /// Several actions, each of them delivering every occurrence of the predicate.
+    #[serde(untagged)]
+    Multiple(Vec<HookAction>),
+}
+
+impl HookAction {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        match &self {
+            HookAction::HttpPost(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'http_post' data", e));
+                }
+            }
+            HookAction::FileAppend(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'file_append' data", e));
+                }
+            }
+            HookAction::ObjectStore(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'object_store' data", e));
+                }
+            }
+            HookAction::Kafka(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'kafka' data", e));
+                }
+            }
+            HookAction::Nats(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'nats' data", e));
+                }
+            }
+            HookAction::Sqs(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'sqs' data", e));
+                }
+            }
+            HookAction::Sns(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'sns' data", e));
+                }
+            }
+            HookAction::Postgres(spec) => {
+                if let Err(e) = spec.validate() {
+                    return Err(append_error_context("invalid 'postgres' data", e));
+                }
+            }
+            HookAction::Noop => {}
+            HookAction::Route(routes) => {
+                if let Err(e) = routes.validate() {
+                    return Err(append_error_context("invalid 'route' data", e));
+                }
+            }
+            HookAction::Multiple(actions) => {
+                if actions.is_empty() {
+                    return Err(vec!["at least one action must be specified".into()]);
+                }
+                let mut errors = vec![];
+                for (i, action) in actions.iter().enumerate() {
+                    let res = match action {
+                        HookAction::Multiple(_) => Err(vec!["actions cannot be nested".into()]),
+                        action => action.validate(),
+                    };
+                    if let Err(e) = res {
+                        errors.append(&mut append_error_context(
+                            &format!("invalid action #{}", i),
+                            e,
+                        ));
+                    }
+                }
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the actions delivering the occurrences: the listed ones for [HookAction::Multiple],
+    /// the action itself otherwise.
+    pub fn actions(&self) -> &[HookAction] {
+        match self {
+            HookAction::Multiple(actions) => actions,
+            action => std::slice::from_ref(action),
+        }
+    }
+
+    /// Returns the retries and dead-letter sink of the action, None for the actions not
+    /// delivering the occurrences themselves.
+    pub fn delivery_options(&self) -> Option<&DeliveryOptions> {
+        match self {
+            HookAction::HttpPost(hook) => Some(&hook.delivery),
+            HookAction::FileAppend(hook) => Some(&hook.delivery),
+            HookAction::ObjectStore(hook) => Some(&hook.delivery),
+            HookAction::Kafka(hook) => Some(&hook.delivery),
+            HookAction::Nats(hook) => Some(&hook.delivery),
+            HookAction::Sqs(hook) => Some(&hook.delivery),
+            HookAction::Sns(hook) => Some(&hook.delivery),
+            HookAction::Postgres(hook) => Some(&hook.delivery),
+            HookAction::Noop | HookAction::Route(_) | HookAction::Multiple(_) => None,
+        }
+    }
+
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.delivery_options()
+            .and_then(|delivery| delivery.retry_policy.as_ref())
+    }
+
+    pub fn dead_letter(&self) -> Option<&DeadLetterSink> {
+        self.delivery_options()
+            .and_then(|delivery| delivery.dead_letter.as_deref())
+    }
+
+    /// Sets `retry_policy` on the actions delivering the occurrences which don't have one.
+    fn set_default_retry_policy(&mut self, retry_policy: &RetryPolicy) {
+        let delivery = match self {
+            HookAction::HttpPost(hook) => &mut hook.delivery,
+            HookAction::FileAppend(hook) => &mut hook.delivery,
+            HookAction::ObjectStore(hook) => &mut hook.delivery,
+            HookAction::Kafka(hook) => &mut hook.delivery,
+            HookAction::Nats(hook) => &mut hook.delivery,
+            HookAction::Sqs(hook) => &mut hook.delivery,
+            HookAction::Sns(hook) => &mut hook.delivery,
+            HookAction::Postgres(hook) => &mut hook.delivery,
+            HookAction::Noop => return,
+            HookAction::Route(routes) => {
+                for rule in routes.rules.iter_mut() {
+                    rule.action.set_default_retry_policy(retry_policy);
+                }
+                if let Some(ref mut otherwise) = routes.otherwise {
+                    otherwise.set_default_retry_policy(retry_policy);
+                }
+                return;
+            }
+            HookAction::Multiple(actions) => {
+                for action in actions.iter_mut() {
+                    action.set_default_retry_policy(retry_policy);
+                }
+                return;
+            }
+        };
+        delivery
+            .retry_policy
+            .get_or_insert_with(|| retry_policy.clone());
+    }
+
+    /// Returns true if deliveries must be acknowledged by the receiver, see
+    /// [HttpHook::require_ack].
+    pub fn requires_ack(&self) -> bool {
+        match self {
+            HookAction::HttpPost(http) => http.require_ack.unwrap_or(false),
+            HookAction::Route(routes) => routes.actions().any(|a| a.requires_ack()),
+            HookAction::Multiple(actions) => actions.iter().any(|a| a.requires_ack()),
+            _ => false,
+        }
+    }
+
+    /// Returns the `http_post` hooks of the action, including the ones nested in
+    /// [HookAction::Multiple] and [HookAction::Route] actions.
+    pub fn http_hooks(&self) -> Vec<&HttpHook> {
+        match self {
+            HookAction::HttpPost(http) => vec![http],
+            HookAction::Route(routes) => routes.actions().flat_map(|a| a.http_hooks()).collect(),
+            HookAction::Multiple(actions) => actions.iter().flat_map(|a| a.http_hooks()).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+static NOOP_ACTION: HookAction = HookAction::Noop;
+
+/// Routes the occurrences of a predicate to the action of the first rule whose condition holds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HookRoutes {
+    pub rules: Vec<HookRoute>,
+    /// Action delivering the occurrences matching none of the rules, which are not delivered
+    /// if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otherwise: Option<Box<HookAction>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HookRoute {
+    /// jq expression evaluated against the occurrence payload, holding if it outputs a value
+    /// other than `false` or `null`.
+    pub when: String,
+    #[serde(rename = "then_that")]
+    pub action: HookAction,
+}
+
+impl HookRoutes {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        if self.rules.is_empty() {
+            return Err(vec!["at least one rule must be specified".into()]);
+        }
+        let mut errors = vec![];
+        for (i, rule) in self.rules.iter().enumerate() {
+            let context = format!("invalid rule #{}", i);
+            if let Err(e) = validate_transform(&rule.when) {
+                errors.append(&mut append_error_context(&context, vec![e]));
+            }
+            if let Err(e) = validate_routed_action(&rule.action) {
+                errors.append(&mut append_error_context(&context, e));
+            }
+        }
+        if let Some(ref otherwise) = self.otherwise {
+            if let Err(e) = validate_routed_action(otherwise) {
+                errors.append(&mut append_error_context("invalid 'otherwise' action", e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(())
+    }
+
+    fn actions(&self) -> impl Iterator<Item = &HookAction> {
+        self.rules
+            .iter()
+            .map(|rule| &rule.action)
+            .chain(self.otherwise.as_deref())
+    }
+
+    /// Returns the action delivering an occurrence, given its payload. Occurrences matching
+    /// none of the rules, without an `otherwise` action, are routed to [HookAction::Noop].
+    pub fn route(&self, payload: &JsonValue) -> &HookAction {
+        for rule in self.rules.iter() {
+            if let Ok(true) = evaluate_condition(&rule.when, payload) {
+                return &rule.action;
+            }
+        }
+        match self.otherwise {
+            Some(ref action) => action,
+            None => &NOOP_ACTION,
+        }
+    }
+}
+
+fn validate_routed_action(action: &HookAction) -> Result<(), Vec<String>> {
+    match action {
+        HookAction::Route(_) | HookAction::Multiple(_) => {
+            Err(vec!["routed actions cannot be nested".into()])
+        }
+        action => action.validate(),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HttpHook {
+    pub url: String,
+    pub authorization_header: String,
+    /// When set, payloads are signed with this secret and the signature is sent in the
+    /// `X-Chainhook-Signature` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_secret: Option<String>,
+    /// Certificates used to reach the endpoint. Overrides the observer's default `http_tls`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<HttpTlsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<PayloadCompression>,
+    /// When true, a delivery only succeeds once the endpoint acknowledges it with a 2xx
+    /// response whose JSON body carries a `cursor`: the height of the last block processed.
+    /// Chainhook resumes deliveries from this cursor after an outage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_ack: Option<bool>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl HttpHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if let Err(e) = reqwest::Url::from_str(&self.url) {
+            errors.push(format!("url string must be a valid Url: {}", e));
+        }
+        if let Err(e) = reqwest::header::HeaderValue::from_str(&self.authorization_header) {
+            errors.push(format!(
+                "auth header must be a valid header value: {}",
+                e
+            ));
+        };
+        if let Some(ref secret) = self.signing_secret {
+            if secret.is_empty() {
+                errors.push("signing secret must not be empty".to_string());
+            }
+        }
+        if let Some(ref tls) = self.tls {
+            if let Err(e) = tls.validate() {
+                errors.extend(e);
+            }
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Compression applied to `http_post` bodies, advertised with the `Content-Encoding` header.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadCompression {
+    Gzip,
+    Zstd,
+}
+
+impl PayloadCompression {
+    pub fn content_encoding(&self) -> &'static str {
+        match self {
+            PayloadCompression::Gzip => "gzip",
+            PayloadCompression::Zstd => "zstd",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            PayloadCompression::Gzip => "gz",
+            PayloadCompression::Zstd => "zst",
+        }
+    }
+}
+
+/// Paths to PEM encoded certificates used when posting payloads, for endpoints served with a
+/// private CA or requiring mutual TLS authentication.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HttpTlsConfig {
+    /// CA bundle trusted in addition to the system roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
+}
+
+impl HttpTlsConfig {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            return Err(vec![
+                "client_cert_path and client_key_path must be provided together".to_string(),
+            ]);
+        }
+        Ok(())
+    }
+}
+
+/// Appends the occurrences to a newline-delimited JSON file.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FileHook {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<FileRotation>,
+    /// Compression applied to the rotated files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<PayloadCompression>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl FileHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.path.is_empty() {
+            errors.push("path must not be empty".to_string());
+        }
+        if self.path == STDOUT_FILE_SINK_PATH && self.rotation.is_some() {
+            errors.push("stdout can't be rotated".to_string());
+        }
+        if let Some(ref rotation) = self.rotation {
+            if let Err(e) = rotation.validate() {
+                errors.push(e);
+            }
+        } else if self.compression.is_some() {
+            errors.push("compression requires a rotation".to_string());
+        }
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(())
+    }
+}
+
+/// Limits of the files written by `file_append` actions. The file is rotated, renamed with the
+/// time of the rotation as suffix, before the write making it exceed one of the limits.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FileRotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_sec: Option<u64>,
+}
+
+impl FileRotation {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.max_size_bytes, self.max_age_sec) {
+            (None, None) => Err("rotation requires max_size_bytes or max_age_sec".to_string()),
+            (Some(0), _) => Err("rotation max_size_bytes must be greater than 0".to_string()),
+            (_, Some(0)) => Err("rotation max_age_sec must be greater than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Envelope wrapping the payloads delivered for a predicate.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEnvelope {
+    /// CloudEvents 1.0 event in structured mode, carrying the payload as its `data`.
+    CloudEvents,
+}
+
+/// Retries and dead-letter sink of the deliveries of an action, set alongside its other
+/// fields, e.g. `"http_post": { "url": "…", "retry_policy": { "max_attempts": 5 } }`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DeliveryOptions {
+    /// Retries applied to failed deliveries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Destination of the occurrences that could not be delivered once retries are exhausted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<Box<DeadLetterSink>>,
+}
+
+impl DeliveryOptions {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if let Some(ref retry_policy) = self.retry_policy {
+            if let Err(e) = retry_policy.validate() {
+                errors.append(&mut append_error_context("invalid 'retry_policy' value", e));
+            }
         }
-        for chainhook in self.stacks_chainhooks.iter() {
-            seq.serialize_element(chainhook)?;
+        if let Some(ref dead_letter) = self.dead_letter {
+            if let Err(e) = dead_letter.validate() {
+                errors.append(&mut append_error_context("invalid 'dead_letter' value", e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
-        seq.end()
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// Retries applied to the deliveries of an action before they are considered failed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum ChainhookInstance {
-    Bitcoin(BitcoinChainhookInstance),
-    Stacks(StacksChainhookInstance),
+pub struct RetryPolicy {
+    /// Number of delivery attempts, defaults to 3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u16>,
+    /// Delay before the first retry in milliseconds, defaults to 1000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+    /// Factor applied to the delay after each retry, defaults to 1 (fixed delay).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff_multiplier: Option<u32>,
+    /// Maximum duration of each attempt in milliseconds. Unbounded by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
 }
 
-impl ChainhookInstance {
-    pub fn either_stx_or_btc_key(uuid: &str) -> String {
-        format!("predicate:{}", uuid)
+impl RetryPolicy {
+    /// Retries `attempts_max` times, waiting `attempts_interval_sec` between attempts.
+    pub fn fixed(attempts_max: u16, attempts_interval_sec: u16) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: Some(attempts_max),
+            backoff_ms: Some(attempts_interval_sec as u64 * 1000),
+            backoff_multiplier: None,
+            timeout_ms: None,
+        }
     }
 
-    pub fn stacks_key(uuid: &str) -> String {
-        format!("predicate:{}", uuid)
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if let Some(0) = self.max_attempts {
+            errors.push("max_attempts must be greater than 0".to_string());
+        }
+        if let Some(0) = self.backoff_multiplier {
+            errors.push("backoff_multiplier must be greater than 0".to_string());
+        }
+        if let Some(0) = self.timeout_ms {
+            errors.push("timeout_ms must be greater than 0".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
+}
 
-    pub fn bitcoin_key(uuid: &str) -> String {
-        format!("predicate:{}", uuid)
-    }
+/// Destination of the occurrences that could not be delivered after exhausting the
+/// action's retry policy. Records posted to an `http_post` sink are retried according to the
+/// sink's own `retry_policy`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterSink {
+    FileAppend(FileHook),
+    RedisList(RedisListHook),
+    HttpPost(HttpHook),
+}
 
-    pub fn key(&self) -> String {
+impl DeadLetterSink {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let delivery = match &self {
+            DeadLetterSink::FileAppend(spec) => &spec.delivery,
+            DeadLetterSink::RedisList(_) => &DeliveryOptions::default(),
+            DeadLetterSink::HttpPost(spec) => &spec.delivery,
+        };
+        if delivery.dead_letter.is_some() {
+            return Err(vec![
+                "dead-letter sinks can't have a dead_letter".to_string()
+            ]);
+        }
         match &self {
-            Self::Bitcoin(data) => Self::bitcoin_key(&data.uuid),
-            Self::Stacks(data) => Self::stacks_key(&data.uuid),
+            DeadLetterSink::FileAppend(_) => Ok(()),
+            DeadLetterSink::RedisList(spec) => spec
+                .validate()
+                .map_err(|e| append_error_context("invalid 'redis_list' data", e)),
+            DeadLetterSink::HttpPost(spec) => spec
+                .validate()
+                .map_err(|e| append_error_context("invalid 'http_post' data", e)),
         }
     }
+}
 
-    pub fn deserialize_specification(spec: &str) -> Result<ChainhookInstance, String> {
-        let spec: ChainhookInstance = serde_json::from_str(spec)
-            .map_err(|e| format!("unable to deserialize predicate {}", e))?;
-        Ok(spec)
-    }
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RedisListHook {
+    /// Redis connection url, such as `redis://localhost:6379/`.
+    pub redis_url: String,
+    /// Key of the list failed deliveries are pushed to.
+    pub key: String,
+}
 
-    pub fn uuid(&self) -> &str {
-        match &self {
-            Self::Bitcoin(data) => &data.uuid,
-            Self::Stacks(data) => &data.uuid,
+impl RedisListHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if !self.redis_url.starts_with("redis://") && !self.redis_url.starts_with("rediss://") {
+            errors.push("redis_url must use the redis:// or rediss:// scheme".to_string());
+        }
+        if self.key.is_empty() {
+            errors.push("key must not be empty".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case", tag = "chain")]
-pub enum ChainhookSpecificationNetworkMap {
-    Bitcoin(BitcoinChainhookSpecificationNetworkMap),
-    Stacks(StacksChainhookSpecificationNetworkMap),
+#[serde(rename_all = "snake_case")]
+pub struct ObjectStoreHook {
+    /// Bucket url, such as `s3://my-bucket` or `gs://my-bucket/path`.
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<ObjectStoreGranularity>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
 }
 
-impl ChainhookSpecificationNetworkMap {
-    pub fn validate(&self) -> Result<(), String> {
-        match &self {
-            Self::Bitcoin(data) => {
-                let mut errors = vec![];
-                for (network, spec) in data.networks.iter() {
-                    if let Err(e) = spec.validate() {
-                        errors.append(&mut append_error_context(
-                            &format!(
-                                "invalid Bitcoin predicate '{}' for network {}",
-                                data.name, network
-                            ),
-                            e,
-                        ));
-                    }
-                }
-                if !errors.is_empty() {
-                    return Err(errors.join("\n"));
+impl ObjectStoreHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if !cfg!(feature = "object-store") {
+            errors.push("object store support is not enabled on this chainhook instance".to_string());
+        }
+        match reqwest::Url::from_str(&self.url) {
+            Ok(url) => {
+                if !["s3", "gs"].contains(&url.scheme()) {
+                    errors.push(format!(
+                        "url scheme {} is not supported (expected s3 or gs)",
+                        url.scheme()
+                    ));
                 }
             }
-            Self::Stacks(data) => {
-                let mut errors = vec![];
-                for (network, spec) in data.networks.iter() {
-                    if let Err(e) = spec.validate() {
-                        errors.append(&mut append_error_context(
-                            &format!(
-                                "invalid Stacks predicate '{}' for network {}",
-                                data.name, network
-                            ),
-                            e,
-                        ));
-                    }
-                }
-                if !errors.is_empty() {
-                    return Err(errors.join("\n"));
-                }
+            Err(e) => errors.push(format!("url string must be a valid Url: {}", e)),
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Controls whether an object is written for each occurrence, or for each block of an occurrence.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectStoreGranularity {
+    Occurrence,
+    Block,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct KafkaHook {
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub key: KafkaMessageKey,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl KafkaHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if !cfg!(feature = "kafka") {
+            errors.push("kafka support is not enabled on this chainhook instance".to_string());
+        }
+        if self.brokers.is_empty() {
+            errors.push("at least one broker must be provided".to_string());
+        }
+        for broker in self.brokers.iter() {
+            if broker.split_once(':').is_none() {
+                errors.push(format!(
+                    "broker {} must be formatted as <host>:<port>",
+                    broker
+                ));
             }
         }
-        Ok(())
+        if self.topic.is_empty() {
+            errors.push("topic must not be empty".to_string());
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
+}
 
-    pub fn get_uuid(&self) -> &str {
-        match &self {
-            Self::Bitcoin(data) => &data.uuid,
-            Self::Stacks(data) => &data.uuid,
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct NatsHook {
+    pub server_url: String,
+    /// Subject to publish to. Supports the `{uuid}`, `{block_height}` and `{block_hash}` placeholders.
+    pub subject: String,
+    /// Wait for a JetStream acknowledgement of each published message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jetstream: Option<bool>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl NatsHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if !cfg!(feature = "nats") {
+            errors.push("nats support is not enabled on this chainhook instance".to_string());
+        }
+        if self.server_url.is_empty() {
+            errors.push("server_url must not be empty".to_string());
+        }
+        if self.subject.is_empty() || self.subject.contains(char::is_whitespace) {
+            errors.push("subject must be a non empty string without whitespaces".to_string());
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
+}
 
-    pub fn deserialize_specification(
-        spec: &str,
-        _key: &str,
-    ) -> Result<ChainhookSpecificationNetworkMap, String> {
-        let spec: ChainhookSpecificationNetworkMap = serde_json::from_str(spec)
-            .map_err(|e| format!("unable to deserialize predicate {}", e))?;
-        Ok(spec)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SqsHook {
+    pub queue_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl SqsHook {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if !cfg!(feature = "aws") {
+            errors.push("aws support is not enabled on this chainhook instance".to_string());
+        }
+        if let Err(e) = reqwest::Url::from_str(&self.queue_url) {
+            errors.push(format!("queue_url string must be a valid Url: {}", e));
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum HookAction {
-    HttpPost(HttpHook),
-    FileAppend(FileHook),
-    Noop,
+pub struct SnsHook {
+    pub topic_arn: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
 }
 
-impl HookAction {
+impl SnsHook {
     pub fn validate(&self) -> Result<(), Vec<String>> {
-        match &self {
-            HookAction::HttpPost(spec) => {
-                if let Err(e) = spec.validate() {
-                    return Err(append_error_context("invalid 'http_post' data", e));
-                }
-            }
-            HookAction::FileAppend(_) => {}
-            HookAction::Noop => {}
+        let mut errors = vec![];
+        if !cfg!(feature = "aws") {
+            errors.push("aws support is not enabled on this chainhook instance".to_string());
+        }
+        if !self.topic_arn.starts_with("arn:") || self.topic_arn.split(':').count() != 6 {
+            errors.push("topic_arn must be a valid SNS topic ARN".to_string());
+        }
+
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
-        Ok(())
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct HttpHook {
-    pub url: String,
-    pub authorization_header: String,
+pub struct PostgresHook {
+    pub dsn: String,
+    pub table: String,
+    /// JSONB column receiving the occurrence payload. Defaults to `payload`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
 }
 
-impl HttpHook {
+impl PostgresHook {
+    pub fn get_column(&self) -> &str {
+        self.column.as_deref().unwrap_or("payload")
+    }
+
     pub fn validate(&self) -> Result<(), Vec<String>> {
         let mut errors = vec![];
-        if let Err(e) = reqwest::Url::from_str(&self.url) {
-            errors.push(format!("url string must be a valid Url: {}", e));
+        if !cfg!(feature = "postgres") {
+            errors.push("postgres support is not enabled on this chainhook instance".to_string());
         }
-        if let Err(e) = reqwest::header::HeaderValue::from_str(&self.authorization_header) {
+        if self.dsn.is_empty() {
+            errors.push("dsn must not be empty".to_string());
+        }
+        if !is_sql_identifier(&self.table, true) {
+            errors.push(format!("table {} is not a valid table name", self.table));
+        }
+        if !is_sql_identifier(self.get_column(), false) {
             errors.push(format!(
-                "auth header must be a valid header value: {}",
-                e
+                "column {} is not a valid column name",
+                self.get_column()
             ));
-        };
+        }
 
+        if let Err(e) = self.delivery.validate() {
+            errors.extend(e);
+        }
         if errors.is_empty() {
             Ok(())
         } else {
@@ -317,10 +1732,29 @@ impl HttpHook {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+fn is_sql_identifier(identifier: &str, allow_schema: bool) -> bool {
+    let parts = identifier.split('.').collect::<Vec<_>>();
+    if parts.len() > 2 || (parts.len() == 2 && !allow_schema) {
+        return false;
+    }
+    parts.iter().all(|part| {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Value used as the key of the messages produced to Kafka.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct FileHook {
-    pub path: String,
+pub enum KafkaMessageKey {
+    #[default]
+    PredicateUuid,
+    BlockHash,
 }
 // todo: can we remove this struct?
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -430,15 +1864,46 @@ impl Default for PoxConfig {
     }
 }
 
-pub fn get_canonical_pox_config(network: &BitcoinNetwork) -> PoxConfig {
-    match network {
-        BitcoinNetwork::Mainnet => PoxConfig::mainnet_default(),
-        BitcoinNetwork::Testnet => PoxConfig::testnet_default(),
-        BitcoinNetwork::Regtest => PoxConfig::default(),
-        BitcoinNetwork::Signet => unreachable!(),
+/// Network-specific constants used while standardizing Bitcoin blocks, such as the magic bytes
+/// prefixing the Stacks operations and the PoX parameters.
+///
+/// [NetworkParameters::canonical] covers the public networks and the Stacks devnet. Regtest and
+/// signet setups deviating from them, e.g. for integration testing, can plug their own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NetworkParameters {
+    pub stacks_magic_bytes: [u8; 2],
+    pub pox_config: PoxConfig,
+}
+
+impl NetworkParameters {
+    /// Parameters of the Stacks chain canonically anchored to `network`. Signet has no canonical
+    /// Stacks chain, and gets the testnet magic bytes along with the devnet PoX parameters.
+    pub fn canonical(network: &BitcoinNetwork) -> NetworkParameters {
+        match network {
+            BitcoinNetwork::Mainnet => NetworkParameters {
+                stacks_magic_bytes: *b"X2",
+                pox_config: PoxConfig::mainnet_default(),
+            },
+            BitcoinNetwork::Testnet => NetworkParameters {
+                stacks_magic_bytes: *b"T2",
+                pox_config: PoxConfig::testnet_default(),
+            },
+            BitcoinNetwork::Regtest => NetworkParameters {
+                stacks_magic_bytes: *b"id",
+                pox_config: PoxConfig::devnet_default(),
+            },
+            BitcoinNetwork::Signet => NetworkParameters {
+                stacks_magic_bytes: *b"T2",
+                pox_config: PoxConfig::devnet_default(),
+            },
+        }
     }
 }
 
+pub fn get_canonical_pox_config(network: &BitcoinNetwork) -> PoxConfig {
+    NetworkParameters::canonical(network).pox_config
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[repr(u8)]
 pub enum StacksOpcodes {
@@ -1059,6 +2524,22 @@ pub fn opcode_to_hex(asm: &str) -> Option<u8> {
     }
 }
 
+/// Builds the deterministic id of the delivery of an occurrence, from the predicate uuid, the hash
+/// of the occurrence's tip block and the sequence of the reorg it was evaluated on, if any.
+/// Delivering the same occurrence again, after a restart for instance, yields the same id,
+/// letting consumers discard the duplicates. Reorgs get increasing sequences, so that blocks
+/// applied again when the chain flip-flops between two forks are delivered under new ids.
+pub fn build_delivery_id(
+    predicate_uuid: &str,
+    tip_block_identifier: Option<&BlockIdentifier>,
+    reorg_sequence: u64,
+) -> String {
+    let block_hash = tip_block_identifier
+        .map(|b| b.hash.as_str())
+        .unwrap_or_default();
+    format!("{}:{}:{}", predicate_uuid, block_hash, reorg_sequence)
+}
+
 pub fn append_error_context(context: &str, errors: Vec<String>) -> Vec<String> {
     errors
         .iter()
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::observer::BitcoinConfig;
+
+/// Delay during which an endpoint that failed to answer is only used if no other one is healthy.
+const UNHEALTHY_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// The RPC interface of a bitcoind node, used when the main one is unhealthy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitcoindRpcEndpoint {
+    pub rpc_url: String,
+    pub rpc_username: String,
+    pub rpc_password: String,
+}
+
+/// Health is shared by the observer and the scans running in other threads, so that a node
+/// failing to answer one of them is avoided by the others.
+fn unhealthy_endpoints() -> &'static Mutex<HashMap<String, Instant>> {
+    static UNHEALTHY_ENDPOINTS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    UNHEALTHY_ENDPOINTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn is_healthy(rpc_url: &str, now: Instant) -> bool {
+    let unhealthy_endpoints = unhealthy_endpoints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    match unhealthy_endpoints.get(rpc_url) {
+        Some(unhealthy_until) => now >= *unhealthy_until,
+        None => true,
+    }
+}
+
+/// Records a request to the endpoint that failed, so that the next ones fail over to another
+/// endpoint until its cooldown elapses.
+pub fn record_endpoint_failure(rpc_url: &str) {
+    unhealthy_endpoints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(
+            rpc_url.to_string(),
+            Instant::now() + UNHEALTHY_ENDPOINT_COOLDOWN,
+        );
+}
+
+/// Records a request to the endpoint that succeeded, marking it healthy again.
+pub fn record_endpoint_success(rpc_url: &str) {
+    unhealthy_endpoints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(rpc_url);
+}
+
+impl BitcoinConfig {
+    /// Number of endpoints a request can be sent to.
+    pub fn endpoints_count(&self) -> usize {
+        1 + self.rpc_fallbacks.len()
+    }
+
+    /// Returns the config targeting the first healthy endpoint, starting with the main one. The
+    /// main endpoint is used if none of them is healthy.
+    pub fn with_healthy_endpoint(&self) -> BitcoinConfig {
+        let now = Instant::now();
+        // Fallbacks are bitcoind nodes, which don't replace an Esplora API
+        if self.esplora_api_url.is_some() || is_healthy(&self.rpc_url, now) {
+            return self.clone();
+        }
+        match self
+            .rpc_fallbacks
+            .iter()
+            .find(|endpoint| is_healthy(&endpoint.rpc_url, now))
+        {
+            Some(endpoint) => BitcoinConfig {
+                username: endpoint.rpc_username.clone(),
+                password: endpoint.rpc_password.clone(),
+                rpc_url: endpoint.rpc_url.clone(),
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_types::{BitcoinBlockSignaling, BitcoinNetwork};
+
+    use super::{record_endpoint_failure, record_endpoint_success, BitcoindRpcEndpoint};
+    use crate::observer::BitcoinConfig;
+
+    fn endpoint(rpc_url: &str) -> BitcoindRpcEndpoint {
+        BitcoindRpcEndpoint {
+            rpc_url: rpc_url.into(),
+            rpc_username: "devnet".into(),
+            rpc_password: "devnet".into(),
+        }
+    }
+
+    #[test]
+    fn it_fails_over_to_healthy_endpoints() {
+        let config = BitcoinConfig {
+            username: "devnet".into(),
+            password: "devnet".into(),
+            rpc_url: "http://failover-main:18443".into(),
+            rpc_fallbacks: vec![
+                endpoint("http://failover-a:18443"),
+                endpoint("http://failover-b:18443"),
+            ],
+            esplora_api_url: None,
+            network: BitcoinNetwork::Regtest,
+            bitcoin_block_signaling: BitcoinBlockSignaling::Polling(1000),
+            prevout_cache_size: None,
+        };
+        assert_eq!(config.endpoints_count(), 3);
+        assert_eq!(config.with_healthy_endpoint().rpc_url, config.rpc_url);
+
+        record_endpoint_failure("http://failover-main:18443");
+        record_endpoint_failure("http://failover-a:18443");
+        assert_eq!(
+            config.with_healthy_endpoint().rpc_url,
+            "http://failover-b:18443"
+        );
+
+        record_endpoint_failure("http://failover-b:18443");
+        assert_eq!(config.with_healthy_endpoint().rpc_url, config.rpc_url);
+
+        record_endpoint_success("http://failover-a:18443");
+        assert_eq!(
+            config.with_healthy_endpoint().rpc_url,
+            "http://failover-a:18443"
+        );
+    }
+}
@@ -1,9 +1,11 @@
+pub mod endpoints;
+pub mod prevouts;
+pub mod source;
+
 use std::time::Duration;
 
-use crate::chainhooks::bitcoin::{
-    get_canonical_pox_config, get_stacks_canonical_magic_bytes, StacksOpcodes,
-};
-use crate::chainhooks::types::PoxConfig;
+use crate::chainhooks::bitcoin::StacksOpcodes;
+use crate::chainhooks::types::{NetworkParameters, PoxConfig};
 use crate::observer::BitcoinConfig;
 use crate::utils::Context;
 use bitcoincore_rpc::bitcoin::hashes::Hash;
@@ -21,6 +23,9 @@ use hiro_system_kit::slog;
 use reqwest::Client as HttpClient;
 use serde::Deserialize;
 
+use self::endpoints::{record_endpoint_failure, record_endpoint_success};
+use self::prevouts::resolve_missing_prevouts;
+use self::source::bitcoin_block_source;
 use super::fork_scratch_pad::CONFIRMED_SEGMENT_MINIMUM_LENGTH;
 
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
@@ -105,6 +110,8 @@ pub struct BitcoinTransactionInputPrevoutFullBreakdown {
     pub height: u64,
     #[serde(with = "bitcoin::amount::serde::as_btc")]
     pub value: Amount,
+    #[serde(default)]
+    pub script_pub_key: Option<GetRawTransactionResultVoutScriptPubKey>,
 }
 
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
@@ -153,10 +160,17 @@ pub async fn download_and_parse_block_with_retry(
 ) -> Result<BitcoinBlockFullBreakdown, String> {
     let mut errors_count = 0;
     let max_retries = 20;
-    let block = loop {
-        match download_and_parse_block(http_client, block_hash, bitcoin_config, ctx).await {
-            Ok(result) => break result,
+    let mut block = loop {
+        // Each attempt is sent to the first healthy endpoint, failing over to the next ones
+        let endpoint_config = bitcoin_config.with_healthy_endpoint();
+        let block_source = bitcoin_block_source(http_client, &endpoint_config, ctx);
+        match block_source.download_and_parse_block(block_hash).await {
+            Ok(result) => {
+                record_endpoint_success(&endpoint_config.rpc_url);
+                break result;
+            }
             Err(e) => {
+                record_endpoint_failure(&endpoint_config.rpc_url);
                 errors_count += 1;
                 if errors_count > 3 && errors_count < max_retries {
                     ctx.try_log(|logger| {
@@ -172,6 +186,15 @@ pub async fn download_and_parse_block_with_retry(
             }
         }
     };
+    // Esplora returns the prevouts of every input
+    if let (Some(cache_size), None) = (
+        bitcoin_config.prevout_cache_size,
+        &bitcoin_config.esplora_api_url,
+    ) {
+        resolve_missing_prevouts(http_client, &mut block, bitcoin_config, cache_size, ctx)
+            .await
+            .map_err(|e| format!("unable to resolve prevouts of block #{block_hash}: {e}"))?;
+    }
     Ok(block)
 }
 
@@ -184,9 +207,15 @@ pub async fn retrieve_block_hash_with_retry(
     let mut errors_count = 0;
     let max_retries = 10;
     let block_hash = loop {
-        match retrieve_block_hash(http_client, block_height, bitcoin_config, ctx).await {
-            Ok(result) => break result,
+        let endpoint_config = bitcoin_config.with_healthy_endpoint();
+        let block_source = bitcoin_block_source(http_client, &endpoint_config, ctx);
+        match block_source.retrieve_block_hash(*block_height).await {
+            Ok(result) => {
+                record_endpoint_success(&endpoint_config.rpc_url);
+                break result;
+            }
             Err(e) => {
+                record_endpoint_failure(&endpoint_config.rpc_url);
                 errors_count += 1;
                 if errors_count > 3 && errors_count < max_retries {
                     ctx.try_log(|logger| {
@@ -235,6 +264,64 @@ pub async fn retrieve_block_hash(
     Ok(block_hash)
 }
 
+pub async fn retrieve_best_block_hash(
+    http_client: &HttpClient,
+    bitcoin_config: &BitcoinConfig,
+    _ctx: &Context,
+) -> Result<String, String> {
+    let body = json!({
+        "jsonrpc": "1.0",
+        "id": "chainhook-cli",
+        "method": "getbestblockhash",
+        "params": []
+    });
+    let block_hash = http_client
+        .post(&bitcoin_config.rpc_url)
+        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
+        .header("Content-Type", "application/json")
+        .header("Host", &bitcoin_config.rpc_url[7..])
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("unable to send request ({})", e))?
+        .json::<bitcoincore_rpc::jsonrpc::Response>()
+        .await
+        .map_err(|e| format!("unable to parse response ({})", e))?
+        .result::<String>()
+        .map_err(|e| format!("unable to parse response ({})", e))?;
+
+    Ok(block_hash)
+}
+
+pub async fn retrieve_block_count(
+    http_client: &HttpClient,
+    bitcoin_config: &BitcoinConfig,
+    _ctx: &Context,
+) -> Result<u64, String> {
+    let body = json!({
+        "jsonrpc": "1.0",
+        "id": "chainhook-cli",
+        "method": "getblockcount",
+        "params": []
+    });
+    let block_count = http_client
+        .post(&bitcoin_config.rpc_url)
+        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
+        .header("Content-Type", "application/json")
+        .header("Host", &bitcoin_config.rpc_url[7..])
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("unable to send request ({})", e))?
+        .json::<bitcoincore_rpc::jsonrpc::Response>()
+        .await
+        .map_err(|e| format!("unable to parse response ({})", e))?
+        .result::<u64>()
+        .map_err(|e| format!("unable to parse response ({})", e))?;
+
+    Ok(block_count)
+}
+
 // not used internally by chainhook; exported for ordhook
 pub async fn try_download_block_bytes_with_retry(
     http_client: HttpClient,
@@ -322,14 +409,25 @@ pub async fn download_block(
     Ok(rpc_response_bytes)
 }
 
+/// JSON-RPC envelope typed over its `result`, so that a response can be decoded in a
+/// single pass instead of being buffered into a raw value and parsed a second time.
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
 pub fn parse_downloaded_block(
     downloaded_block: Vec<u8>,
 ) -> Result<BitcoinBlockFullBreakdown, String> {
-    let block = serde_json::from_slice::<bitcoincore_rpc::jsonrpc::Response>(&downloaded_block[..])
-        .map_err(|e| format!("unable to parse jsonrpc payload ({})", e))?
-        .result::<BitcoinBlockFullBreakdown>()
-        .map_err(|e| format!("unable to parse block ({})", e))?;
-    Ok(block)
+    let response =
+        serde_json::from_slice::<RpcResponse<BitcoinBlockFullBreakdown>>(&downloaded_block[..])
+            .map_err(|e| format!("unable to parse block ({})", e))?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(format!("rpc request unsuccessful ({})", error.message)),
+        (Some(block), None) => Ok(block),
+        (None, None) => Err("unable to parse block (missing result)".to_string()),
+    }
 }
 
 pub async fn download_and_parse_block(
@@ -342,15 +440,18 @@ pub async fn download_and_parse_block(
     parse_downloaded_block(response)
 }
 
+/// Standardizes a block downloaded from bitcoind, parsing the Stacks operations it carries
+/// according to `network_parameters`.
 pub fn standardize_bitcoin_block(
     block: BitcoinBlockFullBreakdown,
     network: &BitcoinNetwork,
+    network_parameters: &NetworkParameters,
     ctx: &Context,
 ) -> Result<BitcoinBlockData, (String, bool)> {
     let mut transactions = vec![];
     let block_height = block.height as u64;
-    let expected_magic_bytes = get_stacks_canonical_magic_bytes(network);
-    let pox_config = get_canonical_pox_config(network);
+    let expected_magic_bytes = network_parameters.stacks_magic_bytes;
+    let pox_config = &network_parameters.pox_config;
 
     ctx.try_log(|logger| slog::debug!(logger, "Standardizing Bitcoin block {}", block.hash,));
 
@@ -362,7 +463,7 @@ pub fn standardize_bitcoin_block(
             block_height,
             &tx.vin,
             &tx.vout,
-            &pox_config,
+            pox_config,
             &expected_magic_bytes,
             ctx,
         ) {
@@ -415,6 +516,10 @@ pub fn standardize_bitcoin_block(
                     vout,
                     block_height: prevout.height,
                     value: prevout.value.to_sat(),
+                    script_pubkey: prevout
+                        .script_pub_key
+                        .as_ref()
+                        .map(|script_pub_key| format!("0x{}", hex::encode(&script_pub_key.hex))),
                 },
                 script_sig: format!("0x{}", script_sig.hex),
                 sequence: input.sequence,
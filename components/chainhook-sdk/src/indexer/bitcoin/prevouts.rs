@@ -0,0 +1,215 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use hiro_system_kit::slog;
+use reqwest::Client as HttpClient;
+use serde::de::DeserializeOwned;
+
+use super::{
+    BitcoinBlockFullBreakdown, BitcoinTransactionInputPrevoutFullBreakdown,
+    BitcoinTransactionOutputFullBreakdown,
+};
+use crate::observer::BitcoinConfig;
+use crate::utils::Context;
+
+/// Outputs created by the blocks processed so far, keyed by txid and vout. Outputs are removed
+/// once spent, and the oldest ones are evicted past `capacity`.
+#[derive(Default)]
+pub struct PrevoutCache {
+    entries: HashMap<(String, u32), BitcoinTransactionInputPrevoutFullBreakdown>,
+    insertion_order: VecDeque<(String, u32)>,
+}
+
+impl PrevoutCache {
+    pub fn insert(
+        &mut self,
+        txid: &str,
+        vout: u32,
+        prevout: BitcoinTransactionInputPrevoutFullBreakdown,
+        capacity: usize,
+    ) {
+        let key = (txid.to_string(), vout);
+        if self.entries.insert(key.clone(), prevout).is_none() {
+            self.insertion_order.push_back(key);
+        }
+        while self.entries.len() > capacity {
+            let Some(key) = self.insertion_order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+        // Spent outputs leave their keys behind
+        if self.insertion_order.len() > 2 * capacity.max(1) {
+            let entries = &self.entries;
+            self.insertion_order.retain(|key| entries.contains_key(key));
+        }
+    }
+
+    /// Removes the output from the cache, as it can only be spent once.
+    pub fn take(
+        &mut self,
+        txid: &str,
+        vout: u32,
+    ) -> Option<BitcoinTransactionInputPrevoutFullBreakdown> {
+        self.entries.remove(&(txid.to_string(), vout))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The cache is shared by the observer and the scans running in other threads, so that the
+/// outputs seen by one of them resolve the prevouts of the others.
+fn prevout_cache() -> &'static Mutex<PrevoutCache> {
+    static PREVOUT_CACHE: OnceLock<Mutex<PrevoutCache>> = OnceLock::new();
+    PREVOUT_CACHE.get_or_init(|| Mutex::new(PrevoutCache::default()))
+}
+
+#[derive(Debug, Deserialize)]
+struct RawTransaction {
+    vout: Vec<BitcoinTransactionOutputFullBreakdown>,
+    blockhash: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockHeader {
+    height: u64,
+}
+
+/// Fills in the prevouts missing from the inputs of `block`, which bitcoind leaves out of
+/// `getblock` when it lacks the undo data of the block. They are looked up
+/// in the outputs of the blocks processed so far, then retrieved with `getrawtransaction`, which
+/// requires bitcoind to maintain a transaction index (`-txindex`). Up to `cache_size` outputs are
+/// kept for the next blocks.
+pub async fn resolve_missing_prevouts(
+    http_client: &HttpClient,
+    block: &mut BitcoinBlockFullBreakdown,
+    bitcoin_config: &BitcoinConfig,
+    cache_size: usize,
+    ctx: &Context,
+) -> Result<(), String> {
+    let block_height = block.height as u64;
+    let mut missing_prevouts = vec![];
+    {
+        let mut cache = prevout_cache().lock().unwrap_or_else(|e| e.into_inner());
+        // Outputs are cached first, as they can be spent by the next transactions of the block
+        for tx in block.tx.iter() {
+            for output in tx.vout.iter() {
+                let prevout = BitcoinTransactionInputPrevoutFullBreakdown {
+                    height: block_height,
+                    value: output.value,
+                    script_pub_key: Some(output.script_pub_key.clone()),
+                };
+                cache.insert(&tx.txid, output.n, prevout, cache_size);
+            }
+        }
+        for (tx_index, tx) in block.tx.iter_mut().enumerate() {
+            for (input_index, input) in tx.vin.iter_mut().enumerate() {
+                let (Some(txid), Some(vout)) = (input.txid.as_ref(), input.vout) else {
+                    continue;
+                };
+                let cached_prevout = cache.take(txid, vout);
+                if input.prevout.is_some() {
+                    continue;
+                }
+                match cached_prevout {
+                    Some(prevout) => input.prevout = Some(prevout),
+                    None => missing_prevouts.push((tx_index, input_index)),
+                }
+            }
+        }
+    }
+    if missing_prevouts.is_empty() {
+        return Ok(());
+    }
+
+    let mut transactions: HashMap<String, RawTransaction> = HashMap::new();
+    let mut block_heights: HashMap<String, u64> = HashMap::new();
+    for (tx_index, input_index) in missing_prevouts.iter() {
+        let input = &mut block.tx[*tx_index].vin[*input_index];
+        let (Some(txid), Some(vout)) = (input.txid.clone(), input.vout) else {
+            continue;
+        };
+        if !transactions.contains_key(&txid) {
+            let transaction: RawTransaction = call_rpc(
+                http_client,
+                bitcoin_config,
+                "getrawtransaction",
+                json!([txid, true]),
+            )
+            .await
+            .map_err(|e| format!("unable to retrieve transaction {} ({})", txid, e))?;
+            transactions.insert(txid.clone(), transaction);
+        }
+        let transaction = &transactions[&txid];
+        let Some(block_hash) = transaction.blockhash.clone() else {
+            return Err(format!("transaction {} is not confirmed", txid));
+        };
+        let Some(output) = transaction.vout.iter().find(|output| output.n == vout) else {
+            return Err(format!("output {}:{} not found", txid, vout));
+        };
+        let (value, script_pub_key) = (output.value, output.script_pub_key.clone());
+        let height = match block_heights.get(&block_hash) {
+            Some(height) => *height,
+            None => {
+                let header: BlockHeader = call_rpc(
+                    http_client,
+                    bitcoin_config,
+                    "getblockheader",
+                    json!([block_hash, true]),
+                )
+                .await
+                .map_err(|e| format!("unable to retrieve block {} ({})", block_hash, e))?;
+                block_heights.insert(block_hash, header.height);
+                header.height
+            }
+        };
+        input.prevout = Some(BitcoinTransactionInputPrevoutFullBreakdown {
+            height,
+            value,
+            script_pub_key: Some(script_pub_key),
+        });
+    }
+    ctx.try_log(|logger| {
+        slog::debug!(
+            logger,
+            "Resolved {} prevouts of block #{} with getrawtransaction",
+            missing_prevouts.len(),
+            block_height
+        )
+    });
+    Ok(())
+}
+
+async fn call_rpc<T: DeserializeOwned>(
+    http_client: &HttpClient,
+    bitcoin_config: &BitcoinConfig,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<T, String> {
+    let body = json!({
+        "jsonrpc": "1.0",
+        "id": "chainhook-cli",
+        "method": method,
+        "params": params
+    });
+    http_client
+        .post(&bitcoin_config.rpc_url)
+        .basic_auth(&bitcoin_config.username, Some(&bitcoin_config.password))
+        .header("Content-Type", "application/json")
+        .header("Host", &bitcoin_config.rpc_url[7..])
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("unable to send request ({})", e))?
+        .json::<bitcoincore_rpc::jsonrpc::Response>()
+        .await
+        .map_err(|e| format!("unable to parse response ({})", e))?
+        .result::<T>()
+        .map_err(|e| format!("unable to parse response ({})", e))
+}
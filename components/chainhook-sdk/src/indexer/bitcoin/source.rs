@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use reqwest::Client as HttpClient;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{
+    download_and_parse_block, retrieve_best_block_hash, retrieve_block_count, retrieve_block_hash,
+    BitcoinBlockFullBreakdown,
+};
+use crate::observer::BitcoinConfig;
+use crate::utils::Context;
+
+/// A service blocks get fetched from, along with the prevouts spent by their transactions.
+pub trait BitcoinBlockSource: Send + Sync {
+    /// Retrieves the hash of the block at `block_height` in the canonical chain.
+    fn retrieve_block_hash(&self, block_height: u64) -> BoxFuture<'_, Result<String, String>>;
+
+    /// Retrieves the hash of the tip of the canonical chain.
+    fn retrieve_best_block_hash(&self) -> BoxFuture<'_, Result<String, String>>;
+
+    /// Retrieves the height of the tip of the canonical chain.
+    fn retrieve_chain_tip_height(&self) -> BoxFuture<'_, Result<u64, String>>;
+
+    fn download_and_parse_block<'a>(
+        &'a self,
+        block_hash: &'a str,
+    ) -> BoxFuture<'a, Result<BitcoinBlockFullBreakdown, String>>;
+}
+
+/// Returns the source of the blocks of this config: its Esplora API if any, bitcoind otherwise.
+pub fn bitcoin_block_source<'a>(
+    http_client: &'a HttpClient,
+    bitcoin_config: &'a BitcoinConfig,
+    ctx: &'a Context,
+) -> Box<dyn BitcoinBlockSource + 'a> {
+    match bitcoin_config.esplora_api_url {
+        Some(ref api_url) => Box::new(EsploraBlockSource::new(http_client, api_url)),
+        None => Box::new(BitcoindBlockSource::new(http_client, bitcoin_config, ctx)),
+    }
+}
+
+/// Fetches blocks from the RPC interface of a bitcoind node.
+pub struct BitcoindBlockSource<'a> {
+    http_client: &'a HttpClient,
+    bitcoin_config: &'a BitcoinConfig,
+    ctx: &'a Context,
+}
+
+impl<'a> BitcoindBlockSource<'a> {
+    pub fn new(
+        http_client: &'a HttpClient,
+        bitcoin_config: &'a BitcoinConfig,
+        ctx: &'a Context,
+    ) -> Self {
+        BitcoindBlockSource {
+            http_client,
+            bitcoin_config,
+            ctx,
+        }
+    }
+}
+
+impl<'a> BitcoinBlockSource for BitcoindBlockSource<'a> {
+    fn retrieve_block_hash(&self, block_height: u64) -> BoxFuture<'_, Result<String, String>> {
+        async move {
+            retrieve_block_hash(
+                self.http_client,
+                &block_height,
+                self.bitcoin_config,
+                self.ctx,
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn retrieve_best_block_hash(&self) -> BoxFuture<'_, Result<String, String>> {
+        retrieve_best_block_hash(self.http_client, self.bitcoin_config, self.ctx).boxed()
+    }
+
+    fn retrieve_chain_tip_height(&self) -> BoxFuture<'_, Result<u64, String>> {
+        retrieve_block_count(self.http_client, self.bitcoin_config, self.ctx).boxed()
+    }
+
+    fn download_and_parse_block<'b>(
+        &'b self,
+        block_hash: &'b str,
+    ) -> BoxFuture<'b, Result<BitcoinBlockFullBreakdown, String>> {
+        download_and_parse_block(self.http_client, block_hash, self.bitcoin_config, self.ctx)
+            .boxed()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraBlock {
+    pub id: String,
+    pub height: usize,
+    pub timestamp: usize,
+    pub nonce: u32,
+    pub tx_count: usize,
+    pub previousblockhash: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTransaction {
+    pub txid: String,
+    pub vin: Vec<EsploraTransactionInput>,
+    pub vout: Vec<EsploraTransactionOutput>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTransactionInput {
+    pub txid: String,
+    pub vout: u32,
+    pub prevout: Option<EsploraTransactionOutput>,
+    pub scriptsig: String,
+    pub witness: Option<Vec<String>>,
+    pub is_coinbase: bool,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTransactionOutput {
+    pub scriptpubkey: String,
+    pub scriptpubkey_asm: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTransactionStatus {
+    pub block_height: Option<u64>,
+}
+
+/// Fetches blocks from an Esplora HTTP API, for deployments that cannot run a bitcoind node.
+/// The height of the prevouts is not part of the transactions returned by Esplora, so each
+/// block costs an extra request per transaction it spends outputs of.
+pub struct EsploraBlockSource<'a> {
+    http_client: &'a HttpClient,
+    api_url: &'a str,
+}
+
+impl<'a> EsploraBlockSource<'a> {
+    pub fn new(http_client: &'a HttpClient, api_url: &'a str) -> Self {
+        EsploraBlockSource {
+            http_client,
+            api_url: api_url.trim_end_matches('/'),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response, String> {
+        let res = self
+            .http_client
+            .get(format!("{}/{}", self.api_url, path))
+            .send()
+            .await
+            .map_err(|e| format!("unable to send request ({})", e))?;
+        if !res.status().is_success() {
+            return Err(format!("http request unsuccessful ({})", res.status()));
+        }
+        Ok(res)
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String, String> {
+        self.get(path)
+            .await?
+            .text()
+            .await
+            .map(|text| text.trim().to_string())
+            .map_err(|e| format!("unable to parse response ({})", e))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        self.get(path)
+            .await?
+            .json::<T>()
+            .await
+            .map_err(|e| format!("unable to parse response ({})", e))
+    }
+
+    async fn get_chain_tip_height(&self) -> Result<u64, String> {
+        self.get_text("blocks/tip/height")
+            .await?
+            .parse::<u64>()
+            .map_err(|e| format!("unable to parse chain tip height ({})", e))
+    }
+
+    async fn get_block(&self, block_hash: &str) -> Result<BitcoinBlockFullBreakdown, String> {
+        let block: EsploraBlock = self.get_json(&format!("block/{}", block_hash)).await?;
+        let mut transactions: Vec<EsploraTransaction> = Vec::with_capacity(block.tx_count);
+        while transactions.len() < block.tx_count {
+            let page: Vec<EsploraTransaction> = self
+                .get_json(&format!("block/{}/txs/{}", block_hash, transactions.len()))
+                .await?;
+            if page.is_empty() {
+                break;
+            }
+            transactions.extend(page);
+        }
+
+        let mut prevout_heights = HashMap::new();
+        for transaction in transactions.iter() {
+            prevout_heights.insert(transaction.txid.clone(), block.height as u64);
+        }
+        for input in transactions.iter().flat_map(|tx| tx.vin.iter()) {
+            if input.is_coinbase || prevout_heights.contains_key(&input.txid) {
+                continue;
+            }
+            let status: EsploraTransactionStatus =
+                self.get_json(&format!("tx/{}/status", input.txid)).await?;
+            let Some(block_height) = status.block_height else {
+                return Err(format!("transaction {} is not confirmed", input.txid));
+            };
+            prevout_heights.insert(input.txid.clone(), block_height);
+        }
+
+        let chain_tip_height = self.get_chain_tip_height().await?;
+        standardize_esplora_block(block, transactions, &prevout_heights, chain_tip_height)
+    }
+}
+
+impl<'a> BitcoinBlockSource for EsploraBlockSource<'a> {
+    fn retrieve_block_hash(&self, block_height: u64) -> BoxFuture<'_, Result<String, String>> {
+        async move {
+            self.get_text(&format!("block-height/{}", block_height))
+                .await
+        }
+        .boxed()
+    }
+
+    fn retrieve_best_block_hash(&self) -> BoxFuture<'_, Result<String, String>> {
+        async move { self.get_text("blocks/tip/hash").await }.boxed()
+    }
+
+    fn retrieve_chain_tip_height(&self) -> BoxFuture<'_, Result<u64, String>> {
+        self.get_chain_tip_height().boxed()
+    }
+
+    fn download_and_parse_block<'b>(
+        &'b self,
+        block_hash: &'b str,
+    ) -> BoxFuture<'b, Result<BitcoinBlockFullBreakdown, String>> {
+        self.get_block(block_hash).boxed()
+    }
+}
+
+/// Converts a block returned by Esplora into the breakdown returned by bitcoind's `getblock`
+/// with a verbosity of 3.
+pub fn standardize_esplora_block(
+    block: EsploraBlock,
+    transactions: Vec<EsploraTransaction>,
+    prevout_heights: &HashMap<String, u64>,
+    chain_tip_height: u64,
+) -> Result<BitcoinBlockFullBreakdown, String> {
+    let mut tx = vec![];
+    for transaction in transactions.into_iter() {
+        let mut vin = vec![];
+        for input in transaction.vin.into_iter() {
+            if input.is_coinbase {
+                vin.push(json!({
+                    "sequence": input.sequence,
+                    "coinbase": input.scriptsig,
+                    "txinwitness": input.witness,
+                }));
+                continue;
+            }
+            let prevout = match (input.prevout, prevout_heights.get(&input.txid)) {
+                (Some(prevout), Some(height)) => Some(json!({
+                    "height": height,
+                    "value": sats_to_btc(prevout.value),
+                    "scriptPubKey": {
+                        "asm": prevout.scriptpubkey_asm,
+                        "hex": prevout.scriptpubkey,
+                    },
+                })),
+                _ => None,
+            };
+            vin.push(json!({
+                "sequence": input.sequence,
+                "txid": input.txid,
+                "vout": input.vout,
+                "scriptSig": { "hex": input.scriptsig },
+                "txinwitness": input.witness,
+                "prevout": prevout,
+            }));
+        }
+        let vout = transaction
+            .vout
+            .into_iter()
+            .enumerate()
+            .map(|(n, output)| {
+                json!({
+                    "value": sats_to_btc(output.value),
+                    "n": n,
+                    "scriptPubKey": {
+                        "asm": output.scriptpubkey_asm,
+                        "hex": output.scriptpubkey,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        tx.push(json!({
+            "txid": transaction.txid,
+            "vin": vin,
+            "vout": vout,
+        }));
+    }
+    let confirmations = (chain_tip_height + 1).saturating_sub(block.height as u64);
+    let block = json!({
+        "hash": block.id,
+        "height": block.height,
+        "tx": tx,
+        "time": block.timestamp,
+        "nonce": block.nonce,
+        "previousblockhash": block.previousblockhash,
+        "confirmations": confirmations,
+    });
+    serde_json::from_value::<BitcoinBlockFullBreakdown>(block)
+        .map_err(|e| format!("unable to parse block ({})", e))
+}
+
+fn sats_to_btc(value: u64) -> f64 {
+    bitcoincore_rpc::bitcoin::Amount::from_sat(value).to_btc()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{standardize_esplora_block, EsploraBlock, EsploraTransaction};
+
+    #[test]
+    fn it_standardizes_esplora_blocks() {
+        let block: EsploraBlock = serde_json::from_value(serde_json::json!({
+            "id": "0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5",
+            "height": 840000,
+            "timestamp": 1713571767,
+            "nonce": 3932395645u32,
+            "tx_count": 2,
+            "previousblockhash": "0000000000000000000172014ba58d66455762add0512355ad651207918494ab",
+        }))
+        .unwrap();
+        let transactions: Vec<EsploraTransaction> = serde_json::from_value(serde_json::json!([
+            {
+                "txid": "a0db149ace545beabbd87a8d6b20954e3c2e3b2ad1ea7ed4e6f6f5e2b6d8b6a1",
+                "vin": [{
+                    "txid": "0000000000000000000000000000000000000000000000000000000000000000",
+                    "vout": 4294967295u32,
+                    "prevout": null,
+                    "scriptsig": "0340d10c",
+                    "witness": ["0000000000000000000000000000000000000000000000000000000000000000"],
+                    "is_coinbase": true,
+                    "sequence": 4294967295u32,
+                }],
+                "vout": [{
+                    "scriptpubkey": "76a914c825a1ecf2a6830c4401620c3a16f1995057c2ab88ac",
+                    "scriptpubkey_asm": "OP_DUP OP_HASH160 OP_PUSHBYTES_20 c825a1ecf2a6830c4401620c3a16f1995057c2ab OP_EQUALVERIFY OP_CHECKSIG",
+                    "value": 650000000u64,
+                }],
+            },
+            {
+                "txid": "b61b0172d95e266c18aea0c624db987e971a5d6d4ebc2aaed85da4642d635735",
+                "vin": [{
+                    "txid": "9eebe848baaf8dd4810e4e4a91168e2e471c949439faf5d768750ca21d067689",
+                    "vout": 3,
+                    "prevout": {
+                        "scriptpubkey": "0014c825a1ecf2a6830c4401620c3a16f1995057c2ab",
+                        "scriptpubkey_asm": "OP_0 OP_PUSHBYTES_20 c825a1ecf2a6830c4401620c3a16f1995057c2ab",
+                        "value": 10000u64,
+                    },
+                    "scriptsig": "",
+                    "is_coinbase": false,
+                    "sequence": 4294967293u32,
+                }],
+                "vout": [{
+                    "scriptpubkey": "0014c825a1ecf2a6830c4401620c3a16f1995057c2ab",
+                    "scriptpubkey_asm": "OP_0 OP_PUSHBYTES_20 c825a1ecf2a6830c4401620c3a16f1995057c2ab",
+                    "value": 9000u64,
+                }],
+            }
+        ]))
+        .unwrap();
+        let mut prevout_heights = HashMap::new();
+        prevout_heights.insert(
+            "9eebe848baaf8dd4810e4e4a91168e2e471c949439faf5d768750ca21d067689".to_string(),
+            839990,
+        );
+
+        let block =
+            standardize_esplora_block(block, transactions, &prevout_heights, 840005).unwrap();
+        assert_eq!(block.height, 840000);
+        assert_eq!(block.confirmations, 6);
+        assert!(block.tx[0].vin[0].is_coinbase());
+        let prevout = block.tx[1].vin[0].prevout.as_ref().unwrap();
+        assert_eq!(prevout.height, 839990);
+        assert_eq!(prevout.value.to_sat(), 10000);
+        assert!(prevout.script_pub_key.is_some());
+        assert_eq!(block.tx[1].vout[0].value.to_sat(), 9000);
+        assert_eq!(block.tx[1].vout[0].n, 0);
+    }
+}
@@ -1,4 +1,15 @@
+use super::super::fork_scratch_pad::ForkScratchPad;
+use super::super::tests::helpers::bitcoin_blocks;
+use super::super::tests::helpers::bitcoin_shapes::expect_chain_updated_with_block;
 use super::super::tests::{helpers, process_bitcoin_blocks_and_check_expectations};
+use super::prevouts::{resolve_missing_prevouts, PrevoutCache};
+use super::{
+    build_http_client, parse_downloaded_block, BitcoinTransactionInputPrevoutFullBreakdown,
+};
+use crate::observer::BitcoinConfig;
+use crate::utils::{AbstractBlock, Context};
+use bitcoincore_rpc::bitcoin::Amount;
+use chainhook_types::{BitcoinBlockSignaling, BitcoinNetwork};
 
 #[test]
 fn test_bitcoin_vector_001() {
@@ -200,6 +211,103 @@ fn test_bitcoin_vector_040() {
     process_bitcoin_blocks_and_check_expectations(helpers::bitcoin_shapes::get_vector_040());
 }
 
+#[test]
+fn test_bitcoin_custom_confirmation_depth() {
+    let mut blocks_processor = ForkScratchPad::new();
+    blocks_processor.set_confirmation_depth(3);
+    let steps = vec![
+        (
+            bitcoin_blocks::A1(None),
+            expect_chain_updated_with_block(bitcoin_blocks::A1(None), vec![]),
+        ),
+        (
+            bitcoin_blocks::B1(None),
+            expect_chain_updated_with_block(bitcoin_blocks::B1(None), vec![]),
+        ),
+        (
+            bitcoin_blocks::C1(None),
+            expect_chain_updated_with_block(
+                bitcoin_blocks::C1(None),
+                vec![bitcoin_blocks::A1(None)],
+            ),
+        ),
+    ];
+    for (block, check_chain_event_expectations) in steps.into_iter() {
+        let chain_event = blocks_processor
+            .process_header(block.get_header(), &Context::empty())
+            .unwrap();
+        check_chain_event_expectations(chain_event);
+    }
+}
+
+#[test]
+fn parse_downloaded_block_decodes_rpc_result() {
+    let payload = br#"{"result":{"hash":"00aa","height":12,"tx":[],"time":1700000000,"nonce":7,"previousblockhash":"00bb","confirmations":1},"error":null,"id":"chainhook-cli"}"#;
+    let block = parse_downloaded_block(payload.to_vec()).unwrap();
+    assert_eq!(block.hash, "00aa");
+    assert_eq!(block.height, 12);
+    assert_eq!(block.previousblockhash.as_deref(), Some("00bb"));
+}
+
+#[test]
+fn parse_downloaded_block_surfaces_rpc_errors() {
+    let payload =
+        br#"{"result":null,"error":{"code":-5,"message":"Block not found"},"id":"chainhook-cli"}"#;
+    let error = parse_downloaded_block(payload.to_vec()).unwrap_err();
+    assert_eq!(error, "rpc request unsuccessful (Block not found)");
+}
+
+#[test]
+fn prevout_cache_evicts_oldest_outputs() {
+    let prevout = |height| BitcoinTransactionInputPrevoutFullBreakdown {
+        height,
+        value: Amount::from_sat(1000),
+        script_pub_key: None,
+    };
+    let mut cache = PrevoutCache::default();
+    cache.insert("aa", 0, prevout(1), 2);
+    cache.insert("aa", 1, prevout(1), 2);
+    cache.insert("bb", 0, prevout(2), 2);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.take("aa", 0), None);
+    assert_eq!(cache.take("bb", 0), Some(prevout(2)));
+    // Outputs can only be spent once
+    assert_eq!(cache.take("bb", 0), None);
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test]
+async fn resolve_missing_prevouts_spent_in_the_same_block() {
+    let payload = br#"{"result":{"hash":"00cc","height":42,"time":1700000000,"nonce":7,"previousblockhash":"00bb","confirmations":1,"tx":[
+        {"txid":"prevout-test-a","vin":[{"sequence":4294967295,"coinbase":"00"}],"vout":[{"value":0.5,"n":0,"scriptPubKey":{"asm":"OP_TRUE","hex":"51"}}]},
+        {"txid":"prevout-test-b","vin":[{"sequence":4294967295,"txid":"prevout-test-a","vout":0,"scriptSig":{"asm":"","hex":""}}],"vout":[]}
+    ]},"error":null,"id":"chainhook-cli"}"#;
+    let mut block = parse_downloaded_block(payload.to_vec()).unwrap();
+    let bitcoin_config = BitcoinConfig {
+        username: "devnet".into(),
+        password: "devnet".into(),
+        rpc_url: "http://localhost:18443".into(),
+        rpc_fallbacks: vec![],
+        esplora_api_url: None,
+        network: BitcoinNetwork::Regtest,
+        bitcoin_block_signaling: BitcoinBlockSignaling::Polling(1000),
+        prevout_cache_size: Some(10),
+    };
+    resolve_missing_prevouts(
+        &build_http_client(),
+        &mut block,
+        &bitcoin_config,
+        10,
+        &Context::empty(),
+    )
+    .await
+    .unwrap();
+    let prevout = block.tx[1].vin[0].prevout.as_ref().unwrap();
+    assert_eq!(prevout.height, 42);
+    assert_eq!(prevout.value.to_sat(), 50_000_000);
+    assert_eq!(prevout.script_pub_key.as_ref().unwrap().hex, vec![0x51]);
+}
+
 // #[test]
 // fn test_bitcoin_vector_041() {
 //     process_bitcoin_blocks_and_check_expectations(helpers::shapes::get_vector_041());
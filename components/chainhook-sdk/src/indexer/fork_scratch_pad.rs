@@ -14,6 +14,7 @@ pub struct ForkScratchPad {
     orphans: BTreeSet<BlockIdentifier>,
     forks: BTreeMap<usize, ChainSegment>,
     headers_store: BTreeMap<BlockIdentifier, BlockHeader>,
+    confirmation_depth: usize,
 }
 pub const CONFIRMED_SEGMENT_MINIMUM_LENGTH: i32 = 7;
 impl Default for ForkScratchPad {
@@ -32,9 +33,16 @@ impl ForkScratchPad {
             orphans: BTreeSet::new(),
             forks,
             headers_store,
+            confirmation_depth: CONFIRMED_SEGMENT_MINIMUM_LENGTH as usize,
         }
     }
 
+    /// Sets the number of blocks, including itself, a block needs in the canonical chain to be
+    /// confirmed. Defaults to [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub fn set_confirmation_depth(&mut self, confirmation_depth: u64) {
+        self.confirmation_depth = confirmation_depth.max(2) as usize;
+    }
+
     pub fn can_process_header(&self, header: &BlockHeader) -> bool {
         if self.headers_store.is_empty() {
             return true;
@@ -234,11 +242,11 @@ impl ForkScratchPad {
             }
             segment
         };
-        if canonical_segment.len() < CONFIRMED_SEGMENT_MINIMUM_LENGTH as usize {
+        if canonical_segment.len() < self.confirmation_depth {
             return;
         }
-        // Any block beyond 6th ancestor is considered as confirmed and can be pruned
-        let cut_off = &canonical_segment[(CONFIRMED_SEGMENT_MINIMUM_LENGTH - 2) as usize];
+        // Any block beyond the confirmation depth is considered as confirmed and can be pruned
+        let cut_off = &canonical_segment[self.confirmation_depth - 2];
 
         // Prune forks using the confirmed block
         let mut blocks_to_prune = vec![];
@@ -263,10 +271,10 @@ impl ForkScratchPad {
             slog::debug!(
                 logger,
                 "Removing {} confirmed blocks from block store.",
-                canonical_segment[6..].len()
+                canonical_segment[self.confirmation_depth - 1..].len()
             )
         });
-        for confirmed_block in canonical_segment[6..].iter() {
+        for confirmed_block in canonical_segment[self.confirmation_depth - 1..].iter() {
             let block = match self.headers_store.remove(confirmed_block) {
                 None => {
                     ctx.try_log(|logger| {
@@ -3,21 +3,22 @@ pub mod fork_scratch_pad;
 pub mod stacks;
 
 use crate::{
-    chainhooks::types::PoxConfig,
+    chainhooks::types::{NetworkParameters, PoxConfig},
     utils::{AbstractBlock, Context},
 };
 
 use chainhook_types::{
     BitcoinBlockSignaling, BitcoinNetwork, BlockHeader, BlockIdentifier, BlockchainEvent,
-    StacksBlockData, StacksChainEvent, StacksNetwork, StacksNodeConfig,
+    StacksBlockData, StacksChainEvent, StacksMicroblockData, StacksNetwork, StacksNodeConfig,
 };
 use hiro_system_kit::slog;
-use rocket::serde::json::Value as JsonValue;
+use serde_json::Value as JsonValue;
 
-use stacks::StacksBlockPool;
+use stacks::{NewBlock, NewMicroblockTrail, StacksBlockPool};
 use std::collections::{HashMap, VecDeque};
 
-use self::fork_scratch_pad::ForkScratchPad;
+use self::bitcoin::endpoints::BitcoindRpcEndpoint;
+use self::fork_scratch_pad::{ForkScratchPad, CONFIRMED_SEGMENT_MINIMUM_LENGTH};
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct AssetClassCache {
@@ -57,6 +58,18 @@ impl BitcoinChainContext {
     }
 }
 
+/// What the indexer does with a Stacks transaction it fails to standardize, e.g. because one of
+/// its events is malformed.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MalformedStacksTransactionPolicy {
+    /// Leave the transaction out, listing it in the `skipped_transactions` of the block metadata.
+    #[default]
+    Skip,
+    /// Reject the whole block, or microblock trail.
+    FailBlock,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct IndexerConfig {
     pub bitcoin_network: BitcoinNetwork,
@@ -64,7 +77,20 @@ pub struct IndexerConfig {
     pub bitcoind_rpc_url: String,
     pub bitcoind_rpc_username: String,
     pub bitcoind_rpc_password: String,
+    pub bitcoind_rpc_fallbacks: Vec<BitcoindRpcEndpoint>,
+    pub esplora_api_url: Option<String>,
     pub bitcoin_block_signaling: BitcoinBlockSignaling,
+    /// Number of blocks, including itself, a Bitcoin block needs in the canonical chain to be
+    /// confirmed. Defaults to [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub bitcoin_confirmation_depth: Option<u64>,
+    /// Number of blocks, including itself, a Stacks block needs in the canonical chain to be
+    /// confirmed. Defaults to [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub stacks_confirmation_depth: Option<u64>,
+    /// Magic bytes and PoX parameters of the observed chain. Defaults to the
+    /// [NetworkParameters::canonical] ones of the `bitcoin_network`.
+    pub network_parameters: Option<NetworkParameters>,
+    /// Whether a Stacks transaction that can't be standardized is skipped or fails its block.
+    pub malformed_stacks_transactions: MalformedStacksTransactionPolicy,
 }
 
 impl IndexerConfig {
@@ -74,6 +100,22 @@ impl IndexerConfig {
             _ => unreachable!(),
         }
     }
+
+    pub fn get_bitcoin_confirmation_depth(&self) -> u64 {
+        self.bitcoin_confirmation_depth
+            .unwrap_or(CONFIRMED_SEGMENT_MINIMUM_LENGTH as u64)
+    }
+
+    pub fn get_stacks_confirmation_depth(&self) -> u64 {
+        self.stacks_confirmation_depth
+            .unwrap_or(CONFIRMED_SEGMENT_MINIMUM_LENGTH as u64)
+    }
+
+    pub fn get_network_parameters(&self) -> NetworkParameters {
+        self.network_parameters
+            .clone()
+            .unwrap_or_else(|| NetworkParameters::canonical(&self.bitcoin_network))
+    }
 }
 
 pub struct Indexer {
@@ -86,9 +128,14 @@ pub struct Indexer {
 
 impl Indexer {
     pub fn new(config: IndexerConfig) -> Indexer {
-        let stacks_blocks_pool = StacksBlockPool::new();
-        let bitcoin_blocks_pool = ForkScratchPad::new();
-        let stacks_context = StacksChainContext::new(&config.stacks_network);
+        let mut stacks_blocks_pool = StacksBlockPool::new();
+        stacks_blocks_pool.set_confirmation_depth(config.get_stacks_confirmation_depth());
+        let mut bitcoin_blocks_pool = ForkScratchPad::new();
+        bitcoin_blocks_pool.set_confirmation_depth(config.get_bitcoin_confirmation_depth());
+        let mut stacks_context = StacksChainContext::new(&config.stacks_network);
+        if let Some(ref network_parameters) = config.network_parameters {
+            stacks_context.pox_config = network_parameters.pox_config.clone();
+        }
         let bitcoin_context = BitcoinChainContext::new();
 
         Indexer {
@@ -126,6 +173,14 @@ impl Indexer {
         )
     }
 
+    pub fn standardize_stacks_block(
+        &mut self,
+        block: &mut NewBlock,
+        ctx: &Context,
+    ) -> Result<StacksBlockData, String> {
+        stacks::standardize_stacks_block(&self.config, block, &mut self.stacks_context, ctx)
+    }
+
     pub fn process_stacks_block(
         &mut self,
         block: StacksBlockData,
@@ -149,6 +204,43 @@ impl Indexer {
             .process_microblocks(microblocks, ctx)
     }
 
+    pub fn handle_stacks_microblock_trail(
+        &mut self,
+        microblock_trail: &mut NewMicroblockTrail,
+        ctx: &Context,
+    ) -> Result<Option<StacksChainEvent>, String> {
+        let microblocks = stacks::standardize_stacks_microblock_trail(
+            &self.config,
+            microblock_trail,
+            &mut self.stacks_context,
+            ctx,
+        )?;
+        self.stacks_blocks_pool
+            .process_microblocks(microblocks, ctx)
+    }
+
+    pub fn standardize_stacks_microblock_trail(
+        &mut self,
+        microblock_trail: &mut NewMicroblockTrail,
+        ctx: &Context,
+    ) -> Result<Vec<StacksMicroblockData>, String> {
+        stacks::standardize_stacks_microblock_trail(
+            &self.config,
+            microblock_trail,
+            &mut self.stacks_context,
+            ctx,
+        )
+    }
+
+    pub fn process_stacks_microblocks(
+        &mut self,
+        microblocks: Vec<StacksMicroblockData>,
+        ctx: &Context,
+    ) -> Result<Option<StacksChainEvent>, String> {
+        self.stacks_blocks_pool
+            .process_microblocks(microblocks, ctx)
+    }
+
     pub fn handle_stacks_marshalled_microblock_trail(
         &mut self,
         marshalled_microblock_trail: JsonValue,
@@ -24,6 +24,7 @@ pub struct StacksBlockPool {
     micro_forks: HashMap<BlockIdentifier, Vec<ChainSegment>>,
     micro_orphans: BTreeSet<(BlockIdentifier, BlockIdentifier)>,
     canonical_micro_fork_id: HashMap<BlockIdentifier, usize>,
+    confirmation_depth: usize,
 }
 
 impl Default for StacksBlockPool {
@@ -46,9 +47,16 @@ impl StacksBlockPool {
             micro_forks: HashMap::new(),
             micro_orphans: BTreeSet::new(),
             canonical_micro_fork_id: HashMap::new(),
+            confirmation_depth: CONFIRMED_SEGMENT_MINIMUM_LENGTH as usize,
         }
     }
 
+    /// Sets the number of blocks, including itself, a block needs in the canonical chain to be
+    /// confirmed. Defaults to [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub fn set_confirmation_depth(&mut self, confirmation_depth: u64) {
+        self.confirmation_depth = confirmation_depth.max(2) as usize;
+    }
+
     pub fn seed_block_pool(&mut self, blocks: Vec<StacksBlockData>, ctx: &Context) {
         ctx.try_log(|logger| {
             slog::info!(logger, "Seeding block pool with {} blocks", blocks.len())
@@ -330,12 +338,12 @@ impl StacksBlockPool {
             segment
         };
 
-        if canonical_segment.len() < CONFIRMED_SEGMENT_MINIMUM_LENGTH as usize {
+        if canonical_segment.len() < self.confirmation_depth {
             ctx.try_log(|logger| slog::info!(logger, "No block to confirm"));
             return;
         }
-        // Any block beyond 6th ancestor is considered as confirmed and can be pruned
-        let cut_off = &canonical_segment[(CONFIRMED_SEGMENT_MINIMUM_LENGTH - 2) as usize];
+        // Any block beyond the confirmation depth is considered as confirmed and can be pruned
+        let cut_off = &canonical_segment[self.confirmation_depth - 2];
 
         // Prune forks using the confirmed block
         let mut blocks_to_prune = vec![];
@@ -359,9 +367,9 @@ impl StacksBlockPool {
         // Looping a first time, to collect:
         // 1) the blocks that we will be returning
         // 2) the tip of the trail confirmed by the subsequent block
-        // Block 6 (index 5) is confirming transactions included in microblocks
-        // that must be merged in Block 7.
-        let mut blocks_to_confirm = canonical_segment[6..].to_vec();
+        // With the default depth, block 6 (index 5) is confirming transactions included in
+        // microblocks that must be merged in Block 7.
+        let mut blocks_to_confirm = canonical_segment[self.confirmation_depth - 1..].to_vec();
         blocks_to_confirm.reverse();
         ctx.try_log(|logger| {
             slog::debug!(
@@ -0,0 +1,69 @@
+use chainhook_types::BlockIdentifier;
+use reqwest::Client as HttpClient;
+use sha2::{Digest, Sha512_256};
+
+/// Maximum number of headers a Stacks node returns for a single `/v2/headers` request.
+pub const MAX_STACKS_HEADERS_PER_REQUEST: u64 = 2100;
+
+#[derive(Debug, Deserialize)]
+struct ExtendedStacksHeader {
+    consensus_hash: String,
+    header: String,
+}
+
+/// Retrieves the identifiers of the last `quantity` blocks of the canonical chain of a Stacks
+/// node, starting from its tip.
+pub async fn retrieve_canonical_stacks_block_identifiers(
+    http_client: &HttpClient,
+    rpc_url: &str,
+    quantity: u64,
+) -> Result<Vec<BlockIdentifier>, String> {
+    let res = http_client
+        .get(format!(
+            "{}/v2/headers/{}",
+            rpc_url.trim_end_matches('/'),
+            quantity.min(MAX_STACKS_HEADERS_PER_REQUEST)
+        ))
+        .send()
+        .await
+        .map_err(|e| format!("unable to send request ({})", e))?;
+    if !res.status().is_success() {
+        return Err(format!("http request unsuccessful ({})", res.status()));
+    }
+    let headers = res
+        .json::<Vec<ExtendedStacksHeader>>()
+        .await
+        .map_err(|e| format!("unable to parse response ({})", e))?;
+    headers
+        .iter()
+        .map(|header| get_stacks_block_identifier(&header.consensus_hash, &header.header))
+        .collect()
+}
+
+/// Computes the identifier of a Stacks block from its consensus hash and its serialized
+/// header: its height is the work score of the header, and its hash the index block hash,
+/// i.e. the hash of the block hash followed by the consensus hash.
+pub fn get_stacks_block_identifier(
+    consensus_hash: &str,
+    serialized_header: &str,
+) -> Result<BlockIdentifier, String> {
+    let header = hex::decode(serialized_header.trim_start_matches("0x"))
+        .map_err(|e| format!("unable to decode block header ({})", e))?;
+    let consensus_hash = hex::decode(consensus_hash.trim_start_matches("0x"))
+        .map_err(|e| format!("unable to decode consensus hash ({})", e))?;
+    // version (1 byte), then the burn (8 bytes) and work (8 bytes) of the total work score
+    let Some(work) = header.get(9..17) else {
+        return Err("unable to decode block header (too short)".into());
+    };
+    let mut height = [0u8; 8];
+    height.copy_from_slice(work);
+
+    let block_hash = Sha512_256::digest(header);
+    let mut hasher = Sha512_256::new();
+    hasher.update(block_hash);
+    hasher.update(consensus_hash);
+    Ok(BlockIdentifier {
+        index: u64::from_be_bytes(height),
+        hash: format!("0x{}", hex::encode(hasher.finalize())),
+    })
+}
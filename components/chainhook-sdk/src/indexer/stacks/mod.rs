@@ -1,17 +1,24 @@
 mod blocks_pool;
+mod headers;
 
 pub use blocks_pool::StacksBlockPool;
+pub use headers::{
+    get_stacks_block_identifier, retrieve_canonical_stacks_block_identifiers,
+    MAX_STACKS_HEADERS_PER_REQUEST,
+};
 
 use crate::chainhooks::stacks::try_decode_clarity_value;
 use crate::indexer::AssetClassCache;
-use crate::indexer::{IndexerConfig, StacksChainContext};
+use crate::indexer::{IndexerConfig, MalformedStacksTransactionPolicy, StacksChainContext};
+use crate::try_warn;
 use crate::utils::Context;
 use chainhook_types::*;
 use clarity::codec::StacksMessageCodec;
 use clarity::vm::types::{SequenceData, Value as ClarityValue};
 use hiro_system_kit::slog;
-use rocket::serde::json::Value as JsonValue;
-use rocket::serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
 use stacks_codec::codec::{StacksTransaction, TransactionAuth, TransactionPayload};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
@@ -48,6 +55,12 @@ pub struct NewBlock {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signer_signature: Option<Vec<String>>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer_signature_hash: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miner_signature: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycle_number: Option<u64>,
 
@@ -153,8 +166,7 @@ pub struct NewEvent {
 impl NewEvent {
     pub fn into_chainhook_event(&self) -> Result<StacksTransactionEvent, String> {
         if let Some(ref event_data) = self.stx_mint_event {
-            let data: STXMintEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: STXMintEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::STXMintEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -162,8 +174,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.stx_lock_event {
-            let data: STXLockEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: STXLockEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::STXLockEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -171,8 +182,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.stx_burn_event {
-            let data: STXBurnEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: STXBurnEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::STXBurnEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -180,8 +190,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.stx_transfer_event {
-            let data: STXTransferEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: STXTransferEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::STXTransferEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -189,8 +198,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.nft_mint_event {
-            let data: NFTMintEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: NFTMintEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::NFTMintEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -198,8 +206,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.nft_burn_event {
-            let data: NFTBurnEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: NFTBurnEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::NFTBurnEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -207,8 +214,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.nft_transfer_event {
-            let data: NFTTransferEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: NFTTransferEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::NFTTransferEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -216,8 +222,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.ft_mint_event {
-            let data: FTMintEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: FTMintEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::FTMintEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -225,8 +230,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.ft_burn_event {
-            let data: FTBurnEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: FTBurnEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::FTBurnEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -234,8 +238,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.ft_transfer_event {
-            let data: FTTransferEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: FTTransferEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::FTTransferEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -243,8 +246,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.data_var_set_event {
-            let data: DataVarSetEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: DataVarSetEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: (StacksTransactionEventPayload::DataVarSetEvent(data)),
                 position: StacksTransactionEventPosition {
@@ -252,8 +254,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.data_map_insert_event {
-            let data: DataMapInsertEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: DataMapInsertEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::DataMapInsertEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -261,8 +262,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.data_map_update_event {
-            let data: DataMapUpdateEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: DataMapUpdateEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::DataMapUpdateEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -270,8 +270,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.data_map_delete_event {
-            let data: DataMapDeleteEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: DataMapDeleteEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::DataMapDeleteEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -279,8 +278,7 @@ impl NewEvent {
                 },
             });
         } else if let Some(ref event_data) = self.contract_event {
-            let data: SmartContractEventData =
-                serde_json::from_value(event_data.clone()).expect("Unable to decode event_data");
+            let data: SmartContractEventData = decode_event_data(event_data)?;
             return Ok(StacksTransactionEvent {
                 event_payload: StacksTransactionEventPayload::SmartContractEvent(data.clone()),
                 position: StacksTransactionEventPosition {
@@ -292,6 +290,11 @@ impl NewEvent {
     }
 }
 
+fn decode_event_data<T: DeserializeOwned>(event_data: &JsonValue) -> Result<T, String> {
+    serde_json::from_value(event_data.clone())
+        .map_err(|e| format!("unable to decode event_data: {}", e))
+}
+
 pub fn get_stacks_currency() -> Currency {
     Currency {
         symbol: "STX".into(),
@@ -375,6 +378,7 @@ pub fn standardize_stacks_block(
     }
 
     let mut transactions = vec![];
+    let mut skipped_transactions = vec![];
     for tx in block.transactions.iter() {
         let tx_events = events.remove(&tx.txid).unwrap_or_default();
         let (description, tx_type, fee, nonce, sender, sponsor) =
@@ -392,17 +396,25 @@ pub fn standardize_stacks_block(
                     ));
                 }
             };
-        let events = tx_events
-            .iter()
-            .map(|e| e.into_chainhook_event())
-            .collect::<Result<Vec<StacksTransactionEvent>, String>>()?;
-        let (receipt, operations) = get_standardized_stacks_receipt(
+        let (receipt, operations) = match get_standardized_stacks_transaction_receipt(
+            indexer_config,
             &tx.txid,
-            events,
-            &mut chain_ctx.asset_class_map,
-            &indexer_config.get_stacks_node_config().rpc_url.clone(),
-            true,
-        );
+            &tx_events,
+            chain_ctx,
+        ) {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                let block_description = format!("block #{}", block.block_height);
+                skipped_transactions.push(skip_malformed_transaction(
+                    indexer_config,
+                    &tx.txid,
+                    &block_description,
+                    e,
+                    ctx,
+                )?);
+                continue;
+            }
+        };
 
         transactions.push(StacksTransactionData {
             transaction_identifier: TransactionIdentifier {
@@ -454,7 +466,12 @@ pub fn standardize_stacks_block(
                 _ => block.block_height - 1,
             },
         },
-        timestamp: block.parent_burn_block_timestamp,
+        // Nakamoto blocks of a same tenure share their parent burn block, their own time is
+        // carried by the header instead.
+        timestamp: match block.block_time {
+            Some(block_time) => block_time as i64,
+            None => block.parent_burn_block_timestamp,
+        },
         metadata: StacksBlockMetadata {
             bitcoin_anchor_block_identifier: BlockIdentifier {
                 hash: block.burn_block_hash.clone(),
@@ -471,6 +488,8 @@ pub fn standardize_stacks_block(
             // TODO: decode `signer_bitvec` into an easy to use bit string representation (e.g. "01010101")
             signer_bitvec: block.signer_bitvec.clone(),
             signer_signature: block.signer_signature.clone(),
+            signer_signature_hash: block.signer_signature_hash.clone(),
+            miner_signature: block.miner_signature.clone(),
 
             cycle_number: block.cycle_number,
             reward_set: block.reward_set.as_ref().and_then(|r| {
@@ -489,12 +508,42 @@ pub fn standardize_stacks_block(
                     }),
                 })
             }),
+            skipped_transactions,
         },
         transactions,
     };
     Ok(block)
 }
 
+/// Applies the [MalformedStacksTransactionPolicy] of the indexer to a transaction that could not
+/// be standardized: either the whole block fails, or the transaction is left out of it.
+fn skip_malformed_transaction(
+    indexer_config: &IndexerConfig,
+    txid: &str,
+    block_description: &str,
+    error: String,
+    ctx: &Context,
+) -> Result<TransactionIdentifier, String> {
+    match indexer_config.malformed_stacks_transactions {
+        MalformedStacksTransactionPolicy::FailBlock => Err(format!(
+            "unable to standardize transaction {} of {} ({})",
+            txid, block_description, error
+        )),
+        MalformedStacksTransactionPolicy::Skip => {
+            try_warn!(
+                ctx,
+                "Skipping transaction {} of {}: {}",
+                txid,
+                block_description,
+                error
+            );
+            Ok(TransactionIdentifier {
+                hash: txid.to_string(),
+            })
+        }
+    }
+}
+
 pub fn standardize_stacks_serialized_microblock_trail(
     indexer_config: &IndexerConfig,
     serialized_microblock_trail: &str,
@@ -534,25 +583,10 @@ pub fn standardize_stacks_microblock_trail(
     }
     let mut microblocks_set: BTreeMap<
         (BlockIdentifier, BlockIdentifier),
-        Vec<StacksTransactionData>,
+        (Vec<StacksTransactionData>, Vec<TransactionIdentifier>),
     > = BTreeMap::new();
     for tx in microblock_trail.transactions.iter() {
         let tx_events = events.remove(&tx.txid).unwrap_or_default();
-        let (description, tx_type, fee, nonce, sender, sponsor) =
-            get_tx_description(&tx.raw_tx, &tx_events).expect("unable to parse transaction");
-
-        let events = tx_events
-            .iter()
-            .map(|e| e.into_chainhook_event())
-            .collect::<Result<Vec<StacksTransactionEvent>, String>>()?;
-        let (receipt, operations) = get_standardized_stacks_receipt(
-            &tx.txid,
-            events,
-            &mut chain_ctx.asset_class_map,
-            &indexer_config.get_stacks_node_config().rpc_url.clone(),
-            true,
-        );
-
         let microblock_identifier = BlockIdentifier {
             hash: tx.microblock_hash.clone(),
             index: u64::try_from(tx.microblock_sequence).unwrap(),
@@ -567,6 +601,43 @@ pub fn standardize_stacks_microblock_trail(
             microblock_identifier.clone()
         };
 
+        let (transactions, skipped_transactions) = microblocks_set
+            .entry((microblock_identifier.clone(), parent_microblock_identifier))
+            .or_default();
+        let microblock_description = format!("microblock {}", tx.microblock_hash);
+        let (description, tx_type, fee, nonce, sender, sponsor) =
+            match get_tx_description(&tx.raw_tx, &tx_events) {
+                Ok(desc) => desc,
+                Err(e) => {
+                    skipped_transactions.push(skip_malformed_transaction(
+                        indexer_config,
+                        &tx.txid,
+                        &microblock_description,
+                        e,
+                        ctx,
+                    )?);
+                    continue;
+                }
+            };
+        let (receipt, operations) = match get_standardized_stacks_transaction_receipt(
+            indexer_config,
+            &tx.txid,
+            &tx_events,
+            chain_ctx,
+        ) {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                skipped_transactions.push(skip_malformed_transaction(
+                    indexer_config,
+                    &tx.txid,
+                    &microblock_description,
+                    e,
+                    ctx,
+                )?);
+                continue;
+            }
+        };
+
         let transaction = StacksTransactionData {
             transaction_identifier: TransactionIdentifier {
                 hash: tx.txid.clone(),
@@ -593,14 +664,13 @@ pub fn standardize_stacks_microblock_trail(
             },
         };
 
-        microblocks_set
-            .entry((microblock_identifier, parent_microblock_identifier))
-            .and_modify(|transactions| transactions.push(transaction.clone()))
-            .or_insert(vec![transaction]);
+        transactions.push(transaction);
     }
 
     let mut microblocks = vec![];
-    for ((block_identifier, parent_block_identifier), transactions) in microblocks_set.into_iter() {
+    for ((block_identifier, parent_block_identifier), (transactions, skipped_transactions)) in
+        microblocks_set.into_iter()
+    {
         microblocks.push(StacksMicroblockData {
             block_identifier,
             parent_block_identifier,
@@ -611,6 +681,7 @@ pub fn standardize_stacks_microblock_trail(
                     hash: microblock_trail.parent_index_block_hash.clone(),
                     index: 0,
                 },
+                skipped_transactions,
             },
         })
     }
@@ -960,14 +1031,35 @@ pub fn get_standardized_non_fungible_currency_from_asset_class_id(
         }),
     }
 }
-//todo: this function has a lot of expects/panics. should return result instead
+
+fn get_standardized_stacks_transaction_receipt(
+    indexer_config: &IndexerConfig,
+    txid: &str,
+    tx_events: &[&NewEvent],
+    chain_ctx: &mut StacksChainContext,
+) -> Result<(StacksTransactionReceipt, Vec<Operation>), String> {
+    let events = tx_events
+        .iter()
+        .map(|e| e.into_chainhook_event())
+        .collect::<Result<Vec<StacksTransactionEvent>, String>>()?;
+    get_standardized_stacks_receipt(
+        txid,
+        events,
+        &mut chain_ctx.asset_class_map,
+        &indexer_config.get_stacks_node_config().rpc_url,
+        true,
+    )
+}
+
+/// Builds the receipt and the operations of a transaction from its events. Fails on malformed
+/// event data, such as amounts that aren't valid integers.
 pub fn get_standardized_stacks_receipt(
     _txid: &str,
     events: Vec<StacksTransactionEvent>,
     asset_class_cache: &mut HashMap<String, AssetClassCache>,
     node_url: &str,
     include_operations: bool,
-) -> (StacksTransactionReceipt, Vec<Operation>) {
+) -> Result<(StacksTransactionReceipt, Vec<Operation>), String> {
     let mut mutated_contracts_radius = HashSet::new();
     let mut mutated_assets_radius = HashSet::new();
     let mut operations = vec![];
@@ -990,7 +1082,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency: get_stacks_currency(),
                         }),
                         metadata: None,
@@ -1011,10 +1103,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data
-                                .locked_amount
-                                .parse::<u128>()
-                                .expect("Unable to parse u64"),
+                            value: parse_amount(&data.locked_amount)?,
                             currency: get_stacks_currency(),
                         }),
                         metadata: None,
@@ -1035,7 +1124,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency: get_stacks_currency(),
                         }),
                         metadata: None,
@@ -1059,7 +1148,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency: get_stacks_currency(),
                         }),
                         metadata: None,
@@ -1081,7 +1170,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency: get_stacks_currency(),
                         }),
                         metadata: None,
@@ -1209,12 +1298,7 @@ pub fn get_standardized_stacks_receipt(
                         node_url,
                     );
 
-                    let value = match data.amount.parse::<u128>() {
-                        Ok(value) => value,
-                        Err(e) => {
-                            panic!("unable to parse u64 {:?}: {:?}", data, e);
-                        }
-                    };
+                    let value = parse_amount(&data.amount)?;
 
                     operations.push(Operation {
                         operation_identifier: OperationIdentifier {
@@ -1257,7 +1341,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency,
                         }),
                         metadata: None,
@@ -1291,7 +1375,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency: currency.clone(),
                         }),
                         metadata: None,
@@ -1313,7 +1397,7 @@ pub fn get_standardized_stacks_receipt(
                             sub_account: None,
                         },
                         amount: Some(Amount {
-                            value: data.amount.parse::<u128>().expect("Unable to parse u64"),
+                            value: parse_amount(&data.amount)?,
                             currency,
                         }),
                         metadata: None,
@@ -1333,7 +1417,13 @@ pub fn get_standardized_stacks_receipt(
 
     let receipt =
         StacksTransactionReceipt::new(mutated_contracts_radius, mutated_assets_radius, events);
-    (receipt, operations)
+    Ok((receipt, operations))
+}
+
+fn parse_amount(amount: &str) -> Result<u128, String> {
+    amount
+        .parse::<u128>()
+        .map_err(|e| format!("unable to parse amount {}: {}", amount, e))
 }
 
 fn get_mutated_ids(asset_class_id: &str) -> (String, String) {
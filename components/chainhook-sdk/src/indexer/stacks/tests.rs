@@ -11,6 +11,7 @@ use super::{
     super::tests::{helpers, process_stacks_blocks_and_check_expectations},
     NewEvent,
 };
+use std::collections::HashMap;
 use test_case::test_case;
 
 #[test]
@@ -398,3 +399,191 @@ fn into_chainhook_event_rejects_invalid_missing_event() {
         .into_chainhook_event()
         .expect_err("expected error on missing event");
 }
+
+#[test]
+fn into_chainhook_event_rejects_malformed_event_data() {
+    let mut new_event = create_new_event_from_stacks_event(
+        StacksTransactionEventPayload::STXTransferEvent(STXTransferEventData {
+            sender: String::new(),
+            recipient: String::new(),
+            amount: "1".to_string(),
+        }),
+    );
+    new_event.stx_transfer_event = Some(serde_json::json!({ "amount": 1 }));
+    new_event
+        .into_chainhook_event()
+        .expect_err("expected error on malformed event data");
+}
+
+#[test]
+fn get_standardized_stacks_receipt_rejects_invalid_amounts() {
+    use super::get_standardized_stacks_receipt;
+    use chainhook_types::{StacksTransactionEvent, StacksTransactionEventPosition};
+
+    let events = vec![StacksTransactionEvent {
+        event_payload: StacksTransactionEventPayload::STXTransferEvent(STXTransferEventData {
+            sender: String::new(),
+            recipient: String::new(),
+            amount: "not-an-amount".to_string(),
+        }),
+        position: StacksTransactionEventPosition { index: 0 },
+    }];
+    get_standardized_stacks_receipt("0x", events, &mut HashMap::new(), "", true)
+        .expect_err("expected error on invalid amount");
+}
+
+#[test]
+fn it_standardizes_nakamoto_blocks() {
+    use super::{standardize_stacks_marshalled_block, IndexerConfig, StacksChainContext};
+    use crate::indexer::MalformedStacksTransactionPolicy;
+    use crate::utils::Context;
+    use chainhook_types::{BitcoinBlockSignaling, BitcoinNetwork, StacksNetwork, StacksNodeConfig};
+
+    let indexer_config = IndexerConfig {
+        bitcoin_network: BitcoinNetwork::Regtest,
+        stacks_network: StacksNetwork::Devnet,
+        bitcoind_rpc_url: "http://0.0.0.0:18443".into(),
+        bitcoind_rpc_username: "devnet".into(),
+        bitcoind_rpc_password: "devnet".into(),
+        bitcoind_rpc_fallbacks: vec![],
+        esplora_api_url: None,
+        bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
+            StacksNodeConfig::default_localhost(20455),
+        ),
+        bitcoin_confirmation_depth: None,
+        stacks_confirmation_depth: None,
+        network_parameters: None,
+        malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
+    };
+    let marshalled_block = serde_json::json!({
+        "block_height": 2,
+        "block_hash": "0x02",
+        "index_block_hash": "0x0002",
+        "burn_block_height": 110,
+        "burn_block_hash": "0x0110",
+        "parent_block_hash": "0x01",
+        "parent_index_block_hash": "0x0001",
+        "parent_microblock": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "parent_microblock_sequence": 0,
+        "parent_burn_block_hash": "0x0109",
+        "parent_burn_block_height": 109,
+        "parent_burn_block_timestamp": 1000,
+        "transactions": [],
+        "events": [],
+        "matured_miner_rewards": [],
+        "block_time": 1012,
+        "tenure_height": 42,
+        "signer_bitvec": "000800000001ff",
+        "signer_signature": ["1234", "2345"],
+        "signer_signature_hash": "3456",
+        "miner_signature": "4567",
+    });
+    let block = standardize_stacks_marshalled_block(
+        &indexer_config,
+        marshalled_block,
+        &mut StacksChainContext::new(&StacksNetwork::Devnet),
+        &Context::empty(),
+    )
+    .unwrap();
+    assert_eq!(block.timestamp, 1012);
+    assert_eq!(block.metadata.tenure_height, Some(42));
+    assert_eq!(
+        block.metadata.signer_signature,
+        Some(vec!["1234".to_string(), "2345".to_string()])
+    );
+    assert_eq!(
+        block.metadata.signer_signature_hash,
+        Some("3456".to_string())
+    );
+    assert_eq!(block.metadata.miner_signature, Some("4567".to_string()));
+    assert!(block.metadata.confirm_microblock_identifier.is_none());
+}
+
+#[test]
+fn it_skips_or_fails_on_malformed_microblock_transactions() {
+    use super::{
+        standardize_stacks_marshalled_microblock_trail, IndexerConfig, StacksChainContext,
+    };
+    use crate::indexer::MalformedStacksTransactionPolicy;
+    use crate::utils::Context;
+    use chainhook_types::{
+        BitcoinBlockSignaling, BitcoinNetwork, StacksNetwork, StacksNodeConfig,
+        TransactionIdentifier,
+    };
+
+    let mut indexer_config = IndexerConfig {
+        bitcoin_network: BitcoinNetwork::Regtest,
+        stacks_network: StacksNetwork::Devnet,
+        bitcoind_rpc_url: "http://0.0.0.0:18443".into(),
+        bitcoind_rpc_username: "devnet".into(),
+        bitcoind_rpc_password: "devnet".into(),
+        bitcoind_rpc_fallbacks: vec![],
+        esplora_api_url: None,
+        bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
+            StacksNodeConfig::default_localhost(20455),
+        ),
+        bitcoin_confirmation_depth: None,
+        stacks_confirmation_depth: None,
+        network_parameters: None,
+        malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
+    };
+    let marshalled_microblock_trail = serde_json::json!({
+        "parent_index_block_hash": "0x0001",
+        "burn_block_hash": "0x0110",
+        "burn_block_height": 110,
+        "burn_block_timestamp": 1000,
+        "transactions": [{
+            "txid": "0x01",
+            "tx_index": 0,
+            "status": "success",
+            "raw_result": "0x03",
+            "raw_tx": "0x00",
+            "execution_cost": null,
+            "microblock_sequence": 0,
+            "microblock_hash": "0x0a",
+            "microblock_parent_hash": "0x00",
+            "contract_abi": null,
+        }],
+        "events": [],
+    });
+
+    let microblocks = standardize_stacks_marshalled_microblock_trail(
+        &indexer_config,
+        marshalled_microblock_trail.clone(),
+        &mut StacksChainContext::new(&StacksNetwork::Devnet),
+        &Context::empty(),
+    )
+    .unwrap();
+    assert_eq!(microblocks.len(), 1);
+    assert!(microblocks[0].transactions.is_empty());
+    assert_eq!(
+        microblocks[0].metadata.skipped_transactions,
+        vec![TransactionIdentifier {
+            hash: "0x01".to_string()
+        }]
+    );
+
+    indexer_config.malformed_stacks_transactions = MalformedStacksTransactionPolicy::FailBlock;
+    standardize_stacks_marshalled_microblock_trail(
+        &indexer_config,
+        marshalled_microblock_trail,
+        &mut StacksChainContext::new(&StacksNetwork::Devnet),
+        &Context::empty(),
+    )
+    .expect_err("expected the malformed transaction to fail the trail");
+}
+
+#[test]
+fn test_stacks_block_identifier_from_header() {
+    // version, burn and work (42), followed by the rest of the header
+    let mut header = vec![0u8; 247];
+    header[16] = 42;
+    let block_identifier =
+        super::get_stacks_block_identifier(&"11".repeat(20), &hex::encode(header)).unwrap();
+    assert_eq!(block_identifier.index, 42);
+    assert_eq!(
+        block_identifier.hash,
+        "0x68b7e76cc0dcea3f13c05a4868b3cde88b5301600a8491889e0b91036f47ce20"
+    );
+    assert!(super::get_stacks_block_identifier("11", "0000").is_err());
+}
@@ -52,6 +52,7 @@ pub fn generate_test_microblock(
         transactions,
         metadata: StacksMicroblockMetadata {
             anchor_block_identifier: anchor.block_identifier,
+            skipped_transactions: vec![],
         },
     })
 }
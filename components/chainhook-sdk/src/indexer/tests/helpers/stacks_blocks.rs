@@ -76,6 +76,8 @@ pub fn generate_test_stacks_block(
             tenure_height: Some(1122),
             signer_bitvec: Some("1010101010101".to_owned()),
             signer_signature: Some(vec!["1234".to_owned(), "2345".to_owned()]),
+            signer_signature_hash: Some("3456".to_owned()),
+            miner_signature: Some("4567".to_owned()),
             cycle_number: Some(1),
             reward_set: Some(StacksBlockMetadataRewardSet {
                 pox_ustx_threshold: "50000".to_owned(),
@@ -93,6 +95,7 @@ pub fn generate_test_stacks_block(
                     },
                 ]),
             }),
+            skipped_transactions: vec![],
         },
     })
 }
@@ -1,3 +1,4 @@
+#[cfg(all(feature = "rocket", not(feature = "axum-ingestion")))]
 #[macro_use]
 extern crate rocket;
 
@@ -21,6 +22,9 @@ pub extern crate fxhash;
 pub use bitcoincore_rpc::bitcoin;
 pub use chainhook_types as types;
 
+#[cfg(not(any(feature = "rocket", feature = "axum-ingestion")))]
+compile_error!("either the `rocket` or the `axum-ingestion` feature must be enabled");
+
 pub mod chainhooks;
 pub mod indexer;
 pub mod monitoring;
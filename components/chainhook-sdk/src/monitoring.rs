@@ -1,3 +1,7 @@
+use crate::chainhooks::types::{ChainhookStore, DEFAULT_NAMESPACE};
+use crate::indexer::bitcoin::{build_http_client, source::bitcoin_block_source};
+use crate::observer::EventObserverConfig;
+use crate::types::BitcoinBlockSignaling;
 use crate::utils::Context;
 
 use hiro_system_kit::slog;
@@ -9,13 +13,16 @@ use hyper::{
 use prometheus::{
     self,
     core::{AtomicU64, GenericGauge},
-    Encoder, IntGauge, Registry, TextEncoder,
+    Encoder, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
 };
-use rocket::serde::json::{json, Value as JsonValue};
-use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value as JsonValue};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 type UInt64Gauge = GenericGauge<AtomicU64>;
 
+/// Interval at which the chain tips of the nodes are polled.
+const NODE_TIP_POLLING_INTERVAL: Duration = Duration::from_secs(15);
+
 #[derive(Debug, Clone)]
 pub struct PrometheusMonitoring {
     pub stx_highest_block_appended: UInt64Gauge,
@@ -29,6 +36,12 @@ pub struct PrometheusMonitoring {
     pub stx_last_block_ingestion_time: UInt64Gauge,
     pub stx_registered_predicates: UInt64Gauge,
     pub stx_deregistered_predicates: UInt64Gauge,
+    pub stx_dropped_mempool_events: UInt64Gauge,
+    pub stx_skipped_transactions: UInt64Gauge,
+    pub stx_node_tip_height: UInt64Gauge,
+    pub stx_node_tip_lag: UInt64Gauge,
+    pub stx_last_event_ingestion_time: UInt64Gauge,
+    pub stx_seconds_since_last_event: UInt64Gauge,
     //
     pub btc_highest_block_appended: UInt64Gauge,
     pub btc_highest_block_received: UInt64Gauge,
@@ -41,7 +54,17 @@ pub struct PrometheusMonitoring {
     pub btc_last_block_ingestion_time: UInt64Gauge,
     pub btc_registered_predicates: UInt64Gauge,
     pub btc_deregistered_predicates: UInt64Gauge,
+    pub btc_node_tip_height: UInt64Gauge,
+    pub btc_node_tip_lag: UInt64Gauge,
+    pub btc_last_event_ingestion_time: UInt64Gauge,
+    pub btc_seconds_since_last_event: UInt64Gauge,
+    pub btc_refetched_blocks: UInt64Gauge,
+    //
+    pub registered_predicates_per_namespace: IntGaugeVec,
     pub registry: Registry,
+    /// Unix timestamp, in seconds, at which the monitoring started, standing for the last
+    /// ingested event until one is ingested.
+    started_at: u64,
 }
 
 impl Default for PrometheusMonitoring {
@@ -110,6 +133,36 @@ impl PrometheusMonitoring {
             "chainhook_stx_deregistered_predicates",
             "The number of Stacks predicates that have been deregistered by the Chainhook node.",
         );
+        let stx_dropped_mempool_events = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_dropped_mempool_events",
+            "The number of Stacks mempool events dropped because the Chainhook node was busy.",
+        );
+        let stx_skipped_transactions = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_skipped_transactions",
+            "The number of Stacks transactions left out of their block because they could not be standardized.",
+        );
+        let stx_node_tip_height = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_node_tip_height",
+            "The height of the chain tip of the Stacks node, as last polled.",
+        );
+        let stx_node_tip_lag = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_node_tip_lag",
+            "The difference between the chain tip of the Stacks node and the highest Stacks block evaluated.",
+        );
+        let stx_last_event_ingestion_time = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_last_event_ingestion_time",
+            "The time that the Chainhook node last ingested a Stacks event: block, microblocks or mempool transactions.",
+        );
+        let stx_seconds_since_last_event = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_stx_seconds_since_last_event",
+            "The number of seconds since the Chainhook node last ingested a Stacks event, or since it started.",
+        );
 
         // bitcoin metrics
         let btc_highest_block_appended = PrometheusMonitoring::create_and_register_uint64_gauge(
@@ -169,6 +222,40 @@ impl PrometheusMonitoring {
             "chainhook_btc_deregistered_predicates",
             "The number of Bitcoin predicates that have been deregistered by the Chainhook node.",
         );
+        let btc_node_tip_height = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_btc_node_tip_height",
+            "The height of the chain tip of the Bitcoin node, as last polled.",
+        );
+        let btc_node_tip_lag = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_btc_node_tip_lag",
+            "The difference between the chain tip of the Bitcoin node and the highest Bitcoin block evaluated.",
+        );
+        let btc_last_event_ingestion_time = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_btc_last_event_ingestion_time",
+            "The time that the Chainhook node last ingested a Bitcoin block, whether appended or not.",
+        );
+        let btc_seconds_since_last_event = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_btc_seconds_since_last_event",
+            "The number of seconds since the Chainhook node last ingested a Bitcoin block, or since it started.",
+        );
+        let btc_refetched_blocks = PrometheusMonitoring::create_and_register_uint64_gauge(
+            &registry,
+            "chainhook_btc_refetched_blocks",
+            "The number of Bitcoin blocks fetched again from the Bitcoin node because they could not be processed.",
+        );
+
+        // namespace metrics
+        let registered_predicates_per_namespace =
+            PrometheusMonitoring::create_and_register_int_gauge_vec(
+                &registry,
+                "chainhook_namespace_registered_predicates",
+                "The number of predicates registered by the Chainhook node, per chain and namespace.",
+                &["chain", "namespace"],
+            );
 
         PrometheusMonitoring {
             stx_highest_block_appended,
@@ -182,6 +269,12 @@ impl PrometheusMonitoring {
             stx_last_block_ingestion_time,
             stx_registered_predicates,
             stx_deregistered_predicates,
+            stx_dropped_mempool_events,
+            stx_skipped_transactions,
+            stx_node_tip_height,
+            stx_node_tip_lag,
+            stx_last_event_ingestion_time,
+            stx_seconds_since_last_event,
             //
             btc_highest_block_appended,
             btc_highest_block_received,
@@ -194,7 +287,15 @@ impl PrometheusMonitoring {
             btc_last_block_ingestion_time,
             btc_registered_predicates,
             btc_deregistered_predicates,
+            btc_node_tip_height,
+            btc_node_tip_lag,
+            btc_last_event_ingestion_time,
+            btc_seconds_since_last_event,
+            btc_refetched_blocks,
+            //
+            registered_predicates_per_namespace,
             registry,
+            started_at: now_secs(),
         }
     }
     // setup helpers
@@ -214,6 +315,17 @@ impl PrometheusMonitoring {
         g
     }
 
+    pub fn create_and_register_int_gauge_vec(
+        registry: &Registry,
+        name: &str,
+        help: &str,
+        labels: &[&str],
+    ) -> IntGaugeVec {
+        let g = IntGaugeVec::new(Opts::new(name, help), labels).unwrap();
+        registry.register(Box::new(g.clone())).unwrap();
+        g
+    }
+
     pub fn initialize(
         &self,
         stx_predicates: u64,
@@ -229,14 +341,38 @@ impl PrometheusMonitoring {
         }
     }
 
+    pub fn initialize_namespaces(&self, chainhook_store: &ChainhookStore) {
+        for predicate in chainhook_store.stacks_chainhooks.iter() {
+            self.namespace_metrics_register_predicate("stacks", predicate.namespace.as_deref());
+        }
+        for predicate in chainhook_store.bitcoin_chainhooks.iter() {
+            self.namespace_metrics_register_predicate("bitcoin", predicate.namespace.as_deref());
+        }
+    }
+
+    // namespace helpers
+    fn namespace_metrics_register_predicate(&self, chain: &str, namespace: Option<&str>) {
+        self.registered_predicates_per_namespace
+            .with_label_values(&[chain, namespace.unwrap_or(DEFAULT_NAMESPACE)])
+            .inc();
+    }
+
+    fn namespace_metrics_deregister_predicate(&self, chain: &str, namespace: Option<&str>) {
+        self.registered_predicates_per_namespace
+            .with_label_values(&[chain, namespace.unwrap_or(DEFAULT_NAMESPACE)])
+            .dec();
+    }
+
     // stx helpers
-    pub fn stx_metrics_deregister_predicate(&self) {
+    pub fn stx_metrics_deregister_predicate(&self, namespace: Option<&str>) {
         self.stx_registered_predicates.dec();
         self.stx_deregistered_predicates.inc();
+        self.namespace_metrics_deregister_predicate("stacks", namespace);
     }
 
-    pub fn stx_metrics_register_predicate(&self) {
+    pub fn stx_metrics_register_predicate(&self, namespace: Option<&str>) {
         self.stx_registered_predicates.inc();
+        self.namespace_metrics_register_predicate("stacks", namespace);
     }
     pub fn stx_metrics_set_registered_predicates(&self, registered_predicates: u64) {
         self.stx_registered_predicates.set(registered_predicates);
@@ -285,6 +421,25 @@ impl PrometheusMonitoring {
         }
     }
 
+    pub fn stx_metrics_mempool_event_dropped(&self) {
+        self.stx_dropped_mempool_events.inc();
+    }
+
+    pub fn stx_metrics_transactions_skipped(&self, skipped_transactions: u64) {
+        self.stx_skipped_transactions.inc_by(skipped_transactions);
+    }
+
+    pub fn stx_metrics_event_ingested(&self) {
+        self.stx_last_event_ingestion_time.set(now_secs());
+        self.stx_seconds_since_last_event.set(0);
+    }
+
+    pub fn stx_metrics_set_node_tip(&self, node_tip_height: u64) {
+        self.stx_node_tip_height.set(node_tip_height);
+        self.stx_node_tip_lag
+            .set(node_tip_height.saturating_sub(self.stx_highest_block_evaluated.get()));
+    }
+
     pub fn stx_metrics_block_evaluated(&self, new_block_height: u64) {
         let highest_evaluated = self.stx_highest_block_evaluated.get();
         if new_block_height > highest_evaluated {
@@ -297,13 +452,15 @@ impl PrometheusMonitoring {
     }
 
     // btc helpers
-    pub fn btc_metrics_deregister_predicate(&self) {
+    pub fn btc_metrics_deregister_predicate(&self, namespace: Option<&str>) {
         self.btc_registered_predicates.dec();
         self.btc_deregistered_predicates.inc();
+        self.namespace_metrics_deregister_predicate("bitcoin", namespace);
     }
 
-    pub fn btc_metrics_register_predicate(&self) {
+    pub fn btc_metrics_register_predicate(&self, namespace: Option<&str>) {
         self.btc_registered_predicates.inc();
+        self.namespace_metrics_register_predicate("bitcoin", namespace);
     }
 
     pub fn btc_metrics_set_registered_predicates(&self, registered_predicates: u64) {
@@ -365,6 +522,34 @@ impl PrometheusMonitoring {
         }
     }
 
+    pub fn btc_metrics_event_ingested(&self) {
+        self.btc_last_event_ingestion_time.set(now_secs());
+        self.btc_seconds_since_last_event.set(0);
+    }
+
+    pub fn btc_metrics_block_refetched(&self) {
+        self.btc_refetched_blocks.inc();
+    }
+
+    pub fn btc_metrics_set_node_tip(&self, node_tip_height: u64) {
+        self.btc_node_tip_height.set(node_tip_height);
+        self.btc_node_tip_lag
+            .set(node_tip_height.saturating_sub(self.btc_highest_block_evaluated.get()));
+    }
+
+    /// Updates the time elapsed since the last event ingested on each chain, as of `now`.
+    pub fn refresh_ingestion_gaps(&self, now: u64) {
+        let last_event_at =
+            |last_event_ingestion_time: &UInt64Gauge| match last_event_ingestion_time.get() {
+                0 => self.started_at,
+                timestamp => timestamp,
+            };
+        self.stx_seconds_since_last_event
+            .set(now.saturating_sub(last_event_at(&self.stx_last_event_ingestion_time)));
+        self.btc_seconds_since_last_event
+            .set(now.saturating_sub(last_event_at(&self.btc_last_event_ingestion_time)));
+    }
+
     pub fn get_metrics(&self) -> JsonValue {
         json!({
             "bitcoin": {
@@ -381,6 +566,10 @@ impl PrometheusMonitoring {
                 },
                 "registered_predicates": self.btc_registered_predicates.get(),
                 "deregistered_predicates": self.btc_deregistered_predicates.get(),
+                "node_tip_height": self.btc_node_tip_height.get(),
+                "node_tip_lag": self.btc_node_tip_lag.get(),
+                "last_event_ingestion_at": self.btc_last_event_ingestion_time.get(),
+                "refetched_blocks": self.btc_refetched_blocks.get(),
             },
             "stacks": {
                 "last_received_block_height": self.stx_highest_block_received.get(),
@@ -396,11 +585,81 @@ impl PrometheusMonitoring {
                 },
                 "registered_predicates": self.stx_registered_predicates.get(),
                 "deregistered_predicates": self.stx_deregistered_predicates.get(),
+                "dropped_mempool_events": self.stx_dropped_mempool_events.get(),
+                "skipped_transactions": self.stx_skipped_transactions.get(),
+                "node_tip_height": self.stx_node_tip_height.get(),
+                "node_tip_lag": self.stx_node_tip_lag.get(),
+                "last_event_ingestion_at": self.stx_last_event_ingestion_time.get(),
             }
         })
     }
 }
 
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Polls the chain tips of the nodes the observer follows, so that the lag of the observer
+/// behind them is known, and refreshes the time elapsed since the last ingested events.
+pub async fn start_monitoring_node_tips(
+    config: EventObserverConfig,
+    prometheus_monitoring: PrometheusMonitoring,
+    ctx: Context,
+) {
+    let http_client = build_http_client();
+    let bitcoin_config = config.get_bitcoin_config();
+    loop {
+        let block_source = bitcoin_block_source(&http_client, &bitcoin_config, &ctx);
+        match block_source.retrieve_chain_tip_height().await {
+            Ok(tip) => prometheus_monitoring.btc_metrics_set_node_tip(tip),
+            Err(e) => ctx.try_log(|logger| {
+                slog::debug!(
+                    logger,
+                    "Prometheus monitoring: unable to poll Bitcoin tip: {}",
+                    e
+                )
+            }),
+        }
+        if let BitcoinBlockSignaling::Stacks(ref stacks_node_config) =
+            config.bitcoin_block_signaling
+        {
+            match retrieve_stacks_node_tip_height(&http_client, &stacks_node_config.rpc_url).await {
+                Ok(tip) => prometheus_monitoring.stx_metrics_set_node_tip(tip),
+                Err(e) => ctx.try_log(|logger| {
+                    slog::debug!(
+                        logger,
+                        "Prometheus monitoring: unable to poll Stacks tip: {}",
+                        e
+                    )
+                }),
+            }
+        }
+        prometheus_monitoring.refresh_ingestion_gaps(now_secs());
+        tokio::time::sleep(NODE_TIP_POLLING_INTERVAL).await;
+    }
+}
+
+async fn retrieve_stacks_node_tip_height(
+    http_client: &reqwest::Client,
+    rpc_url: &str,
+) -> Result<u64, String> {
+    let url = format!("{}/v2/info", rpc_url.trim_end_matches('/'));
+    let info: JsonValue = http_client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("unable to reach {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("unable to parse the answer of {}: {}", url, e))?;
+    info["stacks_tip_height"]
+        .as_u64()
+        .ok_or(format!("no stacks_tip_height in the answer of {}", url))
+}
+
 async fn serve_req(
     req: Request<Body>,
     registry: Registry,
@@ -488,10 +747,10 @@ mod test {
         prometheus.stx_metrics_set_registered_predicates(10);
         assert_eq!(prometheus.stx_registered_predicates.get(), 10);
         assert_eq!(prometheus.stx_deregistered_predicates.get(), 0);
-        prometheus.stx_metrics_register_predicate();
+        prometheus.stx_metrics_register_predicate(None);
         assert_eq!(prometheus.stx_registered_predicates.get(), 11);
         assert_eq!(prometheus.stx_deregistered_predicates.get(), 0);
-        prometheus.stx_metrics_deregister_predicate();
+        prometheus.stx_metrics_deregister_predicate(None);
         assert_eq!(prometheus.stx_registered_predicates.get(), 10);
         assert_eq!(prometheus.stx_deregistered_predicates.get(), 1);
     }
@@ -546,14 +805,63 @@ mod test {
         prometheus.btc_metrics_set_registered_predicates(10);
         assert_eq!(prometheus.btc_registered_predicates.get(), 10);
         assert_eq!(prometheus.btc_deregistered_predicates.get(), 0);
-        prometheus.btc_metrics_register_predicate();
+        prometheus.btc_metrics_register_predicate(None);
         assert_eq!(prometheus.btc_registered_predicates.get(), 11);
         assert_eq!(prometheus.btc_deregistered_predicates.get(), 0);
-        prometheus.btc_metrics_deregister_predicate();
+        prometheus.btc_metrics_deregister_predicate(None);
         assert_eq!(prometheus.btc_registered_predicates.get(), 10);
         assert_eq!(prometheus.btc_deregistered_predicates.get(), 1);
     }
 
+    #[test]
+    fn it_tracks_registered_predicates_per_namespace() {
+        let prometheus = PrometheusMonitoring::new();
+        let registered_predicates = |chain: &str, namespace: &str| {
+            prometheus
+                .registered_predicates_per_namespace
+                .with_label_values(&[chain, namespace])
+                .get()
+        };
+        prometheus.btc_metrics_register_predicate(None);
+        prometheus.btc_metrics_register_predicate(Some("team-a"));
+        prometheus.btc_metrics_register_predicate(Some("team-a"));
+        prometheus.stx_metrics_register_predicate(Some("team-b"));
+        assert_eq!(registered_predicates("bitcoin", "default"), 1);
+        assert_eq!(registered_predicates("bitcoin", "team-a"), 2);
+        assert_eq!(registered_predicates("stacks", "team-b"), 1);
+        prometheus.btc_metrics_deregister_predicate(Some("team-a"));
+        assert_eq!(registered_predicates("bitcoin", "team-a"), 1);
+        assert_eq!(prometheus.btc_registered_predicates.get(), 2);
+    }
+
+    #[test]
+    fn it_tracks_node_tip_lags_and_ingestion_gaps() {
+        let prometheus = PrometheusMonitoring::new();
+        prometheus.btc_metrics_block_evaluated(100);
+        prometheus.btc_metrics_set_node_tip(103);
+        assert_eq!(prometheus.btc_node_tip_height.get(), 103);
+        assert_eq!(prometheus.btc_node_tip_lag.get(), 3);
+        prometheus.btc_metrics_block_evaluated(103);
+        prometheus.btc_metrics_set_node_tip(103);
+        assert_eq!(prometheus.btc_node_tip_lag.get(), 0);
+        prometheus.stx_metrics_set_node_tip(50);
+        assert_eq!(prometheus.stx_node_tip_lag.get(), 50);
+
+        // until an event is ingested, gaps are measured from the start of the monitoring
+        let started_at = prometheus.started_at;
+        prometheus.refresh_ingestion_gaps(started_at + 30);
+        assert_eq!(prometheus.btc_seconds_since_last_event.get(), 30);
+        assert_eq!(prometheus.stx_seconds_since_last_event.get(), 30);
+        prometheus.btc_metrics_event_ingested();
+        let ingested_at = prometheus.btc_last_event_ingestion_time.get();
+        prometheus.refresh_ingestion_gaps(ingested_at + 5);
+        assert_eq!(prometheus.btc_seconds_since_last_event.get(), 5);
+        assert!(prometheus.stx_seconds_since_last_event.get() >= 5);
+
+        prometheus.btc_metrics_block_refetched();
+        assert_eq!(prometheus.btc_refetched_blocks.get(), 1);
+    }
+
     #[test]
     fn it_tracks_btc_reorgs() {
         let prometheus = PrometheusMonitoring::new();
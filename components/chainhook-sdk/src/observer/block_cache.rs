@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chainhook_types::{BitcoinBlockData, BlockIdentifier};
+use hiro_system_kit::slog;
+
+use super::BitcoinBlockDataCached;
+use crate::utils::Context;
+
+/// Storage the blocks evicted from a [BitcoinBlockCache] are spilled to, so that they can
+/// still be rolled back or confirmed later on.
+pub trait BitcoinBlockSpillStore: Send + Sync {
+    fn put(&self, cache: &BitcoinBlockDataCached) -> Result<(), String>;
+    fn get(
+        &self,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<Option<BitcoinBlockDataCached>, String>;
+    fn remove(&self, block_identifier: &BlockIdentifier) -> Result<(), String>;
+}
+
+/// A [BitcoinBlockSpillStore] keeping the evicted blocks in memory, mostly useful for testing.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryBitcoinBlockSpillStore {
+    blocks: Arc<Mutex<HashMap<BlockIdentifier, BitcoinBlockDataCached>>>,
+}
+
+impl InMemoryBitcoinBlockSpillStore {
+    pub fn new() -> Self {
+        InMemoryBitcoinBlockSpillStore::default()
+    }
+}
+
+impl BitcoinBlockSpillStore for InMemoryBitcoinBlockSpillStore {
+    fn put(&self, cache: &BitcoinBlockDataCached) -> Result<(), String> {
+        self.blocks
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(cache.block.block_identifier.clone(), cache.clone());
+        Ok(())
+    }
+
+    fn get(
+        &self,
+        block_identifier: &BlockIdentifier,
+    ) -> Result<Option<BitcoinBlockDataCached>, String> {
+        Ok(self
+            .blocks
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(block_identifier)
+            .cloned())
+    }
+
+    fn remove(&self, block_identifier: &BlockIdentifier) -> Result<(), String> {
+        self.blocks
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(block_identifier);
+        Ok(())
+    }
+}
+
+/// Bitcoin blocks received by the observer and not confirmed yet.
+///
+/// When `max_size` is set, the least recently used blocks are evicted once the cache is full,
+/// and spilled to the [BitcoinBlockSpillStore] if one is set. Evicted blocks are otherwise
+/// dropped, and chain events referring to them will miss them.
+pub struct BitcoinBlockCache {
+    blocks: HashMap<BlockIdentifier, (BitcoinBlockDataCached, u64)>,
+    recency: BTreeMap<u64, BlockIdentifier>,
+    next_tick: u64,
+    max_size: Option<usize>,
+    spill_store: Option<Arc<dyn BitcoinBlockSpillStore>>,
+    spilled: HashSet<BlockIdentifier>,
+}
+
+impl BitcoinBlockCache {
+    pub fn new(
+        max_size: Option<usize>,
+        spill_store: Option<Arc<dyn BitcoinBlockSpillStore>>,
+    ) -> Self {
+        BitcoinBlockCache {
+            blocks: HashMap::new(),
+            recency: BTreeMap::new(),
+            next_tick: 0,
+            max_size: max_size.map(|size| size.max(1)),
+            spill_store,
+            spilled: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn insert(&mut self, cache: BitcoinBlockDataCached, ctx: &Context) {
+        let block_identifier = cache.block.block_identifier.clone();
+        self.forget_spilled(&block_identifier, ctx);
+        let tick = self.tick();
+        if let Some((_, previous_tick)) =
+            self.blocks.insert(block_identifier.clone(), (cache, tick))
+        {
+            self.recency.remove(&previous_tick);
+        }
+        self.recency.insert(tick, block_identifier);
+        self.evict(ctx);
+    }
+
+    /// Returns the block, loading it back from the spill store if it was evicted.
+    pub fn get(
+        &mut self,
+        block_identifier: &BlockIdentifier,
+        ctx: &Context,
+    ) -> Option<&BitcoinBlockDataCached> {
+        if !self.blocks.contains_key(block_identifier) {
+            let cache = self.take_spilled(block_identifier, ctx)?;
+            self.insert(cache, ctx);
+        }
+        let tick = self.tick();
+        let (cache, previous_tick) = self.blocks.get_mut(block_identifier)?;
+        self.recency.remove(previous_tick);
+        self.recency.insert(tick, block_identifier.clone());
+        *previous_tick = tick;
+        Some(cache)
+    }
+
+    /// Removes the block from the cache, and from the spill store if it was evicted.
+    pub fn remove(
+        &mut self,
+        block_identifier: &BlockIdentifier,
+        ctx: &Context,
+    ) -> Option<BitcoinBlockDataCached> {
+        match self.blocks.remove(block_identifier) {
+            Some((cache, tick)) => {
+                self.recency.remove(&tick);
+                Some(cache)
+            }
+            None => self.take_spilled(block_identifier, ctx),
+        }
+    }
+
+    /// Blocks currently held in memory. Blocks spilled to the store are not included.
+    pub fn blocks(&self) -> impl Iterator<Item = &BitcoinBlockData> {
+        self.blocks.values().map(|(cache, _)| &cache.block)
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+
+    fn evict(&mut self, ctx: &Context) {
+        let Some(max_size) = self.max_size else {
+            return;
+        };
+        while self.blocks.len() > max_size {
+            let Some((_, block_identifier)) = self.recency.pop_first() else {
+                return;
+            };
+            let Some((cache, _)) = self.blocks.remove(&block_identifier) else {
+                continue;
+            };
+            let Some(ref spill_store) = self.spill_store else {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Bitcoin block cache full, dropping block {}",
+                        block_identifier
+                    )
+                });
+                continue;
+            };
+            match spill_store.put(&cache) {
+                Ok(()) => {
+                    self.spilled.insert(block_identifier);
+                }
+                Err(e) => ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "Unable to spill bitcoin block {}: {}",
+                        block_identifier,
+                        e
+                    )
+                }),
+            }
+        }
+    }
+
+    fn take_spilled(
+        &mut self,
+        block_identifier: &BlockIdentifier,
+        ctx: &Context,
+    ) -> Option<BitcoinBlockDataCached> {
+        if !self.spilled.contains(block_identifier) {
+            return None;
+        }
+        let spill_store = self.spill_store.as_ref()?;
+        let cache = match spill_store.get(block_identifier) {
+            Ok(cache) => cache,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "Unable to load spilled bitcoin block {}: {}",
+                        block_identifier,
+                        e
+                    )
+                });
+                None
+            }
+        };
+        self.forget_spilled(block_identifier, ctx);
+        cache
+    }
+
+    fn forget_spilled(&mut self, block_identifier: &BlockIdentifier, ctx: &Context) {
+        if !self.spilled.remove(block_identifier) {
+            return;
+        }
+        let Some(ref spill_store) = self.spill_store else {
+            return;
+        };
+        if let Err(e) = spill_store.remove(block_identifier) {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to remove spilled bitcoin block {}: {}",
+                    block_identifier,
+                    e
+                )
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{BitcoinBlockCache, BitcoinBlockSpillStore, InMemoryBitcoinBlockSpillStore};
+    use crate::indexer::tests::helpers::bitcoin_blocks;
+    use crate::observer::BitcoinBlockDataCached;
+    use crate::utils::Context;
+
+    fn cached(block: chainhook_types::BitcoinBlockData) -> BitcoinBlockDataCached {
+        BitcoinBlockDataCached {
+            block,
+            processed_by_sidecar: false,
+        }
+    }
+
+    #[test]
+    fn it_spills_least_recently_used_blocks() {
+        let ctx = Context::empty();
+        let spill_store = InMemoryBitcoinBlockSpillStore::new();
+        let mut cache = BitcoinBlockCache::new(Some(2), Some(Arc::new(spill_store.clone())));
+        let a1 = bitcoin_blocks::A1(None);
+        let b1 = bitcoin_blocks::B1(None);
+        let c1 = bitcoin_blocks::C1(None);
+
+        cache.insert(cached(a1.clone()), &ctx);
+        cache.insert(cached(b1.clone()), &ctx);
+        assert!(cache.get(&a1.block_identifier, &ctx).is_some());
+        cache.insert(cached(c1.clone()), &ctx);
+        assert_eq!(cache.len(), 2);
+        assert!(spill_store.get(&b1.block_identifier).unwrap().is_some());
+
+        // Spilled blocks are loaded back, evicting the least recently used one.
+        assert!(cache.get(&b1.block_identifier, &ctx).is_some());
+        assert!(spill_store.get(&b1.block_identifier).unwrap().is_none());
+        assert!(spill_store.get(&a1.block_identifier).unwrap().is_some());
+
+        assert!(cache.remove(&a1.block_identifier, &ctx).is_some());
+        assert!(spill_store.get(&a1.block_identifier).unwrap().is_none());
+        assert!(cache.remove(&a1.block_identifier, &ctx).is_none());
+    }
+
+    #[test]
+    fn it_drops_evicted_blocks_without_spill_store() {
+        let ctx = Context::empty();
+        let mut cache = BitcoinBlockCache::new(Some(1), None);
+        let a1 = bitcoin_blocks::A1(None);
+        let b1 = bitcoin_blocks::B1(None);
+
+        cache.insert(cached(a1.clone()), &ctx);
+        cache.insert(cached(b1.clone()), &ctx);
+        assert!(cache.get(&a1.block_identifier, &ctx).is_none());
+        assert!(cache.get(&b1.block_identifier, &ctx).is_some());
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, SendError, Sender, TrySendError};
+use serde::Deserialize;
+
+/// What a channel created by [bounded_channel] does with a message sent while it is full.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FullChannelPolicy {
+    /// Wait for the receiver to make room, slowing the sender down to the receiver's pace.
+    #[default]
+    Block,
+    /// Drop the oldest message queued to make room for the new one, the sender never waiting.
+    DropOldest,
+}
+
+/// Sending half of a channel created by [bounded_channel], applying its [FullChannelPolicy].
+/// Senders of unbounded channels convert into one, their messages never being dropped.
+pub struct BoundedSender<T> {
+    tx: Sender<T>,
+    /// Receiving half the oldest messages are dropped from, with [FullChannelPolicy::DropOldest].
+    oldest: Option<Receiver<T>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        BoundedSender {
+            tx: self.tx.clone(),
+            oldest: self.oldest.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl<T> BoundedSender<T> {
+    /// Sends a message, waiting for room or dropping the oldest message queued when the channel
+    /// is full. Fails once the receiving half is dropped, except with
+    /// [FullChannelPolicy::DropOldest], whose senders keep it alive.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let Some(ref oldest) = self.oldest else {
+            return self.tx.send(msg);
+        };
+        let mut msg = msg;
+        loop {
+            match self.tx.try_send(msg) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(rejected)) => {
+                    if oldest.try_recv().is_ok() {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    msg = rejected;
+                }
+                Err(TrySendError::Disconnected(rejected)) => return Err(SendError(rejected)),
+            }
+        }
+    }
+
+    /// Number of messages dropped to make room for newer ones since the channel was created.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of messages the channel holds, or None if it is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.tx.capacity()
+    }
+}
+
+impl<T> From<Sender<T>> for BoundedSender<T> {
+    fn from(tx: Sender<T>) -> Self {
+        BoundedSender {
+            tx,
+            oldest: None,
+            dropped: Arc::default(),
+        }
+    }
+}
+
+/// Creates a channel holding up to `capacity` messages, at least one, and applying `policy` to
+/// the messages sent while it is full.
+pub fn bounded_channel<T>(
+    capacity: usize,
+    policy: FullChannelPolicy,
+) -> (BoundedSender<T>, Receiver<T>) {
+    let (tx, rx) = crossbeam_channel::bounded(capacity.max(1));
+    let oldest = match policy {
+        FullChannelPolicy::Block => None,
+        FullChannelPolicy::DropOldest => Some(rx.clone()),
+    };
+    let tx = BoundedSender {
+        tx,
+        oldest,
+        dropped: Arc::default(),
+    };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bounded_channel, FullChannelPolicy};
+
+    #[test]
+    fn it_drops_the_oldest_messages_of_full_channels() {
+        let (tx, rx) = bounded_channel(2, FullChannelPolicy::DropOldest);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(tx.dropped(), 3);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn it_blocks_senders_of_full_channels() {
+        let (tx, rx) = bounded_channel(1, FullChannelPolicy::Block);
+        tx.send(0).unwrap();
+        let sender = std::thread::spawn(move || tx.send(1));
+        assert_eq!(rx.recv().unwrap(), 0);
+        assert_eq!(rx.recv().unwrap(), 1);
+        sender.join().unwrap().unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+}
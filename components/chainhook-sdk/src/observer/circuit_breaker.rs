@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use hiro_system_kit::slog;
+use serde::{Deserialize, Serialize};
+
+use super::PredicatesConfig;
+use crate::chainhooks::types::{ChainhookInstance, HttpHook};
+use crate::utils::{build_http_post_client, send_request, Context};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_PROBE_INTERVAL_SEC: u64 = 60;
+
+/// Header set on the requests probing an endpoint whose circuit is open.
+pub const PROBE_HEADER: &str = "X-Chainhook-Probe";
+
+/// Pauses the predicates posting to an `http_post` endpoint once deliveries to this endpoint
+/// failed `failure_threshold` times in a row, then probes the endpoint every
+/// `probe_interval_sec` and resumes the predicates once it answers again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed deliveries opening the circuit, defaults to 3.
+    pub failure_threshold: Option<u32>,
+    /// Delay between two probes of an open circuit in seconds, defaults to 60.
+    pub probe_interval_sec: Option<u64>,
+}
+
+impl CircuitBreakerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(0) = self.failure_threshold {
+            return Err("failure_threshold must be greater than 0".to_string());
+        }
+        if let Some(0) = self.probe_interval_sec {
+            return Err("probe_interval_sec must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+struct EndpointCircuit {
+    hook: HttpHook,
+    consecutive_failures: u32,
+    last_probe: Option<Instant>,
+    paused_predicates: Vec<ChainhookInstance>,
+}
+
+impl EndpointCircuit {
+    fn is_open(&self) -> bool {
+        self.last_probe.is_some()
+    }
+}
+
+/// Tracks the health of the endpoints targeted by `http_post` actions, keyed by url.
+pub struct DeliveryCircuitBreakers {
+    failure_threshold: u32,
+    probe_interval: Duration,
+    circuits: HashMap<String, EndpointCircuit>,
+}
+
+impl DeliveryCircuitBreakers {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        DeliveryCircuitBreakers {
+            failure_threshold: config
+                .failure_threshold
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+                .max(1),
+            probe_interval: Duration::from_secs(
+                config
+                    .probe_interval_sec
+                    .unwrap_or(DEFAULT_PROBE_INTERVAL_SEC)
+                    .max(1),
+            ),
+            circuits: HashMap::new(),
+        }
+    }
+
+    pub fn probe_interval(&self) -> Duration {
+        self.probe_interval
+    }
+
+    pub fn is_open(&self, url: &str) -> bool {
+        self.circuits.get(url).map(|c| c.is_open()).unwrap_or(false)
+    }
+
+    pub fn record_success(&mut self, url: &str) {
+        if let Some(circuit) = self.circuits.get_mut(url) {
+            if !circuit.is_open() {
+                circuit.consecutive_failures = 0;
+            }
+        }
+    }
+
+    /// Records a failed delivery to the hook's endpoint, returning true if its circuit is open.
+    pub fn record_failure(&mut self, hook: &HttpHook) -> bool {
+        let circuit = self
+            .circuits
+            .entry(hook.url.clone())
+            .or_insert_with(|| EndpointCircuit {
+                hook: hook.clone(),
+                consecutive_failures: 0,
+                last_probe: None,
+                paused_predicates: vec![],
+            });
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.failure_threshold && !circuit.is_open() {
+            circuit.last_probe = Some(Instant::now());
+        }
+        circuit.is_open()
+    }
+
+    /// Keeps track of a predicate paused because the circuit of its endpoint is open, so that
+    /// it can be resumed once the endpoint recovers. Returns false if it was already paused.
+    pub fn pause_predicate(&mut self, url: &str, predicate: ChainhookInstance) -> bool {
+        let Some(circuit) = self.circuits.get_mut(url) else {
+            return false;
+        };
+        if circuit
+            .paused_predicates
+            .iter()
+            .any(|p| p.uuid() == predicate.uuid())
+        {
+            return false;
+        }
+        circuit.paused_predicates.push(predicate);
+        true
+    }
+
+    /// Forgets a paused predicate, when it gets deregistered.
+    pub fn forget_predicate(&mut self, uuid: &str) {
+        for circuit in self.circuits.values_mut() {
+            circuit.paused_predicates.retain(|p| p.uuid() != uuid);
+        }
+    }
+
+    /// Replaces a paused predicate, when it gets updated, so that it is resumed as updated.
+    pub fn update_predicate(&mut self, predicate: &ChainhookInstance) {
+        for circuit in self.circuits.values_mut() {
+            for paused_predicate in circuit.paused_predicates.iter_mut() {
+                if paused_predicate.uuid() == predicate.uuid() {
+                    *paused_predicate = predicate.clone();
+                }
+            }
+        }
+    }
+
+    /// Returns the hooks of the open circuits that were not probed for `probe_interval`.
+    fn endpoints_due_for_probe(&mut self, now: Instant) -> Vec<HttpHook> {
+        let mut hooks = vec![];
+        for circuit in self.circuits.values_mut() {
+            match circuit.last_probe {
+                Some(last_probe) if now.duration_since(last_probe) >= self.probe_interval => {
+                    circuit.last_probe = Some(now);
+                    hooks.push(circuit.hook.clone());
+                }
+                _ => {}
+            }
+        }
+        hooks
+    }
+
+    /// Closes the circuit of an endpoint, returning the predicates to resume.
+    fn close(&mut self, url: &str) -> Vec<ChainhookInstance> {
+        self.circuits
+            .remove(url)
+            .map(|c| c.paused_predicates)
+            .unwrap_or_default()
+    }
+
+    /// Probes the endpoints whose circuit is open, closing the circuits of the ones answering
+    /// with a success status. Returns the predicates to resume.
+    pub async fn probe_open_circuits(
+        &mut self,
+        predicates_config: &PredicatesConfig,
+        ctx: &Context,
+    ) -> Vec<ChainhookInstance> {
+        let mut predicates_to_resume = vec![];
+        for hook in self.endpoints_due_for_probe(Instant::now()) {
+            match probe_endpoint(&hook, predicates_config, ctx).await {
+                Ok(_) => {
+                    ctx.try_log(|logger| {
+                        slog::info!(logger, "Endpoint {} recovered, closing circuit", hook.url)
+                    });
+                    predicates_to_resume.append(&mut self.close(&hook.url));
+                }
+                Err(e) => {
+                    ctx.try_log(|logger| {
+                        slog::debug!(logger, "Endpoint {} still failing: {}", hook.url, e)
+                    });
+                }
+            }
+        }
+        predicates_to_resume
+    }
+}
+
+async fn probe_endpoint(
+    hook: &HttpHook,
+    predicates_config: &PredicatesConfig,
+    ctx: &Context,
+) -> Result<(), String> {
+    let client = build_http_post_client(hook, predicates_config)?;
+    let request = client
+        .post(&hook.url)
+        .header("Content-Type", "application/json")
+        .header("Authorization", hook.authorization_header.clone())
+        .header(PROBE_HEADER, "true")
+        .body("{}");
+    send_request(request, 1, 0, ctx).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{CircuitBreakerConfig, DeliveryCircuitBreakers};
+    use crate::chainhooks::bitcoin::{BitcoinChainhookInstance, BitcoinPredicateType};
+    use crate::chainhooks::types::{ChainhookInstance, DeliveryOptions, HookAction, HttpHook};
+    use chainhook_types::BitcoinNetwork;
+
+    fn hook() -> HttpHook {
+        HttpHook {
+            url: "http://localhost:20446".into(),
+            authorization_header: "Bearer 1234".into(),
+            signing_secret: None,
+            tls: None,
+            compression: None,
+            require_ack: None,
+            delivery: DeliveryOptions::default(),
+        }
+    }
+
+    fn predicate(uuid: &str) -> ChainhookInstance {
+        ChainhookInstance::Bitcoin(BitcoinChainhookInstance {
+            uuid: uuid.into(),
+            owner_uuid: None,
+            labels: None,
+            metadata: None,
+            namespace: None,
+            name: uuid.into(),
+            network: BitcoinNetwork::Regtest,
+            version: 1,
+            blocks: None,
+            start_block: None,
+            end_block: None,
+            expire_after_occurrence: None,
+            predicate: BitcoinPredicateType::Block,
+            action: HookAction::HttpPost(hook()),
+            include_proof: false,
+            include_inputs: false,
+            include_outputs: false,
+            include_witness: false,
+            include_fields: None,
+            transform: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            rollback_action: None,
+            payload_envelope: None,
+            enabled: true,
+            expired_at: None,
+        })
+    }
+
+    #[test]
+    fn it_opens_after_consecutive_failures() {
+        let mut breakers = DeliveryCircuitBreakers::new(&CircuitBreakerConfig {
+            failure_threshold: Some(2),
+            probe_interval_sec: None,
+        });
+        let hook = hook();
+        assert!(!breakers.record_failure(&hook));
+        breakers.record_success(&hook.url);
+        assert!(!breakers.record_failure(&hook));
+        assert!(breakers.record_failure(&hook));
+        assert!(breakers.is_open(&hook.url));
+        // Successes reported by deliveries in flight don't close an open circuit
+        breakers.record_success(&hook.url);
+        assert!(breakers.is_open(&hook.url));
+    }
+
+    #[test]
+    fn it_resumes_paused_predicates_when_closing() {
+        let mut breakers = DeliveryCircuitBreakers::new(&CircuitBreakerConfig {
+            failure_threshold: Some(1),
+            probe_interval_sec: Some(1),
+        });
+        let hook = hook();
+        assert!(breakers.record_failure(&hook));
+        assert!(breakers.pause_predicate(&hook.url, predicate("a")));
+        assert!(!breakers.pause_predicate(&hook.url, predicate("a")));
+        assert!(breakers.pause_predicate(&hook.url, predicate("b")));
+        breakers.forget_predicate("b");
+
+        assert!(breakers.endpoints_due_for_probe(Instant::now()).is_empty());
+        let later = Instant::now() + Duration::from_secs(2);
+        assert_eq!(breakers.endpoints_due_for_probe(later).len(), 1);
+        assert!(breakers.endpoints_due_for_probe(later).is_empty());
+
+        let resumed = breakers.close(&hook.url);
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].uuid(), "a");
+        assert!(!breakers.is_open(&hook.url));
+    }
+}
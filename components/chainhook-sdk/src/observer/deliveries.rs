@@ -0,0 +1,162 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Number of deliveries kept for each predicate, the oldest ones being dropped first.
+pub const DELIVERY_HISTORY_SIZE: usize = 50;
+
+/// Outcome of the delivery of an occurrence to the `then_that` action of a predicate.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryRecord {
+    /// Unix timestamp, in seconds, at which the delivery completed or gave up.
+    pub delivered_at: u64,
+    pub delivery_id: String,
+    /// Url of `http_post` actions, or the file, bucket, topic, queue or table delivered to.
+    pub target: String,
+    pub success: bool,
+    /// Status of the response to the most recent attempt, for `http_post` actions that got one.
+    pub http_status: Option<u16>,
+    /// Time spent delivering the occurrence, retries included.
+    pub latency_ms: u64,
+    /// Size, in bytes, of the payload sent.
+    pub payload_size: usize,
+    pub error: Option<String>,
+}
+
+impl DeliveryRecord {
+    pub fn new(
+        delivery_id: &str,
+        target: String,
+        error: Option<String>,
+        http_status: Option<u16>,
+        latency_ms: u64,
+        payload_size: usize,
+    ) -> Self {
+        let delivered_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        DeliveryRecord {
+            delivered_at,
+            delivery_id: delivery_id.to_string(),
+            target,
+            success: error.is_none(),
+            http_status,
+            latency_ms,
+            payload_size,
+            error,
+        }
+    }
+}
+
+/// Durable storage of the deliveries recorded by a [DeliveryHistory], so that the outcomes of
+/// the deliveries of a predicate can be looked at from outside the observer.
+pub trait DeliveryRecordStore: Send + Sync {
+    fn log(&self, uuid: &str, record: &DeliveryRecord) -> Result<(), String>;
+    fn remove(&self, uuid: &str) -> Result<(), String>;
+}
+
+/// Registry of the most recent deliveries of each predicate, so that operators can find out
+/// why occurrences did not reach their destination. The history is kept in memory and starts
+/// over when chainhook restarts. Deliveries are also logged to the [DeliveryRecordStore] the
+/// history was created with, if any.
+#[derive(Clone, Default)]
+pub struct DeliveryHistory {
+    records: Arc<RwLock<HashMap<String, VecDeque<DeliveryRecord>>>>,
+    store: Option<Arc<dyn DeliveryRecordStore>>,
+}
+
+impl fmt::Debug for DeliveryHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeliveryHistory")
+            .field("records", &self.records)
+            .field("store", &self.store.is_some())
+            .finish()
+    }
+}
+
+impl DeliveryHistory {
+    pub fn new() -> Self {
+        DeliveryHistory::default()
+    }
+
+    pub fn with_store(store: Arc<dyn DeliveryRecordStore>) -> Self {
+        DeliveryHistory {
+            records: Arc::default(),
+            store: Some(store),
+        }
+    }
+
+    pub fn record(&self, uuid: &str, record: DeliveryRecord) {
+        if let Some(ref store) = self.store {
+            // the history is informative, failing to log a delivery does not fail it
+            let _ = store.log(uuid, &record);
+        }
+        let mut records = self.records.write().unwrap();
+        let history = records
+            .entry(uuid.to_string())
+            .or_insert_with(|| VecDeque::with_capacity(DELIVERY_HISTORY_SIZE));
+        if history.len() == DELIVERY_HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back(record);
+    }
+
+    /// Returns the deliveries of the predicate `uuid`, most recent first.
+    pub fn get(&self, uuid: &str) -> Vec<DeliveryRecord> {
+        self.records
+            .read()
+            .unwrap()
+            .get(uuid)
+            .map(|history| history.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drops the history of the predicate `uuid`, once it is deregistered.
+    pub fn forget(&self, uuid: &str) {
+        if let Some(ref store) = self.store {
+            let _ = store.remove(uuid);
+        }
+        self.records.write().unwrap().remove(uuid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeliveryHistory, DeliveryRecord, DELIVERY_HISTORY_SIZE};
+
+    #[test]
+    fn it_keeps_the_most_recent_deliveries() {
+        let history = DeliveryHistory::new();
+        for i in 0..(DELIVERY_HISTORY_SIZE + 2) {
+            let error = match i % 2 {
+                0 => None,
+                _ => Some("Trigger failed with status 500".to_string()),
+            };
+            let record = DeliveryRecord::new(
+                &format!("{i}"),
+                "http://localhost:3000".into(),
+                error,
+                Some(if i % 2 == 0 { 200 } else { 500 }),
+                10,
+                100,
+            );
+            history.record("1234", record);
+        }
+
+        let deliveries = history.get("1234");
+        assert_eq!(deliveries.len(), DELIVERY_HISTORY_SIZE);
+        assert_eq!(deliveries[0].delivery_id, "51");
+        assert!(!deliveries[0].success);
+        assert_eq!(deliveries[0].http_status, Some(500));
+        assert_eq!(deliveries[1].error, None);
+        assert_eq!(deliveries[DELIVERY_HISTORY_SIZE - 1].delivery_id, "2");
+        assert!(history.get("5678").is_empty());
+
+        history.forget("1234");
+        assert!(history.get("1234").is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::monitoring::PrometheusMonitoring;
+
+/// Lag, in blocks, between the highest block received and the highest block evaluated on a
+/// chain, above which the observer stops reporting itself as ready.
+pub const MAX_READY_CHAIN_TIP_LAG: u64 = 6;
+
+/// A dependency of the service embedding the observer, such as a database, whose connectivity
+/// is reported by the `/health` and `/ready` endpoints.
+pub trait DependencyHealthCheck: Send + Sync {
+    fn name(&self) -> String;
+    fn check(&self) -> Result<(), String>;
+}
+
+#[derive(Clone, Default)]
+pub struct DependencyHealthChecks(pub Vec<Arc<dyn DependencyHealthCheck>>);
+
+impl fmt::Debug for DependencyHealthChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|check| check.name()))
+            .finish()
+    }
+}
+
+pub struct HealthReport {
+    /// Whether every dependency is reachable.
+    pub healthy: bool,
+    /// Whether the observer is healthy and caught up with the tip of both chains.
+    pub ready: bool,
+    pub details: JsonValue,
+}
+
+fn chain_health(
+    highest_block_received: u64,
+    highest_block_evaluated: u64,
+    last_block_ingestion_at: u64,
+    active_predicates: u64,
+) -> (u64, JsonValue) {
+    let chain_tip_lag = highest_block_received.saturating_sub(highest_block_evaluated);
+    (
+        chain_tip_lag,
+        json!({
+            "chain_tip_lag": chain_tip_lag,
+            "last_received_block_height": highest_block_received,
+            "last_evaluated_block_height": highest_block_evaluated,
+            "last_block_ingestion_at": last_block_ingestion_at,
+            "active_predicates": active_predicates,
+        }),
+    )
+}
+
+/// Checks the dependencies and the chain tip lags of the observer.
+pub fn get_health_report(
+    prometheus_monitoring: &PrometheusMonitoring,
+    health_checks: &DependencyHealthChecks,
+) -> HealthReport {
+    let (bitcoin_lag, bitcoin) = chain_health(
+        prometheus_monitoring.btc_highest_block_received.get(),
+        prometheus_monitoring.btc_highest_block_evaluated.get(),
+        prometheus_monitoring.btc_last_block_ingestion_time.get(),
+        prometheus_monitoring.btc_registered_predicates.get(),
+    );
+    let (stacks_lag, stacks) = chain_health(
+        prometheus_monitoring.stx_highest_block_received.get(),
+        prometheus_monitoring.stx_highest_block_evaluated.get(),
+        prometheus_monitoring.stx_last_block_ingestion_time.get(),
+        prometheus_monitoring.stx_registered_predicates.get(),
+    );
+
+    let mut healthy = true;
+    let mut dependencies = serde_json::Map::new();
+    for health_check in health_checks.0.iter() {
+        let status = match health_check.check() {
+            Ok(()) => json!({ "connected": true }),
+            Err(e) => {
+                healthy = false;
+                json!({ "connected": false, "error": e })
+            }
+        };
+        dependencies.insert(health_check.name(), status);
+    }
+
+    let ready =
+        healthy && bitcoin_lag <= MAX_READY_CHAIN_TIP_LAG && stacks_lag <= MAX_READY_CHAIN_TIP_LAG;
+    HealthReport {
+        healthy,
+        ready,
+        details: json!({
+            "bitcoin": bitcoin,
+            "stacks": stacks,
+            "dependencies": dependencies,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{get_health_report, DependencyHealthCheck, DependencyHealthChecks};
+    use crate::monitoring::PrometheusMonitoring;
+
+    struct FailingDependency;
+
+    impl DependencyHealthCheck for FailingDependency {
+        fn name(&self) -> String {
+            "redis".into()
+        }
+
+        fn check(&self) -> Result<(), String> {
+            Err("connection refused".into())
+        }
+    }
+
+    #[test]
+    fn it_reports_lagging_and_unreachable_dependencies() {
+        let prometheus_monitoring = PrometheusMonitoring::new();
+        prometheus_monitoring.initialize(2, 1, Some(100));
+        let report = get_health_report(&prometheus_monitoring, &DependencyHealthChecks::default());
+        assert!(report.healthy);
+        assert!(report.ready);
+        assert_eq!(report.details["stacks"]["active_predicates"], 2);
+
+        prometheus_monitoring.stx_metrics_block_received(110);
+        let report = get_health_report(&prometheus_monitoring, &DependencyHealthChecks::default());
+        assert!(report.healthy);
+        assert!(!report.ready);
+        assert_eq!(report.details["stacks"]["chain_tip_lag"], 10);
+
+        let health_checks = DependencyHealthChecks(vec![Arc::new(FailingDependency)]);
+        let report = get_health_report(&prometheus_monitoring, &health_checks);
+        assert!(!report.healthy);
+        assert_eq!(
+            report.details["dependencies"]["redis"]["error"],
+            "connection refused"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hiro_system_kit::slog;
+
+use super::PredicatesConfig;
+use crate::chainhooks::types::{ChainhookStore, HttpHook};
+use crate::utils::{build_http_post_request, send_request, Context};
+
+/// Header set on the heartbeats posted to the `http_post` actions of a predicate.
+pub const HEARTBEAT_HEADER: &str = "X-Chainhook-Heartbeat";
+
+struct PredicateHeartbeat {
+    chain: &'static str,
+    hooks: Vec<HttpHook>,
+    interval: Duration,
+    last_activity: Instant,
+    last_evaluated_block_height: Option<u64>,
+}
+
+/// Posts a heartbeat to the endpoints of the predicates with a `heartbeat_interval_minutes`,
+/// including the `http_post` actions nested in `multiple` and `route` actions, once no
+/// occurrence was delivered to them for this interval, so that consumers can tell a predicate
+/// without matches from a service that is down. Predicates are keyed by uuid.
+#[derive(Default)]
+pub struct PredicateHeartbeats {
+    predicates: HashMap<String, PredicateHeartbeat>,
+}
+
+impl PredicateHeartbeats {
+    /// Tracks the enabled predicates of the store expecting heartbeats, from the first time they
+    /// are seen, and forgets the other ones.
+    pub fn sync(&mut self, chainhook_store: &ChainhookStore, now: Instant) {
+        let mut expected = HashMap::new();
+        for predicate in chainhook_store
+            .bitcoin_chainhooks
+            .iter()
+            .filter(|p| p.enabled && p.expired_at.is_none())
+        {
+            let Some(minutes) = predicate.heartbeat_interval_minutes else {
+                continue;
+            };
+            let hooks = predicate.action.http_hooks();
+            if !hooks.is_empty() {
+                expected.insert(predicate.uuid.clone(), ("bitcoin", hooks, minutes));
+            }
+        }
+        for predicate in chainhook_store
+            .stacks_chainhooks
+            .iter()
+            .filter(|p| p.enabled && p.expired_at.is_none())
+        {
+            let Some(minutes) = predicate.heartbeat_interval_minutes else {
+                continue;
+            };
+            let hooks = predicate.action.http_hooks();
+            if !hooks.is_empty() {
+                expected.insert(predicate.uuid.clone(), ("stacks", hooks, minutes));
+            }
+        }
+
+        self.predicates
+            .retain(|uuid, _| expected.contains_key(uuid));
+        for (uuid, (chain, hooks, minutes)) in expected.into_iter() {
+            let hooks: Vec<HttpHook> = hooks.into_iter().cloned().collect();
+            let interval = Duration::from_secs(minutes.max(1) * 60);
+            let heartbeat = self
+                .predicates
+                .entry(uuid)
+                .or_insert_with(|| PredicateHeartbeat {
+                    chain,
+                    hooks: hooks.clone(),
+                    interval,
+                    last_activity: now,
+                    last_evaluated_block_height: None,
+                });
+            // updated predicates keep their timer
+            heartbeat.hooks = hooks;
+            heartbeat.interval = interval;
+        }
+    }
+
+    pub fn record_evaluation(&mut self, uuid: &str, block_height: u64) {
+        if let Some(heartbeat) = self.predicates.get_mut(uuid) {
+            heartbeat.last_evaluated_block_height = Some(
+                heartbeat
+                    .last_evaluated_block_height
+                    .map_or(block_height, |height| height.max(block_height)),
+            );
+        }
+    }
+
+    /// Records an occurrence delivered to the endpoint of a predicate, delaying its next
+    /// heartbeat by a full interval.
+    pub fn record_delivery(&mut self, uuid: &str, now: Instant) {
+        if let Some(heartbeat) = self.predicates.get_mut(uuid) {
+            heartbeat.last_activity = now;
+        }
+    }
+
+    /// Returns the time left until the next heartbeat is due, if any predicate expects one.
+    pub fn next_due_in(&self, now: Instant) -> Option<Duration> {
+        self.predicates
+            .values()
+            .map(|h| (h.last_activity + h.interval).saturating_duration_since(now))
+            .min()
+    }
+
+    /// Returns the heartbeats due, as the uuid of their predicate, one of its hooks and the body
+    /// to post to it, then restarts their timer.
+    fn due_heartbeats(&mut self, now: Instant, timestamp: u64) -> Vec<(String, HttpHook, String)> {
+        let mut due = vec![];
+        for (uuid, heartbeat) in self.predicates.iter_mut() {
+            if now.duration_since(heartbeat.last_activity) < heartbeat.interval {
+                continue;
+            }
+            heartbeat.last_activity = now;
+            let body = json!({
+                "chainhook": {
+                    "uuid": uuid,
+                },
+                "heartbeat": {
+                    "chain": heartbeat.chain,
+                    "last_evaluated_block_height": heartbeat.last_evaluated_block_height,
+                    "timestamp": timestamp,
+                },
+            });
+            let body = body.to_string();
+            for hook in heartbeat.hooks.iter() {
+                due.push((uuid.clone(), hook.clone(), body.clone()));
+            }
+        }
+        due
+    }
+
+    /// Posts the heartbeats due. A failing endpoint only delays the next heartbeat by an
+    /// interval: heartbeats are not retried.
+    pub async fn send_due_heartbeats(
+        &mut self,
+        predicates_config: &PredicatesConfig,
+        ctx: &Context,
+    ) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        for (uuid, hook, body) in self.due_heartbeats(Instant::now(), timestamp) {
+            let delivery_id = format!("{}:heartbeat:{}", uuid, timestamp);
+            let res = build_http_post_request(
+                &hook,
+                body.into_bytes(),
+                &delivery_id,
+                "application/json",
+                predicates_config,
+            );
+            let res = match res {
+                Ok(request) => {
+                    send_request(request.header(HEARTBEAT_HEADER, "true"), 1, 0, ctx).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Unable to send heartbeat of predicate {}: {}",
+                        uuid,
+                        e
+                    )
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::PredicateHeartbeats;
+    use crate::chainhooks::bitcoin::{BitcoinChainhookInstance, BitcoinPredicateType};
+    use crate::chainhooks::types::{ChainhookStore, DeliveryOptions, HookAction, HttpHook};
+    use chainhook_types::BitcoinNetwork;
+
+    fn predicate(uuid: &str, heartbeat_interval_minutes: Option<u64>) -> BitcoinChainhookInstance {
+        BitcoinChainhookInstance {
+            uuid: uuid.into(),
+            owner_uuid: None,
+            labels: None,
+            metadata: None,
+            namespace: None,
+            name: uuid.into(),
+            network: BitcoinNetwork::Regtest,
+            version: 1,
+            blocks: None,
+            start_block: None,
+            end_block: None,
+            expire_after_occurrence: None,
+            predicate: BitcoinPredicateType::Block,
+            action: HookAction::HttpPost(HttpHook {
+                url: "http://localhost:20446".into(),
+                authorization_header: "Bearer 1234".into(),
+                signing_secret: None,
+                tls: None,
+                compression: None,
+                require_ack: None,
+                delivery: DeliveryOptions::default(),
+            }),
+            include_proof: false,
+            include_inputs: false,
+            include_outputs: false,
+            include_witness: false,
+            include_fields: None,
+            transform: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes,
+            rollback_action: None,
+            payload_envelope: None,
+            enabled: true,
+            expired_at: None,
+        }
+    }
+
+    #[test]
+    fn it_sends_heartbeats_to_idle_predicates() {
+        let mut store = ChainhookStore::new();
+        store.bitcoin_chainhooks.push(predicate("a", Some(5)));
+        store.bitcoin_chainhooks.push(predicate("b", Some(10)));
+        store.bitcoin_chainhooks.push(predicate("c", None));
+        let now = Instant::now();
+        let mut heartbeats = PredicateHeartbeats::default();
+        heartbeats.sync(&store, now);
+        assert_eq!(heartbeats.next_due_in(now), Some(Duration::from_secs(300)));
+
+        heartbeats.record_evaluation("a", 100);
+        let later = now + Duration::from_secs(300);
+        let due = heartbeats.due_heartbeats(later, 1700000000);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "a");
+        let body: serde_json::Value = serde_json::from_str(&due[0].2).unwrap();
+        assert_eq!(body["heartbeat"]["last_evaluated_block_height"], 100);
+        assert_eq!(body["heartbeat"]["chain"], "bitcoin");
+        assert!(heartbeats.due_heartbeats(later, 1700000000).is_empty());
+
+        // deliveries push the next heartbeat back
+        heartbeats.record_delivery("b", now + Duration::from_secs(500));
+        let due = heartbeats.due_heartbeats(now + Duration::from_secs(600), 1700000300);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "a");
+
+        store.bitcoin_chainhooks.retain(|p| p.uuid != "a");
+        heartbeats.sync(&store, now);
+        assert!(heartbeats
+            .due_heartbeats(now + Duration::from_secs(1200), 1700000900)
+            .iter()
+            .all(|(uuid, _, _)| uuid == "b"));
+    }
+
+    #[test]
+    fn it_sends_heartbeats_to_nested_http_post_actions() {
+        let mut store = ChainhookStore::new();
+        let mut nested = predicate("a", Some(5));
+        nested.action = HookAction::Multiple(vec![HookAction::Noop, nested.action.clone()]);
+        store.bitcoin_chainhooks.push(nested);
+        let mut without_http_post = predicate("b", Some(5));
+        without_http_post.action = HookAction::Multiple(vec![HookAction::Noop]);
+        store.bitcoin_chainhooks.push(without_http_post);
+        let now = Instant::now();
+        let mut heartbeats = PredicateHeartbeats::default();
+        heartbeats.sync(&store, now);
+
+        let due = heartbeats.due_heartbeats(now + Duration::from_secs(300), 1700000000);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "a");
+        assert_eq!(due[0].1.url, "http://localhost:20446");
+    }
+}
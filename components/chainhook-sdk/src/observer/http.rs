@@ -1,22 +1,21 @@
-use crate::indexer::bitcoin::{
-    build_http_client, download_and_parse_block_with_retry, NewBitcoinBlock,
-};
-use crate::indexer::{self, Indexer};
-use crate::monitoring::PrometheusMonitoring;
-use crate::utils::Context;
-use crate::{try_error, try_info};
+use crate::indexer::bitcoin::NewBitcoinBlock;
+use crate::try_error;
+use crate::utils::rocket_tls_config;
+use chainhook_types::ServerTlsConfig;
 use hiro_system_kit::slog;
+use rocket::config::{self, Config, LogLevel};
+use rocket::data::{Limits, ToByteUnit};
 use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
 use rocket::response::status::Custom;
 use rocket::serde::json::{json, Json, Value as JsonValue};
 use rocket::State;
-use std::sync::mpsc::Sender;
-use std::sync::{Arc, Mutex, RwLock};
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr};
 
-use super::{
-    BitcoinConfig, BitcoinRPCRequest, MempoolAdmissionData, ObserverCommand,
-    StacksChainMempoolEvent,
-};
+use super::health::get_health_report;
+use super::ingestion::{IngestionShutdown, IngestionState};
+use super::BitcoinRPCRequest;
 
 fn success_response() -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
     Ok(Json(json!({
@@ -27,9 +26,9 @@ fn success_response() -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
 
 fn error_response(
     message: String,
-    ctx: &State<Context>,
+    state: &State<IngestionState>,
 ) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
-    try_error!(ctx, "{message}");
+    try_error!(state.ctx, "{message}");
     Err(Custom(
         Status::InternalServerError,
         Json(json!({
@@ -39,227 +38,207 @@ fn error_response(
     ))
 }
 
+fn ingestion_response(
+    res: Result<(), String>,
+    state: &State<IngestionState>,
+) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
+    match res {
+        Ok(()) => success_response(),
+        Err(message) => error_response(message, state),
+    }
+}
+
+/// Launches the Stacks events ingestion server on Rocket.
+pub async fn start_ingestion_server(
+    state: IngestionState,
+    ingestion_port: u16,
+    tls: Option<ServerTlsConfig>,
+    bitcoin_rpc_proxy_enabled: bool,
+    display_logs: bool,
+) -> Result<IngestionShutdown, Box<dyn Error>> {
+    let log_level = if display_logs {
+        if cfg!(feature = "cli") {
+            LogLevel::Critical
+        } else {
+            LogLevel::Debug
+        }
+    } else {
+        LogLevel::Off
+    };
+
+    let limits = Limits::default()
+        .limit("json", 500.megabytes())
+        .limit("string", 500.megabytes());
+    let mut shutdown_config = config::Shutdown::default();
+    shutdown_config.ctrlc = false;
+    shutdown_config.grace = 0;
+    shutdown_config.mercy = 0;
+
+    let ingestion_config = Config {
+        port: ingestion_port,
+        workers: 1,
+        address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        keep_alive: 5,
+        temp_dir: std::env::temp_dir().into(),
+        log_level,
+        cli_colors: false,
+        limits,
+        shutdown: shutdown_config,
+        tls: tls.as_ref().map(rocket_tls_config),
+        ..Config::default()
+    };
+
+    let mut routes = rocket::routes![
+        handle_ping,
+        handle_health,
+        handle_ready,
+        handle_new_bitcoin_block,
+        handle_new_stacks_block,
+        handle_new_microblocks,
+        handle_new_mempool_tx,
+        handle_drop_mempool_tx,
+        handle_new_attachement,
+        handle_mined_block,
+        handle_mined_microblock,
+    ];
+
+    if bitcoin_rpc_proxy_enabled {
+        routes.append(&mut routes![handle_bitcoin_rpc_call]);
+        routes.append(&mut routes![handle_bitcoin_wallet_rpc_call]);
+    }
+
+    let ignite = rocket::custom(ingestion_config)
+        .manage(state)
+        .mount("/", routes)
+        .ignite()
+        .await?;
+    let ingestion_shutdown = ignite.shutdown();
+
+    let _ = std::thread::spawn(move || {
+        let _ = hiro_system_kit::nestable_block_on(ignite.launch());
+    });
+    Ok(ingestion_shutdown.into())
+}
+
+/// Request guard rejecting the events not carrying the [IngestionState::auth_token], when one
+/// is set.
+pub struct IngestionAuth;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IngestionAuth {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let authorized = match request.rocket().state::<IngestionState>() {
+            Some(state) => state.is_authorized(request.headers().get_one("Authorization")),
+            None => true,
+        };
+        if authorized {
+            request::Outcome::Success(IngestionAuth)
+        } else {
+            request::Outcome::Error((Status::Unauthorized, ()))
+        }
+    }
+}
+
 #[rocket::get("/ping", format = "application/json")]
-pub fn handle_ping(
-    ctx: &State<Context>,
-    prometheus_monitoring: &State<PrometheusMonitoring>,
-) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "GET /ping"));
+pub fn handle_ping(state: &State<IngestionState>) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /ping"));
 
     Json(json!({
         "status": 200,
-        "result": prometheus_monitoring.get_metrics(),
+        "result": state.prometheus_monitoring.get_metrics(),
     }))
 }
 
-#[post("/new_burn_block", format = "json", data = "<bitcoin_block>")]
-pub async fn handle_new_bitcoin_block(
-    indexer_rw_lock: &State<Arc<RwLock<Indexer>>>,
-    bitcoin_config: &State<BitcoinConfig>,
-    bitcoin_block: Json<NewBitcoinBlock>,
-    background_job_tx: &State<Arc<Mutex<Sender<ObserverCommand>>>>,
-    prometheus_monitoring: &State<PrometheusMonitoring>,
-    ctx: &State<Context>,
-) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
-    if bitcoin_config
-        .bitcoin_block_signaling
-        .should_ignore_bitcoin_block_signaling_through_stacks()
-    {
-        return success_response();
-    }
+#[rocket::get("/health", format = "application/json")]
+pub fn handle_health(state: &State<IngestionState>) -> Custom<Json<JsonValue>> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /health"));
 
-    try_info!(ctx, "POST /new_burn_block");
-    // Standardize the structure of the block, and identify the
-    // kind of update that this new block would imply, taking
-    // into account the last 7 blocks.
+    let report = get_health_report(&state.prometheus_monitoring, &state.health_checks);
+    health_response(report.healthy, report.details)
+}
 
-    let http_client = build_http_client();
-    let block_hash = bitcoin_block.burn_block_hash.strip_prefix("0x").unwrap();
-    let block =
-        match download_and_parse_block_with_retry(&http_client, block_hash, bitcoin_config, ctx)
-            .await
-        {
-            Ok(block) => block,
-            Err(e) => {
-                return error_response(format!("unable to download_and_parse_block: {e}"), ctx)
-            }
-        };
+#[rocket::get("/ready", format = "application/json")]
+pub fn handle_ready(state: &State<IngestionState>) -> Custom<Json<JsonValue>> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /ready"));
 
-    let header = block.get_block_header();
-    let block_height = header.block_identifier.index;
-    prometheus_monitoring.btc_metrics_block_received(block_height);
-    if let Err(e) = background_job_tx.lock().map(|tx| {
-        tx.send(ObserverCommand::ProcessBitcoinBlock(block))
-            .map_err(|e| format!("Unable to send stacks chain event: {}", e))
-    }) {
-        return error_response(format!("unable to acquire background_job_tx: {e}"), ctx);
-    }
+    let report = get_health_report(&state.prometheus_monitoring, &state.health_checks);
+    health_response(report.ready, report.details)
+}
 
-    let chain_update = match indexer_rw_lock.inner().write() {
-        Ok(mut indexer) => indexer.handle_bitcoin_header(header, ctx),
-        Err(e) => {
-            return error_response(format!("Unable to acquire indexer_rw_lock: {e}"), ctx);
-        }
+fn health_response(ok: bool, details: JsonValue) -> Custom<Json<JsonValue>> {
+    let status = if ok {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
     };
+    Custom(
+        status,
+        Json(json!({
+            "status": status.code,
+            "result": details,
+        })),
+    )
+}
 
-    match chain_update {
-        Ok(Some(chain_event)) => {
-            prometheus_monitoring.btc_metrics_block_appended(block_height);
-            if let Err(e) = background_job_tx.lock().map(|tx| {
-                tx.send(ObserverCommand::PropagateBitcoinChainEvent(chain_event))
-                    .map_err(|e| format!("Unable to send stacks chain event: {}", e))
-            }) {
-                return error_response(format!("unable to acquire background_job_tx: {e}"), ctx);
-            }
-        }
-        Ok(None) => {
-            try_info!(ctx, "No chain event was generated");
-        }
-        Err(e) => {
-            return error_response(format!("Unable to handle bitcoin block: {e}"), ctx);
-        }
-    }
-
-    success_response()
+#[post("/new_burn_block", format = "json", data = "<bitcoin_block>")]
+pub async fn handle_new_bitcoin_block(
+    _auth: IngestionAuth,
+    bitcoin_block: Json<NewBitcoinBlock>,
+    state: &State<IngestionState>,
+) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
+    ingestion_response(state.ingest_bitcoin_block(&bitcoin_block).await, state)
 }
 
-#[post("/new_block", format = "application/json", data = "<marshalled_block>")]
+#[post("/new_block", format = "application/json", data = "<serialized_block>")]
 pub fn handle_new_stacks_block(
-    indexer_rw_lock: &State<Arc<RwLock<Indexer>>>,
-    marshalled_block: Json<JsonValue>,
-    background_job_tx: &State<Arc<Mutex<Sender<ObserverCommand>>>>,
-    prometheus_monitoring: &State<PrometheusMonitoring>,
-    ctx: &State<Context>,
+    _auth: IngestionAuth,
+    serialized_block: String,
+    state: &State<IngestionState>,
 ) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
-    try_info!(ctx, "POST /new_block");
-    // Standardize the structure of the block, and identify the
-    // kind of update that this new block would imply, taking
-    // into account the last 7 blocks.
-    // TODO(lgalabru): use _pox_config
-    let (_pox_config, chain_event, new_tip) = match indexer_rw_lock.inner().write() {
-        Ok(mut indexer) => {
-            let pox_config = indexer.get_pox_config();
-            let block = match indexer
-                .standardize_stacks_marshalled_block(marshalled_block.into_inner(), ctx)
-            {
-                Ok(block) => block,
-                Err(e) => {
-                    return error_response(format!("Unable to standardize stacks block {e}"), ctx);
-                }
-            };
-            let new_tip = block.block_identifier.index;
-            prometheus_monitoring.stx_metrics_block_received(new_tip);
-            let chain_event = indexer.process_stacks_block(block, ctx);
-            (pox_config, chain_event, new_tip)
-        }
-        Err(e) => {
-            return error_response(format!("Unable to acquire indexer_rw_lock: {e}"), ctx);
-        }
-    };
-
-    match chain_event {
-        Ok(Some(chain_event)) => {
-            prometheus_monitoring.stx_metrics_block_appeneded(new_tip);
-            if let Err(e) = background_job_tx.lock().map(|tx| {
-                tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event))
-                    .map_err(|e| format!("Unable to send stacks chain event: {}", e))
-            }) {
-                return error_response(format!("unable to acquire background_job_tx: {e}"), ctx);
-            }
-        }
-        Ok(None) => {
-            try_info!(ctx, "No chain event was generated");
-        }
-        Err(e) => {
-            return error_response(format!("Chain event error: {e}"), ctx);
-        }
-    }
-
-    success_response()
+    ingestion_response(state.ingest_stacks_block(&serialized_block), state)
 }
 
 #[post(
     "/new_microblocks",
     format = "application/json",
-    data = "<marshalled_microblock>"
+    data = "<serialized_microblock_trail>"
 )]
 pub fn handle_new_microblocks(
-    indexer_rw_lock: &State<Arc<RwLock<Indexer>>>,
-    marshalled_microblock: Json<JsonValue>,
-    background_job_tx: &State<Arc<Mutex<Sender<ObserverCommand>>>>,
-    ctx: &State<Context>,
+    _auth: IngestionAuth,
+    serialized_microblock_trail: String,
+    state: &State<IngestionState>,
 ) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
-    try_info!(ctx, "POST /new_microblocks");
-    // Standardize the structure of the microblock, and identify the
-    // kind of update that this new microblock would imply
-    let chain_event = match indexer_rw_lock.inner().write() {
-        Ok(mut indexer) => indexer
-            .handle_stacks_marshalled_microblock_trail(marshalled_microblock.into_inner(), ctx),
-        Err(e) => {
-            return error_response(format!("Unable to acquire background_job_tx: {e}"), ctx);
-        }
-    };
-
-    match chain_event {
-        Ok(Some(chain_event)) => {
-            if let Err(e) = background_job_tx.lock().map(|tx| {
-                tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event))
-                    .map_err(|e| format!("Unable to send stacks chain event: {}", e))
-            }) {
-                return error_response(format!("unable to acquire background_job_tx: {e}"), ctx);
-            }
-        }
-        Ok(None) => {
-            try_info!(ctx, "No chain event was generated");
-        }
-        Err(e) => {
-            return error_response(format!("Chain event error: {e}"), ctx);
-        }
-    }
-
-    success_response()
+    ingestion_response(
+        state.ingest_microblocks(&serialized_microblock_trail),
+        state,
+    )
 }
 
 #[post("/new_mempool_tx", format = "application/json", data = "<raw_txs>")]
 pub fn handle_new_mempool_tx(
+    _auth: IngestionAuth,
     raw_txs: Json<Vec<String>>,
-    background_job_tx: &State<Arc<Mutex<Sender<ObserverCommand>>>>,
-    ctx: &State<Context>,
+    state: &State<IngestionState>,
 ) -> Result<Json<JsonValue>, Custom<Json<JsonValue>>> {
-    try_info!(ctx, "POST /new_mempool_tx");
-    let transactions = match raw_txs
-        .iter()
-        .map(|tx_data| {
-            indexer::stacks::get_tx_description(tx_data, &vec![])
-                .map(|(tx_description, ..)| MempoolAdmissionData {
-                    tx_data: tx_data.clone(),
-                    tx_description,
-                })
-                .map_err(|e| e)
-        })
-        .collect::<Result<Vec<MempoolAdmissionData>, _>>()
-    {
-        Ok(transactions) => transactions,
-        Err(e) => {
-            return error_response(format!("Failed to parse mempool transactions: {e}"), ctx);
-        }
-    };
-
-    if let Err(e) = background_job_tx.lock().map(|tx| {
-        tx.send(ObserverCommand::PropagateStacksMempoolEvent(
-            StacksChainMempoolEvent::TransactionsAdmitted(transactions),
-        ))
-            .map_err(|e| format!("Unable to send stacks chain event: {}", e))
-    }) {
-        return error_response(format!("unable to acquire background_job_tx: {e}"), ctx);
-    }
-
-    success_response()
+    ingestion_response(state.ingest_mempool_txs(&raw_txs), state)
 }
 
 #[post("/drop_mempool_tx", format = "application/json")]
-pub fn handle_drop_mempool_tx(ctx: &State<Context>) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /drop_mempool_tx"));
+pub fn handle_drop_mempool_tx(
+    _auth: IngestionAuth,
+    state: &State<IngestionState>,
+) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /drop_mempool_tx"));
     // TODO(lgalabru): use propagate mempool events
     Json(json!({
         "status": 200,
@@ -268,8 +247,13 @@ pub fn handle_drop_mempool_tx(ctx: &State<Context>) -> Json<JsonValue> {
 }
 
 #[post("/attachments/new", format = "application/json")]
-pub fn handle_new_attachement(ctx: &State<Context>) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /attachments/new"));
+pub fn handle_new_attachement(
+    _auth: IngestionAuth,
+    state: &State<IngestionState>,
+) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /attachments/new"));
     Json(json!({
         "status": 200,
         "result": "Ok",
@@ -277,8 +261,14 @@ pub fn handle_new_attachement(ctx: &State<Context>) -> Json<JsonValue> {
 }
 
 #[post("/mined_block", format = "application/json", data = "<payload>")]
-pub fn handle_mined_block(payload: Json<JsonValue>, ctx: &State<Context>) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /mined_block {:?}", payload));
+pub fn handle_mined_block(
+    _auth: IngestionAuth,
+    payload: Json<JsonValue>,
+    state: &State<IngestionState>,
+) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /mined_block {:?}", payload));
     Json(json!({
         "status": 200,
         "result": "Ok",
@@ -286,8 +276,14 @@ pub fn handle_mined_block(payload: Json<JsonValue>, ctx: &State<Context>) -> Jso
 }
 
 #[post("/mined_microblock", format = "application/json", data = "<payload>")]
-pub fn handle_mined_microblock(payload: Json<JsonValue>, ctx: &State<Context>) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /mined_microblock {:?}", payload));
+pub fn handle_mined_microblock(
+    _auth: IngestionAuth,
+    payload: Json<JsonValue>,
+    state: &State<IngestionState>,
+) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /mined_microblock {:?}", payload));
     Json(json!({
         "status": 200,
         "result": "Ok",
@@ -296,101 +292,28 @@ pub fn handle_mined_microblock(payload: Json<JsonValue>, ctx: &State<Context>) -
 
 #[post("/wallet", format = "application/json", data = "<bitcoin_rpc_call>")]
 pub async fn handle_bitcoin_wallet_rpc_call(
-    bitcoin_config: &State<BitcoinConfig>,
     bitcoin_rpc_call: Json<BitcoinRPCRequest>,
-    ctx: &State<Context>,
+    state: &State<IngestionState>,
 ) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /wallet"));
-
-    use base64::engine::general_purpose::STANDARD as BASE64;
-    use base64::engine::Engine as _;
-    use reqwest::Client;
-
-    let bitcoin_rpc_call = bitcoin_rpc_call.into_inner().clone();
-
-    let body = rocket::serde::json::serde_json::to_vec(&bitcoin_rpc_call).unwrap_or_default();
-
-    let token = BASE64.encode(format!(
-        "{}:{}",
-        bitcoin_config.username, bitcoin_config.password
-    ));
-
-    let url = bitcoin_config.rpc_url.to_string();
-    let client = Client::new();
-    let builder = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Basic {}", token))
-        .timeout(std::time::Duration::from_secs(5));
-
-    match builder.body(body).send().await {
-        Ok(res) => Json(res.json().await.unwrap()),
-        Err(_) => Json(json!({
-            "status": 500
-        })),
-    }
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /wallet"));
+    Json(
+        state
+            .forward_bitcoin_rpc_call(bitcoin_rpc_call.into_inner(), true)
+            .await,
+    )
 }
 
 #[post("/", format = "application/json", data = "<bitcoin_rpc_call>")]
 pub async fn handle_bitcoin_rpc_call(
-    bitcoin_config: &State<BitcoinConfig>,
     bitcoin_rpc_call: Json<BitcoinRPCRequest>,
-    background_job_tx: &State<Arc<Mutex<Sender<ObserverCommand>>>>,
-    ctx: &State<Context>,
+    state: &State<IngestionState>,
 ) -> Json<JsonValue> {
-    ctx.try_log(|logger| slog::debug!(logger, "POST /"));
-
-    use base64::engine::general_purpose::STANDARD as BASE64;
-    use base64::engine::Engine as _;
-    use reqwest::Client;
-
-    let bitcoin_rpc_call = bitcoin_rpc_call.into_inner().clone();
-    let method = bitcoin_rpc_call.method.clone();
-
-    let body = rocket::serde::json::serde_json::to_vec(&bitcoin_rpc_call).unwrap_or_default();
-
-    let token = BASE64.encode(format!(
-        "{}:{}",
-        bitcoin_config.username, bitcoin_config.password
-    ));
-
-    ctx.try_log(|logger| {
-        slog::debug!(
-            logger,
-            "Forwarding {} request to {}",
-            method,
-            bitcoin_config.rpc_url
-        )
-    });
-
-    let url = if method == "listunspent" {
-        format!("{}/wallet/", bitcoin_config.rpc_url)
-    } else {
-        bitcoin_config.rpc_url.to_string()
-    };
-
-    let client = Client::new();
-    let builder = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Basic {}", token))
-        .timeout(std::time::Duration::from_secs(5));
-
-    if method == "sendrawtransaction" {
-        let background_job_tx = background_job_tx.inner();
-        if let Ok(tx) = background_job_tx.lock() {
-            let _ = tx.send(ObserverCommand::NotifyBitcoinTransactionProxied);
-        };
-    }
-
-    match builder.body(body).send().await {
-        Ok(res) => {
-            let payload = res.json().await.unwrap();
-            ctx.try_log(|logger| slog::debug!(logger, "Responding with response {:?}", payload));
-            Json(payload)
-        }
-        Err(_) => Json(json!({
-            "status": 500
-        })),
-    }
+    state.ctx.try_log(|logger| slog::debug!(logger, "POST /"));
+    Json(
+        state
+            .forward_bitcoin_rpc_call(bitcoin_rpc_call.into_inner(), false)
+            .await,
+    )
 }
@@ -0,0 +1,264 @@
+use std::error::Error;
+use std::net::{SocketAddr, TcpListener};
+
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use hiro_system_kit::slog;
+use serde_json::{json, Value as JsonValue};
+
+use super::health::get_health_report;
+use super::ingestion::{IngestionShutdown, IngestionState};
+use super::BitcoinRPCRequest;
+use crate::indexer::bitcoin::NewBitcoinBlock;
+use crate::try_error;
+use crate::utils::rustls_server_config;
+use chainhook_types::ServerTlsConfig;
+
+const MAX_PAYLOAD_SIZE: usize = 500 * 1024 * 1024;
+
+/// Launches the Stacks events ingestion server on axum, serving the same routes as the Rocket
+/// one, over HTTPS when `tls` is set. Requests are only logged by the handlers, `display_logs`
+/// is ignored.
+pub async fn start_ingestion_server(
+    state: IngestionState,
+    ingestion_port: u16,
+    tls: Option<ServerTlsConfig>,
+    bitcoin_rpc_proxy_enabled: bool,
+    _display_logs: bool,
+) -> Result<IngestionShutdown, Box<dyn Error>> {
+    let tls_config = match tls {
+        Some(ref tls) => Some(RustlsConfig::from_config(rustls_server_config(tls)?)),
+        None => None,
+    };
+    let ingestion_routes = Router::new()
+        .route("/new_burn_block", post(handle_new_bitcoin_block))
+        .route("/new_block", post(handle_new_stacks_block))
+        .route("/new_microblocks", post(handle_new_microblocks))
+        .route("/new_mempool_tx", post(handle_new_mempool_tx))
+        .route("/drop_mempool_tx", post(handle_drop_mempool_tx))
+        .route("/attachments/new", post(handle_new_attachement))
+        .route("/mined_block", post(handle_mined_block))
+        .route("/mined_microblock", post(handle_mined_microblock))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_ingestion_auth,
+        ));
+
+    let mut router = Router::new()
+        .route("/ping", get(handle_ping))
+        .route("/health", get(handle_health))
+        .route("/ready", get(handle_ready))
+        .merge(ingestion_routes);
+    if bitcoin_rpc_proxy_enabled {
+        router = router
+            .route("/", post(handle_bitcoin_rpc_call))
+            .route("/wallet", post(handle_bitcoin_wallet_rpc_call));
+    }
+    let app = router
+        .layer(DefaultBodyLimit::max(MAX_PAYLOAD_SIZE))
+        .with_state(state);
+
+    // Binding before spawning the server surfaces the port being unavailable to the caller.
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], ingestion_port)))?;
+    listener.set_nonblocking(true)?;
+    let handle = Handle::new();
+    let server_handle = handle.clone();
+
+    let _ = std::thread::spawn(move || {
+        let _ = hiro_system_kit::nestable_block_on(async move {
+            match tls_config {
+                Some(tls_config) => {
+                    axum_server::from_tcp_rustls(listener, tls_config)
+                        .handle(server_handle)
+                        .serve(app.into_make_service())
+                        .await
+                }
+                None => {
+                    axum_server::from_tcp(listener)
+                        .handle(server_handle)
+                        .serve(app.into_make_service())
+                        .await
+                }
+            }
+        });
+    });
+    Ok(IngestionShutdown::new(move || {
+        handle.graceful_shutdown(None)
+    }))
+}
+
+async fn require_ingestion_auth<B>(
+    State(state): State<IngestionState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !state.is_authorized(authorization) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+fn success_response() -> Response {
+    Json(json!({
+        "status": 200,
+        "result": "Ok",
+    }))
+    .into_response()
+}
+
+fn ingestion_response(res: Result<(), String>, state: &IngestionState) -> Response {
+    match res {
+        Ok(()) => success_response(),
+        Err(message) => {
+            try_error!(state.ctx, "{message}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "status": 500,
+                    "result": message,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn health_response(ok: bool, details: JsonValue) -> Response {
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": status.as_u16(),
+            "result": details,
+        })),
+    )
+        .into_response()
+}
+
+async fn handle_ping(State(state): State<IngestionState>) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /ping"));
+    Json(json!({
+        "status": 200,
+        "result": state.prometheus_monitoring.get_metrics(),
+    }))
+    .into_response()
+}
+
+async fn handle_health(State(state): State<IngestionState>) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /health"));
+    let report = get_health_report(&state.prometheus_monitoring, &state.health_checks);
+    health_response(report.healthy, report.details)
+}
+
+async fn handle_ready(State(state): State<IngestionState>) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "GET /ready"));
+    let report = get_health_report(&state.prometheus_monitoring, &state.health_checks);
+    health_response(report.ready, report.details)
+}
+
+async fn handle_new_bitcoin_block(
+    State(state): State<IngestionState>,
+    Json(bitcoin_block): Json<NewBitcoinBlock>,
+) -> Response {
+    ingestion_response(state.ingest_bitcoin_block(&bitcoin_block).await, &state)
+}
+
+async fn handle_new_stacks_block(
+    State(state): State<IngestionState>,
+    serialized_block: String,
+) -> Response {
+    ingestion_response(state.ingest_stacks_block(&serialized_block), &state)
+}
+
+async fn handle_new_microblocks(
+    State(state): State<IngestionState>,
+    serialized_microblock_trail: String,
+) -> Response {
+    ingestion_response(
+        state.ingest_microblocks(&serialized_microblock_trail),
+        &state,
+    )
+}
+
+async fn handle_new_mempool_tx(
+    State(state): State<IngestionState>,
+    Json(raw_txs): Json<Vec<String>>,
+) -> Response {
+    ingestion_response(state.ingest_mempool_txs(&raw_txs), &state)
+}
+
+async fn handle_drop_mempool_tx(State(state): State<IngestionState>) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /drop_mempool_tx"));
+    success_response()
+}
+
+async fn handle_new_attachement(State(state): State<IngestionState>) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /attachments/new"));
+    success_response()
+}
+
+async fn handle_mined_block(
+    State(state): State<IngestionState>,
+    Json(payload): Json<JsonValue>,
+) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /mined_block {:?}", payload));
+    success_response()
+}
+
+async fn handle_mined_microblock(
+    State(state): State<IngestionState>,
+    Json(payload): Json<JsonValue>,
+) -> Response {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /mined_microblock {:?}", payload));
+    success_response()
+}
+
+async fn handle_bitcoin_wallet_rpc_call(
+    State(state): State<IngestionState>,
+    Json(bitcoin_rpc_call): Json<BitcoinRPCRequest>,
+) -> Json<JsonValue> {
+    state
+        .ctx
+        .try_log(|logger| slog::debug!(logger, "POST /wallet"));
+    Json(state.forward_bitcoin_rpc_call(bitcoin_rpc_call, true).await)
+}
+
+async fn handle_bitcoin_rpc_call(
+    State(state): State<IngestionState>,
+    Json(bitcoin_rpc_call): Json<BitcoinRPCRequest>,
+) -> Json<JsonValue> {
+    state.ctx.try_log(|logger| slog::debug!(logger, "POST /"));
+    Json(
+        state
+            .forward_bitcoin_rpc_call(bitcoin_rpc_call, false)
+            .await,
+    )
+}
@@ -0,0 +1,376 @@
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+
+use hiro_system_kit::slog;
+use serde_json::{json, Value as JsonValue};
+
+use super::health::DependencyHealthChecks;
+use super::{
+    BitcoinConfig, BitcoinRPCRequest, MempoolAdmissionData, ObserverCommand,
+    StacksChainMempoolEvent,
+};
+use crate::indexer::bitcoin::{
+    build_http_client, download_and_parse_block_with_retry, NewBitcoinBlock,
+};
+use crate::indexer::stacks::{NewBlock, NewMicroblockTrail};
+use crate::indexer::{self, Indexer};
+use crate::monitoring::PrometheusMonitoring;
+use crate::utils::Context;
+use crate::{try_info, try_warn};
+
+/// Handle stopping the Stacks events ingestion server, whichever HTTP server runs it.
+pub struct IngestionShutdown(Box<dyn FnOnce() + Send>);
+
+impl IngestionShutdown {
+    pub fn new<F: FnOnce() + Send + 'static>(notify: F) -> Self {
+        IngestionShutdown(Box::new(notify))
+    }
+
+    pub fn notify(self) {
+        (self.0)()
+    }
+}
+
+#[cfg(feature = "rocket")]
+impl From<rocket::Shutdown> for IngestionShutdown {
+    fn from(shutdown: rocket::Shutdown) -> Self {
+        IngestionShutdown::new(move || shutdown.notify())
+    }
+}
+
+/// State shared by the handlers of the Stacks events ingestion server. The ingestion logic
+/// lives here, the HTTP servers only decode the requests and encode the responses.
+#[derive(Clone)]
+pub struct IngestionState {
+    pub indexer_rw_lock: Arc<RwLock<Indexer>>,
+    pub background_job_tx: Arc<Mutex<SyncSender<ObserverCommand>>>,
+    pub bitcoin_config: BitcoinConfig,
+    pub prometheus_monitoring: PrometheusMonitoring,
+    pub health_checks: DependencyHealthChecks,
+    /// Shared secret expected in the `Authorization` header of the events posted by the Stacks
+    /// node, see [StacksNodeConfig::ingestion_auth_token](chainhook_types::StacksNodeConfig).
+    pub auth_token: Option<String>,
+    pub ctx: Context,
+}
+
+impl IngestionState {
+    /// Whether a request carrying this `Authorization` header can post events.
+    pub fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        match self.auth_token {
+            None => true,
+            Some(ref token) => {
+                matches!(authorization, Some(value) if is_ingestion_auth_valid(value, token))
+            }
+        }
+    }
+
+    pub async fn ingest_bitcoin_block(
+        &self,
+        bitcoin_block: &NewBitcoinBlock,
+    ) -> Result<(), String> {
+        if self
+            .bitcoin_config
+            .bitcoin_block_signaling
+            .should_ignore_bitcoin_block_signaling_through_stacks()
+        {
+            return Ok(());
+        }
+
+        let ctx = &self.ctx;
+        try_info!(ctx, "POST /new_burn_block");
+        // Standardize the structure of the block, and identify the
+        // kind of update that this new block would imply, taking
+        // into account the last 7 blocks.
+
+        let http_client = build_http_client();
+        let block_hash = bitcoin_block
+            .burn_block_hash
+            .strip_prefix("0x")
+            .unwrap_or(&bitcoin_block.burn_block_hash);
+        let block = download_and_parse_block_with_retry(
+            &http_client,
+            block_hash,
+            &self.bitcoin_config,
+            ctx,
+        )
+        .await
+        .map_err(|e| format!("unable to download_and_parse_block: {e}"))?;
+
+        let header = block.get_block_header();
+        let block_height = header.block_identifier.index;
+        self.prometheus_monitoring
+            .btc_metrics_block_received(block_height);
+        self.propagate_from_async(ObserverCommand::ProcessBitcoinBlock(block))
+            .await?;
+
+        let chain_update = match self.indexer_rw_lock.write() {
+            Ok(mut indexer) => indexer.handle_bitcoin_header(header, ctx),
+            Err(e) => {
+                return Err(format!("Unable to acquire indexer_rw_lock: {e}"));
+            }
+        };
+
+        match chain_update {
+            Ok(Some(chain_event)) => {
+                self.prometheus_monitoring
+                    .btc_metrics_block_appended(block_height);
+                self.propagate_from_async(ObserverCommand::PropagateBitcoinChainEvent(chain_event))
+                    .await?;
+            }
+            Ok(None) => {
+                try_info!(ctx, "No chain event was generated");
+            }
+            Err(e) => {
+                return Err(format!("Unable to handle bitcoin block: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ingests a `/new_block` payload, deserialized straight from the request body so that
+    /// large blocks are never held as an intermediate JSON value, nor parsed under the indexer lock.
+    pub fn ingest_stacks_block(&self, serialized_block: &str) -> Result<(), String> {
+        let ctx = &self.ctx;
+        try_info!(ctx, "POST /new_block");
+        self.prometheus_monitoring.stx_metrics_event_ingested();
+        let mut block: NewBlock = serde_json::from_str(serialized_block)
+            .map_err(|e| format!("Unable to parse stacks block {e}"))?;
+        // Standardize the structure of the block, and identify the
+        // kind of update that this new block would imply, taking
+        // into account the last 7 blocks.
+        // TODO(lgalabru): use _pox_config
+        let (_pox_config, chain_event, new_tip) = match self.indexer_rw_lock.write() {
+            Ok(mut indexer) => {
+                let pox_config = indexer.get_pox_config();
+                let block = indexer
+                    .standardize_stacks_block(&mut block, ctx)
+                    .map_err(|e| format!("Unable to standardize stacks block {e}"))?;
+                let new_tip = block.block_identifier.index;
+                self.prometheus_monitoring
+                    .stx_metrics_block_received(new_tip);
+                self.prometheus_monitoring.stx_metrics_transactions_skipped(
+                    block.metadata.skipped_transactions.len() as u64,
+                );
+                let chain_event = indexer.process_stacks_block(block, ctx);
+                (pox_config, chain_event, new_tip)
+            }
+            Err(e) => {
+                return Err(format!("Unable to acquire indexer_rw_lock: {e}"));
+            }
+        };
+
+        match chain_event {
+            Ok(Some(chain_event)) => {
+                self.prometheus_monitoring
+                    .stx_metrics_block_appeneded(new_tip);
+                self.propagate(ObserverCommand::PropagateStacksChainEvent(chain_event))?;
+            }
+            Ok(None) => {
+                try_info!(ctx, "No chain event was generated");
+            }
+            Err(e) => {
+                return Err(format!("Chain event error: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn ingest_microblocks(&self, serialized_microblock_trail: &str) -> Result<(), String> {
+        let ctx = &self.ctx;
+        try_info!(ctx, "POST /new_microblocks");
+        self.prometheus_monitoring.stx_metrics_event_ingested();
+        let mut microblock_trail: NewMicroblockTrail =
+            serde_json::from_str(serialized_microblock_trail)
+                .map_err(|e| format!("Unable to parse microblock trail {e}"))?;
+        // Standardize the structure of the microblock, and identify the
+        // kind of update that this new microblock would imply
+        let chain_event = match self.indexer_rw_lock.write() {
+            Ok(mut indexer) => {
+                let microblocks = indexer
+                    .standardize_stacks_microblock_trail(&mut microblock_trail, ctx)
+                    .map_err(|e| format!("Unable to standardize microblock trail {e}"))?;
+                let skipped_transactions = microblocks
+                    .iter()
+                    .map(|microblock| microblock.metadata.skipped_transactions.len() as u64)
+                    .sum();
+                self.prometheus_monitoring
+                    .stx_metrics_transactions_skipped(skipped_transactions);
+                indexer.process_stacks_microblocks(microblocks, ctx)
+            }
+            Err(e) => {
+                return Err(format!("Unable to acquire background_job_tx: {e}"));
+            }
+        };
+
+        match chain_event {
+            Ok(Some(chain_event)) => {
+                self.propagate(ObserverCommand::PropagateStacksChainEvent(chain_event))?;
+            }
+            Ok(None) => {
+                try_info!(ctx, "No chain event was generated");
+            }
+            Err(e) => {
+                return Err(format!("Chain event error: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn ingest_mempool_txs(&self, raw_txs: &[String]) -> Result<(), String> {
+        let ctx = &self.ctx;
+        try_info!(ctx, "POST /new_mempool_tx");
+        self.prometheus_monitoring.stx_metrics_event_ingested();
+        let transactions = raw_txs
+            .iter()
+            .map(|tx_data| {
+                indexer::stacks::get_tx_description(tx_data, &vec![]).map(|(tx_description, ..)| {
+                    MempoolAdmissionData {
+                        tx_data: tx_data.clone(),
+                        tx_description,
+                    }
+                })
+            })
+            .collect::<Result<Vec<MempoolAdmissionData>, _>>()
+            .map_err(|e| format!("Failed to parse mempool transactions: {e}"))?;
+
+        let command = ObserverCommand::PropagateStacksMempoolEvent(
+            StacksChainMempoolEvent::TransactionsAdmitted(transactions),
+        );
+        match self.background_job_tx.lock() {
+            // Unlike blocks, mempool events are dropped when the observer can't keep up, rather
+            // than holding the ingestion until it catches up
+            Ok(tx) => match tx.try_send(command) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    self.prometheus_monitoring
+                        .stx_metrics_mempool_event_dropped();
+                    try_warn!(ctx, "Observer busy, dropping mempool event");
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    return Err("Unable to send mempool event".into());
+                }
+            },
+            Err(e) => {
+                return Err(format!("unable to acquire background_job_tx: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Forwards a JSON-RPC call to bitcoind. Calls posted to `/wallet` are always forwarded to
+    /// the RPC url, and don't notify the observer of the transactions they broadcast.
+    pub async fn forward_bitcoin_rpc_call(
+        &self,
+        bitcoin_rpc_call: BitcoinRPCRequest,
+        wallet: bool,
+    ) -> JsonValue {
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use base64::engine::Engine as _;
+        use reqwest::Client;
+
+        let ctx = &self.ctx;
+        let bitcoin_config = &self.bitcoin_config;
+        let method = bitcoin_rpc_call.method.clone();
+        let body = serde_json::to_vec(&bitcoin_rpc_call).unwrap_or_default();
+
+        let token = BASE64.encode(format!(
+            "{}:{}",
+            bitcoin_config.username, bitcoin_config.password
+        ));
+
+        ctx.try_log(|logger| {
+            slog::debug!(
+                logger,
+                "Forwarding {} request to {}",
+                method,
+                bitcoin_config.rpc_url
+            )
+        });
+
+        let url = if !wallet && method == "listunspent" {
+            format!("{}/wallet/", bitcoin_config.rpc_url)
+        } else {
+            bitcoin_config.rpc_url.to_string()
+        };
+
+        let client = Client::new();
+        let builder = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Basic {}", token))
+            .timeout(std::time::Duration::from_secs(5));
+
+        if !wallet && method == "sendrawtransaction" {
+            let _ = self
+                .propagate_from_async(ObserverCommand::NotifyBitcoinTransactionProxied)
+                .await;
+        }
+
+        let error = json!({
+            "status": 500
+        });
+        match builder.body(body).send().await {
+            Ok(res) => {
+                let payload = res.json().await.unwrap_or(error);
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Responding with response {:?}", payload)
+                });
+                payload
+            }
+            Err(_) => error,
+        }
+    }
+
+    fn propagate(&self, command: ObserverCommand) -> Result<(), String> {
+        send_command(&self.background_job_tx, command)
+    }
+
+    /// Sends a command to the observer from an async handler. The observer's channel is
+    /// bounded and blocks its senders while the observer catches up, so the command is sent
+    /// from a blocking thread rather than from the async runtime's workers.
+    async fn propagate_from_async(&self, command: ObserverCommand) -> Result<(), String> {
+        let background_job_tx = self.background_job_tx.clone();
+        tokio::task::spawn_blocking(move || send_command(&background_job_tx, command))
+            .await
+            .map_err(|e| format!("Unable to send observer command: {e}"))?
+    }
+}
+
+/// Sends a command to the observer, releasing the lock on its channel before waiting for room
+/// in it.
+fn send_command(
+    background_job_tx: &Mutex<SyncSender<ObserverCommand>>,
+    command: ObserverCommand,
+) -> Result<(), String> {
+    let tx = background_job_tx
+        .lock()
+        .map_err(|e| format!("unable to acquire background_job_tx: {e}"))?
+        .clone();
+    tx.send(command)
+        .map_err(|e| format!("Unable to send observer command: {}", e))
+}
+
+/// Compares the `Authorization` header, optionally using the `Bearer` scheme, to the token in
+/// constant time.
+fn is_ingestion_auth_valid(value: &str, token: &str) -> bool {
+    let value = value.strip_prefix("Bearer ").unwrap_or(value);
+    value.len() == token.len()
+        && value
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_ingestion_auth_valid;
+
+    #[test]
+    fn it_checks_ingestion_auth() {
+        assert!(is_ingestion_auth_valid("secret", "secret"));
+        assert!(is_ingestion_auth_valid("Bearer secret", "secret"));
+        assert!(!is_ingestion_auth_valid("Bearer other", "secret"));
+        assert!(!is_ingestion_auth_valid("secre", "secret"));
+    }
+}
@@ -1,27 +1,68 @@
+mod block_cache;
+mod channel;
+mod circuit_breaker;
+mod deliveries;
+mod health;
+mod heartbeats;
+#[cfg(not(feature = "axum-ingestion"))]
 mod http;
+#[cfg(feature = "axum-ingestion")]
+mod http_axum;
+mod ingestion;
+mod occurrences;
+mod outbox;
+mod polling;
+mod sidecar;
+mod stream;
+mod subscriptions;
 #[cfg(feature = "zeromq")]
 mod zmq;
 
 use crate::chainhooks::bitcoin::{
     evaluate_bitcoin_chainhooks_on_chain_event, handle_bitcoin_hook_action,
+    serialize_bitcoin_payload_for_delivery,
     BitcoinChainhookInstance, BitcoinChainhookOccurrence, BitcoinChainhookOccurrencePayload,
     BitcoinTriggerChainhook,
 };
 use crate::chainhooks::stacks::{
-    evaluate_stacks_chainhooks_on_chain_event, handle_stacks_hook_action, StacksChainhookInstance,
-    StacksChainhookOccurrence, StacksChainhookOccurrencePayload,
+    evaluate_stacks_chainhooks_on_chain_event, handle_stacks_hook_action,
+    serialize_stacks_payload_for_delivery, BitcoinAnchorContext,
+    StacksChainhookInstance, StacksChainhookOccurrence, StacksChainhookOccurrencePayload,
 };
 use crate::chainhooks::types::{
-    ChainhookInstance, ChainhookSpecificationNetworkMap, ChainhookStore,
+    ChainhookInstance, ChainhookSpecificationNetworkMap, ChainhookStore, DeadLetterSink,
+    FileHook, HookAction, HttpHook, HttpTlsConfig, NetworkParameters, RetryPolicy,
 };
 
+use crate::indexer::bitcoin::endpoints::{
+    record_endpoint_failure, record_endpoint_success, BitcoindRpcEndpoint,
+};
 use crate::indexer::bitcoin::{
     build_http_client, download_and_parse_block_with_retry, standardize_bitcoin_block,
     BitcoinBlockFullBreakdown,
 };
-use crate::indexer::{Indexer, IndexerConfig};
-use crate::monitoring::{start_serving_prometheus_metrics, PrometheusMonitoring};
-use crate::utils::{send_request, Context};
+use crate::indexer::fork_scratch_pad::CONFIRMED_SEGMENT_MINIMUM_LENGTH;
+use crate::indexer::{Indexer, IndexerConfig, MalformedStacksTransactionPolicy};
+use crate::monitoring::{
+    start_monitoring_node_tips, start_serving_prometheus_metrics, PrometheusMonitoring,
+};
+use crate::utils::aws::{send_aws_message, AwsDestination, AwsMessage};
+use crate::utils::file_sink::append_to_file_sink;
+use crate::utils::kafka::{send_kafka_message, KafkaMessage};
+use crate::utils::nats::{send_nats_message, NatsMessage};
+use crate::utils::object_storage::{send_object_store_upload, ObjectStoreUpload};
+use crate::utils::postgres::{send_postgres_record, PostgresRecord};
+use crate::utils::rate_limit::{action_rate_limiter, HostRateLimiter, RateLimitConfig};
+use crate::utils::dead_letter::dead_letter_failed_delivery;
+use crate::utils::retry::{send_http_occurrence_recording_status, send_with_retry_policy};
+use crate::utils::Context;
+use circuit_breaker::DeliveryCircuitBreakers;
+use heartbeats::PredicateHeartbeats;
+use occurrences::OccurrencesTracker;
+use outbox::{
+    complete_pending_delivery, is_already_delivered, next_reorg_sequence, record_pending_delivery,
+    release_pending_delivery, resume_pending_deliveries,
+};
 
 use bitcoincore_rpc::bitcoin::{BlockHash, Txid};
 use bitcoincore_rpc::{Auth, Client, RpcApi};
@@ -31,22 +72,59 @@ use chainhook_types::{
     StacksBlockData, StacksChainEvent, StacksNetwork, StacksNodeConfig, TransactionIdentifier,
     DEFAULT_STACKS_NODE_RPC,
 };
+use futures::Stream;
 use hiro_system_kit;
 use hiro_system_kit::slog;
-use rocket::config::{self, Config, LogLevel};
-use rocket::data::{Limits, ToByteUnit};
-use rocket::serde::Deserialize;
-use rocket::Shutdown;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
-use std::net::{IpAddr, Ipv4Addr};
 use std::str;
 use std::str::FromStr;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+pub use block_cache::{BitcoinBlockCache, BitcoinBlockSpillStore, InMemoryBitcoinBlockSpillStore};
+pub use channel::{bounded_channel, BoundedSender, FullChannelPolicy};
+pub use circuit_breaker::{CircuitBreakerConfig, PROBE_HEADER};
+pub use deliveries::{
+    DeliveryHistory, DeliveryRecord, DeliveryRecordStore, DELIVERY_HISTORY_SIZE,
+};
+pub use health::{DependencyHealthCheck, DependencyHealthChecks, MAX_READY_CHAIN_TIP_LAG};
+pub use heartbeats::HEARTBEAT_HEADER;
+#[cfg(not(feature = "axum-ingestion"))]
+use http::start_ingestion_server;
+#[cfg(feature = "axum-ingestion")]
+use http_axum::start_ingestion_server;
+pub use ingestion::IngestionShutdown;
+use ingestion::IngestionState;
+pub use occurrences::{InMemoryOccurrenceCountStore, OccurrenceCountStore};
+pub use outbox::{DeliveryOutbox, InMemoryDeliveryOutbox, PendingDelivery, MAX_DELIVERED_IDS};
+pub use sidecar::{
+    AsyncBlockPreprocessor, AsyncChainEventListener, AsyncHookActionHandler, BlockPreprocessor,
+    ChainEventListener, HookActionHandler, ObserverSidecar,
+};
+pub use stream::{OccurrenceStreams, StreamedOccurrence};
+pub use subscriptions::{ObserverSubscriptions, PredicateEvent, DEFAULT_SUBSCRIPTION_CAPACITY};
 
 pub const DEFAULT_INGESTION_PORT: u16 = 20445;
 
+/// Default capacity of the channel carrying [ObserverCommand]s to the observer. Once it is full,
+/// the ingestion of new blocks waits for the observer to catch up, rather than queueing them in
+/// memory without bound.
+pub const DEFAULT_OBSERVER_COMMANDS_CAPACITY: usize = 256;
+
+/// Default capacity of the channels carrying [ObserverEvent]s out of the observer, see
+/// [bounded_channel].
+pub const DEFAULT_OBSERVER_EVENTS_CAPACITY: usize = 1024;
+
+/// Default time given to the observer to process the commands still queued when it is asked to
+/// terminate, see [PredicatesConfig::shutdown_drain_timeout_ms].
+pub const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 30_000;
+
 #[derive(Deserialize)]
 pub struct NewTransaction {
     pub txid: String,
@@ -69,14 +147,36 @@ pub enum DataHandlerEvent {
 #[derive(Clone, Debug, PartialEq)]
 pub struct PredicatesConfig {
     pub payload_http_request_timeout_ms: Option<u64>,
+    /// Certificates used by `http_post` actions that don't specify their own.
+    pub http_tls: Option<HttpTlsConfig>,
+    /// When set, predicates whose `http_post` endpoint keeps failing are paused instead of
+    /// interrupted, and resumed once the endpoint recovers.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Limits of the requests sent to each destination host by `http_post` actions.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Time given, on termination, to process the blocks already queued and finish their
+    /// deliveries before exiting. Defaults to [DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS], `0` exits
+    /// right away.
+    pub shutdown_drain_timeout_ms: Option<u64>,
 }
 
 impl PredicatesConfig {
     pub fn new() -> Self {
         PredicatesConfig {
             payload_http_request_timeout_ms: None,
+            http_tls: None,
+            circuit_breaker: None,
+            rate_limit: None,
+            shutdown_drain_timeout_ms: None,
         }
     }
+
+    pub fn get_shutdown_drain_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.shutdown_drain_timeout_ms
+                .unwrap_or(DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS),
+        )
+    }
 }
 
 impl Default for PredicatesConfig {
@@ -85,19 +185,45 @@ impl Default for PredicatesConfig {
     }
 }
 
+/// Settings of the event observer, built with [EventObserverConfigBuilder]. Its fields are only
+/// read through the getters of the settings used outside of the observer.
 #[derive(Debug, Clone)]
 pub struct EventObserverConfig {
-    pub registered_chainhooks: ChainhookStore,
-    pub predicates_config: PredicatesConfig,
-    pub bitcoin_rpc_proxy_enabled: bool,
-    pub bitcoind_rpc_username: String,
-    pub bitcoind_rpc_password: String,
-    pub bitcoind_rpc_url: String,
-    pub bitcoin_block_signaling: BitcoinBlockSignaling,
-    pub display_stacks_ingestion_logs: bool,
-    pub bitcoin_network: BitcoinNetwork,
-    pub stacks_network: StacksNetwork,
-    pub prometheus_monitoring_port: Option<u16>,
+    pub(crate) registered_chainhooks: ChainhookStore,
+    pub(crate) predicates_config: PredicatesConfig,
+    pub(crate) bitcoin_rpc_proxy_enabled: bool,
+    pub(crate) bitcoind_rpc_username: String,
+    pub(crate) bitcoind_rpc_password: String,
+    pub(crate) bitcoind_rpc_url: String,
+    /// Endpoints of other bitcoind nodes, used while the one above fails to answer.
+    pub(crate) bitcoind_rpc_fallbacks: Vec<BitcoindRpcEndpoint>,
+    /// Esplora HTTP API blocks are fetched from instead of bitcoind, if set.
+    pub(crate) esplora_api_url: Option<String>,
+    pub(crate) bitcoin_block_signaling: BitcoinBlockSignaling,
+    pub(crate) display_stacks_ingestion_logs: bool,
+    pub(crate) bitcoin_network: BitcoinNetwork,
+    pub(crate) stacks_network: StacksNetwork,
+    pub(crate) prometheus_monitoring_port: Option<u16>,
+    /// Number of blocks, including itself, a Bitcoin block needs in the canonical chain before it
+    /// is moved to the `confirmed_blocks` of chain events. Defaults to
+    /// [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub(crate) bitcoin_confirmation_depth: Option<u64>,
+    /// Number of blocks, including itself, a Stacks block needs in the canonical chain before it
+    /// is moved to the `confirmed_blocks` of chain events. Defaults to
+    /// [CONFIRMED_SEGMENT_MINIMUM_LENGTH].
+    pub(crate) stacks_confirmation_depth: Option<u64>,
+    /// Maximum number of unconfirmed Bitcoin blocks kept in memory by the observer. Least recently
+    /// used blocks are evicted past this size, see [BitcoinBlockCache]. Unbounded if not set.
+    pub(crate) bitcoin_block_cache_size: Option<usize>,
+    /// Maximum number of outputs kept in memory to resolve the prevouts missing from the blocks
+    /// returned by bitcoind without undo data. Prevouts are not resolved if not set.
+    pub(crate) bitcoin_prevout_cache_size: Option<usize>,
+    /// Magic bytes and PoX parameters used to parse the Stacks operations of Bitcoin blocks.
+    /// Defaults to the [NetworkParameters::canonical] ones of the `bitcoin_network`.
+    pub(crate) network_parameters: Option<NetworkParameters>,
+    /// Whether a Stacks transaction that can't be standardized is left out of its block, or
+    /// fails it. Defaults to [MalformedStacksTransactionPolicy::Skip].
+    pub(crate) malformed_stacks_transactions: MalformedStacksTransactionPolicy,
 }
 
 /// A builder that is used to create a general purpose [EventObserverConfig].
@@ -109,10 +235,11 @@ pub struct EventObserverConfig {
 ///
 /// fn get_config() -> Result<EventObserverConfig, String> {
 ///     EventObserverConfigBuilder::new()
-///         .bitcoind_rpc_password("my_password")
+///         .bitcoin_rpc("http://localhost:8332", "my_username", "my_password")
+///         .bitcoind_zmq_url("tcp://0.0.0.0:18543")
 ///         .bitcoin_network("mainnet")
 ///         .stacks_network("mainnet")
-///         .finish()
+///         .build()
 /// }
 /// ```
 #[derive(Deserialize, Debug, Clone)]
@@ -121,12 +248,34 @@ pub struct EventObserverConfigBuilder {
     pub bitcoind_rpc_password: Option<String>,
     pub bitcoind_rpc_url: Option<String>,
     pub bitcoind_zmq_url: Option<String>,
+    pub bitcoind_polling_interval_ms: Option<u64>,
     pub chainhook_stacks_block_ingestion_port: Option<u16>,
     pub stacks_node_rpc_url: Option<String>,
     pub display_stacks_ingestion_logs: Option<bool>,
     pub bitcoin_network: Option<String>,
     pub stacks_network: Option<String>,
     pub prometheus_monitoring_port: Option<u16>,
+    pub bitcoin_confirmation_depth: Option<u64>,
+    pub stacks_confirmation_depth: Option<u64>,
+    pub network_parameters: Option<NetworkParameters>,
+    pub malformed_stacks_transactions: Option<MalformedStacksTransactionPolicy>,
+    // The settings below are only set through their setters, not deserialized.
+    #[serde(skip)]
+    bitcoin_rpc_proxy_enabled: bool,
+    #[serde(skip)]
+    bitcoind_rpc_fallbacks: Vec<BitcoindRpcEndpoint>,
+    #[serde(skip)]
+    esplora_api_url: Option<String>,
+    #[serde(skip)]
+    bitcoin_block_signaling: Option<BitcoinBlockSignaling>,
+    #[serde(skip)]
+    predicates_config: Option<PredicatesConfig>,
+    #[serde(skip)]
+    bitcoin_block_cache_size: Option<usize>,
+    #[serde(skip)]
+    bitcoin_prevout_cache_size: Option<usize>,
+    #[serde(skip)]
+    registered_chainhooks: ChainhookStore,
 }
 
 impl Default for EventObserverConfigBuilder {
@@ -142,15 +291,35 @@ impl EventObserverConfigBuilder {
             bitcoind_rpc_password: None,
             bitcoind_rpc_url: None,
             bitcoind_zmq_url: None,
+            bitcoind_polling_interval_ms: None,
             chainhook_stacks_block_ingestion_port: None,
             stacks_node_rpc_url: None,
             display_stacks_ingestion_logs: None,
             bitcoin_network: None,
             stacks_network: None,
             prometheus_monitoring_port: None,
+            bitcoin_confirmation_depth: None,
+            stacks_confirmation_depth: None,
+            network_parameters: None,
+            malformed_stacks_transactions: None,
+            bitcoin_rpc_proxy_enabled: false,
+            bitcoind_rpc_fallbacks: vec![],
+            esplora_api_url: None,
+            bitcoin_block_signaling: None,
+            predicates_config: None,
+            bitcoin_block_cache_size: None,
+            bitcoin_prevout_cache_size: None,
+            registered_chainhooks: ChainhookStore::new(),
         }
     }
 
+    /// Sets the bitcoind node's RPC url and credentials.
+    pub fn bitcoin_rpc(&mut self, url: &str, username: &str, password: &str) -> &mut Self {
+        self.bitcoind_rpc_url(url)
+            .bitcoind_rpc_username(username)
+            .bitcoind_rpc_password(password)
+    }
+
     /// Sets the bitcoind node's RPC username.
     pub fn bitcoind_rpc_username(&mut self, username: &str) -> &mut Self {
         self.bitcoind_rpc_username = Some(username.to_string());
@@ -175,6 +344,13 @@ impl EventObserverConfigBuilder {
         self
     }
 
+    /// Sets the interval at which the observer polls the bitcoind node's RPC for new blocks, when
+    /// neither ZMQ nor a Stacks node can be used. Ignored if a ZMQ url is set.
+    pub fn bitcoind_polling_interval_ms(&mut self, interval_ms: u64) -> &mut Self {
+        self.bitcoind_polling_interval_ms = Some(interval_ms);
+        self
+    }
+
     /// Sets the Bitcoin network. Must be a valid bitcoin network string according to [BitcoinNetwork::from_str].
     pub fn bitcoin_network(&mut self, network: &str) -> &mut Self {
         self.bitcoin_network = Some(network.to_string());
@@ -212,14 +388,208 @@ impl EventObserverConfigBuilder {
         self
     }
 
+    /// Sets the number of blocks, including itself, a Bitcoin block needs in the canonical chain
+    /// to be confirmed.
+    pub fn bitcoin_confirmation_depth(&mut self, depth: u64) -> &mut Self {
+        self.bitcoin_confirmation_depth = Some(depth);
+        self
+    }
+
+    /// Sets the number of blocks, including itself, a Stacks block needs in the canonical chain
+    /// to be confirmed.
+    pub fn stacks_confirmation_depth(&mut self, depth: u64) -> &mut Self {
+        self.stacks_confirmation_depth = Some(depth);
+        self
+    }
+
+    /// Sets the magic bytes and PoX parameters of the observed chain, for regtest or signet
+    /// setups not using the canonical ones.
+    pub fn network_parameters(&mut self, network_parameters: NetworkParameters) -> &mut Self {
+        self.network_parameters = Some(network_parameters);
+        self
+    }
+
+    /// Sets whether a Stacks transaction that can't be standardized is left out of its block, or
+    /// fails it.
+    pub fn malformed_stacks_transactions(
+        &mut self,
+        policy: MalformedStacksTransactionPolicy,
+    ) -> &mut Self {
+        self.malformed_stacks_transactions = Some(policy);
+        self
+    }
+
+    /// Sets the endpoints of other bitcoind nodes, used while the main one fails to answer.
+    pub fn bitcoind_rpc_fallbacks(&mut self, fallbacks: Vec<BitcoindRpcEndpoint>) -> &mut Self {
+        self.bitcoind_rpc_fallbacks = fallbacks;
+        self
+    }
+
+    /// Sets the Esplora HTTP API blocks are fetched from instead of bitcoind.
+    pub fn esplora_api_url(&mut self, url: &str) -> &mut Self {
+        self.esplora_api_url = Some(url.to_string());
+        self
+    }
+
+    /// Sets how the observer is notified of new Bitcoin blocks, in place of the ZMQ url, polling
+    /// interval and Stacks node settings.
+    pub fn bitcoin_block_signaling(&mut self, signaling: BitcoinBlockSignaling) -> &mut Self {
+        self.bitcoin_block_signaling = Some(signaling);
+        self
+    }
+
+    /// Sets whether the observer proxies the transactions it is sent to bitcoind.
+    pub fn bitcoin_rpc_proxy_enabled(&mut self, enabled: bool) -> &mut Self {
+        self.bitcoin_rpc_proxy_enabled = enabled;
+        self
+    }
+
+    /// Sets the settings of the deliveries of the predicates' occurrences.
+    pub fn predicates_config(&mut self, predicates_config: PredicatesConfig) -> &mut Self {
+        self.predicates_config = Some(predicates_config);
+        self
+    }
+
+    /// Sets the maximum number of unconfirmed Bitcoin blocks kept in memory by the observer.
+    pub fn bitcoin_block_cache_size(&mut self, size: usize) -> &mut Self {
+        self.bitcoin_block_cache_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum number of outputs kept in memory to resolve the prevouts missing from
+    /// the blocks returned by bitcoind without undo data.
+    pub fn bitcoin_prevout_cache_size(&mut self, size: usize) -> &mut Self {
+        self.bitcoin_prevout_cache_size = Some(size);
+        self
+    }
+
+    /// Registers a predicate, evaluated as soon as the observer starts.
+    pub fn with_predicate(&mut self, predicate: ChainhookInstance) -> &mut Self {
+        // registering an instance never fails
+        let _ = self.registered_chainhooks.register_instance(predicate);
+        self
+    }
+
+    /// Replaces the predicates registered with [EventObserverConfigBuilder::with_predicate].
+    pub fn registered_chainhooks(&mut self, chainhook_store: ChainhookStore) -> &mut Self {
+        self.registered_chainhooks = chainhook_store;
+        self
+    }
+
     /// Attempts to convert a [EventObserverConfigBuilder] instance into an [EventObserverConfig], filling in
     /// defaults as necessary according to [EventObserverConfig::default].
     ///
     /// This function will return an error if the `bitcoin_network` or `stacks_network` strings are set and are not a valid [BitcoinNetwork] or [StacksNetwork].
-    ///
+    /// See [EventObserverConfigBuilder::build] for a conversion validating the other settings as well.
     pub fn finish(&self) -> Result<EventObserverConfig, String> {
         EventObserverConfig::new_using_overrides(Some(self))
     }
+
+    /// Converts a [EventObserverConfigBuilder] instance into an [EventObserverConfig] like
+    /// [EventObserverConfigBuilder::finish], after checking that its settings are consistent:
+    /// urls must be valid, settings picking how new Bitcoin blocks are received must not
+    /// conflict, confirmation depths must be positive, and the registered predicates must target
+    /// the observed networks. All the errors found are returned, separated by commas.
+    pub fn build(&self) -> Result<EventObserverConfig, String> {
+        let config = self.finish()?;
+        let mut errors = vec![];
+
+        let mut urls = vec![("bitcoind_rpc_url", &config.bitcoind_rpc_url)];
+        for fallback in config.bitcoind_rpc_fallbacks.iter() {
+            urls.push(("bitcoind_rpc_fallbacks", &fallback.rpc_url));
+        }
+        if let Some(ref url) = config.esplora_api_url {
+            urls.push(("esplora_api_url", url));
+        }
+        if let BitcoinBlockSignaling::Stacks(ref stacks_node_config) =
+            config.bitcoin_block_signaling
+        {
+            urls.push(("stacks_node_rpc_url", &stacks_node_config.rpc_url));
+        }
+        for (setting, url) in urls.into_iter() {
+            if let Err(e) = reqwest::Url::parse(url) {
+                errors.push(format!("{}: invalid url {}: {}", setting, url, e));
+            }
+        }
+
+        let signaling_settings = [
+            ("bitcoind_zmq_url", self.bitcoind_zmq_url.is_some()),
+            (
+                "bitcoind_polling_interval_ms",
+                self.bitcoind_polling_interval_ms.is_some(),
+            ),
+            (
+                "stacks_node_rpc_url",
+                self.stacks_node_rpc_url.is_some()
+                    || self.chainhook_stacks_block_ingestion_port.is_some(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(setting, is_set)| is_set.then_some(setting))
+        .collect::<Vec<_>>();
+        if self.bitcoin_block_signaling.is_some() && !signaling_settings.is_empty() {
+            errors.push(format!(
+                "bitcoin_block_signaling: can't be combined with {}",
+                signaling_settings.join(" and ")
+            ));
+        } else if signaling_settings.len() > 1 {
+            errors.push(format!(
+                "{}: only one way of receiving Bitcoin blocks can be set",
+                signaling_settings.join(" and ")
+            ));
+        }
+        if let BitcoinBlockSignaling::Polling(0) = config.bitcoin_block_signaling {
+            errors.push("bitcoind_polling_interval_ms: must be positive".into());
+        }
+        if let (BitcoinBlockSignaling::Stacks(ref stacks_node_config), Some(port)) = (
+            &config.bitcoin_block_signaling,
+            config.prometheus_monitoring_port,
+        ) {
+            if stacks_node_config.ingestion_port == port {
+                errors.push(format!(
+                    "prometheus_monitoring_port: port {} is already the ingestion port",
+                    port
+                ));
+            }
+        }
+
+        for (setting, depth) in [
+            (
+                "bitcoin_confirmation_depth",
+                config.bitcoin_confirmation_depth,
+            ),
+            (
+                "stacks_confirmation_depth",
+                config.stacks_confirmation_depth,
+            ),
+        ] {
+            if depth == Some(0) {
+                errors.push(format!("{}: must be positive", setting));
+            }
+        }
+
+        for predicate in config.registered_chainhooks.bitcoin_chainhooks.iter() {
+            if predicate.network != config.bitcoin_network {
+                errors.push(format!(
+                    "predicate {}: targets the Bitcoin network {}, not {}",
+                    predicate.uuid, predicate.network, config.bitcoin_network
+                ));
+            }
+        }
+        for predicate in config.registered_chainhooks.stacks_chainhooks.iter() {
+            if predicate.network != config.stacks_network {
+                errors.push(format!(
+                    "predicate {}: targets the Stacks network {}, not {}",
+                    predicate.uuid, predicate.network, config.stacks_network
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join(", "));
+        }
+        Ok(config)
+    }
 }
 
 /// A builder that is used to create an [EventObserverConfig] that is tailored for use with a bitcoind node emitting events via the ZMQ interface.
@@ -242,7 +612,9 @@ pub struct BitcoinEventObserverConfigBuilder {
     pub bitcoind_rpc_url: Option<String>,
     pub bitcoin_network: Option<String>,
     pub bitcoind_zmq_url: Option<String>,
+    pub bitcoind_polling_interval_ms: Option<u64>,
     pub prometheus_monitoring_port: Option<u16>,
+    pub confirmation_depth: Option<u64>,
 }
 impl Default for BitcoinEventObserverConfigBuilder {
     fn default() -> Self {
@@ -258,7 +630,9 @@ impl BitcoinEventObserverConfigBuilder {
             bitcoind_rpc_url: None,
             bitcoin_network: None,
             bitcoind_zmq_url: None,
+            bitcoind_polling_interval_ms: None,
             prometheus_monitoring_port: None,
+            confirmation_depth: None,
         }
     }
 
@@ -286,6 +660,13 @@ impl BitcoinEventObserverConfigBuilder {
         self
     }
 
+    /// Sets the interval at which the observer polls the bitcoind node's RPC for new blocks,
+    /// instead of receiving them from its ZMQ interface.
+    pub fn polling_interval_ms(&mut self, interval_ms: u64) -> &mut Self {
+        self.bitcoind_polling_interval_ms = Some(interval_ms);
+        self
+    }
+
     /// Sets the Bitcoin network. Must be a valid bitcoin network string according to [BitcoinNetwork::from_str].
     pub fn network(&mut self, network: &str) -> &mut Self {
         self.bitcoin_network = Some(network.to_string());
@@ -298,6 +679,13 @@ impl BitcoinEventObserverConfigBuilder {
         self
     }
 
+    /// Sets the number of blocks, including itself, a block needs in the canonical chain to be
+    /// confirmed.
+    pub fn confirmation_depth(&mut self, depth: u64) -> &mut Self {
+        self.confirmation_depth = Some(depth);
+        self
+    }
+
     /// Attempts to convert a [BitcoinEventObserverConfigBuilder] instance into an [EventObserverConfig], filling in
     /// defaults as necessary according to [EventObserverConfig::default].
     ///
@@ -310,9 +698,7 @@ impl BitcoinEventObserverConfigBuilder {
         };
         Ok(EventObserverConfig {
             registered_chainhooks: ChainhookStore::new(),
-            predicates_config: PredicatesConfig {
-                payload_http_request_timeout_ms: None,
-            },
+            predicates_config: PredicatesConfig::new(),
             bitcoin_rpc_proxy_enabled: false,
             bitcoind_rpc_username: self
                 .bitcoind_rpc_username
@@ -326,15 +712,26 @@ impl BitcoinEventObserverConfigBuilder {
                 .bitcoind_rpc_url
                 .clone()
                 .unwrap_or_else(|| "http://localhost:18443".into()),
-            bitcoin_block_signaling: BitcoinBlockSignaling::ZeroMQ(
-                self.bitcoind_zmq_url
-                    .clone()
-                    .unwrap_or_else(|| "tcp://0.0.0.0:18543".into()),
-            ),
+            bitcoind_rpc_fallbacks: vec![],
+            esplora_api_url: None,
+            bitcoin_block_signaling: match self.bitcoind_polling_interval_ms {
+                Some(interval_ms) => BitcoinBlockSignaling::Polling(interval_ms),
+                None => BitcoinBlockSignaling::ZeroMQ(
+                    self.bitcoind_zmq_url
+                        .clone()
+                        .unwrap_or_else(|| "tcp://0.0.0.0:18543".into()),
+                ),
+            },
             display_stacks_ingestion_logs: false,
             bitcoin_network,
             stacks_network: StacksNetwork::Devnet,
             prometheus_monitoring_port: self.prometheus_monitoring_port,
+            bitcoin_confirmation_depth: self.confirmation_depth,
+            stacks_confirmation_depth: None,
+            bitcoin_block_cache_size: None,
+            bitcoin_prevout_cache_size: None,
+            network_parameters: None,
+            malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
         })
     }
 }
@@ -343,13 +740,13 @@ impl EventObserverConfig {
     pub fn default() -> Self {
         EventObserverConfig {
             registered_chainhooks: ChainhookStore::new(),
-            predicates_config: PredicatesConfig {
-                payload_http_request_timeout_ms: None,
-            },
+            predicates_config: PredicatesConfig::new(),
             bitcoin_rpc_proxy_enabled: false,
             bitcoind_rpc_username: "devnet".into(),
             bitcoind_rpc_password: "devnet".into(),
             bitcoind_rpc_url: "http://localhost:18443".into(),
+            bitcoind_rpc_fallbacks: vec![],
+            esplora_api_url: None,
             bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(StacksNodeConfig::new(
                 DEFAULT_STACKS_NODE_RPC.to_string(),
                 DEFAULT_INGESTION_PORT,
@@ -358,6 +755,12 @@ impl EventObserverConfig {
             bitcoin_network: BitcoinNetwork::Regtest,
             stacks_network: StacksNetwork::Devnet,
             prometheus_monitoring_port: None,
+            bitcoin_confirmation_depth: None,
+            stacks_confirmation_depth: None,
+            bitcoin_block_cache_size: None,
+            bitcoin_prevout_cache_size: None,
+            network_parameters: None,
+            malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
         }
     }
 
@@ -393,8 +796,11 @@ impl EventObserverConfig {
             username: self.bitcoind_rpc_username.clone(),
             password: self.bitcoind_rpc_password.clone(),
             rpc_url: self.bitcoind_rpc_url.clone(),
+            rpc_fallbacks: self.bitcoind_rpc_fallbacks.clone(),
+            esplora_api_url: self.esplora_api_url.clone(),
             network: self.bitcoin_network.clone(),
             bitcoin_block_signaling: self.bitcoin_block_signaling.clone(),
+            prevout_cache_size: self.bitcoin_prevout_cache_size,
         }
     }
 
@@ -405,6 +811,42 @@ impl EventObserverConfig {
         }
     }
 
+    pub fn get_registered_chainhooks(&self) -> &ChainhookStore {
+        &self.registered_chainhooks
+    }
+
+    pub fn get_predicates_config(&self) -> &PredicatesConfig {
+        &self.predicates_config
+    }
+
+    pub fn get_bitcoin_network(&self) -> &BitcoinNetwork {
+        &self.bitcoin_network
+    }
+
+    pub fn get_stacks_network(&self) -> &StacksNetwork {
+        &self.stacks_network
+    }
+
+    pub fn get_prometheus_monitoring_port(&self) -> Option<u16> {
+        self.prometheus_monitoring_port
+    }
+
+    pub fn get_bitcoin_confirmation_depth(&self) -> u64 {
+        self.bitcoin_confirmation_depth
+            .unwrap_or(CONFIRMED_SEGMENT_MINIMUM_LENGTH as u64)
+    }
+
+    pub fn get_stacks_confirmation_depth(&self) -> u64 {
+        self.stacks_confirmation_depth
+            .unwrap_or(CONFIRMED_SEGMENT_MINIMUM_LENGTH as u64)
+    }
+
+    pub fn get_network_parameters(&self) -> NetworkParameters {
+        self.network_parameters
+            .clone()
+            .unwrap_or_else(|| NetworkParameters::canonical(&self.bitcoin_network))
+    }
+
     /// Helper to allow overriding some default fields in creating a new EventObserverConfig.
     ///
     /// *Note: This is used by external crates, so it should not be removed, even if not used internally by Chainhook.*
@@ -426,11 +868,15 @@ impl EventObserverConfig {
             };
 
         let config = EventObserverConfig {
-            bitcoin_rpc_proxy_enabled: false,
-            registered_chainhooks: ChainhookStore::new(),
-            predicates_config: PredicatesConfig {
-                payload_http_request_timeout_ms: None,
-            },
+            bitcoin_rpc_proxy_enabled: overrides
+                .map(|c| c.bitcoin_rpc_proxy_enabled)
+                .unwrap_or(false),
+            registered_chainhooks: overrides
+                .map(|c| c.registered_chainhooks.clone())
+                .unwrap_or_default(),
+            predicates_config: overrides
+                .and_then(|c| c.predicates_config.clone())
+                .unwrap_or_default(),
             bitcoind_rpc_username: overrides
                 .and_then(|c| c.bitcoind_rpc_username.clone())
                 .unwrap_or_else(|| "devnet".to_string()),
@@ -440,9 +886,22 @@ impl EventObserverConfig {
             bitcoind_rpc_url: overrides
                 .and_then(|c| c.bitcoind_rpc_url.clone())
                 .unwrap_or_else(|| "http://localhost:18443".to_string()),
+            bitcoind_rpc_fallbacks: overrides
+                .map(|c| c.bitcoind_rpc_fallbacks.clone())
+                .unwrap_or_default(),
+            esplora_api_url: overrides.and_then(|c| c.esplora_api_url.clone()),
             bitcoin_block_signaling: overrides
-                .and_then(|c| c.bitcoind_zmq_url.as_ref())
-                .map(|url| BitcoinBlockSignaling::ZeroMQ(url.clone()))
+                .and_then(|c| c.bitcoin_block_signaling.clone())
+                .or_else(|| {
+                    overrides
+                        .and_then(|c| c.bitcoind_zmq_url.as_ref())
+                        .map(|url| BitcoinBlockSignaling::ZeroMQ(url.clone()))
+                })
+                .or_else(|| {
+                    overrides
+                        .and_then(|c| c.bitcoind_polling_interval_ms)
+                        .map(BitcoinBlockSignaling::Polling)
+                })
                 .unwrap_or_else(|| {
                     BitcoinBlockSignaling::Stacks(StacksNodeConfig::new(
                         overrides
@@ -459,6 +918,14 @@ impl EventObserverConfig {
             bitcoin_network,
             stacks_network,
             prometheus_monitoring_port: overrides.and_then(|c| c.prometheus_monitoring_port),
+            bitcoin_confirmation_depth: overrides.and_then(|c| c.bitcoin_confirmation_depth),
+            stacks_confirmation_depth: overrides.and_then(|c| c.stacks_confirmation_depth),
+            bitcoin_block_cache_size: overrides.and_then(|c| c.bitcoin_block_cache_size),
+            bitcoin_prevout_cache_size: overrides.and_then(|c| c.bitcoin_prevout_cache_size),
+            network_parameters: overrides.and_then(|c| c.network_parameters.clone()),
+            malformed_stacks_transactions: overrides
+                .and_then(|c| c.malformed_stacks_transactions)
+                .unwrap_or_default(),
         };
         Ok(config)
     }
@@ -478,15 +945,38 @@ pub enum ObserverCommand {
     PropagateStacksChainEvent(StacksChainEvent),
     PropagateStacksMempoolEvent(StacksChainMempoolEvent),
     RegisterPredicate(ChainhookSpecificationNetworkMap),
+    /// Registers a predicate already specified for the observed networks, e.g. one loaded from
+    /// a predicates db by another process.
+    RegisterPredicateInstance(ChainhookInstance),
+    /// Replaces the specification of a registered predicate with the same uuid and chain.
+    UpdatePredicate(ChainhookSpecificationNetworkMap),
     EnablePredicate(ChainhookInstance),
     DeregisterBitcoinPredicate(String),
     DeregisterStacksPredicate(String),
+    /// Disables a predicate, on either chain, until it is resumed with
+    /// [ObserverCommand::ResumePredicate]. The predicate stays registered.
+    PausePredicate(String),
+    /// Resumes a paused predicate. Like when its endpoint recovers, a
+    /// [ObserverEvent::PredicateResumed] is emitted so that the blocks observed while it was
+    /// paused can be evaluated, and the predicate is enabled with
+    /// [ObserverCommand::EnablePredicate] once caught up.
+    ResumePredicate(String),
+    /// Evaluates a registered predicate against a range of blocks again, without changing its
+    /// status. A [ObserverEvent::PredicateRescanRequested] is emitted for the range to be scanned.
+    RescanPredicate(RescanPredicateData),
     ExpireBitcoinPredicate(HookExpirationData),
     ExpireStacksPredicate(HookExpirationData),
     NotifyBitcoinTransactionProxied,
     Terminate,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct RescanPredicateData {
+    pub predicate_uuid: String,
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct HookExpirationData {
     pub hook_uuid: String,
@@ -575,6 +1065,43 @@ pub struct PredicateInterruptedData {
     pub error: String,
 }
 
+/// Cursor acknowledged by the receiver of a predicate requiring acks: the height of the last
+/// block it processed.
+#[derive(Clone, Debug)]
+pub struct PredicateAcknowledgedData {
+    pub predicate_key: String,
+    pub cursor: u64,
+}
+
+/// Emitted when a delivery of a predicate requiring acks fails. The predicate is disabled
+/// rather than deregistered, so that deliveries can resume from its last acknowledged cursor.
+#[derive(Clone, Debug)]
+pub struct PredicateDeliveryFailedData {
+    pub predicate: ChainhookInstance,
+    pub error: String,
+}
+
+/// Emitted when a predicate is paused because the circuit of its `http_post` endpoint opened.
+/// The predicate is resumed with [ObserverEvent::PredicateResumed] once the endpoint answers
+/// the observer's probes again.
+#[derive(Clone, Debug)]
+pub struct PredicatePausedData {
+    pub predicate: ChainhookInstance,
+    pub error: String,
+    /// Whether the pause was requested with [ObserverCommand::PausePredicate]. Such predicates
+    /// are only resumed with [ObserverCommand::ResumePredicate].
+    pub requested: bool,
+}
+
+/// Emitted when a re-scan of the blocks `start_block` to `end_block` is requested for a
+/// registered predicate, for instance after its consumer lost data.
+#[derive(Clone, Debug)]
+pub struct PredicateRescanData {
+    pub predicate: ChainhookInstance,
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
 #[derive(Clone, Debug)]
 pub enum ObserverEvent {
     Error(String),
@@ -584,12 +1111,18 @@ pub enum ObserverEvent {
     StacksChainEvent((StacksChainEvent, PredicateEvaluationReport)),
     NotifyBitcoinTransactionProxied,
     PredicateRegistered(ChainhookInstance),
+    PredicateUpdated(ChainhookInstance),
     PredicateDeregistered(PredicateDeregisteredEvent),
     PredicateEnabled(ChainhookInstance),
     BitcoinPredicateTriggered(BitcoinChainhookOccurrencePayload),
     StacksPredicateTriggered(StacksChainhookOccurrencePayload),
     PredicatesTriggered(usize),
     PredicateInterrupted(PredicateInterruptedData),
+    PredicateAcknowledged(PredicateAcknowledgedData),
+    PredicateDeliveryFailed(PredicateDeliveryFailedData),
+    PredicatePaused(PredicatePausedData),
+    PredicateResumed(ChainhookInstance),
+    PredicateRescanRequested(PredicateRescanData),
     Terminate,
     StacksChainMempoolEvent(StacksChainMempoolEvent),
 }
@@ -618,85 +1151,146 @@ pub struct BitcoinConfig {
     pub username: String,
     pub password: String,
     pub rpc_url: String,
+    pub rpc_fallbacks: Vec<BitcoindRpcEndpoint>,
+    pub esplora_api_url: Option<String>,
     pub network: BitcoinNetwork,
     pub bitcoin_block_signaling: BitcoinBlockSignaling,
+    pub prevout_cache_size: Option<usize>,
 }
 
-#[derive(Debug, Clone)]
-pub struct BitcoinBlockDataCached {
-    pub block: BitcoinBlockData,
-    pub processed_by_sidecar: bool,
-}
-
-pub struct ObserverSidecar {
-    pub bitcoin_blocks_mutator: Option<(
-        crossbeam_channel::Sender<(Vec<BitcoinBlockDataCached>, Vec<BlockIdentifier>)>,
-        crossbeam_channel::Receiver<Vec<BitcoinBlockDataCached>>,
-    )>,
-    pub bitcoin_chain_event_notifier: Option<crossbeam_channel::Sender<HandleBlock>>,
+/// Occurrences delivered to a file, an object store, a message broker or a database rather
+/// than through an http request.
+enum OccurrenceDelivery {
+    File(FileHook, Vec<u8>),
+    ObjectStore(ObjectStoreUpload),
+    Kafka(KafkaMessage),
+    Nats(NatsMessage),
+    Aws(AwsMessage),
+    Postgres(PostgresRecord),
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct StacksObserverStartupContext {
-    pub block_pool_seed: Vec<StacksBlockData>,
-    pub last_block_height_appended: u64,
-}
-
-impl ObserverSidecar {
-    fn perform_bitcoin_sidecar_mutations(
-        &self,
-        blocks: Vec<BitcoinBlockDataCached>,
-        blocks_ids_to_rollback: Vec<BlockIdentifier>,
-        ctx: &Context,
-    ) -> Vec<BitcoinBlockDataCached> {
-        if let Some(ref block_mutator) = self.bitcoin_blocks_mutator {
-            ctx.try_log(|logger| slog::info!(logger, "Sending blocks to pre-processor",));
-            let _ = block_mutator
-                .0
-                .send((blocks.clone(), blocks_ids_to_rollback));
-            ctx.try_log(|logger| slog::info!(logger, "Waiting for blocks from pre-processor",));
-            match block_mutator.1.recv() {
-                Ok(updated_blocks) => {
-                    ctx.try_log(|logger| slog::info!(logger, "Block received from pre-processor",));
-                    updated_blocks
+impl OccurrenceDelivery {
+    async fn send(&self, retry_policy: &RetryPolicy, ctx: &Context) -> Result<(), String> {
+        send_with_retry_policy(retry_policy, ctx, || async move {
+            match self {
+                OccurrenceDelivery::File(hook, bytes) => {
+                    append_to_file_sink(hook, bytes.clone(), ctx)
                 }
-                Err(e) => {
-                    ctx.try_log(|logger| {
-                        slog::error!(
-                            logger,
-                            "Unable to receive block from pre-processor {}",
-                            e.to_string()
-                        )
-                    });
-                    blocks
+                OccurrenceDelivery::ObjectStore(upload) => {
+                    send_object_store_upload(upload, 1, 0, ctx).await
+                }
+                OccurrenceDelivery::Kafka(message) => send_kafka_message(message, 1, 0, ctx).await,
+                OccurrenceDelivery::Nats(message) => send_nats_message(message, 1, 0, ctx).await,
+                OccurrenceDelivery::Aws(message) => send_aws_message(message, 1, 0, ctx).await,
+                OccurrenceDelivery::Postgres(record) => {
+                    send_postgres_record(record, 1, 0, ctx).await
                 }
             }
-        } else {
-            blocks
+        })
+        .await
+    }
+
+    /// Destination of the delivery, reported in the [DeliveryHistory].
+    fn target(&self) -> String {
+        match self {
+            OccurrenceDelivery::File(hook, _) => hook.path.clone(),
+            OccurrenceDelivery::ObjectStore(upload) => upload.url.clone(),
+            OccurrenceDelivery::Kafka(message) => message.topic.clone(),
+            OccurrenceDelivery::Nats(message) => message.subject.clone(),
+            OccurrenceDelivery::Aws(message) => match message.destination {
+                AwsDestination::SqsQueue(ref queue_url) => queue_url.clone(),
+                AwsDestination::SnsTopic(ref topic_arn) => topic_arn.clone(),
+            },
+            // The dsn holds the credentials of the database
+            OccurrenceDelivery::Postgres(record) => record.table.clone(),
         }
     }
 
-    fn notify_chain_event(&self, chain_event: &BitcoinChainEvent, _ctx: &Context) {
-        if let Some(ref notifier) = self.bitcoin_chain_event_notifier {
-            match chain_event {
-                BitcoinChainEvent::ChainUpdatedWithBlocks(data) => {
-                    for block in data.new_blocks.iter() {
-                        let _ = notifier.send(HandleBlock::ApplyBlock(block.clone()));
-                    }
-                }
-                BitcoinChainEvent::ChainUpdatedWithReorg(data) => {
-                    for block in data.blocks_to_rollback.iter() {
-                        let _ = notifier.send(HandleBlock::UndoBlock(block.clone()));
-                    }
-                    for block in data.blocks_to_apply.iter() {
-                        let _ = notifier.send(HandleBlock::ApplyBlock(block.clone()));
-                    }
-                }
+    fn payload_size(&self) -> usize {
+        match self {
+            OccurrenceDelivery::File(_, bytes) => bytes.len(),
+            OccurrenceDelivery::ObjectStore(upload) => {
+                upload.objects.iter().map(|(_, bytes)| bytes.len()).sum()
             }
+            OccurrenceDelivery::Kafka(message) => message.payload.len(),
+            OccurrenceDelivery::Nats(message) => message.payload.len(),
+            OccurrenceDelivery::Aws(message) => message.payload.len(),
+            OccurrenceDelivery::Postgres(record) => record.payload.len(),
+        }
+    }
+}
+
+/// Url and body size of the request of an `http_post` occurrence, reported in the
+/// [DeliveryHistory].
+fn describe_http_request(request: &RequestBuilder) -> (String, usize) {
+    match request.try_clone().and_then(|request| request.build().ok()) {
+        Some(request) => {
+            let body_size = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|bytes| bytes.len())
+                .unwrap_or(0);
+            (request.url().to_string(), body_size)
         }
+        None => (String::new(), 0),
     }
 }
 
+/// Retry policy and dead-letter sink of the action an occurrence is delivered to, along with
+/// the id of the delivery in the [DeliveryOutbox], if recorded.
+struct DeliveryPolicy {
+    retry_policy: RetryPolicy,
+    dead_letter: Option<DeadLetterSink>,
+    outbox_id: Option<String>,
+    /// Id of the delivery, reported in the [DeliveryHistory].
+    delivery_id: String,
+    require_ack: bool,
+    /// Endpoint of `http_post` actions, tracked by the [DeliveryCircuitBreakers].
+    endpoint: Option<HttpHook>,
+    rate_limiter: Option<Arc<HostRateLimiter>>,
+}
+
+impl DeliveryPolicy {
+    fn endpoint_of(action: &HookAction) -> Option<HttpHook> {
+        match action {
+            HookAction::HttpPost(http) => Some(http.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitcoinBlockDataCached {
+    pub block: BitcoinBlockData,
+    pub processed_by_sidecar: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StacksObserverStartupContext {
+    pub block_pool_seed: Vec<StacksBlockData>,
+    pub last_block_height_appended: u64,
+    /// Dependencies whose connectivity is reported by the `/health` and `/ready` endpoints.
+    pub health_checks: DependencyHealthChecks,
+}
+
+/// Optional dependencies of the observer, shared with the rest of the process. Set them through
+/// [EventObserverBuilder], or start from [ObserverDependencies::default] when calling
+/// [start_event_observer] directly.
+#[derive(Clone, Default)]
+pub struct ObserverDependencies {
+    /// Registry used to stream occurrences to live subscribers. See [OccurrenceStreams].
+    pub occurrence_streams: Option<OccurrenceStreams>,
+    /// Registry recording the most recent deliveries of each predicate. See [DeliveryHistory].
+    pub delivery_history: Option<DeliveryHistory>,
+    /// Outbox recording the deliveries in flight. See [DeliveryOutbox].
+    pub delivery_outbox: Option<Arc<dyn DeliveryOutbox>>,
+    /// Store persisting the number of occurrences of each predicate. See [OccurrenceCountStore].
+    pub occurrence_count_store: Option<Arc<dyn OccurrenceCountStore>>,
+    /// Store the Bitcoin blocks evicted from the observer's cache are spilled to. See
+    /// [BitcoinBlockSpillStore].
+    pub bitcoin_block_spill_store: Option<Arc<dyn BitcoinBlockSpillStore>>,
+}
+
 /// A helper struct used to configure and call [start_event_observer], which spawns a thread to observer chain events.
 ///
 /// ### Examples
@@ -706,11 +1300,11 @@ impl ObserverSidecar {
 /// use chainhook_sdk::observer::ObserverCommand;
 /// use chainhook_sdk::utils::Context;
 /// use std::error::Error;
-/// use std::sync::mpsc::{Receiver, Sender};
+/// use std::sync::mpsc::{Receiver, SyncSender};
 ///
 /// fn start_event_observer(
 ///     config: EventObserverConfig,
-///     observer_commands_tx: &Sender<ObserverCommand>,
+///     observer_commands_tx: &SyncSender<ObserverCommand>,
 ///     observer_commands_rx: Receiver<ObserverCommand>,
 ///     ctx: &Context,
 /// )-> Result<(), Box<dyn Error>> {
@@ -725,18 +1319,20 @@ impl ObserverSidecar {
 /// ```
 pub struct EventObserverBuilder {
     config: EventObserverConfig,
-    observer_commands_tx: Sender<ObserverCommand>,
+    observer_commands_tx: SyncSender<ObserverCommand>,
     observer_commands_rx: Receiver<ObserverCommand>,
     ctx: Context,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
     observer_sidecar: Option<ObserverSidecar>,
+    dependencies: ObserverDependencies,
     stacks_startup_context: Option<StacksObserverStartupContext>,
+    subscriptions: Option<ObserverSubscriptions>,
 }
 
 impl EventObserverBuilder {
     pub fn new(
         config: EventObserverConfig,
-        observer_commands_tx: &Sender<ObserverCommand>,
+        observer_commands_tx: &SyncSender<ObserverCommand>,
         observer_commands_rx: Receiver<ObserverCommand>,
         ctx: &Context,
     ) -> Self {
@@ -747,17 +1343,20 @@ impl EventObserverBuilder {
             ctx: ctx.clone(),
             observer_events_tx: None,
             observer_sidecar: None,
+            dependencies: ObserverDependencies::default(),
             stacks_startup_context: None,
+            subscriptions: None,
         }
     }
 
     /// Sets the `observer_events_tx` Sender. Set this and listen on the corresponding
-    /// Receiver to be notified of every [ObserverEvent].
+    /// Receiver to be notified of every [ObserverEvent]. Use [bounded_channel] to choose what
+    /// happens to the events sent while the Receiver lags behind.
     pub fn events_tx(
         &mut self,
-        observer_events_tx: crossbeam_channel::Sender<ObserverEvent>,
+        observer_events_tx: impl Into<BoundedSender<ObserverEvent>>,
     ) -> &mut Self {
-        self.observer_events_tx = Some(observer_events_tx);
+        self.observer_events_tx = Some(observer_events_tx.into());
         self
     }
 
@@ -767,21 +1366,113 @@ impl EventObserverBuilder {
         self
     }
 
+    /// Sets the registry used to stream occurrences to live subscribers. See [OccurrenceStreams].
+    pub fn occurrence_streams(&mut self, occurrence_streams: OccurrenceStreams) -> &mut Self {
+        self.dependencies.occurrence_streams = Some(occurrence_streams);
+        self
+    }
+
+    /// Sets the registry recording the most recent deliveries of each predicate. See
+    /// [DeliveryHistory].
+    pub fn delivery_history(&mut self, delivery_history: DeliveryHistory) -> &mut Self {
+        self.dependencies.delivery_history = Some(delivery_history);
+        self
+    }
+
+    /// Sets the outbox recording deliveries in flight, so that the ones interrupted by a restart
+    /// are resumed when the observer starts. See [DeliveryOutbox].
+    pub fn delivery_outbox(&mut self, delivery_outbox: Arc<dyn DeliveryOutbox>) -> &mut Self {
+        self.dependencies.delivery_outbox = Some(delivery_outbox);
+        self
+    }
+
+    /// Sets the store persisting the number of occurrences of each predicate, so that
+    /// `expire_after_occurrence` survives restarts. See [OccurrenceCountStore].
+    pub fn occurrence_count_store(
+        &mut self,
+        occurrence_count_store: Arc<dyn OccurrenceCountStore>,
+    ) -> &mut Self {
+        self.dependencies.occurrence_count_store = Some(occurrence_count_store);
+        self
+    }
+
+    /// Sets the store the Bitcoin blocks evicted from the observer's cache are spilled to. See
+    /// [BitcoinBlockSpillStore].
+    pub fn bitcoin_block_spill_store(
+        &mut self,
+        bitcoin_block_spill_store: Arc<dyn BitcoinBlockSpillStore>,
+    ) -> &mut Self {
+        self.dependencies.bitcoin_block_spill_store = Some(bitcoin_block_spill_store);
+        self
+    }
+
     /// Sets the Stacks startup context. See [StacksObserverStartupContext].
     pub fn stacks_startup_context(&mut self, context: StacksObserverStartupContext) -> &mut Self {
         self.stacks_startup_context = Some(context);
         self
     }
 
+    /// Returns a handle to subscribe to typed events of the observer, such as
+    /// [ObserverSubscriptions::subscribe_bitcoin]. Events are still sent to `observer_events_tx`
+    /// when it is set.
+    pub fn subscriptions(&mut self) -> ObserverSubscriptions {
+        self.subscriptions
+            .get_or_insert_with(ObserverSubscriptions::new)
+            .clone()
+    }
+
+    /// Sets the number of events buffered for each subscriber created afterwards, see
+    /// [ObserverSubscriptions::set_capacity].
+    pub fn subscription_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.subscriptions().set_capacity(capacity);
+        self
+    }
+
+    /// Streams the Bitcoin chain events evaluated by the observer, see
+    /// [ObserverSubscriptions::bitcoin_events].
+    pub fn bitcoin_events(&mut self) -> impl Stream<Item = BitcoinChainEvent> + Send + Unpin {
+        self.subscriptions().bitcoin_events()
+    }
+
+    /// Streams the Stacks chain events evaluated by the observer, see
+    /// [ObserverSubscriptions::stacks_events].
+    pub fn stacks_events(&mut self) -> impl Stream<Item = StacksChainEvent> + Send + Unpin {
+        self.subscriptions().stacks_events()
+    }
+
     /// Starts the event observer, calling [start_event_observer]. This function consumes the
     /// [EventObserverBuilder] and spawns a new thread to run the observer.
     pub fn start(self) -> Result<(), Box<dyn Error>> {
+        let observer_events_tx = match self.subscriptions {
+            Some(subscriptions) => {
+                let capacity = self
+                    .observer_events_tx
+                    .as_ref()
+                    .and_then(|tx| tx.capacity())
+                    .unwrap_or(DEFAULT_OBSERVER_EVENTS_CAPACITY);
+                let (tx, rx) = bounded_channel(capacity, FullChannelPolicy::Block);
+                let observer_events_tx = self.observer_events_tx;
+                hiro_system_kit::thread_named("Observer subscriptions dispatcher")
+                    .spawn(move || {
+                        while let Ok(event) = rx.recv() {
+                            subscriptions.dispatch(&event);
+                            if let Some(ref tx) = observer_events_tx {
+                                let _ = tx.send(event);
+                            }
+                        }
+                    })
+                    .expect("unable to spawn thread");
+                Some(tx)
+            }
+            None => self.observer_events_tx,
+        };
         start_event_observer(
             self.config,
             self.observer_commands_tx,
             self.observer_commands_rx,
-            self.observer_events_tx,
+            observer_events_tx,
             self.observer_sidecar,
+            self.dependencies,
             self.stacks_startup_context,
             self.ctx,
         )
@@ -791,18 +1482,28 @@ impl EventObserverBuilder {
 /// Spawns a thread to observe blockchain events. Use [EventObserverBuilder] to configure easily.
 pub fn start_event_observer(
     config: EventObserverConfig,
-    observer_commands_tx: Sender<ObserverCommand>,
+    observer_commands_tx: SyncSender<ObserverCommand>,
     observer_commands_rx: Receiver<ObserverCommand>,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
     observer_sidecar: Option<ObserverSidecar>,
+    dependencies: ObserverDependencies,
     stacks_startup_context: Option<StacksObserverStartupContext>,
     ctx: Context,
 ) -> Result<(), Box<dyn Error>> {
     match config.bitcoin_block_signaling {
-        BitcoinBlockSignaling::ZeroMQ(ref url) => {
-            ctx.try_log(|logger| {
-                slog::info!(logger, "Observing Bitcoin chain events via ZeroMQ: {}", url)
-            });
+        BitcoinBlockSignaling::ZeroMQ(_) | BitcoinBlockSignaling::Polling(_) => {
+            match config.bitcoin_block_signaling {
+                BitcoinBlockSignaling::ZeroMQ(ref url) => ctx.try_log(|logger| {
+                    slog::info!(logger, "Observing Bitcoin chain events via ZeroMQ: {}", url)
+                }),
+                _ => ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Observing Bitcoin chain events by polling bitcoind: {}",
+                        config.bitcoind_rpc_url
+                    )
+                }),
+            }
             let context_cloned = ctx.clone();
             let event_observer_config_moved = config.clone();
             let observer_commands_tx_moved = observer_commands_tx.clone();
@@ -814,6 +1515,7 @@ pub fn start_event_observer(
                         observer_commands_rx,
                         observer_events_tx.clone(),
                         observer_sidecar,
+                        dependencies,
                         context_cloned.clone(),
                     );
                     match hiro_system_kit::nestable_block_on(future) {
@@ -826,6 +1528,9 @@ pub fn start_event_observer(
                                         "Chainhook event observer thread failed with error: {e}",
                                     )
                                 });
+                                let _ = tx.send(ObserverEvent::Fatal(format!(
+                                    "Chainhook event observer thread failed with error: {e}"
+                                )));
                                 let _ = tx.send(ObserverEvent::Terminate);
                             }
                         }
@@ -847,6 +1552,7 @@ pub fn start_event_observer(
                         observer_commands_rx,
                         observer_events_tx.clone(),
                         observer_sidecar,
+                        dependencies,
                         stacks_startup_context.unwrap_or_default(),
                         context_cloned.clone(),
                     );
@@ -860,6 +1566,9 @@ pub fn start_event_observer(
                                         "Chainhook event observer thread failed with error: {e}",
                                     )
                                 });
+                                let _ = tx.send(ObserverEvent::Fatal(format!(
+                                    "Chainhook event observer thread failed with error: {e}"
+                                )));
                                 let _ = tx.send(ObserverEvent::Terminate);
                             }
                         }
@@ -885,21 +1594,33 @@ pub fn start_event_observer(
 
 pub async fn start_bitcoin_event_observer(
     config: EventObserverConfig,
-    observer_commands_tx: Sender<ObserverCommand>,
+    observer_commands_tx: SyncSender<ObserverCommand>,
     observer_commands_rx: Receiver<ObserverCommand>,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
     observer_sidecar: Option<ObserverSidecar>,
+    dependencies: ObserverDependencies,
     ctx: Context,
 ) -> Result<(), Box<dyn Error>> {
     let chainhook_store = config.registered_chainhooks.clone();
-    #[cfg(feature = "zeromq")]
-    {
+    if let BitcoinBlockSignaling::Polling(_) = config.bitcoin_block_signaling {
         let ctx_moved = ctx.clone();
         let config_moved = config.clone();
-        let _ = hiro_system_kit::thread_named("ZMQ handler").spawn(move || {
-            let future = zmq::start_zeromq_runloop(&config_moved, observer_commands_tx, &ctx_moved);
+        let _ = hiro_system_kit::thread_named("Bitcoind polling handler").spawn(move || {
+            let future =
+                polling::start_polling_runloop(&config_moved, observer_commands_tx, &ctx_moved);
             hiro_system_kit::nestable_block_on(future);
         });
+    } else {
+        #[cfg(feature = "zeromq")]
+        {
+            let ctx_moved = ctx.clone();
+            let config_moved = config.clone();
+            let _ = hiro_system_kit::thread_named("ZMQ handler").spawn(move || {
+                let future =
+                    zmq::start_zeromq_runloop(&config_moved, observer_commands_tx, &ctx_moved);
+                hiro_system_kit::nestable_block_on(future);
+            });
+        }
     }
 
     let prometheus_monitoring = PrometheusMonitoring::new();
@@ -908,6 +1629,7 @@ pub async fn start_bitcoin_event_observer(
         chainhook_store.bitcoin_chainhooks.len() as u64,
         None,
     );
+    prometheus_monitoring.initialize_namespaces(&chainhook_store);
 
     if let Some(port) = config.prometheus_monitoring_port {
         let registry_moved = prometheus_monitoring.registry.clone();
@@ -919,6 +1641,16 @@ pub async fn start_bitcoin_event_observer(
                 ctx_cloned,
             ));
         });
+        let config_moved = config.clone();
+        let prometheus_monitoring_moved = prometheus_monitoring.clone();
+        let ctx_cloned = ctx.clone();
+        let _ = std::thread::spawn(move || {
+            hiro_system_kit::nestable_block_on(start_monitoring_node_tips(
+                config_moved,
+                prometheus_monitoring_moved,
+                ctx_cloned,
+            ));
+        });
     }
 
     // This loop is used for handling background jobs, emitted by HTTP calls.
@@ -930,6 +1662,7 @@ pub async fn start_bitcoin_event_observer(
         None,
         prometheus_monitoring,
         observer_sidecar,
+        dependencies,
         ctx,
     )
     .await
@@ -937,10 +1670,11 @@ pub async fn start_bitcoin_event_observer(
 
 pub async fn start_stacks_event_observer(
     config: EventObserverConfig,
-    observer_commands_tx: Sender<ObserverCommand>,
+    observer_commands_tx: SyncSender<ObserverCommand>,
     observer_commands_rx: Receiver<ObserverCommand>,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
     observer_sidecar: Option<ObserverSidecar>,
+    dependencies: ObserverDependencies,
     stacks_startup_context: StacksObserverStartupContext,
     ctx: Context,
 ) -> Result<(), Box<dyn Error>> {
@@ -948,26 +1682,24 @@ pub async fn start_stacks_event_observer(
         bitcoind_rpc_url: config.bitcoind_rpc_url.clone(),
         bitcoind_rpc_username: config.bitcoind_rpc_username.clone(),
         bitcoind_rpc_password: config.bitcoind_rpc_password.clone(),
+        bitcoind_rpc_fallbacks: config.bitcoind_rpc_fallbacks.clone(),
+        esplora_api_url: config.esplora_api_url.clone(),
         stacks_network: StacksNetwork::Devnet,
         bitcoin_network: BitcoinNetwork::Regtest,
         bitcoin_block_signaling: config.bitcoin_block_signaling.clone(),
+        bitcoin_confirmation_depth: config.bitcoin_confirmation_depth,
+        stacks_confirmation_depth: config.stacks_confirmation_depth,
+        network_parameters: config.network_parameters.clone(),
+        malformed_stacks_transactions: config.malformed_stacks_transactions,
     };
 
     let mut indexer = Indexer::new(indexer_config.clone());
 
     indexer.seed_stacks_block_pool(stacks_startup_context.block_pool_seed, &ctx);
 
-    let log_level = if config.display_stacks_ingestion_logs {
-        if cfg!(feature = "cli") {
-            LogLevel::Critical
-        } else {
-            LogLevel::Debug
-        }
-    } else {
-        LogLevel::Off
-    };
-
     let ingestion_port = config.get_stacks_node_config().ingestion_port;
+    let ingestion_auth_token = config.get_stacks_node_config().ingestion_auth_token.clone();
+    let ingestion_tls = config.get_stacks_node_config().ingestion_tls.clone();
     let bitcoin_rpc_proxy_enabled = config.bitcoin_rpc_proxy_enabled;
     let bitcoin_config = config.get_bitcoin_config();
 
@@ -975,7 +1707,7 @@ pub async fn start_stacks_event_observer(
 
     let indexer_rw_lock = Arc::new(RwLock::new(indexer));
 
-    let background_job_tx_mutex = Arc::new(Mutex::new(observer_commands_tx.clone()));
+    let background_job_tx = Arc::new(Mutex::new(observer_commands_tx.clone()));
 
     let prometheus_monitoring = PrometheusMonitoring::new();
     prometheus_monitoring.initialize(
@@ -983,6 +1715,7 @@ pub async fn start_stacks_event_observer(
         chainhook_store.bitcoin_chainhooks.len() as u64,
         Some(stacks_startup_context.last_block_height_appended),
     );
+    prometheus_monitoring.initialize_namespaces(&chainhook_store);
 
     if let Some(port) = config.prometheus_monitoring_port {
         let registry_moved = prometheus_monitoring.registry.clone();
@@ -994,59 +1727,37 @@ pub async fn start_stacks_event_observer(
                 ctx_cloned,
             ));
         });
+        let config_moved = config.clone();
+        let prometheus_monitoring_moved = prometheus_monitoring.clone();
+        let ctx_cloned = ctx.clone();
+        let _ = std::thread::spawn(move || {
+            hiro_system_kit::nestable_block_on(start_monitoring_node_tips(
+                config_moved,
+                prometheus_monitoring_moved,
+                ctx_cloned,
+            ));
+        });
     }
 
-    let limits = Limits::default().limit("json", 500.megabytes());
-    let mut shutdown_config = config::Shutdown::default();
-    shutdown_config.ctrlc = false;
-    shutdown_config.grace = 0;
-    shutdown_config.mercy = 0;
-
-    let ingestion_config = Config {
-        port: ingestion_port,
-        workers: 1,
-        address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-        keep_alive: 5,
-        temp_dir: std::env::temp_dir().into(),
-        log_level,
-        cli_colors: false,
-        limits,
-        shutdown: shutdown_config,
-        ..Config::default()
+    let ingestion_state = IngestionState {
+        indexer_rw_lock,
+        background_job_tx,
+        bitcoin_config,
+        prometheus_monitoring: prometheus_monitoring.clone(),
+        health_checks: stacks_startup_context.health_checks,
+        auth_token: ingestion_auth_token,
+        ctx: ctx.clone(),
     };
-
-    let mut routes = rocket::routes![
-        http::handle_ping,
-        http::handle_new_bitcoin_block,
-        http::handle_new_stacks_block,
-        http::handle_new_microblocks,
-        http::handle_new_mempool_tx,
-        http::handle_drop_mempool_tx,
-        http::handle_new_attachement,
-        http::handle_mined_block,
-        http::handle_mined_microblock,
-    ];
-
-    if bitcoin_rpc_proxy_enabled {
-        routes.append(&mut routes![http::handle_bitcoin_rpc_call]);
-        routes.append(&mut routes![http::handle_bitcoin_wallet_rpc_call]);
-    }
-
-    let ctx_cloned = ctx.clone();
-    let ignite = rocket::custom(ingestion_config)
-        .manage(indexer_rw_lock)
-        .manage(background_job_tx_mutex)
-        .manage(bitcoin_config)
-        .manage(ctx_cloned)
-        .manage(prometheus_monitoring.clone())
-        .mount("/", routes)
-        .ignite()
-        .await?;
-    let ingestion_shutdown = Some(ignite.shutdown());
-
-    let _ = std::thread::spawn(move || {
-        let _ = hiro_system_kit::nestable_block_on(ignite.launch());
-    });
+    let ingestion_shutdown = Some(
+        start_ingestion_server(
+            ingestion_state,
+            ingestion_port,
+            ingestion_tls,
+            bitcoin_rpc_proxy_enabled,
+            config.display_stacks_ingestion_logs,
+        )
+        .await?,
+    );
 
     // This loop is used for handling background jobs, emitted by HTTP calls.
     start_observer_commands_handler(
@@ -1057,6 +1768,7 @@ pub async fn start_stacks_event_observer(
         ingestion_shutdown,
         prometheus_monitoring,
         observer_sidecar,
+        dependencies,
         ctx,
     )
     .await
@@ -1088,14 +1800,7 @@ pub fn gather_proofs<'a>(
     config: &EventObserverConfig,
     ctx: &Context,
 ) {
-    let bitcoin_client_rpc = Client::new(
-        &config.bitcoind_rpc_url,
-        Auth::UserPass(
-            config.bitcoind_rpc_username.to_string(),
-            config.bitcoind_rpc_password.to_string(),
-        ),
-    )
-    .expect("unable to build http client");
+    let bitcoin_config = config.get_bitcoin_config();
 
     for (transactions, block) in trigger.apply.iter() {
         for transaction in transactions.iter() {
@@ -1107,16 +1812,31 @@ pub fn gather_proofs<'a>(
                         transaction.transaction_identifier.hash
                     )
                 });
-                match get_bitcoin_proof(
-                    &bitcoin_client_rpc,
-                    &transaction.transaction_identifier,
-                    &block.block_identifier,
-                ) {
-                    Ok(proof) => {
-                        proofs.insert(&transaction.transaction_identifier, proof);
-                    }
-                    Err(e) => {
-                        ctx.try_log(|logger| slog::warn!(logger, "{e}"));
+                // Every endpoint gets a chance to provide the proof before giving up
+                for _ in 0..bitcoin_config.endpoints_count() {
+                    let endpoint_config = bitcoin_config.with_healthy_endpoint();
+                    let bitcoin_client_rpc = Client::new(
+                        &endpoint_config.rpc_url,
+                        Auth::UserPass(
+                            endpoint_config.username.to_string(),
+                            endpoint_config.password.to_string(),
+                        ),
+                    )
+                    .expect("unable to build http client");
+                    match get_bitcoin_proof(
+                        &bitcoin_client_rpc,
+                        &transaction.transaction_identifier,
+                        &block.block_identifier,
+                    ) {
+                        Ok(proof) => {
+                            record_endpoint_success(&endpoint_config.rpc_url);
+                            proofs.insert(&transaction.transaction_identifier, proof);
+                            break;
+                        }
+                        Err(e) => {
+                            record_endpoint_failure(&endpoint_config.rpc_url);
+                            ctx.try_log(|logger| slog::warn!(logger, "{e}"));
+                        }
                     }
                 }
             }
@@ -1133,36 +1853,112 @@ pub async fn start_observer_commands_handler(
     config: EventObserverConfig,
     mut chainhook_store: ChainhookStore,
     observer_commands_rx: Receiver<ObserverCommand>,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
-    ingestion_shutdown: Option<Shutdown>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
+    mut ingestion_shutdown: Option<IngestionShutdown>,
     prometheus_monitoring: PrometheusMonitoring,
     observer_sidecar: Option<ObserverSidecar>,
+    dependencies: ObserverDependencies,
     ctx: Context,
 ) -> Result<(), Box<dyn Error>> {
-    let mut chainhooks_occurrences_tracker: HashMap<String, u64> = HashMap::new();
+    let ObserverDependencies {
+        occurrence_streams,
+        delivery_history,
+        delivery_outbox,
+        occurrence_count_store,
+        bitcoin_block_spill_store,
+    } = dependencies;
+    let mut chainhooks_occurrences_tracker = OccurrencesTracker::new(occurrence_count_store, &ctx);
     let networks = (&config.bitcoin_network, &config.stacks_network);
-    let mut bitcoin_block_store: HashMap<BlockIdentifier, BitcoinBlockDataCached> = HashMap::new();
+    let mut bitcoin_block_store =
+        BitcoinBlockCache::new(config.bitcoin_block_cache_size, bitcoin_block_spill_store);
     let http_client = build_http_client();
     let store_update_required = observer_sidecar
         .as_ref()
-        .and_then(|s| s.bitcoin_blocks_mutator.as_ref())
-        .is_some();
+        .map(|s| s.mutates_blocks())
+        .unwrap_or(false);
+
+    if let Some(ref outbox) = delivery_outbox {
+        resume_pending_deliveries(outbox, &config, &ctx).await;
+    }
+
+    let mut circuit_breakers = config
+        .predicates_config
+        .circuit_breaker
+        .as_ref()
+        .map(DeliveryCircuitBreakers::new);
+    // Predicates paused with `PausePredicate`, which must not be enabled by a scan completing
+    let mut requested_pauses: HashSet<String> = HashSet::new();
+    let mut heartbeats = PredicateHeartbeats::default();
+    // Set once termination is requested, while the commands still queued are processed
+    let mut drain_deadline: Option<Instant> = None;
 
     loop {
-        let command = match observer_commands_rx.recv() {
-            Ok(cmd) => cmd,
-            Err(e) => {
-                ctx.try_log(|logger| {
-                    slog::crit!(logger, "Error: broken channel {}", e.to_string())
-                });
-                break;
+        let command = if let Some(deadline) = drain_deadline {
+            match receive_drained_command(&observer_commands_rx, deadline, &ctx) {
+                Some(cmd) => cmd,
+                None => break,
+            }
+        } else {
+            if let Some(ref mut breakers) = circuit_breakers {
+                for mut predicate in breakers
+                    .probe_open_circuits(&config.predicates_config, &ctx)
+                    .await
+                {
+                    ctx.try_log(|logger| {
+                        slog::info!(logger, "Resuming predicate {}", predicate.uuid())
+                    });
+                    if let Some(ref tx) = observer_events_tx {
+                        let _ = tx.send(ObserverEvent::PredicateResumed(predicate));
+                    } else {
+                        chainhook_store.enable_instance(&mut predicate);
+                    }
+                }
+            }
+            heartbeats.sync(&chainhook_store, Instant::now());
+            heartbeats
+                .send_due_heartbeats(&config.predicates_config, &ctx)
+                .await;
+            let timeout = [
+                circuit_breakers.as_ref().map(|b| b.probe_interval()),
+                heartbeats.next_due_in(Instant::now()),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            match receive_observer_command(&observer_commands_rx, timeout) {
+                Ok(Some(cmd)) => cmd,
+                // Timed out, time to probe the open circuits or send heartbeats again
+                Ok(None) => continue,
+                Err(e) => {
+                    ctx.try_log(|logger| slog::crit!(logger, "Error: broken channel {}", e));
+                    break;
+                }
             }
         };
         match command {
             ObserverCommand::Terminate => {
-                break;
+                if drain_deadline.is_some() {
+                    continue;
+                }
+                let timeout = config.predicates_config.get_shutdown_drain_timeout();
+                if timeout.is_zero() {
+                    break;
+                }
+                ctx.try_log(|logger| {
+                    slog::info!(
+                        logger,
+                        "Processing pending commands before terminating (timeout: {}ms)",
+                        timeout.as_millis()
+                    )
+                });
+                // Stop the ingestion of new blocks, so that the queue drains
+                if let Some(ingestion_shutdown) = ingestion_shutdown.take() {
+                    ingestion_shutdown.notify();
+                }
+                drain_deadline = Some(Instant::now() + timeout);
             }
             ObserverCommand::ProcessBitcoinBlock(mut block_data) => {
+                prometheus_monitoring.btc_metrics_event_ingested();
                 let block_hash = block_data.hash.to_string();
                 let mut attempts = 0;
                 let max_attempts = 10;
@@ -1170,6 +1966,7 @@ pub async fn start_observer_commands_handler(
                     match standardize_bitcoin_block(
                         block_data.clone(),
                         &config.bitcoin_network,
+                        &config.get_network_parameters(),
                         &ctx,
                     ) {
                         Ok(block) => break Some(block),
@@ -1182,6 +1979,7 @@ pub async fn start_observer_commands_handler(
                                 slog::warn!(logger, "Error standardizing block: {}", e)
                             });
                             if refetch_block {
+                                prometheus_monitoring.btc_metrics_block_refetched();
                                 block_data = match download_and_parse_block_with_retry(
                                     &http_client,
                                     &block_hash,
@@ -1218,20 +2016,20 @@ pub async fn start_observer_commands_handler(
                 };
 
                 bitcoin_block_store.insert(
-                    block.block_identifier.clone(),
                     BitcoinBlockDataCached {
                         block,
                         processed_by_sidecar: false,
                     },
+                    &ctx,
                 );
             }
             ObserverCommand::CacheBitcoinBlock(block) => {
                 bitcoin_block_store.insert(
-                    block.block_identifier.clone(),
                     BitcoinBlockDataCached {
                         block,
                         processed_by_sidecar: false,
                     },
+                    &ctx,
                 );
             }
             ObserverCommand::PropagateBitcoinChainEvent(blockchain_event) => {
@@ -1254,13 +2052,14 @@ pub async fn start_observer_commands_handler(
 
                             if store_update_required {
                                 let Some(block) =
-                                    bitcoin_block_store.remove(&header.block_identifier)
+                                    bitcoin_block_store.remove(&header.block_identifier, &ctx)
                                 else {
                                     continue;
                                 };
                                 blocks_to_mutate.push(block);
                             } else {
-                                let Some(cache) = bitcoin_block_store.get(&header.block_identifier)
+                                let Some(cache) =
+                                    bitcoin_block_store.get(&header.block_identifier, &ctx)
                                 else {
                                     continue;
                                 };
@@ -1269,20 +2068,17 @@ pub async fn start_observer_commands_handler(
                         }
 
                         if let Some(ref sidecar) = observer_sidecar {
-                            let updated_blocks = sidecar.perform_bitcoin_sidecar_mutations(
-                                blocks_to_mutate,
-                                vec![],
-                                &ctx,
-                            );
+                            let updated_blocks = sidecar
+                                .perform_bitcoin_sidecar_mutations(blocks_to_mutate, vec![], &ctx)
+                                .await;
                             for cache in updated_blocks.into_iter() {
-                                bitcoin_block_store
-                                    .insert(cache.block.block_identifier.clone(), cache.clone());
+                                bitcoin_block_store.insert(cache.clone(), &ctx);
                                 new_blocks.push(cache.block);
                             }
                         }
 
                         for header in data.confirmed_headers.iter() {
-                            match bitcoin_block_store.remove(&header.block_identifier) {
+                            match bitcoin_block_store.remove(&header.block_identifier, &ctx) {
                                 Some(res) => {
                                     confirmed_blocks.push(res.block);
                                 }
@@ -1322,13 +2118,14 @@ pub async fn start_observer_commands_handler(
 
                             if store_update_required {
                                 let Some(block) =
-                                    bitcoin_block_store.remove(&header.block_identifier)
+                                    bitcoin_block_store.remove(&header.block_identifier, &ctx)
                                 else {
                                     continue;
                                 };
                                 blocks_to_mutate.push(block);
                             } else {
-                                let Some(cache) = bitcoin_block_store.get(&header.block_identifier)
+                                let Some(cache) =
+                                    bitcoin_block_store.get(&header.block_identifier, &ctx)
                                 else {
                                     continue;
                                 };
@@ -1339,7 +2136,7 @@ pub async fn start_observer_commands_handler(
                         let mut blocks_ids_to_rollback: Vec<BlockIdentifier> = vec![];
 
                         for header in data.headers_to_rollback.iter() {
-                            match bitcoin_block_store.get(&header.block_identifier) {
+                            match bitcoin_block_store.get(&header.block_identifier, &ctx) {
                                 Some(cache) => {
                                     blocks_ids_to_rollback.push(header.block_identifier.clone());
                                     blocks_to_rollback.push(cache.block.clone());
@@ -1357,20 +2154,21 @@ pub async fn start_observer_commands_handler(
                         }
 
                         if let Some(ref sidecar) = observer_sidecar {
-                            let updated_blocks = sidecar.perform_bitcoin_sidecar_mutations(
-                                blocks_to_mutate,
-                                blocks_ids_to_rollback,
-                                &ctx,
-                            );
+                            let updated_blocks = sidecar
+                                .perform_bitcoin_sidecar_mutations(
+                                    blocks_to_mutate,
+                                    blocks_ids_to_rollback,
+                                    &ctx,
+                                )
+                                .await;
                             for cache in updated_blocks.into_iter() {
-                                bitcoin_block_store
-                                    .insert(cache.block.block_identifier.clone(), cache.clone());
+                                bitcoin_block_store.insert(cache.clone(), &ctx);
                                 blocks_to_apply.push(cache.block);
                             }
                         }
 
                         for header in data.confirmed_headers.iter() {
-                            match bitcoin_block_store.remove(&header.block_identifier) {
+                            match bitcoin_block_store.remove(&header.block_identifier, &ctx) {
                                 Some(res) => {
                                     confirmed_blocks.push(res.block);
                                 }
@@ -1411,11 +2209,12 @@ pub async fn start_observer_commands_handler(
                 };
 
                 if let Some(ref sidecar) = observer_sidecar {
-                    sidecar.notify_chain_event(&chain_event, &ctx)
+                    sidecar.notify_chain_event(&chain_event, &ctx).await
                 }
                 // process hooks
                 let mut hooks_ids_to_deregister = vec![];
                 let mut requests = vec![];
+                let mut deliveries = vec![];
                 let mut report = PredicateEvaluationReport::new();
 
                 let bitcoin_chainhooks = chainhook_store
@@ -1440,6 +2239,7 @@ pub async fn start_observer_commands_handler(
                     );
 
                 for (uuid, block_identifier) in predicates_evaluated.into_iter() {
+                    heartbeats.record_evaluation(uuid, block_identifier.index);
                     report.track_evaluation(uuid, block_identifier);
                 }
                 for (uuid, block_identifier) in predicates_expired.into_iter() {
@@ -1464,10 +2264,18 @@ pub async fn start_observer_commands_handler(
 
                 let mut chainhooks_to_trigger = vec![];
 
-                for trigger in predicates_triggered.into_iter() {
-                    let mut total_occurrences: u64 = *chainhooks_occurrences_tracker
-                        .get(&trigger.chainhook.uuid)
-                        .unwrap_or(&0);
+                // Blocks applied again after a reorg are delivered under new ids
+                let reorg_sequence = match chain_event {
+                    BitcoinChainEvent::ChainUpdatedWithReorg(_) => {
+                        next_reorg_sequence(&delivery_outbox, "bitcoin", &ctx)
+                    }
+                    BitcoinChainEvent::ChainUpdatedWithBlocks(_) => 0,
+                };
+                for mut trigger in predicates_triggered.into_iter() {
+                    trigger.confirmation_depth = Some(config.get_bitcoin_confirmation_depth());
+                    trigger.reorg_sequence = reorg_sequence;
+                    let mut total_occurrences =
+                        chainhooks_occurrences_tracker.get(&trigger.chainhook.uuid);
                     // todo: this currently is only additive, and an occurrence means we match a chain event,
                     // rather than the number of blocks. Should we instead add to the total occurrences for
                     // every apply block, and subtract for every rollback? If we did this, we could set the
@@ -1477,8 +2285,11 @@ pub async fn start_observer_commands_handler(
 
                     let limit = trigger.chainhook.expire_after_occurrence.unwrap_or(0);
                     if limit == 0 || total_occurrences <= limit {
-                        chainhooks_occurrences_tracker
-                            .insert(trigger.chainhook.uuid.clone(), total_occurrences);
+                        chainhooks_occurrences_tracker.set(
+                            &trigger.chainhook.uuid,
+                            total_occurrences,
+                            &ctx,
+                        );
                         chainhooks_to_trigger.push(trigger);
                     } else {
                         hooks_ids_to_deregister.push(trigger.chainhook.uuid.clone());
@@ -1507,6 +2318,59 @@ pub async fn start_observer_commands_handler(
                 }
                 for chainhook_to_trigger in chainhooks_to_trigger.into_iter() {
                     let predicate_uuid = &chainhook_to_trigger.chainhook.uuid;
+                    let delivery_id = chainhook_to_trigger.get_delivery_id();
+                    let hook_action = chainhook_to_trigger.get_action();
+                    if PendingDelivery::is_supported_action(hook_action)
+                        && is_already_delivered(&delivery_outbox, &delivery_id, &ctx)
+                    {
+                        ctx.try_log(|logger| {
+                            slog::info!(
+                                logger,
+                                "Skipping delivery {} of predicate {}, already delivered",
+                                delivery_id,
+                                predicate_uuid
+                            )
+                        });
+                        continue;
+                    }
+                    if let Some(ref streams) = occurrence_streams {
+                        if streams.is_streamed(predicate_uuid) {
+                            if let Ok(payload) = serialize_bitcoin_payload_for_delivery(
+                                &chainhook_to_trigger,
+                                &proofs,
+                            ) {
+                                streams.publish(predicate_uuid, payload.to_string());
+                            }
+                        }
+                    }
+                    let outbox_id = match delivery_outbox {
+                        Some(_) => record_pending_delivery(
+                            &delivery_outbox,
+                            PendingDelivery::new(
+                                delivery_id.clone(),
+                                predicate_uuid,
+                                hook_action,
+                                chainhook_to_trigger.get_tip_block_identifier(),
+                                serialize_bitcoin_payload_for_delivery(
+                                    &chainhook_to_trigger,
+                                    &proofs,
+                                )
+                                .unwrap_or(JsonValue::Null),
+                                &chainhook_to_trigger.chainhook.payload_envelope,
+                            ),
+                            &ctx,
+                        ),
+                        None => None,
+                    };
+                    let policy = DeliveryPolicy {
+                        retry_policy: hook_action.retry_policy().cloned().unwrap_or_default(),
+                        dead_letter: hook_action.dead_letter().cloned(),
+                        outbox_id,
+                        delivery_id,
+                        require_ack: hook_action.requires_ack(),
+                        endpoint: DeliveryPolicy::endpoint_of(hook_action),
+                        rate_limiter: action_rate_limiter(hook_action, &config.predicates_config),
+                    };
                     match handle_bitcoin_hook_action(chainhook_to_trigger, &proofs, &config) {
                         Err(e) => {
                             // todo: we may want to set predicates that reach this branch as interrupted,
@@ -1519,16 +2383,37 @@ pub async fn start_observer_commands_handler(
                                     e
                                 )
                             });
+                            complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
                         }
                         Ok(BitcoinChainhookOccurrence::Http(request, data)) => {
-                            requests.push((request, data));
+                            requests.push((request, data, policy));
                         }
-                        Ok(BitcoinChainhookOccurrence::File(_path, _bytes)) => {
-                            ctx.try_log(|logger| {
-                                slog::warn!(logger, "Writing to disk not supported in server mode")
-                            })
+                        Ok(BitcoinChainhookOccurrence::ObjectStore(upload, data)) => {
+                            deliveries.push((
+                                OccurrenceDelivery::ObjectStore(upload),
+                                data,
+                                policy,
+                            ));
+                        }
+                        Ok(BitcoinChainhookOccurrence::Kafka(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Kafka(message), data, policy));
+                        }
+                        Ok(BitcoinChainhookOccurrence::Nats(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Nats(message), data, policy));
+                        }
+                        Ok(BitcoinChainhookOccurrence::Aws(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Aws(message), data, policy));
+                        }
+                        Ok(BitcoinChainhookOccurrence::Postgres(record, data)) => {
+                            deliveries.push((OccurrenceDelivery::Postgres(record), data, policy));
+                        }
+                        Ok(BitcoinChainhookOccurrence::File(hook, bytes, data)) => {
+                            deliveries.push((OccurrenceDelivery::File(hook, bytes), data, policy));
                         }
                         Ok(BitcoinChainhookOccurrence::Data(payload)) => {
+                            if let Some(ref sidecar) = observer_sidecar {
+                                sidecar.handle_bitcoin_occurrence(&payload, &ctx).await;
+                            }
                             if let Some(ref tx) = observer_events_tx {
                                 let _ = tx.send(ObserverEvent::BitcoinPredicateTriggered(payload));
                             }
@@ -1544,12 +2429,11 @@ pub async fn start_observer_commands_handler(
                 });
 
                 for hook_uuid in hooks_ids_to_deregister.iter() {
-                    if chainhook_store
-                        .deregister_bitcoin_hook(hook_uuid.clone())
-                        .is_some()
-                    {
-                        prometheus_monitoring.btc_metrics_deregister_predicate();
+                    if let Some(hook) = chainhook_store.deregister_bitcoin_hook(hook_uuid.clone()) {
+                        prometheus_monitoring
+                            .btc_metrics_deregister_predicate(hook.namespace.as_deref());
                     }
+                    chainhooks_occurrences_tracker.forget(hook_uuid, &ctx);
                     if let Some(ref tx) = observer_events_tx {
                         let _ = tx.send(ObserverEvent::PredicateDeregistered(
                             PredicateDeregisteredEvent {
@@ -1560,23 +2444,195 @@ pub async fn start_observer_commands_handler(
                     }
                 }
 
-                for (request, data) in requests.into_iter() {
-                    match send_request(request, 3, 1, &ctx).await {
-                        Ok(_) => {
+                for (request, data, policy) in requests.into_iter() {
+                    if let (Some(breakers), Some(endpoint)) =
+                        (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                    {
+                        if breakers.is_open(&endpoint.url) {
+                            // Don't hit an endpoint known to be failing
+                            pause_predicate(
+                                breakers,
+                                endpoint,
+                                chainhook_store
+                                    .disable_bitcoin_hook(&data.chainhook.uuid)
+                                    .map(ChainhookInstance::Bitcoin),
+                                format!("circuit of endpoint {} is open", endpoint.url),
+                                &observer_events_tx,
+                                &ctx,
+                            );
+                            release_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
+                            continue;
+                        }
+                    }
+                    let (target, payload_size) = describe_http_request(&request);
+                    let started_at = Instant::now();
+                    let last_status = AtomicU16::new(0);
+                    let res = send_http_occurrence_recording_status(
+                        request,
+                        policy.require_ack,
+                        &policy.retry_policy,
+                        &policy.rate_limiter,
+                        &last_status,
+                        &ctx,
+                    )
+                    .await;
+                    if let Some(ref history) = delivery_history {
+                        history.record(
+                            &data.chainhook.uuid,
+                            DeliveryRecord::new(
+                                &policy.delivery_id,
+                                target,
+                                res.as_ref().err().cloned(),
+                                Some(last_status.load(Ordering::Relaxed)).filter(|s| *s != 0),
+                                started_at.elapsed().as_millis() as u64,
+                                payload_size,
+                            ),
+                        );
+                    }
+                    match res {
+                        Ok(cursor) => {
+                            heartbeats.record_delivery(&data.chainhook.uuid, Instant::now());
+                            if let (Some(breakers), Some(endpoint)) =
+                                (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                            {
+                                breakers.record_success(&endpoint.url);
+                            }
                             if let Some(ref tx) = observer_events_tx {
+                                if let Some(cursor) = cursor {
+                                    let _ = tx.send(ObserverEvent::PredicateAcknowledged(
+                                        PredicateAcknowledgedData {
+                                            predicate_key: data.chainhook.key(),
+                                            cursor,
+                                        },
+                                    ));
+                                }
                                 let _ = tx.send(ObserverEvent::BitcoinPredicateTriggered(data));
                             }
                         }
                         Err(e) => {
-                            chainhook_store.deregister_bitcoin_hook(data.chainhook.uuid.clone());
+                            let mut failure_tolerated = false;
+                            if let (Some(breakers), Some(endpoint)) =
+                                (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                            {
+                                if breakers.record_failure(endpoint) {
+                                    // The occurrence is redelivered once the predicate resumes
+                                    pause_predicate(
+                                        breakers,
+                                        endpoint,
+                                        chainhook_store
+                                            .disable_bitcoin_hook(&data.chainhook.uuid)
+                                            .map(ChainhookInstance::Bitcoin),
+                                        e,
+                                        &observer_events_tx,
+                                        &ctx,
+                                    );
+                                    release_pending_delivery(
+                                        &delivery_outbox,
+                                        &policy.outbox_id,
+                                        &ctx,
+                                    );
+                                    continue;
+                                }
+                                // Failures are tolerated until the circuit opens
+                                failure_tolerated = !policy.require_ack;
+                            }
+                            let dead_lettered = match policy.dead_letter {
+                                None if failure_tolerated => Ok(()),
+                                _ => {
+                                    dead_letter_failed_delivery(
+                                        policy.dead_letter.as_ref(),
+                                        &data.chainhook.uuid,
+                                        &policy.delivery_id,
+                                        e,
+                                        &data,
+                                        &config.predicates_config,
+                                        &ctx,
+                                    )
+                                    .await
+                                }
+                            };
+                            if let Err(e) = dead_lettered {
+                                if policy.require_ack {
+                                    // Deliveries resume from the last acknowledged cursor
+                                    if let Some(hook) =
+                                        chainhook_store.disable_bitcoin_hook(&data.chainhook.uuid)
+                                    {
+                                        if let Some(ref tx) = observer_events_tx {
+                                            let _ = tx.send(ObserverEvent::PredicateDeliveryFailed(
+                                                PredicateDeliveryFailedData {
+                                                    predicate: ChainhookInstance::Bitcoin(hook),
+                                                    error: e,
+                                                },
+                                            ));
+                                        }
+                                    }
+                                    complete_pending_delivery(
+                                        &delivery_outbox,
+                                        &policy.outbox_id,
+                                        &ctx,
+                                    );
+                                    continue;
+                                }
+                                chainhook_store
+                                    .deregister_bitcoin_hook(data.chainhook.uuid.clone());
+                                if let Some(ref tx) = observer_events_tx {
+                                    let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
+                                        predicate_key: data.chainhook.key(),
+                                        error: format!("Unable to evaluate predicate on Bitcoin chainstate: {}", e)
+                                    }));
+                                }
+                            }
+                        }
+                    }
+                    complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
+                }
+
+                for (delivery, data, policy) in deliveries.into_iter() {
+                    let started_at = Instant::now();
+                    let res = delivery.send(&policy.retry_policy, &ctx).await;
+                    if let Some(ref history) = delivery_history {
+                        history.record(
+                            &data.chainhook.uuid,
+                            DeliveryRecord::new(
+                                &policy.delivery_id,
+                                delivery.target(),
+                                res.as_ref().err().cloned(),
+                                None,
+                                started_at.elapsed().as_millis() as u64,
+                                delivery.payload_size(),
+                            ),
+                        );
+                    }
+                    match res {
+                        Ok(_) => {
                             if let Some(ref tx) = observer_events_tx {
-                                let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
-                                    predicate_key: ChainhookInstance::bitcoin_key(&data.chainhook.uuid),
-                                    error: format!("Unable to evaluate predicate on Bitcoin chainstate: {}", e)
-                                }));
+                                let _ = tx.send(ObserverEvent::BitcoinPredicateTriggered(data));
+                            }
+                        }
+                        Err(e) => {
+                            if let Err(e) = dead_letter_failed_delivery(
+                                policy.dead_letter.as_ref(),
+                                &data.chainhook.uuid,
+                                &policy.delivery_id,
+                                e,
+                                &data,
+                                &config.predicates_config,
+                                &ctx,
+                            )
+                            .await
+                            {
+                                chainhook_store
+                                    .deregister_bitcoin_hook(data.chainhook.uuid.clone());
+                                if let Some(ref tx) = observer_events_tx {
+                                    let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
+                                        predicate_key: data.chainhook.key(),
+                                        error: format!("Unable to deliver occurrence: {}", e)
+                                    }));
+                                }
                             }
                         }
                     }
+                    complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
                 }
 
                 prometheus_monitoring.btc_metrics_block_evaluated(new_tip);
@@ -1591,6 +2647,7 @@ pub async fn start_observer_commands_handler(
                 });
                 let mut hooks_ids_to_deregister = vec![];
                 let mut requests = vec![];
+                let mut deliveries = vec![];
                 let mut report = PredicateEvaluationReport::new();
 
                 let stacks_chainhooks = chainhook_store
@@ -1642,13 +2699,16 @@ pub async fn start_observer_commands_handler(
                 };
 
                 // process hooks
+                let bitcoin_anchors = BitcoinAnchorContext::new(bitcoin_block_store.blocks());
                 let (predicates_triggered, predicates_evaluated, predicates_expired) =
                     evaluate_stacks_chainhooks_on_chain_event(
                         &chain_event,
                         stacks_chainhooks,
+                        &bitcoin_anchors,
                         &ctx,
                     );
                 for (uuid, block_identifier) in predicates_evaluated.into_iter() {
+                    heartbeats.record_evaluation(uuid, block_identifier.index);
                     report.track_evaluation(uuid, block_identifier);
                 }
                 for (uuid, block_identifier) in predicates_expired.into_iter() {
@@ -1672,16 +2732,29 @@ pub async fn start_observer_commands_handler(
 
                 let mut chainhooks_to_trigger = vec![];
 
-                for trigger in predicates_triggered.into_iter() {
-                    let mut total_occurrences: u64 = *chainhooks_occurrences_tracker
-                        .get(&trigger.chainhook.uuid)
-                        .unwrap_or(&0);
+                // Blocks applied again after a reorg are delivered under new ids
+                let reorg_sequence = match chain_event {
+                    StacksChainEvent::ChainUpdatedWithReorg(_)
+                    | StacksChainEvent::ChainUpdatedWithMicroblocksReorg(_) => {
+                        next_reorg_sequence(&delivery_outbox, "stacks", &ctx)
+                    }
+                    StacksChainEvent::ChainUpdatedWithBlocks(_)
+                    | StacksChainEvent::ChainUpdatedWithMicroblocks(_) => 0,
+                };
+                for mut trigger in predicates_triggered.into_iter() {
+                    trigger.confirmation_depth = Some(config.get_stacks_confirmation_depth());
+                    trigger.reorg_sequence = reorg_sequence;
+                    let mut total_occurrences =
+                        chainhooks_occurrences_tracker.get(&trigger.chainhook.uuid);
                     total_occurrences += 1;
 
                     let limit = trigger.chainhook.expire_after_occurrence.unwrap_or(0);
                     if limit == 0 || total_occurrences <= limit {
-                        chainhooks_occurrences_tracker
-                            .insert(trigger.chainhook.uuid.clone(), total_occurrences);
+                        chainhooks_occurrences_tracker.set(
+                            &trigger.chainhook.uuid,
+                            total_occurrences,
+                            &ctx,
+                        );
                         chainhooks_to_trigger.push(trigger);
                     } else {
                         hooks_ids_to_deregister.push(trigger.chainhook.uuid.clone());
@@ -1696,6 +2769,61 @@ pub async fn start_observer_commands_handler(
                 let proofs = HashMap::new();
                 for chainhook_to_trigger in chainhooks_to_trigger.into_iter() {
                     let predicate_uuid = &chainhook_to_trigger.chainhook.uuid;
+                    let delivery_id = chainhook_to_trigger.get_delivery_id();
+                    let hook_action = chainhook_to_trigger.get_action();
+                    if PendingDelivery::is_supported_action(hook_action)
+                        && is_already_delivered(&delivery_outbox, &delivery_id, &ctx)
+                    {
+                        ctx.try_log(|logger| {
+                            slog::info!(
+                                logger,
+                                "Skipping delivery {} of predicate {}, already delivered",
+                                delivery_id,
+                                predicate_uuid
+                            )
+                        });
+                        continue;
+                    }
+                    if let Some(ref streams) = occurrence_streams {
+                        if streams.is_streamed(predicate_uuid) {
+                            if let Ok(payload) = serialize_stacks_payload_for_delivery(
+                                chainhook_to_trigger.clone(),
+                                &proofs,
+                                &ctx,
+                            ) {
+                                streams.publish(predicate_uuid, payload.to_string());
+                            }
+                        }
+                    }
+                    let outbox_id = match delivery_outbox {
+                        Some(_) => record_pending_delivery(
+                            &delivery_outbox,
+                            PendingDelivery::new(
+                                delivery_id.clone(),
+                                predicate_uuid,
+                                hook_action,
+                                chainhook_to_trigger.get_tip_block_identifier(),
+                                serialize_stacks_payload_for_delivery(
+                                    chainhook_to_trigger.clone(),
+                                    &proofs,
+                                    &ctx,
+                                )
+                                .unwrap_or(JsonValue::Null),
+                                &chainhook_to_trigger.chainhook.payload_envelope,
+                            ),
+                            &ctx,
+                        ),
+                        None => None,
+                    };
+                    let policy = DeliveryPolicy {
+                        retry_policy: hook_action.retry_policy().cloned().unwrap_or_default(),
+                        dead_letter: hook_action.dead_letter().cloned(),
+                        outbox_id,
+                        delivery_id,
+                        require_ack: hook_action.requires_ack(),
+                        endpoint: DeliveryPolicy::endpoint_of(hook_action),
+                        rate_limiter: action_rate_limiter(hook_action, &config.predicates_config),
+                    };
                     match handle_stacks_hook_action(chainhook_to_trigger, &proofs, &config, &ctx) {
                         Err(e) => {
                             ctx.try_log(|logger| {
@@ -1708,16 +2836,37 @@ pub async fn start_observer_commands_handler(
                                     e
                                 )
                             });
+                            complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
                         }
                         Ok(StacksChainhookOccurrence::Http(request, data)) => {
-                            requests.push((request, data));
+                            requests.push((request, data, policy));
                         }
-                        Ok(StacksChainhookOccurrence::File(_path, _bytes)) => {
-                            ctx.try_log(|logger| {
-                                slog::warn!(logger, "Writing to disk not supported in server mode")
-                            })
+                        Ok(StacksChainhookOccurrence::ObjectStore(upload, data)) => {
+                            deliveries.push((
+                                OccurrenceDelivery::ObjectStore(upload),
+                                data,
+                                policy,
+                            ));
+                        }
+                        Ok(StacksChainhookOccurrence::Kafka(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Kafka(message), data, policy));
+                        }
+                        Ok(StacksChainhookOccurrence::Nats(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Nats(message), data, policy));
+                        }
+                        Ok(StacksChainhookOccurrence::Aws(message, data)) => {
+                            deliveries.push((OccurrenceDelivery::Aws(message), data, policy));
+                        }
+                        Ok(StacksChainhookOccurrence::Postgres(record, data)) => {
+                            deliveries.push((OccurrenceDelivery::Postgres(record), data, policy));
+                        }
+                        Ok(StacksChainhookOccurrence::File(hook, bytes, data)) => {
+                            deliveries.push((OccurrenceDelivery::File(hook, bytes), data, policy));
                         }
                         Ok(StacksChainhookOccurrence::Data(payload)) => {
+                            if let Some(ref sidecar) = observer_sidecar {
+                                sidecar.handle_stacks_occurrence(&payload, &ctx).await;
+                            }
                             if let Some(ref tx) = observer_events_tx {
                                 let _ = tx.send(ObserverEvent::StacksPredicateTriggered(payload));
                             }
@@ -1726,12 +2875,11 @@ pub async fn start_observer_commands_handler(
                 }
 
                 for hook_uuid in hooks_ids_to_deregister.iter() {
-                    if chainhook_store
-                        .deregister_stacks_hook(hook_uuid.clone())
-                        .is_some()
-                    {
-                        prometheus_monitoring.stx_metrics_deregister_predicate();
+                    if let Some(hook) = chainhook_store.deregister_stacks_hook(hook_uuid.clone()) {
+                        prometheus_monitoring
+                            .stx_metrics_deregister_predicate(hook.namespace.as_deref());
                     }
+                    chainhooks_occurrences_tracker.forget(hook_uuid, &ctx);
                     if let Some(ref tx) = observer_events_tx {
                         let _ = tx.send(ObserverEvent::PredicateDeregistered(
                             PredicateDeregisteredEvent {
@@ -1742,7 +2890,7 @@ pub async fn start_observer_commands_handler(
                     }
                 }
 
-                for (request, data) in requests.into_iter() {
+                for (request, data, policy) in requests.into_iter() {
                     // todo(lgalabru): collect responses for reporting
                     ctx.try_log(|logger| {
                         slog::debug!(
@@ -1751,22 +2899,192 @@ pub async fn start_observer_commands_handler(
                             request
                         )
                     });
-                    match send_request(request, 3, 1, &ctx).await {
-                        Ok(_) => {
+                    if let (Some(breakers), Some(endpoint)) =
+                        (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                    {
+                        if breakers.is_open(&endpoint.url) {
+                            // Don't hit an endpoint known to be failing
+                            pause_predicate(
+                                breakers,
+                                endpoint,
+                                chainhook_store
+                                    .disable_stacks_hook(&data.chainhook.uuid)
+                                    .map(ChainhookInstance::Stacks),
+                                format!("circuit of endpoint {} is open", endpoint.url),
+                                &observer_events_tx,
+                                &ctx,
+                            );
+                            release_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
+                            continue;
+                        }
+                    }
+                    let (target, payload_size) = describe_http_request(&request);
+                    let started_at = Instant::now();
+                    let last_status = AtomicU16::new(0);
+                    let res = send_http_occurrence_recording_status(
+                        request,
+                        policy.require_ack,
+                        &policy.retry_policy,
+                        &policy.rate_limiter,
+                        &last_status,
+                        &ctx,
+                    )
+                    .await;
+                    if let Some(ref history) = delivery_history {
+                        history.record(
+                            &data.chainhook.uuid,
+                            DeliveryRecord::new(
+                                &policy.delivery_id,
+                                target,
+                                res.as_ref().err().cloned(),
+                                Some(last_status.load(Ordering::Relaxed)).filter(|s| *s != 0),
+                                started_at.elapsed().as_millis() as u64,
+                                payload_size,
+                            ),
+                        );
+                    }
+                    match res {
+                        Ok(cursor) => {
+                            heartbeats.record_delivery(&data.chainhook.uuid, Instant::now());
+                            if let (Some(breakers), Some(endpoint)) =
+                                (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                            {
+                                breakers.record_success(&endpoint.url);
+                            }
                             if let Some(ref tx) = observer_events_tx {
+                                if let Some(cursor) = cursor {
+                                    let _ = tx.send(ObserverEvent::PredicateAcknowledged(
+                                        PredicateAcknowledgedData {
+                                            predicate_key: data.chainhook.key(),
+                                            cursor,
+                                        },
+                                    ));
+                                }
                                 let _ = tx.send(ObserverEvent::StacksPredicateTriggered(data));
                             }
                         }
                         Err(e) => {
-                            chainhook_store.deregister_stacks_hook(data.chainhook.uuid.clone());
-                            if let Some(ref tx) = observer_events_tx {
-                                let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
-                                    predicate_key: ChainhookInstance::stacks_key(&data.chainhook.uuid),
-                                    error: format!("Unable to evaluate predicate on Bitcoin chainstate: {}", e)
-                                }));
+                            let mut failure_tolerated = false;
+                            if let (Some(breakers), Some(endpoint)) =
+                                (circuit_breakers.as_mut(), policy.endpoint.as_ref())
+                            {
+                                if breakers.record_failure(endpoint) {
+                                    // The occurrence is redelivered once the predicate resumes
+                                    pause_predicate(
+                                        breakers,
+                                        endpoint,
+                                        chainhook_store
+                                            .disable_stacks_hook(&data.chainhook.uuid)
+                                            .map(ChainhookInstance::Stacks),
+                                        e,
+                                        &observer_events_tx,
+                                        &ctx,
+                                    );
+                                    release_pending_delivery(
+                                        &delivery_outbox,
+                                        &policy.outbox_id,
+                                        &ctx,
+                                    );
+                                    continue;
+                                }
+                                // Failures are tolerated until the circuit opens
+                                failure_tolerated = !policy.require_ack;
+                            }
+                            let dead_lettered = match policy.dead_letter {
+                                None if failure_tolerated => Ok(()),
+                                _ => {
+                                    dead_letter_failed_delivery(
+                                        policy.dead_letter.as_ref(),
+                                        &data.chainhook.uuid,
+                                        &policy.delivery_id,
+                                        e,
+                                        &data,
+                                        &config.predicates_config,
+                                        &ctx,
+                                    )
+                                    .await
+                                }
+                            };
+                            if let Err(e) = dead_lettered {
+                                if policy.require_ack {
+                                    // Deliveries resume from the last acknowledged cursor
+                                    if let Some(hook) =
+                                        chainhook_store.disable_stacks_hook(&data.chainhook.uuid)
+                                    {
+                                        if let Some(ref tx) = observer_events_tx {
+                                            let _ = tx.send(ObserverEvent::PredicateDeliveryFailed(
+                                                PredicateDeliveryFailedData {
+                                                    predicate: ChainhookInstance::Stacks(hook),
+                                                    error: e,
+                                                },
+                                            ));
+                                        }
+                                    }
+                                    complete_pending_delivery(
+                                        &delivery_outbox,
+                                        &policy.outbox_id,
+                                        &ctx,
+                                    );
+                                    continue;
+                                }
+                                chainhook_store.deregister_stacks_hook(data.chainhook.uuid.clone());
+                                if let Some(ref tx) = observer_events_tx {
+                                    let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
+                                        predicate_key: data.chainhook.key(),
+                                        error: format!("Unable to evaluate predicate on Bitcoin chainstate: {}", e)
+                                    }));
+                                }
                             }
                         }
                     };
+                    complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
+                }
+
+                for (delivery, data, policy) in deliveries.into_iter() {
+                    let started_at = Instant::now();
+                    let res = delivery.send(&policy.retry_policy, &ctx).await;
+                    if let Some(ref history) = delivery_history {
+                        history.record(
+                            &data.chainhook.uuid,
+                            DeliveryRecord::new(
+                                &policy.delivery_id,
+                                delivery.target(),
+                                res.as_ref().err().cloned(),
+                                None,
+                                started_at.elapsed().as_millis() as u64,
+                                delivery.payload_size(),
+                            ),
+                        );
+                    }
+                    match res {
+                        Ok(_) => {
+                            if let Some(ref tx) = observer_events_tx {
+                                let _ = tx.send(ObserverEvent::StacksPredicateTriggered(data));
+                            }
+                        }
+                        Err(e) => {
+                            if let Err(e) = dead_letter_failed_delivery(
+                                policy.dead_letter.as_ref(),
+                                &data.chainhook.uuid,
+                                &policy.delivery_id,
+                                e,
+                                &data,
+                                &config.predicates_config,
+                                &ctx,
+                            )
+                            .await
+                            {
+                                chainhook_store.deregister_stacks_hook(data.chainhook.uuid.clone());
+                                if let Some(ref tx) = observer_events_tx {
+                                    let _ = tx.send(ObserverEvent::PredicateInterrupted(PredicateInterruptedData {
+                                        predicate_key: data.chainhook.key(),
+                                        error: format!("Unable to deliver occurrence: {}", e)
+                                    }));
+                                }
+                            }
+                        }
+                    }
+                    complete_pending_delivery(&delivery_outbox, &policy.outbox_id, &ctx);
                 }
 
                 prometheus_monitoring.stx_metrics_block_evaluated(new_tip);
@@ -1810,11 +3128,11 @@ pub async fn start_observer_commands_handler(
                     };
 
                 match spec {
-                    ChainhookInstance::Bitcoin(_) => {
-                        prometheus_monitoring.btc_metrics_register_predicate()
+                    ChainhookInstance::Bitcoin(ref data) => {
+                        prometheus_monitoring.btc_metrics_register_predicate(data.namespace.as_deref())
                     }
-                    ChainhookInstance::Stacks(_) => {
-                        prometheus_monitoring.stx_metrics_register_predicate()
+                    ChainhookInstance::Stacks(ref data) => {
+                        prometheus_monitoring.stx_metrics_register_predicate(data.namespace.as_deref())
                     }
                 };
 
@@ -1830,24 +3148,147 @@ pub async fn start_observer_commands_handler(
                     chainhook_store.enable_instance(&mut spec);
                 }
             }
+            ObserverCommand::RegisterPredicateInstance(spec) => {
+                ctx.try_log(|logger| {
+                    slog::info!(logger, "Handling RegisterPredicateInstance command")
+                });
+                if let Err(e) = chainhook_store.register_instance(spec.clone()) {
+                    ctx.try_log(|logger| {
+                        slog::warn!(logger, "Unable to register chainhook spec: {}", e)
+                    });
+                    continue;
+                }
+                match spec {
+                    ChainhookInstance::Bitcoin(ref data) => prometheus_monitoring
+                        .btc_metrics_register_predicate(data.namespace.as_deref()),
+                    ChainhookInstance::Stacks(ref data) => prometheus_monitoring
+                        .stx_metrics_register_predicate(data.namespace.as_deref()),
+                };
+                if let Some(ref tx) = observer_events_tx {
+                    let _ = tx.send(ObserverEvent::PredicateRegistered(spec));
+                }
+            }
+            ObserverCommand::UpdatePredicate(spec) => {
+                ctx.try_log(|logger| slog::info!(logger, "Handling UpdatePredicate command"));
+                let spec = match chainhook_store.update_instance_from_network_map(networks, spec) {
+                    Ok(spec) => spec,
+                    Err(e) => {
+                        ctx.try_log(|logger| {
+                            slog::warn!(logger, "Unable to update chainhook spec: {}", e)
+                        });
+                        continue;
+                    }
+                };
+                if let Some(ref mut breakers) = circuit_breakers {
+                    breakers.update_predicate(&spec);
+                }
+                if let Some(ref tx) = observer_events_tx {
+                    let _ = tx.send(ObserverEvent::PredicateUpdated(spec));
+                }
+            }
             ObserverCommand::EnablePredicate(mut spec) => {
+                if requested_pauses.contains(spec.uuid()) {
+                    ctx.try_log(|logger| {
+                        slog::info!(
+                            logger,
+                            "Predicate {} is paused, not enabling it",
+                            spec.uuid()
+                        )
+                    });
+                    continue;
+                }
                 ctx.try_log(|logger| slog::info!(logger, "Enabling Predicate {}", spec.uuid()));
                 chainhook_store.enable_instance(&mut spec);
                 if let Some(ref tx) = observer_events_tx {
                     let _ = tx.send(ObserverEvent::PredicateEnabled(spec));
                 }
             }
+            ObserverCommand::PausePredicate(hook_uuid) => {
+                ctx.try_log(|logger| slog::info!(logger, "Handling PausePredicate command"));
+                let Some(predicate) = chainhook_store.disable_instance(&hook_uuid) else {
+                    ctx.try_log(|logger| {
+                        slog::warn!(logger, "Unable to pause unknown predicate {}", hook_uuid)
+                    });
+                    continue;
+                };
+                // The circuit breaker must not resume a predicate paused on request
+                if let Some(ref mut breakers) = circuit_breakers {
+                    breakers.forget_predicate(&hook_uuid);
+                }
+                requested_pauses.insert(hook_uuid);
+                if let Some(ref tx) = observer_events_tx {
+                    let _ = tx.send(ObserverEvent::PredicatePaused(PredicatePausedData {
+                        predicate,
+                        error: "paused on request".into(),
+                        requested: true,
+                    }));
+                }
+            }
+            ObserverCommand::ResumePredicate(hook_uuid) => {
+                ctx.try_log(|logger| slog::info!(logger, "Handling ResumePredicate command"));
+                requested_pauses.remove(&hook_uuid);
+                let mut predicate = match chainhook_store.get_instance(&hook_uuid) {
+                    Some(predicate) if !predicate.enabled() => predicate,
+                    _ => {
+                        ctx.try_log(|logger| {
+                            slog::warn!(logger, "Predicate {} is not paused", hook_uuid)
+                        });
+                        continue;
+                    }
+                };
+                if let Some(ref mut breakers) = circuit_breakers {
+                    breakers.forget_predicate(&hook_uuid);
+                }
+                if let Some(ref tx) = observer_events_tx {
+                    let _ = tx.send(ObserverEvent::PredicateResumed(predicate));
+                } else {
+                    chainhook_store.enable_instance(&mut predicate);
+                }
+            }
+            ObserverCommand::RescanPredicate(data) => {
+                ctx.try_log(|logger| slog::info!(logger, "Handling RescanPredicate command"));
+                let Some(predicate) = chainhook_store.get_instance(&data.predicate_uuid) else {
+                    ctx.try_log(|logger| {
+                        slog::warn!(
+                            logger,
+                            "Unable to rescan unknown predicate {}",
+                            data.predicate_uuid
+                        )
+                    });
+                    continue;
+                };
+                if let Some(ref tx) = observer_events_tx {
+                    let _ = tx.send(ObserverEvent::PredicateRescanRequested(
+                        PredicateRescanData {
+                            predicate,
+                            start_block: data.start_block,
+                            end_block: data.end_block,
+                        },
+                    ));
+                }
+            }
             ObserverCommand::DeregisterStacksPredicate(hook_uuid) => {
                 ctx.try_log(|logger| {
                     slog::info!(logger, "Handling DeregisterStacksPredicate command")
                 });
                 let hook = chainhook_store.deregister_stacks_hook(hook_uuid.clone());
 
-                if hook.is_some() {
+                if let Some(ref hook) = hook {
                     // on startup, only the predicates in the `chainhook_store` are added to the monitoring count,
                     // so only those that we find in the store should be removed
-                    prometheus_monitoring.stx_metrics_deregister_predicate();
+                    prometheus_monitoring.stx_metrics_deregister_predicate(hook.namespace.as_deref());
                 };
+                if let Some(ref streams) = occurrence_streams {
+                    streams.close(&hook_uuid);
+                }
+                if let Some(ref history) = delivery_history {
+                    history.forget(&hook_uuid);
+                }
+                if let Some(ref mut breakers) = circuit_breakers {
+                    breakers.forget_predicate(&hook_uuid);
+                }
+                requested_pauses.remove(&hook_uuid);
+                chainhooks_occurrences_tracker.forget(&hook_uuid, &ctx);
                 // event if the predicate wasn't in the `chainhook_store`, propogate this event to delete from redis
                 if let Some(tx) = &observer_events_tx {
                     let _ = tx.send(ObserverEvent::PredicateDeregistered(
@@ -1864,11 +3305,22 @@ pub async fn start_observer_commands_handler(
                 });
                 let hook = chainhook_store.deregister_bitcoin_hook(hook_uuid.clone());
 
-                if hook.is_some() {
+                if let Some(ref hook) = hook {
                     // on startup, only the predicates in the `chainhook_store` are added to the monitoring count,
                     // so only those that we find in the store should be removed
-                    prometheus_monitoring.btc_metrics_deregister_predicate();
+                    prometheus_monitoring.btc_metrics_deregister_predicate(hook.namespace.as_deref());
                 };
+                if let Some(ref streams) = occurrence_streams {
+                    streams.close(&hook_uuid);
+                }
+                if let Some(ref history) = delivery_history {
+                    history.forget(&hook_uuid);
+                }
+                if let Some(ref mut breakers) = circuit_breakers {
+                    breakers.forget_predicate(&hook_uuid);
+                }
+                requested_pauses.remove(&hook_uuid);
+                chainhooks_occurrences_tracker.forget(&hook_uuid, &ctx);
                 // even if the predicate wasn't in the `chainhook_store`, propogate this event to delete from redis
                 if let Some(tx) = &observer_events_tx {
                     let _ = tx.send(ObserverEvent::PredicateDeregistered(
@@ -1901,9 +3353,87 @@ pub async fn start_observer_commands_handler(
     Ok(())
 }
 
+/// Waits for the next command, giving up after `timeout` if set so that open circuits keep being
+/// probed and heartbeats keep being sent while no block is observed.
+fn receive_observer_command(
+    observer_commands_rx: &Receiver<ObserverCommand>,
+    timeout: Option<Duration>,
+) -> Result<Option<ObserverCommand>, String> {
+    match timeout {
+        Some(timeout) => match observer_commands_rx.recv_timeout(timeout) {
+            Ok(cmd) => Ok(Some(cmd)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        },
+        None => observer_commands_rx
+            .recv()
+            .map(Some)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Takes the next command queued when termination was requested, until the queue is empty or
+/// the drain timeout elapses. The commands left once the timeout elapses are dropped.
+fn receive_drained_command(
+    observer_commands_rx: &Receiver<ObserverCommand>,
+    deadline: Instant,
+    ctx: &Context,
+) -> Option<ObserverCommand> {
+    if Instant::now() < deadline {
+        return observer_commands_rx.try_recv().ok();
+    }
+    let dropped = observer_commands_rx
+        .try_iter()
+        .filter(|cmd| !matches!(cmd, ObserverCommand::Terminate))
+        .count();
+    if dropped > 0 {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "Shutdown drain timeout elapsed, dropping {} pending commands",
+                dropped
+            )
+        });
+    }
+    None
+}
+
+/// Pauses a predicate posting to an endpoint whose circuit is open, until the endpoint recovers.
+fn pause_predicate(
+    breakers: &mut DeliveryCircuitBreakers,
+    endpoint: &HttpHook,
+    predicate: Option<ChainhookInstance>,
+    error: String,
+    observer_events_tx: &Option<BoundedSender<ObserverEvent>>,
+    ctx: &Context,
+) {
+    let Some(predicate) = predicate else {
+        return;
+    };
+    if !breakers.pause_predicate(&endpoint.url, predicate.clone()) {
+        return;
+    }
+    ctx.try_log(|logger| {
+        slog::warn!(
+            logger,
+            "Pausing predicate {}, deliveries to {} are failing: {}",
+            predicate.uuid(),
+            endpoint.url,
+            error
+        )
+    });
+    if let Some(ref tx) = observer_events_tx {
+        let _ = tx.send(ObserverEvent::PredicatePaused(PredicatePausedData {
+            predicate,
+            error,
+            requested: false,
+        }));
+    }
+}
+
 fn terminate(
-    ingestion_shutdown: Option<Shutdown>,
-    observer_events_tx: Option<crossbeam_channel::Sender<ObserverEvent>>,
+    ingestion_shutdown: Option<IngestionShutdown>,
+    observer_events_tx: Option<BoundedSender<ObserverEvent>>,
     ctx: &Context,
 ) {
     ctx.try_log(|logger| slog::info!(logger, "Handling Termination command"));
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use hiro_system_kit::slog;
+
+use crate::utils::Context;
+
+/// Durable storage of the number of occurrences of each predicate, so that a predicate's
+/// `expire_after_occurrence` keeps counting from where it was when the observer restarts.
+pub trait OccurrenceCountStore: Send + Sync {
+    fn load(&self) -> Result<HashMap<String, u64>, String>;
+    fn set(&self, predicate_uuid: &str, occurrences: u64) -> Result<(), String>;
+    fn remove(&self, predicate_uuid: &str) -> Result<(), String>;
+}
+
+/// An [OccurrenceCountStore] keeping the counts in memory, which only survives observer
+/// restarts within the same process.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryOccurrenceCountStore {
+    counts: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl InMemoryOccurrenceCountStore {
+    pub fn new() -> Self {
+        InMemoryOccurrenceCountStore::default()
+    }
+}
+
+impl OccurrenceCountStore for InMemoryOccurrenceCountStore {
+    fn load(&self) -> Result<HashMap<String, u64>, String> {
+        Ok(self.counts.lock().map_err(|e| e.to_string())?.clone())
+    }
+
+    fn set(&self, predicate_uuid: &str, occurrences: u64) -> Result<(), String> {
+        self.counts
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(predicate_uuid.to_string(), occurrences);
+        Ok(())
+    }
+
+    fn remove(&self, predicate_uuid: &str) -> Result<(), String> {
+        self.counts
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(predicate_uuid);
+        Ok(())
+    }
+}
+
+/// Number of occurrences of the registered predicates, written through to an
+/// [OccurrenceCountStore] when one is set.
+pub struct OccurrencesTracker {
+    counts: HashMap<String, u64>,
+    store: Option<Arc<dyn OccurrenceCountStore>>,
+}
+
+impl OccurrencesTracker {
+    /// Builds a tracker starting from the counts found in the store.
+    pub fn new(store: Option<Arc<dyn OccurrenceCountStore>>, ctx: &Context) -> Self {
+        let counts = match store.as_ref().map(|store| store.load()) {
+            Some(Ok(counts)) => counts,
+            Some(Err(e)) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Unable to restore predicates occurrences: {}", e)
+                });
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+        OccurrencesTracker { counts, store }
+    }
+
+    pub fn get(&self, predicate_uuid: &str) -> u64 {
+        *self.counts.get(predicate_uuid).unwrap_or(&0)
+    }
+
+    pub fn set(&mut self, predicate_uuid: &str, occurrences: u64, ctx: &Context) {
+        self.counts.insert(predicate_uuid.to_string(), occurrences);
+        if let Some(ref store) = self.store {
+            if let Err(e) = store.set(predicate_uuid, occurrences) {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Unable to persist occurrences of predicate {}: {}",
+                        predicate_uuid,
+                        e
+                    )
+                });
+            }
+        }
+    }
+
+    /// Forgets the occurrences of a deregistered predicate, so that a predicate registered
+    /// later with the same uuid starts counting from zero.
+    pub fn forget(&mut self, predicate_uuid: &str, ctx: &Context) {
+        self.counts.remove(predicate_uuid);
+        if let Some(ref store) = self.store {
+            if let Err(e) = store.remove(predicate_uuid) {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Unable to remove occurrences of predicate {}: {}",
+                        predicate_uuid,
+                        e
+                    )
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{InMemoryOccurrenceCountStore, OccurrenceCountStore, OccurrencesTracker};
+    use crate::utils::Context;
+
+    #[test]
+    fn it_restores_persisted_occurrences() {
+        let store = InMemoryOccurrenceCountStore::new();
+        let ctx = Context::empty();
+
+        let mut tracker = OccurrencesTracker::new(Some(Arc::new(store.clone())), &ctx);
+        assert_eq!(tracker.get("1234"), 0);
+        tracker.set("1234", 2, &ctx);
+        tracker.set("5678", 1, &ctx);
+
+        // a new tracker, as built when the observer restarts, keeps counting from there
+        let mut tracker = OccurrencesTracker::new(Some(Arc::new(store.clone())), &ctx);
+        assert_eq!(tracker.get("1234"), 2);
+        tracker.forget("1234", &ctx);
+        assert_eq!(tracker.get("1234"), 0);
+        assert_eq!(store.load().unwrap().get("1234"), None);
+        assert_eq!(store.load().unwrap().get("5678"), Some(&1));
+    }
+}
@@ -0,0 +1,721 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chainhook_types::BlockIdentifier;
+use hiro_system_kit::slog;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::{EventObserverConfig, DELIVERY_HISTORY_SIZE};
+use crate::chainhooks::types::{HookAction, KafkaMessageKey, PayloadEnvelope};
+use crate::utils::aws::{send_aws_message, AwsMessage};
+use crate::utils::cloud_events::payload_content_type;
+use crate::utils::dead_letter::dead_letter_failed_delivery;
+use crate::utils::file_sink::append_to_file_sink;
+use crate::utils::kafka::{send_kafka_message, KafkaMessage};
+use crate::utils::nats::{send_nats_message, NatsMessage};
+use crate::utils::object_storage::{send_object_store_upload, ObjectStoreUpload};
+use crate::utils::postgres::{send_postgres_record, PostgresRecord};
+use crate::utils::rate_limit::host_rate_limiter;
+use crate::utils::retry::{send_http_occurrence_with_retry_policy, send_with_retry_policy};
+use crate::utils::{build_http_post_request, Context};
+
+/// Number of completed deliveries a [DeliveryOutbox] remembers to suppress duplicates.
+pub const MAX_DELIVERED_IDS: usize = 100_000;
+
+/// An occurrence recorded in a [DeliveryOutbox] before being delivered, along with everything
+/// needed to deliver it again if chainhook stops before the delivery completes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PendingDelivery {
+    /// Deterministic id of the delivery, see
+    /// [build_delivery_id](crate::chainhooks::types::build_delivery_id).
+    pub id: String,
+    pub predicate_uuid: String,
+    pub action: HookAction,
+    pub tip_block_identifier: Option<BlockIdentifier>,
+    /// Payload of the occurrence, with the predicate's `include_fields`, `transform` and
+    /// `payload_envelope` already applied.
+    pub payload: JsonValue,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_envelope: Option<PayloadEnvelope>,
+}
+
+impl PendingDelivery {
+    pub fn new(
+        delivery_id: String,
+        predicate_uuid: &str,
+        action: &HookAction,
+        tip_block_identifier: Option<&BlockIdentifier>,
+        payload: JsonValue,
+        payload_envelope: &Option<PayloadEnvelope>,
+    ) -> PendingDelivery {
+        PendingDelivery {
+            id: delivery_id,
+            predicate_uuid: predicate_uuid.to_string(),
+            action: action.clone(),
+            tip_block_identifier: tip_block_identifier.cloned(),
+            payload,
+            payload_envelope: payload_envelope.clone(),
+        }
+    }
+
+    /// Returns true if the action delivers occurrences somewhere the outbox can resume from.
+    pub fn is_supported_action(action: &HookAction) -> bool {
+        !matches!(action, HookAction::Noop | HookAction::FileAppend(_))
+    }
+
+    /// Delivers the payload with the recorded action, routing it to the action's dead-letter
+    /// sink if the delivery fails.
+    pub async fn send(&self, config: &EventObserverConfig, ctx: &Context) -> Result<(), String> {
+        match self.deliver(config, ctx).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                dead_letter_failed_delivery(
+                    self.action.dead_letter(),
+                    &self.predicate_uuid,
+                    &self.id,
+                    e,
+                    &self.payload,
+                    &config.predicates_config,
+                    ctx,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Delivers the payload with the recorded action.
+    pub async fn deliver(&self, config: &EventObserverConfig, ctx: &Context) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&self.payload)
+            .map_err(|e| format!("unable to serialize payload {}", e))?;
+        let policy = &self.action.retry_policy().cloned().unwrap_or_default();
+        match &self.action {
+            HookAction::HttpPost(http) => {
+                let request = build_http_post_request(
+                    http,
+                    bytes,
+                    &self.id,
+                    payload_content_type(&self.payload_envelope),
+                    &config.predicates_config,
+                )?;
+                let require_ack = self.action.requires_ack();
+                let rate_limiter = host_rate_limiter(&http.url, &config.predicates_config);
+                send_http_occurrence_with_retry_policy(
+                    request,
+                    require_ack,
+                    policy,
+                    &rate_limiter,
+                    ctx,
+                )
+                .await
+                .map(|_| ())
+            }
+            HookAction::FileAppend(disk) => append_to_file_sink(disk, bytes, ctx),
+            HookAction::ObjectStore(object_store) => {
+                let upload =
+                    ObjectStoreUpload::new(object_store, &self.predicate_uuid, &self.payload)?;
+                send_with_retry_policy(policy, ctx, || send_object_store_upload(&upload, 1, 0, ctx))
+                    .await
+            }
+            HookAction::Kafka(kafka) => {
+                let key = match kafka.key {
+                    KafkaMessageKey::PredicateUuid => self.predicate_uuid.clone(),
+                    KafkaMessageKey::BlockHash => self
+                        .tip_block_identifier
+                        .as_ref()
+                        .map(|b| b.hash.clone())
+                        .unwrap_or(self.predicate_uuid.clone()),
+                };
+                let message = KafkaMessage::new(kafka, key, bytes);
+                send_with_retry_policy(policy, ctx, || send_kafka_message(&message, 1, 0, ctx))
+                    .await
+            }
+            HookAction::Nats(nats) => {
+                let message = NatsMessage::new(
+                    nats,
+                    &self.predicate_uuid,
+                    self.tip_block_identifier.as_ref(),
+                    bytes,
+                );
+                send_with_retry_policy(policy, ctx, || send_nats_message(&message, 1, 0, ctx)).await
+            }
+            HookAction::Sqs(sqs) => {
+                let message = AwsMessage::sqs(&sqs.queue_url, &sqs.region, bytes);
+                send_with_retry_policy(policy, ctx, || send_aws_message(&message, 1, 0, ctx)).await
+            }
+            HookAction::Sns(sns) => {
+                let message = AwsMessage::sns(&sns.topic_arn, &sns.region, bytes);
+                send_with_retry_policy(policy, ctx, || send_aws_message(&message, 1, 0, ctx)).await
+            }
+            HookAction::Postgres(postgres) => {
+                let record = PostgresRecord::new(
+                    &postgres.dsn,
+                    &postgres.table,
+                    postgres.get_column(),
+                    bytes,
+                );
+                send_with_retry_policy(policy, ctx, || send_postgres_record(&record, 1, 0, ctx))
+                    .await
+            }
+            HookAction::Noop => Ok(()),
+            HookAction::Route(_) | HookAction::Multiple(_) => {
+                Err("unable to deliver a payload to an unresolved action".to_string())
+            }
+        }
+    }
+
+    /// Destination of the delivery, reported in the [DeliveryHistory](super::DeliveryHistory).
+    pub fn target(&self) -> String {
+        match &self.action {
+            HookAction::HttpPost(http) => http.url.clone(),
+            HookAction::FileAppend(disk) => disk.path.clone(),
+            HookAction::ObjectStore(object_store) => object_store.url.clone(),
+            HookAction::Kafka(kafka) => kafka.topic.clone(),
+            HookAction::Nats(nats) => nats.subject.clone(),
+            HookAction::Sqs(sqs) => sqs.queue_url.clone(),
+            HookAction::Sns(sns) => sns.topic_arn.clone(),
+            // The dsn holds the credentials of the database
+            HookAction::Postgres(postgres) => postgres.table.clone(),
+            HookAction::Noop | HookAction::Route(_) | HookAction::Multiple(_) => String::new(),
+        }
+    }
+}
+
+/// Durable storage of the deliveries in flight. Deliveries are recorded before being
+/// attempted and removed once they complete, so that the ones interrupted by a crash or a
+/// restart are resumed when the observer starts, giving at-least-once delivery.
+///
+/// The ids of the last [MAX_DELIVERED_IDS] completed deliveries are also kept, so that an
+/// occurrence evaluated again after a restart isn't delivered twice.
+///
+/// The last [DELIVERY_HISTORY_SIZE] deliveries of each predicate are logged as well, whether
+/// they completed or failed, so that they can be redelivered on demand.
+///
+/// The outbox also counts the reorgs of each chain, so that the occurrences evaluated on a
+/// reorg get new delivery ids even once restarted.
+pub trait DeliveryOutbox: Send + Sync {
+    fn insert(&self, delivery: &PendingDelivery) -> Result<(), String>;
+    fn remove(&self, delivery_id: &str) -> Result<(), String>;
+    fn pending(&self) -> Result<Vec<PendingDelivery>, String>;
+    fn mark_delivered(&self, delivery_id: &str) -> Result<(), String>;
+    fn is_delivered(&self, delivery_id: &str) -> Result<bool, String>;
+    fn log_delivery(&self, delivery: &PendingDelivery) -> Result<(), String>;
+    fn logged_delivery(
+        &self,
+        predicate_uuid: &str,
+        delivery_id: &str,
+    ) -> Result<Option<PendingDelivery>, String>;
+    /// Increments the number of reorgs of `chain`, starting from 0, and returns it.
+    fn next_reorg_sequence(&self, chain: &str) -> Result<u64, String>;
+}
+
+#[derive(Debug, Default)]
+struct InMemoryDeliveries {
+    pending: BTreeMap<String, PendingDelivery>,
+    delivered: HashSet<String>,
+    delivered_order: VecDeque<String>,
+    logged: HashMap<String, VecDeque<PendingDelivery>>,
+    reorg_sequences: HashMap<String, u64>,
+}
+
+/// A [DeliveryOutbox] keeping deliveries in memory, which only survives observer restarts
+/// within the same process.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryDeliveryOutbox {
+    deliveries: Arc<Mutex<InMemoryDeliveries>>,
+}
+
+impl InMemoryDeliveryOutbox {
+    pub fn new() -> Self {
+        InMemoryDeliveryOutbox::default()
+    }
+}
+
+impl DeliveryOutbox for InMemoryDeliveryOutbox {
+    fn insert(&self, delivery: &PendingDelivery) -> Result<(), String> {
+        self.deliveries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .pending
+            .insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+
+    fn remove(&self, delivery_id: &str) -> Result<(), String> {
+        self.deliveries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .pending
+            .remove(delivery_id);
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingDelivery>, String> {
+        Ok(self
+            .deliveries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .pending
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn mark_delivered(&self, delivery_id: &str) -> Result<(), String> {
+        let mut deliveries = self.deliveries.lock().map_err(|e| e.to_string())?;
+        if deliveries.delivered.insert(delivery_id.to_string()) {
+            deliveries
+                .delivered_order
+                .push_back(delivery_id.to_string());
+        }
+        while deliveries.delivered_order.len() > MAX_DELIVERED_IDS {
+            if let Some(oldest) = deliveries.delivered_order.pop_front() {
+                deliveries.delivered.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_delivered(&self, delivery_id: &str) -> Result<bool, String> {
+        Ok(self
+            .deliveries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .delivered
+            .contains(delivery_id))
+    }
+
+    fn log_delivery(&self, delivery: &PendingDelivery) -> Result<(), String> {
+        let mut deliveries = self.deliveries.lock().map_err(|e| e.to_string())?;
+        let logged = deliveries
+            .logged
+            .entry(delivery.predicate_uuid.clone())
+            .or_default();
+        if logged.len() == DELIVERY_HISTORY_SIZE {
+            logged.pop_front();
+        }
+        logged.push_back(delivery.clone());
+        Ok(())
+    }
+
+    fn logged_delivery(
+        &self,
+        predicate_uuid: &str,
+        delivery_id: &str,
+    ) -> Result<Option<PendingDelivery>, String> {
+        Ok(self
+            .deliveries
+            .lock()
+            .map_err(|e| e.to_string())?
+            .logged
+            .get(predicate_uuid)
+            .and_then(|logged| logged.iter().find(|d| d.id == delivery_id))
+            .cloned())
+    }
+
+    fn next_reorg_sequence(&self, chain: &str) -> Result<u64, String> {
+        let mut deliveries = self.deliveries.lock().map_err(|e| e.to_string())?;
+        let sequence = deliveries
+            .reorg_sequences
+            .entry(chain.to_string())
+            .or_default();
+        *sequence += 1;
+        Ok(*sequence)
+    }
+}
+
+/// Records a delivery in the outbox, returning its id if it was stored.
+pub fn record_pending_delivery(
+    outbox: &Option<Arc<dyn DeliveryOutbox>>,
+    delivery: PendingDelivery,
+    ctx: &Context,
+) -> Option<String> {
+    let outbox = outbox.as_ref()?;
+    if !PendingDelivery::is_supported_action(&delivery.action) {
+        return None;
+    }
+    if let Err(e) = outbox.log_delivery(&delivery) {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "Unable to log delivery of predicate {}: {}",
+                delivery.predicate_uuid,
+                e
+            )
+        });
+    }
+    match outbox.insert(&delivery) {
+        Ok(_) => Some(delivery.id),
+        Err(e) => {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to record delivery of predicate {} in outbox: {}",
+                    delivery.predicate_uuid,
+                    e
+                )
+            });
+            None
+        }
+    }
+}
+
+/// Returns true if a delivery with the same id already completed, in which case it should not
+/// be attempted again.
+pub fn is_already_delivered(
+    outbox: &Option<Arc<dyn DeliveryOutbox>>,
+    delivery_id: &str,
+    ctx: &Context,
+) -> bool {
+    let Some(outbox) = outbox else {
+        return false;
+    };
+    match outbox.is_delivered(delivery_id) {
+        Ok(delivered) => delivered,
+        Err(e) => {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to check delivery {} in outbox: {}",
+                    delivery_id,
+                    e
+                )
+            });
+            false
+        }
+    }
+}
+
+/// Returns the sequence of a new reorg of `chain`, which [build_delivery_id] includes in the
+/// ids of the occurrences evaluated on the reorg. Sequences are persisted in the outbox; without
+/// one, or if it fails, they only increase within the process.
+///
+/// [build_delivery_id]: crate::chainhooks::types::build_delivery_id
+pub fn next_reorg_sequence(
+    outbox: &Option<Arc<dyn DeliveryOutbox>>,
+    chain: &str,
+    ctx: &Context,
+) -> u64 {
+    static PROCESS_REORG_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    if let Some(outbox) = outbox {
+        match outbox.next_reorg_sequence(chain) {
+            Ok(sequence) => return sequence,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "Unable to persist {} reorg sequence in outbox: {}",
+                        chain,
+                        e
+                    )
+                });
+            }
+        }
+    }
+    PROCESS_REORG_SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Marks a delivery as completed and removes it from the outbox.
+pub fn complete_pending_delivery(
+    outbox: &Option<Arc<dyn DeliveryOutbox>>,
+    delivery_id: &Option<String>,
+    ctx: &Context,
+) {
+    let (Some(outbox), Some(delivery_id)) = (outbox, delivery_id) else {
+        return;
+    };
+    if let Err(e) = outbox.mark_delivered(delivery_id) {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "Unable to mark delivery {} as completed: {}",
+                delivery_id,
+                e
+            )
+        });
+    }
+    if let Err(e) = outbox.remove(delivery_id) {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "Unable to remove delivery {} from outbox: {}",
+                delivery_id,
+                e
+            )
+        });
+    }
+}
+
+/// Removes a delivery from the outbox without marking it as completed, so that it gets
+/// delivered again once its predicate resumes.
+pub fn release_pending_delivery(
+    outbox: &Option<Arc<dyn DeliveryOutbox>>,
+    delivery_id: &Option<String>,
+    ctx: &Context,
+) {
+    let (Some(outbox), Some(delivery_id)) = (outbox, delivery_id) else {
+        return;
+    };
+    if let Err(e) = outbox.remove(delivery_id) {
+        ctx.try_log(|logger| {
+            slog::warn!(
+                logger,
+                "Unable to remove delivery {} from outbox: {}",
+                delivery_id,
+                e
+            )
+        });
+    }
+}
+
+/// Delivers the occurrences left in the outbox by a previous run.
+pub async fn resume_pending_deliveries(
+    outbox: &Arc<dyn DeliveryOutbox>,
+    config: &EventObserverConfig,
+    ctx: &Context,
+) {
+    let deliveries = match outbox.pending() {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            ctx.try_log(|logger| slog::warn!(logger, "Unable to read delivery outbox: {}", e));
+            return;
+        }
+    };
+    if deliveries.is_empty() {
+        return;
+    }
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Resuming {} deliveries interrupted by the previous run",
+            deliveries.len()
+        )
+    });
+    let outbox = Some(outbox.clone());
+    for delivery in deliveries.into_iter() {
+        if is_already_delivered(&outbox, &delivery.id, ctx) {
+            complete_pending_delivery(&outbox, &Some(delivery.id), ctx);
+            continue;
+        }
+        if let Err(e) = delivery.send(config, ctx).await {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Unable to resume delivery of predicate {}: {}",
+                    delivery.predicate_uuid,
+                    e
+                )
+            });
+        }
+        complete_pending_delivery(&outbox, &Some(delivery.id), ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use std::collections::HashSet;
+
+    use chainhook_types::{
+        BitcoinBlockData, BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData,
+        BitcoinChainUpdatedWithReorgData, BitcoinNetwork,
+    };
+
+    use super::{
+        complete_pending_delivery, is_already_delivered, next_reorg_sequence,
+        record_pending_delivery, resume_pending_deliveries, DeliveryOutbox, InMemoryDeliveryOutbox,
+        PendingDelivery, DELIVERY_HISTORY_SIZE,
+    };
+    use crate::chainhooks::bitcoin::{
+        evaluate_bitcoin_chainhooks_on_chain_event, BitcoinChainhookInstance, BitcoinPredicateType,
+    };
+    use crate::chainhooks::types::{DeliveryOptions, FileHook, HookAction};
+    use crate::indexer::tests::helpers::accounts;
+    use crate::indexer::tests::helpers::bitcoin_blocks::generate_test_bitcoin_block;
+    use crate::indexer::tests::helpers::transactions::generate_test_tx_bitcoin_p2pkh_transfer;
+    use crate::observer::EventObserverConfig;
+    use crate::utils::Context;
+
+    fn pending_delivery(action: HookAction) -> PendingDelivery {
+        PendingDelivery::new(
+            "1234::0".into(),
+            "1234",
+            &action,
+            None,
+            json!({ "apply": [] }),
+            &None,
+        )
+    }
+
+    #[test]
+    fn it_records_deliveries_of_supported_actions() {
+        let memory_outbox = InMemoryDeliveryOutbox::new();
+        let outbox: Option<Arc<dyn DeliveryOutbox>> = Some(Arc::new(memory_outbox.clone()));
+        let ctx = Context::empty();
+
+        let noop = record_pending_delivery(&outbox, pending_delivery(HookAction::Noop), &ctx);
+        assert!(noop.is_none());
+        let file = HookAction::FileAppend(FileHook {
+            path: "occurrences.json".into(),
+            rotation: None,
+            compression: None,
+            delivery: DeliveryOptions::default(),
+        });
+        assert!(record_pending_delivery(&outbox, pending_delivery(file), &ctx).is_none());
+        assert!(memory_outbox.pending().unwrap().is_empty());
+
+        let delivery = pending_delivery(HookAction::Sqs(crate::chainhooks::types::SqsHook {
+            queue_url: "https://sqs.us-east-1.amazonaws.com/123456789012/chainhook".into(),
+            region: None,
+            delivery: DeliveryOptions::default(),
+        }));
+        let id = record_pending_delivery(&outbox, delivery.clone(), &ctx);
+        assert_eq!(id, Some(delivery.id.clone()));
+        assert_eq!(memory_outbox.pending().unwrap(), vec![delivery]);
+    }
+
+    #[tokio::test]
+    async fn it_empties_the_outbox_when_resuming() {
+        let memory_outbox = InMemoryDeliveryOutbox::new();
+        memory_outbox
+            .insert(&pending_delivery(HookAction::Noop))
+            .unwrap();
+        let outbox: Arc<dyn DeliveryOutbox> = Arc::new(memory_outbox.clone());
+        resume_pending_deliveries(&outbox, &EventObserverConfig::default(), &Context::empty())
+            .await;
+        assert!(memory_outbox.pending().unwrap().is_empty());
+        assert!(memory_outbox.is_delivered("1234::0").unwrap());
+    }
+
+    #[test]
+    fn it_suppresses_completed_deliveries() {
+        let memory_outbox = InMemoryDeliveryOutbox::new();
+        let outbox: Option<Arc<dyn DeliveryOutbox>> = Some(Arc::new(memory_outbox.clone()));
+        let ctx = Context::empty();
+
+        let delivery = pending_delivery(HookAction::Sqs(crate::chainhooks::types::SqsHook {
+            queue_url: "https://sqs.us-east-1.amazonaws.com/123456789012/chainhook".into(),
+            region: None,
+            delivery: DeliveryOptions::default(),
+        }));
+        assert!(!is_already_delivered(&outbox, &delivery.id, &ctx));
+        let id = record_pending_delivery(&outbox, delivery.clone(), &ctx);
+        complete_pending_delivery(&outbox, &id, &ctx);
+        assert!(memory_outbox.pending().unwrap().is_empty());
+        assert!(is_already_delivered(&outbox, &delivery.id, &ctx));
+    }
+
+    #[test]
+    fn it_logs_the_last_deliveries_of_each_predicate() {
+        let memory_outbox = InMemoryDeliveryOutbox::new();
+        let outbox: Option<Arc<dyn DeliveryOutbox>> = Some(Arc::new(memory_outbox.clone()));
+        let ctx = Context::empty();
+
+        let sqs = HookAction::Sqs(crate::chainhooks::types::SqsHook {
+            queue_url: "https://sqs.us-east-1.amazonaws.com/123456789012/chainhook".into(),
+            region: None,
+            delivery: DeliveryOptions::default(),
+        });
+        for i in 0..(DELIVERY_HISTORY_SIZE + 1) {
+            let mut delivery = pending_delivery(sqs.clone());
+            delivery.id = format!("1234::{i}");
+            let id = record_pending_delivery(&outbox, delivery, &ctx);
+            complete_pending_delivery(&outbox, &id, &ctx);
+        }
+        let logged = |predicate_uuid, delivery_id| {
+            memory_outbox
+                .logged_delivery(predicate_uuid, delivery_id)
+                .unwrap()
+                .map(|d| d.id)
+        };
+        assert_eq!(logged("1234", "1234::0"), None);
+        assert_eq!(logged("1234", "1234::1"), Some("1234::1".to_string()));
+        assert_eq!(logged("5678", "1234::1"), None);
+    }
+
+    #[test]
+    fn it_assigns_new_delivery_ids_when_the_chain_flip_flops() {
+        let memory_outbox = InMemoryDeliveryOutbox::new();
+        let outbox: Option<Arc<dyn DeliveryOutbox>> = Some(Arc::new(memory_outbox.clone()));
+        let ctx = Context::empty();
+        let chainhook = BitcoinChainhookInstance {
+            uuid: "1234".into(),
+            owner_uuid: None,
+            labels: None,
+            metadata: None,
+            namespace: None,
+            name: "flip-flop".into(),
+            network: BitcoinNetwork::Regtest,
+            version: 1,
+            blocks: None,
+            start_block: None,
+            end_block: None,
+            expire_after_occurrence: None,
+            predicate: BitcoinPredicateType::Block,
+            action: HookAction::Noop,
+            include_proof: false,
+            include_inputs: false,
+            include_outputs: false,
+            include_witness: false,
+            include_fields: None,
+            transform: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            rollback_action: None,
+            payload_envelope: None,
+            enabled: true,
+            expired_at: None,
+        };
+        let transaction = generate_test_tx_bitcoin_p2pkh_transfer(
+            0,
+            &accounts::wallet_1_btc_address(),
+            &accounts::wallet_3_btc_address(),
+            3,
+        );
+        let block_a = generate_test_bitcoin_block(0, 1, vec![transaction.clone()], None);
+        let block_b = generate_test_bitcoin_block(1, 1, vec![transaction], None);
+        let reorg = |from: &BitcoinBlockData, to: &BitcoinBlockData| {
+            BitcoinChainEvent::ChainUpdatedWithReorg(BitcoinChainUpdatedWithReorgData {
+                blocks_to_rollback: vec![from.clone()],
+                blocks_to_apply: vec![to.clone()],
+                confirmed_blocks: vec![],
+            })
+        };
+        // A, then A -> B, B -> A and A -> B again
+        let chain_events = vec![
+            BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+                new_blocks: vec![block_a.clone()],
+                confirmed_blocks: vec![],
+            }),
+            reorg(&block_a, &block_b),
+            reorg(&block_b, &block_a),
+            reorg(&block_a, &block_b),
+        ];
+
+        let mut delivery_ids = HashSet::new();
+        for chain_event in chain_events.iter() {
+            let reorg_sequence = match chain_event {
+                BitcoinChainEvent::ChainUpdatedWithReorg(_) => {
+                    next_reorg_sequence(&outbox, "bitcoin", &ctx)
+                }
+                _ => 0,
+            };
+            let (triggers, _, _) =
+                evaluate_bitcoin_chainhooks_on_chain_event(chain_event, &vec![&chainhook], &ctx);
+            assert_eq!(triggers.len(), 1);
+            for mut trigger in triggers.into_iter() {
+                trigger.reorg_sequence = reorg_sequence;
+                let delivery_id = trigger.get_delivery_id();
+                assert!(!is_already_delivered(&outbox, &delivery_id, &ctx));
+                memory_outbox.mark_delivered(&delivery_id).unwrap();
+                assert!(delivery_ids.insert(delivery_id));
+            }
+        }
+        assert_eq!(delivery_ids.len(), 4);
+    }
+}
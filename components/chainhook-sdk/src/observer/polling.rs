@@ -0,0 +1,183 @@
+use chainhook_types::BitcoinBlockSignaling;
+use hiro_system_kit::slog;
+use reqwest::Client as HttpClient;
+use std::collections::VecDeque;
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+
+use crate::{
+    indexer::{
+        bitcoin::{
+            build_http_client, download_and_parse_block_with_retry, source::bitcoin_block_source,
+        },
+        fork_scratch_pad::ForkScratchPad,
+    },
+    utils::Context,
+};
+
+use super::{BitcoinConfig, EventObserverConfig, ObserverCommand};
+
+/// Polls bitcoind for its best block hash and dispatches the blocks it didn't see yet, for
+/// deployments where neither ZMQ notifications nor a Stacks node are available.
+pub async fn start_polling_runloop(
+    config: &EventObserverConfig,
+    observer_commands_tx: SyncSender<ObserverCommand>,
+    ctx: &Context,
+) {
+    let BitcoinBlockSignaling::Polling(interval_ms) = config.bitcoin_block_signaling else {
+        unreachable!()
+    };
+
+    let bitcoin_config = config.get_bitcoin_config();
+    let http_client = build_http_client();
+    let interval = Duration::from_millis(interval_ms.max(1));
+
+    ctx.try_log(|logger| {
+        slog::info!(
+            logger,
+            "Polling bitcoind every {}ms for new blocks",
+            interval.as_millis()
+        )
+    });
+
+    let mut bitcoin_blocks_pool = ForkScratchPad::new();
+    bitcoin_blocks_pool.set_confirmation_depth(config.get_bitcoin_confirmation_depth());
+    let mut known_tip: Option<String> = None;
+
+    loop {
+        let block_source = bitcoin_block_source(&http_client, &bitcoin_config, ctx);
+        match block_source.retrieve_best_block_hash().await {
+            Ok(block_hash) => match known_tip {
+                None => {
+                    // The chain tip found at startup is not dispatched, but seeds the pool so that
+                    // the blocks mined until the next poll can be chained to it.
+                    match download_and_parse_block_with_retry(
+                        &http_client,
+                        &block_hash,
+                        &bitcoin_config,
+                        ctx,
+                    )
+                    .await
+                    {
+                        Ok(block) => {
+                            let _ =
+                                bitcoin_blocks_pool.process_header(block.get_block_header(), ctx);
+                            known_tip = Some(block_hash);
+                        }
+                        Err(e) => {
+                            ctx.try_log(|logger| {
+                                slog::warn!(logger, "Unable to retrieve chain tip: {}", e)
+                            });
+                        }
+                    }
+                }
+                Some(ref tip) if tip.eq(&block_hash) => {}
+                Some(_) => {
+                    ctx.try_log(|logger| {
+                        slog::info!(logger, "Bitcoin block hash polled #{block_hash}",)
+                    });
+                    known_tip = Some(block_hash.clone());
+                    process_announced_block_hash(
+                        block_hash,
+                        &http_client,
+                        &bitcoin_config,
+                        &mut bitcoin_blocks_pool,
+                        &observer_commands_tx,
+                        ctx,
+                    )
+                    .await;
+                }
+            },
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(logger, "Unable to retrieve best block hash: {}", e)
+                });
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Downloads the block announced by bitcoind and dispatches it for processing, along with the
+/// ancestors that were not seen yet.
+pub async fn process_announced_block_hash(
+    block_hash: String,
+    http_client: &HttpClient,
+    bitcoin_config: &BitcoinConfig,
+    bitcoin_blocks_pool: &mut ForkScratchPad,
+    observer_commands_tx: &SyncSender<ObserverCommand>,
+    ctx: &Context,
+) {
+    let mut block_hashes: VecDeque<String> = VecDeque::new();
+    block_hashes.push_front(block_hash);
+
+    while let Some(block_hash) = block_hashes.pop_front() {
+        let block = match download_and_parse_block_with_retry(
+            http_client,
+            &block_hash,
+            bitcoin_config,
+            ctx,
+        )
+        .await
+        {
+            Ok(block) => block,
+            Err(e) => {
+                ctx.try_log(|logger| {
+                    slog::warn!(
+                        logger,
+                        "unable to download_and_parse_block: {}",
+                        e.to_string()
+                    )
+                });
+                continue;
+            }
+        };
+
+        let header = block.get_block_header();
+        ctx.try_log(|logger| {
+            slog::info!(
+                logger,
+                "Bitcoin block #{} dispatched for processing",
+                block.height
+            )
+        });
+
+        let _ = observer_commands_tx.send(ObserverCommand::ProcessBitcoinBlock(block));
+
+        if bitcoin_blocks_pool.can_process_header(&header) {
+            match bitcoin_blocks_pool.process_header(header, ctx) {
+                Ok(Some(event)) => {
+                    let _ = observer_commands_tx
+                        .send(ObserverCommand::PropagateBitcoinChainEvent(event));
+                }
+                Err(e) => {
+                    ctx.try_log(|logger| slog::warn!(logger, "Unable to append block: {:?}", e));
+                }
+                Ok(None) => {
+                    ctx.try_log(|logger| slog::warn!(logger, "Unable to append block"));
+                }
+            }
+        } else {
+            // Handle a behaviour specific to ZMQ usage in bitcoind.
+            // Considering a simple re-org:
+            // A (1) - B1 (2) - C1 (3)
+            //       \ B2 (4) - C2 (5) - D2 (6)
+            // When D2 is being discovered (making A -> B2 -> C2 -> D2 the new canonical fork)
+            // it looks like ZMQ is only publishing D2.
+            // Without additional operation, we end up with a block that we can't append.
+            // The same goes for the blocks mined between two polls.
+            let parent_block_hash = header
+                .parent_block_identifier
+                .get_hash_bytes_str()
+                .to_string();
+            ctx.try_log(|logger| {
+                slog::info!(
+                    logger,
+                    "Possible re-org detected, retrieving parent block {parent_block_hash}"
+                )
+            });
+            block_hashes.push_front(block_hash);
+            block_hashes.push_front(parent_block_hash);
+        }
+    }
+}
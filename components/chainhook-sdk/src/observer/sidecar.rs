@@ -0,0 +1,477 @@
+use std::sync::Arc;
+
+use chainhook_types::{BitcoinChainEvent, BlockIdentifier};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use hiro_system_kit::slog;
+
+use super::{BitcoinBlockDataCached, HandleBlock};
+use crate::chainhooks::bitcoin::BitcoinChainhookOccurrencePayload;
+use crate::chainhooks::stacks::StacksChainhookOccurrencePayload;
+use crate::utils::Context;
+
+/// Mutates the Bitcoin blocks received by the observer before predicates are evaluated on them,
+/// e.g. to attach the data computed by an indexer. Blocks are passed along with the blocks
+/// rolled back by the same chain event, and must be returned in the same order.
+pub trait BlockPreprocessor: Send + Sync {
+    fn preprocess_blocks(
+        &self,
+        blocks: Vec<BitcoinBlockDataCached>,
+        blocks_ids_to_rollback: Vec<BlockIdentifier>,
+        ctx: &Context,
+    ) -> Result<Vec<BitcoinBlockDataCached>, String>;
+}
+
+/// Asynchronous variant of [BlockPreprocessor].
+pub trait AsyncBlockPreprocessor: Send + Sync {
+    fn preprocess_blocks<'a>(
+        &'a self,
+        blocks: Vec<BitcoinBlockDataCached>,
+        blocks_ids_to_rollback: Vec<BlockIdentifier>,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<Vec<BitcoinBlockDataCached>, String>>;
+}
+
+/// Notified of every Bitcoin chain event, once its blocks went through the
+/// [BlockPreprocessor], before predicates are evaluated on them.
+pub trait ChainEventListener: Send + Sync {
+    fn on_chain_event(&self, chain_event: &BitcoinChainEvent, ctx: &Context);
+}
+
+/// Asynchronous variant of [ChainEventListener].
+pub trait AsyncChainEventListener: Send + Sync {
+    fn on_chain_event<'a>(
+        &'a self,
+        chain_event: &'a BitcoinChainEvent,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, ()>;
+}
+
+/// Handles the occurrences of the predicates whose action is `noop`, within the process running
+/// the observer, rather than draining them from the [super::ObserverEvent]s. Occurrences of both
+/// chains are ignored by default.
+pub trait HookActionHandler: Send + Sync {
+    fn on_bitcoin_occurrence(
+        &self,
+        _payload: &BitcoinChainhookOccurrencePayload,
+        _ctx: &Context,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_stacks_occurrence(
+        &self,
+        _payload: &StacksChainhookOccurrencePayload,
+        _ctx: &Context,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Asynchronous variant of [HookActionHandler].
+pub trait AsyncHookActionHandler: Send + Sync {
+    fn on_bitcoin_occurrence<'a>(
+        &'a self,
+        _payload: &'a BitcoinChainhookOccurrencePayload,
+        _ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        async { Ok(()) }.boxed()
+    }
+
+    fn on_stacks_occurrence<'a>(
+        &'a self,
+        _payload: &'a StacksChainhookOccurrencePayload,
+        _ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        async { Ok(()) }.boxed()
+    }
+}
+
+struct SyncBlockPreprocessor<P>(P);
+
+impl<P: BlockPreprocessor> AsyncBlockPreprocessor for SyncBlockPreprocessor<P> {
+    fn preprocess_blocks<'a>(
+        &'a self,
+        blocks: Vec<BitcoinBlockDataCached>,
+        blocks_ids_to_rollback: Vec<BlockIdentifier>,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<Vec<BitcoinBlockDataCached>, String>> {
+        let res = self
+            .0
+            .preprocess_blocks(blocks, blocks_ids_to_rollback, ctx);
+        async move { res }.boxed()
+    }
+}
+
+struct SyncChainEventListener<L>(L);
+
+impl<L: ChainEventListener> AsyncChainEventListener for SyncChainEventListener<L> {
+    fn on_chain_event<'a>(
+        &'a self,
+        chain_event: &'a BitcoinChainEvent,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, ()> {
+        self.0.on_chain_event(chain_event, ctx);
+        async {}.boxed()
+    }
+}
+
+struct SyncHookActionHandler<H>(H);
+
+impl<H: HookActionHandler> AsyncHookActionHandler for SyncHookActionHandler<H> {
+    fn on_bitcoin_occurrence<'a>(
+        &'a self,
+        payload: &'a BitcoinChainhookOccurrencePayload,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        let res = self.0.on_bitcoin_occurrence(payload, ctx);
+        async move { res }.boxed()
+    }
+
+    fn on_stacks_occurrence<'a>(
+        &'a self,
+        payload: &'a StacksChainhookOccurrencePayload,
+        ctx: &'a Context,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        let res = self.0.on_stacks_occurrence(payload, ctx);
+        async move { res }.boxed()
+    }
+}
+
+/// Extensions of the observer's handling of Bitcoin blocks and predicate occurrences.
+///
+/// Blocks can either be exchanged over channels, with `bitcoin_blocks_mutator` and
+/// `bitcoin_chain_event_notifier`, or handed to the plugins set with
+/// [ObserverSidecar::block_preprocessor] and [ObserverSidecar::chain_event_listener]. Channels
+/// run first when both are set. The occurrences of `noop` predicates are handed to the plugins
+/// set with [ObserverSidecar::hook_action_handler], in addition to being sent as
+/// [super::ObserverEvent]s.
+#[derive(Default)]
+pub struct ObserverSidecar {
+    pub bitcoin_blocks_mutator: Option<(
+        crossbeam_channel::Sender<(Vec<BitcoinBlockDataCached>, Vec<BlockIdentifier>)>,
+        crossbeam_channel::Receiver<Vec<BitcoinBlockDataCached>>,
+    )>,
+    pub bitcoin_chain_event_notifier: Option<crossbeam_channel::Sender<HandleBlock>>,
+    pub block_preprocessor: Option<Arc<dyn AsyncBlockPreprocessor>>,
+    pub chain_event_listeners: Vec<Arc<dyn AsyncChainEventListener>>,
+    pub hook_action_handlers: Vec<Arc<dyn AsyncHookActionHandler>>,
+}
+
+impl ObserverSidecar {
+    pub fn new() -> Self {
+        ObserverSidecar::default()
+    }
+
+    /// Sets the plugin mutating blocks before predicates are evaluated on them.
+    pub fn block_preprocessor<P: BlockPreprocessor + 'static>(
+        &mut self,
+        preprocessor: P,
+    ) -> &mut Self {
+        self.block_preprocessor = Some(Arc::new(SyncBlockPreprocessor(preprocessor)));
+        self
+    }
+
+    /// Sets the plugin mutating blocks before predicates are evaluated on them, see
+    /// [AsyncBlockPreprocessor].
+    pub fn async_block_preprocessor<P: AsyncBlockPreprocessor + 'static>(
+        &mut self,
+        preprocessor: P,
+    ) -> &mut Self {
+        self.block_preprocessor = Some(Arc::new(preprocessor));
+        self
+    }
+
+    /// Adds a plugin notified of every chain event. Listeners are notified in the order they
+    /// were added.
+    pub fn chain_event_listener<L: ChainEventListener + 'static>(
+        &mut self,
+        listener: L,
+    ) -> &mut Self {
+        self.chain_event_listeners
+            .push(Arc::new(SyncChainEventListener(listener)));
+        self
+    }
+
+    /// Adds a plugin notified of every chain event, see [AsyncChainEventListener].
+    pub fn async_chain_event_listener<L: AsyncChainEventListener + 'static>(
+        &mut self,
+        listener: L,
+    ) -> &mut Self {
+        self.chain_event_listeners.push(Arc::new(listener));
+        self
+    }
+
+    /// Adds a plugin handling the occurrences of `noop` predicates. Handlers are called in the
+    /// order they were added, a failing handler not preventing the next ones from being called.
+    pub fn hook_action_handler<H: HookActionHandler + 'static>(&mut self, handler: H) -> &mut Self {
+        self.hook_action_handlers
+            .push(Arc::new(SyncHookActionHandler(handler)));
+        self
+    }
+
+    /// Adds a plugin handling the occurrences of `noop` predicates, see [AsyncHookActionHandler].
+    pub fn async_hook_action_handler<H: AsyncHookActionHandler + 'static>(
+        &mut self,
+        handler: H,
+    ) -> &mut Self {
+        self.hook_action_handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// Whether blocks must go through the sidecar before being evaluated.
+    pub(super) fn mutates_blocks(&self) -> bool {
+        self.bitcoin_blocks_mutator.is_some() || self.block_preprocessor.is_some()
+    }
+
+    pub(super) async fn perform_bitcoin_sidecar_mutations(
+        &self,
+        blocks: Vec<BitcoinBlockDataCached>,
+        blocks_ids_to_rollback: Vec<BlockIdentifier>,
+        ctx: &Context,
+    ) -> Vec<BitcoinBlockDataCached> {
+        let blocks = if let Some(ref block_mutator) = self.bitcoin_blocks_mutator {
+            ctx.try_log(|logger| slog::info!(logger, "Sending blocks to pre-processor",));
+            let _ = block_mutator
+                .0
+                .send((blocks.clone(), blocks_ids_to_rollback.clone()));
+            ctx.try_log(|logger| slog::info!(logger, "Waiting for blocks from pre-processor",));
+            match block_mutator.1.recv() {
+                Ok(updated_blocks) => {
+                    ctx.try_log(|logger| slog::info!(logger, "Block received from pre-processor",));
+                    updated_blocks
+                }
+                Err(e) => {
+                    ctx.try_log(|logger| {
+                        slog::error!(
+                            logger,
+                            "Unable to receive block from pre-processor {}",
+                            e.to_string()
+                        )
+                    });
+                    blocks
+                }
+            }
+        } else {
+            blocks
+        };
+        let Some(ref preprocessor) = self.block_preprocessor else {
+            return blocks;
+        };
+        match preprocessor
+            .preprocess_blocks(blocks.clone(), blocks_ids_to_rollback, ctx)
+            .await
+        {
+            Ok(updated_blocks) => updated_blocks,
+            Err(e) => {
+                ctx.try_log(|logger| slog::error!(logger, "Unable to pre-process blocks: {}", e));
+                blocks
+            }
+        }
+    }
+
+    pub(super) async fn notify_chain_event(&self, chain_event: &BitcoinChainEvent, ctx: &Context) {
+        if let Some(ref notifier) = self.bitcoin_chain_event_notifier {
+            match chain_event {
+                BitcoinChainEvent::ChainUpdatedWithBlocks(data) => {
+                    for block in data.new_blocks.iter() {
+                        let _ = notifier.send(HandleBlock::ApplyBlock(block.clone()));
+                    }
+                }
+                BitcoinChainEvent::ChainUpdatedWithReorg(data) => {
+                    for block in data.blocks_to_rollback.iter() {
+                        let _ = notifier.send(HandleBlock::UndoBlock(block.clone()));
+                    }
+                    for block in data.blocks_to_apply.iter() {
+                        let _ = notifier.send(HandleBlock::ApplyBlock(block.clone()));
+                    }
+                }
+            }
+        }
+        for listener in self.chain_event_listeners.iter() {
+            listener.on_chain_event(chain_event, ctx).await;
+        }
+    }
+
+    pub(super) async fn handle_bitcoin_occurrence(
+        &self,
+        payload: &BitcoinChainhookOccurrencePayload,
+        ctx: &Context,
+    ) {
+        for handler in self.hook_action_handlers.iter() {
+            if let Err(e) = handler.on_bitcoin_occurrence(payload, ctx).await {
+                ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "Unable to handle occurrence of predicate {}: {}",
+                        payload.chainhook.uuid,
+                        e
+                    )
+                });
+            }
+        }
+    }
+
+    pub(super) async fn handle_stacks_occurrence(
+        &self,
+        payload: &StacksChainhookOccurrencePayload,
+        ctx: &Context,
+    ) {
+        for handler in self.hook_action_handlers.iter() {
+            if let Err(e) = handler.on_stacks_occurrence(payload, ctx).await {
+                ctx.try_log(|logger| {
+                    slog::error!(
+                        logger,
+                        "Unable to handle occurrence of predicate {}: {}",
+                        payload.chainhook.uuid,
+                        e
+                    )
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chainhook_types::{BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData, BlockIdentifier};
+
+    use super::{BlockPreprocessor, ChainEventListener, HookActionHandler, ObserverSidecar};
+    use crate::chainhooks::bitcoin::{BitcoinChainhookOccurrencePayload, BitcoinChainhookPayload};
+    use crate::chainhooks::stacks::{StacksChainhookOccurrencePayload, StacksChainhookPayload};
+    use crate::indexer::tests::helpers::bitcoin_blocks;
+    use crate::observer::BitcoinBlockDataCached;
+    use crate::utils::Context;
+
+    struct FlagBlocks;
+
+    impl BlockPreprocessor for FlagBlocks {
+        fn preprocess_blocks(
+            &self,
+            blocks: Vec<BitcoinBlockDataCached>,
+            _blocks_ids_to_rollback: Vec<BlockIdentifier>,
+            _ctx: &Context,
+        ) -> Result<Vec<BitcoinBlockDataCached>, String> {
+            Ok(blocks
+                .into_iter()
+                .map(|mut cache| {
+                    cache.processed_by_sidecar = true;
+                    cache
+                })
+                .collect())
+        }
+    }
+
+    struct RecordChainEvents(Arc<Mutex<Vec<usize>>>);
+
+    impl ChainEventListener for RecordChainEvents {
+        fn on_chain_event(&self, chain_event: &BitcoinChainEvent, _ctx: &Context) {
+            if let BitcoinChainEvent::ChainUpdatedWithBlocks(data) = chain_event {
+                self.0.lock().unwrap().push(data.new_blocks.len());
+            }
+        }
+    }
+
+    #[test]
+    fn it_runs_plugins() {
+        let ctx = Context::empty();
+        let recorded = Arc::new(Mutex::new(vec![]));
+        let mut sidecar = ObserverSidecar::new();
+        sidecar
+            .block_preprocessor(FlagBlocks)
+            .chain_event_listener(RecordChainEvents(recorded.clone()));
+        assert!(sidecar.mutates_blocks());
+
+        let blocks = vec![BitcoinBlockDataCached {
+            block: bitcoin_blocks::A1(None),
+            processed_by_sidecar: false,
+        }];
+        let blocks = hiro_system_kit::nestable_block_on(sidecar.perform_bitcoin_sidecar_mutations(
+            blocks,
+            vec![],
+            &ctx,
+        ));
+        assert!(blocks[0].processed_by_sidecar);
+
+        let chain_event =
+            BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+                new_blocks: vec![blocks[0].block.clone()],
+                confirmed_blocks: vec![],
+            });
+        hiro_system_kit::nestable_block_on(sidecar.notify_chain_event(&chain_event, &ctx));
+        assert_eq!(*recorded.lock().unwrap(), vec![1]);
+    }
+
+    struct FailingHandler;
+
+    impl HookActionHandler for FailingHandler {
+        fn on_bitcoin_occurrence(
+            &self,
+            _payload: &BitcoinChainhookOccurrencePayload,
+            _ctx: &Context,
+        ) -> Result<(), String> {
+            Err("unavailable".into())
+        }
+    }
+
+    struct RecordOccurrences(Arc<Mutex<Vec<String>>>);
+
+    impl HookActionHandler for RecordOccurrences {
+        fn on_bitcoin_occurrence(
+            &self,
+            payload: &BitcoinChainhookOccurrencePayload,
+            _ctx: &Context,
+        ) -> Result<(), String> {
+            self.0.lock().unwrap().push(payload.chainhook.uuid.clone());
+            Ok(())
+        }
+
+        fn on_stacks_occurrence(
+            &self,
+            payload: &StacksChainhookOccurrencePayload,
+            _ctx: &Context,
+        ) -> Result<(), String> {
+            self.0.lock().unwrap().push(payload.chainhook.uuid.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_runs_hook_action_handlers() {
+        let ctx = Context::empty();
+        let recorded = Arc::new(Mutex::new(vec![]));
+        let mut sidecar = ObserverSidecar::new();
+        sidecar
+            .hook_action_handler(FailingHandler)
+            .hook_action_handler(RecordOccurrences(recorded.clone()));
+        assert!(!sidecar.mutates_blocks());
+
+        let bitcoin_payload = BitcoinChainhookOccurrencePayload {
+            apply: vec![],
+            rollback: vec![],
+            chainhook: BitcoinChainhookPayload {
+                uuid: "bitcoin-predicate".into(),
+            },
+        };
+        let stacks_payload = StacksChainhookOccurrencePayload {
+            apply: vec![],
+            rollback: vec![],
+            chainhook: StacksChainhookPayload {
+                uuid: "stacks-predicate".into(),
+            },
+        };
+        hiro_system_kit::nestable_block_on(
+            sidecar.handle_bitcoin_occurrence(&bitcoin_payload, &ctx),
+        );
+        hiro_system_kit::nestable_block_on(sidecar.handle_stacks_occurrence(&stacks_payload, &ctx));
+        assert_eq!(
+            *recorded.lock().unwrap(),
+            vec![
+                "bitcoin-predicate".to_string(),
+                "stacks-predicate".to_string()
+            ]
+        );
+    }
+}
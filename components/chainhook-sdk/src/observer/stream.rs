@@ -0,0 +1,156 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::broadcast;
+
+/// Number of occurrences buffered for each predicate. Subscribers lagging further behind
+/// will skip the oldest occurrences.
+const OCCURRENCE_STREAM_CAPACITY: usize = 128;
+
+/// Number of past occurrences kept for each streamed predicate, so that subscribers
+/// reconnecting with the id of the last occurrence they received can resume.
+const OCCURRENCE_REPLAY_BUFFER_SIZE: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamedOccurrence {
+    /// Sequence number of the occurrence, starting at 1 for every predicate. Ids are not
+    /// persisted and start over when chainhook restarts.
+    pub id: u64,
+    pub payload: String,
+}
+
+#[derive(Debug)]
+struct OccurrenceStream {
+    tx: broadcast::Sender<StreamedOccurrence>,
+    last_id: u64,
+    replay_buffer: VecDeque<StreamedOccurrence>,
+}
+
+impl OccurrenceStream {
+    fn new() -> Self {
+        let (tx, _) = broadcast::channel(OCCURRENCE_STREAM_CAPACITY);
+        OccurrenceStream {
+            tx,
+            last_id: 0,
+            replay_buffer: VecDeque::with_capacity(OCCURRENCE_REPLAY_BUFFER_SIZE),
+        }
+    }
+}
+
+/// Registry of broadcast channels, one per predicate uuid, used to stream occurrences to
+/// live subscribers (such as the predicates API websockets and server-sent events) in
+/// addition to the predicate's `then_that` action.
+///
+/// A predicate is streamed from its first subscription until it is closed, even when all
+/// of its subscribers have disconnected, so that they can resume from the replay buffer.
+#[derive(Clone, Debug, Default)]
+pub struct OccurrenceStreams {
+    channels: Arc<RwLock<HashMap<String, OccurrenceStream>>>,
+}
+
+impl OccurrenceStreams {
+    pub fn new() -> Self {
+        OccurrenceStreams::default()
+    }
+
+    /// Subscribes to the occurrences of the predicate `uuid`, creating its channel if needed.
+    pub fn subscribe(&self, uuid: &str) -> broadcast::Receiver<StreamedOccurrence> {
+        let mut channels = self.channels.write().unwrap();
+        channels
+            .entry(uuid.to_string())
+            .or_insert_with(OccurrenceStream::new)
+            .tx
+            .subscribe()
+    }
+
+    /// Subscribes to the occurrences of the predicate `uuid`, also returning the buffered
+    /// occurrences published after `last_id`.
+    pub fn subscribe_after(
+        &self,
+        uuid: &str,
+        last_id: u64,
+    ) -> (
+        Vec<StreamedOccurrence>,
+        broadcast::Receiver<StreamedOccurrence>,
+    ) {
+        let mut channels = self.channels.write().unwrap();
+        let stream = channels
+            .entry(uuid.to_string())
+            .or_insert_with(OccurrenceStream::new);
+        let missed = stream
+            .replay_buffer
+            .iter()
+            .filter(|o| o.id > last_id)
+            .cloned()
+            .collect();
+        (missed, stream.tx.subscribe())
+    }
+
+    pub fn is_streamed(&self, uuid: &str) -> bool {
+        self.channels.read().unwrap().contains_key(uuid)
+    }
+
+    /// Broadcasts a serialized occurrence to the subscribers of the predicate `uuid`.
+    pub fn publish(&self, uuid: &str, payload: String) {
+        let mut channels = self.channels.write().unwrap();
+        let Some(stream) = channels.get_mut(uuid) else {
+            return;
+        };
+        stream.last_id += 1;
+        let occurrence = StreamedOccurrence {
+            id: stream.last_id,
+            payload,
+        };
+        if stream.replay_buffer.len() == OCCURRENCE_REPLAY_BUFFER_SIZE {
+            stream.replay_buffer.pop_front();
+        }
+        stream.replay_buffer.push_back(occurrence.clone());
+        // Sending only fails when nobody is currently subscribed.
+        let _ = stream.tx.send(occurrence);
+    }
+
+    /// Drops the channel of the predicate `uuid`, ending the streams of its subscribers.
+    pub fn close(&self, uuid: &str) {
+        self.channels.write().unwrap().remove(uuid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OccurrenceStreams, OCCURRENCE_REPLAY_BUFFER_SIZE};
+
+    #[tokio::test]
+    async fn it_broadcasts_occurrences_to_subscribers() {
+        let streams = OccurrenceStreams::new();
+        assert!(!streams.is_streamed("1234"));
+
+        let mut rx = streams.subscribe("1234");
+        assert!(streams.is_streamed("1234"));
+        streams.publish("1234", "{}".to_string());
+        streams.publish("5678", "{}".to_string());
+        let occurrence = rx.recv().await.unwrap();
+        assert_eq!(occurrence.id, 1);
+        assert_eq!(occurrence.payload, "{}");
+
+        streams.close("1234");
+        assert!(rx.recv().await.is_err());
+        assert!(!streams.is_streamed("1234"));
+    }
+
+    #[test]
+    fn it_replays_occurrences_after_last_id() {
+        let streams = OccurrenceStreams::new();
+        drop(streams.subscribe("1234"));
+        for i in 0..(OCCURRENCE_REPLAY_BUFFER_SIZE + 2) {
+            streams.publish("1234", format!("{i}"));
+        }
+
+        let (missed, _rx) = streams.subscribe_after("1234", 60);
+        let ids = missed.iter().map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![61, 62, 63, 64, 65, 66]);
+
+        let (missed, _rx) = streams.subscribe_after("1234", 0);
+        assert_eq!(missed.len(), OCCURRENCE_REPLAY_BUFFER_SIZE);
+        assert_eq!(missed[0].id, 3);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use chainhook_types::{BitcoinChainEvent, StacksChainEvent};
+use crossbeam_channel::{Receiver, Sender};
+use futures::Stream;
+use tokio::sync::mpsc;
+
+use super::ObserverEvent;
+use crate::chainhooks::bitcoin::BitcoinChainhookOccurrencePayload;
+use crate::chainhooks::stacks::StacksChainhookOccurrencePayload;
+
+/// Event concerning a single predicate, see [ObserverSubscriptions::subscribe_predicate].
+#[derive(Clone, Debug)]
+pub enum PredicateEvent {
+    BitcoinOccurrence(BitcoinChainhookOccurrencePayload),
+    StacksOccurrence(StacksChainhookOccurrencePayload),
+    /// The predicate was interrupted by a failed delivery.
+    Interrupted(String),
+    /// The predicate was deregistered. This is the last event sent to its subscribers.
+    Deregistered,
+}
+
+/// Default number of events buffered for each subscriber, see
+/// [ObserverSubscriptions::set_capacity].
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 256;
+
+/// Sending half of a subscription, either to a channel or to a [Stream].
+enum Subscriber<T> {
+    Channel(Sender<T>),
+    Stream(mpsc::Sender<T>),
+}
+
+impl<T> Subscriber<T> {
+    /// Returns whether the subscriber is still connected. Subscribers lagging behind, whose
+    /// buffer is full, are disconnected rather than waited for.
+    fn send(&self, event: T) -> bool {
+        match self {
+            Subscriber::Channel(tx) => tx.try_send(event).is_ok(),
+            Subscriber::Stream(tx) => tx.try_send(event).is_ok(),
+        }
+    }
+}
+
+struct SubscriptionStream<T>(mpsc::Receiver<T>);
+
+impl<T> Stream for SubscriptionStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+struct Subscribers {
+    capacity: usize,
+    bitcoin: Vec<Subscriber<BitcoinChainEvent>>,
+    stacks: Vec<Subscriber<StacksChainEvent>>,
+    predicates: HashMap<String, Vec<Subscriber<PredicateEvent>>>,
+}
+
+impl Default for Subscribers {
+    fn default() -> Self {
+        Subscribers {
+            capacity: DEFAULT_SUBSCRIPTION_CAPACITY,
+            bitcoin: vec![],
+            stacks: vec![],
+            predicates: HashMap::new(),
+        }
+    }
+}
+
+/// Handle used to subscribe to typed events of the observer, rather than matching on every
+/// [ObserverEvent]. Obtained with [super::EventObserverBuilder::subscriptions], it can be
+/// cloned and used before or after the observer is started.
+///
+/// Events can either be received over channels, e.g. with
+/// [ObserverSubscriptions::subscribe_bitcoin], or as [Stream]s for tokio-based applications, e.g.
+/// with [ObserverSubscriptions::bitcoin_events]. Receivers are disconnected, and streams end, once
+/// the observer terminates. Subscribers are dropped once their receiver is disconnected, or once
+/// they lag behind by more events than their buffer holds, so that a subscriber that stopped
+/// reading doesn't hold events in memory.
+#[derive(Clone, Default)]
+pub struct ObserverSubscriptions {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+impl ObserverSubscriptions {
+    pub fn new() -> Self {
+        ObserverSubscriptions::default()
+    }
+
+    /// Sets the number of events buffered for each subscriber created afterwards, at least one.
+    /// A subscriber is disconnected once its buffer is full, see [ObserverSubscriptions].
+    pub fn set_capacity(&self, capacity: usize) {
+        self.subscribers.lock().unwrap().capacity = capacity.max(1);
+    }
+
+    /// Subscribes to the Bitcoin chain events evaluated by the observer.
+    pub fn subscribe_bitcoin(&self) -> Receiver<BitcoinChainEvent> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = crossbeam_channel::bounded(subscribers.capacity);
+        subscribers.bitcoin.push(Subscriber::Channel(tx));
+        rx
+    }
+
+    /// Streams the Bitcoin chain events evaluated by the observer.
+    pub fn bitcoin_events(&self) -> impl Stream<Item = BitcoinChainEvent> + Send + Unpin {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = mpsc::channel(subscribers.capacity);
+        subscribers.bitcoin.push(Subscriber::Stream(tx));
+        SubscriptionStream(rx)
+    }
+
+    /// Subscribes to the Stacks chain events evaluated by the observer.
+    pub fn subscribe_stacks(&self) -> Receiver<StacksChainEvent> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = crossbeam_channel::bounded(subscribers.capacity);
+        subscribers.stacks.push(Subscriber::Channel(tx));
+        rx
+    }
+
+    /// Streams the Stacks chain events evaluated by the observer.
+    pub fn stacks_events(&self) -> impl Stream<Item = StacksChainEvent> + Send + Unpin {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = mpsc::channel(subscribers.capacity);
+        subscribers.stacks.push(Subscriber::Stream(tx));
+        SubscriptionStream(rx)
+    }
+
+    /// Subscribes to the occurrences of the predicate `uuid`. The receiver is disconnected
+    /// once the predicate is deregistered.
+    pub fn subscribe_predicate(&self, uuid: &str) -> Receiver<PredicateEvent> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = crossbeam_channel::bounded(subscribers.capacity);
+        subscribers
+            .predicates
+            .entry(uuid.to_string())
+            .or_default()
+            .push(Subscriber::Channel(tx));
+        rx
+    }
+
+    /// Streams the occurrences of the predicate `uuid`. The stream ends once the predicate is
+    /// deregistered.
+    pub fn predicate_events(
+        &self,
+        uuid: &str,
+    ) -> impl Stream<Item = PredicateEvent> + Send + Unpin {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let (tx, rx) = mpsc::channel(subscribers.capacity);
+        subscribers
+            .predicates
+            .entry(uuid.to_string())
+            .or_default()
+            .push(Subscriber::Stream(tx));
+        SubscriptionStream(rx)
+    }
+
+    /// Forwards `event` to the matching subscribers.
+    pub(super) fn dispatch(&self, event: &ObserverEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        match event {
+            ObserverEvent::BitcoinChainEvent((chain_event, _)) => {
+                subscribers
+                    .bitcoin
+                    .retain(|tx| tx.send(chain_event.clone()));
+            }
+            ObserverEvent::StacksChainEvent((chain_event, _)) => {
+                subscribers.stacks.retain(|tx| tx.send(chain_event.clone()));
+            }
+            ObserverEvent::BitcoinPredicateTriggered(payload) => {
+                subscribers.notify_predicate(
+                    &payload.chainhook.uuid,
+                    PredicateEvent::BitcoinOccurrence(payload.clone()),
+                );
+            }
+            ObserverEvent::StacksPredicateTriggered(payload) => {
+                subscribers.notify_predicate(
+                    &payload.chainhook.uuid,
+                    PredicateEvent::StacksOccurrence(payload.clone()),
+                );
+            }
+            ObserverEvent::PredicateInterrupted(data) => {
+                // Keys are either `predicate:{uuid}` or `predicate:{namespace}:{uuid}`
+                let uuid = data
+                    .predicate_key
+                    .rsplit(':')
+                    .next()
+                    .unwrap_or(&data.predicate_key);
+                subscribers.notify_predicate(uuid, PredicateEvent::Interrupted(data.error.clone()));
+            }
+            ObserverEvent::PredicateDeregistered(data) => {
+                subscribers.notify_predicate(&data.predicate_uuid, PredicateEvent::Deregistered);
+                subscribers.predicates.remove(&data.predicate_uuid);
+            }
+            ObserverEvent::Terminate => {
+                subscribers.bitcoin.clear();
+                subscribers.stacks.clear();
+                subscribers.predicates.clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Subscribers {
+    fn notify_predicate(&mut self, uuid: &str, event: PredicateEvent) {
+        let Some(senders) = self.predicates.get_mut(uuid) else {
+            return;
+        };
+        senders.retain(|tx| tx.send(event.clone()));
+        if senders.is_empty() {
+            self.predicates.remove(uuid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_types::{BitcoinChainEvent, BitcoinChainUpdatedWithBlocksData};
+    use futures::StreamExt;
+
+    use super::{ObserverSubscriptions, PredicateEvent};
+    use crate::chainhooks::bitcoin::{BitcoinChainhookOccurrencePayload, BitcoinChainhookPayload};
+    use crate::chainhooks::types::Chain;
+    use crate::indexer::tests::helpers::bitcoin_blocks;
+    use crate::observer::{ObserverEvent, PredicateDeregisteredEvent, PredicateEvaluationReport};
+
+    #[test]
+    fn it_dispatches_typed_events() {
+        let subscriptions = ObserverSubscriptions::new();
+        let bitcoin_rx = subscriptions.subscribe_bitcoin();
+        let stacks_rx = subscriptions.subscribe_stacks();
+        let predicate_rx = subscriptions.subscribe_predicate("1234");
+        let other_predicate_rx = subscriptions.subscribe_predicate("5678");
+
+        let chain_event =
+            BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+                new_blocks: vec![bitcoin_blocks::A1(None)],
+                confirmed_blocks: vec![],
+            });
+        subscriptions.dispatch(&ObserverEvent::BitcoinChainEvent((
+            chain_event,
+            PredicateEvaluationReport::new(),
+        )));
+        subscriptions.dispatch(&ObserverEvent::BitcoinPredicateTriggered(
+            BitcoinChainhookOccurrencePayload {
+                apply: vec![],
+                rollback: vec![],
+                chainhook: BitcoinChainhookPayload {
+                    uuid: "1234".to_string(),
+                },
+            },
+        ));
+        subscriptions.dispatch(&ObserverEvent::PredicateDeregistered(
+            PredicateDeregisteredEvent {
+                predicate_uuid: "1234".to_string(),
+                chain: Chain::Bitcoin,
+            },
+        ));
+
+        assert!(matches!(
+            bitcoin_rx.try_recv(),
+            Ok(BitcoinChainEvent::ChainUpdatedWithBlocks(_))
+        ));
+        assert!(stacks_rx.try_recv().is_err());
+        assert!(matches!(
+            predicate_rx.try_recv(),
+            Ok(PredicateEvent::BitcoinOccurrence(_))
+        ));
+        assert!(matches!(
+            predicate_rx.try_recv(),
+            Ok(PredicateEvent::Deregistered)
+        ));
+        assert!(predicate_rx.recv().is_err());
+        assert!(other_predicate_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn it_disconnects_lagging_subscribers() {
+        let subscriptions = ObserverSubscriptions::new();
+        subscriptions.set_capacity(1);
+        let lagging_rx = subscriptions.subscribe_predicate("1234");
+        let predicate_rx = subscriptions.subscribe_predicate("1234");
+
+        for _ in 0..2 {
+            subscriptions.dispatch(&ObserverEvent::BitcoinPredicateTriggered(
+                BitcoinChainhookOccurrencePayload {
+                    apply: vec![],
+                    rollback: vec![],
+                    chainhook: BitcoinChainhookPayload {
+                        uuid: "1234".to_string(),
+                    },
+                },
+            ));
+            assert!(matches!(
+                predicate_rx.try_recv(),
+                Ok(PredicateEvent::BitcoinOccurrence(_))
+            ));
+        }
+
+        assert!(matches!(
+            lagging_rx.try_recv(),
+            Ok(PredicateEvent::BitcoinOccurrence(_))
+        ));
+        assert!(lagging_rx.recv().is_err());
+    }
+
+    #[test]
+    fn it_streams_typed_events() {
+        let subscriptions = ObserverSubscriptions::new();
+        let mut bitcoin_events = subscriptions.bitcoin_events();
+        let mut predicate_events = subscriptions.predicate_events("1234");
+
+        let chain_event =
+            BitcoinChainEvent::ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData {
+                new_blocks: vec![bitcoin_blocks::A1(None)],
+                confirmed_blocks: vec![],
+            });
+        subscriptions.dispatch(&ObserverEvent::BitcoinChainEvent((
+            chain_event,
+            PredicateEvaluationReport::new(),
+        )));
+        subscriptions.dispatch(&ObserverEvent::BitcoinPredicateTriggered(
+            BitcoinChainhookOccurrencePayload {
+                apply: vec![],
+                rollback: vec![],
+                chainhook: BitcoinChainhookPayload {
+                    uuid: "1234".to_string(),
+                },
+            },
+        ));
+        subscriptions.dispatch(&ObserverEvent::Terminate);
+
+        hiro_system_kit::nestable_block_on(async {
+            assert!(matches!(
+                bitcoin_events.next().await,
+                Some(BitcoinChainEvent::ChainUpdatedWithBlocks(_))
+            ));
+            assert!(bitcoin_events.next().await.is_none());
+            assert!(matches!(
+                predicate_events.next().await,
+                Some(PredicateEvent::BitcoinOccurrence(_))
+            ));
+            assert!(predicate_events.next().await.is_none());
+        });
+    }
+}
@@ -19,10 +19,15 @@ use crate::indexer::tests::helpers::transactions::generate_test_tx_bitcoin_p2pkh
 use crate::indexer::tests::helpers::{
     accounts, bitcoin_blocks, stacks_blocks, transactions::generate_test_tx_stacks_contract_call,
 };
+use crate::indexer::MalformedStacksTransactionPolicy;
 use crate::monitoring::PrometheusMonitoring;
-use crate::observer::PredicateDeregisteredEvent;
 use crate::observer::{
-    start_observer_commands_handler, EventObserverConfig, ObserverCommand, ObserverSidecar,
+    start_observer_commands_handler, EventObserverConfig, EventObserverConfigBuilder,
+    InMemoryOccurrenceCountStore, ObserverCommand, ObserverDependencies, ObserverSidecar,
+    OccurrenceCountStore,
+};
+use crate::observer::{
+    PredicateDeregisteredEvent, PredicatePausedData, PredicateRescanData, RescanPredicateData,
 };
 use crate::utils::{AbstractBlock, Context};
 use chainhook_types::{
@@ -33,10 +38,11 @@ use chainhook_types::{
 };
 use hiro_system_kit;
 use std::collections::BTreeMap;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
 
 use super::PredicatesConfig;
-use super::{ObserverEvent, DEFAULT_INGESTION_PORT};
+use super::{ObserverEvent, DEFAULT_INGESTION_PORT, DEFAULT_OBSERVER_COMMANDS_CAPACITY};
 
 fn generate_test_config() -> (EventObserverConfig, ChainhookStore) {
     let config: EventObserverConfig = EventObserverConfig {
@@ -46,6 +52,8 @@ fn generate_test_config() -> (EventObserverConfig, ChainhookStore) {
         bitcoind_rpc_username: "user".into(),
         bitcoind_rpc_password: "user".into(),
         bitcoind_rpc_url: "http://localhost:18443".into(),
+        bitcoind_rpc_fallbacks: vec![],
+        esplora_api_url: None,
         display_stacks_ingestion_logs: false,
         bitcoin_block_signaling: BitcoinBlockSignaling::Stacks(
             StacksNodeConfig::default_localhost(DEFAULT_INGESTION_PORT),
@@ -53,6 +61,12 @@ fn generate_test_config() -> (EventObserverConfig, ChainhookStore) {
         bitcoin_network: BitcoinNetwork::Regtest,
         stacks_network: StacksNetwork::Devnet,
         prometheus_monitoring_port: None,
+        bitcoin_confirmation_depth: None,
+        stacks_confirmation_depth: None,
+        bitcoin_block_cache_size: None,
+        bitcoin_prevout_cache_size: None,
+        network_parameters: None,
+        malformed_stacks_transactions: MalformedStacksTransactionPolicy::Skip,
     };
     (config, ChainhookStore::new())
 }
@@ -71,6 +85,13 @@ fn stacks_chainhook_contract_call(
             end_block: None,
             blocks: None,
             expire_after_occurrence,
+            rollback_action: None,
+            payload_envelope: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
             capture_all_events: None,
             decode_clarity_values: Some(true),
             include_contract_abi: None,
@@ -87,6 +108,9 @@ fn stacks_chainhook_contract_call(
         uuid: format!("{}", id),
         name: format!("Chainhook {}", id),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         networks,
         version: 1,
     }
@@ -105,6 +129,13 @@ fn bitcoin_chainhook_p2pkh(
             end_block: None,
             blocks: None,
             expire_after_occurrence,
+            rollback_action: None,
+            payload_envelope: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
             predicate: BitcoinPredicateType::Outputs(OutputPredicate::P2pkh(
                 ExactMatchingRule::Equals(address.to_string()),
             )),
@@ -121,6 +152,9 @@ fn bitcoin_chainhook_p2pkh(
         uuid: format!("{}", id),
         name: format!("Chainhook {}", id),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         version: 1,
         networks,
     }
@@ -135,6 +169,13 @@ fn bitcoin_chainhook_ordinals(id: u8) -> BitcoinChainhookSpecificationNetworkMap
             end_block: None,
             blocks: None,
             expire_after_occurrence: None,
+            rollback_action: None,
+            payload_envelope: None,
+            max_batch_size: None,
+            max_batch_wait_ms: None,
+            heartbeat_interval_minutes: None,
+            include_fields: None,
+            transform: None,
             predicate: BitcoinPredicateType::OrdinalsProtocol(OrdinalOperations::InscriptionFeed(
                 InscriptionFeedData {
                     meta_protocols: None,
@@ -153,13 +194,16 @@ fn bitcoin_chainhook_ordinals(id: u8) -> BitcoinChainhookSpecificationNetworkMap
         uuid: format!("{}", id),
         name: format!("Chainhook {}", id),
         owner_uuid: None,
+        labels: None,
+        metadata: None,
+        namespace: None,
         version: 1,
         networks,
     }
 }
 
 fn generate_and_register_new_stacks_chainhook(
-    observer_commands_tx: &Sender<ObserverCommand>,
+    observer_commands_tx: &SyncSender<ObserverCommand>,
     observer_events_rx: &crossbeam_channel::Receiver<ObserverEvent>,
     id: u8,
     contract_name: &str,
@@ -204,7 +248,7 @@ fn generate_and_register_new_stacks_chainhook(
 }
 
 fn generate_and_register_new_bitcoin_chainhook(
-    observer_commands_tx: &Sender<ObserverCommand>,
+    observer_commands_tx: &SyncSender<ObserverCommand>,
     observer_events_rx: &crossbeam_channel::Receiver<ObserverEvent>,
     id: u8,
     p2pkh_address: &str,
@@ -325,7 +369,7 @@ fn assert_observer_metrics_bitcoin_deregistered_predicates(
 }
 
 fn generate_and_register_new_ordinals_chainhook(
-    observer_commands_tx: &Sender<ObserverCommand>,
+    observer_commands_tx: &SyncSender<ObserverCommand>,
     observer_events_rx: &crossbeam_channel::Receiver<ObserverEvent>,
     id: u8,
 ) -> BitcoinChainhookInstance {
@@ -357,7 +401,8 @@ fn generate_and_register_new_ordinals_chainhook(
 
 #[test]
 fn test_stacks_chainhook_register_deregister() {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
     let prometheus_monitoring = PrometheusMonitoring::new();
     let prometheus_monitoring_moved = prometheus_monitoring.clone();
@@ -368,10 +413,11 @@ fn test_stacks_chainhook_register_deregister() {
             config,
             chainhook_store,
             observer_commands_rx,
-            Some(observer_events_tx),
+            Some(observer_events_tx.into()),
             None,
             prometheus_monitoring_moved,
             None,
+            ObserverDependencies::default(),
             Context::empty(),
         ));
     });
@@ -565,9 +611,257 @@ fn test_stacks_chainhook_register_deregister() {
     handle.join().expect("unable to terminate thread");
 }
 
+#[test]
+fn test_stacks_chainhook_pause_resume() {
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
+    let prometheus_monitoring = PrometheusMonitoring::new();
+
+    let handle = std::thread::spawn(move || {
+        let (config, chainhook_store) = generate_test_config();
+        let _ = hiro_system_kit::nestable_block_on(start_observer_commands_handler(
+            config,
+            chainhook_store,
+            observer_commands_rx,
+            Some(observer_events_tx.into()),
+            None,
+            prometheus_monitoring,
+            None,
+            ObserverDependencies::default(),
+            Context::empty(),
+        ));
+    });
+
+    let chainhook = generate_and_register_new_stacks_chainhook(
+        &observer_commands_tx,
+        &observer_events_rx,
+        1,
+        "counter",
+        "increment",
+    );
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    let _ = observer_commands_tx.send(ObserverCommand::PausePredicate(chainhook.uuid.clone()));
+    assert!(match observer_events_rx.recv() {
+        Ok(ObserverEvent::PredicatePaused(PredicatePausedData {
+            predicate,
+            requested,
+            ..
+        })) => {
+            assert_eq!(predicate.uuid(), chainhook.uuid);
+            assert!(requested);
+            true
+        }
+        _ => false,
+    });
+
+    // Simulate a block that would trigger the paused predicate
+    let transactions = vec![generate_test_tx_stacks_contract_call(
+        0,
+        &accounts::wallet_1_stx_address(),
+        "counter",
+        "increment",
+        vec!["u1"],
+    )];
+    let chain_event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate::new(
+            stacks_blocks::generate_test_stacks_block(0, 1, transactions, None).expect_block(),
+        )],
+        confirmed_blocks: vec![],
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event));
+    assert_predicates_triggered_event(&observer_events_rx, 0);
+    assert_stacks_chain_event(&observer_events_rx);
+
+    // A scan completing doesn't enable a paused predicate
+    let _ = observer_commands_tx.send(ObserverCommand::EnablePredicate(ChainhookInstance::Stacks(
+        chainhook.clone(),
+    )));
+
+    let _ = observer_commands_tx.send(ObserverCommand::ResumePredicate(chainhook.uuid.clone()));
+    assert!(match observer_events_rx.recv() {
+        Ok(ObserverEvent::PredicateResumed(predicate)) => {
+            assert_eq!(predicate.uuid(), chainhook.uuid);
+            true
+        }
+        _ => false,
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::EnablePredicate(ChainhookInstance::Stacks(
+        chainhook.clone(),
+    )));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    let transactions = vec![generate_test_tx_stacks_contract_call(
+        1,
+        &accounts::wallet_1_stx_address(),
+        "counter",
+        "increment",
+        vec!["u1"],
+    )];
+    let chain_event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate::new(
+            stacks_blocks::generate_test_stacks_block(0, 2, transactions, None).expect_block(),
+        )],
+        confirmed_blocks: vec![],
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event));
+    assert_predicates_triggered_event(&observer_events_rx, 1);
+
+    let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+    handle.join().expect("unable to terminate thread");
+}
+
+#[test]
+fn test_stacks_chainhook_rescan() {
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
+    let prometheus_monitoring = PrometheusMonitoring::new();
+
+    let handle = std::thread::spawn(move || {
+        let (config, chainhook_store) = generate_test_config();
+        let _ = hiro_system_kit::nestable_block_on(start_observer_commands_handler(
+            config,
+            chainhook_store,
+            observer_commands_rx,
+            Some(observer_events_tx.into()),
+            None,
+            prometheus_monitoring,
+            None,
+            ObserverDependencies::default(),
+            Context::empty(),
+        ));
+    });
+
+    let chainhook = generate_and_register_new_stacks_chainhook(
+        &observer_commands_tx,
+        &observer_events_rx,
+        1,
+        "counter",
+        "increment",
+    );
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    let _ = observer_commands_tx.send(ObserverCommand::RescanPredicate(RescanPredicateData {
+        predicate_uuid: chainhook.uuid.clone(),
+        start_block: 10,
+        end_block: 20,
+    }));
+    assert!(match observer_events_rx.recv() {
+        Ok(ObserverEvent::PredicateRescanRequested(PredicateRescanData {
+            predicate,
+            start_block,
+            end_block,
+        })) => {
+            assert_eq!(predicate.uuid(), chainhook.uuid);
+            assert_eq!((start_block, end_block), (10, 20));
+            true
+        }
+        _ => false,
+    });
+
+    // Unknown predicates are not rescanned
+    let _ = observer_commands_tx.send(ObserverCommand::RescanPredicate(RescanPredicateData {
+        predicate_uuid: "unknown".into(),
+        start_block: 10,
+        end_block: 20,
+    }));
+    let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::Info(_))
+    ));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::Terminate)
+    ));
+    handle.join().expect("unable to terminate thread");
+}
+
+#[test]
+fn test_stacks_chainhook_update() {
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
+    let prometheus_monitoring = PrometheusMonitoring::new();
+
+    let handle = std::thread::spawn(move || {
+        let (config, chainhook_store) = generate_test_config();
+        let _ = hiro_system_kit::nestable_block_on(start_observer_commands_handler(
+            config,
+            chainhook_store,
+            observer_commands_rx,
+            Some(observer_events_tx.into()),
+            None,
+            prometheus_monitoring,
+            None,
+            ObserverDependencies::default(),
+            Context::empty(),
+        ));
+    });
+
+    let chainhook = generate_and_register_new_stacks_chainhook(
+        &observer_commands_tx,
+        &observer_events_rx,
+        1,
+        "counter",
+        "increment",
+    );
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    // Update the predicate to match another method
+    let contract_identifier = format!("{}.{}", accounts::deployer_stx_address(), "counter");
+    let updated_chainhook =
+        stacks_chainhook_contract_call(1, &contract_identifier, None, "decrement");
+    let _ = observer_commands_tx.send(ObserverCommand::UpdatePredicate(
+        ChainhookSpecificationNetworkMap::Stacks(updated_chainhook),
+    ));
+    assert!(match observer_events_rx.recv() {
+        Ok(ObserverEvent::PredicateUpdated(ChainhookInstance::Stacks(predicate))) => {
+            assert_eq!(predicate.uuid, chainhook.uuid);
+            assert!(predicate.enabled);
+            true
+        }
+        _ => false,
+    });
+
+    let transactions = vec![generate_test_tx_stacks_contract_call(
+        0,
+        &accounts::wallet_1_stx_address(),
+        "counter",
+        "decrement",
+        vec!["u1"],
+    )];
+    let chain_event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate::new(
+            stacks_blocks::generate_test_stacks_block(0, 1, transactions, None).expect_block(),
+        )],
+        confirmed_blocks: vec![],
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event));
+    assert_predicates_triggered_event(&observer_events_rx, 1);
+
+    let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+    handle.join().expect("unable to terminate thread");
+}
+
 #[test]
 fn test_stacks_chainhook_auto_deregister() {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
     let prometheus_monitoring = PrometheusMonitoring::new();
     let prometheus_monitoring_moved = prometheus_monitoring.clone();
@@ -578,10 +872,11 @@ fn test_stacks_chainhook_auto_deregister() {
             config,
             chainhook_store,
             observer_commands_rx,
-            Some(observer_events_tx),
+            Some(observer_events_tx.into()),
             None,
             prometheus_monitoring_moved,
             None,
+            ObserverDependencies::default(),
             Context::empty(),
         ));
     });
@@ -720,9 +1015,173 @@ fn test_stacks_chainhook_auto_deregister() {
     handle.join().expect("unable to terminate thread");
 }
 
+#[test]
+fn test_stacks_chainhook_restored_occurrences() {
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
+    let prometheus_monitoring = PrometheusMonitoring::new();
+    // The predicate already occurred once before the observer restarted
+    let occurrence_count_store = InMemoryOccurrenceCountStore::new();
+    occurrence_count_store.set("0", 1).unwrap();
+    let occurrence_count_store_moved: Arc<dyn OccurrenceCountStore> =
+        Arc::new(occurrence_count_store.clone());
+
+    let handle = std::thread::spawn(move || {
+        let (config, chainhook_store) = generate_test_config();
+        let _ = hiro_system_kit::nestable_block_on(start_observer_commands_handler(
+            config,
+            chainhook_store,
+            observer_commands_rx,
+            Some(observer_events_tx.into()),
+            None,
+            prometheus_monitoring,
+            None,
+            ObserverDependencies {
+                occurrence_count_store: Some(occurrence_count_store_moved),
+                ..Default::default()
+            },
+            Context::empty(),
+        ));
+    });
+
+    let contract_identifier = format!("{}.{}", accounts::deployer_stx_address(), "counter");
+    let chainhook = stacks_chainhook_contract_call(0, &contract_identifier, Some(1), "increment");
+    let _ = observer_commands_tx.send(ObserverCommand::RegisterPredicate(
+        ChainhookSpecificationNetworkMap::Stacks(chainhook.clone()),
+    ));
+    let mut chainhook = chainhook
+        .into_specification_for_network(&StacksNetwork::Devnet)
+        .unwrap();
+    chainhook.enabled = true;
+    let _ = observer_commands_tx.send(ObserverCommand::EnablePredicate(ChainhookInstance::Stacks(
+        chainhook.clone(),
+    )));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateRegistered(_))
+    ));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    // Simulate a block that does include a trigger
+    let transactions = vec![generate_test_tx_stacks_contract_call(
+        0,
+        &accounts::wallet_1_stx_address(),
+        "counter",
+        "increment",
+        vec!["u1"],
+    )];
+    let chain_event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate::new(
+            stacks_blocks::generate_test_stacks_block(0, 1, transactions, None).expect_block(),
+        )],
+        confirmed_blocks: vec![],
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event));
+    // The restored occurrence counts, so the predicate expires instead of triggering
+    assert_predicates_triggered_event(&observer_events_rx, 0);
+    assert!(match observer_events_rx.recv() {
+        Ok(ObserverEvent::PredicateDeregistered(PredicateDeregisteredEvent {
+            predicate_uuid: deregistered_hook,
+            ..
+        })) => {
+            assert_eq!(deregistered_hook, chainhook.uuid);
+            true
+        }
+        _ => false,
+    });
+    assert_stacks_chain_event(&observer_events_rx);
+    assert_eq!(occurrence_count_store.load().unwrap().get("0"), None);
+
+    let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+    handle.join().expect("unable to terminate thread");
+}
+
+#[test]
+fn test_stacks_chainhook_drained_on_termination() {
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
+    let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
+    let prometheus_monitoring = PrometheusMonitoring::new();
+
+    let handle = std::thread::spawn(move || {
+        let (config, chainhook_store) = generate_test_config();
+        let _ = hiro_system_kit::nestable_block_on(start_observer_commands_handler(
+            config,
+            chainhook_store,
+            observer_commands_rx,
+            Some(observer_events_tx.into()),
+            None,
+            prometheus_monitoring,
+            None,
+            ObserverDependencies::default(),
+            Context::empty(),
+        ));
+    });
+
+    let contract_identifier = format!("{}.{}", accounts::deployer_stx_address(), "counter");
+    let chainhook = stacks_chainhook_contract_call(0, &contract_identifier, None, "increment");
+    let _ = observer_commands_tx.send(ObserverCommand::RegisterPredicate(
+        ChainhookSpecificationNetworkMap::Stacks(chainhook.clone()),
+    ));
+    let mut chainhook = chainhook
+        .into_specification_for_network(&StacksNetwork::Devnet)
+        .unwrap();
+    chainhook.enabled = true;
+    let _ = observer_commands_tx.send(ObserverCommand::EnablePredicate(ChainhookInstance::Stacks(
+        chainhook.clone(),
+    )));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateRegistered(_))
+    ));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::PredicateEnabled(_))
+    ));
+
+    // Queue a block that does include a trigger, immediately followed by the termination
+    let transactions = vec![generate_test_tx_stacks_contract_call(
+        0,
+        &accounts::wallet_1_stx_address(),
+        "counter",
+        "increment",
+        vec!["u1"],
+    )];
+    let chain_event = StacksChainEvent::ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData {
+        new_blocks: vec![StacksBlockUpdate::new(
+            stacks_blocks::generate_test_stacks_block(0, 1, transactions, None).expect_block(),
+        )],
+        confirmed_blocks: vec![],
+    });
+    let _ = observer_commands_tx.send(ObserverCommand::PropagateStacksChainEvent(chain_event));
+    let _ = observer_commands_tx.send(ObserverCommand::Terminate);
+
+    // The queued block is still processed before the observer terminates
+    assert_predicates_triggered_event(&observer_events_rx, 1);
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::StacksPredicateTriggered(_))
+    ));
+    assert_stacks_chain_event(&observer_events_rx);
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::Info(_))
+    ));
+    assert!(matches!(
+        observer_events_rx.recv(),
+        Ok(ObserverEvent::Terminate)
+    ));
+    handle.join().expect("unable to terminate thread");
+}
+
 #[test]
 fn test_bitcoin_chainhook_register_deregister() {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
     let prometheus_monitoring = PrometheusMonitoring::new();
     let prometheus_monitoring_moved = prometheus_monitoring.clone();
@@ -733,10 +1192,11 @@ fn test_bitcoin_chainhook_register_deregister() {
             config,
             chainhook_store,
             observer_commands_rx,
-            Some(observer_events_tx),
+            Some(observer_events_tx.into()),
             None,
             prometheus_monitoring_moved,
             None,
+            ObserverDependencies::default(),
             Context::empty(),
         ));
     });
@@ -939,7 +1399,8 @@ fn test_bitcoin_chainhook_register_deregister() {
 
 #[test]
 fn test_bitcoin_chainhook_auto_deregister() {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let (observer_events_tx, observer_events_rx) = crossbeam_channel::unbounded();
     let prometheus_monitoring = PrometheusMonitoring::new();
     let prometheus_monitoring_moved = prometheus_monitoring.clone();
@@ -950,10 +1411,11 @@ fn test_bitcoin_chainhook_auto_deregister() {
             config,
             chainhook_store,
             observer_commands_rx,
-            Some(observer_events_tx),
+            Some(observer_events_tx.into()),
             None,
             prometheus_monitoring_moved,
             None,
+            ObserverDependencies::default(),
             Context::empty(),
         ));
     });
@@ -1107,7 +1569,8 @@ fn test_bitcoin_chainhook_auto_deregister() {
 
 #[test]
 fn test_bitcoin_chainhook_through_reorg() {
-    let (observer_commands_tx, observer_commands_rx) = channel();
+    let (observer_commands_tx, observer_commands_rx) =
+        sync_channel(DEFAULT_OBSERVER_COMMANDS_CAPACITY);
     let (block_pre_processor_in_tx, block_pre_processor_in_rx) = crossbeam_channel::unbounded();
     let (block_pre_processor_out_tx, block_pre_processor_out_rx) = crossbeam_channel::unbounded();
 
@@ -1117,7 +1580,7 @@ fn test_bitcoin_chainhook_through_reorg() {
 
     let observer_sidecar = ObserverSidecar {
         bitcoin_blocks_mutator: Some((block_pre_processor_in_tx, block_pre_processor_out_rx)),
-        bitcoin_chain_event_notifier: None,
+        ..Default::default()
     };
     let prometheus_monitoring = PrometheusMonitoring::new();
     let prometheus_monitoring_moved = prometheus_monitoring.clone();
@@ -1128,10 +1591,11 @@ fn test_bitcoin_chainhook_through_reorg() {
             config,
             chainhook_store,
             observer_commands_rx,
-            Some(observer_events_tx),
+            Some(observer_events_tx.into()),
             None,
             prometheus_monitoring_moved,
             Some(observer_sidecar),
+            ObserverDependencies::default(),
             Context::empty(),
         ));
     });
@@ -1333,3 +1797,38 @@ fn test_bitcoin_chainhook_through_reorg() {
         .join()
         .expect("unable to terminate thread");
 }
+
+#[test]
+fn test_event_observer_config_builder_validates_settings() {
+    let predicate = bitcoin_chainhook_p2pkh(1, &accounts::wallet_2_btc_address(), None)
+        .into_specification_for_network(&BitcoinNetwork::Regtest)
+        .unwrap();
+    let config = EventObserverConfigBuilder::new()
+        .bitcoin_rpc("http://localhost:18443", "user", "password")
+        .bitcoind_zmq_url("tcp://0.0.0.0:18543")
+        .with_predicate(ChainhookInstance::Bitcoin(predicate.clone()))
+        .build()
+        .unwrap();
+    assert_eq!(config.get_bitcoin_config().username, "user");
+    assert_eq!(
+        config.bitcoin_block_signaling,
+        BitcoinBlockSignaling::ZeroMQ("tcp://0.0.0.0:18543".into())
+    );
+    assert_eq!(config.get_registered_chainhooks().bitcoin_chainhooks.len(), 1);
+
+    let mut builder = EventObserverConfigBuilder::new();
+    builder
+        .bitcoind_rpc_url("localhost")
+        .bitcoind_zmq_url("tcp://0.0.0.0:18543")
+        .bitcoind_polling_interval_ms(1000)
+        .bitcoin_confirmation_depth(0)
+        .bitcoin_network("mainnet")
+        .with_predicate(ChainhookInstance::Bitcoin(predicate));
+    let error = builder.build().unwrap_err();
+    assert!(error.contains("bitcoind_rpc_url: invalid url localhost"));
+    assert!(error.contains("bitcoind_zmq_url and bitcoind_polling_interval_ms"));
+    assert!(error.contains("bitcoin_confirmation_depth: must be positive"));
+    assert!(error.contains("predicate 1: targets the Bitcoin network regtest, not mainnet"));
+    // settings are only validated when built
+    assert!(builder.finish().is_ok());
+}
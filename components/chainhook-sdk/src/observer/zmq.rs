@@ -1,24 +1,31 @@
 use chainhook_types::BitcoinBlockSignaling;
 use hiro_system_kit::slog;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::SyncSender;
 use zmq::Socket;
 
+use reqwest::Client as HttpClient;
+
 use crate::{
     indexer::{
-        bitcoin::{build_http_client, download_and_parse_block_with_retry},
+        bitcoin::{build_http_client, source::bitcoin_block_source},
         fork_scratch_pad::ForkScratchPad,
     },
     utils::Context,
 };
-use std::collections::VecDeque;
 
-use super::{EventObserverConfig, ObserverCommand};
+use super::polling::process_announced_block_hash;
+use super::{BitcoinConfig, EventObserverConfig, ObserverCommand};
+
+/// Time without any ZMQ message after which bitcoind is asked for its chain tip, so that the
+/// blocks announced while the connection was silently dropped are not skipped.
+const ZMQ_RECEIVE_TIMEOUT_MS: i32 = 60_000;
 
 fn new_zmq_socket() -> Socket {
     let context = zmq::Context::new();
     let socket = context.socket(zmq::SUB).unwrap();
     assert!(socket.set_subscribe(b"hashblock").is_ok());
     assert!(socket.set_rcvhwm(0).is_ok());
+    assert!(socket.set_rcvtimeo(ZMQ_RECEIVE_TIMEOUT_MS).is_ok());
     // We override the OS default behavior:
     assert!(socket.set_tcp_keepalive(1).is_ok());
     // The keepalive routine will wait for 5 minutes
@@ -30,9 +37,67 @@ fn new_zmq_socket() -> Socket {
     socket
 }
 
+/// Parses the sequence number bitcoind attaches to every notification of a topic.
+fn parse_sequence(bytes: &[u8]) -> Option<u32> {
+    let bytes: [u8; 4] = bytes.try_into().ok()?;
+    Some(u32::from_le_bytes(bytes))
+}
+
+/// Whether notifications were missed between `last_sequence` and `sequence`. bitcoind restarts
+/// its sequence numbers from 0 when it restarts, which is reported as a gap too.
+fn is_sequence_gap(last_sequence: Option<u32>, sequence: u32) -> bool {
+    match last_sequence {
+        Some(last_sequence) => sequence != last_sequence.wrapping_add(1),
+        None => false,
+    }
+}
+
+/// Asks bitcoind for its chain tip, and dispatches it along with its missing ancestors if it
+/// differs from the last block announced.
+async fn recover_missed_blocks(
+    known_tip: &mut Option<String>,
+    http_client: &HttpClient,
+    bitcoin_config: &BitcoinConfig,
+    bitcoin_blocks_pool: &mut ForkScratchPad,
+    observer_commands_tx: &SyncSender<ObserverCommand>,
+    ctx: &Context,
+) {
+    // Blocks are only recovered once the first announcement gave us a starting point.
+    let Some(tip) = known_tip.as_ref() else {
+        return;
+    };
+    let block_source = bitcoin_block_source(http_client, bitcoin_config, ctx);
+    let block_hash = match block_source.retrieve_best_block_hash().await {
+        Ok(block_hash) => block_hash,
+        Err(e) => {
+            ctx.try_log(|logger| slog::warn!(logger, "Unable to retrieve best block hash: {}", e));
+            return;
+        }
+    };
+    if tip.eq(&block_hash) {
+        return;
+    }
+    ctx.try_log(|logger| {
+        slog::warn!(
+            logger,
+            "Bitcoin block hash #{block_hash} was not announced over ZMQ, recovering missed blocks"
+        )
+    });
+    *known_tip = Some(block_hash.clone());
+    process_announced_block_hash(
+        block_hash,
+        http_client,
+        bitcoin_config,
+        bitcoin_blocks_pool,
+        observer_commands_tx,
+        ctx,
+    )
+    .await;
+}
+
 pub async fn start_zeromq_runloop(
     config: &EventObserverConfig,
-    observer_commands_tx: Sender<ObserverCommand>,
+    observer_commands_tx: SyncSender<ObserverCommand>,
     ctx: &Context,
 ) {
     let BitcoinBlockSignaling::ZeroMQ(ref bitcoind_zmq_url) = config.bitcoin_block_signaling else {
@@ -55,20 +120,50 @@ pub async fn start_zeromq_runloop(
     ctx.try_log(|logger| slog::info!(logger, "Waiting for ZMQ messages from bitcoind"));
 
     let mut bitcoin_blocks_pool = ForkScratchPad::new();
+    bitcoin_blocks_pool.set_confirmation_depth(config.get_bitcoin_confirmation_depth());
+    let mut known_tip: Option<String> = None;
+    let mut last_sequence: Option<u32> = None;
 
     loop {
         let msg = match socket.recv_multipart(0) {
             Ok(msg) => msg,
+            Err(zmq::Error::EAGAIN) => {
+                // No block was announced for a while, make sure none was missed.
+                recover_missed_blocks(
+                    &mut known_tip,
+                    &http_client,
+                    &bitcoin_config,
+                    &mut bitcoin_blocks_pool,
+                    &observer_commands_tx,
+                    ctx,
+                )
+                .await;
+                continue;
+            }
             Err(e) => {
                 ctx.try_log(|logger| {
                     slog::error!(logger, "Unable to receive ZMQ message: {}", e.to_string())
                 });
                 socket = new_zmq_socket();
                 assert!(socket.connect(&bitcoind_zmq_url).is_ok());
+                last_sequence = None;
+                recover_missed_blocks(
+                    &mut known_tip,
+                    &http_client,
+                    &bitcoin_config,
+                    &mut bitcoin_blocks_pool,
+                    &observer_commands_tx,
+                    ctx,
+                )
+                .await;
                 continue;
             }
         };
-        let (topic, data, _sequence) = (&msg[0], &msg[1], &msg[2]);
+        if msg.len() < 3 {
+            ctx.try_log(|logger| slog::error!(logger, "Malformed ZMQ message",));
+            continue;
+        }
+        let (topic, data, sequence) = (&msg[0], &msg[1], &msg[2]);
 
         if !topic.eq(b"hashblock") {
             ctx.try_log(|logger| slog::error!(logger, "Topic not supported",));
@@ -79,78 +174,59 @@ pub async fn start_zeromq_runloop(
 
         ctx.try_log(|logger| slog::info!(logger, "Bitcoin block hash announced #{block_hash}",));
 
-        let mut block_hashes: VecDeque<String> = VecDeque::new();
-        block_hashes.push_front(block_hash);
-
-        while let Some(block_hash) = block_hashes.pop_front() {
-            let block = match download_and_parse_block_with_retry(
-                &http_client,
-                &block_hash,
-                &bitcoin_config,
-                ctx,
-            )
-            .await
-            {
-                Ok(block) => block,
-                Err(e) => {
-                    ctx.try_log(|logger| {
-                        slog::warn!(
-                            logger,
-                            "unable to download_and_parse_block: {}",
-                            e.to_string()
-                        )
-                    });
-                    continue;
-                }
-            };
-
-            let header = block.get_block_header();
+        let sequence = parse_sequence(sequence);
+        let gap_detected =
+            sequence.is_some_and(|sequence| is_sequence_gap(last_sequence, sequence));
+        if gap_detected {
             ctx.try_log(|logger| {
-                slog::info!(
+                slog::warn!(
                     logger,
-                    "Bitcoin block #{} dispatched for processing",
-                    block.height
+                    "ZMQ notifications missed before #{block_hash}, recovering missed blocks"
                 )
             });
+        }
+        last_sequence = sequence;
+
+        // The ancestors of the announced block that were missed are dispatched along with it.
+        known_tip = Some(block_hash.clone());
+        process_announced_block_hash(
+            block_hash,
+            &http_client,
+            &bitcoin_config,
+            &mut bitcoin_blocks_pool,
+            &observer_commands_tx,
+            ctx,
+        )
+        .await;
 
-            let _ = observer_commands_tx.send(ObserverCommand::ProcessBitcoinBlock(block));
-
-            if bitcoin_blocks_pool.can_process_header(&header) {
-                match bitcoin_blocks_pool.process_header(header, ctx) {
-                    Ok(Some(event)) => {
-                        let _ = observer_commands_tx
-                            .send(ObserverCommand::PropagateBitcoinChainEvent(event));
-                    }
-                    Err(e) => {
-                        ctx.try_log(|logger| {
-                            slog::warn!(logger, "Unable to append block: {:?}", e)
-                        });
-                    }
-                    Ok(None) => {
-                        ctx.try_log(|logger| slog::warn!(logger, "Unable to append block"));
-                    }
-                }
-            } else {
-                // Handle a behaviour specific to ZMQ usage in bitcoind.
-                // Considering a simple re-org:
-                // A (1) - B1 (2) - C1 (3)
-                //       \ B2 (4) - C2 (5) - D2 (6)
-                // When D2 is being discovered (making A -> B2 -> C2 -> D2 the new canonical fork)
-                // it looks like ZMQ is only publishing D2.
-                // Without additional operation, we end up with a block that we can't append.
-                let parent_block_hash = header
-                    .parent_block_identifier
-                    .get_hash_bytes_str()
-                    .to_string();
-                ctx.try_log(|logger| {
-                    slog::info!(
-                        logger,
-                        "Possible re-org detected, retrieving parent block {parent_block_hash}"
-                    )
-                });
-                block_hashes.push_front(block_hash);
-                block_hashes.push_front(parent_block_hash);
-            }
+        if gap_detected {
+            recover_missed_blocks(
+                &mut known_tip,
+                &http_client,
+                &bitcoin_config,
+                &mut bitcoin_blocks_pool,
+                &observer_commands_tx,
+                ctx,
+            )
+            .await;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_sequence_gap, parse_sequence};
+
+    #[test]
+    fn it_detects_sequence_gaps() {
+        assert_eq!(parse_sequence(&[1, 0, 0, 0]), Some(1));
+        assert_eq!(parse_sequence(&[1, 0]), None);
+
+        assert!(!is_sequence_gap(None, 7));
+        assert!(!is_sequence_gap(Some(6), 7));
+        assert!(!is_sequence_gap(Some(u32::MAX), 0));
+        assert!(is_sequence_gap(Some(6), 8));
+        // bitcoind restarted
+        assert!(is_sequence_gap(Some(6), 0));
+    }
+}
@@ -0,0 +1,154 @@
+#[cfg(feature = "aws")]
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use hiro_system_kit::slog;
+
+use super::Context;
+
+/// Destination of an occurrence delivered through AWS.
+#[derive(Clone, Debug)]
+pub enum AwsDestination {
+    SqsQueue(String),
+    SnsTopic(String),
+}
+
+/// A serialized occurrence, ready to be sent to an SQS queue or an SNS topic.
+///
+/// Requests are signed (SigV4) with the credentials resolved from the environment
+/// (env variables, shared config files, or instance / task role).
+#[derive(Clone, Debug)]
+pub struct AwsMessage {
+    pub destination: AwsDestination,
+    pub region: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+impl AwsMessage {
+    pub fn sqs(queue_url: &str, region: &Option<String>, payload: Vec<u8>) -> AwsMessage {
+        AwsMessage {
+            destination: AwsDestination::SqsQueue(queue_url.to_string()),
+            region: region.clone(),
+            payload,
+        }
+    }
+
+    pub fn sns(topic_arn: &str, region: &Option<String>, payload: Vec<u8>) -> AwsMessage {
+        AwsMessage {
+            destination: AwsDestination::SnsTopic(topic_arn.to_string()),
+            region: region.clone(),
+            payload,
+        }
+    }
+}
+
+/// SQS and SNS clients, sharing the configuration loaded for a region.
+#[cfg(feature = "aws")]
+#[derive(Clone)]
+struct AwsClients {
+    sqs: aws_sdk_sqs::Client,
+    sns: aws_sdk_sns::Client,
+}
+
+/// Clients are shared by the observer and the scans running in other threads: the credentials
+/// chain is resolved once per region, rather than for every message.
+#[cfg(feature = "aws")]
+fn aws_clients() -> &'static Mutex<HashMap<Option<String>, AwsClients>> {
+    static AWS_CLIENTS: OnceLock<Mutex<HashMap<Option<String>, AwsClients>>> = OnceLock::new();
+    AWS_CLIENTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(feature = "aws")]
+async fn get_or_load_clients(region: &Option<String>) -> AwsClients {
+    if let Some(clients) = aws_clients()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(region)
+    {
+        return clients.clone();
+    }
+    let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+    if let Some(ref region) = region {
+        loader = loader.region(aws_config::Region::new(region.clone()));
+    }
+    let config = loader.load().await;
+    let clients = AwsClients {
+        sqs: aws_sdk_sqs::Client::new(&config),
+        sns: aws_sdk_sns::Client::new(&config),
+    };
+    let mut cached_clients = aws_clients().lock().unwrap_or_else(|e| e.into_inner());
+    cached_clients
+        .entry(region.clone())
+        .or_insert(clients)
+        .clone()
+}
+
+#[cfg(feature = "aws")]
+async fn publish(message: &AwsMessage) -> Result<(), String> {
+    let body = String::from_utf8(message.payload.clone())
+        .map_err(|e| format!("unable to encode payload: {}", e))?;
+    let clients = get_or_load_clients(&message.region).await;
+    match &message.destination {
+        AwsDestination::SqsQueue(queue_url) => {
+            clients
+                .sqs
+                .send_message()
+                .queue_url(queue_url)
+                .message_body(body)
+                .send()
+                .await
+                .map_err(|e| format!("unable to send message to queue {}: {}", queue_url, e))?;
+        }
+        AwsDestination::SnsTopic(topic_arn) => {
+            clients
+                .sns
+                .publish()
+                .topic_arn(topic_arn)
+                .message(body)
+                .send()
+                .await
+                .map_err(|e| format!("unable to publish message to topic {}: {}", topic_arn, e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "aws"))]
+async fn publish(_message: &AwsMessage) -> Result<(), String> {
+    Err("aws support is not enabled (requires the `aws` feature)".to_string())
+}
+
+pub async fn send_aws_message(
+    message: &AwsMessage,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut retry = 0;
+    loop {
+        let err_msg = match publish(message).await {
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Message delivered to {:?}", message.destination)
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                retry += 1;
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                e
+            }
+        };
+        if retry >= attempts_max {
+            let msg: String = format!(
+                "unable to deliver aws message after several retries. most recent error: {}",
+                err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(attempts_interval_sec.into())).await;
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chainhook_types::Chain;
+use serde_json::{json, Value as JsonValue};
+
+use crate::chainhooks::types::PayloadEnvelope;
+
+/// Media type of the CloudEvents delivered in structured mode.
+pub const CLOUD_EVENTS_CONTENT_TYPE: &str = "application/cloudevents+json";
+
+/// Wraps the payload of an occurrence in the predicate's envelope, if any.
+pub fn wrap_payload(
+    envelope: &Option<PayloadEnvelope>,
+    payload: JsonValue,
+    chain: &Chain,
+    predicate_uuid: &str,
+    delivery_id: &str,
+) -> JsonValue {
+    match envelope {
+        Some(PayloadEnvelope::CloudEvents) => {
+            let chain = match chain {
+                Chain::Bitcoin => "bitcoin",
+                Chain::Stacks => "stacks",
+            };
+            json!({
+                "specversion": "1.0",
+                "id": delivery_id,
+                "source": format!("/chainhook/predicates/{}", predicate_uuid),
+                "type": format!("com.hiro.chainhook.{}.occurrence", chain),
+                "time": format_rfc3339(SystemTime::now()),
+                "datacontenttype": "application/json",
+                "data": payload,
+            })
+        }
+        None => payload,
+    }
+}
+
+/// Returns the media type of the payloads wrapped in `envelope`.
+pub fn payload_content_type(envelope: &Option<PayloadEnvelope>) -> &'static str {
+    match envelope {
+        Some(PayloadEnvelope::CloudEvents) => CLOUD_EVENTS_CONTENT_TYPE,
+        None => "application/json",
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, such as `2024-03-01T12:30:05Z`.
+fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    // Civil date from the number of days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use chainhook_types::Chain;
+    use serde_json::json;
+
+    use super::{format_rfc3339, wrap_payload};
+    use crate::chainhooks::types::PayloadEnvelope;
+
+    #[test]
+    fn it_formats_rfc3339_timestamps() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + Duration::from_secs(1_709_296_205)),
+            "2024-03-01T12:30:05Z"
+        );
+    }
+
+    #[test]
+    fn it_wraps_payloads_in_cloud_events() {
+        let payload = json!({ "apply": [], "rollback": [] });
+        assert_eq!(
+            wrap_payload(&None, payload.clone(), &Chain::Bitcoin, "uuid", "uuid::0"),
+            payload
+        );
+        let event = wrap_payload(
+            &Some(PayloadEnvelope::CloudEvents),
+            payload.clone(),
+            &Chain::Stacks,
+            "uuid",
+            "uuid::0",
+        );
+        assert_eq!(event["specversion"], "1.0");
+        assert_eq!(event["id"], "uuid::0");
+        assert_eq!(event["source"], "/chainhook/predicates/uuid");
+        assert_eq!(event["type"], "com.hiro.chainhook.stacks.occurrence");
+        assert_eq!(event["data"], payload);
+    }
+}
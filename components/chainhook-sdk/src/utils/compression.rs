@@ -0,0 +1,49 @@
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::chainhooks::types::PayloadCompression;
+
+pub fn compress_payload(
+    compression: &PayloadCompression,
+    payload: &[u8],
+) -> Result<Vec<u8>, String> {
+    match compression {
+        PayloadCompression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(payload)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("unable to compress payload: {}", e))
+        }
+        PayloadCompression::Zstd => zstd::encode_all(payload, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map_err(|e| format!("unable to compress payload: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::compress_payload;
+    use crate::chainhooks::types::PayloadCompression;
+
+    #[test]
+    fn it_compresses_payloads() {
+        let payload = br#"{"apply":[],"rollback":[]}"#.repeat(100);
+
+        let gzipped = compress_payload(&PayloadCompression::Gzip, &payload).unwrap();
+        assert!(gzipped.len() < payload.len());
+        let mut decoded = vec![];
+        GzDecoder::new(&gzipped[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, payload);
+
+        let zstded = compress_payload(&PayloadCompression::Zstd, &payload).unwrap();
+        assert!(zstded.len() < payload.len());
+        assert_eq!(zstd::decode_all(&zstded[..]).unwrap(), payload);
+    }
+}
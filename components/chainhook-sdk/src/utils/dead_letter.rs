@@ -0,0 +1,144 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hiro_system_kit::slog;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+
+use super::file_sink::append_to_file_sink;
+use super::retry::send_request_with_retry_policy;
+use super::{build_http_post_request, Context};
+use crate::chainhooks::types::{DeadLetterSink, RedisListHook};
+use crate::observer::PredicatesConfig;
+
+/// Builds the record sent to a dead-letter sink for an occurrence that could not be delivered.
+pub fn build_dead_letter_record(
+    predicate_uuid: &str,
+    error: &str,
+    payload: &JsonValue,
+) -> JsonValue {
+    let failed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    json!({
+        "predicate_uuid": predicate_uuid,
+        "error": error,
+        "failed_at": failed_at,
+        "payload": payload,
+    })
+}
+
+#[cfg(feature = "redis")]
+fn push_to_redis_list(hook: &RedisListHook, bytes: Vec<u8>) -> Result<(), String> {
+    let client = redis::Client::open(hook.redis_url.as_str())
+        .map_err(|e| format!("unable to open redis client: {}", e))?;
+    let mut con = client
+        .get_connection()
+        .map_err(|e| format!("unable to connect to redis: {}", e))?;
+    redis::cmd("RPUSH")
+        .arg(&hook.key)
+        .arg(bytes)
+        .query::<()>(&mut con)
+        .map_err(|e| format!("unable to push to redis list {}: {}", hook.key, e))
+}
+
+#[cfg(not(feature = "redis"))]
+fn push_to_redis_list(_hook: &RedisListHook, _bytes: Vec<u8>) -> Result<(), String> {
+    Err("redis support is not enabled (requires the `redis` feature)".to_string())
+}
+
+async fn send_dead_letter_record(
+    sink: &DeadLetterSink,
+    record: &JsonValue,
+    delivery_id: &str,
+    predicates_config: &PredicatesConfig,
+    ctx: &Context,
+) -> Result<(), String> {
+    let bytes = serde_json::to_vec(record)
+        .map_err(|e| format!("unable to serialize dead-letter record: {}", e))?;
+    match sink {
+        DeadLetterSink::FileAppend(hook) => append_to_file_sink(hook, bytes, ctx),
+        DeadLetterSink::RedisList(hook) => push_to_redis_list(hook, bytes),
+        DeadLetterSink::HttpPost(hook) => {
+            // Signed, compressed and sent with the certificates of the sink like any occurrence
+            let request = build_http_post_request(
+                hook,
+                bytes,
+                delivery_id,
+                "application/json",
+                predicates_config,
+            )?;
+            let retry_policy = hook.delivery.retry_policy.clone().unwrap_or_default();
+            send_request_with_retry_policy(request, &retry_policy, &None, ctx)
+                .await
+                .map_err(|e| format!("unable to send dead-letter record: {}", e))
+        }
+    }
+}
+
+/// Routes an occurrence that could not be delivered to the action's dead-letter sink. The
+/// record is only serialized once a sink is known to receive it. Returns an error, including
+/// the original delivery error, when the action has no dead-letter sink or when the record
+/// could not be stored.
+pub async fn dead_letter_failed_delivery<T: Serialize>(
+    dead_letter: Option<&DeadLetterSink>,
+    predicate_uuid: &str,
+    delivery_id: &str,
+    error: String,
+    payload: &T,
+    predicates_config: &PredicatesConfig,
+    ctx: &Context,
+) -> Result<(), String> {
+    let Some(sink) = dead_letter else {
+        return Err(error);
+    };
+    let payload = serde_json::to_value(payload).unwrap_or(JsonValue::Null);
+    let record = build_dead_letter_record(predicate_uuid, &error, &payload);
+    match send_dead_letter_record(sink, &record, delivery_id, predicates_config, ctx).await {
+        Ok(_) => {
+            ctx.try_log(|logger| {
+                slog::warn!(
+                    logger,
+                    "Occurrence of predicate {} sent to dead-letter sink: {}",
+                    predicate_uuid,
+                    error
+                )
+            });
+            Ok(())
+        }
+        Err(e) => Err(format!("{} (dead-letter delivery failed: {})", error, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{build_dead_letter_record, dead_letter_failed_delivery};
+    use crate::observer::PredicatesConfig;
+    use crate::utils::Context;
+
+    #[test]
+    fn it_builds_dead_letter_records() {
+        let record = build_dead_letter_record("1234", "timeout", &json!({ "apply": [] }));
+        assert_eq!(record["predicate_uuid"], "1234");
+        assert_eq!(record["error"], "timeout");
+        assert_eq!(record["payload"], json!({ "apply": [] }));
+        assert!(record["failed_at"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn it_returns_the_delivery_error_without_sink() {
+        let res = dead_letter_failed_delivery(
+            None,
+            "1234",
+            "1234:1",
+            "timeout".to_string(),
+            &json!({}),
+            &PredicatesConfig::new(),
+            &Context::empty(),
+        )
+        .await;
+        assert_eq!(res, Err("timeout".to_string()));
+    }
+}
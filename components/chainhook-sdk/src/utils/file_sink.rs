@@ -0,0 +1,183 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hiro_system_kit::slog;
+
+use crate::chainhooks::types::{FileHook, FileRotation, PayloadCompression};
+use crate::utils::compression::compress_payload;
+use crate::utils::{file_append, Context};
+
+/// Serializes the writes to file sinks, which may be rotated by any of them.
+static FILE_SINK_LOCK: Mutex<()> = Mutex::new(());
+
+/// Path of the `file_append` sink writing the occurrences to stdout rather than to a file.
+pub const STDOUT_FILE_SINK_PATH: &str = "-";
+
+/// Appends an occurrence as a line of the newline-delimited JSON file of a `file_append`
+/// action, rotating the file first if the write would exceed the limits of its `rotation`.
+pub fn append_to_file_sink(hook: &FileHook, bytes: Vec<u8>, ctx: &Context) -> Result<(), String> {
+    let _guard = FILE_SINK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if hook.path == STDOUT_FILE_SINK_PATH {
+        return stdout_append(bytes);
+    }
+    if let Some(ref rotation) = hook.rotation {
+        let path = std::env::current_dir()
+            .map_err(|e| format!("unable to retrieve current_dir {}", e))?
+            .join(&hook.path);
+        if should_rotate(&path, rotation, bytes.len() as u64 + 1) {
+            let rotated_path = rotate_file(&path, &hook.compression)?;
+            ctx.try_log(|logger| {
+                slog::info!(
+                    logger,
+                    "Rotated file {} to {}",
+                    path.display(),
+                    rotated_path.display()
+                )
+            });
+        }
+    }
+    file_append(hook.path.clone(), bytes, ctx)
+}
+
+fn stdout_append(bytes: Vec<u8>) -> Result<(), String> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(&bytes)
+        .and_then(|_| stdout.write_all(b"\n"))
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("unable to write to stdout: {}", e))
+}
+
+fn should_rotate(path: &Path, rotation: &FileRotation, incoming_bytes: u64) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if metadata.len() == 0 {
+        return false;
+    }
+    if let Some(max_size_bytes) = rotation.max_size_bytes {
+        if metadata.len() + incoming_bytes > max_size_bytes {
+            return true;
+        }
+    }
+    if let Some(max_age_sec) = rotation.max_age_sec {
+        // Files on file systems not recording creation times only get rotated by size
+        let age = metadata
+            .created()
+            .ok()
+            .and_then(|created| created.elapsed().ok());
+        if let Some(age) = age {
+            if age.as_secs() >= max_age_sec {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Renames the file with the current time as suffix, compressing it if requested. Returns the
+/// path of the rotated file.
+fn rotate_file(path: &Path, compression: &Option<PayloadCompression>) -> Result<PathBuf, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let extension = match compression {
+        Some(compression) => format!(".{}", compression.file_extension()),
+        None => String::new(),
+    };
+    // Files rotated within the same millisecond are told apart by a counter
+    let mut rotated_path = PathBuf::new();
+    for i in 0.. {
+        let mut candidate = path.as_os_str().to_owned();
+        match i {
+            0 => candidate.push(format!(".{}{}", timestamp, extension)),
+            i => candidate.push(format!(".{}-{}{}", timestamp, i, extension)),
+        }
+        rotated_path = PathBuf::from(candidate);
+        if !rotated_path.exists() {
+            break;
+        }
+    }
+    match compression {
+        None => {
+            fs::rename(path, &rotated_path)
+                .map_err(|e| format!("unable to rotate file {}: {}", path.display(), e))?;
+        }
+        Some(compression) => {
+            let bytes = fs::read(path)
+                .map_err(|e| format!("unable to read file {}: {}", path.display(), e))?;
+            fs::write(&rotated_path, compress_payload(compression, &bytes)?).map_err(|e| {
+                format!(
+                    "unable to write rotated file {}: {}",
+                    rotated_path.display(),
+                    e
+                )
+            })?;
+            fs::remove_file(path)
+                .map_err(|e| format!("unable to rotate file {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(rotated_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{append_to_file_sink, STDOUT_FILE_SINK_PATH};
+    use crate::chainhooks::types::{DeliveryOptions, FileHook, FileRotation, PayloadCompression};
+    use crate::utils::Context;
+
+    #[test]
+    fn it_rotates_files_exceeding_their_size() {
+        let dir = std::env::temp_dir().join(format!("chainhook-sink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("occurrences.ndjson");
+        let hook = FileHook {
+            path: path.display().to_string(),
+            rotation: Some(FileRotation {
+                max_size_bytes: Some(30),
+                max_age_sec: None,
+            }),
+            compression: Some(PayloadCompression::Gzip),
+            delivery: DeliveryOptions::default(),
+        };
+        let ctx = Context::empty();
+        for i in 0..3 {
+            let line = format!(r#"{{"apply":[],"index":{}}}"#, i);
+            append_to_file_sink(&hook, line.into_bytes(), &ctx).unwrap();
+        }
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"apply\":[],\"index\":2}\n"
+        );
+        let rotated = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".gz"))
+            .count();
+        assert_eq!(rotated, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_rejects_rotating_stdout() {
+        let mut hook = FileHook {
+            path: STDOUT_FILE_SINK_PATH.into(),
+            rotation: None,
+            compression: None,
+            delivery: DeliveryOptions::default(),
+        };
+        assert!(hook.validate().is_ok());
+        hook.rotation = Some(FileRotation {
+            max_size_bytes: Some(30),
+            max_age_sec: None,
+        });
+        assert!(hook.validate().is_err());
+    }
+}
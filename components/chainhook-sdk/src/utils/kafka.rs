@@ -0,0 +1,141 @@
+use hiro_system_kit::slog;
+
+use crate::chainhooks::types::KafkaHook;
+
+use super::Context;
+
+/// A serialized occurrence, ready to be produced to a Kafka topic.
+#[derive(Clone, Debug)]
+pub struct KafkaMessage {
+    pub brokers: Vec<String>,
+    pub topic: String,
+    pub key: String,
+    pub payload: Vec<u8>,
+}
+
+impl KafkaMessage {
+    pub fn new(hook: &KafkaHook, key: String, payload: Vec<u8>) -> KafkaMessage {
+        KafkaMessage {
+            brokers: hook.brokers.clone(),
+            topic: hook.topic.clone(),
+            key,
+            payload,
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+mod producers {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::Duration;
+
+    use kafka::producer::{Producer, Record, RequiredAcks};
+
+    use super::KafkaMessage;
+
+    type SharedProducer = Arc<Mutex<Producer>>;
+
+    /// Producers are shared by the observer and the scans running in other threads, a single
+    /// connection being kept per brokers and topic.
+    fn kafka_producers() -> &'static Mutex<HashMap<(Vec<String>, String), SharedProducer>> {
+        static KAFKA_PRODUCERS: OnceLock<Mutex<HashMap<(Vec<String>, String), SharedProducer>>> =
+            OnceLock::new();
+        KAFKA_PRODUCERS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    fn get_or_create_producer(message: &KafkaMessage) -> Result<SharedProducer, String> {
+        let key = (message.brokers.clone(), message.topic.clone());
+        let mut producers = kafka_producers().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(producer) = producers.get(&key) {
+            return Ok(producer.clone());
+        }
+        let producer = Producer::from_hosts(message.brokers.clone())
+            .with_ack_timeout(Duration::from_secs(1))
+            .with_required_acks(RequiredAcks::One)
+            .create()
+            .map_err(|e| format!("unable to connect to kafka brokers: {}", e))?;
+        let producer = Arc::new(Mutex::new(producer));
+        producers.insert(key, producer.clone());
+        Ok(producer)
+    }
+
+    /// Drops the producer of `message`, so that the next message reconnects to the brokers.
+    fn discard_producer(message: &KafkaMessage, producer: &SharedProducer) {
+        let key = (message.brokers.clone(), message.topic.clone());
+        let mut producers = kafka_producers().lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(producers.get(&key), Some(cached) if Arc::ptr_eq(cached, producer)) {
+            producers.remove(&key);
+        }
+    }
+
+    pub fn produce_blocking(message: &KafkaMessage) -> Result<(), String> {
+        let producer = get_or_create_producer(message)?;
+        let res = producer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send(&Record::from_key_value(
+                &message.topic,
+                message.key.as_bytes(),
+                message.payload.as_slice(),
+            ))
+            .map_err(|e| {
+                format!(
+                    "unable to produce message to topic {}: {}",
+                    message.topic, e
+                )
+            });
+        if res.is_err() {
+            discard_producer(message, &producer);
+        }
+        res
+    }
+}
+
+/// Produces the message with the producer kept for its brokers and topic. Kafka producers being
+/// synchronous, the message is sent from the blocking thread pool of the runtime.
+#[cfg(feature = "kafka")]
+async fn produce(message: &KafkaMessage) -> Result<(), String> {
+    let message = message.clone();
+    tokio::task::spawn_blocking(move || producers::produce_blocking(&message))
+        .await
+        .map_err(|e| format!("unable to produce kafka message: {}", e))?
+}
+
+#[cfg(not(feature = "kafka"))]
+async fn produce(_message: &KafkaMessage) -> Result<(), String> {
+    Err("kafka support is not enabled (requires the `kafka` feature)".to_string())
+}
+
+pub async fn send_kafka_message(
+    message: &KafkaMessage,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut retry = 0;
+    loop {
+        let err_msg = match produce(message).await {
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Message produced to kafka topic {}", message.topic)
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                retry += 1;
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                e
+            }
+        };
+        if retry >= attempts_max {
+            let msg: String = format!(
+                "unable to produce kafka message after several retries. most recent error: {}",
+                err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(attempts_interval_sec.into())).await;
+    }
+}
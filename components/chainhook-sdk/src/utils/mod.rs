@@ -1,3 +1,18 @@
+pub mod aws;
+pub mod cloud_events;
+pub mod compression;
+pub mod dead_letter;
+pub mod file_sink;
+pub mod kafka;
+pub mod nats;
+pub mod object_storage;
+pub mod postgres;
+pub mod projection;
+pub mod rate_limit;
+pub mod retry;
+pub mod signature;
+pub mod transform;
+
 use std::{
     collections::{BTreeSet, VecDeque},
     fs::{self, OpenOptions},
@@ -6,13 +21,25 @@ use std::{
 };
 
 use chainhook_types::{
-    BitcoinBlockData, BlockHeader, BlockIdentifier, StacksBlockData, StacksMicroblockData,
-    StacksTransactionData,
+    BitcoinBlockData, BlockHeader, BlockIdentifier, ServerTlsConfig, StacksBlockData,
+    StacksMicroblockData, StacksTransactionData,
 };
 use hiro_system_kit::slog::{self, Logger};
-use reqwest::RequestBuilder;
+use reqwest::{Certificate, Client, Identity, Method, RequestBuilder, Response};
+#[cfg(feature = "rocket")]
+use rocket::config::{MutualTls, TlsConfig};
+#[cfg(feature = "axum-ingestion")]
+use rustls::server::AllowAnyAuthenticatedClient;
+#[cfg(feature = "axum-ingestion")]
+use rustls::{Certificate as TlsCertificate, PrivateKey, RootCertStore, ServerConfig};
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 
+use crate::chainhooks::types::HttpHook;
+use crate::observer::PredicatesConfig;
+use compression::compress_payload;
+use signature::{sign_payload, SIGNATURE_HEADER};
+
 #[derive(Clone)]
 pub struct Context {
     pub logger: Option<Logger>,
@@ -47,6 +74,7 @@ pub trait AbstractStacksBlock {
     fn get_transactions(&self) -> &Vec<StacksTransactionData>;
     fn get_timestamp(&self) -> i64;
     fn get_serialized_metadata(&self) -> JsonValue;
+    fn get_bitcoin_anchor_block_identifier(&self) -> Option<&BlockIdentifier>;
 }
 
 impl AbstractStacksBlock for StacksBlockData {
@@ -69,6 +97,10 @@ impl AbstractStacksBlock for StacksBlockData {
     fn get_serialized_metadata(&self) -> JsonValue {
         json!(self.metadata)
     }
+
+    fn get_bitcoin_anchor_block_identifier(&self) -> Option<&BlockIdentifier> {
+        Some(&self.metadata.bitcoin_anchor_block_identifier)
+    }
 }
 
 impl AbstractStacksBlock for StacksMicroblockData {
@@ -91,6 +123,11 @@ impl AbstractStacksBlock for StacksMicroblockData {
     fn get_serialized_metadata(&self) -> JsonValue {
         json!(self.metadata)
     }
+
+    fn get_bitcoin_anchor_block_identifier(&self) -> Option<&BlockIdentifier> {
+        // Microblocks are not anchored to the burnchain directly
+        None
+    }
 }
 
 pub trait AbstractBlock {
@@ -144,6 +181,137 @@ impl AbstractBlock for BitcoinBlockData {
     }
 }
 
+/// Builds the client used to post the payloads of an `http_post` action, using the hook's
+/// certificates or, when it has none, the ones configured for every predicate.
+pub fn build_http_post_client(
+    hook: &HttpHook,
+    predicates_config: &PredicatesConfig,
+) -> Result<Client, String> {
+    let mut client_builder = Client::builder();
+    if let Some(timeout) = predicates_config.payload_http_request_timeout_ms {
+        client_builder = client_builder.timeout(std::time::Duration::from_millis(timeout));
+    }
+    if let Some(tls) = hook.tls.as_ref().or(predicates_config.http_tls.as_ref()) {
+        if let Some(ref ca_cert_path) = tls.ca_cert_path {
+            let pem = read_file_content_at_path(&PathBuf::from(ca_cert_path))?;
+            for cert in Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("unable to parse CA bundle {}: {}", ca_cert_path, e))?
+            {
+                client_builder = client_builder.add_root_certificate(cert);
+            }
+        }
+        if let (Some(cert_path), Some(key_path)) = (&tls.client_cert_path, &tls.client_key_path) {
+            let mut pem = read_file_content_at_path(&PathBuf::from(cert_path))?;
+            pem.extend(read_file_content_at_path(&PathBuf::from(key_path))?);
+            let identity = Identity::from_pem(&pem)
+                .map_err(|e| format!("unable to parse client certificate {}: {}", cert_path, e))?;
+            client_builder = client_builder.identity(identity);
+        }
+    }
+    client_builder
+        .build()
+        .map_err(|e| format!("unable to build http client: {}", e))
+}
+
+/// Header carrying the deterministic id of a delivery, see
+/// [build_delivery_id](crate::chainhooks::types::build_delivery_id).
+pub const DELIVERY_ID_HEADER: &str = "X-Chainhook-Delivery-Id";
+
+/// Builds the TLS config of a Rocket server from the paths of its certificate files.
+#[cfg(feature = "rocket")]
+pub fn rocket_tls_config(tls: &ServerTlsConfig) -> TlsConfig {
+    let config = TlsConfig::from_paths(&tls.cert_path, &tls.key_path);
+    match tls.client_ca_path {
+        Some(ref client_ca_path) => {
+            config.with_mutual(MutualTls::from_path(client_ca_path).mandatory(true))
+        }
+        None => config,
+    }
+}
+
+/// Builds the TLS config of an axum server from the paths of its certificate files, the clients
+/// having to present a certificate signed by `client_ca_path` when set.
+#[cfg(feature = "axum-ingestion")]
+pub fn rustls_server_config(tls: &ServerTlsConfig) -> Result<std::sync::Arc<ServerConfig>, String> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match tls.client_ca_path {
+        Some(ref client_ca_path) => {
+            let mut client_cas = RootCertStore::empty();
+            for certificate in read_pem_certificates(client_ca_path)?.iter() {
+                client_cas
+                    .add(certificate)
+                    .map_err(|e| format!("invalid TLS certificate in {}: {}", client_ca_path, e))?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(client_cas).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(
+            read_pem_certificates(&tls.cert_path)?,
+            read_pem_private_key(&tls.key_path)?,
+        )
+        .map_err(|e| format!("invalid TLS certificate or key: {}", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(std::sync::Arc::new(config))
+}
+
+#[cfg(feature = "axum-ingestion")]
+fn read_pem_certificates(path: &str) -> Result<Vec<TlsCertificate>, String> {
+    let file =
+        fs::File::open(path).map_err(|e| format!("unable to open TLS file {}: {}", path, e))?;
+    let certificates = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .map_err(|e| format!("unable to read TLS certificates from {}: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("no TLS certificate found in {}", path));
+    }
+    Ok(certificates.into_iter().map(TlsCertificate).collect())
+}
+
+#[cfg(feature = "axum-ingestion")]
+fn read_pem_private_key(path: &str) -> Result<PrivateKey, String> {
+    use rustls_pemfile::Item;
+    let file =
+        fs::File::open(path).map_err(|e| format!("unable to open TLS file {}: {}", path, e))?;
+    let mut reader = std::io::BufReader::new(file);
+    loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .map_err(|e| format!("unable to read TLS key from {}: {}", path, e))?
+        {
+            Some(Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key)) => {
+                return Ok(PrivateKey(key))
+            }
+            Some(_) => continue,
+            None => return Err(format!("no TLS private key found in {}", path)),
+        }
+    }
+}
+
+/// Builds the request posting a serialized payload to the endpoint of an `http_post` action,
+/// signing and compressing the body when the hook requires it.
+pub fn build_http_post_request(
+    hook: &HttpHook,
+    mut body: Vec<u8>,
+    delivery_id: &str,
+    content_type: &str,
+    predicates_config: &PredicatesConfig,
+) -> Result<RequestBuilder, String> {
+    let client = build_http_post_client(hook, predicates_config)?;
+    let mut request = client
+        .request(Method::POST, &hook.url)
+        .header("Content-Type", content_type)
+        .header("Authorization", hook.authorization_header.clone())
+        .header(DELIVERY_ID_HEADER, delivery_id);
+    if let Some(ref secret) = hook.signing_secret {
+        request = request.header(SIGNATURE_HEADER, sign_payload(secret, &body));
+    }
+    if let Some(ref compression) = hook.compression {
+        request = request.header("Content-Encoding", compression.content_encoding());
+        body = compress_payload(compression, &body)?;
+    }
+    Ok(request.body(body))
+}
+
 pub async fn send_request(
     request_builder: RequestBuilder,
     attempts_max: u16,
@@ -191,6 +359,42 @@ pub async fn send_request(
     }
 }
 
+/// Body of the response acknowledging a delivery, for `http_post` actions requiring acks.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeliveryAck {
+    /// Height of the last block processed by the receiver.
+    pub cursor: u64,
+}
+
+/// Sends a request once, expecting the endpoint to acknowledge the delivery with a
+/// [DeliveryAck]. Returns the acknowledged cursor.
+pub async fn send_request_expecting_ack(
+    request_builder: RequestBuilder,
+    ctx: &Context,
+) -> Result<u64, String> {
+    let res = request_builder
+        .send()
+        .await
+        .map_err(|e| format!("unable to send request {}", e))?;
+    read_delivery_ack(res, ctx).await
+}
+
+/// Reads the [DeliveryAck] of the response to a delivery. Returns the acknowledged cursor.
+pub async fn read_delivery_ack(res: Response, ctx: &Context) -> Result<u64, String> {
+    let url = res.url().clone();
+    if !res.status().is_success() {
+        return Err(format!("Trigger {} failed with status {}", url, res.status()));
+    }
+    let ack: DeliveryAck = res
+        .json()
+        .await
+        .map_err(|e| format!("Trigger {} was not acknowledged: {}", url, e))?;
+    ctx.try_log(|logger| {
+        slog::debug!(logger, "Trigger {} acknowledged up to block #{}", url, ack.cursor)
+    });
+    Ok(ack.cursor)
+}
+
 pub fn file_append(path: String, bytes: Vec<u8>, ctx: &Context) -> Result<(), String> {
     let mut file_path = match std::env::current_dir() {
         Err(e) => {
@@ -201,31 +405,14 @@ pub fn file_append(path: String, bytes: Vec<u8>, ctx: &Context) -> Result<(), St
         Ok(p) => p,
     };
     file_path.push(path);
-    if !file_path.exists() {
-        match std::fs::File::create(&file_path) {
-            Ok(ref mut file) => {
-                let _ = file.write_all(&bytes);
-            }
-            Err(e) => {
-                let msg = format!(
-                    "unable to create file {}: {}",
-                    file_path.display(),
-                    e
-                );
-                ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
-                return Err(msg);
-            }
-        }
-    }
-
+    // Each payload is written as a line, so that the file holds newline-delimited JSON
     let mut file = match OpenOptions::new()
-        .create(false)
-        
+        .create(true)
         .append(true)
-        .open(file_path)
+        .open(&file_path)
     {
         Err(e) => {
-            let msg = format!("unable to open file {}", e);
+            let msg = format!("unable to open file {}: {}", file_path.display(), e);
             ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
             return Err(msg);
         }
@@ -0,0 +1,176 @@
+#[cfg(feature = "nats")]
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use chainhook_types::BlockIdentifier;
+use hiro_system_kit::slog;
+
+use crate::chainhooks::types::NatsHook;
+
+use super::Context;
+
+/// A serialized occurrence, ready to be published on a NATS subject.
+#[derive(Clone, Debug)]
+pub struct NatsMessage {
+    pub server_url: String,
+    pub subject: String,
+    pub jetstream: bool,
+    pub payload: Vec<u8>,
+}
+
+impl NatsMessage {
+    pub fn new(
+        hook: &NatsHook,
+        predicate_uuid: &str,
+        block_identifier: Option<&BlockIdentifier>,
+        payload: Vec<u8>,
+    ) -> NatsMessage {
+        NatsMessage {
+            server_url: hook.server_url.clone(),
+            subject: render_subject(&hook.subject, predicate_uuid, block_identifier),
+            jetstream: hook.jetstream.unwrap_or(false),
+            payload,
+        }
+    }
+}
+
+/// Replaces the `{uuid}`, `{block_height}` and `{block_hash}` placeholders of a subject template.
+pub fn render_subject(
+    template: &str,
+    predicate_uuid: &str,
+    block_identifier: Option<&BlockIdentifier>,
+) -> String {
+    let (block_height, block_hash) = match block_identifier {
+        Some(block_identifier) => (
+            block_identifier.index.to_string(),
+            block_identifier.hash.clone(),
+        ),
+        None => ("unknown".to_string(), "unknown".to_string()),
+    };
+    template
+        .replace("{uuid}", predicate_uuid)
+        .replace("{block_height}", &block_height)
+        .replace("{block_hash}", &block_hash)
+}
+
+/// Clients are shared by the observer and the scans running in other threads, a single
+/// connection being kept per server. Clients reconnect on their own when the connection drops.
+#[cfg(feature = "nats")]
+fn nats_clients() -> &'static Mutex<HashMap<String, async_nats::Client>> {
+    static NATS_CLIENTS: OnceLock<Mutex<HashMap<String, async_nats::Client>>> = OnceLock::new();
+    NATS_CLIENTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(feature = "nats")]
+async fn get_or_connect_client(server_url: &str) -> Result<async_nats::Client, String> {
+    if let Some(client) = nats_clients()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(server_url)
+    {
+        return Ok(client.clone());
+    }
+    let client = async_nats::connect(server_url)
+        .await
+        .map_err(|e| format!("unable to connect to nats server: {}", e))?;
+    let mut clients = nats_clients().lock().unwrap_or_else(|e| e.into_inner());
+    Ok(clients
+        .entry(server_url.to_string())
+        .or_insert(client)
+        .clone())
+}
+
+#[cfg(feature = "nats")]
+async fn publish(message: &NatsMessage) -> Result<(), String> {
+    let client = get_or_connect_client(&message.server_url).await?;
+    if message.jetstream {
+        let jetstream = async_nats::jetstream::new(client);
+        jetstream
+            .publish(message.subject.clone(), message.payload.clone().into())
+            .await
+            .map_err(|e| format!("unable to publish message to {}: {}", message.subject, e))?
+            .await
+            .map_err(|e| format!("message to {} not acknowledged: {}", message.subject, e))?;
+    } else {
+        client
+            .publish(message.subject.clone(), message.payload.clone().into())
+            .await
+            .map_err(|e| format!("unable to publish message to {}: {}", message.subject, e))?;
+        client
+            .flush()
+            .await
+            .map_err(|e| format!("unable to flush nats connection: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "nats"))]
+async fn publish(_message: &NatsMessage) -> Result<(), String> {
+    Err("nats support is not enabled (requires the `nats` feature)".to_string())
+}
+
+pub async fn send_nats_message(
+    message: &NatsMessage,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut retry = 0;
+    loop {
+        let err_msg = match publish(message).await {
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(
+                        logger,
+                        "Message published on nats subject {}",
+                        message.subject
+                    )
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                retry += 1;
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                e
+            }
+        };
+        if retry >= attempts_max {
+            let msg: String = format!(
+                "unable to publish nats message after several retries. most recent error: {}",
+                err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(attempts_interval_sec.into())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chainhook_types::BlockIdentifier;
+
+    use super::render_subject;
+
+    #[test]
+    fn it_renders_subject_templates() {
+        let block_identifier = BlockIdentifier {
+            index: 100,
+            hash: "0xabcd".to_string(),
+        };
+        assert_eq!(
+            render_subject(
+                "chainhook.{uuid}.{block_height}.{block_hash}",
+                "1234",
+                Some(&block_identifier)
+            ),
+            "chainhook.1234.100.0xabcd"
+        );
+        assert_eq!(
+            render_subject("chainhook.{uuid}.{block_height}", "1234", None),
+            "chainhook.1234.unknown"
+        );
+    }
+}
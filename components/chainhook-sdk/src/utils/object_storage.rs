@@ -0,0 +1,215 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hiro_system_kit::slog;
+use serde_json::Value as JsonValue;
+
+use crate::chainhooks::types::{ObjectStoreGranularity, ObjectStoreHook};
+
+use super::Context;
+
+/// Serialized occurrences, ready to be written to an object store as NDJSON objects.
+#[derive(Clone, Debug)]
+pub struct ObjectStoreUpload {
+    pub url: String,
+    pub objects: Vec<(String, Vec<u8>)>,
+}
+
+impl ObjectStoreUpload {
+    pub fn new(
+        hook: &ObjectStoreHook,
+        predicate_uuid: &str,
+        payload: &JsonValue,
+    ) -> Result<ObjectStoreUpload, String> {
+        let prefix = match hook.key_prefix {
+            Some(ref prefix) => format!("{}/{}", prefix.trim_matches('/'), predicate_uuid),
+            None => predicate_uuid.to_string(),
+        };
+        let mut objects = vec![];
+        match hook
+            .granularity
+            .as_ref()
+            .unwrap_or(&ObjectStoreGranularity::Occurrence)
+        {
+            ObjectStoreGranularity::Occurrence => {
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Could not get current time in ms")
+                    .as_millis();
+                let tip = payload["apply"]
+                    .as_array()
+                    .and_then(|blocks| blocks.last())
+                    .or(payload["rollback"]
+                        .as_array()
+                        .and_then(|blocks| blocks.last()));
+                let key = match tip {
+                    Some(block) => format!("{}/{}-{}.ndjson", prefix, object_name(block), now_ms),
+                    None => format!("{}/{}.ndjson", prefix, now_ms),
+                };
+                objects.push((key, to_ndjson_line(payload)?));
+            }
+            ObjectStoreGranularity::Block => {
+                for (direction, blocks) in [
+                    ("apply", &payload["apply"]),
+                    ("rollback", &payload["rollback"]),
+                ] {
+                    for block in blocks.as_array().unwrap_or(&vec![]) {
+                        let entry = json!({
+                            "type": direction,
+                            "block": block,
+                            "chainhook": payload["chainhook"],
+                        });
+                        let key = format!("{}/{}-{}.ndjson", prefix, object_name(block), direction);
+                        objects.push((key, to_ndjson_line(&entry)?));
+                    }
+                }
+            }
+        }
+        Ok(ObjectStoreUpload {
+            url: hook.url.clone(),
+            objects,
+        })
+    }
+}
+
+fn object_name(block: &JsonValue) -> String {
+    format!(
+        "{}-{}",
+        block["block_identifier"]["index"],
+        block["block_identifier"]["hash"]
+            .as_str()
+            .unwrap_or("unknown")
+    )
+}
+
+fn to_ndjson_line(value: &JsonValue) -> Result<Vec<u8>, String> {
+    let mut bytes =
+        serde_json::to_vec(value).map_err(|e| format!("unable to serialize payload {}", e))?;
+    bytes.push(b'\n');
+    Ok(bytes)
+}
+
+#[cfg(feature = "object-store")]
+async fn upload(upload: &ObjectStoreUpload) -> Result<(), String> {
+    use ::object_store::{
+        aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, path::Path, ObjectStore,
+    };
+
+    let url = reqwest::Url::parse(&upload.url)
+        .map_err(|e| format!("invalid object store url {}: {}", upload.url, e))?;
+    // Credentials (and custom endpoints, such as MinIO) are read from the environment
+    let store: Box<dyn ObjectStore> = match url.scheme() {
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_url(upload.url.clone())
+                .build()
+                .map_err(|e| format!("unable to build s3 client: {}", e))?,
+        ),
+        "gs" => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(upload.url.clone())
+                .build()
+                .map_err(|e| format!("unable to build gcs client: {}", e))?,
+        ),
+        scheme => return Err(format!("unsupported object store scheme {}", scheme)),
+    };
+    let base_path = url.path().trim_matches('/');
+    for (key, bytes) in upload.objects.iter() {
+        let location = if base_path.is_empty() {
+            Path::from(key.as_str())
+        } else {
+            Path::from(format!("{}/{}", base_path, key))
+        };
+        store
+            .put(&location, bytes.clone().into())
+            .await
+            .map_err(|e| format!("unable to write object {}: {}", location, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "object-store"))]
+async fn upload(_upload: &ObjectStoreUpload) -> Result<(), String> {
+    Err("object store support is not enabled (requires the `object-store` feature)".to_string())
+}
+
+pub async fn send_object_store_upload(
+    object_store_upload: &ObjectStoreUpload,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut retry = 0;
+    loop {
+        let err_msg = match upload(object_store_upload).await {
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(
+                        logger,
+                        "{} objects written to {}",
+                        object_store_upload.objects.len(),
+                        object_store_upload.url
+                    )
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                retry += 1;
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                e
+            }
+        };
+        if retry >= attempts_max {
+            let msg: String = format!(
+                "unable to write objects after several retries. most recent error: {}",
+                err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        std::thread::sleep(std::time::Duration::from_secs(attempts_interval_sec.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chainhooks::types::{DeliveryOptions, ObjectStoreGranularity, ObjectStoreHook};
+
+    use super::ObjectStoreUpload;
+
+    #[test]
+    fn it_splits_payloads_per_block() {
+        let hook = ObjectStoreHook {
+            url: "s3://bucket".to_string(),
+            key_prefix: Some("/occurrences/".to_string()),
+            granularity: Some(ObjectStoreGranularity::Block),
+            delivery: DeliveryOptions::default(),
+        };
+        let payload = json!({
+            "apply": [
+                { "block_identifier": { "index": 1, "hash": "0x01" } },
+                { "block_identifier": { "index": 2, "hash": "0x02" } }
+            ],
+            "rollback": [
+                { "block_identifier": { "index": 2, "hash": "0x0b" } }
+            ],
+            "chainhook": { "uuid": "1234" }
+        });
+        let upload = ObjectStoreUpload::new(&hook, "1234", &payload).unwrap();
+        let keys = upload
+            .objects
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "occurrences/1234/1-0x01-apply.ndjson",
+                "occurrences/1234/2-0x02-apply.ndjson",
+                "occurrences/1234/2-0x0b-rollback.ndjson",
+            ]
+        );
+        for (_, bytes) in upload.objects.iter() {
+            assert_eq!(bytes.last(), Some(&b'\n'));
+        }
+    }
+}
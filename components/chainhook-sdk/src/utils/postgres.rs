@@ -0,0 +1,117 @@
+#[cfg(feature = "postgres")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use hiro_system_kit::slog;
+
+use super::Context;
+
+/// A serialized occurrence, ready to be inserted in a Postgres table.
+#[derive(Clone, Debug)]
+pub struct PostgresRecord {
+    pub dsn: String,
+    pub table: String,
+    pub column: String,
+    pub payload: Vec<u8>,
+}
+
+impl PostgresRecord {
+    pub fn new(dsn: &str, table: &str, column: &str, payload: Vec<u8>) -> PostgresRecord {
+        PostgresRecord {
+            dsn: dsn.to_string(),
+            table: table.to_string(),
+            column: column.to_string(),
+            payload,
+        }
+    }
+}
+
+/// Clients are shared by the observer and the scans running in other threads, a single
+/// connection being kept per DSN. Closed connections are reopened on the next insert.
+#[cfg(feature = "postgres")]
+fn postgres_clients() -> &'static Mutex<HashMap<String, Arc<tokio_postgres::Client>>> {
+    static POSTGRES_CLIENTS: OnceLock<Mutex<HashMap<String, Arc<tokio_postgres::Client>>>> =
+        OnceLock::new();
+    POSTGRES_CLIENTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(feature = "postgres")]
+async fn get_or_connect_client(dsn: &str) -> Result<Arc<tokio_postgres::Client>, String> {
+    if let Some(client) = postgres_clients()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(dsn)
+    {
+        if !client.is_closed() {
+            return Ok(client.clone());
+        }
+    }
+    let (client, connection) = tokio_postgres::connect(dsn, tokio_postgres::NoTls)
+        .await
+        .map_err(|e| format!("unable to connect to postgres: {}", e))?;
+    // the client is closed once the connection fails or its runtime shuts down
+    tokio::spawn(connection);
+    let client = Arc::new(client);
+    postgres_clients()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(dsn.to_string(), client.clone());
+    Ok(client)
+}
+
+#[cfg(feature = "postgres")]
+async fn insert(record: &PostgresRecord) -> Result<(), String> {
+    let body = String::from_utf8(record.payload.clone())
+        .map_err(|e| format!("unable to encode payload: {}", e))?;
+    let client = get_or_connect_client(&record.dsn).await?;
+    // table and column were validated as plain identifiers when the predicate was registered
+    let statement = format!(
+        "INSERT INTO {} ({}) VALUES ($1::text::jsonb)",
+        record.table, record.column
+    );
+    client
+        .execute(&statement, &[&body])
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("unable to insert occurrence in {}: {}", record.table, e))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn insert(_record: &PostgresRecord) -> Result<(), String> {
+    Err("postgres support is not enabled (requires the `postgres` feature)".to_string())
+}
+
+pub async fn send_postgres_record(
+    record: &PostgresRecord,
+    attempts_max: u16,
+    attempts_interval_sec: u16,
+    ctx: &Context,
+) -> Result<(), String> {
+    let mut retry = 0;
+    loop {
+        let err_msg = match insert(record).await {
+            Ok(_) => {
+                ctx.try_log(|logger| {
+                    slog::debug!(logger, "Occurrence inserted in table {}", record.table)
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                retry += 1;
+                ctx.try_log(|logger| slog::warn!(logger, "{}", e));
+                e
+            }
+        };
+        if retry >= attempts_max {
+            let msg: String = format!(
+                "unable to insert occurrence after several retries. most recent error: {}",
+                err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(attempts_interval_sec.into())).await;
+    }
+}
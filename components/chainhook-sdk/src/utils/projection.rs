@@ -0,0 +1,97 @@
+use serde_json::{Map, Value as JsonValue};
+
+/// Keeps only the `fields` of the blocks applied and rolled back in an occurrence payload.
+/// Fields are dot separated paths relative to a block, such as `block_identifier` or
+/// `transactions.metadata.kind`; arrays met along a path are projected element-wise.
+pub fn project_payload_fields(mut payload: JsonValue, fields: &[String]) -> JsonValue {
+    let paths = fields
+        .iter()
+        .map(|field| field.split('.').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for key in ["apply", "rollback"] {
+        if let Some(blocks) = payload.get_mut(key) {
+            *blocks = project(blocks, &paths).unwrap_or(JsonValue::Array(vec![]));
+        }
+    }
+    payload
+}
+
+pub fn validate_include_fields(fields: &[String]) -> Result<(), String> {
+    for field in fields.iter() {
+        if field.split('.').any(|segment| segment.is_empty()) {
+            return Err(format!(
+                "include_fields entry '{}' must be a dot separated path",
+                field
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn project(value: &JsonValue, paths: &[Vec<&str>]) -> Option<JsonValue> {
+    if paths.iter().any(|path| path.is_empty()) {
+        return Some(value.clone());
+    }
+    match value {
+        JsonValue::Array(entries) => Some(JsonValue::Array(
+            entries.iter().filter_map(|e| project(e, paths)).collect(),
+        )),
+        JsonValue::Object(entries) => {
+            let mut projected = Map::new();
+            for (key, entry) in entries.iter() {
+                let sub_paths = paths
+                    .iter()
+                    .filter(|path| path[0] == key.as_str())
+                    .map(|path| path[1..].to_vec())
+                    .collect::<Vec<_>>();
+                if sub_paths.is_empty() {
+                    continue;
+                }
+                if let Some(entry) = project(entry, &sub_paths) {
+                    projected.insert(key.clone(), entry);
+                }
+            }
+            Some(JsonValue::Object(projected))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::project_payload_fields;
+
+    #[test]
+    fn it_projects_block_fields() {
+        let payload = json!({
+            "apply": [{
+                "block_identifier": { "index": 1, "hash": "0x01" },
+                "metadata": {},
+                "transactions": [
+                    { "transaction_identifier": { "hash": "0xaa" }, "metadata": { "kind": { "type": "Coinbase" }, "fee": 0 } },
+                    { "transaction_identifier": { "hash": "0xbb" }, "metadata": { "kind": { "type": "NativeTokenTransfer" }, "fee": 180 } }
+                ]
+            }],
+            "rollback": [],
+            "chainhook": { "uuid": "1234" }
+        });
+        let fields = vec![
+            "block_identifier".to_string(),
+            "transactions.metadata.kind".to_string(),
+        ];
+        assert_eq!(
+            project_payload_fields(payload, &fields),
+            json!({
+                "apply": [{
+                    "block_identifier": { "index": 1, "hash": "0x01" },
+                    "transactions": [
+                        { "metadata": { "kind": { "type": "Coinbase" } } },
+                        { "metadata": { "kind": { "type": "NativeTokenTransfer" } } }
+                    ]
+                }],
+                "rollback": [],
+                "chainhook": { "uuid": "1234" }
+            })
+        );
+    }
+}
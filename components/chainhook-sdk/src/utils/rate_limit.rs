@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::chainhooks::types::HookAction;
+use crate::observer::PredicatesConfig;
+
+/// Limits applied to the requests sent to a single destination host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostRateLimitConfig {
+    /// Maximum number of requests sent to the host per second.
+    pub requests_per_second: Option<u32>,
+    /// Maximum number of requests waiting for a response from the host.
+    pub max_in_flight: Option<u32>,
+}
+
+impl HostRateLimitConfig {
+    fn is_unlimited(&self) -> bool {
+        self.requests_per_second.is_none() && self.max_in_flight.is_none()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(0) = self.requests_per_second {
+            return Err("requests_per_second must be greater than 0".to_string());
+        }
+        if let Some(0) = self.max_in_flight {
+            return Err("max_in_flight must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Rate limits of the requests sent by `http_post` actions, shared by every predicate
+/// posting to the same destination host.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Maximum number of requests sent to each host per second.
+    pub requests_per_second: Option<u32>,
+    /// Maximum number of requests waiting for a response from each host.
+    pub max_in_flight: Option<u32>,
+    /// Limits replacing the ones above for specific hosts, keyed by host name.
+    pub hosts: Option<BTreeMap<String, HostRateLimitConfig>>,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.default_limits().validate()?;
+        for (host, limits) in self.hosts.iter().flatten() {
+            limits
+                .validate()
+                .map_err(|e| format!("hosts.{}: {}", host, e))?;
+        }
+        Ok(())
+    }
+
+    fn default_limits(&self) -> HostRateLimitConfig {
+        HostRateLimitConfig {
+            requests_per_second: self.requests_per_second,
+            max_in_flight: self.max_in_flight,
+        }
+    }
+
+    /// Returns the limits applied to the requests sent to `host`.
+    pub fn limits_for(&self, host: &str) -> HostRateLimitConfig {
+        self.hosts
+            .as_ref()
+            .and_then(|hosts| hosts.get(host))
+            .copied()
+            .unwrap_or_else(|| self.default_limits())
+    }
+}
+
+/// Throttles the requests sent to a destination host.
+pub struct HostRateLimiter {
+    limits: HostRateLimitConfig,
+    next_slot: Mutex<Instant>,
+    in_flight: Option<Arc<Semaphore>>,
+}
+
+/// Held while a request is in flight, releasing its slot when dropped.
+pub struct RateLimitPermit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+}
+
+impl HostRateLimiter {
+    pub fn new(limits: HostRateLimitConfig) -> Self {
+        HostRateLimiter {
+            limits,
+            next_slot: Mutex::new(Instant::now()),
+            in_flight: limits
+                .max_in_flight
+                .map(|max| Arc::new(Semaphore::new(max.max(1) as usize))),
+        }
+    }
+
+    /// Waits until a request can be sent to the host without exceeding its limits.
+    pub async fn acquire(&self) -> RateLimitPermit {
+        let in_flight = match self.in_flight {
+            Some(ref semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        if let Some(requests_per_second) = self.limits.requests_per_second {
+            let interval = Duration::from_secs(1) / requests_per_second.max(1);
+            let slot = {
+                let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+                let slot = (*next_slot).max(Instant::now());
+                *next_slot = slot + interval;
+                slot
+            };
+            tokio::time::sleep_until(tokio::time::Instant::from_std(slot)).await;
+        }
+        RateLimitPermit {
+            _in_flight: in_flight,
+        }
+    }
+}
+
+/// Waits for a slot of the rate limiter, if any.
+pub async fn acquire_rate_limit_permit(
+    rate_limiter: &Option<Arc<HostRateLimiter>>,
+) -> Option<RateLimitPermit> {
+    match rate_limiter {
+        Some(rate_limiter) => Some(rate_limiter.acquire().await),
+        None => None,
+    }
+}
+
+/// Limiters are shared by the observer and the scans running in other threads, so that the
+/// limits of a host apply to all the requests sent to it by this process.
+fn host_rate_limiters() -> &'static Mutex<HashMap<String, Arc<HostRateLimiter>>> {
+    static HOST_RATE_LIMITERS: OnceLock<Mutex<HashMap<String, Arc<HostRateLimiter>>>> =
+        OnceLock::new();
+    HOST_RATE_LIMITERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the rate limiter of the host targeted by `url`, or None if it isn't rate limited.
+pub fn host_rate_limiter(
+    url: &str,
+    predicates_config: &PredicatesConfig,
+) -> Option<Arc<HostRateLimiter>> {
+    let config = predicates_config.rate_limit.as_ref()?;
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let limits = config.limits_for(host);
+    if limits.is_unlimited() {
+        return None;
+    }
+    let mut rate_limiters = host_rate_limiters()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    match rate_limiters.get(host) {
+        Some(rate_limiter) if rate_limiter.limits == limits => Some(rate_limiter.clone()),
+        _ => {
+            let rate_limiter = Arc::new(HostRateLimiter::new(limits));
+            rate_limiters.insert(host.to_string(), rate_limiter.clone());
+            Some(rate_limiter)
+        }
+    }
+}
+
+/// Returns the rate limiter of the host targeted by an `http_post` action.
+pub fn action_rate_limiter(
+    action: &HookAction,
+    predicates_config: &PredicatesConfig,
+) -> Option<Arc<HostRateLimiter>> {
+    match action {
+        HookAction::HttpPost(http) => host_rate_limiter(&http.url, predicates_config),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, Instant};
+
+    use super::{host_rate_limiter, HostRateLimitConfig, HostRateLimiter, RateLimitConfig};
+    use crate::observer::PredicatesConfig;
+
+    #[test]
+    fn it_picks_the_limits_of_each_host() {
+        let mut hosts = BTreeMap::new();
+        hosts.insert(
+            "slow.example.com".to_string(),
+            HostRateLimitConfig {
+                requests_per_second: Some(1),
+                max_in_flight: None,
+            },
+        );
+        let mut predicates_config = PredicatesConfig::new();
+        predicates_config.rate_limit = Some(RateLimitConfig {
+            requests_per_second: None,
+            max_in_flight: Some(4),
+            hosts: Some(hosts),
+        });
+
+        let slow = host_rate_limiter("https://slow.example.com/hook", &predicates_config).unwrap();
+        assert_eq!(slow.limits.requests_per_second, Some(1));
+        assert_eq!(slow.limits.max_in_flight, None);
+        let other = host_rate_limiter("http://other.example.com:3000", &predicates_config).unwrap();
+        assert_eq!(other.limits.max_in_flight, Some(4));
+        let same = host_rate_limiter("http://other.example.com:3000/b", &predicates_config);
+        assert!(std::sync::Arc::ptr_eq(&other, &same.unwrap()));
+
+        assert!(host_rate_limiter("http://other.example.com", &PredicatesConfig::new()).is_none());
+        assert!(RateLimitConfig {
+            requests_per_second: Some(0),
+            max_in_flight: None,
+            hosts: None,
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn it_spaces_requests() {
+        let rate_limiter = HostRateLimiter::new(HostRateLimitConfig {
+            requests_per_second: Some(20),
+            max_in_flight: None,
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            rate_limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn it_limits_requests_in_flight() {
+        let rate_limiter = HostRateLimiter::new(HostRateLimitConfig {
+            requests_per_second: None,
+            max_in_flight: Some(1),
+        });
+        let permit = rate_limiter.acquire().await;
+        let blocked = tokio::time::timeout(Duration::from_millis(50), rate_limiter.acquire()).await;
+        assert!(blocked.is_err());
+        drop(permit);
+        let released =
+            tokio::time::timeout(Duration::from_millis(50), rate_limiter.acquire()).await;
+        assert!(released.is_ok());
+    }
+}
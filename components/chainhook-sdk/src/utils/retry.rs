@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hiro_system_kit::slog;
+use reqwest::RequestBuilder;
+
+use super::rate_limit::{acquire_rate_limit_permit, HostRateLimiter};
+use super::{read_delivery_ack, send_request, send_request_expecting_ack, Context};
+use crate::chainhooks::types::RetryPolicy;
+
+const DEFAULT_MAX_ATTEMPTS: u16 = 3;
+const DEFAULT_BACKOFF_MS: u64 = 1000;
+
+/// Calls `attempt` until it succeeds or the policy's attempts are exhausted, returning the
+/// most recent error in that case. Each attempt is expected to perform a single try.
+pub async fn send_with_retry_policy<T, F, Fut>(
+    policy: &RetryPolicy,
+    ctx: &Context,
+    mut attempt: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let max_attempts = policy.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1);
+    let multiplier = policy.backoff_multiplier.unwrap_or(1).max(1);
+    let mut backoff = Duration::from_millis(policy.backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS));
+    let mut attempts = 0;
+    loop {
+        let res = match policy.timeout_ms {
+            Some(timeout_ms) => {
+                match tokio::time::timeout(Duration::from_millis(timeout_ms), attempt()).await {
+                    Ok(res) => res,
+                    Err(_) => Err(format!("attempt timed out after {}ms", timeout_ms)),
+                }
+            }
+            None => attempt().await,
+        };
+        let err_msg = match res {
+            Ok(res) => return Ok(res),
+            Err(e) => e,
+        };
+        attempts += 1;
+        if attempts >= max_attempts {
+            let msg = format!(
+                "delivery failed after {} attempts. most recent error: {}",
+                attempts, err_msg
+            );
+            ctx.try_log(|logger| slog::warn!(logger, "{}", msg));
+            return Err(msg);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = backoff.saturating_mul(multiplier);
+    }
+}
+
+pub async fn send_request_with_retry_policy(
+    request_builder: RequestBuilder,
+    policy: &RetryPolicy,
+    rate_limiter: &Option<Arc<HostRateLimiter>>,
+    ctx: &Context,
+) -> Result<(), String> {
+    send_with_retry_policy(policy, ctx, || async {
+        let Some(request_builder) = request_builder.try_clone() else {
+            return Err("internal server error: unable to clone request builder".to_string());
+        };
+        let _permit = acquire_rate_limit_permit(rate_limiter).await;
+        send_request(request_builder, 1, 0, ctx).await
+    })
+    .await
+}
+
+/// Sends the request of an `http_post` occurrence, waiting for the receiver to acknowledge it
+/// when `require_ack` is set. Each attempt waits for the `rate_limiter` of the destination
+/// host, if any. Returns the acknowledged cursor, if any.
+pub async fn send_http_occurrence_with_retry_policy(
+    request_builder: RequestBuilder,
+    require_ack: bool,
+    policy: &RetryPolicy,
+    rate_limiter: &Option<Arc<HostRateLimiter>>,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    if !require_ack {
+        return send_request_with_retry_policy(request_builder, policy, rate_limiter, ctx)
+            .await
+            .map(|_| None);
+    }
+    send_with_retry_policy(policy, ctx, || async {
+        let Some(request_builder) = request_builder.try_clone() else {
+            return Err("internal server error: unable to clone request builder".to_string());
+        };
+        let _permit = acquire_rate_limit_permit(rate_limiter).await;
+        send_request_expecting_ack(request_builder, ctx)
+            .await
+            .map(Some)
+    })
+    .await
+}
+
+/// Same as [send_http_occurrence_with_retry_policy], also storing the status of the response to
+/// the most recent attempt in `last_status`, which is left to 0 when no response was received.
+pub async fn send_http_occurrence_recording_status(
+    request_builder: RequestBuilder,
+    require_ack: bool,
+    policy: &RetryPolicy,
+    rate_limiter: &Option<Arc<HostRateLimiter>>,
+    last_status: &AtomicU16,
+    ctx: &Context,
+) -> Result<Option<u64>, String> {
+    send_with_retry_policy(policy, ctx, || async {
+        let Some(request_builder) = request_builder.try_clone() else {
+            return Err("internal server error: unable to clone request builder".to_string());
+        };
+        let _permit = acquire_rate_limit_permit(rate_limiter).await;
+        last_status.store(0, Ordering::Relaxed);
+        let res = request_builder
+            .send()
+            .await
+            .map_err(|e| format!("unable to send request {}", e))?;
+        last_status.store(res.status().as_u16(), Ordering::Relaxed);
+        if require_ack {
+            return read_delivery_ack(res, ctx).await.map(Some);
+        }
+        if !res.status().is_success() {
+            let err_msg = format!("Trigger {} failed with status {}", res.url(), res.status());
+            ctx.try_log(|logger| slog::warn!(logger, "{}", err_msg));
+            return Err(err_msg);
+        }
+        ctx.try_log(|logger| slog::debug!(logger, "Trigger {} successful", res.url()));
+        Ok(None)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use super::send_with_retry_policy;
+    use crate::chainhooks::types::RetryPolicy;
+    use crate::utils::Context;
+
+    #[tokio::test]
+    async fn it_retries_until_max_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: Some(4),
+            backoff_ms: Some(1),
+            backoff_multiplier: Some(2),
+            timeout_ms: None,
+        };
+        let attempts = AtomicU16::new(0);
+        let res = send_with_retry_policy(&policy, &Context::empty(), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err("unreachable".to_string())
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn it_stops_retrying_on_success() {
+        let policy = RetryPolicy::fixed(5, 0);
+        let attempts = AtomicU16::new(0);
+        let res = send_with_retry_policy(&policy, &Context::empty(), || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 1 {
+                Err("unreachable".to_string())
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+        assert!(res.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn it_times_out_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: Some(1),
+            backoff_ms: None,
+            backoff_multiplier: None,
+            timeout_ms: Some(10),
+        };
+        let res = send_with_retry_policy(&policy, &Context::empty(), || async {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await;
+        assert!(res.unwrap_err().contains("timed out"));
+    }
+}
@@ -0,0 +1,30 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Name of the header carrying the signature of `http_post` payloads.
+pub const SIGNATURE_HEADER: &str = "X-Chainhook-Signature";
+
+/// Computes the HMAC-SHA256 of `payload` using `secret`, formatted as `sha256=<hex digest>`.
+/// Receivers authenticate a delivery by computing the same value over the raw request body
+/// and comparing it with the `X-Chainhook-Signature` header.
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    // HMAC accepts keys of any length, so this can't fail
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("unable to initialize hmac");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign_payload;
+
+    #[test]
+    fn it_signs_payloads_with_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
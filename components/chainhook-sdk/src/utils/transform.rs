@@ -0,0 +1,100 @@
+use jaq_interpret::{Ctx, Filter, FilterT, ParseCtx, RcIter, Val};
+use serde_json::Value as JsonValue;
+
+fn compile_transform(expression: &str) -> Result<Filter, String> {
+    let mut defs = ParseCtx::new(Vec::new());
+    defs.insert_natives(jaq_core::core());
+    defs.insert_defs(jaq_std::std());
+
+    let (parsed, errors) = jaq_parse::parse(expression, jaq_parse::main());
+    if let Some(e) = errors.first() {
+        return Err(format!("invalid transform expression: {}", e));
+    }
+    let Some(parsed) = parsed else {
+        return Err("invalid transform expression".to_string());
+    };
+    let filter = defs.compile(parsed);
+    if !defs.errs.is_empty() {
+        return Err(format!(
+            "invalid transform expression: {} undefined filters or variables",
+            defs.errs.len()
+        ));
+    }
+    Ok(filter)
+}
+
+pub fn validate_transform(expression: &str) -> Result<(), String> {
+    compile_transform(expression).map(|_| ())
+}
+
+/// Applies the jq `expression` to an occurrence payload. Expressions producing several
+/// outputs are delivered as an array.
+pub fn apply_transform(expression: &str, payload: JsonValue) -> Result<JsonValue, String> {
+    let filter = compile_transform(expression)?;
+    let inputs = RcIter::new(core::iter::empty());
+    let mut outputs = filter
+        .run((Ctx::new([], &inputs), Val::from(payload)))
+        .map(|output| {
+            output
+                .map(JsonValue::from)
+                .map_err(|e| format!("unable to apply transform: {}", e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if outputs.len() == 1 {
+        Ok(outputs.remove(0))
+    } else {
+        Ok(JsonValue::Array(outputs))
+    }
+}
+
+/// Evaluates the jq `expression` against an occurrence payload, returning true if it outputs a
+/// value other than `false` or `null`.
+pub fn evaluate_condition(expression: &str, payload: &JsonValue) -> Result<bool, String> {
+    let filter = compile_transform(expression)?;
+    let inputs = RcIter::new(core::iter::empty());
+    for output in filter.run((Ctx::new([], &inputs), Val::from(payload.clone()))) {
+        let output = output.map_err(|e| format!("unable to evaluate condition: {}", e))?;
+        if output.as_bool() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_transform, evaluate_condition, validate_transform};
+
+    #[test]
+    fn it_applies_jq_expressions_to_payloads() {
+        let payload = json!({
+            "apply": [
+                { "block_identifier": { "index": 1, "hash": "0x01" }, "transactions": [] },
+                { "block_identifier": { "index": 2, "hash": "0x02" }, "transactions": [] }
+            ],
+            "rollback": []
+        });
+        assert_eq!(
+            apply_transform("[.apply[].block_identifier.index]", payload.clone()).unwrap(),
+            json!([1, 2])
+        );
+        assert_eq!(
+            apply_transform(".apply[].block_identifier.hash", payload).unwrap(),
+            json!(["0x01", "0x02"])
+        );
+        assert!(validate_transform(".apply[").is_err());
+        assert!(validate_transform("undefined_filter").is_err());
+    }
+
+    #[test]
+    fn it_evaluates_conditions_against_payloads() {
+        let payload = json!({
+            "apply": [{ "transactions": [{ "amount": 10 }, { "amount": 2000 }] }]
+        });
+        let condition = "any(.apply[].transactions[]; .amount > 1000)";
+        assert!(evaluate_condition(condition, &payload).unwrap());
+        let condition = ".apply[].transactions[].amount > 5000";
+        assert!(!evaluate_condition(condition, &payload).unwrap());
+        assert!(!evaluate_condition(".rollback", &payload).unwrap());
+    }
+}
@@ -699,6 +699,8 @@ export interface StacksBlockMetadata {
   tenure_height?: number | null;
   signer_bitvec?: string | null;
   signer_signature?: string[] | null;
+  signer_signature_hash?: string | null;
+  miner_signature?: string | null;
   cycle_number?: number | null;
   reward_set?: {
     pox_ustx_threshold: string;
@@ -712,6 +714,7 @@ export interface StacksBlockMetadata {
       missed_reward_slots: [];
     };
   } | null;
+  skipped_transactions?: TransactionIdentifier[];
 }
 
 /**
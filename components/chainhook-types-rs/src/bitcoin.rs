@@ -1,7 +1,10 @@
 use crate::TransactionIdentifier;
+use schemars::JsonSchema;
 
 /// A transaction input, which defines old coins to be consumed
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub struct TxIn {
     /// The reference to the previous output that is being used an an input.
     pub previous_output: OutPoint,
@@ -22,7 +25,9 @@ pub struct TxIn {
 }
 
 /// A transaction output, which defines new coins to be created from old ones.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub struct TxOut {
     /// The value of the output, in satoshis.
     pub value: u64,
@@ -31,7 +36,9 @@ pub struct TxOut {
 }
 
 /// A reference to a transaction output.
-#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(
+    Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub struct OutPoint {
     /// The referenced transaction's txid.
     pub txid: TransactionIdentifier,
@@ -41,6 +48,9 @@ pub struct OutPoint {
     pub value: u64,
     /// The script which must be satisfied for the output to be spent.
     pub block_height: u64,
+    /// The script of the referenced output, when it could be resolved.
+    #[serde(default)]
+    pub script_pubkey: Option<String>,
 }
 
 impl TxOut {
@@ -61,7 +71,9 @@ impl TxOut {
 /// For serialization and deserialization performance it is stored internally as a single `Vec`,
 /// saving some allocations.
 ///
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub struct Witness {
     /// contains the witness `Vec<Vec<u8>>` serialization without the initial varint indicating the
     /// number of elements (which is stored in `witness_elements`)
@@ -1,30 +1,32 @@
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+use schemars::JsonSchema;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct STXTransferEventData {
     pub sender: String,
     pub recipient: String,
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct STXMintEventData {
     pub recipient: String,
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct STXLockEventData {
     pub locked_amount: String,
     pub unlock_height: String,
     pub locked_address: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct STXBurnEventData {
     pub sender: String,
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct NFTTransferEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -34,7 +36,7 @@ pub struct NFTTransferEventData {
     pub recipient: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct NFTMintEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -43,7 +45,7 @@ pub struct NFTMintEventData {
     pub recipient: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct NFTBurnEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -52,7 +54,7 @@ pub struct NFTBurnEventData {
     pub sender: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct FTTransferEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -61,7 +63,7 @@ pub struct FTTransferEventData {
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct FTMintEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -69,7 +71,7 @@ pub struct FTMintEventData {
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct FTBurnEventData {
     #[serde(rename = "asset_identifier")]
     pub asset_class_identifier: String,
@@ -77,7 +79,7 @@ pub struct FTBurnEventData {
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DataVarSetEventData {
     pub contract_identifier: String,
     pub var: String,
@@ -85,7 +87,7 @@ pub struct DataVarSetEventData {
     pub hex_new_value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DataMapInsertEventData {
     pub contract_identifier: String,
     pub map: String,
@@ -95,7 +97,7 @@ pub struct DataMapInsertEventData {
     pub hex_inserted_value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DataMapUpdateEventData {
     pub contract_identifier: String,
     pub map: String,
@@ -105,7 +107,7 @@ pub struct DataMapUpdateEventData {
     pub hex_new_value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DataMapDeleteEventData {
     pub contract_identifier: String,
     pub map: String,
@@ -113,7 +115,7 @@ pub struct DataMapDeleteEventData {
     pub hex_deleted_key: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct SmartContractEventData {
     pub contract_identifier: String,
     pub topic: String,
@@ -121,7 +123,7 @@ pub struct SmartContractEventData {
     pub hex_value: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum StacksTransactionEventPayload {
     STXTransferEvent(STXTransferEventData),
@@ -141,7 +143,7 @@ pub enum StacksTransactionEventPayload {
     SmartContractEvent(SmartContractEventData),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksTransactionEvent {
     #[serde(flatten)]
     pub event_payload: StacksTransactionEventPayload,
@@ -149,7 +151,7 @@ pub struct StacksTransactionEvent {
     pub position: StacksTransactionEventPosition,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksTransactionEventPosition {
     pub index: u32,
 }
@@ -1,13 +1,14 @@
+use schemars::JsonSchema;
 use serde_json::Value;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum OrdinalOperation {
     InscriptionRevealed(OrdinalInscriptionRevealData),
     InscriptionTransferred(OrdinalInscriptionTransferData),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct OrdinalInscriptionTransferData {
     pub ordinal_number: u64,
     pub destination: OrdinalInscriptionTransferDestination,
@@ -17,7 +18,7 @@ pub struct OrdinalInscriptionTransferData {
     pub tx_index: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum OrdinalInscriptionTransferDestination {
     Transferred(String),
@@ -25,7 +26,7 @@ pub enum OrdinalInscriptionTransferDestination {
     Burnt(String),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub enum OrdinalInscriptionCurseType {
     DuplicateField,
     IncompleteField,
@@ -39,7 +40,7 @@ pub enum OrdinalInscriptionCurseType {
     Generic,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct OrdinalInscriptionRevealData {
     pub content_bytes: String,
     pub content_type: String,
@@ -79,13 +80,13 @@ impl OrdinalInscriptionRevealData {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct OrdinalInscriptionNumber {
     pub classic: i64,
     pub jubilee: i64,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Brc20TokenDeployData {
     pub tick: String,
     pub max: String,
@@ -96,7 +97,7 @@ pub struct Brc20TokenDeployData {
     pub self_mint: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Brc20BalanceData {
     pub tick: String,
     pub amt: String,
@@ -104,7 +105,7 @@ pub struct Brc20BalanceData {
     pub inscription_id: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Brc20TransferData {
     pub tick: String,
     pub amt: String,
@@ -113,7 +114,7 @@ pub struct Brc20TransferData {
     pub inscription_id: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Brc20Operation {
     Deploy(Brc20TokenDeployData),
@@ -9,7 +9,7 @@ use std::fmt::Display;
 use std::hash::{Hash, Hasher};
 
 /// BlockIdentifier uniquely identifies a block in a particular network.
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
 pub struct BlockIdentifier {
     /// Also known as the block height.
     pub index: u64,
@@ -70,7 +70,7 @@ impl Eq for BlockIdentifier {}
 /// requested and received a block identified by a specific BlockIndentifier,
 /// all future calls for that same BlockIdentifier must return the same block
 /// contents.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksBlockData {
     pub block_identifier: BlockIdentifier,
     pub parent_block_identifier: BlockIdentifier,
@@ -84,7 +84,7 @@ pub struct StacksBlockData {
 
 /// StacksMicroblock contain an array of Transactions that occurred at a particular
 /// BlockIdentifier.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksMicroblockData {
     pub block_identifier: BlockIdentifier,
     pub parent_block_identifier: BlockIdentifier,
@@ -96,17 +96,20 @@ pub struct StacksMicroblockData {
     pub metadata: StacksMicroblockMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksMicroblockMetadata {
     pub anchor_block_identifier: BlockIdentifier,
+    /// Transactions left out of the microblock because they could not be standardized.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_transactions: Vec<TransactionIdentifier>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksMicroblocksTrail {
     pub microblocks: Vec<StacksMicroblockData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksBlockMetadata {
     pub bitcoin_anchor_block_identifier: BlockIdentifier,
     pub pox_cycle_index: u32,
@@ -119,6 +122,8 @@ pub struct StacksBlockMetadata {
     pub block_time: Option<u64>,
     pub signer_bitvec: Option<String>,
     pub signer_signature: Option<Vec<String>>,
+    pub signer_signature_hash: Option<String>,
+    pub miner_signature: Option<String>,
 
     // Available starting in epoch3, only included in blocks where the pox cycle rewards are first calculated
     pub cycle_number: Option<u64>,
@@ -126,16 +131,20 @@ pub struct StacksBlockMetadata {
 
     // Available in /new_block messages sent from stacks-core v3.0 and newer
     pub tenure_height: Option<u64>,
+
+    /// Transactions left out of the block because they could not be standardized.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_transactions: Vec<TransactionIdentifier>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksBlockMetadataRewardSet {
     pub pox_ustx_threshold: String,
     pub rewarded_addresses: Vec<String>,
     pub signers: Option<Vec<StacksBlockMetadataRewardSetSigner>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksBlockMetadataRewardSetSigner {
     pub signing_key: String,
     pub weight: u32,
@@ -148,7 +157,7 @@ pub struct StacksBlockMetadataRewardSetSigner {
 /// requested and received a block identified by a specific BlockIndentifier,
 /// all future calls for that same BlockIdentifier must return the same block
 /// contents.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct BitcoinBlockData {
     pub block_identifier: BlockIdentifier,
     pub parent_block_identifier: BlockIdentifier,
@@ -160,7 +169,7 @@ pub struct BitcoinBlockData {
     pub metadata: BitcoinBlockMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct BitcoinBlockMetadata {
     pub network: BitcoinNetwork,
 }
@@ -168,12 +177,12 @@ pub struct BitcoinBlockMetadata {
 /// The timestamp of the block in milliseconds since the Unix Epoch. The
 /// timestamp is stored in milliseconds because some blockchains produce blocks
 /// more often than once a second.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Deserialize, Serialize, JsonSchema)]
 pub struct Timestamp(i64);
 
 /// Transactions contain an array of Operations that are attributable to the
 /// same TransactionIdentifier.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksTransactionData {
     pub transaction_identifier: TransactionIdentifier,
     pub operations: Vec<Operation>,
@@ -182,7 +191,7 @@ pub struct StacksTransactionData {
     pub metadata: StacksTransactionMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum StacksTransactionKind {
     ContractCall(StacksContractCallData),
@@ -194,21 +203,21 @@ pub enum StacksTransactionKind {
     Unsupported,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum BitcoinOpData {
     StackSTX(StackSTXData),
     DelegateStackSTX(DelegateStackSTXData),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StackSTXData {
     pub locked_amount: String,
     pub unlock_height: String,
     pub stacking_address: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DelegateStackSTXData {
     pub stacking_address: String,
     pub amount: String,
@@ -217,21 +226,21 @@ pub struct DelegateStackSTXData {
     pub unlock_height: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksContractCallData {
     pub contract_identifier: String,
     pub method: String,
     pub args: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksContractDeploymentData {
     pub contract_identifier: String,
     pub code: String,
 }
 
 /// Extra data for Transaction
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksTransactionMetadata {
     pub success: bool,
     pub raw_tx: String,
@@ -253,7 +262,7 @@ pub struct StacksTransactionMetadata {
 }
 
 /// TODO
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged)]
 pub enum StacksTransactionPosition {
     AnchorBlock(AnchorBlockPosition),
@@ -276,18 +285,18 @@ impl StacksTransactionPosition {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AnchorBlockPosition {
     index: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct MicroBlockPosition {
     micro_block_identifier: BlockIdentifier,
     index: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StacksTransactionExecutionCost {
     pub write_length: u64,
     pub write_count: u64,
@@ -297,7 +306,7 @@ pub struct StacksTransactionExecutionCost {
 }
 
 /// Extra event data for Transaction
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default, JsonSchema)]
 pub struct StacksTransactionReceipt {
     pub mutated_contracts_radius: HashSet<String>,
     pub mutated_assets_radius: HashSet<String>,
@@ -322,7 +331,7 @@ impl StacksTransactionReceipt {
 
 /// Transactions contain an array of Operations that are attributable to the
 /// same TransactionIdentifier.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct BitcoinTransactionData {
     pub transaction_identifier: TransactionIdentifier,
     pub operations: Vec<Operation>,
@@ -332,7 +341,7 @@ pub struct BitcoinTransactionData {
 }
 
 /// Extra data for Transaction
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct BitcoinTransactionMetadata {
     pub inputs: Vec<TxIn>,
     pub outputs: Vec<TxOut>,
@@ -344,7 +353,7 @@ pub struct BitcoinTransactionMetadata {
     pub index: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum StacksBaseChainOperation {
     BlockCommitted(StacksBlockCommitmentData),
@@ -353,7 +362,7 @@ pub enum StacksBaseChainOperation {
     StxLocked(LockSTXData),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct StacksBlockCommitmentData {
     pub block_hash: String,
@@ -367,36 +376,36 @@ pub struct StacksBlockCommitmentData {
     pub mining_sats_left: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct PoxReward {
     pub recipient_address: String,
     pub amount: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct KeyRegistrationData;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct PobBlockCommitmentData {
     pub signers: Vec<String>,
     pub stacks_block_hash: String,
     pub amount: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct BlockCommitmentData {
     pub stacks_block_hash: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct TransferSTXData {
     pub sender: String,
     pub recipient: String,
     pub amount: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct LockSTXData {
     pub sender: String,
     pub amount: String,
@@ -405,7 +414,9 @@ pub struct LockSTXData {
 
 /// The transaction_identifier uniquely identifies a transaction in a particular
 /// network and block or in the mempool.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash, PartialOrd, Ord)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash, PartialOrd, Ord, JsonSchema,
+)]
 pub struct TransactionIdentifier {
     /// Any transactions that are attributable only to a block (ex: a block
     /// event) should use the hash of the block as the identifier.
@@ -440,7 +451,16 @@ impl TransactionIdentifier {
 }
 
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::EnumIter, strum::IntoStaticStr,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    strum::EnumIter,
+    strum::IntoStaticStr,
 )]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OperationType {
@@ -449,7 +469,7 @@ pub enum OperationType {
     Lock,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct OperationMetadata {
     /// Has to be specified for ADD_KEY, REMOVE_KEY, and STAKE operations
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -471,7 +491,7 @@ pub struct OperationMetadata {
 /// PublicKey contains a public key byte array for a particular CurveType
 /// encoded in hex. Note that there is no PrivateKey struct as this is NEVER the
 /// concern of an implementation.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct PublicKey {
     /// Hex-encoded public key bytes in the format specified by the CurveType.
     pub hex_bytes: Option<String>,
@@ -479,7 +499,7 @@ pub struct PublicKey {
 }
 
 /// CurveType is the type of cryptographic curve associated with a PublicKey.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum CurveType {
     /// `y (255-bits) || x-sign-bit (1-bit)` - `32 bytes` (<https://ed25519.cr.yp.to/ed25519-20110926.pdf>)
@@ -491,7 +511,7 @@ pub enum CurveType {
 /// Operations contain all balance-changing information within a transaction.
 /// They are always one-sided (only affect 1 AccountIdentifier) and can
 /// succeed or fail independently from a Transaction.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Operation {
     pub operation_identifier: OperationIdentifier,
 
@@ -528,7 +548,7 @@ pub struct Operation {
 
 /// The operation_identifier uniquely identifies an operation within a
 /// transaction.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct OperationIdentifier {
     /// The operation index is used to ensure each operation has a unique
     /// identifier within a transaction. This index is only relative to the
@@ -546,7 +566,7 @@ pub struct OperationIdentifier {
     pub network_index: Option<i64>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, strum::EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, strum::EnumIter, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OperationStatusKind {
     Success,
@@ -555,7 +575,7 @@ pub enum OperationStatusKind {
 /// The account_identifier uniquely identifies an account within a network. All
 /// fields in the account_identifier are utilized to determine this uniqueness
 /// (including the metadata field, if populated).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, JsonSchema)]
 pub struct AccountIdentifier {
     /// The address may be a cryptographic public key (or some encoding of it)
     /// or a provided username.
@@ -575,7 +595,7 @@ pub struct AccountIdentifier {
 /// An account may have state specific to a contract address (ERC-20 token)
 /// and/or a stake (delegated balance). The sub_account_identifier should
 /// specify which state (if applicable) an account instantiation refers to.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, JsonSchema)]
 pub struct SubAccountIdentifier {
     /// The SubAccount address may be a cryptographic value or some other
     /// identifier (ex: bonded) that uniquely specifies a SubAccount.
@@ -590,7 +610,7 @@ pub struct SubAccountIdentifier {
      * pub metadata: Option<serde_json::Value>, */
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SubAccount {
     LiquidBalanceForStorage,
@@ -599,7 +619,7 @@ pub enum SubAccount {
 
 /// Amount is some Value of a Currency. It is considered invalid to specify a
 /// Value without a Currency.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amount {
     /// Value of the transaction in atomic units represented as an
     /// arbitrary-sized signed integer.  For example, 1 BTC would be represented
@@ -616,7 +636,7 @@ pub struct Amount {
 /// Currency is composed of a canonical Symbol and Decimals. This Decimals value
 /// is used to convert an Amount.Value from atomic units (Satoshis) to standard
 /// units (Bitcoins).
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Currency {
     /// Canonical symbol associated with a currency.
     pub symbol: String,
@@ -632,7 +652,7 @@ pub struct Currency {
     pub metadata: Option<CurrencyMetadata>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum CurrencyStandard {
     Sip09,
@@ -640,7 +660,7 @@ pub enum CurrencyStandard {
     None,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct CurrencyMetadata {
     pub asset_class_identifier: String,
     pub asset_identifier: Option<String>,
@@ -648,45 +668,45 @@ pub struct CurrencyMetadata {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
 pub enum BlockchainEvent {
     BlockchainUpdatedWithHeaders(BlockchainUpdatedWithHeaders),
     BlockchainUpdatedWithReorg(BlockchainUpdatedWithReorg),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
 pub struct BlockchainUpdatedWithHeaders {
     pub new_headers: Vec<BlockHeader>,
     pub confirmed_headers: Vec<BlockHeader>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
 pub struct BlockchainUpdatedWithReorg {
     pub headers_to_rollback: Vec<BlockHeader>,
     pub headers_to_apply: Vec<BlockHeader>,
     pub confirmed_headers: Vec<BlockHeader>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
 pub struct BlockHeader {
     pub block_identifier: BlockIdentifier,
     pub parent_block_identifier: BlockIdentifier,
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum BitcoinChainEvent {
     ChainUpdatedWithBlocks(BitcoinChainUpdatedWithBlocksData),
     ChainUpdatedWithReorg(BitcoinChainUpdatedWithReorgData),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct BitcoinChainUpdatedWithBlocksData {
     pub new_blocks: Vec<BitcoinBlockData>,
     pub confirmed_blocks: Vec<BitcoinBlockData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct BitcoinChainUpdatedWithReorgData {
     pub blocks_to_rollback: Vec<BitcoinBlockData>,
     pub blocks_to_apply: Vec<BitcoinBlockData>,
@@ -694,7 +714,7 @@ pub struct BitcoinChainUpdatedWithReorgData {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum StacksChainEvent {
     ChainUpdatedWithBlocks(StacksChainUpdatedWithBlocksData),
     ChainUpdatedWithReorg(StacksChainUpdatedWithReorgData),
@@ -733,7 +753,7 @@ impl StacksChainEvent {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StacksBlockUpdate {
     pub block: StacksBlockData,
     pub parent_microblocks_to_rollback: Vec<StacksMicroblockData>,
@@ -750,25 +770,25 @@ impl StacksBlockUpdate {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StacksChainUpdatedWithBlocksData {
     pub new_blocks: Vec<StacksBlockUpdate>,
     pub confirmed_blocks: Vec<StacksBlockData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StacksChainUpdatedWithReorgData {
     pub blocks_to_rollback: Vec<StacksBlockUpdate>,
     pub blocks_to_apply: Vec<StacksBlockUpdate>,
     pub confirmed_blocks: Vec<StacksBlockData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StacksChainUpdatedWithMicroblocksData {
     pub new_microblocks: Vec<StacksMicroblockData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StacksChainUpdatedWithMicroblocksReorgData {
     pub microblocks_to_rollback: Vec<StacksMicroblockData>,
     pub microblocks_to_apply: Vec<StacksMicroblockData>,
@@ -916,12 +936,48 @@ impl BitcoinNetwork {
 pub enum BitcoinBlockSignaling {
     Stacks(StacksNodeConfig),
     ZeroMQ(String),
+    /// Polls bitcoind for its best block hash, every given number of milliseconds.
+    Polling(u64),
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct StacksNodeConfig {
     pub rpc_url: String,
     pub ingestion_port: u16,
+    /// Shared secret the Stacks node must send in the `Authorization` header of the events it
+    /// posts to the ingestion port. Events are accepted from anyone if not set.
+    #[serde(default)]
+    pub ingestion_auth_token: Option<String>,
+    /// Certificate served on the ingestion port, which then only accepts HTTPS requests.
+    #[serde(default)]
+    pub ingestion_tls: Option<ServerTlsConfig>,
+}
+
+/// Certificate and private key, PEM encoded, of a server accepting HTTPS requests.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerTlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    /// CA bundle the certificates of the clients must be signed by. Clients are not asked for
+    /// a certificate if not set.
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+}
+
+impl ServerTlsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let paths = [
+            Some(&self.cert_path),
+            Some(&self.key_path),
+            self.client_ca_path.as_ref(),
+        ];
+        for path in paths.into_iter().flatten() {
+            if !std::path::Path::new(path).is_file() {
+                return Err(format!("unable to find TLS file {}", path));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl StacksNodeConfig {
@@ -929,6 +985,8 @@ impl StacksNodeConfig {
         StacksNodeConfig {
             rpc_url,
             ingestion_port,
+            ingestion_auth_token: None,
+            ingestion_tls: None,
         }
     }
 
@@ -936,6 +994,8 @@ impl StacksNodeConfig {
         StacksNodeConfig {
             rpc_url: DEFAULT_STACKS_NODE_RPC.to_string(),
             ingestion_port,
+            ingestion_auth_token: None,
+            ingestion_tls: None,
         }
     }
 }
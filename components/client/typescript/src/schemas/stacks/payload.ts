@@ -73,6 +73,8 @@ export const StacksEventMetadataSchema = Type.Object({
   block_time: Nullable(Type.Integer()),
   signer_bitvec: Nullable(Type.String()),
   signer_signature: Nullable(Type.Array(Type.String())),
+  signer_signature_hash: Nullable(Type.String()),
+  miner_signature: Nullable(Type.String()),
 
   // Available starting in epoch3, only included in blocks where the pox cycle rewards are first calculated
   cycle_number: Nullable(Type.Integer()),
@@ -91,6 +93,9 @@ export const StacksEventMetadataSchema = Type.Object({
       ),
     })
   ),
+
+  // Transactions left out of the block because they could not be standardized
+  skipped_transactions: Type.Optional(Type.Array(TransactionIdentifierSchema)),
 });
 export type StacksEventMetadata = Static<typeof StacksEventMetadataSchema>;
 